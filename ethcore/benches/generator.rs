@@ -0,0 +1,69 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generates and encodes test chains, to make regressions in the per-block cost of the block generator visible.
+
+#![feature(test)]
+extern crate test;
+
+extern crate ethcore;
+extern crate ethcore_transaction as transaction;
+extern crate ethereum_types;
+
+use ethcore::test_helpers::generator::{BlockBuilder, FixedKeySigner, TxSigner};
+use ethereum_types::{Address, U256};
+use transaction::{Action, SignedTransaction, Transaction};
+use self::test::Bencher;
+
+const BLOCKS: usize = 1000;
+const TXS_PER_BLOCK: usize = 16;
+
+fn transactions() -> Vec<SignedTransaction> {
+	let signer = FixedKeySigner::default();
+	(0..TXS_PER_BLOCK).map(|nonce| signer.sign(Transaction {
+		nonce: nonce.into(),
+		gas_price: U256::one(),
+		gas: 21_000.into(),
+		action: Action::Call(Address::from(nonce as u64 + 1)),
+		value: U256::one(),
+		data: Vec::new(),
+	})).collect()
+}
+
+// generates and encodes `BLOCKS` blocks 15 seconds apart with jitter, every other one carrying `transactions`.
+fn generate(b: &mut Bencher, transactions: &[SignedTransaction]) {
+	b.iter(|| {
+		let mut builder = BlockBuilder::genesis().with_target_block_time(15).with_block_time_jitter(2);
+		for i in 0..BLOCKS {
+			builder = match (i % 2, transactions.is_empty()) {
+				(1, false) => builder.add_block_with_transactions(transactions.iter().cloned()),
+				_ => builder.add_block(),
+			};
+			builder.last().encoded();
+		}
+		builder
+	});
+}
+
+#[bench]
+fn generate_empty_blocks(b: &mut Bencher) {
+	generate(b, &[]);
+}
+
+#[bench]
+fn generate_blocks_with_transactions(b: &mut Bencher) {
+	generate(b, &transactions());
+}
@@ -0,0 +1,89 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Replays a transaction-heavy chain into clients with state caches of different sizes.
+
+#![feature(test)]
+extern crate test;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate ethcore;
+extern crate ethcore_io as io;
+extern crate ethereum_types;
+extern crate parity_bytes as bytes;
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use ethcore::client::{BlockChainClient, BlockId, Client, ClientConfig, ImportBlock};
+use ethcore::miner::Miner;
+use ethcore::spec::Spec;
+use ethcore::test_helpers::{generate_dummy_client_with_data, new_db};
+use ethcore::verification::queue::kind::blocks::Unverified;
+use ethereum_types::U256;
+use io::IoChannel;
+use self::test::Bencher;
+
+const BLOCKS: u32 = 32;
+const TXS_PER_BLOCK: usize = 64;
+
+lazy_static! {
+	static ref CHAIN: Vec<Bytes> = {
+		let client = generate_dummy_client_with_data(BLOCKS, TXS_PER_BLOCK, &[U256::from(1)]);
+		(1..BLOCKS as u64 + 1)
+			.map(|number| client.block(BlockId::Number(number)).expect("generated block").into_inner())
+			.collect()
+	};
+}
+
+// imports `CHAIN` into a fresh client with a state cache of `cache_size` bytes.
+fn replay(b: &mut Bencher, cache_size: usize) {
+	let spec = Spec::new_null();
+	b.iter(|| {
+		let config = ClientConfig { state_cache_size: cache_size, ..Default::default() };
+		let client = Client::new(
+			config,
+			&spec,
+			new_db(),
+			Arc::new(Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+
+		for block in CHAIN.iter() {
+			client.import_block(Unverified::from_rlp(block.clone()).unwrap()).unwrap();
+		}
+		client.flush_queue();
+		client.import_verified_blocks();
+		client.state_cache_stats()
+	});
+}
+
+#[bench]
+fn replay_state_cache_16kb(b: &mut Bencher) {
+	replay(b, 16 * 1024);
+}
+
+#[bench]
+fn replay_state_cache_256kb(b: &mut Bencher) {
+	replay(b, 256 * 1024);
+}
+
+#[bench]
+fn replay_state_cache_4mb(b: &mut Bencher) {
+	replay(b, 4 * 1024 * 1024);
+}
@@ -0,0 +1,66 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifies the body of a generated block, recovering its transaction senders in one batch
+//! and one at a time.
+
+#![feature(test)]
+extern crate test;
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate ethcore;
+extern crate ethereum_types;
+extern crate parity_bytes as bytes;
+
+use bytes::Bytes;
+use ethcore::client::{BlockChainClient, BlockId};
+use ethcore::spec::Spec;
+use ethcore::test_helpers::generate_dummy_client_with_data;
+use ethcore::verification::verify_block_unordered;
+use ethcore::verification::queue::kind::blocks::Unverified;
+use ethereum_types::U256;
+use self::test::Bencher;
+
+const TXS_PER_BLOCK: usize = 200;
+
+lazy_static! {
+	static ref BLOCK: Bytes = {
+		let client = generate_dummy_client_with_data(1, TXS_PER_BLOCK, &[U256::from(1)]);
+		client.block(BlockId::Number(1)).expect("generated block").into_inner()
+	};
+}
+
+#[bench]
+fn verify_block_batched_recovery(b: &mut Bencher) {
+	let spec = Spec::new_null();
+	b.iter(|| {
+		let block = Unverified::from_rlp(BLOCK.clone()).unwrap();
+		verify_block_unordered(block, &*spec.engine, false).unwrap()
+	});
+}
+
+#[bench]
+fn verify_block_single_recovery(b: &mut Bencher) {
+	let spec = Spec::new_null();
+	b.iter(|| {
+		let Unverified { header, transactions, .. } = Unverified::from_rlp(BLOCK.clone()).unwrap();
+		transactions.into_iter()
+			.map(|t| spec.engine.verify_transaction_unordered(t, &header).unwrap())
+			.collect::<Vec<_>>()
+	});
+}
@@ -620,7 +620,7 @@ impl<Cost: CostType> Interpreter<Cost> {
 			},
 			instructions::SLOAD => {
 				let key = H256::from(&self.stack.pop_back());
-				let word = U256::from(&*ext.storage_at(&key)?);
+				let word = U256::from(&*ext.storage_read(&key)?);
 				self.stack.push(word);
 			},
 			instructions::SSTORE => {
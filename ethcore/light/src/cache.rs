@@ -17,19 +17,24 @@
 //! Cache for data fetched from the network.
 //!
 //! Stores ancient block headers, bodies, receipts, and total difficulties.
+//! Verified accounts are stored per block hash and contract code per code hash.
 //! Furthermore, stores a "gas price corpus" of relative recency, which is a sorted
 //! vector of all gas prices from a recent range of blocks.
 
 use ethcore::encoded;
 use ethcore::header::BlockNumber;
 use ethcore::receipt::Receipt;
+use ethcore::basic_account::BasicAccount;
 
 use stats::Corpus;
 use std::time::{Instant, Duration};
 use heapsize::HeapSizeOf;
-use ethereum_types::{H256, U256};
+use ethereum_types::{H256, U256, Address};
 use memory_cache::MemoryLruCache;
 
+/// Time after which a cached account is dropped.
+const ACCOUNT_EXPIRATION: Duration = Duration::from_secs(10 * 60);
+
 /// Configuration for how much data to cache.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct CacheSizes {
@@ -43,6 +48,10 @@ pub struct CacheSizes {
 	pub receipts: usize,
 	/// Maximum size, in bytes, of cached chain score for the block.
 	pub chain_score: usize,
+	/// Maximum size, in bytes, of cached accounts.
+	pub accounts: usize,
+	/// Maximum size, in bytes, of cached contract code.
+	pub code: usize,
 }
 
 impl Default for CacheSizes {
@@ -54,10 +63,33 @@ impl Default for CacheSizes {
 			bodies: 20 * MB,
 			receipts: 10 * MB,
 			chain_score: 7 * MB,
+			accounts: 2 * MB,
+			code: 5 * MB,
 		}
 	}
 }
 
+/// Numbers of requests answered from the cache and of those which had to go to the network.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+	/// Requests answered from the cache.
+	pub hits: usize,
+	/// Requests not found in the cache.
+	pub misses: usize,
+}
+
+// account state at a given block, `None` if the account doesn't exist.
+struct CachedAccount {
+	account: Option<BasicAccount>,
+	inserted: Instant,
+}
+
+impl HeapSizeOf for CachedAccount {
+	fn heap_size_of_children(&self) -> usize {
+		0
+	}
+}
+
 /// The light client data cache.
 ///
 /// Note that almost all getter methods take `&mut self` due to the necessity to update
@@ -69,8 +101,11 @@ pub struct Cache {
 	bodies: MemoryLruCache<H256, encoded::Body>,
 	receipts: MemoryLruCache<H256, Vec<Receipt>>,
 	chain_score: MemoryLruCache<H256, U256>,
+	accounts: MemoryLruCache<(H256, Address), CachedAccount>,
+	code: MemoryLruCache<H256, Vec<u8>>,
 	corpus: Option<(Corpus<U256>, Instant)>,
 	corpus_expiration: Duration,
+	stats: CacheStats,
 }
 
 impl Cache {
@@ -82,8 +117,11 @@ impl Cache {
 			bodies: MemoryLruCache::new(sizes.bodies),
 			receipts: MemoryLruCache::new(sizes.receipts),
 			chain_score: MemoryLruCache::new(sizes.chain_score),
+			accounts: MemoryLruCache::new(sizes.accounts),
+			code: MemoryLruCache::new(sizes.code),
 			corpus: None,
 			corpus_expiration,
+			stats: CacheStats::default(),
 		}
 	}
 
@@ -112,6 +150,24 @@ impl Cache {
 		self.chain_score.get_mut(hash).map(|h| *h)
 	}
 
+	/// Query account state by block hash and address.
+	/// Returns `Some(None)` if the account is known not to exist at that block.
+	pub fn account(&mut self, hash: &H256, address: &Address) -> Option<Option<BasicAccount>> {
+		// expired entries are left to be evicted or replaced by a fresh response.
+		let now = Instant::now();
+		self.accounts.get_mut(&(*hash, *address))
+			.and_then(|cached| if cached.inserted + ACCOUNT_EXPIRATION >= now {
+				Some(cached.account.clone())
+			} else {
+				None
+			})
+	}
+
+	/// Query contract code by code hash.
+	pub fn code(&mut self, code_hash: &H256) -> Option<Vec<u8>> {
+		self.code.get_mut(code_hash).cloned()
+	}
+
 	/// Cache the given header.
 	pub fn insert_block_header(&mut self, hash: H256, hdr: encoded::Header) {
 		self.headers.insert(hash, hdr);
@@ -137,6 +193,33 @@ impl Cache {
 		self.chain_score.insert(hash, score);
 	}
 
+	/// Cache the given account state at the block with given hash.
+	pub fn insert_account(&mut self, hash: H256, address: Address, account: Option<BasicAccount>) {
+		self.accounts.insert((hash, address), CachedAccount {
+			account,
+			inserted: Instant::now(),
+		});
+	}
+
+	/// Cache the given contract code.
+	pub fn insert_code(&mut self, code_hash: H256, code: Vec<u8>) {
+		self.code.insert(code_hash, code);
+	}
+
+	/// Record whether a request could be answered from the cache.
+	pub fn note_lookup(&mut self, hit: bool) {
+		if hit {
+			self.stats.hits += 1;
+		} else {
+			self.stats.misses += 1;
+		}
+	}
+
+	/// Get the cache hit and miss counters.
+	pub fn stats(&self) -> CacheStats {
+		self.stats
+	}
+
 	/// Get gas price corpus, if recent enough.
 	pub fn gas_price_corpus(&self) -> Option<Corpus<U256>> {
 		let now = Instant::now();
@@ -168,13 +251,15 @@ impl HeapSizeOf for Cache {
 			+ self.bodies.current_size()
 			+ self.receipts.current_size()
 			+ self.chain_score.current_size()
+			+ self.accounts.current_size()
+			+ self.code.current_size()
 			// TODO: + corpus
 	}
 }
 
 #[cfg(test)]
 mod tests {
-	use super::Cache;
+	use super::{Cache, ACCOUNT_EXPIRATION};
 	use std::time::Duration;
 
 	#[test]
@@ -190,4 +275,20 @@ mod tests {
 		}
 		assert!(cache.gas_price_corpus().is_none());
 	}
+
+	#[test]
+	fn account_expires() {
+		let mut cache = Cache::new(Default::default(), Duration::from_secs(5 * 3600));
+		let (hash, address) = (1.into(), 2.into());
+
+		cache.insert_account(hash, address, None);
+		assert_eq!(cache.account(&hash, &address), Some(None));
+		assert_eq!(cache.account(&1.into(), &3.into()), None);
+
+		{
+			let inserted = &mut cache.accounts.get_mut(&(hash, address)).unwrap().inserted;
+			*inserted = *inserted - ACCOUNT_EXPIRATION - Duration::from_secs(1);
+		}
+		assert_eq!(cache.account(&hash, &address), None);
+	}
 }
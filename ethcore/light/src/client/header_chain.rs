@@ -21,6 +21,11 @@
 //! historical blocks all the way to the genesis. If the engine makes use
 //! of epoch transitions, those are stored as well.
 //!
+//! Canonical headers older than the candidates are kept until they fall out of
+//! the configured retention window and are pruned in batches. The last header of
+//! every CHT and epoch transition headers are never pruned. Pruned headers can
+//! be fetched from the network and proven against the CHT roots.
+//!
 //! This is separate from the `BlockChain` for two reasons:
 //!   - It stores only headers (and a pruned subset of them)
 //!   - To allow for flexibility in the database layout..
@@ -56,6 +61,9 @@ const CURRENT_KEY: &[u8] = &*b"best_and_latest";
 /// Key storing the last canonical epoch transition.
 const LAST_CANONICAL_TRANSITION: &[u8] = &*b"canonical_transition";
 
+/// Key storing the lowest block number which hasn't been checked for pruning.
+const PRUNE_FLOOR_KEY: &[u8] = &*b"prune_floor";
+
 /// Information about a block.
 #[derive(Debug, Clone)]
 pub struct BlockDescriptor {
@@ -149,6 +157,16 @@ fn era_key(number: u64) -> String {
 	format!("candidates_{}", number)
 }
 
+// canonical hash of a block no longer covered by the candidates.
+fn canonical_key(number: u64) -> String {
+	format!("canonical_{}", number)
+}
+
+// marks a block whose header must never be pruned.
+fn anchor_key(number: u64) -> String {
+	format!("anchor_{}", number)
+}
+
 fn pending_transition_key(block_hash: H256) -> H264 {
 	const LEADING: u8 = 1;
 
@@ -205,18 +223,27 @@ pub struct HeaderChain {
 	db: Arc<KeyValueDB>,
 	col: Option<u32>,
 	cache: Arc<Mutex<Cache>>,
+	header_retention: u64,
+	prune_floor: Mutex<u64>,
 }
 
 impl HeaderChain {
 	/// Create a new header chain given this genesis block and database to read from.
+	///
+	/// At least `header_retention` of the most recent canonical headers are kept.
 	pub fn new(
 		db: Arc<KeyValueDB>,
 		col: Option<u32>,
 		spec: &Spec,
 		cache: Arc<Mutex<Cache>>,
 		allow_hs: HardcodedSync,
+		header_retention: u64,
 	) -> Result<Self, Error> {
 		let mut live_epoch_proofs = ::std::collections::HashMap::default();
+		let prune_floor = match db.get(col, PRUNE_FLOOR_KEY)? {
+			Some(floor) => ::rlp::decode(&floor).expect("decoding db value failed"),
+			None => 1,
+		};
 
 		let genesis = ::rlp::encode(&spec.genesis_header()).into_vec();
 		let decoded_header = spec.genesis_header();
@@ -273,6 +300,8 @@ impl HeaderChain {
 				db,
 				col,
 				cache,
+				header_retention,
+				prune_floor: Mutex::new(prune_floor),
 			}
 
 		} else {
@@ -288,6 +317,8 @@ impl HeaderChain {
 				db: db.clone(),
 				col,
 				cache,
+				header_retention,
+				prune_floor: Mutex::new(prune_floor),
 			};
 
 			// insert the hardcoded sync into the database.
@@ -502,15 +533,20 @@ impl HeaderChain {
 							.expect("all eras are sequential with no gaps; qed");
 						transaction.delete(self.col, era_key(i).as_bytes());
 
+						// keep the canonical header around until it's pruned.
+						transaction.put(self.col, canonical_key(i).as_bytes(), &::rlp::encode(&era_entry.canonical_hash));
+
 						i += 1;
 
-						// prune old blocks and epoch proofs.
+						// prune old non-canonical blocks and epoch proofs.
 						for ancient in &era_entry.candidates {
 							let maybe_transition = live_epoch_proofs.remove(&ancient.hash);
 							if let Some(epoch_transition) = maybe_transition {
 								transaction.delete(self.col, &*transition_key(ancient.hash));
 
 								if ancient.hash == era_entry.canonical_hash {
+									transaction.put(self.col, anchor_key(i - 1).as_bytes(), &[]);
+
 									last_canonical_transition = match self.db.get(self.col, &ancient.hash) {
 										Err(e) => {
 											warn!(target: "chain", "Error reading from DB: {}\n
@@ -526,7 +562,9 @@ impl HeaderChain {
 								}
 							}
 
-							transaction.delete(self.col, &ancient.hash);
+							if ancient.hash != era_entry.canonical_hash {
+								transaction.delete(self.col, &ancient.hash);
+							}
 						}
 
 						let canon = &era_entry.candidates[0];
@@ -630,7 +668,7 @@ impl HeaderChain {
 			BlockId::Hash(hash) => Some(hash),
 			BlockId::Number(num) => {
 				if self.best_block.read().number < num { return None }
-				self.candidates.read().get(&num).map(|entry| entry.canonical_hash)
+				self.canonical_hash(num)
 			}
 			BlockId::Latest => {
 				Some(self.best_block.read().hash)
@@ -671,8 +709,7 @@ impl HeaderChain {
 			BlockId::Number(num) => {
 				if self.best_block.read().number < num { return None }
 
-				self.candidates.read().get(&num).map(|entry| entry.canonical_hash)
-					.and_then(load_from_db)
+				self.canonical_hash(num).and_then(load_from_db)
 			}
 			BlockId::Latest => {
 				// hold candidates hear to prevent deletion of the header
@@ -692,6 +729,66 @@ impl HeaderChain {
 		}
 	}
 
+	// canonical hash of the given block number, if it's stored.
+	fn canonical_hash(&self, num: u64) -> Option<H256> {
+		if let Some(entry) = self.candidates.read().get(&num) {
+			return Some(entry.canonical_hash)
+		}
+
+		match self.db.get(self.col, canonical_key(num).as_bytes()) {
+			Ok(db_fetch) => db_fetch.map(|bytes| ::rlp::decode(&bytes).expect("decoding value from db failed")),
+			Err(e) => {
+				warn!(target: "chain", "Error reading from database: {}", e);
+				None
+			}
+		}
+	}
+
+	/// Prune canonical headers which fell out of the retention window,
+	/// looking at no more than `max` block numbers.
+	///
+	/// Headers of the candidates, the last header of every CHT and epoch transition
+	/// headers are kept. Returns the number of headers pruned.
+	pub fn prune(&self, max: u64) -> Result<usize, Error> {
+		let mut floor = self.prune_floor.lock();
+
+		let end = {
+			let earliest_era = match self.candidates.read().keys().next() {
+				Some(&era) => era,
+				None => return Ok(0),
+			};
+			let best = self.best_block.read().number;
+			::std::cmp::min(earliest_era, best.saturating_sub(self.header_retention))
+		};
+
+		if *floor >= end { return Ok(0) }
+
+		let next_floor = ::std::cmp::min(end, floor.saturating_add(max));
+		let mut batch = self.db.transaction();
+		let mut pruned = 0;
+
+		for num in *floor..next_floor {
+			if num % cht::SIZE == 0 || self.db.get(self.col, anchor_key(num).as_bytes())?.is_some() {
+				continue
+			}
+
+			let key = canonical_key(num);
+			if let Some(hash) = self.db.get(self.col, key.as_bytes())? {
+				let hash: H256 = ::rlp::decode(&hash).expect("decoding value from db failed");
+				batch.delete(self.col, &hash);
+				batch.delete(self.col, key.as_bytes());
+				pruned += 1;
+			}
+		}
+
+		batch.put(self.col, PRUNE_FLOOR_KEY, &::rlp::encode(&next_floor));
+		self.db.write(batch)?;
+		*floor = next_floor;
+
+		trace!(target: "chain", "Pruned {} headers, next to prune is #{}", pruned, next_floor);
+		Ok(pruned)
+	}
+
 	/// Get a block's chain score.
 	/// Returns nothing for non-canonical blocks.
 	pub fn score(&self, id: BlockId) -> Option<U256> {
@@ -806,11 +903,14 @@ impl HeaderChain {
 		// slow path: loop back block by block
 		let live_proofs = self.live_epoch_proofs.read();
 
-		for hdr in self.ancestry_iter(BlockId::Hash(parent_hash)) {
-			if let Some(transition) = live_proofs.get(&hdr.hash()).cloned() {
-				return hdr.decode().map(|decoded_hdr| {
-					(decoded_hdr, transition.proof)
-				}).ok();
+		// no transitions since the last canonical one, nothing to look for.
+		if !live_proofs.is_empty() {
+			for hdr in self.ancestry_iter(BlockId::Hash(parent_hash)) {
+				if let Some(transition) = live_proofs.get(&hdr.hash()).cloned() {
+					return hdr.decode().map(|decoded_hdr| {
+						(decoded_hdr, transition.proof)
+					}).ok();
+				}
 			}
 		}
 
@@ -839,6 +939,8 @@ impl HeapSizeOf for HeaderChain {
 }
 
 /// Iterator over a block's ancestry.
+///
+/// Ends at the first header which isn't stored, so it never crosses into pruned blocks.
 pub struct AncestryIter<'a> {
 	next: Option<encoded::Header>,
 	chain: &'a HeaderChain,
@@ -862,7 +964,7 @@ mod tests {
 	use super::{HeaderChain, HardcodedSync};
 	use std::sync::Arc;
 
-	use ethereum_types::U256;
+	use ethereum_types::{H256, U256};
 	use ethcore::ids::BlockId;
 	use ethcore::header::Header;
 	use ethcore::spec::Spec;
@@ -885,7 +987,7 @@ mod tests {
 
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
-		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow).unwrap();
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 0).unwrap();
 
 		let mut parent_hash = genesis_header.hash();
 		let mut rolling_timestamp = genesis_header.timestamp();
@@ -905,6 +1007,8 @@ mod tests {
 			rolling_timestamp += 10;
 		}
 
+		chain.prune(10_000).unwrap();
+
 		assert!(chain.block_header(BlockId::Number(10)).is_none());
 		assert!(chain.block_header(BlockId::Number(9000)).is_some());
 		assert!(chain.cht_root(2).is_some());
@@ -918,7 +1022,7 @@ mod tests {
 		let db = make_db();
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
-		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow).unwrap();
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 0).unwrap();
 
 		let mut parent_hash = genesis_header.hash();
 		let mut rolling_timestamp = genesis_header.timestamp();
@@ -1000,7 +1104,7 @@ mod tests {
 		let db = make_db();
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
-		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow).unwrap();
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 0).unwrap();
 
 		assert!(chain.block_header(BlockId::Earliest).is_some());
 		assert!(chain.block_header(BlockId::Latest).is_some());
@@ -1015,7 +1119,7 @@ mod tests {
 
 		{
 			let chain = HeaderChain::new(db.clone(), None, &spec, cache.clone(),
-										HardcodedSync::Allow).unwrap();
+										HardcodedSync::Allow, 0).unwrap();
 			let mut parent_hash = genesis_header.hash();
 			let mut rolling_timestamp = genesis_header.timestamp();
 			for i in 1..10000 {
@@ -1033,10 +1137,12 @@ mod tests {
 
 				rolling_timestamp += 10;
 			}
+
+			chain.prune(10_000).unwrap();
 		}
 
 		let chain = HeaderChain::new(db.clone(), None, &spec, cache.clone(),
-									HardcodedSync::Allow).unwrap();
+									HardcodedSync::Allow, 0).unwrap();
 		assert!(chain.block_header(BlockId::Number(10)).is_none());
 		assert!(chain.block_header(BlockId::Number(9000)).is_some());
 		assert!(chain.cht_root(2).is_some());
@@ -1053,7 +1159,7 @@ mod tests {
 
 		{
 			let chain = HeaderChain::new(db.clone(), None, &spec, cache.clone(),
-										HardcodedSync::Allow).unwrap();
+										HardcodedSync::Allow, 0).unwrap();
 			let mut parent_hash = genesis_header.hash();
 			let mut rolling_timestamp = genesis_header.timestamp();
 
@@ -1096,7 +1202,7 @@ mod tests {
 
 		// after restoration, non-canonical eras should still be loaded.
 		let chain = HeaderChain::new(db.clone(), None, &spec, cache.clone(),
-									HardcodedSync::Allow).unwrap();
+									HardcodedSync::Allow, 0).unwrap();
 		assert_eq!(chain.block_header(BlockId::Latest).unwrap().number(), 10);
 		assert!(chain.candidates.read().get(&100).is_some())
 	}
@@ -1109,7 +1215,7 @@ mod tests {
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
 		let chain = HeaderChain::new(db.clone(), None, &spec, cache.clone(),
-									HardcodedSync::Allow).unwrap();
+									HardcodedSync::Allow, 0).unwrap();
 
 		assert!(chain.block_header(BlockId::Earliest).is_some());
 		assert!(chain.block_header(BlockId::Number(0)).is_some());
@@ -1123,7 +1229,7 @@ mod tests {
 		let db = make_db();
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
-		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow).unwrap();
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 0).unwrap();
 
 		let mut parent_hash = genesis_header.hash();
 		let mut rolling_timestamp = genesis_header.timestamp();
@@ -1190,7 +1296,7 @@ mod tests {
 
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
 
-		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow).expect("failed to instantiate a new HeaderChain");
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 0).expect("failed to instantiate a new HeaderChain");
 
 		let mut parent_hash = genesis_header.hash();
 		let mut rolling_timestamp = genesis_header.timestamp();
@@ -1222,4 +1328,77 @@ mod tests {
 		let decoded: Header = hardcoded_sync.header.decode().expect("decoding failed");
 		assert_eq!(decoded.number(), h_num);
 	}
+
+	#[test]
+	fn prune_keeps_recent_and_anchor_headers() {
+		use std::collections::HashMap;
+		use cht;
+
+		let spec = Spec::new_test();
+		let genesis_header = spec.genesis_header();
+		let db = make_db();
+		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
+
+		let chain = HeaderChain::new(db.clone(), None, &spec, cache, HardcodedSync::Allow, 5000).unwrap();
+
+		let mut parent_hash = genesis_header.hash();
+		let mut rolling_timestamp = genesis_header.timestamp();
+		let mut total_difficulty = *genesis_header.difficulty();
+		let mut infos = vec![(parent_hash, H256::default(), total_difficulty)];
+		for i in 1..10000 {
+			let mut header = Header::new();
+			header.set_parent_hash(parent_hash);
+			header.set_number(i);
+			header.set_timestamp(rolling_timestamp);
+			header.set_difficulty(*genesis_header.difficulty() * i as u32);
+			total_difficulty = total_difficulty + *header.difficulty();
+			infos.push((header.hash(), parent_hash, total_difficulty));
+			parent_hash = header.hash();
+
+			let epoch_proof = if i == 3 { Some(vec![1, 2, 3, 4]) } else { None };
+
+			let mut tx = db.transaction();
+			let pending = chain.insert(&mut tx, &header, epoch_proof).unwrap();
+			db.write(tx).unwrap();
+			chain.apply_pending(pending);
+
+			rolling_timestamp += 10;
+		}
+
+		// prune in batches until the retention window is reached.
+		assert!(chain.block_header(BlockId::Number(10)).is_some());
+		while chain.prune(512).unwrap() > 0 { }
+		assert_eq!(chain.prune(512).unwrap(), 0);
+
+		// recent headers are still there, by hash and number.
+		assert!(chain.block_header(BlockId::Hash(infos[9500].0)).is_some());
+		assert_eq!(chain.block_hash(BlockId::Number(5500)), Some(infos[5500].0));
+		assert!(chain.block_header(BlockId::Number(5500)).is_some());
+
+		// so are the genesis, CHT anchors and epoch transitions.
+		assert!(chain.block_header(BlockId::Number(0)).is_some());
+		assert_eq!(chain.block_header(BlockId::Number(cht::SIZE)).unwrap().hash(), infos[cht::SIZE as usize].0);
+		assert_eq!(chain.block_header(BlockId::Number(3)).unwrap().hash(), infos[3].0);
+
+		// ancient headers are gone, but can be proven against the stored CHT root.
+		assert!(chain.block_header(BlockId::Number(10)).is_none());
+		assert!(chain.block_header(BlockId::Number(4000)).is_none());
+
+		let by_hash: HashMap<_, _> = infos.iter().enumerate().map(|(num, info)| (info.0, num)).collect();
+		let cht = cht::build(0, |id| {
+			let num = match id {
+				BlockId::Number(num) => num as usize,
+				BlockId::Hash(hash) => by_hash[&hash],
+				_ => return None,
+			};
+			let (hash, parent_hash, total_difficulty) = infos[num];
+			Some(cht::BlockInfo { hash, parent_hash, total_difficulty })
+		}).unwrap();
+
+		let root = chain.cht_root(0).unwrap();
+		assert_eq!(cht.root(), root);
+
+		let proof = cht.prove(10, 0).unwrap().unwrap();
+		assert_eq!(cht::check_proof(&proof, 10, root), Some((infos[10].0, infos[10].2)));
+	}
 }
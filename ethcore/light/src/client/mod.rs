@@ -48,6 +48,12 @@ mod service;
 
 pub mod fetch;
 
+/// Number of recent headers kept by default.
+pub const DEFAULT_HEADER_RETENTION: u64 = 2048;
+
+// block numbers to check for pruning after each import round.
+const PRUNE_BATCH: u64 = 1024;
+
 /// Configuration for the light client.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -61,6 +67,9 @@ pub struct Config {
 	pub check_seal: bool,
 	/// Disable hardcoded sync.
 	pub no_hardcoded_sync: bool,
+	/// Minimum number of recent headers to keep. Older ones are pruned,
+	/// except for those needed to build CHTs and anchors.
+	pub header_retention: u64,
 }
 
 impl Default for Config {
@@ -71,6 +80,7 @@ impl Default for Config {
 			verify_full: true,
 			check_seal: true,
 			no_hardcoded_sync: false,
+			header_retention: DEFAULT_HEADER_RETENTION,
 		}
 	}
 }
@@ -181,7 +191,7 @@ impl<T: ChainDataFetcher> Client<T> {
 			engine: spec.engine.clone(),
 			chain: {
 				let hs_cfg = if config.no_hardcoded_sync { HardcodedSync::Deny } else { HardcodedSync::Allow };
-				HeaderChain::new(db.clone(), chain_col, &spec, cache, hs_cfg)?
+				HeaderChain::new(db.clone(), chain_col, &spec, cache, hs_cfg, config.header_retention)?
 			},
 			report: RwLock::new(ClientReport::default()),
 			import_lock: Mutex::new(()),
@@ -308,9 +318,11 @@ impl<T: ChainDataFetcher> Client<T> {
 					panic!("Unable to fetch epoch transition proof: {:?}", e),
 			};
 
+			// without the proof, descendants of this header can't be verified.
 			if let Err(e) = write_proof_result {
 				warn!(target: "client", "Error writing pending transition proof to DB: {:?} \
 					The node may not be able to synchronize further.", e);
+				continue;
 			}
 
 			let epoch_proof = self.engine.is_epoch_end(
@@ -345,6 +357,11 @@ impl<T: ChainDataFetcher> Client<T> {
 		self.queue.mark_as_good(&good);
 
 		self.notify(|listener| listener.new_headers(&good));
+
+		// prune a batch of old headers. lookups of pruned ones go to the network.
+		if let Err(e) = self.chain.prune(PRUNE_BATCH) {
+			warn!(target: "client", "Error pruning old headers: {}", e);
+		}
 	}
 
 	/// Get a report about blocks imported.
@@ -449,6 +466,25 @@ impl<T: ChainDataFetcher> Client<T> {
 			return false;
 		};
 
+		// verify the seal against the validator set proven by the latest epoch transition.
+		let verify_epoch_result = match self.chain.epoch_transition_for(*verified_header.parent_hash()) {
+			Some((transition_header, proof)) => self.engine.epoch_verifier(&transition_header, &proof)
+				.known_confirmed()
+				.and_then(|verifier| verifier.verify_light(&verified_header)),
+			None => {
+				debug!(target: "client", "No epoch transition known for block ({}, {})",
+					verified_header.number(), hash);
+				return false // skip import until the transition is known.
+			}
+		};
+		if let Err(e) = verify_epoch_result {
+			warn!(target: "client", "Epoch verification failed for #{} ({})\nError: {:?}",
+				verified_header.number(), verified_header.hash(), e);
+
+			bad.push(hash);
+			return false;
+		};
+
 		true
 	}
 
@@ -130,4 +130,55 @@ mod tests {
 
 		Service::start(Default::default(), &spec, fetch::unavailable(), db, cache).unwrap();
 	}
+
+	#[test]
+	fn rejects_header_of_removed_validator() {
+		use ethcore::account_provider::AccountProvider;
+		use ethcore::header::Header;
+		use ethereum_types::{H520, U128, U256};
+		use hash::keccak;
+		use rlp::encode;
+
+		let db = test_helpers::new_db();
+		let spec = Spec::new_test_round_validator_change();
+		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(6 * 3600))));
+
+		let service = Service::start(Default::default(), &spec, fetch::unavailable(), db, cache).unwrap();
+		let client = service.client();
+
+		let accounts = AccountProvider::transient_provider();
+		let removed = accounts.insert_account(keccak("0").into(), &"".into()).unwrap();
+		let remaining = accounts.insert_account(keccak("1").into(), &"".into()).unwrap();
+
+		let sealed = |parent: &Header, step: usize, signer| {
+			let mut header = Header::new();
+			header.set_parent_hash(parent.hash());
+			header.set_number(parent.number() + 1);
+			header.set_timestamp(step as u64);
+			header.set_gas_limit(*parent.gas_limit());
+			header.set_difficulty(U256::from(U128::max_value()) - U256::from(1));
+			header.set_author(signer);
+			let signature = accounts.sign(signer, Some("".into()), header.bare_hash()).unwrap();
+			header.set_seal(vec![encode(&step).into_vec(), encode(&(&H520::from(signature) as &[u8])).into_vec()]);
+			header
+		};
+		let import = |header: Header| {
+			client.import_header(header).unwrap();
+			client.flush_queue();
+			client.import_verified();
+		};
+
+		// block 1 is verified against the genesis set and transitions to the new one.
+		let genesis = client.best_block_header().decode().unwrap();
+		let first = sealed(&genesis, 1, remaining);
+		import(first.clone());
+		assert_eq!(client.chain_info().best_block_number, 1);
+
+		// step 2 belonged to the removed validator under the old set.
+		import(sealed(&first, 2, removed));
+		assert_eq!(client.chain_info().best_block_number, 1);
+
+		import(sealed(&first, 2, remaining));
+		assert_eq!(client.chain_info().best_block_number, 2);
+	}
 }
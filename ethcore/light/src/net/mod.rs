@@ -23,7 +23,7 @@ use transaction::UnverifiedTransaction;
 use ethereum_types::{H256, U256};
 use io::TimerToken;
 use kvdb::DBValue;
-use network::{NetworkProtocolHandler, NetworkContext, PeerId};
+use network::{NetworkProtocolHandler, NetworkContext, PeerId, NodeId};
 use parking_lot::{Mutex, RwLock};
 use provider::Provider;
 use request::{Request, NetworkRequests as Requests, Response};
@@ -149,6 +149,14 @@ struct PendingPeer {
 	last_update: Instant,
 }
 
+// Credits of a recently disconnected peer, kept so that a quick reconnect
+// doesn't reset its balance to the full limit.
+struct StoredCredits {
+	credits: Credits,
+	limit: U256,
+	stored_at: Instant,
+}
+
 /// Relevant data to each peer. Not accessible publicly, only `pub` due to
 /// limitations of the privacy system.
 pub struct Peer {
@@ -164,6 +172,8 @@ pub struct Peer {
 	skip_update: bool,
 	local_flow: Arc<FlowParams>,
 	awaiting_acknowledge: Option<(Instant, Arc<FlowParams>)>,
+	node_id: Option<NodeId>,
+	overdrafts: usize, // consecutive request packets they couldn't pay for.
 }
 
 /// Whether or not a peer was kept by a handler
@@ -258,16 +268,25 @@ pub struct Config {
 	pub max_stored_seconds: u64,
 	/// How much of the total load capacity each peer should be allowed to take.
 	pub load_share: f64,
+	/// How many consecutive request packets a peer may send without sufficient
+	/// credits before it is disabled. Those packets are answered without responses.
+	pub overdraft_grace: usize,
+	/// How long the credits of a disconnected peer are kept in case it reconnects.
+	pub credit_retention: Duration,
 }
 
 impl Default for Config {
 	fn default() -> Self {
 		const LOAD_SHARE: f64 = 1.0 / 25.0;
 		const MAX_ACCUMULATED: u64 = 60 * 5; // only charge for 5 minutes.
+		const OVERDRAFT_GRACE: usize = 2;
+		const CREDIT_RETENTION_SECS: u64 = 60 * 10;
 
 		Config {
 			max_stored_seconds: MAX_ACCUMULATED,
 			load_share: LOAD_SHARE,
+			overdraft_grace: OVERDRAFT_GRACE,
+			credit_retention: Duration::from_secs(CREDIT_RETENTION_SECS),
 		}
 	}
 }
@@ -355,6 +374,7 @@ pub struct LightProtocol {
 	capabilities: RwLock<Capabilities>,
 	flow_params: RwLock<Arc<FlowParams>>,
 	free_flow_params: Arc<FlowParams>,
+	stored_credits: Mutex<HashMap<NodeId, StoredCredits>>,
 	handlers: Vec<Arc<Handler>>,
 	req_id: AtomicUsize,
 	sample_store: Box<SampleStore>,
@@ -385,6 +405,7 @@ impl LightProtocol {
 			capabilities: RwLock::new(params.capabilities),
 			flow_params: RwLock::new(Arc::new(flow_params)),
 			free_flow_params: Arc::new(FlowParams::free()),
+			stored_credits: Mutex::new(HashMap::new()),
 			handlers: Vec::new(),
 			req_id: AtomicUsize::new(0),
 			sample_store,
@@ -398,6 +419,12 @@ impl LightProtocol {
 			.map(|peer| peer.lock().status.clone())
 	}
 
+	/// Get the request credit parameters currently announced to peers
+	/// which aren't reserved.
+	pub fn flow_params(&self) -> Arc<FlowParams> {
+		self.flow_params.read().clone()
+	}
+
 	/// Get number of (connected, active) peers.
 	pub fn peer_count(&self) -> (usize, usize) {
 		let num_pending = self.pending_peers.read().len();
@@ -734,6 +761,8 @@ impl LightProtocol {
 			None => return,
 			Some(peer_info) => {
 				let peer_info = peer_info.into_inner();
+				self.store_credits(&peer_info);
+
 				let mut unfulfilled: Vec<_> = peer_info.pending_requests.collect_ids();
 				unfulfilled.extend(peer_info.failed_requests);
 
@@ -769,6 +798,37 @@ impl LightProtocol {
 		}
 	}
 
+	// keep the credits of a disconnecting peer around for a while, so reconnecting
+	// doesn't give it a fresh balance.
+	fn store_credits(&self, peer_info: &Peer) {
+		let node_id = match peer_info.node_id {
+			Some(id) => id,
+			None => return,
+		};
+
+		if Arc::ptr_eq(&peer_info.local_flow, &self.free_flow_params) { return }
+
+		let retention = self.config.credit_retention;
+		let mut stored = self.stored_credits.lock();
+		stored.retain(|_, s| s.stored_at.elapsed() < retention);
+		stored.insert(node_id, StoredCredits {
+			credits: peer_info.local_credits.clone(),
+			limit: *peer_info.local_flow.limit(),
+			stored_at: Instant::now(),
+		});
+	}
+
+	// take the stored credits of a reconnecting peer, scaled to the given flow params.
+	fn restore_credits(&self, node_id: &NodeId, flow: &FlowParams) -> Option<Credits> {
+		let stored = self.stored_credits.lock().remove(node_id)?;
+		if stored.stored_at.elapsed() >= self.config.credit_retention { return None }
+
+		let mut credits = stored.credits;
+		credits.maintain_ratio(stored.limit, *flow.limit());
+		flow.recharge(&mut credits);
+		Some(credits)
+	}
+
 	fn begin_new_cost_period(&self, io: &IoContext) {
 		self.load_distribution.end_period(&*self.sample_store);
 
@@ -777,7 +837,12 @@ impl LightProtocol {
 			self.config.load_share,
 			Duration::from_secs(self.config.max_stored_seconds),
 		));
-		*self.flow_params.write() = new_params.clone();
+
+		{
+			let mut flow_params = self.flow_params.write();
+			if **flow_params == *new_params { return }
+			*flow_params = new_params.clone();
+		}
 
 		let peers = self.peers.read();
 		let now = Instant::now();
@@ -793,6 +858,9 @@ impl LightProtocol {
 		for (peer_id, peer_info) in peers.iter() {
 			let mut peer_info = peer_info.lock();
 
+			// reserved peers keep being served for free.
+			if Arc::ptr_eq(&peer_info.local_flow, &self.free_flow_params) { continue }
+
 			io.send(*peer_id, packet::UPDATE_CREDITS, packet_body.clone());
 			peer_info.awaiting_acknowledge = Some((now, new_params.clone()));
 		}
@@ -831,8 +899,15 @@ impl LightProtocol {
 			self.flow_params.read().clone()
 		};
 
+		let node_id = io.persistent_peer_id(peer);
+		let restored = match node_id {
+			Some(ref id) if !io.is_reserved_peer(peer) => self.restore_credits(id, &local_flow),
+			_ => None,
+		};
+		let local_credits = restored.unwrap_or_else(|| local_flow.create_credits());
+
 		self.peers.write().insert(peer, Mutex::new(Peer {
-			local_credits: local_flow.create_credits(),
+			local_credits,
 			status: status.clone(),
 			capabilities,
 			remote_flow,
@@ -844,6 +919,8 @@ impl LightProtocol {
 			skip_update: false,
 			local_flow,
 			awaiting_acknowledge: None,
+			node_id,
+			overdrafts: 0,
 		}));
 
 		let any_kept = self.handlers.iter().map(
@@ -937,14 +1014,29 @@ impl LightProtocol {
 		// deserialize requests, check costs and request validity.
 		peer.local_flow.recharge(&mut peer.local_credits);
 
-		peer.local_credits.deduct_cost(peer.local_flow.base_cost())?;
+		let mut cost = peer.local_flow.base_cost();
 		for request_rlp in raw.at(1)?.iter().take(MAX_REQUESTS) {
 			let request: Request = request_rlp.as_val()?;
-			let cost = peer.local_flow.compute_cost(&request).ok_or(Error::NotServer)?;
-			peer.local_credits.deduct_cost(cost)?;
+			cost = cost + peer.local_flow.compute_cost(&request).ok_or(Error::NotServer)?;
 			request_builder.push(request).map_err(|_| Error::BadBackReference)?;
 		}
 
+		if let Err(e) = peer.local_credits.deduct_cost(cost) {
+			if peer.overdrafts >= self.config.overdraft_grace { return Err(e) }
+
+			peer.overdrafts += 1;
+			debug!(target: "pip", "Peer {} can't afford requests (id: {}), overdraft {}/{}",
+				peer_id, req_id, peer.overdrafts, self.config.overdraft_grace);
+
+			io.respond(packet::RESPONSE, {
+				let mut stream = RlpStream::new_list(3);
+				stream.append(&req_id).append(&peer.local_credits.current()).begin_list(0);
+				stream.out()
+			});
+			return Ok(())
+		}
+		peer.overdrafts = 0;
+
 		let requests = request_builder.build();
 		let num_requests = requests.requests().len();
 		trace!(target: "pip", "Beginning to respond to requests (id: {}) from peer {}", req_id, peer_id);
@@ -114,6 +114,23 @@ impl CostTable {
 
 		num_set
 	}
+
+	/// Get the cost of a single request of the given kind, if it is served.
+	/// For headers this is the cost per header, for executions the cost per gas.
+	pub fn cost_of(&self, kind: request::Kind) -> Option<U256> {
+		match kind {
+			request::Kind::Headers => self.headers,
+			request::Kind::TransactionIndex => self.transaction_index,
+			request::Kind::Body => self.body,
+			request::Kind::Receipts => self.receipts,
+			request::Kind::Account => self.account,
+			request::Kind::Storage => self.storage,
+			request::Kind::Code => self.code,
+			request::Kind::HeaderProof => self.header_proof,
+			request::Kind::Execution => self.transaction_proof,
+			request::Kind::Signal => self.epoch_signal,
+		}
+	}
 }
 
 impl Default for CostTable {
@@ -26,6 +26,7 @@ use net::{LightProtocol, Params, packet, Peer};
 use net::context::IoContext;
 use net::status::{Capabilities, Status};
 use network::{PeerId, NodeId};
+use parking_lot::Mutex;
 use provider::Provider;
 use request;
 use request::*;
@@ -33,7 +34,7 @@ use rlp::{Rlp, RlpStream};
 use transaction::{Action, PendingTransaction};
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // helper for encoding a single request into a packet.
 // panics on bad backreference.
@@ -84,8 +85,42 @@ impl IoContext for Expect {
 		Some(super::MAX_PROTOCOL_VERSION)
 	}
 
-	fn persistent_peer_id(&self, _peer: PeerId) -> Option<NodeId> {
-		None
+	fn persistent_peer_id(&self, peer: PeerId) -> Option<NodeId> {
+		Some(NodeId::from(peer as u64))
+	}
+
+	fn is_reserved_peer(&self, peer: PeerId) -> bool {
+		peer == 0xff
+	}
+}
+
+// records all packets sent to peers.
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(PeerId, u8, Vec<u8>)>>);
+
+impl IoContext for Recorder {
+	fn send(&self, peer: PeerId, packet_id: u8, packet_body: Vec<u8>) {
+		self.0.lock().push((peer, packet_id, packet_body));
+	}
+
+	fn respond(&self, _packet_id: u8, _packet_body: Vec<u8>) {
+		panic!("unexpected response");
+	}
+
+	fn disconnect_peer(&self, peer: PeerId) {
+		panic!("unexpected disconnect of peer {}", peer);
+	}
+
+	fn disable_peer(&self, peer: PeerId) {
+		panic!("unexpected punishment of peer {}", peer);
+	}
+
+	fn protocol_version(&self, _peer: PeerId) -> Option<u8> {
+		Some(super::MAX_PROTOCOL_VERSION)
+	}
+
+	fn persistent_peer_id(&self, peer: PeerId) -> Option<NodeId> {
+		Some(NodeId::from(peer as u64))
 	}
 
 	fn is_reserved_peer(&self, peer: PeerId) -> bool {
@@ -294,9 +329,179 @@ fn credit_overflow() {
 	}));
 	let request = make_packet(111, &requests);
 
+	// the first few packets which can't be paid for are answered without responses.
+	let unanswered = {
+		let mut stream = RlpStream::new_list(3);
+		stream.append(&111_usize).append(proto.flow_params().limit()).begin_list(0);
+		stream.out()
+	};
+
+	for _ in 0..proto.config.overdraft_grace {
+		let expected = Expect::Respond(packet::RESPONSE, unanswered.clone());
+		proto.handle_packet(&expected, 1, packet::REQUEST, &request);
+	}
+
 	proto.handle_packet(&Expect::Punish(1), 1, packet::REQUEST, &request);
 }
 
+#[test]
+fn overdraft_grace_resets_after_paid_request() {
+	let capabilities = capabilities();
+
+	let (provider, proto) = setup(capabilities);
+	let flow_params = proto.flow_params();
+
+	let status = status(provider.client.chain_info());
+
+	{
+		let packet_body = write_handshake(&status, &capabilities, &proto);
+		proto.on_connect(1, &Expect::Send(1, packet::STATUS, packet_body.clone()));
+		proto.handle_packet(&Expect::Nothing, 1, packet::STATUS, &packet_body);
+	}
+
+	let expensive = make_packet(111, &encode_single(Request::Headers(IncompleteHeadersRequest {
+		start: HashOrNumber::Number(1).into(),
+		max: 1_000_000_000,
+		skip: 0,
+		reverse: false,
+	})));
+
+	let cheap_requests = encode_single(Request::Headers(IncompleteHeadersRequest {
+		start: HashOrNumber::Number(1).into(),
+		max: 1,
+		skip: 0,
+		reverse: false,
+	}));
+	let cheap = make_packet(112, &cheap_requests);
+	let cheap_cost = flow_params.compute_cost_multi(cheap_requests.requests()).unwrap();
+
+	let response = |req_id: usize, credits: U256| {
+		let mut stream = RlpStream::new_list(3);
+		stream.append(&req_id).append(&credits).begin_list(0);
+		stream.out()
+	};
+
+	let mut credits = *flow_params.limit();
+	for _ in 0..2 {
+		for _ in 0..proto.config.overdraft_grace {
+			let expected = Expect::Respond(packet::RESPONSE, response(111, credits));
+			proto.handle_packet(&expected, 1, packet::REQUEST, &expensive);
+		}
+
+		// a request the peer can pay for resets the grace.
+		credits = credits - cheap_cost;
+		let expected = Expect::Respond(packet::RESPONSE, response(112, credits));
+		proto.handle_packet(&expected, 1, packet::REQUEST, &cheap);
+	}
+}
+
+#[test]
+fn credits_persist_across_reconnect() {
+	let capabilities = capabilities();
+
+	let (provider, proto) = setup(capabilities);
+	let flow_params = proto.flow_params();
+
+	let status = status(provider.client.chain_info());
+	let handshake = write_handshake(&status, &capabilities, &proto);
+
+	let connect = |peer| {
+		proto.on_connect(peer, &Expect::Send(peer, packet::STATUS, handshake.clone()));
+		proto.handle_packet(&Expect::Nothing, peer, packet::STATUS, &handshake);
+	};
+
+	let credits_of = |peer| proto.peers.read().get(&peer).unwrap().lock().local_credits.current();
+
+	connect(1);
+
+	let requests = encode_single(Request::Headers(IncompleteHeadersRequest {
+		start: HashOrNumber::Number(1).into(),
+		max: 1_000,
+		skip: 0,
+		reverse: false,
+	}));
+	let cost = flow_params.compute_cost_multi(requests.requests()).unwrap();
+
+	let response = {
+		let mut stream = RlpStream::new_list(3);
+		stream.append(&111_usize).append(&(*flow_params.limit() - cost)).begin_list(0);
+		stream.out()
+	};
+
+	proto.handle_packet(&Expect::Respond(packet::RESPONSE, response), 1, packet::REQUEST, &make_packet(111, &requests));
+	proto.on_disconnect(1, &Expect::Nothing);
+
+	// the same node reconnecting doesn't get a fresh balance.
+	connect(1);
+	assert!(credits_of(1) < *flow_params.limit());
+
+	// but a different one does.
+	connect(2);
+	assert_eq!(credits_of(2), *flow_params.limit());
+}
+
+#[test]
+fn announces_costs_after_slow_serves() {
+	let capabilities = capabilities();
+
+	let (provider, proto) = setup(capabilities);
+	let old_params = proto.flow_params();
+
+	let status = status(provider.client.chain_info());
+
+	{
+		let packet_body = write_handshake(&status, &capabilities, &proto);
+		proto.on_connect(1, &Expect::Send(1, packet::STATUS, packet_body.clone()));
+		proto.handle_packet(&Expect::Nothing, 1, packet::STATUS, &packet_body);
+	}
+
+	{
+		let packet_body = write_free_handshake(&status, &capabilities, &proto);
+		proto.on_connect(0xff, &Expect::Send(0xff, packet::STATUS, packet_body.clone()));
+		proto.handle_packet(&Expect::Nothing, 0xff, packet::STATUS, &packet_body);
+	}
+
+	// serving a single header takes far longer than expected.
+	{
+		let req = CompleteRequest::Headers(CompleteHeadersRequest {
+			start: HashOrNumber::Number(1),
+			skip: 0,
+			max: 1,
+			reverse: false,
+		});
+
+		let _timer = proto.load_distribution.begin_timer(&req);
+		::std::thread::sleep(Duration::from_millis(20));
+	}
+
+	let recorder = Recorder::default();
+	proto.begin_new_cost_period(&recorder);
+
+	// only the peer which isn't reserved is told about the new costs.
+	let sent = recorder.0.into_inner();
+	assert_eq!(sent.len(), 1);
+
+	let (peer, packet_id, ref body) = sent[0];
+	assert_eq!((peer, packet_id), (1, packet::UPDATE_CREDITS));
+
+	let rlp = Rlp::new(body);
+	let announced = ::net::request_credits::FlowParams::new(
+		rlp.val_at(0).unwrap(),
+		rlp.val_at(2).unwrap(),
+		rlp.val_at(1).unwrap(),
+	);
+
+	assert_eq!(announced, *proto.flow_params());
+	assert!(announced.cost_table().cost_of(request::Kind::Headers) > old_params.cost_table().cost_of(request::Kind::Headers));
+
+	// new costs apply once the peer acknowledges them.
+	proto.handle_packet(&Expect::Nothing, 1, packet::ACKNOWLEDGE_UPDATE, &[]);
+
+	let peers = proto.peers.read();
+	assert_eq!(*peers.get(&1).unwrap().lock().local_flow, announced);
+	assert_eq!(*peers.get(&0xff).unwrap().lock().local_flow, *proto.free_flow_params);
+}
+
 // test the basic request types -- these just make sure that requests are parsed
 // and sent to the provider correctly as well as testing response formatting.
 
@@ -695,6 +900,8 @@ fn id_guard() {
 		skip_update: false,
 		local_flow: flow_params,
 		awaiting_acknowledge: None,
+		node_id: None,
+		overdrafts: 0,
 	}));
 
 	// first, malformed responses.
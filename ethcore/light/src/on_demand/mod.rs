@@ -18,18 +18,19 @@
 //! The request service is implemented using Futures. Higher level request handlers
 //! will take the raw data received here and extract meaningful results from it.
 
-use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use ethcore::executed::{Executed, ExecutionError};
 
-use futures::{Poll, Future};
+use futures::{Async, Poll, Future};
 use futures::sync::oneshot::{self, Receiver, Canceled};
 use network::PeerId;
 use parking_lot::{RwLock, Mutex};
-use rand;
+use rand::{self, Rng};
 
 use net::{
 	self, Handler, PeerStatus, Status, Capabilities,
@@ -49,13 +50,113 @@ pub mod request;
 /// The result of execution
 pub type ExecutionResult = Result<Executed, ExecutionError>;
 
+/// The responses to a batch of requests, or the reason they couldn't be obtained.
+pub type PendingResponse = Result<Vec<Response>, Error>;
+
+// peers which failed to serve a request within this window are tried last.
+const RECENT_FAILURE: Duration = Duration::from_secs(60);
+
+/// Errors which can occur while serving on-demand requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+	/// The request was dropped before completion, e.g. because the service shut down.
+	Canceled,
+	/// The retry budget was used up without any peer serving the request.
+	Exhausted {
+		/// Number of times the request was dispatched.
+		attempts: usize,
+		/// Number of distinct peers the request was dispatched to.
+		peers_tried: usize,
+	},
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::Canceled => write!(f, "On-demand request canceled"),
+			Error::Exhausted { attempts, peers_tried } =>
+				write!(f, "No peers could serve the request after {} attempts to {} peers", attempts, peers_tried),
+		}
+	}
+}
+
+impl ::std::error::Error for Error {
+	fn description(&self) -> &str {
+		"On-demand request error"
+	}
+}
+
+impl From<Canceled> for Error {
+	fn from(_: Canceled) -> Self {
+		Error::Canceled
+	}
+}
+
+/// Limits on the effort spent serving a single batch of requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryBudget {
+	/// Maximum number of times a request is dispatched to a peer.
+	pub max_attempts: usize,
+	/// Time a peer has to respond before the request is dispatched to another one.
+	pub attempt_timeout: Duration,
+	/// Time after submission at which the request is given up.
+	pub deadline: Duration,
+}
+
+impl Default for RetryBudget {
+	fn default() -> Self {
+		RetryBudget {
+			max_attempts: 5,
+			attempt_timeout: Duration::from_secs(10),
+			deadline: Duration::from_secs(40),
+		}
+	}
+}
+
+/// Number of failures by reason.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FailureStats {
+	/// Attempts the peer didn't respond to in time.
+	pub timeouts: usize,
+	/// Attempts the peer responded to with invalid data.
+	pub bad_responses: usize,
+	/// Attempts aborted by the peer disconnecting.
+	pub disconnects: usize,
+	/// Requests given up after exhausting their retry budget.
+	pub exhausted: usize,
+}
+
+// reasons for a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+	Timeout,
+	BadResponse,
+	Disconnect,
+}
+
+impl FailureStats {
+	fn note(&mut self, failure: Failure) {
+		match failure {
+			Failure::Timeout => self.timeouts += 1,
+			Failure::BadResponse => self.bad_responses += 1,
+			Failure::Disconnect => self.disconnects += 1,
+		}
+	}
+}
+
 // relevant peer info.
 struct Peer {
 	status: Status,
 	capabilities: Capabilities,
+	last_failure: Option<Instant>,
 }
 
 impl Peer {
+	// whether this peer failed to serve a request recently.
+	fn failed_recently(&self, now: Instant) -> bool {
+		self.last_failure.map_or(false, |at| now.duration_since(at) < RECENT_FAILURE)
+	}
+
 	// whether this peer can fulfill the necessary capabilities for the given
 	// request.
 	fn can_fulfill(&self, request: &Capabilities) -> bool {
@@ -80,16 +181,45 @@ struct Pending {
 	net_requests: basic_request::Batch<NetworkRequest>,
 	required_capabilities: Capabilities,
 	responses: Vec<Response>,
-	sender: oneshot::Sender<Vec<Response>>,
+	sender: oneshot::Sender<PendingResponse>,
+	submitted: Instant,
+	attempts: usize,
+	peers_tried: HashSet<PeerId>,
+	// peer currently serving the request and when it was dispatched.
+	dispatched: Option<(PeerId, Instant)>,
 }
 
 impl Pending {
+	// note a dispatch of the request to the given peer.
+	fn note_dispatch(&mut self, peer: PeerId, now: Instant) {
+		self.attempts += 1;
+		self.peers_tried.insert(peer);
+		self.dispatched = Some((peer, now));
+	}
+
+	// whether the budget allows no further attempts.
+	fn is_exhausted(&self, budget: &RetryBudget, now: Instant) -> bool {
+		self.attempts >= budget.max_attempts || now.duration_since(self.submitted) >= budget.deadline
+	}
+
+	// give up on the request, consuming self.
+	fn exhaust(self) {
+		trace!(target: "on_demand", "Giving up request after {} attempts to {} peers",
+			self.attempts, self.peers_tried.len());
+		let _ = self.sender.send(Err(Error::Exhausted {
+			attempts: self.attempts,
+			peers_tried: self.peers_tried.len(),
+		}));
+	}
+
 	// answer as many of the given requests from the supplied cache as possible.
 	// TODO: support re-shuffling.
 	fn answer_from_cache(&mut self, cache: &Mutex<Cache>) {
 		while !self.requests.is_complete() {
 			let idx = self.requests.num_answered();
-			match self.requests[idx].respond_local(cache) {
+			let response = self.requests[idx].respond_local(cache);
+			cache.lock().note_lookup(response.is_some());
+			match response {
 				Some(response) => {
 					self.requests.supply_response_unchecked(&response);
 
@@ -142,7 +272,7 @@ impl Pending {
 	// if the requests are complete, send the result and consume self.
 	fn try_complete(self) -> Option<Self> {
 		if self.requests.is_complete() {
-			let _ = self.sender.send(self.responses);
+			let _ = self.sender.send(Ok(self.responses));
 			None
 		} else {
 			Some(self)
@@ -230,16 +360,20 @@ fn guess_capabilities(requests: &[CheckedRequest]) -> Capabilities {
 /// A future extracting the concrete output type of the generic adapter
 /// from a vector of responses.
 pub struct OnResponses<T: request::RequestAdapter> {
-	receiver: Receiver<Vec<Response>>,
+	receiver: Receiver<PendingResponse>,
 	_marker: PhantomData<T>,
 }
 
 impl<T: request::RequestAdapter> Future for OnResponses<T> {
 	type Item = T::Out;
-	type Error = Canceled;
+	type Error = Error;
 
 	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-		self.receiver.poll().map(|async| async.map(T::extract_from))
+		match self.receiver.poll()? {
+			Async::Ready(Ok(responses)) => Ok(Async::Ready(T::extract_from(responses))),
+			Async::Ready(Err(e)) => Err(e),
+			Async::NotReady => Ok(Async::NotReady),
+		}
 	}
 }
 
@@ -251,18 +385,28 @@ pub struct OnDemand {
 	pending: RwLock<Vec<Pending>>,
 	peers: RwLock<HashMap<PeerId, Peer>>,
 	in_transit: RwLock<HashMap<ReqId, Pending>>,
+	failures: Mutex<FailureStats>,
 	cache: Arc<Mutex<Cache>>,
+	budget: RetryBudget,
 	no_immediate_dispatch: bool,
 }
 
 impl OnDemand {
 	/// Create a new `OnDemand` service with the given cache.
 	pub fn new(cache: Arc<Mutex<Cache>>) -> Self {
+		OnDemand::with_retry_budget(cache, Default::default())
+	}
+
+	/// Create a new `OnDemand` service with the given cache, serving each
+	/// batch of requests within the given retry budget.
+	pub fn with_retry_budget(cache: Arc<Mutex<Cache>>, budget: RetryBudget) -> Self {
 		OnDemand {
 			pending: RwLock::new(Vec::new()),
 			peers: RwLock::new(HashMap::new()),
 			in_transit: RwLock::new(HashMap::new()),
+			failures: Mutex::new(FailureStats::default()),
 			cache,
+			budget,
 			no_immediate_dispatch: false,
 		}
 	}
@@ -270,23 +414,28 @@ impl OnDemand {
 	// make a test version: this doesn't dispatch pending requests
 	// until you trigger it manually.
 	#[cfg(test)]
-	fn new_test(cache: Arc<Mutex<Cache>>) -> Self {
-		let mut me = OnDemand::new(cache);
+	fn new_test(cache: Arc<Mutex<Cache>>, budget: RetryBudget) -> Self {
+		let mut me = OnDemand::with_retry_budget(cache, budget);
 		me.no_immediate_dispatch = true;
 
 		me
 	}
 
+	/// Get the number of failures encountered so far, by reason.
+	pub fn failure_stats(&self) -> FailureStats {
+		*self.failures.lock()
+	}
+
 	/// Submit a vector of requests to be processed together.
 	///
 	/// Fails if back-references are not coherent.
 	/// The returned vector of responses will correspond to the requests exactly.
 	pub fn request_raw(&self, ctx: &BasicContext, requests: Vec<Request>)
-		-> Result<Receiver<Vec<Response>>, basic_request::NoSuchOutput>
+		-> Result<Receiver<PendingResponse>, basic_request::NoSuchOutput>
 	{
 		let (sender, receiver) = oneshot::channel();
 		if requests.is_empty() {
-			assert!(sender.send(Vec::new()).is_ok(), "receiver still in scope; qed");
+			assert!(sender.send(Ok(Vec::new())).is_ok(), "receiver still in scope; qed");
 			return Ok(receiver);
 		}
 
@@ -325,6 +474,10 @@ impl OnDemand {
 			required_capabilities: capabilities,
 			responses,
 			sender,
+			submitted: Instant::now(),
+			attempts: 0,
+			peers_tried: HashSet::new(),
+			dispatched: None,
 		});
 
 		Ok(receiver)
@@ -358,25 +511,36 @@ impl OnDemand {
 
 		debug!(target: "on_demand", "Attempting to dispatch {} pending requests", pending.len());
 
-		// iterate over all pending requests, and check them for hang-up.
+		// iterate over all pending requests, and check them for hang-up
+		// and an exhausted retry budget.
 		// then, try and find a peer who can serve it.
+		let now = Instant::now();
 		let peers = self.peers.read();
 		*pending = ::std::mem::replace(&mut *pending, Vec::new()).into_iter()
 			.filter(|pending| !pending.sender.is_canceled())
-			.filter_map(|pending| {
-				// the peer we dispatch to is chosen randomly
-				let num_peers = peers.len();
-				let rng = rand::random::<usize>() % cmp::max(num_peers, 1);
-				for (peer_id, peer) in peers.iter().chain(peers.iter()).skip(rng).take(num_peers) {
-					// TODO: see which requests can be answered by the cache?
+			.filter_map(|mut pending| {
+				if pending.is_exhausted(&self.budget, now) {
+					self.failures.lock().exhausted += 1;
+					pending.exhaust();
+					return None
+				}
 
-					if !peer.can_fulfill(&pending.required_capabilities) {
-						continue
-					}
+				// peers not yet tried for this request and without recent failures
+				// come first, the peer we dispatch to is chosen randomly among them.
+				let mut candidates = peers.iter()
+					.filter(|&(_, peer)| peer.can_fulfill(&pending.required_capabilities))
+					.map(|(peer_id, peer)| (*peer_id, peer.failed_recently(now)))
+					.collect::<Vec<_>>();
+				rand::thread_rng().shuffle(&mut candidates);
+				candidates.sort_by_key(|&(peer_id, failed_recently)| (pending.peers_tried.contains(&peer_id), failed_recently));
 
-					match ctx.request_from(*peer_id, pending.net_requests.clone()) {
+				for (peer_id, _) in candidates {
+					// TODO: see which requests can be answered by the cache?
+
+					match ctx.request_from(peer_id, pending.net_requests.clone()) {
 						Ok(req_id) => {
 							trace!(target: "on_demand", "Dispatched request {} to peer {}", req_id, peer_id);
+							pending.note_dispatch(peer_id, now);
 							self.in_transit.write().insert(req_id, pending);
 							return None
 						}
@@ -385,7 +549,6 @@ impl OnDemand {
 					}
 				}
 
-				// TODO: maximum number of failures _when we have peers_.
 				Some(pending)
 			})
 			.collect(); // `pending` now contains all requests we couldn't dispatch.
@@ -393,6 +556,38 @@ impl OnDemand {
 		debug!(target: "on_demand", "Was unable to dispatch {} requests.", pending.len());
 	}
 
+	// return requests to pending whose peer didn't respond in time.
+	// responses arriving for them later are ignored.
+	fn check_timeouts(&self) {
+		let now = Instant::now();
+		let attempt_timeout = self.budget.attempt_timeout;
+		let timed_out = |pending: &Pending| pending.dispatched
+			.map_or(false, |(_, at)| now.duration_since(at) >= attempt_timeout);
+
+		if !self.in_transit.read().values().any(&timed_out) { return }
+
+		let mut pending = self.pending.write();
+		let mut peers = self.peers.write();
+		let mut in_transit = self.in_transit.write();
+
+		let expired = in_transit.iter()
+			.filter(|&(_, pending)| timed_out(pending))
+			.map(|(req_id, _)| *req_id)
+			.collect::<Vec<_>>();
+
+		for req_id in expired {
+			let mut request = in_transit.remove(&req_id).expect("collected from in-transit requests; qed");
+			if let Some((peer_id, _)) = request.dispatched.take() {
+				debug!(target: "on_demand", "Peer {} didn't respond to request {} in time", peer_id, req_id);
+				if let Some(peer) = peers.get_mut(&peer_id) {
+					peer.last_failure = Some(now);
+				}
+			}
+			self.failures.lock().note(Failure::Timeout);
+			pending.push(request);
+		}
+	}
+
 	// submit a pending request set. attempts to answer from cache before
 	// going to the network. if complete, sends response and consumes the struct.
 	fn submit_pending(&self, ctx: &BasicContext, mut pending: Pending) {
@@ -417,7 +612,7 @@ impl Handler for OnDemand {
 	) -> PeerStatus {
 		self.peers.write().insert(
 			ctx.peer(),
-			Peer { status: status.clone(), capabilities: *capabilities }
+			Peer { status: status.clone(), capabilities: *capabilities, last_failure: None }
 		);
 		self.attempt_dispatch(ctx.as_basic());
 		PeerStatus::Kept
@@ -430,8 +625,10 @@ impl Handler for OnDemand {
 		{
 			let mut pending = self.pending.write();
 			for unfulfilled in unfulfilled {
-				if let Some(unfulfilled) = self.in_transit.write().remove(unfulfilled) {
+				if let Some(mut unfulfilled) = self.in_transit.write().remove(unfulfilled) {
 					trace!(target: "on_demand", "Attempting to reassign dropped request");
+					unfulfilled.dispatched = None;
+					self.failures.lock().note(Failure::Disconnect);
 					pending.push(unfulfilled);
 				}
 			}
@@ -457,6 +654,7 @@ impl Handler for OnDemand {
 			Some(req) => req,
 			None => return,
 		};
+		pending.dispatched = None;
 
 		// for each incoming response
 		//   1. ensure verification data filled.
@@ -466,6 +664,7 @@ impl Handler for OnDemand {
 			if let Err(e) = pending.supply_response(&*self.cache, response) {
 				let peer = ctx.peer();
 				debug!(target: "on_demand", "Peer {} gave bad response: {:?}", peer, e);
+				self.failures.lock().note(Failure::BadResponse);
 				ctx.disable_peer(peer);
 
 				break;
@@ -477,6 +676,7 @@ impl Handler for OnDemand {
 	}
 
 	fn tick(&self, ctx: &BasicContext) {
+		self.check_timeouts();
 		self.attempt_dispatch(ctx)
 	}
 }
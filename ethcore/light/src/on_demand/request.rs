@@ -27,16 +27,13 @@ use ethcore::machine::EthereumMachine;
 use ethcore::receipt::Receipt;
 use ethcore::state::{self, ProvedExecution};
 use ethereum_types::{H256, U256, Address};
-use ethtrie::{TrieError, TrieDB};
+use ethtrie::{self, ProofError, TrieError};
 use hash::{KECCAK_NULL_RLP, KECCAK_EMPTY, KECCAK_EMPTY_LIST_RLP, keccak};
-use hashdb::HashDB;
 use kvdb::DBValue;
-use memorydb::MemoryDB;
 use parking_lot::Mutex;
 use request::{self as net_request, IncompleteRequest, CompleteRequest, Output, OutputKind, Field};
 use rlp::{RlpStream, Rlp};
 use transaction::SignedTransaction;
-use trie::Trie;
 use vm::EnvInfo;
 
 const SUPPLIED_MATCHES: &str = "supplied responses always match produced requests; enforced by `check_response`; qed";
@@ -487,11 +484,19 @@ impl CheckedRequest {
 						Response::Body(encoded::Block::new_from_header_and_body(&hdr.view(), &body.view()))
 					})
 			}
+			CheckedRequest::Account(ref check, _) => {
+				// only accounts at a known block are cached, so reorgs can't make them stale.
+				check.header.as_ref().ok()
+					.and_then(|hdr| cache.lock().account(&hdr.hash(), &check.address))
+					.map(Response::Account)
+			}
 			CheckedRequest::Code(_, ref req) => {
 				if req.code_hash.as_ref().map_or(false, |&h| h == KECCAK_EMPTY) {
 					Some(Response::Code(Vec::new()))
 				} else {
-					None
+					req.code_hash.as_ref()
+						.and_then(|hash| cache.lock().code(hash))
+						.map(Response::Code)
 				}
 			}
 			_ => None,
@@ -723,6 +728,8 @@ pub enum Error {
 	TooFewResults(u64, u64),
 	/// Trie lookup error (result of bad proof)
 	Trie(TrieError),
+	/// Merkle proof failing verification.
+	Proof(ProofError),
 	/// Bad inclusion proof
 	BadProof,
 	/// Header by number instead of hash.
@@ -753,6 +760,12 @@ impl From<Box<TrieError>> for Error {
 	}
 }
 
+impl From<ProofError> for Error {
+	fn from(err: ProofError) -> Self {
+		Error::Proof(err)
+	}
+}
+
 /// Request for header proof by number
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HeaderProof {
@@ -977,28 +990,28 @@ pub struct Account {
 
 impl Account {
 	/// Check a response with an account against the stored header.
-	pub fn check_response(&self, _: &Mutex<::cache::Cache>, proof: &[Bytes]) -> Result<Option<BasicAccount>, Error> {
+	pub fn check_response(&self, cache: &Mutex<::cache::Cache>, proof: &[Bytes]) -> Result<Option<BasicAccount>, Error> {
 		let header = self.header.as_ref()?;
 		let state_root = header.state_root();
 
-		let mut db = MemoryDB::new();
-		for node in proof { db.insert(&node[..]); }
-
-		match TrieDB::new(&db, &state_root).and_then(|t| t.get(&keccak(&self.address)))? {
+		let account = match ethtrie::verify_proof(state_root, &keccak(&self.address), proof)? {
 			Some(val) => {
 				let rlp = Rlp::new(&val);
-				Ok(Some(BasicAccount {
+				Some(BasicAccount {
 					nonce: rlp.val_at(0)?,
 					balance: rlp.val_at(1)?,
 					storage_root: rlp.val_at(2)?,
 					code_hash: rlp.val_at(3)?,
-				}))
+				})
 			},
 			None => {
 				trace!(target: "on_demand", "Account {:?} not found", self.address);
-				Ok(None)
+				None
 			}
-		}
+		};
+
+		cache.lock().insert_account(header.hash(), self.address, account.clone());
+		Ok(account)
 	}
 }
 
@@ -1015,12 +1028,13 @@ impl Code {
 	/// Check a response with code against the code hash.
 	pub fn check_response(
 		&self,
-		_: &Mutex<::cache::Cache>,
+		cache: &Mutex<::cache::Cache>,
 		code_hash: &H256,
 		code: &[u8]
 	) -> Result<Vec<u8>, Error> {
 		let found_hash = keccak(code);
 		if &found_hash == code_hash {
+			cache.lock().insert_code(*code_hash, code.to_vec());
 			Ok(code.to_vec())
 		} else {
 			Err(Error::WrongHash(*code_hash, found_hash))
@@ -1324,6 +1338,9 @@ mod tests {
 
 		let cache = Mutex::new(make_cache());
 		assert!(req.check_response(&cache, &proof[..]).is_ok());
+
+		let last = proof.len() - 1;
+		assert_eq!(req.check_response(&cache, &proof[..last]), Err(Error::Proof(ProofError::MissingNode(keccak(&proof[last])))));
 	}
 
 	#[test]
@@ -16,28 +16,46 @@
 
 //! Tests for the on-demand service.
 
-use cache::Cache;
+use cache::{Cache, CacheStats};
 use ethcore::header::Header;
 use futures::Future;
 use network::{PeerId, NodeId};
 use net::*;
-use ethereum_types::H256;
+use ethereum_types::{H256, Address};
 use parking_lot::Mutex;
 use std::time::Duration;
 use ::request::{self as basic_request, Response};
 
 use std::sync::Arc;
 
-use super::{request, OnDemand, Peer, HeaderRef};
+use super::{request, OnDemand, Peer, HeaderRef, Error as OnDemandError, RetryBudget};
 
 // useful contexts to give the service.
 enum Context {
 	NoOp,
 	WithPeer(PeerId),
 	RequestFrom(PeerId, ReqId),
+	// accepts a request to any peer, noting which one.
+	RequestFromAny(ReqId, Mutex<Option<PeerId>>),
 	Punish(PeerId),
 }
 
+impl Context {
+	fn request_from_any(req_id: ReqId) -> Self {
+		Context::RequestFromAny(req_id, Mutex::new(None))
+	}
+
+	fn requested_peer(&self) -> PeerId {
+		match *self {
+			Context::RequestFromAny(_, ref peer) => {
+				let peer = *peer.lock();
+				peer.expect("a request was dispatched; qed")
+			}
+			_ => panic!("didn't expect to have requested peer queried."),
+		}
+	}
+}
+
 impl EventContext for Context {
 	fn peer(&self) -> PeerId {
 		match *self {
@@ -60,6 +78,10 @@ impl BasicContext for Context {
 	fn request_from(&self, peer_id: PeerId, _: ::request::NetworkRequests) -> Result<ReqId, Error> {
 		match *self {
 			Context::RequestFrom(id, req_id) => if peer_id == id { Ok(req_id) } else { Err(Error::NoCredits) },
+			Context::RequestFromAny(req_id, ref peer) => {
+				*peer.lock() = Some(peer_id);
+				Ok(req_id)
+			}
 			_ => panic!("didn't expect to have requests dispatched."),
 		}
 	}
@@ -87,9 +109,13 @@ struct Harness {
 
 impl Harness {
 	fn create() -> Self {
+		Harness::with_budget(Default::default())
+	}
+
+	fn with_budget(budget: RetryBudget) -> Self {
 		let cache = Arc::new(Mutex::new(Cache::new(Default::default(), Duration::from_secs(60))));
 		Harness {
-			service: OnDemand::new_test(cache),
+			service: OnDemand::new_test(cache, budget),
 		}
 	}
 
@@ -144,6 +170,7 @@ fn single_request() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let header = Header::default();
@@ -166,7 +193,7 @@ fn single_request() {
 		&[Response::Headers(basic_request::HeadersResponse { headers: vec![encoded] })]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
 }
 
 #[test]
@@ -181,6 +208,7 @@ fn no_capabilities() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: capabilities,
+		last_failure: None,
 	});
 
 	let _recv = harness.service.request_raw(
@@ -205,6 +233,7 @@ fn reassign() {
 	harness.inject_peer(peer_ids.0, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let header = Header::default();
@@ -226,6 +255,7 @@ fn reassign() {
 	harness.inject_peer(peer_ids.1, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	harness.service.dispatch_pending(&Context::RequestFrom(peer_ids.1, req_ids.1));
@@ -237,7 +267,7 @@ fn reassign() {
 		&[Response::Headers(basic_request::HeadersResponse { headers: vec![encoded] })]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
 }
 
 #[test]
@@ -250,6 +280,7 @@ fn partial_response() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let make = |num| {
@@ -296,7 +327,7 @@ fn partial_response() {
 		&[Response::Headers(basic_request::HeadersResponse { headers: vec![encoded2] })]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
 }
 
 #[test]
@@ -309,6 +340,7 @@ fn part_bad_part_good() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let make = |num| {
@@ -352,6 +384,7 @@ fn part_bad_part_good() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	harness.service.dispatch_pending(&Context::RequestFrom(peer_id, req_ids.1));
@@ -364,7 +397,7 @@ fn part_bad_part_good() {
 		&[Response::Headers(basic_request::HeadersResponse { headers: vec![encoded2] })]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
 }
 
 #[test]
@@ -377,6 +410,7 @@ fn wrong_kind() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let _recv = harness.service.request_raw(
@@ -409,6 +443,7 @@ fn back_references() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let header = Header::default();
@@ -437,7 +472,7 @@ fn back_references() {
 		]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
 }
 
 #[test]
@@ -466,6 +501,7 @@ fn fill_from_cache() {
 	harness.inject_peer(peer_id, Peer {
 		status: dummy_status(),
 		capabilities: dummy_capabilities(),
+		last_failure: None,
 	});
 
 	let header = Header::default();
@@ -493,5 +529,179 @@ fn fill_from_cache() {
 		]
 	);
 
-	assert!(recv.wait().is_ok());
+	assert!(recv.wait().unwrap().is_ok());
+}
+
+#[test]
+fn repeated_request_from_cache() {
+	let harness = Harness::create();
+
+	let peer_id = 10101;
+	let req_id = ReqId(14426);
+
+	harness.inject_peer(peer_id, Peer {
+		status: dummy_status(),
+		capabilities: dummy_capabilities(),
+		last_failure: None,
+	});
+
+	let header = Header::default();
+	let encoded = header.encoded();
+	let make_request = || request::Account {
+		header: HeaderRef::Stored(encoded.clone()),
+		address: Address::from(0xbeef),
+	};
+
+	let recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![make_request().into()],
+	).unwrap();
+
+	assert_eq!(harness.service.pending.read().len(), 1);
+
+	harness.service.dispatch_pending(&Context::RequestFrom(peer_id, req_id));
+
+	assert_eq!(harness.service.pending.read().len(), 0);
+
+	// empty state: the proof of absence needs no nodes.
+	harness.service.on_responses(
+		&Context::WithPeer(peer_id),
+		req_id,
+		&[Response::Account(basic_request::AccountResponse {
+			proof: Vec::new(),
+			nonce: 0.into(),
+			balance: 0.into(),
+			code_hash: H256::default(),
+			storage_root: H256::default(),
+		})]
+	);
+
+	assert!(recv.wait().unwrap().is_ok());
+	assert_eq!(harness.service.cache.lock().stats(), CacheStats { hits: 0, misses: 1 });
+
+	// `Context::NoOp` panics on any network request.
+	let recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![make_request().into()],
+	).unwrap();
+
+	assert!(harness.service.pending.read().is_empty());
+	assert!(recv.wait().unwrap().is_ok());
+	assert_eq!(harness.service.cache.lock().stats(), CacheStats { hits: 1, misses: 1 });
+}
+
+#[test]
+fn rotates_peers_on_timeout() {
+	let harness = Harness::with_budget(RetryBudget {
+		max_attempts: 3,
+		attempt_timeout: Duration::from_secs(0),
+		deadline: Duration::from_secs(60),
+	});
+
+	for peer_id in 1..4 {
+		harness.inject_peer(peer_id, Peer {
+			status: dummy_status(),
+			capabilities: dummy_capabilities(),
+			last_failure: None,
+		});
+	}
+
+	let header = Header::default();
+	let encoded = header.encoded();
+
+	let recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![request::HeaderByHash(header.hash().into()).into()]
+	).unwrap();
+
+	// the first two peers never respond.
+	let mut tried = Vec::new();
+	for req_id in 0..2 {
+		let ctx = Context::request_from_any(ReqId(req_id));
+		harness.service.dispatch_pending(&ctx);
+		assert!(harness.service.pending.read().is_empty());
+		assert!(!tried.contains(&ctx.requested_peer()));
+		tried.push(ctx.requested_peer());
+
+		harness.service.tick(&Context::NoOp);
+		assert_eq!(harness.service.pending.read().len(), 1);
+	}
+
+	// the third one answers.
+	let ctx = Context::request_from_any(ReqId(2));
+	harness.service.dispatch_pending(&ctx);
+	let responsive = ctx.requested_peer();
+	assert!(!tried.contains(&responsive));
+
+	harness.service.on_responses(
+		&Context::WithPeer(responsive),
+		ReqId(2),
+		&[Response::Headers(basic_request::HeadersResponse { headers: vec![encoded] })]
+	);
+
+	assert!(recv.wait().unwrap().is_ok());
+	assert_eq!(harness.service.failure_stats().timeouts, 2);
+
+	// peers which failed recently are tried last.
+	let _recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![request::HeaderByHash(H256::from(1).into()).into()]
+	).unwrap();
+
+	let ctx = Context::request_from_any(ReqId(3));
+	harness.service.dispatch_pending(&ctx);
+	assert_eq!(ctx.requested_peer(), responsive);
+}
+
+#[test]
+fn exhausts_retry_budget() {
+	let harness = Harness::with_budget(RetryBudget {
+		max_attempts: 2,
+		attempt_timeout: Duration::from_secs(0),
+		deadline: Duration::from_secs(60),
+	});
+
+	for peer_id in 1..3 {
+		harness.inject_peer(peer_id, Peer {
+			status: dummy_status(),
+			capabilities: dummy_capabilities(),
+			last_failure: None,
+		});
+	}
+
+	let recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![request::HeaderByHash(H256::default().into()).into()]
+	).unwrap();
+
+	for req_id in 0..2 {
+		harness.service.dispatch_pending(&Context::request_from_any(ReqId(req_id)));
+		harness.service.tick(&Context::NoOp);
+	}
+
+	// `Context::NoOp` panics on any network request.
+	harness.service.dispatch_pending(&Context::NoOp);
+	assert!(harness.service.pending.read().is_empty());
+
+	assert_eq!(recv.wait().unwrap().err(), Some(OnDemandError::Exhausted { attempts: 2, peers_tried: 2 }));
+
+	let stats = harness.service.failure_stats();
+	assert_eq!(stats.timeouts, 2);
+	assert_eq!(stats.exhausted, 1);
+}
+
+#[test]
+fn exhausts_deadline_without_peers() {
+	let harness = Harness::with_budget(RetryBudget {
+		deadline: Duration::from_secs(0),
+		..Default::default()
+	});
+
+	let recv = harness.service.request_raw(
+		&Context::NoOp,
+		vec![request::HeaderByHash(H256::default().into()).into()]
+	).unwrap();
+
+	harness.service.dispatch_pending(&Context::NoOp);
+	assert_eq!(recv.wait().unwrap().err(), Some(OnDemandError::Exhausted { attempts: 0, peers_tried: 0 }));
 }
@@ -17,17 +17,26 @@
 //! A provider for the PIP protocol. This is typically a full node, who can
 //! give as much data as necessary to its peers.
 
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::Duration;
 
+use bytes::Bytes;
 use ethcore::blockchain_info::BlockChainInfo;
-use ethcore::client::{BlockChainClient, ProvingBlockChainClient, ChainInfo, BlockInfo as ClientBlockInfo};
+use ethcore::client::{
+	BlockChainClient, ProvingBlockChainClient, ChainInfo, BlockInfo as ClientBlockInfo,
+	ChainNotify, ChainRoute,
+};
 use ethcore::ids::BlockId;
 use ethcore::encoded;
 use ethereum_types::H256;
-use parking_lot::RwLock;
+use keccak_hasher::KeccakHasher;
+use kvdb::{DBTransaction, KeyValueDB};
+use memorydb::MemoryDB;
+use parking_lot::{Mutex, RwLock};
 use transaction::PendingTransaction;
 
-use cht::{self, BlockInfo};
+use cht::{self, BlockInfo, CHT};
 use client::{LightChainClient, AsLightClient};
 use transaction_queue::TransactionQueue;
 
@@ -36,6 +45,13 @@ use request;
 /// Maximum allowed size of a headers request.
 pub const MAX_HEADERS_PER_REQUEST: u64 = 512;
 
+/// Number of blocks the last block of a CHT has to be buried under before
+/// the CHT is built incrementally, so that reorgs near the boundary can't invalidate it.
+pub const CHT_CONFIRMATION_DEPTH: u64 = 256;
+
+// Maximal number of built CHTs kept in memory for proving.
+const MAX_CACHED_CHTS: usize = 4;
+
 /// Defines the operations that a provider for the light subprotocol must fulfill.
 pub trait Provider: Send + Sync {
 	/// Provide current blockchain info.
@@ -215,52 +231,9 @@ impl<T: ProvingBlockChainClient + ?Sized> Provider for T {
 			}
 		};
 
-		let mut needed = None;
-
-		// build the CHT, caching the requested header as we pass through it.
-		let cht = {
-			let block_info = |id| {
-				let hdr = self.block_header(id);
-				let td = self.block_total_difficulty(id);
-
-				match (hdr, td) {
-					(Some(hdr), Some(td)) => {
-						let info = BlockInfo {
-							hash: hdr.hash(),
-							parent_hash: hdr.parent_hash(),
-							total_difficulty: td,
-						};
-
-						if hdr.number() == req.num {
-							needed = Some((hdr, td));
-						}
-
-						Some(info)
-					}
-					_ => None,
-				}
-			};
-
-			match cht::build(cht_number, block_info) {
-				Some(cht) => cht,
-				None => return None, // incomplete CHT.
-			}
-		};
-
-		let (needed_hdr, needed_td) = needed.expect("`needed` always set in loop, number checked before; qed");
-
-		// prove our result.
-		match cht.prove(req.num, 0) {
-			Ok(Some(proof)) => Some(::request::HeaderProofResponse {
-				proof,
-				hash: needed_hdr.hash(),
-				td: needed_td,
-			}),
-			Ok(None) => None,
-			Err(e) => {
-				debug!(target: "pip_provider", "Error looking up number in freshly-created CHT: {}", e);
-				None
-			}
+		match build_cht(self, cht_number) {
+			Some(cht) => prove_header(self, &cht, req.num),
+			None => None, // incomplete CHT.
 		}
 	}
 
@@ -299,6 +272,222 @@ impl<T: ProvingBlockChainClient + ?Sized> Provider for T {
 	}
 }
 
+// build the CHT with the given number from the chain of a full client.
+fn build_cht<T: ProvingBlockChainClient + ?Sized>(client: &T, cht_num: u64) -> Option<CHT<MemoryDB<KeccakHasher>>> {
+	cht::build(cht_num, |id| {
+		let hdr = ClientBlockInfo::block_header(client, id);
+		let td = client.block_total_difficulty(id);
+
+		match (hdr, td) {
+			(Some(hdr), Some(td)) => Some(BlockInfo {
+				hash: hdr.hash(),
+				parent_hash: hdr.parent_hash(),
+				total_difficulty: td,
+			}),
+			_ => None,
+		}
+	})
+}
+
+// prove the canonical header with the given number against a CHT built from the same chain.
+fn prove_header<T: ProvingBlockChainClient + ?Sized>(client: &T, cht: &CHT<MemoryDB<KeccakHasher>>, num: u64)
+	-> Option<request::HeaderProofResponse>
+{
+	let id = BlockId::Number(num);
+	let (hdr, td) = match (ClientBlockInfo::block_header(client, id), client.block_total_difficulty(id)) {
+		(Some(hdr), Some(td)) => (hdr, td),
+		_ => return None,
+	};
+
+	match cht.prove(num, 0) {
+		Ok(Some(proof)) => Some(::request::HeaderProofResponse {
+			proof,
+			hash: hdr.hash(),
+			td,
+		}),
+		Ok(None) => None,
+		Err(e) => {
+			debug!(target: "pip_provider", "Error looking up number in freshly-created CHT: {}", e);
+			None
+		}
+	}
+}
+
+fn cht_root_key(cht_num: u64) -> String {
+	format!("{:08x}_cht_root", cht_num)
+}
+
+/// A full node provider which builds CHTs incrementally as the chain passes
+/// each boundary. Roots of the built CHTs are stored in the database and the most
+/// recent tries are kept in memory, so header proofs don't require rebuilding them.
+///
+/// Needs to be registered for chain notifications to make progress.
+pub struct ChtProvider<T: ?Sized> {
+	client: Arc<T>,
+	db: Arc<KeyValueDB>,
+	col: Option<u32>,
+	chts: Mutex<VecDeque<CHT<MemoryDB<KeccakHasher>>>>,
+}
+
+impl<T: ProvingBlockChainClient + ?Sized> ChtProvider<T> {
+	/// Create a new `ChtProvider` serving data from the given client and
+	/// storing CHT roots in the given database column.
+	pub fn new(client: Arc<T>, db: Arc<KeyValueDB>, col: Option<u32>) -> Self {
+		ChtProvider {
+			client,
+			db,
+			col,
+			chts: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// Get the root of a CHT which has already been built.
+	pub fn cht_root(&self, cht_num: u64) -> Option<H256> {
+		match self.db.get(self.col, cht_root_key(cht_num).as_bytes()) {
+			Ok(val) => val.map(|val| ::rlp::decode(&val).expect("decoding value from db failed")),
+			Err(e) => {
+				warn!(target: "pip_provider", "Error reading CHT root from database: {}", e);
+				None
+			}
+		}
+	}
+
+	/// Build the most recent CHT which is buried deep enough under the given best block,
+	/// unless it has been built already.
+	pub fn note_best_block(&self, best: u64) {
+		// CHT `n` ends at block `(n + 1) * SIZE`.
+		let cht_num = match best.checked_sub(CHT_CONFIRMATION_DEPTH).map(|n| n / cht::SIZE) {
+			Some(0) | None => return,
+			Some(n) => n - 1,
+		};
+
+		if self.cht_root(cht_num).is_none() {
+			self.with_cht(cht_num, |_| ());
+		}
+	}
+
+	// run a closure on the CHT with the given number, building and storing it if necessary.
+	fn with_cht<F, R>(&self, cht_num: u64, f: F) -> Option<R>
+		where F: FnOnce(&CHT<MemoryDB<KeccakHasher>>) -> R
+	{
+		let mut chts = self.chts.lock();
+		if let Some(cht) = chts.iter().find(|cht| cht.number() == cht_num) {
+			return Some(f(cht))
+		}
+
+		let cht = match build_cht(&*self.client, cht_num) {
+			Some(cht) => cht,
+			None => return None,
+		};
+
+		debug!(target: "pip_provider", "Built CHT {} with root {:?}", cht_num, cht.root());
+		let mut batch = DBTransaction::new();
+		batch.put(self.col, cht_root_key(cht_num).as_bytes(), &::rlp::encode(&cht.root()));
+		if let Err(e) = self.db.write(batch) {
+			warn!(target: "pip_provider", "Error writing CHT root to database: {}", e);
+		}
+
+		let res = f(&cht);
+		chts.push_back(cht);
+		if chts.len() > MAX_CACHED_CHTS {
+			chts.pop_front();
+		}
+
+		Some(res)
+	}
+}
+
+impl<T: ProvingBlockChainClient + ?Sized> ChainNotify for ChtProvider<T> {
+	fn new_blocks(
+		&self,
+		imported: Vec<H256>,
+		_: Vec<H256>,
+		_: ChainRoute,
+		_: Vec<H256>,
+		_: Vec<Bytes>,
+		_duration: Duration)
+	{
+		if imported.is_empty() { return }
+
+		self.note_best_block(ChainInfo::chain_info(&*self.client).best_block_number);
+	}
+}
+
+impl<T: ProvingBlockChainClient + ?Sized> Provider for ChtProvider<T> {
+	fn chain_info(&self) -> BlockChainInfo {
+		Provider::chain_info(&*self.client)
+	}
+
+	fn reorg_depth(&self, a: &H256, b: &H256) -> Option<u64> {
+		Provider::reorg_depth(&*self.client, a, b)
+	}
+
+	fn earliest_state(&self) -> Option<u64> {
+		Provider::earliest_state(&*self.client)
+	}
+
+	fn block_header(&self, id: BlockId) -> Option<encoded::Header> {
+		Provider::block_header(&*self.client, id)
+	}
+
+	fn transaction_index(&self, req: request::CompleteTransactionIndexRequest)
+		-> Option<request::TransactionIndexResponse>
+	{
+		Provider::transaction_index(&*self.client, req)
+	}
+
+	fn block_body(&self, req: request::CompleteBodyRequest) -> Option<request::BodyResponse> {
+		Provider::block_body(&*self.client, req)
+	}
+
+	fn block_receipts(&self, req: request::CompleteReceiptsRequest) -> Option<request::ReceiptsResponse> {
+		Provider::block_receipts(&*self.client, req)
+	}
+
+	fn account_proof(&self, req: request::CompleteAccountRequest) -> Option<request::AccountResponse> {
+		Provider::account_proof(&*self.client, req)
+	}
+
+	fn storage_proof(&self, req: request::CompleteStorageRequest) -> Option<request::StorageResponse> {
+		Provider::storage_proof(&*self.client, req)
+	}
+
+	fn contract_code(&self, req: request::CompleteCodeRequest) -> Option<request::CodeResponse> {
+		Provider::contract_code(&*self.client, req)
+	}
+
+	fn header_proof(&self, req: request::CompleteHeaderProofRequest) -> Option<request::HeaderProofResponse> {
+		let cht_number = match cht::block_to_cht_number(req.num) {
+			Some(cht_num) => cht_num,
+			None => {
+				debug!(target: "pip_provider", "Requested CHT proof with invalid block number");
+				return None;
+			}
+		};
+
+		// CHTs near the head could still be affected by a reorg, so don't keep them.
+		let best = Provider::chain_info(&*self.client).best_block_number;
+		if (cht_number + 1) * cht::SIZE + CHT_CONFIRMATION_DEPTH > best {
+			return Provider::header_proof(&*self.client, req);
+		}
+
+		let client = &*self.client;
+		self.with_cht(cht_number, |cht| prove_header(client, cht, req.num)).and_then(|x| x)
+	}
+
+	fn transaction_proof(&self, req: request::CompleteExecutionRequest) -> Option<request::ExecutionResponse> {
+		Provider::transaction_proof(&*self.client, req)
+	}
+
+	fn epoch_signal(&self, req: request::CompleteSignalRequest) -> Option<request::SignalResponse> {
+		Provider::epoch_signal(&*self.client, req)
+	}
+
+	fn transactions_to_propagate(&self) -> Vec<PendingTransaction> {
+		Provider::transactions_to_propagate(&*self.client)
+	}
+}
+
 /// The light client "provider" implementation. This wraps a `LightClient` and
 /// a light transaction queue.
 pub struct LightProvider<L> {
@@ -389,8 +578,10 @@ impl<L: AsLightClient> AsLightClient for LightProvider<L> {
 
 #[cfg(test)]
 mod tests {
-	use ethcore::client::{EachBlockWith, TestBlockChainClient};
-	use super::Provider;
+	use std::sync::Arc;
+	use ethcore::client::{BlockChainClient, EachBlockWith, TestBlockChainClient};
+	use ethcore::ids::BlockId;
+	use super::{Provider, ChtProvider, CHT_CONFIRMATION_DEPTH};
 
 	#[test]
 	fn cht_proof() {
@@ -407,4 +598,28 @@ mod tests {
 
 		assert!(client.header_proof(req.clone()).is_some());
 	}
+
+	#[test]
+	fn incremental_cht_proof() {
+		let client = Arc::new(TestBlockChainClient::new());
+		let db = Arc::new(::kvdb_memorydb::create(0));
+		let provider = ChtProvider::new(client.clone(), db, None);
+
+		// the boundary isn't buried deep enough yet.
+		client.add_blocks((::cht::SIZE + CHT_CONFIRMATION_DEPTH - 1) as usize, EachBlockWith::Nothing);
+		provider.note_best_block(client.chain_info().best_block_number);
+		assert!(provider.cht_root(0).is_none());
+
+		client.add_blocks(1, EachBlockWith::Nothing);
+		provider.note_best_block(client.chain_info().best_block_number);
+		let root = provider.cht_root(0).expect("CHT 0 built once buried deep enough; qed");
+		assert!(provider.cht_root(1).is_none());
+
+		let response = provider.header_proof(::request::CompleteHeaderProofRequest { num: 1000 })
+			.expect("CHT 0 is complete; qed");
+		let (hash, td) = ::cht::check_proof(&response.proof, 1000, root).expect("proof valid against stored root; qed");
+		assert_eq!(Some(hash), client.block_hash(BlockId::Number(1000)));
+		assert_eq!(hash, response.hash);
+		assert_eq!(td, response.td);
+	}
 }
@@ -18,14 +18,17 @@
 //!
 //! Manages local transactions,
 //! but stores all local transactions, removing only on invalidated nonce.
+//! Removed transactions are remembered for a while so that their inclusion
+//! in the chain can be checked.
 //!
 //! Under the assumption that light nodes will have a relatively limited set of
 //! accounts for which they create transactions, this queue is structured in an
 //! address-wise manner.
 
 use std::fmt;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::collections::hash_map::Entry;
+use std::time::{Duration, Instant};
 
 use transaction::{self, Condition, PendingTransaction, SignedTransaction};
 use ethereum_types::{H256, U256, Address};
@@ -126,6 +129,34 @@ pub enum ImportDestination {
 	Future,
 }
 
+/// Maximal number of transactions which left the queue to keep the status of.
+const MAX_FINISHED_TRANSACTIONS: usize = 256;
+
+/// Status of a local transaction which is no longer queued.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishedStatus {
+	/// Transaction was invalidated by the sender's nonce, but hasn't been found in the chain yet.
+	Culled,
+	/// Transaction was found in the canonical chain.
+	Mined {
+		/// Number of the block including the transaction.
+		block_number: u64,
+		/// Hash of the block including the transaction.
+		block_hash: H256,
+		/// Index of the transaction within the block.
+		index: u64,
+	},
+	/// Transaction wasn't found in the chain within the inclusion timeout.
+	Dropped,
+}
+
+#[derive(Debug, Clone)]
+struct FinishedTransaction {
+	tx: PendingTransaction,
+	status: FinishedStatus,
+	culled_at: Instant,
+}
+
 type Listener = Box<Fn(&[H256]) + Send + Sync>;
 
 /// Light transaction queue. See module docs for more details.
@@ -133,6 +164,8 @@ type Listener = Box<Fn(&[H256]) + Send + Sync>;
 pub struct TransactionQueue {
 	by_account: HashMap<Address, AccountTransactions>,
 	by_hash: H256FastMap<PendingTransaction>,
+	finished: H256FastMap<FinishedTransaction>,
+	finished_order: VecDeque<H256>,
 	listeners: Vec<Listener>,
 }
 
@@ -141,6 +174,7 @@ impl fmt::Debug for TransactionQueue {
 		fmt.debug_struct("TransactionQueue")
 			.field("by_account", &self.by_account)
 			.field("by_hash", &self.by_hash)
+			.field("finished", &self.finished)
 			.field("listeners", &self.listeners.len())
 			.finish()
 	}
@@ -297,6 +331,11 @@ impl TransactionQueue {
 			.collect()
 	}
 
+	/// Get all queued transactions, both ready and future ones.
+	pub fn all_transactions(&self) -> Vec<PendingTransaction> {
+		self.by_hash.values().cloned().collect()
+	}
+
 	/// Addresses for which we store transactions.
 	pub fn queued_senders(&self) -> Vec<Address> {
 		self.by_account.keys().cloned().collect()
@@ -343,8 +382,69 @@ impl TransactionQueue {
 		trace!(target: "txqueue", "Culled {} old transactions from sender {} (nonce={})",
 			removed_hashes.len(), address, cur_nonce);
 
+		let now = Instant::now();
 		for hash in removed_hashes {
-			self.by_hash.remove(&hash);
+			if let Some(tx) = self.by_hash.remove(&hash) {
+				self.note_finished(hash, tx, now);
+			}
+		}
+	}
+
+	/// Hashes of culled transactions whose inclusion in the chain hasn't been confirmed yet.
+	pub fn unconfirmed_transactions(&self) -> Vec<H256> {
+		self.finished.iter()
+			.filter(|&(_, finished)| finished.status == FinishedStatus::Culled)
+			.map(|(hash, _)| *hash)
+			.collect()
+	}
+
+	/// Note that a culled transaction was found at the given position in the canonical chain.
+	pub fn mark_mined(&mut self, hash: &H256, block_number: u64, block_hash: H256, index: u64) {
+		if let Some(finished) = self.finished.get_mut(hash) {
+			trace!(target: "txqueue", "Transaction {} mined in block #{} ({})", hash, block_number, block_hash);
+			finished.status = FinishedStatus::Mined { block_number, block_hash, index };
+		}
+	}
+
+	/// Mark transactions which were culled more than `timeout` ago and haven't been
+	/// found in the chain as dropped. Returns the number of dropped transactions.
+	pub fn drop_unconfirmed(&mut self, timeout: Duration) -> usize {
+		self.drop_unconfirmed_at(timeout, Instant::now())
+	}
+
+	fn drop_unconfirmed_at(&mut self, timeout: Duration, now: Instant) -> usize {
+		let mut dropped = 0;
+		for finished in self.finished.values_mut() {
+			if finished.status == FinishedStatus::Culled && now.duration_since(finished.culled_at) >= timeout {
+				finished.status = FinishedStatus::Dropped;
+				dropped += 1;
+			}
+		}
+
+		if dropped > 0 {
+			debug!(target: "txqueue", "Dropped {} transactions not found in the chain", dropped);
+		}
+		dropped
+	}
+
+	/// Get local transactions which are no longer queued, along with their status.
+	pub fn finished_transactions(&self) -> Vec<(PendingTransaction, FinishedStatus)> {
+		self.finished_order.iter()
+			.filter_map(|hash| self.finished.get(hash))
+			.map(|finished| (finished.tx.clone(), finished.status.clone()))
+			.collect()
+	}
+
+	fn note_finished(&mut self, hash: H256, tx: PendingTransaction, now: Instant) {
+		let finished = FinishedTransaction { tx, status: FinishedStatus::Culled, culled_at: now };
+		if self.finished.insert(hash, finished).is_none() {
+			self.finished_order.push_back(hash);
+		}
+
+		while self.finished_order.len() > MAX_FINISHED_TRANSACTIONS {
+			if let Some(oldest) = self.finished_order.pop_front() {
+				self.finished.remove(&oldest);
+			}
 		}
 	}
 
@@ -368,8 +468,9 @@ impl TransactionQueue {
 
 #[cfg(test)]
 mod tests {
-	use super::TransactionQueue;
-	use ethereum_types::Address;
+	use std::time::{Duration, Instant};
+	use super::{TransactionQueue, FinishedStatus};
+	use ethereum_types::{Address, H256};
 	use transaction::{Transaction, PendingTransaction, Condition};
 
 	#[test]
@@ -558,4 +659,44 @@ mod tests {
 		assert_eq!(txq.future_transactions(0, 0).len(), 7);
 		assert_eq!(txq.next_nonce(&sender).unwrap(), 1.into());
 	}
+
+	#[test]
+	fn culled_transactions_are_tracked() {
+		let sender = Address::default();
+		let mut txq = TransactionQueue::default();
+
+		let hashes: Vec<_> = (0..3).map(|i| {
+			let mut tx = Transaction::default();
+			tx.nonce = i.into();
+			let tx = tx.fake_sign(sender);
+			let hash = tx.hash();
+			txq.import(tx.into()).unwrap();
+			hash
+		}).collect();
+
+		assert!(txq.finished_transactions().is_empty());
+
+		txq.cull(sender, 2.into());
+		assert_eq!(txq.all_transactions().len(), 1);
+		let mut unconfirmed = txq.unconfirmed_transactions();
+		unconfirmed.sort();
+		let mut expected = hashes[..2].to_vec();
+		expected.sort();
+		assert_eq!(unconfirmed, expected);
+
+		txq.mark_mined(&hashes[0], 10, H256::from(10), 0);
+		assert_eq!(txq.unconfirmed_transactions(), vec![hashes[1]]);
+
+		let timeout = Duration::from_secs(60);
+		assert_eq!(txq.drop_unconfirmed_at(timeout, Instant::now()), 0);
+		assert_eq!(txq.drop_unconfirmed_at(timeout, Instant::now() + timeout), 1);
+
+		let statuses: Vec<_> = txq.finished_transactions().into_iter()
+			.map(|(tx, status)| (tx.hash(), status))
+			.collect();
+		assert_eq!(statuses, vec![
+			(hashes[0], FinishedStatus::Mined { block_number: 10, block_hash: H256::from(10), index: 0 }),
+			(hashes[1], FinishedStatus::Dropped),
+		]);
+	}
 }
@@ -18,8 +18,7 @@ use ethereum_types::{H256, U256, Address};
 use bytes::Bytes;
 use hash::keccak;
 use rlp::Encodable;
-use ethkey::Signature;
-use transaction::signature::{add_chain_replay_protection, check_replay_protection};
+use ethkey::{self, Signature};
 
 /// Message with private transaction encrypted
 #[derive(Default, Debug, Clone, PartialEq, RlpEncodable, RlpDecodable, Eq)]
@@ -86,7 +85,8 @@ impl SignedPrivateTransaction {
 			private_transaction_hash: private_transaction_hash,
 			r: sig.r().into(),
 			s: sig.s().into(),
-			v: add_chain_replay_protection(sig.v() as u64, chain_id),
+			v: ethkey::to_eip155_v(sig.v(), chain_id)
+				.expect("v is the recovery id of a signature and chain ids are far below u64::max_value() / 2; qed"),
 			hash: 0.into(),
 		}.compute_hash()
 	}
@@ -96,7 +96,7 @@ impl SignedPrivateTransaction {
 		self
 	}
 
-	pub fn standard_v(&self) -> u8 { check_replay_protection(self.v) }
+	pub fn standard_v(&self) -> u8 { ethkey::recovery_id_from_v(self.v).unwrap_or(4) }
 
 	/// Construct a signature object from the sig.
 	pub fn signature(&self) -> Signature {
@@ -120,6 +120,7 @@ impl ClientService {
 			db_restore: client.clone(),
 		};
 		let snapshot = Arc::new(SnapshotService::new(snapshot_params)?);
+		snapshot.throttle().set_busy_signal(client.clone());
 
 		let provider = Arc::new(ethcore_private_tx::Provider::new(
 				client.clone(),
@@ -234,6 +235,20 @@ impl IoHandler<ClientIoMessage> for ClientIoHandler {
 					warn!("Failed to initialize snapshot restoration: {}", e);
 				}
 			}
+			ClientIoMessage::RestoreFromPath(ref path) => {
+				let snapshot = self.snapshot.clone();
+				let path = path.clone();
+
+				let res = thread::Builder::new().name("Snapshot Import".into()).spawn(move || {
+					if let Err(e) = snapshot.restore_from_disk(&path) {
+						warn!("Failed to restore snapshot from {}: {}", path.display(), e);
+					}
+				});
+
+				if let Err(e) = res {
+					debug!(target: "snapshot", "Failed to initialize snapshot import thread: {:?}", e);
+				}
+			}
 			ClientIoMessage::FeedStateChunk(ref hash, ref chunk) => {
 				self.snapshot.feed_state_chunk(*hash, chunk)
 			}
@@ -35,7 +35,7 @@ use parking_lot::RwLock;
 
 pub use ethstore::ethkey::Signature;
 pub use ethstore::{Derivation, IndexDerivation, KeyFile};
-pub use hardware_wallet::{Error as HardwareError, HardwareWalletManager, KeyPath, TransactionInfo};
+pub use hardware_wallet::{Error as HardwareError, HardwareWalletManager, HardwareAccount, KeyPath, PathTemplate, TransactionInfo};
 pub use super::transaction::{Action, Transaction};
 
 /// Type of unlock.
@@ -130,6 +130,9 @@ pub struct AccountProviderSettings {
 	pub enable_hardware_wallets: bool,
 	/// Use the classic chain key on the hardware wallet.
 	pub hardware_wallet_classic_key: bool,
+	/// Derivation path templates of hardware wallet accounts, along with the number of accounts to
+	/// discover along each. Only the chain key is used if empty.
+	pub hardware_wallet_paths: Vec<(PathTemplate, usize)>,
 	/// Store raw account secret when unlocking the account permanently.
 	pub unlock_keep_secret: bool,
 	/// Disallowed accounts.
@@ -141,6 +144,7 @@ impl Default for AccountProviderSettings {
 		AccountProviderSettings {
 			enable_hardware_wallets: false,
 			hardware_wallet_classic_key: false,
+			hardware_wallet_paths: Vec::new(),
 			unlock_keep_secret: false,
 			blacklisted_accounts: vec![],
 		}
@@ -155,7 +159,11 @@ impl AccountProvider {
 		if settings.enable_hardware_wallets {
 			match HardwareWalletManager::new() {
 				Ok(manager) => {
-					manager.set_key_path(if settings.hardware_wallet_classic_key { KeyPath::EthereumClassic } else { KeyPath::Ethereum });
+					if settings.hardware_wallet_paths.is_empty() {
+						manager.set_key_path(if settings.hardware_wallet_classic_key { KeyPath::EthereumClassic } else { KeyPath::Ethereum });
+					} else {
+						manager.set_derivation_paths(settings.hardware_wallet_paths);
+					}
 					hardware_store = Some(manager)
 				},
 				Err(e) => debug!("Error initializing hardware wallets: {}", e),
@@ -287,6 +295,11 @@ impl AccountProvider {
 		Err(SSError::Custom("No hardware wallet accounts were found".into()))
 	}
 
+	/// Returns accounts of connected hardware wallets along with their derivation paths.
+	pub fn hardware_accounts_with_paths(&self) -> Vec<HardwareAccount> {
+		self.hardware_store.as_ref().map_or_else(Vec::new, |h| h.list_accounts())
+	}
+
 	/// Get a list of paths to locked hardware wallets
 	pub fn locked_hardware_accounts(&self) -> Result<Vec<String>, SignError> {
 		match self.hardware_store.as_ref().map(|h| h.list_locked_wallets()) {
@@ -607,9 +620,10 @@ impl AccountProvider {
 			.map_err(Into::into)
 	}
 
-	/// Change vault password.
-	pub fn change_vault_password(&self, name: &str, new_password: &Password) -> Result<(), Error> {
-		self.sstore.change_vault_password(name, new_password)
+	/// Change vault password, re-encrypting the vault with the new one. Given the current password the vault
+	/// is closed if it was opened, otherwise the vault must be opened and is reopened with the new password.
+	pub fn change_vault_password(&self, name: &str, old_password: Option<&Password>, new_password: &Password) -> Result<(), Error> {
+		self.sstore.change_vault_password(name, old_password, new_password)
 			.map_err(Into::into)
 	}
 
@@ -62,6 +62,11 @@ pub trait BlockChainDB: Send + Sync {
 	/// Trace blooms database.
 	fn trace_blooms(&self) -> &blooms_db::Database;
 
+	/// Directory holding the database, if it is stored on disk.
+	fn path(&self) -> Option<&Path> {
+		None
+	}
+
 	/// Restore the DB from the given path
 	fn restore(&self, new_db: &str) -> Result<(), EthcoreError> {
 		// First, close the Blooms databases
@@ -2417,4 +2422,26 @@ mod tests {
 			assert_eq!(bc.epoch_transition_for(fork_hash).unwrap().block_number, 0);
 		}
 	}
+
+	#[test]
+	fn imports_periodic_reorgs() {
+		let genesis = BlockBuilder::genesis();
+		let db = new_db();
+		let bc = new_chain(genesis.last().encoded(), db.clone());
+
+		let mut generator = genesis.with_periodic_reorg(10, 3);
+		let mut reorgs = 0;
+		for block in generator.blocks(50) {
+			let route = insert_block(&db, &bc, block.encoded(), vec![]);
+			if !route.retracted.is_empty() {
+				assert_eq!(route.retracted.len(), 3);
+				reorgs += 1;
+			}
+		}
+
+		assert_eq!(reorgs, 5);
+		assert_eq!(generator.reorgs(), 5);
+		assert_eq!(bc.best_block_number(), 50);
+		assert_eq!(bc.best_block_hash(), generator.head());
+	}
 }
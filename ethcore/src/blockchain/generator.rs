@@ -16,21 +16,335 @@
 
 //! Blockchain generator for tests.
 
-use std::collections::VecDeque;
-use ethereum_types::{U256, H256, Bloom};
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use byteorder::{BigEndian, ByteOrder};
+use ethereum_types::{U256, H64, H256, H520, Address, Bloom};
+use ethkey::{sign, Secret};
 
-use header::Header;
-use rlp::encode;
-use transaction::SignedTransaction;
+use bytes::Bytes;
+use hash::{keccak, KECCAK_NULL_RLP, KECCAK_EMPTY_LIST_RLP};
+use header::{BlockNumber, Header};
+use log_entry::LogEntry;
+use receipt::{Receipt, TransactionOutcome};
+use rlp::{encode, Encodable, PayloadInfo, Rlp, RlpStream};
+use transaction::{Action, SignedTransaction, Transaction};
+use triehash::ordered_trie_root;
+use trie::{Recorder, Trie, TrieMut};
+use ethtrie::{TrieDB, TrieDBMut};
+use keccak_hasher::KeccakHasher;
+use memorydb::MemoryDB;
 use views::BlockView;
 use encoded;
 
+/// Root of an empty trie, used as transactions and receipts root of blocks without any.
+pub const EMPTY_TRIE_ROOT: H256 = KECCAK_NULL_RLP;
+
+/// Hash of an empty RLP list, used as uncles hash of blocks without uncles.
+pub const EMPTY_LIST_HASH: H256 = KECCAK_EMPTY_LIST_RLP;
+
+/// Maximal depth of an uncle relative to the block including it.
+const MAX_UNCLE_AGE: usize = 6;
+
+/// Divisor of the parent difficulty giving a single difficulty adjustment step.
+const DIFFICULTY_BOUND_DIVISOR: u64 = 2048;
+
+/// Difficulty never falls below this value when derived from block times.
+const MINIMUM_DIFFICULTY: u64 = 131_072;
+
+/// Padding stops once the encoded body is at most this many bytes below the target size.
+pub const BODY_SIZE_TOLERANCE: usize = 128;
+
+/// Largest payload of a single padding transaction.
+const MAX_PADDING_DATA: usize = 32 * 1024;
+
+/// Difficulty of the blocks of `PeriodicReorg`, the last block of each competing branch gets one more.
+const REORG_DIFFICULTY: u64 = 10;
+
+/// Outcome encoding of generated receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptFormat {
+	/// Post-transaction state root, used before Byzantium.
+	StateRoot(H256),
+	/// Status of the transaction, used since Byzantium.
+	Status(bool),
+}
+
+impl ReceiptFormat {
+	/// Create a receipt with the outcome encoded in this format.
+	pub fn receipt(&self, gas_used: U256, logs: Vec<LogEntry>) -> Receipt {
+		let outcome = match *self {
+			ReceiptFormat::StateRoot(root) => TransactionOutcome::StateRoot(root),
+			ReceiptFormat::Status(success) => TransactionOutcome::StatusCode(success as u8),
+		};
+		Receipt::new(outcome, gas_used, logs)
+	}
+}
+
+/// Non-canonical, yet unambiguous, RLP encoding of a header field, see `BlockBuilder::with_noncanonical_rlp`.
+///
+/// Lenient decoders read the same values as from the canonical encoding, strict ones reject the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RlpQuirk {
+	/// Difficulty encoded with a redundant leading zero byte.
+	LeadingZeroDifficulty,
+	/// Number encoded with a redundant leading zero byte.
+	LeadingZeroNumber,
+	/// Gas limit encoded with a redundant leading zero byte.
+	LeadingZeroGasLimit,
+	/// Timestamp encoded with a redundant leading zero byte.
+	LeadingZeroTimestamp,
+}
+
+impl RlpQuirk {
+	/// Position of the affected field in the encoded header.
+	pub fn field_index(&self) -> usize {
+		match *self {
+			RlpQuirk::LeadingZeroDifficulty => 7,
+			RlpQuirk::LeadingZeroNumber => 8,
+			RlpQuirk::LeadingZeroGasLimit => 9,
+			RlpQuirk::LeadingZeroTimestamp => 11,
+		}
+	}
+
+	/// Encode `header` with the affected field quirked and all other fields canonical.
+	pub fn encode_header(&self, header: &Header) -> Bytes {
+		let canonical = encode(header);
+		let fields = Rlp::new(&canonical);
+		let count = fields.item_count().expect("Headers encode as a list");
+		let mut stream = RlpStream::new_list(count);
+		for (index, field) in fields.iter().enumerate() {
+			if index == self.field_index() {
+				let mut value = vec![0u8];
+				value.extend_from_slice(field.data().expect("Header fields are encoded values"));
+				stream.append(&value);
+			} else {
+				stream.append_raw(field.as_raw(), 1);
+			}
+		}
+		stream.out()
+	}
+}
+
+/// Order of the transactions within generated blocks, see `BlockBuilder::order_transactions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxOrdering {
+	/// The order the transactions were given in.
+	InsertionOrder,
+	/// Highest gas price first.
+	GasPriceDescending,
+	/// Lowest nonce first, then highest gas price first.
+	NonceThenGasPrice,
+}
+
+impl Default for TxOrdering {
+	fn default() -> Self {
+		TxOrdering::InsertionOrder
+	}
+}
+
+impl TxOrdering {
+	/// Reorder `transactions`, keeping the given order among equal transactions.
+	pub fn sort(&self, transactions: &mut [SignedTransaction]) {
+		match *self {
+			TxOrdering::InsertionOrder => {},
+			TxOrdering::GasPriceDescending => transactions.sort_by(|a, b| b.gas_price.cmp(&a.gas_price)),
+			TxOrdering::NonceThenGasPrice => transactions.sort_by(|a, b| a.nonce.cmp(&b.nonce).then(b.gas_price.cmp(&a.gas_price))),
+		}
+	}
+}
+
+/// Distribution of the gas of generated transactions, see `BlockBuilder::with_gas_distribution`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasDistribution {
+	/// Every transaction gets the same gas.
+	Constant(U256),
+	/// Gas drawn uniformly from the inclusive range.
+	Uniform(u64, u64),
+	/// Gas drawn uniformly from the `simple` range, or from the `complex` range for a `ratio` share
+	/// of the transactions.
+	Bimodal {
+		/// Inclusive gas range of simple transactions, e.g. transfers.
+		simple: (u64, u64),
+		/// Inclusive gas range of complex transactions, e.g. contract calls.
+		complex: (u64, u64),
+		/// Share of complex transactions, between 0 and 1.
+		ratio: f64,
+	},
+}
+
+impl GasDistribution {
+	/// Gas of the `index`-th transaction of the block with the given parent.
+	///
+	/// Draws are derived from the parent hash and the index, so generated chains are reproducible.
+	pub fn sample(&self, parent_hash: &H256, index: usize) -> U256 {
+		let mut seed = parent_hash.to_vec();
+		seed.extend_from_slice(&encode(&(index as u64)));
+		let draw = keccak(&seed);
+		let uniform = |(low, high): (u64, u64)| low + draw.low_u64() % (high - low).saturating_add(1);
+		match *self {
+			GasDistribution::Constant(gas) => gas,
+			GasDistribution::Uniform(low, high) => uniform((low, high)).into(),
+			GasDistribution::Bimodal { simple, complex, ratio } => {
+				let pick = BigEndian::read_u64(&draw[..8]) as f64 / u64::max_value() as f64;
+				if pick < ratio { uniform(complex).into() } else { uniform(simple).into() }
+			},
+		}
+	}
+
+	fn is_valid(&self) -> bool {
+		match *self {
+			GasDistribution::Constant(_) => true,
+			GasDistribution::Uniform(low, high) => low <= high,
+			GasDistribution::Bimodal { simple, complex, ratio } =>
+				simple.0 <= simple.1 && complex.0 <= complex.1 && ratio >= 0.0 && ratio <= 1.0,
+		}
+	}
+}
+
+/// Byte order of the nonce of generated PoW seals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceOrder {
+	/// Most significant byte first, as Ethash encodes its `H64` nonce.
+	BigEndian,
+	/// Least significant byte first.
+	LittleEndian,
+}
+
+/// Layout of the PoW seals of generated blocks, see `BlockBuilder::with_pow_seal`.
+///
+/// The seal holds the mix hash as a full `H256`, followed by the nonce. The default matches Ethash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowSealFormat {
+	/// Width of the nonce in bytes.
+	pub nonce_width: usize,
+	/// Byte order of the nonce.
+	pub nonce_order: NonceOrder,
+}
+
+impl Default for PowSealFormat {
+	fn default() -> Self {
+		PowSealFormat {
+			nonce_width: 8,
+			nonce_order: NonceOrder::BigEndian,
+		}
+	}
+}
+
+impl PowSealFormat {
+	/// Bytes of `nonce` in this format, zero-extended or truncated to the width.
+	pub fn nonce_bytes(&self, nonce: u64) -> Bytes {
+		let mut value = [0u8; 8];
+		BigEndian::write_u64(&mut value, nonce);
+		let mut bytes = vec![0u8; self.nonce_width.saturating_sub(8)];
+		bytes.extend_from_slice(&value[8 - cmp::min(self.nonce_width, 8)..]);
+		if self.nonce_order == NonceOrder::LittleEndian {
+			bytes.reverse();
+		}
+		bytes
+	}
+
+	/// Seal fields of a PoW seal of `mix_hash` and `nonce` in this format.
+	pub fn seal(&self, mix_hash: &H256, nonce: u64) -> Vec<Bytes> {
+		vec![encode(mix_hash).into_vec(), encode(&self.nonce_bytes(nonce)).into_vec()]
+	}
+}
+
+/// AuRa seal of generated blocks, see `BlockBuilder::with_authority_seal`.
+///
+/// The seal holds the step, the timestamp divided by the step duration, followed by the signature
+/// of the bare hash of the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthoritySeal {
+	secret: Secret,
+	step_duration: u64,
+	field_count: usize,
+}
+
+impl AuthoritySeal {
+	/// Seal of steps of `step_duration` seconds, signed with `secret`.
+	pub fn new(secret: Secret, step_duration: u64) -> Self {
+		assert!(step_duration > 0, "Steps last at least 1 second");
+		AuthoritySeal {
+			secret,
+			step_duration,
+			field_count: 2,
+		}
+	}
+
+	/// Negative-test helper: emit `count` seal fields instead of two, dropping the signature and
+	/// then the step, or appending empty fields.
+	pub fn with_seal_field_count(mut self, count: usize) -> Self {
+		self.field_count = count;
+		self
+	}
+
+	/// Seal fields of the AuRa seal of `header`.
+	pub fn seal(&self, header: &Header) -> Vec<Bytes> {
+		let step = header.timestamp() / self.step_duration;
+		let signature = sign(&self.secret, &header.bare_hash()).expect("Generated blocks are signed with a valid secret");
+		let mut fields = vec![encode(&step).into_vec(), encode(&(&H520::from(signature) as &[u8])).into_vec()];
+		fields.resize(self.field_count, ::rlp::NULL_RLP.to_vec());
+		fields
+	}
+}
+
+/// Signs transactions included in generated blocks.
+pub trait TxSigner {
+	/// Sign the transaction.
+	fn sign(&self, tx: Transaction) -> SignedTransaction;
+}
+
+/// Signer using a fixed key, the default for generated transactions.
+#[derive(Clone)]
+pub struct FixedKeySigner {
+	secret: Secret,
+}
+
+impl FixedKeySigner {
+	/// Create a signer using the given key.
+	pub fn new(secret: Secret) -> Self {
+		FixedKeySigner { secret }
+	}
+}
+
+impl Default for FixedKeySigner {
+	fn default() -> Self {
+		FixedKeySigner::new(keccak("").into())
+	}
+}
+
+impl TxSigner for FixedKeySigner {
+	fn sign(&self, tx: Transaction) -> SignedTransaction {
+		tx.sign(&self.secret, None)
+	}
+}
+
 /// Helper structure, used for encoding blocks.
-#[derive(Default, Clone, RlpEncodable)]
+#[derive(Default, Clone)]
 pub struct Block {
 	pub header: Header,
 	pub transactions: Vec<SignedTransaction>,
-	pub uncles: Vec<Header>
+	pub uncles: Vec<Header>,
+	/// Receipts committed to by the receipts root, not part of the encoded block.
+	pub receipts: Vec<Receipt>,
+	/// Non-canonical encoding of the header, if any.
+	pub rlp_quirk: Option<RlpQuirk>,
+}
+
+impl Encodable for Block {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		match self.rlp_quirk {
+			Some(quirk) => s.append_raw(&quirk.encode_header(&self.header), 1),
+			None => s.append(&self.header),
+		};
+		s.append_list(&self.transactions);
+		s.append_list(&self.uncles);
+	}
 }
 
 impl Block {
@@ -58,6 +372,11 @@ impl Block {
 	pub fn difficulty(&self) -> U256 {
 		*self.header.difficulty()
 	}
+
+	/// Length of the encoded body, the list of transactions and uncles as sent in `BlockBodies`.
+	pub fn body_size(&self) -> usize {
+		body_size(&self.transactions, &self.uncles)
+	}
 }
 
 #[derive(Debug)]
@@ -65,6 +384,15 @@ pub struct BlockOptions {
 	pub difficulty: U256,
 	pub bloom: Bloom,
 	pub transactions: Vec<SignedTransaction>,
+	pub uncles: Vec<Header>,
+	pub author: Address,
+	pub gas_limit: U256,
+	pub gas_used: U256,
+	/// Receipts committed to by the receipts root.
+	pub receipts: Vec<Receipt>,
+	/// Seconds since the parent block, if the timestamp should advance.
+	/// Overrides the target block time of the builder.
+	pub timestamp_delta: Option<u64>,
 }
 
 impl Default for BlockOptions {
@@ -73,23 +401,760 @@ impl Default for BlockOptions {
 			difficulty: 10.into(),
 			bloom: Bloom::default(),
 			transactions: Vec::new(),
+			uncles: Vec::new(),
+			author: Address::default(),
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			receipts: Vec::new(),
+			timestamp_delta: None,
+		}
+	}
+}
+
+/// Parameters of a well-known network a generated chain can mimic, see `BlockBuilder::preset`.
+///
+/// Values are taken from the genesis and engine of the network's chain spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkPreset {
+	/// Ethereum main network: genesis difficulty `0x400000000`, gas limit `0x1388`, 13 second blocks.
+	Foundation,
+	/// Ropsten test network: genesis difficulty `0x100000`, gas limit `0x1000000`, 13 second blocks.
+	Ropsten,
+	/// Kovan test network: genesis difficulty `0x20000`, gas limit `0x5b8d80`, 4 second steps.
+	Kovan,
+}
+
+impl NetworkPreset {
+	/// Difficulty of the genesis block.
+	pub fn genesis_difficulty(&self) -> U256 {
+		match *self {
+			NetworkPreset::Foundation => 0x4_0000_0000u64.into(),
+			NetworkPreset::Ropsten => 0x10_0000.into(),
+			NetworkPreset::Kovan => 0x2_0000.into(),
+		}
+	}
+
+	/// Gas limit of the genesis block.
+	pub fn gas_limit(&self) -> U256 {
+		match *self {
+			NetworkPreset::Foundation => 0x1388.into(),
+			NetworkPreset::Ropsten => 0x100_0000.into(),
+			NetworkPreset::Kovan => 0x5b_8d80.into(),
+		}
+	}
+
+	/// Target block time in seconds.
+	pub fn block_time(&self) -> u64 {
+		match *self {
+			NetworkPreset::Foundation | NetworkPreset::Ropsten => 13,
+			NetworkPreset::Kovan => 4,
+		}
+	}
+}
+
+/// Block rewards of a generated chain, see `reward_ledger`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewardSchedule {
+	/// Reward of the author of every block before the first tier, before any uncle inclusion reward.
+	pub base_reward: U256,
+	/// Base rewards replacing `base_reward` from their activation block on, as forks reduced it.
+	pub tiers: BTreeMap<BlockNumber, U256>,
+}
+
+impl RewardSchedule {
+	/// Reward every block with `base_reward`.
+	pub fn new(base_reward: U256) -> Self {
+		RewardSchedule {
+			base_reward,
+			tiers: BTreeMap::new(),
+		}
+	}
+
+	/// Reward blocks from `activation_block` on with `base_reward`, until the next tier.
+	pub fn with_tier(mut self, activation_block: BlockNumber, base_reward: U256) -> Self {
+		self.tiers.insert(activation_block, base_reward);
+		self
+	}
+
+	/// Base reward of the block at `number`.
+	pub fn base_reward_at(&self, number: BlockNumber) -> U256 {
+		self.tiers.iter().rev()
+			.find(|&(activation, _)| *activation <= number)
+			.map_or(self.base_reward, |(_, reward)| *reward)
+	}
+}
+
+/// Block rewards of the chain made of the blocks of `segments`, in order, per payee, following the Ethash rules.
+///
+/// Each block pays `base_reward` plus `base_reward / 32` per uncle to its beneficiary, which is
+/// its author unless set by `with_reward_beneficiary` on its segment, and `base_reward * (8 + uncle - block) / 8`
+/// to the author of each of its uncles, `base_reward` being that of the tier active at the block.
+/// The genesis block isn't rewarded.
+pub fn reward_ledger(segments: &[&BlockBuilder], schedule: &RewardSchedule) -> BTreeMap<Address, U256> {
+	let mut ledger = BTreeMap::new();
+	for segment in segments {
+		for block in segment.blocks.iter().filter(|block| block.number() > 0) {
+			let base = schedule.base_reward_at(block.number());
+			let payee = segment.beneficiary.unwrap_or(*block.header.author());
+			let reward = base + base / U256::from(32) * U256::from(block.uncles.len());
+			*ledger.entry(payee).or_insert_with(U256::zero) += reward;
+
+			for uncle in &block.uncles {
+				let depth = U256::from(8 + uncle.number() - block.number());
+				*ledger.entry(*uncle.author()).or_insert_with(U256::zero) += base * depth / U256::from(8);
+			}
+		}
+	}
+
+	ledger
+}
+
+/// Parent hash new blocks are stamped with, see `BlockBuilder::with_parent_policy`.
+///
+/// Timestamps, difficulty and numbers still follow the actual previous block, only the parent hash is affected.
+#[derive(Clone, Copy)]
+pub enum ParentPolicy {
+	/// Hash of the previous block.
+	Correct,
+	/// Hash of the genesis block of the builder.
+	AlwaysGenesis,
+	/// Hash of the block before the previous one, or zero for the block following genesis.
+	SkipOne,
+	/// Hash returned for the number of the new block.
+	Custom(fn(BlockNumber) -> H256),
+}
+
+impl Default for ParentPolicy {
+	fn default() -> Self {
+		ParentPolicy::Correct
+	}
+}
+
+/// Header a block lists as its own uncle, see `BlockBuilder::add_block_with_self_uncle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfUncle {
+	/// The header of the including block itself, as built before its uncles were set.
+	Itself,
+	/// The header of the including block's parent.
+	Parent,
+}
+
+/// Block time every block of a builder aims for.
+#[derive(Debug, Clone, Copy)]
+struct BlockTime {
+	target: u64,
+	jitter: u64,
+}
+
+impl BlockTime {
+	/// Seconds between the block with given parent and its parent, `target` shifted by up to `jitter` either way.
+	fn delta(&self, parent_hash: &H256) -> u64 {
+		let shift = parent_hash.low_u64() % (2 * self.jitter + 1);
+		self.target + shift - self.jitter
+	}
+
+	/// Homestead difficulty adjustment with the 10 seconds duration limit replaced by `target`.
+	///
+	/// Difficulty grows by one step when the block came in less than half of `target`, stays the same
+	/// within half of `target` either way and drops by one step for each further `target` elapsed.
+	fn difficulty(&self, parent: &Header, timestamp: u64) -> U256 {
+		let delta = timestamp.saturating_sub(parent.timestamp());
+		let minimum_difficulty = U256::from(MINIMUM_DIFFICULTY);
+		let parent_difficulty = cmp::max(*parent.difficulty(), minimum_difficulty);
+		let step = parent_difficulty / U256::from(DIFFICULTY_BOUND_DIVISOR);
+		let periods = (2 * delta + self.target) / (2 * self.target);
+		let difficulty = match periods {
+			0 => parent_difficulty + step,
+			periods => parent_difficulty - step * U256::from(cmp::min(periods - 1, 99)),
+		};
+		cmp::max(difficulty, minimum_difficulty)
+	}
+}
+
+/// Successful receipts of `transactions`, each with a log per topic emitted by the transaction's
+/// destination. The receipts claim no gas used.
+///
+/// Given `addresses`, the logs are emitted by them in turn instead, starting with the one at
+/// `next_address`, which is left at the one following the last log.
+fn log_receipts(transactions: &[SignedTransaction], topics: &[H256], addresses: Option<&[Address]>, next_address: &mut usize) -> Vec<Receipt> {
+	transactions.iter().map(|tx| {
+		let destination = match tx.action {
+			Action::Call(address) => address,
+			Action::Create => Address::zero(),
+		};
+		let logs = topics.iter().map(|topic| {
+			let address = match addresses {
+				Some(addresses) if !addresses.is_empty() => {
+					let address = addresses[*next_address % addresses.len()];
+					*next_address = (*next_address + 1) % addresses.len();
+					address
+				},
+				_ => destination,
+			};
+			LogEntry {
+				address,
+				topics: vec![*topic],
+				data: Vec::new(),
+			}
+		}).collect();
+		ReceiptFormat::Status(true).receipt(U256::zero(), logs)
+	}).collect()
+}
+
+/// Synthesizes `count` distinct uncles sharing parent, number and difficulty with `sibling_of`.
+///
+/// Uncles depend on nothing but `sibling_of` and their index, the i-th one being authored by
+/// `Address::from(i + 1)` with all other fields left at their defaults. For a fixed builder
+/// configuration they, and the `uncles_hash` of the block including them, are byte-stable
+/// across runs, so they can be relied on by golden-file fixtures.
+fn sibling_uncles(sibling_of: &Header, count: usize) -> Vec<Header> {
+	(0..count).map(|i| {
+		let mut uncle = Header::default();
+		uncle.set_parent_hash(*sibling_of.parent_hash());
+		uncle.set_number(sibling_of.number());
+		uncle.set_difficulty(*sibling_of.difficulty());
+		uncle.set_author(Address::from(i as u64 + 1));
+		uncle
+	}).collect()
+}
+
+/// Computes the `uncles_hash` of a block including given uncles.
+fn uncles_hash(uncles: &[Header]) -> H256 {
+	let mut stream = RlpStream::new_list(uncles.len());
+	for uncle in uncles {
+		stream.append(uncle);
+	}
+	keccak(stream.out())
+}
+
+fn body_size(transactions: &[SignedTransaction], uncles: &[Header]) -> usize {
+	let mut stream = RlpStream::new_list(2);
+	stream.append_list(transactions);
+	stream.append_list(uncles);
+	stream.out().len()
+}
+
+/// Appends zero-filled calls to `transactions` until the encoded body is within `BODY_SIZE_TOLERANCE`
+/// below `target` bytes. The body never grows beyond `target`.
+fn pad_body(transactions: &mut Vec<SignedTransaction>, uncles: &[Header], target: usize) {
+	let signer = FixedKeySigner::default();
+	let padding = |nonce: usize, len: usize| signer.sign(Transaction {
+		nonce: nonce.into(),
+		gas_price: U256::zero(),
+		gas: (21_000 + 4 * len).into(),
+		action: Action::Call(Address::default()),
+		value: U256::zero(),
+		data: vec![0; len],
+	});
+
+	let mut size = body_size(transactions, uncles);
+	while size + BODY_SIZE_TOLERANCE < target {
+		let nonce = transactions.len();
+		let mut len = cmp::min(target - size, MAX_PADDING_DATA);
+		loop {
+			transactions.push(padding(nonce, len));
+			let padded = body_size(transactions, uncles);
+			if padded <= target {
+				size = padded;
+				break;
+			}
+
+			transactions.pop();
+			let excess = padded - target;
+			if excess > len {
+				// not even an empty padding transaction fits.
+				return;
+			}
+			len -= excess;
+		}
+	}
+}
+
+/// Splits an encoded block into its encoded header and its body as sent in `BlockBodies`,
+/// a list of the block's transactions and uncles.
+pub fn split_block(rlp: &Bytes) -> (Bytes, Bytes) {
+	let block = Rlp::new(rlp);
+	let item = |i| block.at(i).expect("split_block requires an encoded block").as_raw();
+
+	let mut body = RlpStream::new_list(2);
+	body.append_raw(item(1), 1);
+	body.append_raw(item(2), 1);
+	(item(0).to_vec(), body.out())
+}
+
+/// Joins an encoded header and body, as produced by `split_block`, into an encoded block.
+pub fn join_block(header: &Bytes, body: &Bytes) -> Bytes {
+	let body = Rlp::new(body);
+	let item = |i| body.at(i).expect("join_block requires an encoded block body").as_raw();
+
+	let mut block = RlpStream::new_list(3);
+	block.append_raw(header, 1);
+	block.append_raw(item(0), 1);
+	block.append_raw(item(1), 1);
+	block.out()
+}
+
+/// Hash of the header of an encoded block, computed from its decoded fields rather than its raw bytes.
+///
+/// The header is re-encoded canonically before hashing, so this matches `BlockView::header_view().hash()`
+/// exactly when the header was encoded canonically, unlike those of `BlockBuilder::with_noncanonical_rlp`.
+pub fn block_hash(block_rlp: &Bytes) -> H256 {
+	let header: Header = Rlp::new(block_rlp).val_at(0).expect("block_hash requires an encoded block");
+	keccak(encode(&header))
+}
+
+/// Merkle-Patricia proof of inclusion of the transaction at `tx_index` in an encoded block.
+///
+/// Rebuilds the transactions trie of the block and returns the nodes on the path of the key
+/// `rlp(tx_index)`, which verify against the transactions root of the header with `ethtrie::verify_proof`.
+pub fn tx_inclusion_proof(block_rlp: &Bytes, tx_index: usize) -> Result<Vec<Bytes>, String> {
+	let transactions = Rlp::new(block_rlp).at(1).map_err(|e| format!("Invalid block: {}", e))?;
+	let count = transactions.item_count().map_err(|e| format!("Invalid block transactions: {}", e))?;
+	if tx_index >= count {
+		return Err(format!("Transaction index {} out of range, the block has {} transactions", tx_index, count));
+	}
+
+	let mut db = MemoryDB::<KeccakHasher>::new();
+	let mut root = H256::new();
+	{
+		let mut trie = TrieDBMut::new(&mut db, &mut root);
+		for (i, tx) in transactions.iter().enumerate() {
+			trie.insert(&encode(&i), tx.as_raw()).map_err(|e| format!("{}", e))?;
+		}
+	}
+
+	let trie = TrieDB::new(&db, &root).map_err(|e| format!("{}", e))?;
+	let mut recorder = Recorder::new();
+	trie.get_with(&encode(&tx_index), &mut recorder).map_err(|e| format!("{}", e))?;
+	Ok(recorder.drain().into_iter().map(|record| record.data).collect())
+}
+
+/// Rule choosing the canonical chain among competing ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkChoiceRule {
+	/// The chain with the highest total difficulty wins.
+	HighestTotalDifficulty,
+	/// The chain with the highest head block number wins.
+	LongestChain,
+}
+
+/// Index and head hash of the chain winning under `rule` among `chains` of encoded blocks.
+///
+/// Chains are expected to branch off a common ancestor, total difficulty counts the blocks given
+/// only. Ties are broken in favour of the lowest head hash. Panics if any chain is empty.
+pub fn canonical_head(chains: &[Vec<Bytes>], rule: ForkChoiceRule) -> (usize, H256) {
+	assert!(!chains.is_empty(), "canonical_head requires at least one chain");
+
+	let scored = chains.iter().map(|chain| {
+		let headers = chain.iter().map(|block| view!(BlockView, block).header()).collect::<Vec<_>>();
+		let head = headers.last().expect("canonical_head requires non-empty chains");
+		let score = match rule {
+			ForkChoiceRule::HighestTotalDifficulty => headers.iter().fold(U256::zero(), |td, header| td + *header.difficulty()),
+			ForkChoiceRule::LongestChain => head.number().into(),
+		};
+		(score, head.hash())
+	}).collect::<Vec<_>>();
+
+	scored.into_iter()
+		.enumerate()
+		.max_by(|&(_, (ref a, ref a_hash)), &(_, (ref b, ref b_hash))| a.cmp(b).then(b_hash.cmp(a_hash)))
+		.map(|(index, (_, hash))| (index, hash))
+		.expect("there is at least one chain; qed")
+}
+
+/// Write `count` blocks generated on top of the last block of `builder` to `writer`, as a sequence
+/// of encoded blocks. Returns the number of bytes written.
+///
+/// Blocks are generated and written one at a time, so memory use doesn't grow with `count`.
+pub fn generate_to_writer<W>(builder: &BlockBuilder, count: usize, writer: &mut W) -> io::Result<u64> where W: Write {
+	let mut chain = ChainHandle::new(builder.clone(), BlockOptions::default);
+	let mut written = 0;
+	for _ in 0..count {
+		let block = chain.next_block();
+		writer.write_all(&block)?;
+		written += block.len() as u64;
+	}
+	writer.flush()?;
+	Ok(written)
+}
+
+/// Write encoded `blocks` in the binary layout `parity import <file>` reads, returning the number of
+/// bytes written.
+///
+/// The layout has no framing: blocks are concatenated, and the importer learns the length of each one
+/// from its RLP list header. Unless given `--format binary`, the importer only takes the file as binary
+/// if its first byte is `0xf9`, the header of lists of 256 bytes to 64 KiB, so the first block must be
+/// that long. Fails before writing anything if a block isn't exactly one RLP item or the first one
+/// would make the importer read the file as hex.
+pub fn export_import_format<W>(blocks: &[Bytes], writer: &mut W) -> io::Result<u64> where W: Write {
+	let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+	for (index, block) in blocks.iter().enumerate() {
+		match PayloadInfo::from(block) {
+			Ok(ref info) if info.total() == block.len() => {},
+			_ => return Err(invalid(format!("Block {} is not a single RLP item", index))),
+		}
+	}
+	if let Some(first) = blocks.first() {
+		if first[0] != 0xf9 {
+			return Err(invalid(format!("First block of {} bytes would be read as hex", first.len())));
+		}
+	}
+
+	let mut written = 0;
+	for block in blocks {
+		writer.write_all(block)?;
+		written += block.len() as u64;
+	}
+	writer.flush()?;
+	Ok(written)
+}
+
+/// Reads back the blocks written by `export_import_format` the way `parity import` does: the first
+/// 8 bytes of each block are read to learn its length from the RLP header, then the rest of it.
+pub fn read_import_format<R>(reader: &mut R) -> io::Result<Vec<Bytes>> where R: Read {
+	const READAHEAD_BYTES: usize = 8;
+
+	let mut blocks = Vec::new();
+	loop {
+		let mut bytes = vec![0; READAHEAD_BYTES];
+		let read = reader.read(&mut bytes)?;
+		if read == 0 {
+			break;
+		}
+
+		let len = PayloadInfo::from(&bytes)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid block RLP: {:?}", e)))?
+			.total();
+		if len < read {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Block of {} bytes is too short", len)));
+		}
+		bytes.resize(len, 0);
+		reader.read_exact(&mut bytes[read..])?;
+		blocks.push(bytes);
+	}
+
+	Ok(blocks)
+}
+
+// name of the `index`-th file of a sharded chain.
+fn shard_file_name(index: usize) -> String {
+	format!("chain-{:04}.rlp", index)
+}
+
+/// Writes encoded `blocks` into `dir` as numbered files (`chain-0000.rlp`, `chain-0001.rlp`, ...)
+/// of `blocks_per_file` blocks each, the last file holding the remainder.
+///
+/// Every block is prefixed with its length as a big-endian `u32`. Returns the number of files written.
+pub fn export_sharded(blocks: &[Bytes], dir: &Path, blocks_per_file: usize) -> Result<usize, String> {
+	assert!(blocks_per_file > 0, "export_sharded requires at least one block per file");
+
+	fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+	for (index, shard) in blocks.chunks(blocks_per_file).enumerate() {
+		let path = dir.join(shard_file_name(index));
+		let mut file = File::create(&path).map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+		for block in shard {
+			let mut len = [0u8; 4];
+			BigEndian::write_u32(&mut len, block.len() as u32);
+			file.write_all(&len)
+				.and_then(|_| file.write_all(block))
+				.map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+		}
+	}
+
+	Ok((blocks.len() + blocks_per_file - 1) / blocks_per_file)
+}
+
+/// Reads back the blocks written by `export_sharded` into `dir`, in order.
+///
+/// Fails if a file of the sequence is missing, a file is truncated or the blocks of consecutive
+/// files don't follow each other by number and parent hash.
+pub fn import_sharded(dir: &Path) -> Result<Vec<Bytes>, String> {
+	let entries = fs::read_dir(dir).map_err(|e| format!("Unable to read {}: {}", dir.display(), e))?;
+	let mut indices = Vec::new();
+	for entry in entries {
+		let name = entry.map_err(|e| format!("Unable to read {}: {}", dir.display(), e))?.file_name();
+		let index = name.to_str().and_then(|name| {
+			let index = name.trim_left_matches("chain-").trim_right_matches(".rlp").parse::<usize>().ok()?;
+			if shard_file_name(index) == name { Some(index) } else { None }
+		});
+		if let Some(index) = index {
+			indices.push(index);
+		}
+	}
+
+	if indices.is_empty() {
+		return Err(format!("No chain files found in {}", dir.display()));
+	}
+
+	indices.sort();
+	if let Some(missing) = (0..indices.len()).find(|&i| indices[i] != i) {
+		return Err(format!("Missing chain file {} in {}", shard_file_name(missing), dir.display()));
+	}
+
+	let mut blocks: Vec<Bytes> = Vec::new();
+	for index in indices {
+		let path = dir.join(shard_file_name(index));
+		let mut data = Vec::new();
+		File::open(&path)
+			.and_then(|mut file| file.read_to_end(&mut data))
+			.map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+
+		let mut rest = &data[..];
+		while !rest.is_empty() {
+			if rest.len() < 4 {
+				return Err(format!("Truncated block length in {}", path.display()));
+			}
+			let len = BigEndian::read_u32(&rest[..4]) as usize;
+			if rest.len() < 4 + len {
+				return Err(format!("Truncated block in {}", path.display()));
+			}
+
+			let block = rest[4..4 + len].to_vec();
+			let header = Rlp::new(&block).val_at::<Header>(0)
+				.map_err(|e| format!("Invalid block in {}: {}", path.display(), e))?;
+			if let Some(previous) = blocks.last() {
+				let previous = view!(BlockView, previous).header_view();
+				if header.number() != previous.number() + 1 || *header.parent_hash() != previous.hash() {
+					return Err(format!("Block #{} in {} doesn't follow block #{}, chain files are out of order",
+						header.number(), path.display(), previous.number()));
+				}
+			}
+
+			blocks.push(block);
+			rest = &rest[4 + len..];
 		}
 	}
+
+	Ok(blocks)
 }
 
 #[derive(Clone)]
 pub struct BlockBuilder {
 	blocks: VecDeque<Block>,
+	/// Hashes of the most recent blocks of the chain, up to the last one.
+	ancestry: VecDeque<H256>,
+	/// Block time driving timestamps and difficulty of new blocks, if any.
+	block_time: Option<BlockTime>,
+	/// Author of all new blocks, if any.
+	author: Option<Address>,
+	/// Encoded body size new blocks are padded to, if any.
+	body_size: Option<usize>,
+	/// Gas limit of new blocks not setting one, if any.
+	gas_limit: Option<U256>,
+	/// Parent hash new blocks are stamped with.
+	parent_policy: ParentPolicy,
+	/// Hash of the genesis block the chain started from.
+	genesis: H256,
+	/// Payee of the block rewards of the blocks, if not their author.
+	beneficiary: Option<Address>,
+	/// Gas used by the blocks beyond their gas limit, if any.
+	gas_used_excess: Option<U256>,
+	/// Seconds the timestamp of new blocks falls behind their parent's, if any.
+	timestamp_decrease: Option<u64>,
+	/// Topics of the logs in the generated receipt of each transaction, if any.
+	log_topics: Option<Vec<H256>>,
+	/// Addresses taking turns as the source of the generated logs, if any.
+	log_addresses: Option<Vec<Address>>,
+	/// Index within `log_addresses` of the source of the next generated log.
+	next_log_address: usize,
+	/// Non-canonical encoding of the headers of new blocks, if any.
+	rlp_quirk: Option<RlpQuirk>,
+	/// Value of `prevRandao` stamped into the mix hash of new post-merge blocks, if any.
+	prev_randao: Option<H256>,
+	/// Layout of the PoW seal of new blocks, if they get one.
+	pow_seal: Option<PowSealFormat>,
+	/// Order of the transactions within new blocks.
+	tx_ordering: TxOrdering,
+	/// Whether new blocks get a header of just the classic 15 fields.
+	minimal_header: bool,
+	/// Distribution of the gas of transactions signed for new blocks, if any.
+	gas_distribution: Option<GasDistribution>,
+	/// AuRa seal of new blocks, if they get one.
+	authority_seal: Option<AuthoritySeal>,
+	/// Number of the block stamped with zero difficulty, if any.
+	td_anomaly: Option<BlockNumber>,
+	/// Whether new blocks are stamped with zero difficulty.
+	zero_difficulty: bool,
+	/// Total difficulty of the chain up to the last block.
+	total_difficulty: U256,
 }
 
 impl BlockBuilder {
 	pub fn genesis() -> Self {
 		let mut blocks = VecDeque::with_capacity(1);
 		blocks.push_back(Block::default());
+		let mut ancestry = VecDeque::with_capacity(MAX_UNCLE_AGE + 1);
+		let genesis = blocks[0].hash();
+		ancestry.push_back(genesis);
 
 		BlockBuilder {
 			blocks,
+			ancestry,
+			block_time: None,
+			author: None,
+			body_size: None,
+			gas_limit: None,
+			parent_policy: ParentPolicy::default(),
+			genesis,
+			beneficiary: None,
+			gas_used_excess: None,
+			timestamp_decrease: None,
+			log_topics: None,
+			log_addresses: None,
+			next_log_address: 0,
+			rlp_quirk: None,
+			prev_randao: None,
+			pow_seal: None,
+			tx_ordering: TxOrdering::default(),
+			minimal_header: false,
+			gas_distribution: None,
+			authority_seal: None,
+			td_anomaly: None,
+			zero_difficulty: false,
+			total_difficulty: U256::zero(),
+		}
+	}
+
+	/// Start a chain mimicking `network`, with its genesis difficulty and gas limit and aiming for its block time.
+	///
+	/// All following blocks get the gas limit of the network unless their `BlockOptions` set one.
+	pub fn preset(network: NetworkPreset) -> Self {
+		let mut builder = BlockBuilder::genesis();
+		builder.blocks[0].header.set_difficulty(network.genesis_difficulty());
+		builder.blocks[0].header.set_gas_limit(network.gas_limit());
+		builder.ancestry[0] = builder.blocks[0].hash();
+		builder.genesis = builder.ancestry[0];
+		builder.total_difficulty = network.genesis_difficulty();
+		builder.gas_limit = Some(network.gas_limit());
+		builder.with_target_block_time(network.block_time())
+	}
+
+	/// Start a chain at block number `u64::MAX - offset`, for tests of block numbers close to overflow.
+	///
+	/// Adding blocks past the last representable number panics, `ChainHandle` stops there instead.
+	pub fn starting_at_max(offset: u64) -> Self {
+		let mut builder = BlockBuilder::genesis();
+		builder.blocks[0].header.set_number(BlockNumber::max_value() - offset);
+		builder.ancestry[0] = builder.blocks[0].hash();
+		builder.genesis = builder.ancestry[0];
+		builder
+	}
+
+	/// Continue a chain restored from a snapshot at block `pivot_number` with hash `pivot_hash` and total
+	/// difficulty `pivot_td`, for tests of chains whose ancestry below the pivot is missing.
+	///
+	/// The first added block is number `pivot_number + 1` and has `pivot_hash` as its parent, total
+	/// difficulty accumulating from `pivot_td`. None of the blocks up to the pivot are generated: the
+	/// last block of the returned builder is a stand-in for the pivot, with its number but not its hash,
+	/// which must not be imported. Blocks added on top of it reference `pivot_hash` only, so they can.
+	pub fn continue_from_snapshot(pivot_number: BlockNumber, pivot_hash: H256, pivot_td: U256) -> Self {
+		let mut builder = BlockBuilder::genesis();
+		builder.blocks[0].header.set_number(pivot_number);
+		builder.ancestry[0] = pivot_hash;
+		builder.genesis = pivot_hash;
+		builder.total_difficulty = pivot_td;
+		builder
+	}
+
+	/// Total difficulty of the chain up to the last block, counting from the genesis or snapshot pivot.
+	pub fn total_difficulty(&self) -> U256 {
+		self.total_difficulty
+	}
+
+	/// Hash of the genesis block the chain starts from, as emitted first by a `BlockGenerator` over
+	/// the chain. Available without generating any further blocks, e.g. to configure a spec.
+	pub fn genesis_hash(&self) -> H256 {
+		self.genesis
+	}
+
+	/// Add all following blocks `secs` seconds apart, deriving their difficulty from the actual block time.
+	///
+	/// The difficulty set in `BlockOptions` is ignored, `timestamp_delta` still overrides the block time.
+	pub fn with_target_block_time(&self, secs: u64) -> Self {
+		assert!(secs > 0, "Target block time must be positive");
+		let mut builder = self.clone();
+		builder.block_time = Some(BlockTime {
+			target: secs,
+			jitter: 0,
+		});
+		builder
+	}
+
+	/// Stamp all following blocks with zero difficulty, as produced by PoA and post-merge chains.
+	///
+	/// Total difficulty does not grow across these blocks, so fork choice has to fall back to another rule.
+	/// Takes priority over the difficulty of the block time and of `BlockOptions`, timestamps still follow them.
+	pub fn with_zero_difficulty(&self) -> Self {
+		let mut builder = self.clone();
+		builder.zero_difficulty = true;
+		builder
+	}
+
+	/// Author all following blocks by `author`, e.g. to tell a fork apart from the chain it branches off.
+	///
+	/// The author set in `BlockOptions` is ignored.
+	pub fn with_author(&self, author: Address) -> Self {
+		let mut builder = self.clone();
+		builder.author = Some(author);
+		builder
+	}
+
+	/// Stamp all following blocks with the parent hash chosen by `policy`, e.g. to feed import a broken chain.
+	pub fn with_parent_policy(&self, policy: ParentPolicy) -> Self {
+		let mut builder = self.clone();
+		builder.parent_policy = policy;
+		builder
+	}
+
+	/// Credit the block rewards of the blocks of this builder and all following ones to `beneficiary`,
+	/// while their headers still record their author. Models setups delegating rewards.
+	pub fn with_reward_beneficiary(&self, beneficiary: Address) -> Self {
+		let mut builder = self.clone();
+		builder.beneficiary = Some(beneficiary);
+		builder
+	}
+
+	/// Pad the bodies of all following blocks with transactions until they are about `bytes` long encoded.
+	///
+	/// Bodies end up at most `BODY_SIZE_TOLERANCE` bytes short of the target and never exceed it.
+	/// Bodies already larger than the target are left alone. `Block::body_size` reports the achieved size.
+	pub fn with_target_body_size(&self, bytes: usize) -> Self {
+		let mut builder = self.clone();
+		builder.body_size = Some(bytes);
+		builder
+	}
+
+	/// Order the transactions of all following blocks `by` the given ordering rather than as given.
+	///
+	/// Generated receipts follow the new order, receipts given in `BlockOptions` are left alone.
+	pub fn order_transactions(&self, by: TxOrdering) -> Self {
+		let mut builder = self.clone();
+		builder.tx_ordering = by;
+		builder
+	}
+
+	/// Draw the gas of the transactions signed for all following blocks from `distribution`.
+	///
+	/// Applies to `add_block_with_unsigned_transactions` and `add_block_with_transactions_signed_by`,
+	/// whose blocks then use the gas of all their transactions, raising the gas limit to fit if needed.
+	pub fn with_gas_distribution(&self, distribution: GasDistribution) -> Self {
+		assert!(distribution.is_valid(), "Gas ranges must not be empty and the ratio must be between 0 and 1");
+		let mut builder = self.clone();
+		builder.gas_distribution = Some(distribution);
+		builder
+	}
+
+	/// Shift the block time of the following blocks by up to `secs` seconds either way.
+	///
+	/// The shift is derived from the parent hash, so generated chains are reproducible.
+	pub fn with_block_time_jitter(&self, secs: u64) -> Self {
+		let mut builder = self.clone();
+		{
+			let block_time = builder.block_time.as_mut().expect("Jitter requires a target block time");
+			assert!(secs < block_time.target, "Jitter must be smaller than the target block time");
+			block_time.jitter = secs;
 		}
+		builder
 	}
 
 	#[inline]
@@ -127,36 +1192,526 @@ impl BlockBuilder {
 	}
 
 	#[inline]
-	pub fn add_block_with_bloom(&self, bloom: Bloom) -> Self {
-		self.add_blocks_with(1, move || BlockOptions {
-			bloom,
+	pub fn add_block_with_unsigned_transactions<T>(&self, transactions: T) -> Self
+		where T: IntoIterator<Item = Transaction> {
+		self.add_block_with_transactions_signed_by(transactions, &FixedKeySigner::default())
+	}
+
+	pub fn add_block_with_transactions_signed_by<T, S>(&self, transactions: T, signer: &S) -> Self
+		where T: IntoIterator<Item = Transaction>, S: TxSigner {
+		let distribution = match self.gas_distribution {
+			Some(distribution) => distribution,
+			None => return self.add_block_with_transactions(transactions.into_iter().map(|tx| signer.sign(tx))),
+		};
+
+		let parent_hash = self.last().hash();
+		let transactions = transactions.into_iter().enumerate().map(|(index, mut tx)| {
+			tx.gas = distribution.sample(&parent_hash, index);
+			signer.sign(tx)
+		}).collect::<Vec<_>>();
+		let gas_used = transactions.iter().fold(U256::zero(), |sum, tx| sum + tx.gas);
+		let gas_limit = cmp::max(self.gas_limit.unwrap_or_else(U256::zero), gas_used);
+		self.add_blocks_with(1, || BlockOptions {
+			transactions: transactions.clone(),
+			gas_limit,
+			gas_used,
 			..Default::default()
 		})
 	}
 
-	pub fn add_blocks_with<T>(&self, count: usize, get_metadata: T) -> Self where T: Fn() -> BlockOptions {
-		assert!(count > 0, "There must be at least 1 block");
-		let mut parent_hash = self.last().hash();
+	#[inline]
+	pub fn add_block_with_receipts<T>(&self, receipts: T) -> Self
+		where T: IntoIterator<Item = Receipt> {
+		let receipts = receipts.into_iter().collect::<Vec<_>>();
+		self.add_blocks_with(1, || BlockOptions {
+			receipts: receipts.clone(),
+			..Default::default()
+		})
+	}
+
+	#[inline]
+	pub fn add_block_with_bloom(&self, bloom: Bloom) -> Self {
+		self.add_blocks_with(1, move || BlockOptions {
+			bloom,
+			..Default::default()
+		})
+	}
+
+	/// Add `count` blocks, each including up to `per_block` uncles taken in order from the blocks of `fork`.
+	///
+	/// Panics if an uncle's parent is not an ancestor of the including block within the uncle age window.
+	pub fn add_blocks_with_uncles_from(&self, count: usize, fork: &BlockBuilder, per_block: usize) -> Self {
+		assert!(count > 0, "There must be at least 1 block");
+		let mut uncles = fork.blocks.iter().map(Block::header);
+		let mut builder = self.clone();
+		let mut blocks = VecDeque::with_capacity(count);
+		for _ in 0..count {
+			let block_uncles = uncles.by_ref().take(per_block).collect::<Vec<_>>();
+			for uncle in &block_uncles {
+				assert!(builder.is_valid_uncle(uncle), "Uncle {} is not a child of an ancestor within the uncle window", uncle.hash());
+			}
+
+			builder = builder.add_block_with(move || BlockOptions {
+				uncles: block_uncles.clone(),
+				..Default::default()
+			});
+			blocks.push_back(builder.last().clone());
+		}
+
+		BlockBuilder { blocks, ..builder }
+	}
+
+	/// Negative-test helper: make all following blocks use `excess` more gas than their gas limit.
+	///
+	/// The blocks are well-formed, so import rejects them only for their gas used. The gas used
+	/// set in `BlockOptions` is ignored.
+	pub fn with_gas_used_over_limit(&self, excess: U256) -> Self {
+		assert!(!excess.is_zero(), "Blocks using up to their gas limit are valid, use `add_blocks_with` instead");
+		let mut builder = self.clone();
+		builder.gas_used_excess = Some(excess);
+		builder
+	}
+
+	/// Give each transaction of all following blocks a receipt with `count` logs, the i-th one with the
+	/// single topic `topic_generator(i)`. The header bloom accrues all logs.
+	///
+	/// Blocks given receipts through `BlockOptions` keep those.
+	pub fn with_logs_per_tx<F>(&self, count: usize, topic_generator: F) -> Self where F: Fn(usize) -> H256 {
+		let mut builder = self.clone();
+		builder.log_topics = Some((0..count).map(topic_generator).collect());
+		builder
+	}
+
+	/// Make the logs given by `with_logs_per_tx` to all following blocks take turns emitting from
+	/// `addresses`, instead of from the destination of their transaction.
+	///
+	/// Turns carry over from block to block, so each log of a given chain has a stable address, found
+	/// both in its receipt and in the header bloom of its block. Empty `addresses` restore destinations.
+	pub fn with_log_addresses(&self, addresses: Vec<Address>) -> Self {
+		let mut builder = self.clone();
+		builder.log_addresses = if addresses.is_empty() { None } else { Some(addresses) };
+		builder.next_log_address = 0;
+		builder
+	}
+
+	/// Interop helper: encode the headers of all following blocks with `quirk`.
+	///
+	/// Headers keep their values, only their encoding and so their hashes differ from canonical blocks.
+	pub fn with_noncanonical_rlp(&self, quirk: RlpQuirk) -> Self {
+		let mut builder = self.clone();
+		builder.rlp_quirk = Some(quirk);
+		builder
+	}
+
+	/// Make all following blocks post-merge (EIP-3675) blocks, with zero difficulty and an Ethash seal
+	/// of `prev_randao` as mix hash and a zero nonce.
+	///
+	/// Overrides the difficulty of the block time and of `BlockOptions`, so total difficulty stops
+	/// growing from the first post-merge block on.
+	pub fn with_merge_fields(&self, prev_randao: H256) -> Self {
+		let mut builder = self.clone();
+		builder.prev_randao = Some(prev_randao);
+		builder
+	}
+
+	/// Interop helper: seal all following blocks with a PoW seal in `format`, of the block number as
+	/// mix hash and nonce.
+	///
+	/// Post-merge blocks keep their Ethash seal, see `with_merge_fields`.
+	pub fn with_pow_seal(&self, format: PowSealFormat) -> Self {
+		assert!(format.nonce_width > 0, "Nonces are at least 1 byte wide");
+		let mut builder = self.clone();
+		builder.pow_seal = Some(format);
+		builder
+	}
+
+	/// Seal all following blocks with an AuRa seal.
+	///
+	/// Overrides the seals of `with_pow_seal` and `with_merge_fields`.
+	pub fn with_authority_seal(&self, seal: AuthoritySeal) -> Self {
+		let mut builder = self.clone();
+		builder.authority_seal = Some(seal);
+		builder
+	}
+
+	/// Interop helper: give all following blocks a header of only the classic 15 fields, the 13
+	/// pre-merge fields and an Ethash seal of mix hash and 8-byte nonce, as archival blocks have.
+	///
+	/// Overrides the seal format of `with_pow_seal`, post-merge blocks keep `prev_randao` as mix hash.
+	pub fn with_minimal_header(&self) -> Self {
+		let mut builder = self.clone();
+		builder.minimal_header = true;
+		builder
+	}
+
+	/// Negative-test helper: stamp the block at index `at` of the following blocks with zero
+	/// difficulty, so total difficulty plateaus at it.
+	///
+	/// Overrides the difficulty of the block time and of `BlockOptions` for that block only.
+	pub fn with_td_anomaly(&self, at: usize) -> Self {
+		let mut builder = self.clone();
+		builder.td_anomaly = Some(self.last().number() + 1 + at as BlockNumber);
+		builder
+	}
+
+	/// Negative-test helper: make the timestamp of all following blocks `step` seconds before their
+	/// parent's, saturating at 0.
+	///
+	/// Overrides the block time and the timestamp delta set in `BlockOptions`, so import rejects the
+	/// blocks for going back in time.
+	pub fn with_decreasing_timestamps(&self, step: u64) -> Self {
+		let mut builder = self.clone();
+		builder.timestamp_decrease = Some(step);
+		builder
+	}
+
+	/// Negative-test helper: add a block including `count` uncles, more than consensus allows.
+	///
+	/// The uncles are distinct siblings of the last block, so the block only breaks the uncle count limit.
+	/// They are synthesized by `sibling_uncles`, so the block's `uncles_hash` is stable across runs.
+	pub fn add_block_with_over_limit_uncles(&self, count: usize) -> Self {
+		assert!(count > 2, "Blocks with up to 2 uncles are valid, use `add_blocks_with_uncles_from` instead");
+		assert!(self.last().number() > 0, "Genesis has no siblings to use as uncles");
+		let uncles = sibling_uncles(&self.last().header(), count);
+
+		self.add_block_with(move || BlockOptions {
+			uncles: uncles.clone(),
+			..Default::default()
+		})
+	}
+
+	/// Negative-test helper: add a block whose number is `skip` higher than that of a regular successor.
+	///
+	/// The block still references the last block as its parent, so it is rejected only for its number.
+	pub fn add_block_with_number_jump(&self, skip: u64) -> Self {
+		assert!(skip > 0, "Blocks without a number jump are valid, use `add_block` instead");
+		let mut builder = self.add_block();
+		builder.blocks.back_mut().expect("There is always at least 1 block").header.set_number(self.last().number() + skip + 1);
+		let hash = builder.last().hash();
+		*builder.ancestry.back_mut().expect("Ancestry ends with the last block") = hash;
+		builder
+	}
+
+	/// Negative-test helper: add a block including a valid uncle, but declaring `fake` as its `uncles_hash`.
+	///
+	/// The uncle is a sibling of the last block synthesized by `sibling_uncles`, so the block is rejected
+	/// only for its uncles hash not matching the uncle list.
+	pub fn add_block_with_broken_uncles_hash(&self, fake: H256) -> Self {
+		assert!(self.last().number() > 0, "Genesis has no siblings to use as uncles");
+		let uncles = sibling_uncles(&self.last().header(), 1);
+		assert!(fake != uncles_hash(&uncles), "Uncles hash must mismatch, use `add_blocks_with_uncles_from` instead");
+
+		let mut builder = self.add_block_with(move || BlockOptions {
+			uncles: uncles.clone(),
+			..Default::default()
+		});
+		builder.blocks.back_mut().expect("There is always at least 1 block").header.set_uncles_hash(fake);
+		let hash = builder.last().hash();
+		*builder.ancestry.back_mut().expect("Ancestry ends with the last block") = hash;
+		builder
+	}
+
+	/// Negative-test helper: add a block listing its own header, or its parent's, as its only uncle.
+	///
+	/// The block's `uncles_hash` matches the uncle list, so the block is rejected only for including
+	/// itself or an ancestor as an uncle, which `add_blocks_with_uncles_from` never produces.
+	pub fn add_block_with_self_uncle(&self, uncle: SelfUncle) -> Self {
+		match uncle {
+			SelfUncle::Parent => {
+				let parent = self.last().header();
+				self.add_block_with(move || BlockOptions {
+					uncles: vec![parent.clone()],
+					..Default::default()
+				})
+			},
+			SelfUncle::Itself => {
+				let mut builder = self.add_block();
+				{
+					let block = builder.blocks.back_mut().expect("There is always at least 1 block");
+					let header = block.header();
+					block.header.set_uncles_hash(uncles_hash(&[header.clone()]));
+					block.uncles = vec![header];
+				}
+				let hash = builder.last().hash();
+				*builder.ancestry.back_mut().expect("Ancestry ends with the last block") = hash;
+				builder
+			},
+		}
+	}
+
+	/// Add a block for each of `ratios`, using `ratio * gas_limit` gas with the ratio clamped to `[0, 1]`.
+	///
+	/// Blocks with a ratio above 0.5 are above the gas target of a fee market, blocks below it are below the target.
+	pub fn add_blocks_with_gas_used_ratio(&self, gas_limit: U256, ratios: Vec<f64>) -> Self {
+		assert!(!ratios.is_empty(), "There must be at least 1 block");
+		let mut builder = self.clone();
+		let mut blocks = VecDeque::with_capacity(ratios.len());
+		for ratio in ratios {
+			let parts_per_million = (ratio.max(0.0).min(1.0) * 1_000_000.0) as u64;
+			let gas_used = gas_limit * parts_per_million / 1_000_000;
+			builder = builder.add_block_with(move || BlockOptions {
+				gas_limit,
+				gas_used,
+				..Default::default()
+			});
+			blocks.push_back(builder.last().clone());
+		}
+
+		BlockBuilder { blocks, ..builder }
+	}
+
+	pub fn add_blocks_with<T>(&self, count: usize, get_metadata: T) -> Self where T: Fn() -> BlockOptions {
+		assert!(count > 0, "There must be at least 1 block");
+		let mut parent = self.last().header();
+		let mut parent_hash = *self.ancestry.back().expect("Ancestry ends with the last block");
 		let mut parent_number = self.last().number();
+		let mut ancestry = self.ancestry.clone();
+		let mut next_log_address = self.next_log_address;
+		let mut total_difficulty = self.total_difficulty;
 		let mut blocks = VecDeque::with_capacity(count);
 		for _ in 0..count {
 			let mut block = Block::default();
-			let metadata = get_metadata();
-			let block_number = parent_number + 1;
-			block.header.set_parent_hash(parent_hash);
+			let mut metadata = get_metadata();
+			self.tx_ordering.sort(&mut metadata.transactions);
+			let block_number = parent_number.checked_add(1).expect("Block number overflow");
+			let timestamp_delta = metadata.timestamp_delta
+				.or_else(|| self.block_time.map(|block_time| block_time.delta(&parent_hash)))
+				.unwrap_or(0);
+			let timestamp = match self.timestamp_decrease {
+				Some(step) => parent.timestamp().saturating_sub(step),
+				None => parent.timestamp() + timestamp_delta,
+			};
+			let difficulty = match (self.prev_randao, self.block_time) {
+				_ if self.zero_difficulty || self.td_anomaly == Some(block_number) => U256::zero(),
+				(Some(_), _) => U256::zero(),
+				(None, Some(block_time)) => block_time.difficulty(&parent, timestamp),
+				(None, None) => metadata.difficulty,
+			};
+			block.header.set_parent_hash(match self.parent_policy {
+				ParentPolicy::Correct => parent_hash,
+				ParentPolicy::AlwaysGenesis => self.genesis,
+				ParentPolicy::SkipOne if ancestry.len() > 1 => ancestry[ancestry.len() - 2],
+				ParentPolicy::SkipOne => H256::zero(),
+				ParentPolicy::Custom(f) => f(block_number),
+			});
 			block.header.set_number(block_number);
-			block.header.set_log_bloom(metadata.bloom);
-			block.header.set_difficulty(metadata.difficulty);
+			block.header.set_timestamp(timestamp);
+			block.header.set_difficulty(difficulty);
+			block.header.set_author(self.author.unwrap_or(metadata.author));
+			let gas_limit = match self.gas_limit {
+				Some(gas_limit) if metadata.gas_limit.is_zero() => gas_limit,
+				_ => metadata.gas_limit,
+			};
+			let gas_used = match self.gas_used_excess {
+				Some(excess) => gas_limit + excess,
+				None => {
+					debug_assert!(metadata.gas_used <= gas_limit, "Blocks must not use more gas than their gas limit, use `with_gas_used_over_limit` instead");
+					metadata.gas_used
+				},
+			};
+			block.header.set_gas_limit(gas_limit);
+			block.header.set_gas_used(gas_used);
+			block.header.set_uncles_hash(uncles_hash(&metadata.uncles));
+			let pow_seal = if self.minimal_header { Some(PowSealFormat::default()) } else { self.pow_seal };
+			match (self.prev_randao, pow_seal) {
+				(Some(prev_randao), _) => block.header.set_seal(vec![encode(&prev_randao).into_vec(), encode(&H64::zero()).into_vec()]),
+				(None, Some(format)) => block.header.set_seal(format.seal(&H256::from(block_number), block_number)),
+				(None, None) => {},
+			}
+			let mut bloom = metadata.bloom;
+			let receipts = match self.log_topics {
+				Some(ref topics) if metadata.receipts.is_empty() => {
+					let addresses = self.log_addresses.as_ref().map(|addresses| &addresses[..]);
+					let receipts = log_receipts(&metadata.transactions, topics, addresses, &mut next_log_address);
+					for receipt in &receipts {
+						bloom = bloom | receipt.log_bloom;
+					}
+					receipts
+				},
+				_ => metadata.receipts,
+			};
+			block.header.set_log_bloom(bloom);
+			if !receipts.is_empty() {
+				block.header.set_receipts_root(ordered_trie_root(receipts.iter().map(|r| encode(r))));
+			}
+			block.receipts = receipts;
+			block.rlp_quirk = self.rlp_quirk;
 			block.transactions = metadata.transactions;
+			block.uncles = metadata.uncles;
+			if let Some(target) = self.body_size {
+				pad_body(&mut block.transactions, &block.uncles, target);
+			}
+			if !block.transactions.is_empty() {
+				block.header.set_transactions_root(ordered_trie_root(block.transactions.iter().map(|t| encode(t))));
+			}
+			if let Some(ref seal) = self.authority_seal {
+				let fields = seal.seal(&block.header);
+				block.header.set_seal(fields);
+			}
 
+			parent = block.header();
 			parent_hash = block.hash();
 			parent_number = block_number;
+			total_difficulty = total_difficulty + difficulty;
+
+			ancestry.push_back(parent_hash);
+			if ancestry.len() > MAX_UNCLE_AGE + 1 {
+				ancestry.pop_front();
+			}
 
 			blocks.push_back(block);
 		}
 
 		BlockBuilder {
 			blocks,
+			ancestry,
+			next_log_address,
+			total_difficulty,
+			..self.clone()
+		}
+	}
+
+	/// Generate `count` blocks, passing each to `finalizer` before encoding it, and return every encoded
+	/// block paired with its header.
+	///
+	/// Each block is generated on top of its finalized parent. Headers are the ones the blocks were
+	/// encoded from, so they aren't decoded back. With an `RlpQuirk` the block hash is the hash of the
+	/// quirked header bytes, which differs from `Header::hash` of the returned header.
+	pub fn generate_with_headers<F>(&self, count: usize, mut finalizer: F) -> Vec<(Bytes, Header)> where F: FnMut(&mut Block) {
+		let mut builder = self.clone();
+		let mut blocks = Vec::with_capacity(count);
+		for _ in 0..count {
+			builder = builder.add_block();
+			let (rlp, header) = {
+				let block = builder.blocks.back_mut().expect("There is always at least 1 block");
+				finalizer(block);
+				(block.encoded().into_inner(), block.header())
+			};
+			let hash = match builder.last().rlp_quirk {
+				Some(_) => keccak(Rlp::new(&rlp).at(0).expect("Generated blocks are valid RLP; qed").as_raw()),
+				None => header.hash(),
+			};
+			*builder.ancestry.back_mut().expect("Ancestry ends with the last block") = hash;
+			blocks.push((rlp, header));
+		}
+		blocks
+	}
+
+	/// Add blocks until one of them hashes to `target`, generating at most `limit` blocks.
+	///
+	/// Generation is deterministic, so this reproduces a segment of a chain built earlier from the same parent
+	/// and the same options. Returns an error if no block within the limit matches.
+	pub fn add_blocks_until_hash<T>(&self, target: H256, limit: usize, get_metadata: T) -> Result<Self, String>
+		where T: Fn() -> BlockOptions {
+		let mut builder = self.clone();
+		let mut blocks = VecDeque::new();
+		for _ in 0..limit {
+			builder = builder.add_block_with(&get_metadata);
+			blocks.push_back(builder.last().clone());
+			if builder.last().hash() == target {
+				return Ok(BlockBuilder { blocks, ..builder });
+			}
+		}
+
+		Err(format!("Block {} not found within {} blocks", target, limit))
+	}
+
+	/// Build a canonical chain of `canonical_len` blocks on top of the last block, plus a fork for
+	/// each `(fork_at, len)` branching off the `fork_at`-th canonical block with `len` blocks of its own.
+	///
+	/// A `fork_at` of 0 branches off the last block of this builder. Fork blocks are lighter than
+	/// canonical ones and every fork must stay lighter than the canonical chain in total.
+	/// Returns the encoded canonical blocks and the encoded blocks of each fork.
+	pub fn canonical_with_forks(&self, canonical_len: usize, forks: Vec<(usize, usize)>) -> (Vec<Bytes>, Vec<Vec<Bytes>>) {
+		const CANONICAL_DIFFICULTY: u64 = 10;
+		const FORK_DIFFICULTY: u64 = 9;
+
+		let canonical = self.add_blocks_with(canonical_len, || BlockOptions {
+			difficulty: CANONICAL_DIFFICULTY.into(),
+			..Default::default()
+		});
+		let forks = forks.into_iter().enumerate().map(|(i, (fork_at, len))| {
+			assert!(fork_at < canonical_len, "Fork point {} is not within the canonical chain of {} blocks", fork_at, canonical_len);
+			assert!(len as u64 * FORK_DIFFICULTY < (canonical_len - fork_at) as u64 * CANONICAL_DIFFICULTY,
+				"Fork of {} blocks at {} is not lighter than the canonical chain", len, fork_at);
+
+			let mut parent = self.clone();
+			for block in canonical.blocks.iter().take(fork_at) {
+				parent.ancestry.push_back(block.hash());
+				if parent.ancestry.len() > MAX_UNCLE_AGE + 1 {
+					parent.ancestry.pop_front();
+				}
+				parent.blocks.push_back(block.clone());
+			}
+
+			// distinct authors keep forks at the same point apart.
+			parent.with_author(Address::from(i as u64 + 1)).add_blocks_with(len, || BlockOptions {
+				difficulty: FORK_DIFFICULTY.into(),
+				..Default::default()
+			}).blocks.iter().map(|block| block.encoded().into_inner()).collect()
+		}).collect();
+
+		let canonical = canonical.blocks.iter().map(|block| block.encoded().into_inner()).collect();
+		(canonical, forks)
+	}
+
+	/// Checks whether `uncle` could be included by the next block added to this builder.
+	fn is_valid_uncle(&self, uncle: &Header) -> bool {
+		let parent = self.last().hash();
+		uncle.parent_hash() != &parent &&
+			self.ancestry.contains(uncle.parent_hash()) &&
+			!self.ancestry.contains(&uncle.hash())
+	}
+
+	/// Generate blocks on top of the last block stamped with `difficulties` in order, ending once
+	/// they are exhausted unless cycled, see `DifficultySequence::cycling`.
+	///
+	/// The difficulties override those of the block time and of `BlockOptions`.
+	pub fn with_difficulty_sequence(&self, difficulties: Vec<U256>) -> DifficultySequence {
+		assert!(!difficulties.is_empty(), "Difficulty sequence must not be empty");
+		DifficultySequence {
+			builder: self.clone(),
+			difficulties,
+			next: 0,
+			cycle: false,
+			total_difficulty: U256::zero(),
+		}
+	}
+
+	/// Generate blocks on top of the last block alternating between empty blocks and blocks with
+	/// `full_tx_count` transactions, starting with an empty one.
+	///
+	/// Transactions are synthesized unless drawn from a pool, see `AlternatingFill::with_pool`.
+	pub fn alternating_fill(&self, full_tx_count: usize) -> AlternatingFill {
+		assert!(full_tx_count > 0, "Full blocks must carry at least 1 transaction");
+		AlternatingFill {
+			builder: self.clone(),
+			full_tx_count,
+			next_full: false,
+			pool: None,
+			drawn: 0,
+		}
+	}
+
+	/// Generate blocks on top of the last block, injecting a competing branch of `depth` blocks every
+	/// `interval` blocks which is heavier than the `depth` blocks it competes with.
+	///
+	/// Blocks come in import order: each branch follows the block completing an interval and the chain
+	/// carries on from the branch, so importing them reorgs once per interval. Only the last block of a
+	/// branch tips the scales, see `PeriodicReorg::head` for the expected head.
+	pub fn with_periodic_reorg(&self, interval: usize, depth: usize) -> PeriodicReorg {
+		assert!(depth > 0 && depth <= interval, "Reorg depth must be between 1 and the interval");
+		assert!(self.block_time.is_none() && self.prev_randao.is_none(), "Periodic reorgs require difficulties of the generator's own");
+		let mut chain = VecDeque::with_capacity(depth + 1);
+		chain.push_back(self.clone());
+		PeriodicReorg {
+			chain,
+			interval,
+			depth,
+			pending: VecDeque::with_capacity(depth),
+			generated: 0,
+			reorgs: 0,
+			head: self.last().hash(),
 		}
 	}
 
@@ -177,6 +1732,19 @@ impl BlockGenerator {
 			builders: builders.into_iter().collect(),
 		}
 	}
+
+	/// Withhold every `k`-th generated block, leaving gaps in the emitted chain.
+	///
+	/// Blocks following a gap still reference the withheld block as their parent, as an importer
+	/// receiving them out of a real chain would have to backfill it.
+	pub fn drop_every(self, k: usize) -> DropEvery {
+		assert!(k > 1, "Dropping every block leaves nothing to import");
+		DropEvery {
+			generator: self,
+			k,
+			generated: 0,
+		}
+	}
 }
 
 impl Iterator for BlockGenerator {
@@ -198,9 +1766,314 @@ impl Iterator for BlockGenerator {
 	}
 }
 
+/// Stateful handle generating a chain one block at a time, for tests interleaving generation with assertions.
+///
+/// Blocks are the same as those of `BlockBuilder::add_blocks_with` for the same builder and options.
+pub struct ChainHandle<F> {
+	builder: BlockBuilder,
+	get_metadata: F,
+}
+
+impl<F> ChainHandle<F> where F: Fn() -> BlockOptions {
+	/// Generate blocks on top of the last block of `builder`, using the options returned by `get_metadata`.
+	pub fn new(builder: BlockBuilder, get_metadata: F) -> Self {
+		ChainHandle {
+			builder,
+			get_metadata,
+		}
+	}
+
+	/// Generate the next block, returning it encoded.
+	pub fn next_block(&mut self) -> Bytes {
+		self.builder = self.builder.add_blocks_with(1, &self.get_metadata);
+		self.builder.last().encoded().into_inner()
+	}
+
+	/// Number of the last generated block.
+	pub fn height(&self) -> u64 {
+		self.builder.last().number()
+	}
+
+	/// Hash of the last generated block.
+	pub fn head_hash(&self) -> H256 {
+		self.builder.last().hash()
+	}
+}
+
+impl<F> Iterator for ChainHandle<F> where F: Fn() -> BlockOptions {
+	type Item = Bytes;
+
+	/// Generate the next block, or `None` once the last representable block number is reached.
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.height() == BlockNumber::max_value() {
+			return None;
+		}
+
+		Some(self.next_block())
+	}
+}
+
+/// Generator adapter withholding every `k`-th block.
+pub struct DropEvery {
+	generator: BlockGenerator,
+	k: usize,
+	generated: usize,
+}
+
+impl Iterator for DropEvery {
+	type Item = Block;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let block = match self.generator.next() {
+				Some(block) => block,
+				None => return None,
+			};
+			self.generated += 1;
+			if self.generated % self.k != 0 {
+				return Some(block);
+			}
+		}
+	}
+}
+
+/// Generator stamping blocks with given difficulties, see `BlockBuilder::with_difficulty_sequence`.
+pub struct DifficultySequence {
+	builder: BlockBuilder,
+	difficulties: Vec<U256>,
+	next: usize,
+	cycle: bool,
+	total_difficulty: U256,
+}
+
+impl DifficultySequence {
+	/// Start over with the first difficulty once the sequence is exhausted, generating blocks indefinitely.
+	pub fn cycling(mut self) -> Self {
+		self.cycle = true;
+		self
+	}
+
+	/// Sum of the difficulties of the blocks generated so far.
+	pub fn total_difficulty(&self) -> U256 {
+		self.total_difficulty
+	}
+}
+
+impl Iterator for DifficultySequence {
+	type Item = Block;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.next == self.difficulties.len() {
+			if !self.cycle {
+				return None;
+			}
+			self.next = 0;
+		}
+
+		let difficulty = self.difficulties[self.next];
+		self.next += 1;
+		self.total_difficulty = self.total_difficulty + difficulty;
+
+		// restamp, as a block time derives difficulty from timestamps.
+		let mut builder = self.builder.add_block_with_difficulty(difficulty);
+		builder.blocks.back_mut().expect("There is always at least 1 block").header.set_difficulty(difficulty);
+		let hash = builder.last().hash();
+		*builder.ancestry.back_mut().expect("Ancestry ends with the last block") = hash;
+		builder.total_difficulty = self.builder.total_difficulty + difficulty;
+		self.builder = builder;
+		Some(self.builder.last().clone())
+	}
+}
+
+/// Generator of a chain reorganising at fixed intervals, see `BlockBuilder::with_periodic_reorg`.
+pub struct PeriodicReorg {
+	// builders of the last `depth + 1` blocks of the heaviest chain.
+	chain: VecDeque<BlockBuilder>,
+	interval: usize,
+	depth: usize,
+	// blocks of the branch being emitted.
+	pending: VecDeque<Block>,
+	// blocks generated on top of the heaviest chain, excluding branches.
+	generated: usize,
+	reorgs: usize,
+	head: H256,
+}
+
+impl PeriodicReorg {
+	/// Generate the blocks of the next `count` heights, along with the branches injected meanwhile.
+	pub fn blocks(&mut self, count: usize) -> Vec<Block> {
+		let target = self.generated + count;
+		let mut blocks = Vec::new();
+		while self.generated < target || !self.pending.is_empty() {
+			blocks.push(self.next().expect("Periodic reorgs are generated indefinitely"));
+		}
+		blocks
+	}
+
+	/// Hash of the head an importer ends up with once all blocks generated so far are imported.
+	pub fn head(&self) -> H256 {
+		self.head
+	}
+
+	/// Number of reorgs triggered by the blocks generated so far.
+	pub fn reorgs(&self) -> usize {
+		self.reorgs
+	}
+
+	// replace the last `depth` blocks with a heavier branch, queueing its blocks.
+	fn inject_branch(&mut self) {
+		self.chain.truncate(1);
+		// distinct authors keep the branch apart from the blocks it competes with.
+		let author = Address::from(self.reorgs as u64 + 1);
+		for i in 0..self.depth {
+			let difficulty = if i + 1 == self.depth { REORG_DIFFICULTY + 1 } else { REORG_DIFFICULTY };
+			let builder = self.chain.back().expect("Chain keeps the fork point").with_author(author).add_block_with_difficulty(difficulty);
+			self.pending.push_back(builder.last().clone());
+			self.chain.push_back(builder);
+		}
+		self.reorgs += 1;
+	}
+}
+
+impl Iterator for PeriodicReorg {
+	type Item = Block;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(block) = self.pending.pop_front() {
+			if self.pending.is_empty() {
+				self.head = block.hash();
+			}
+			return Some(block);
+		}
+
+		let builder = self.chain.back().expect("Chain is never empty").add_block_with_difficulty(REORG_DIFFICULTY);
+		let block = builder.last().clone();
+		self.chain.push_back(builder);
+		if self.chain.len() > self.depth + 1 {
+			self.chain.pop_front();
+		}
+		self.head = block.hash();
+		self.generated += 1;
+		if self.generated % self.interval == 0 {
+			self.inject_branch();
+		}
+		Some(block)
+	}
+}
+
+/// Generator alternating between empty and full blocks, see `BlockBuilder::alternating_fill`.
+pub struct AlternatingFill {
+	builder: BlockBuilder,
+	full_tx_count: usize,
+	next_full: bool,
+	pool: Option<VecDeque<SignedTransaction>>,
+	drawn: usize,
+}
+
+impl AlternatingFill {
+	/// Start with a full block rather than an empty one.
+	pub fn starting_full(mut self) -> Self {
+		self.next_full = true;
+		self
+	}
+
+	/// Fill full blocks with transactions taken in order from `pool` instead of synthesizing them.
+	///
+	/// Generation ends once the pool can't fill another full block.
+	pub fn with_pool<T>(mut self, pool: T) -> Self where T: IntoIterator<Item = SignedTransaction> {
+		self.pool = Some(pool.into_iter().collect());
+		self
+	}
+
+	// transactions of the next full block, or `None` if the pool is exhausted.
+	fn draw(&mut self) -> Option<Vec<SignedTransaction>> {
+		let count = self.full_tx_count;
+		let transactions = match self.pool {
+			Some(ref mut pool) => {
+				if pool.len() < count {
+					return None;
+				}
+				pool.drain(..count).collect()
+			},
+			None => {
+				let signer = FixedKeySigner::default();
+				(self.drawn..self.drawn + count).map(|nonce| signer.sign(Transaction {
+					nonce: nonce.into(),
+					gas_price: U256::one(),
+					gas: 21_000.into(),
+					action: Action::Call(Address::from(nonce as u64 + 1)),
+					value: U256::one(),
+					data: Vec::new(),
+				})).collect()
+			},
+		};
+		self.drawn += count;
+		Some(transactions)
+	}
+}
+
+impl Iterator for AlternatingFill {
+	type Item = Block;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.builder = if self.next_full {
+			let transactions = self.draw()?;
+			self.builder.add_block_with_transactions(transactions)
+		} else {
+			self.builder.add_block()
+		};
+		self.next_full = !self.next_full;
+		Some(self.builder.last().clone())
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{BlockBuilder, BlockOptions, BlockGenerator};
+	use std::cmp;
+	use bytes::Bytes;
+	use ethereum_types::{Address, U256};
+	use hash::keccak;
+	use header::Header;
+	use rlp::{self, RlpStream};
+	use views::BlockView;
+	use ethereum_types::H256;
+	use receipt::{Receipt, TransactionOutcome};
+	use transaction::{Action, SignedTransaction, Transaction};
+	use super::{
+		Block, BlockBuilder, BlockOptions, BlockGenerator, ChainHandle, TxSigner, FixedKeySigner, ReceiptFormat, NetworkPreset,
+		ParentPolicy, RewardSchedule, reward_ledger,
+		EMPTY_TRIE_ROOT, EMPTY_LIST_HASH, BODY_SIZE_TOLERANCE, split_block, join_block, export_sharded, import_sharded,
+		ForkChoiceRule, canonical_head, RlpQuirk, SelfUncle, PowSealFormat, NonceOrder, AuthoritySeal, TxOrdering,
+		tx_inclusion_proof, GasDistribution, block_hash, export_import_format, read_import_format,
+	};
+
+	// call to `nonce + 1` sending 1 wei with 21000 gas at gas price 1.
+	fn call(nonce: u64) -> Transaction {
+		Transaction {
+			nonce: nonce.into(),
+			gas_price: U256::one(),
+			gas: 21_000.into(),
+			action: Action::Call(Address::from(nonce + 1)),
+			value: U256::one(),
+			data: Vec::new(),
+		}
+	}
+
+	// first `count` calls, signed by the `FixedKeySigner`.
+	fn signed_calls(count: u64) -> Vec<SignedTransaction> {
+		let signer = FixedKeySigner::default();
+		(0..count).map(|nonce| signer.sign(call(nonce))).collect()
+	}
+
+	// first `count` calls, all sent to `address`.
+	fn calls_to(address: Address, count: u64) -> Vec<Transaction> {
+		(0..count).map(|nonce| Transaction { action: Action::Call(address), ..call(nonce) }).collect()
+	}
+
+	// rlp of the blocks of `builder`.
+	fn encoded_blocks(builder: BlockBuilder) -> Vec<Bytes> {
+		BlockGenerator::new(vec![builder]).map(|block| block.encoded().into_inner()).collect()
+	}
 
 	#[test]
 	fn test_block_builder() {
@@ -220,4 +2093,1054 @@ mod tests {
 		assert_eq!(block_10a.last().number(), 10);
 		assert_eq!(block_11b.last().number(), 11);
 	}
+
+	#[test]
+	fn test_block_builder_zero_difficulty() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.with_zero_difficulty().add_blocks(10);
+		let generator = BlockGenerator::new(vec![blocks]);
+
+		let mut total_difficulty = genesis.last().difficulty();
+		for block in generator {
+			assert!(block.difficulty().is_zero());
+			let next_total_difficulty = total_difficulty + block.difficulty();
+			assert_eq!(next_total_difficulty, total_difficulty);
+			total_difficulty = next_total_difficulty;
+		}
+	}
+
+	#[test]
+	fn test_block_builder_zero_difficulty_with_target_block_time() {
+		let genesis = BlockBuilder::genesis()
+			.add_block_with_difficulty(1_000_000)
+			.with_target_block_time(15);
+		let blocks = genesis.with_zero_difficulty().add_blocks(10);
+		assert_eq!(blocks.total_difficulty(), genesis.total_difficulty());
+
+		let mut parent = genesis.last().header();
+		for block in BlockGenerator::new(vec![blocks]) {
+			assert!(block.difficulty().is_zero());
+			assert_eq!(block.header.timestamp() - parent.timestamp(), 15);
+			parent = block.header();
+		}
+	}
+
+	#[test]
+	fn test_block_builder_td_anomaly() {
+		let genesis = BlockBuilder::genesis().add_blocks(3);
+		let blocks = genesis.with_td_anomaly(4).add_blocks(10);
+		let generator = BlockGenerator::new(vec![blocks]);
+
+		let mut total_difficulty = genesis.last().difficulty();
+		for (index, block) in generator.enumerate() {
+			let next_total_difficulty = total_difficulty + block.difficulty();
+			if index == 4 {
+				assert_eq!(block.number(), 8);
+				assert_eq!(next_total_difficulty, total_difficulty);
+			} else {
+				assert!(next_total_difficulty > total_difficulty);
+			}
+			total_difficulty = next_total_difficulty;
+		}
+	}
+
+	#[test]
+	fn test_block_builder_fork_author() {
+		let (miner, fork_miner) = (Address::from(0xc0), Address::from(0xf0));
+		let genesis = BlockBuilder::genesis();
+		let canonical = genesis.with_author(miner).add_blocks(5);
+		let fork = genesis.with_author(fork_miner).add_blocks(5);
+
+		let canonical_blocks = BlockGenerator::new(vec![canonical.clone()]).collect::<Vec<_>>();
+		assert_eq!(canonical_blocks.len(), 5);
+		for block in canonical_blocks {
+			let encoded = block.encoded();
+			assert_eq!(view!(BlockView, encoded.raw()).header().author(), &miner);
+		}
+
+		let fork_blocks = BlockGenerator::new(vec![fork.clone()]).collect::<Vec<_>>();
+		assert_eq!(fork_blocks.len(), 5);
+		for block in fork_blocks {
+			let encoded = block.encoded();
+			assert_eq!(view!(BlockView, encoded.raw()).header().author(), &fork_miner);
+		}
+
+		// the author sticks to the fork as it grows, options can't override it.
+		let extended = fork.add_block_with(|| BlockOptions {
+			author: miner,
+			..Default::default()
+		});
+		assert_eq!(extended.last().header.author(), &fork_miner);
+		assert_eq!(genesis.add_block().last().header.author(), &Address::default());
+	}
+
+	#[test]
+	fn test_block_builder_canonical_with_forks() {
+		let genesis = BlockBuilder::genesis();
+		let (canonical, forks) = genesis.canonical_with_forks(10, vec![(0, 3), (4, 5)]);
+		assert_eq!(canonical.len(), 10);
+		assert_eq!(forks.len(), 2);
+
+		let canonical = canonical.iter().map(|b| view!(BlockView, b).header()).collect::<Vec<_>>();
+		let canonical_difficulty = canonical.iter().fold(U256::zero(), |acc, h| acc + *h.difficulty());
+		for (fork, &(fork_at, len)) in forks.iter().zip(&[(0usize, 3usize), (4, 5)]) {
+			let fork = fork.iter().map(|b| view!(BlockView, b).header()).collect::<Vec<_>>();
+			assert_eq!(fork.len(), len);
+
+			let ancestor = if fork_at == 0 { genesis.last().hash() } else { canonical[fork_at - 1].hash() };
+			assert_eq!(fork[0].parent_hash(), &ancestor);
+			assert_eq!(fork[0].number(), fork_at as u64 + 1);
+			assert_ne!(fork[0].hash(), canonical[fork_at].hash());
+
+			let fork_difficulty = canonical[..fork_at].iter().chain(&fork).fold(U256::zero(), |acc, h| acc + *h.difficulty());
+			assert!(fork_difficulty < canonical_difficulty);
+		}
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_block_builder_rejects_fork_past_canonical() {
+		BlockBuilder::genesis().canonical_with_forks(5, vec![(5, 1)]);
+	}
+
+	#[test]
+	fn test_block_builder_uncles_from_fork() {
+		let author = Address::from(0x42);
+		let genesis = BlockBuilder::genesis();
+		let block_2 = genesis.add_blocks(2);
+		let fork = genesis.add_block_with(|| BlockOptions {
+			difficulty: 9.into(),
+			author,
+			..Default::default()
+		});
+		let block_3 = block_2.add_blocks_with_uncles_from(1, &fork, 1);
+
+		let block = block_3.last();
+		let uncles = view!(BlockView, &block.encoded().raw()).uncles();
+		assert_eq!(uncles.len(), 1);
+		assert_eq!(uncles[0].author(), &author);
+		assert_eq!(uncles[0].hash(), fork.last().hash());
+		assert_ne!(block.header.author(), &author);
+	}
+
+	#[test]
+	fn test_split_and_join_block() {
+		let genesis = BlockBuilder::genesis();
+		let fork = genesis.add_block_with_difficulty(9);
+		let transactions = (0..2).map(call).collect::<Vec<_>>();
+		let block_2 = genesis.add_block().add_block_with_unsigned_transactions(transactions);
+		let block_3 = block_2.add_blocks_with_uncles_from(1, &fork, 1);
+
+		for block in &[block_2.last(), block_3.last()] {
+			let raw = block.encoded().into_inner();
+			let (header, body) = split_block(&raw);
+
+			assert_eq!(header, rlp::encode(&block.header).into_vec());
+			let body_view = rlp::Rlp::new(&body);
+			assert_eq!(body_view.at(0).unwrap().item_count().unwrap(), block.transactions.len());
+			assert_eq!(body_view.at(1).unwrap().item_count().unwrap(), block.uncles.len());
+
+			assert_eq!(join_block(&header, &body), raw);
+		}
+	}
+
+	#[test]
+	fn test_block_builder_target_body_size() {
+		const TARGET: usize = 100 * 1024;
+
+		let genesis = BlockBuilder::genesis();
+		let fork = genesis.add_block_with_difficulty(9);
+		let padded = genesis.add_block().with_target_body_size(TARGET).add_blocks(3);
+		let padded = padded.add_blocks_with_uncles_from(1, &fork, 1);
+
+		for block in BlockGenerator::new(vec![padded]) {
+			let raw = block.encoded().into_inner();
+			let body = split_block(&raw).1;
+			assert_eq!(block.body_size(), body.len());
+			assert!(body.len() <= TARGET);
+			assert!(body.len() + BODY_SIZE_TOLERANCE >= TARGET);
+			assert!(block.transactions.iter().all(|tx| tx.gas >= (21_000 + 4 * tx.data.len()).into()));
+		}
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_block_builder_rejects_stale_uncles() {
+		let genesis = BlockBuilder::genesis();
+		let fork = genesis.add_block_with(|| BlockOptions {
+			difficulty: 9.into(),
+			..Default::default()
+		});
+		let canonical = genesis.add_blocks(10);
+		canonical.add_blocks_with_uncles_from(1, &fork, 1);
+	}
+
+	#[test]
+	fn test_block_builder_until_hash() {
+		let genesis = BlockBuilder::genesis();
+		let target = genesis.add_blocks(5).blocks[2].hash();
+
+		let blocks = genesis.add_blocks_until_hash(target, 10, || BlockOptions::default()).unwrap();
+		assert_eq!(blocks.blocks.len(), 3);
+		assert_eq!(blocks.last().number(), 3);
+		assert_eq!(blocks.last().hash(), target);
+
+		let extended = blocks.add_blocks(2);
+		assert!(extended.add_blocks_until_hash(target, 10, || BlockOptions::default()).is_err());
+		assert!(genesis.add_blocks_until_hash(target, 2, || BlockOptions::default()).is_err());
+	}
+
+	#[test]
+	fn test_block_builder_empty_roots() {
+		assert_eq!(EMPTY_TRIE_ROOT, keccak(rlp::NULL_RLP));
+		assert_eq!(EMPTY_LIST_HASH, keccak(RlpStream::new_list(0).out()));
+
+		let block = BlockBuilder::genesis().add_block();
+		let header = block.last().header();
+		assert_eq!(header.transactions_root(), &EMPTY_TRIE_ROOT);
+		assert_eq!(header.receipts_root(), &EMPTY_TRIE_ROOT);
+		assert_eq!(header.uncles_hash(), &EMPTY_LIST_HASH);
+	}
+
+	#[test]
+	fn test_block_builder_gas_used_ratio() {
+		let gas_limit = U256::from(8_000_000);
+		let gas_target = gas_limit / 2;
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.add_blocks_with_gas_used_ratio(gas_limit, vec![1.0, 0.0, 0.5, 2.0, -1.0]);
+
+		let gas_used = BlockGenerator::new(vec![blocks])
+			.map(|block| {
+				assert_eq!(block.header.gas_limit(), &gas_limit);
+				*block.header.gas_used()
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(gas_used, vec![gas_limit, U256::zero(), gas_target, gas_limit, U256::zero()]);
+		assert!(gas_used[0] > gas_target);
+		assert!(gas_used[1] < gas_target);
+	}
+
+	// base fee of the child of `parent` following EIP-1559, with the gas target at half the gas limit.
+	fn next_base_fee(parent: &Header, parent_base_fee: U256) -> U256 {
+		let gas_target = *parent.gas_limit() / 2;
+		let gas_used = *parent.gas_used();
+		if gas_used > gas_target {
+			parent_base_fee + cmp::max(parent_base_fee * (gas_used - gas_target) / gas_target / 8, U256::one())
+		} else {
+			parent_base_fee - parent_base_fee * (gas_target - gas_used) / gas_target / 8
+		}
+	}
+
+	#[test]
+	fn test_block_builder_gas_used_ratio_base_fee() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.add_blocks_with_gas_used_ratio(8_000_000.into(), vec![1.0, 0.0, 0.5]);
+
+		let mut base_fee = U256::from(1_000_000_000);
+		let base_fees = BlockGenerator::new(vec![blocks])
+			.map(|block| {
+				base_fee = next_base_fee(&block.header, base_fee);
+				base_fee
+			})
+			.collect::<Vec<_>>();
+		// a full block raises the base fee by 1/8, an empty one lowers it by 1/8, a half full one keeps it.
+		assert_eq!(base_fees, vec![U256::from(1_125_000_000), U256::from(984_375_000), U256::from(984_375_000)]);
+	}
+
+	#[test]
+	fn test_block_builder_over_limit_uncles() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.add_block().add_block_with_over_limit_uncles(3);
+
+		let encoded = blocks.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+		assert_eq!(view.uncles().len(), 3);
+		assert_eq!(view.header().uncles_hash(), &keccak(view.rlp().at(2).as_raw()));
+		assert_ne!(view.header().uncles_hash(), &EMPTY_LIST_HASH);
+	}
+
+	#[test]
+	fn test_block_builder_self_uncle() {
+		let parent = BlockBuilder::genesis().add_block();
+
+		let blocks = parent.add_block_with_self_uncle(SelfUncle::Itself);
+		let encoded = blocks.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+		let uncles = view.uncles();
+		assert_eq!(uncles.len(), 1);
+		assert_eq!(uncles[0].number(), view.header().number());
+		assert_eq!(uncles[0].parent_hash(), view.header().parent_hash());
+		assert_eq!(view.header().uncles_hash(), &keccak(view.rlp().at(2).as_raw()));
+		assert_eq!(blocks.add_block().last().header.parent_hash(), &blocks.last().hash());
+
+		let blocks = parent.add_block_with_self_uncle(SelfUncle::Parent);
+		let encoded = blocks.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+		let uncles = view.uncles();
+		assert_eq!(uncles.len(), 1);
+		assert_eq!(uncles[0].number() + 1, view.header().number());
+		assert_eq!(uncles[0].hash(), parent.last().hash());
+		assert_eq!(view.header().uncles_hash(), &keccak(view.rlp().at(2).as_raw()));
+	}
+
+	#[test]
+	fn test_block_builder_broken_uncles_hash() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.add_block().add_block_with_broken_uncles_hash(EMPTY_LIST_HASH);
+
+		let encoded = blocks.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+		assert_eq!(view.uncles().len(), 1);
+		assert_eq!(view.header().uncles_hash(), &EMPTY_LIST_HASH);
+		assert_ne!(view.header().uncles_hash(), &keccak(view.rlp().at(2).as_raw()));
+
+		// valid uncles can still be added on top.
+		let fork = genesis.add_block().with_author(Address::from(0xf0)).add_block();
+		let valid = blocks.add_blocks_with_uncles_from(1, &fork, 1);
+		let encoded = valid.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+		assert_eq!(view.header().uncles_hash(), &keccak(view.rlp().at(2).as_raw()));
+	}
+
+	#[test]
+	fn test_block_builder_uncles_hash_is_stable() {
+		fn build() -> Vec<H256> {
+			let genesis = BlockBuilder::genesis().with_target_block_time(15).with_block_time_jitter(3);
+			let fork_a = genesis.with_author(Address::from(0xf0)).add_block();
+			let fork_b = genesis.with_author(Address::from(0xf1)).add_block();
+			let block_2 = genesis.add_blocks(2);
+			let block_3 = block_2.add_blocks_with_uncles_from(1, &fork_a, 1);
+			let block_4 = block_3.add_blocks_with_uncles_from(1, &fork_b, 1);
+			let block_5 = block_4.add_block_with_over_limit_uncles(3);
+			BlockGenerator::new(vec![block_2, block_3, block_4, block_5])
+				.map(|block| *block.header.uncles_hash())
+				.collect()
+		}
+
+		let first = build();
+		assert_eq!(first.len(), 5);
+		assert!(first[2..].iter().all(|hash| hash != &EMPTY_LIST_HASH));
+		assert_eq!(first, build());
+	}
+
+	#[test]
+	fn test_block_builder_target_block_time() {
+		let start_difficulty = U256::from(1_000_000);
+		let genesis = BlockBuilder::genesis()
+			.add_block_with_difficulty(start_difficulty)
+			.with_target_block_time(15)
+			.with_block_time_jitter(3);
+
+		let steady = genesis.add_blocks(20);
+		let mut parent = genesis.last().header();
+		for block in BlockGenerator::new(vec![steady]) {
+			let delta = block.header.timestamp() - parent.timestamp();
+			assert!(delta >= 12 && delta <= 18);
+			assert_eq!(block.difficulty(), start_difficulty);
+			parent = block.header();
+		}
+
+		let fast = genesis.add_blocks_with(10, || BlockOptions {
+			timestamp_delta: Some(5),
+			..Default::default()
+		});
+		let mut parent_difficulty = start_difficulty;
+		for block in BlockGenerator::new(vec![fast]) {
+			assert!(block.difficulty() > parent_difficulty);
+			parent_difficulty = block.difficulty();
+		}
+	}
+
+	#[test]
+	fn test_block_builder_foundation_preset() {
+		let foundation = BlockBuilder::preset(NetworkPreset::Foundation);
+		let genesis = foundation.last().header();
+		assert_eq!(genesis.difficulty(), &U256::from(17_179_869_184u64));
+		assert_eq!(genesis.gas_limit(), &U256::from(5000));
+
+		let mut parent = genesis;
+		for block in BlockGenerator::new(vec![foundation.add_blocks(5)]) {
+			assert_eq!(block.header.timestamp() - parent.timestamp(), 13);
+			assert_eq!(block.header.gas_limit(), &U256::from(5000));
+			parent = block.header();
+		}
+	}
+
+	#[test]
+	fn test_block_builder_gas_used_over_limit() {
+		let gas_limit = U256::from(8_000_000);
+		let excess = U256::from(21_000);
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis
+			.with_gas_used_over_limit(excess)
+			.add_blocks_with(2, move || BlockOptions {
+				gas_limit,
+				..Default::default()
+			});
+
+		for block in BlockGenerator::new(vec![blocks]) {
+			assert_eq!(block.header.gas_limit(), &gas_limit);
+			assert_eq!(*block.header.gas_used() - *block.header.gas_limit(), excess);
+		}
+	}
+
+	#[test]
+	fn test_reward_ledger_credits_beneficiary() {
+		let author = Address::from(0xa1);
+		let beneficiary = Address::from(0xb2);
+		let schedule = RewardSchedule::new(U256::from(5000));
+
+		let blocks = BlockBuilder::genesis()
+			.with_author(author)
+			.with_reward_beneficiary(beneficiary)
+			.add_blocks(3);
+
+		for block in BlockGenerator::new(vec![blocks.clone()]) {
+			assert_eq!(block.header.author(), &author);
+		}
+
+		let ledger = reward_ledger(&[&blocks], &schedule);
+		assert_eq!(ledger.get(&beneficiary), Some(&U256::from(15000)));
+		assert_eq!(ledger.get(&author), None);
+
+		// by default the author is credited.
+		let ledger = reward_ledger(&[&BlockBuilder::genesis().with_author(author).add_blocks(3)], &schedule);
+		assert_eq!(ledger.get(&author), Some(&U256::from(15000)));
+	}
+
+	#[test]
+	fn test_reward_ledger_follows_reward_tiers() {
+		let (before, after, uncle_author) = (Address::from(0xa1), Address::from(0xa2), Address::from(0xc1));
+		let schedule = RewardSchedule::new(U256::from(5000))
+			.with_tier(4, U256::from(3000))
+			.with_tier(8, U256::from(2000));
+		assert_eq!(schedule.base_reward_at(3), U256::from(5000));
+		assert_eq!(schedule.base_reward_at(4), U256::from(3000));
+		assert_eq!(schedule.base_reward_at(100), U256::from(2000));
+
+		// blocks 1 to 3 before the reduction, 4 and 5 after it, the latter including a sibling of block 1.
+		let genesis = BlockBuilder::genesis();
+		let fork = genesis.with_author(uncle_author).add_block();
+		let blocks_1_3 = genesis.with_author(before).add_blocks(3);
+		let block_4 = blocks_1_3.with_author(after).add_block();
+		let block_5 = block_4.add_blocks_with_uncles_from(1, &fork, 1);
+		assert_eq!(block_5.last().number(), 5);
+		assert_eq!(block_5.last().uncles[0].number(), 1);
+
+		let ledger = reward_ledger(&[&blocks_1_3, &block_4, &block_5], &schedule);
+		assert_eq!(ledger.len(), 3);
+		assert_eq!(ledger.get(&before), Some(&U256::from(3 * 5000)));
+		assert_eq!(ledger.get(&after), Some(&U256::from(3000 + (3000 + 3000 / 32))));
+		assert_eq!(ledger.get(&uncle_author), Some(&U256::from(3000 * (8 + 1 - 5) / 8)));
+
+		// a single segment only accounts for its own blocks.
+		let ledger = reward_ledger(&[&block_5], &schedule);
+		assert_eq!(ledger.get(&before), None);
+		assert_eq!(ledger.get(&after), Some(&U256::from(3000 + 3000 / 32)));
+	}
+
+	#[test]
+	fn test_block_builder_parent_policy_always_genesis() {
+		let genesis = BlockBuilder::genesis();
+		let genesis_hash = genesis.last().hash();
+		let blocks = genesis.with_parent_policy(ParentPolicy::AlwaysGenesis).add_blocks(5);
+
+		let mut number = 0;
+		for block in BlockGenerator::new(vec![blocks]) {
+			number += 1;
+			assert_eq!(block.number(), number);
+			assert_eq!(block.header.parent_hash(), &genesis_hash);
+		}
+		assert_eq!(number, 5);
+	}
+
+	#[test]
+	fn test_block_builder_parent_policy_skip_one() {
+		let blocks = BlockBuilder::genesis().with_parent_policy(ParentPolicy::SkipOne).add_blocks(4);
+		let hashes = blocks.blocks.iter().map(|block| block.hash()).collect::<Vec<_>>();
+
+		assert_eq!(blocks.blocks[0].header.parent_hash(), &H256::zero());
+		for i in 2..4 {
+			assert_eq!(blocks.blocks[i].header.parent_hash(), &hashes[i - 2]);
+		}
+	}
+
+	#[test]
+	fn test_chain_handle_stops_at_max_block_number() {
+		let mut handle = ChainHandle::new(BlockBuilder::starting_at_max(1), BlockOptions::default);
+		assert_eq!(handle.height(), u64::max_value() - 1);
+
+		let block = handle.next().unwrap();
+		assert_eq!(view!(BlockView, &block).header_view().number(), u64::max_value());
+		assert_eq!(handle.height(), u64::max_value());
+
+		assert!(handle.next().is_none());
+		assert_eq!(handle.height(), u64::max_value());
+	}
+
+	#[test]
+	fn test_chain_handle_matches_generator() {
+		let genesis = BlockBuilder::genesis().with_target_block_time(15).with_block_time_jitter(3);
+		let options = || BlockOptions {
+			author: Address::from(0xf0),
+			..Default::default()
+		};
+
+		let expected = encoded_blocks(genesis.add_blocks_with(10, options));
+
+		let mut handle = ChainHandle::new(genesis.clone(), options);
+		assert_eq!(handle.height(), 0);
+		assert_eq!(handle.head_hash(), genesis.last().hash());
+
+		for (i, expected) in expected.into_iter().enumerate() {
+			let block = handle.next_block();
+			assert_eq!(block, expected);
+			assert_eq!(handle.height(), i as u64 + 1);
+			assert_eq!(handle.head_hash(), view!(BlockView, &block).header_view().hash());
+		}
+	}
+
+	#[test]
+	fn test_block_generator_drop_every() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.add_blocks(9);
+		let chain = BlockGenerator::new(vec![genesis.clone(), blocks.clone()]).collect::<Vec<_>>();
+
+		let emitted = BlockGenerator::new(vec![genesis, blocks]).drop_every(3).collect::<Vec<_>>();
+		let numbers = emitted.iter().map(|block| block.number()).collect::<Vec<_>>();
+		assert_eq!(numbers, vec![0, 1, 3, 4, 6, 7, 9]);
+
+		// blocks after a gap reference the withheld block
+		for block in &emitted[1..] {
+			let parent = &chain[block.number() as usize - 1];
+			assert_eq!(block.header.parent_hash(), &parent.hash());
+		}
+	}
+
+	#[test]
+	fn test_block_builder_number_jump() {
+		let genesis = BlockBuilder::genesis();
+		let block_3 = genesis.add_blocks(3);
+		let block_5 = block_3.add_block_with_number_jump(1);
+
+		assert_eq!(block_3.last().number(), 3);
+		assert_eq!(block_5.last().number(), 5);
+		assert_eq!(block_5.last().header.parent_hash(), &block_3.last().hash());
+		assert_eq!(block_5.add_block().last().header.parent_hash(), &block_5.last().hash());
+	}
+
+	#[test]
+	fn test_block_builder_custom_signer() {
+		struct StampSigner(Address);
+
+		impl TxSigner for StampSigner {
+			fn sign(&self, tx: Transaction) -> SignedTransaction {
+				tx.fake_sign(self.0)
+			}
+		}
+
+		let stamp = Address::from(0xdead);
+		let transactions = (0..3).map(call).collect::<Vec<_>>();
+
+		let genesis = BlockBuilder::genesis();
+		let stamped = genesis.add_block_with_transactions_signed_by(transactions.clone(), &StampSigner(stamp));
+		assert_eq!(stamped.last().transactions.len(), 3);
+		assert!(stamped.last().transactions.iter().all(|tx| tx.sender() == stamp && tx.is_unsigned()));
+
+		let signed = genesis.add_block_with_unsigned_transactions(transactions);
+		let sender = FixedKeySigner::default().sign(Transaction::default()).sender();
+		assert!(signed.last().transactions.iter().all(|tx| tx.sender() == sender && !tx.is_unsigned()));
+	}
+
+	#[test]
+	fn test_block_builder_receipt_formats() {
+		let pre_byzantium = ReceiptFormat::StateRoot(H256::from(1)).receipt(21_000.into(), vec![]);
+		let post_byzantium = ReceiptFormat::Status(true).receipt(21_000.into(), vec![]);
+		assert_eq!(pre_byzantium.outcome, TransactionOutcome::StateRoot(H256::from(1)));
+		assert_eq!(post_byzantium.outcome, TransactionOutcome::StatusCode(1));
+
+		for receipt in &[pre_byzantium.clone(), post_byzantium.clone()] {
+			let decoded: Receipt = rlp::decode(&rlp::encode(receipt)).expect("receipt round-trips; qed");
+			assert_eq!(&decoded, receipt);
+		}
+
+		let genesis = BlockBuilder::genesis();
+		let pre_root = *genesis.add_block_with_receipts(vec![pre_byzantium]).last().header.receipts_root();
+		let post_root = *genesis.add_block_with_receipts(vec![post_byzantium]).last().header.receipts_root();
+		assert!(pre_root != EMPTY_TRIE_ROOT);
+		assert!(post_root != EMPTY_TRIE_ROOT);
+		assert!(pre_root != post_root);
+	}
+
+	#[test]
+	fn test_import_format_round_trips() {
+		let blocks = encoded_blocks(BlockBuilder::genesis().add_blocks(20));
+
+		let mut file = Vec::new();
+		let written = export_import_format(&blocks, &mut file).unwrap();
+		assert_eq!(written, file.len() as u64);
+		assert_eq!(file, blocks.concat());
+		assert_eq!(file[0], 0xf9);
+
+		let read = read_import_format(&mut &file[..]).unwrap();
+		assert_eq!(read, blocks);
+		assert_eq!(read.concat(), file);
+
+		let mut truncated = &file[..file.len() - 1];
+		assert!(read_import_format(&mut truncated).is_err());
+		assert!(export_import_format(&[vec![0xc0]], &mut Vec::new()).is_err());
+	}
+
+	#[test]
+	fn test_sharded_export_round_trips() {
+		use std::fs;
+		use tempdir::TempDir;
+
+		let tempdir = TempDir::new("").unwrap();
+		let dir = tempdir.path().join("chain");
+		let blocks = encoded_blocks(BlockBuilder::genesis().add_blocks(250));
+
+		assert_eq!(export_sharded(&blocks, &dir, 100), Ok(3));
+		assert!(dir.join("chain-0002.rlp").exists());
+		assert!(!dir.join("chain-0003.rlp").exists());
+		assert_eq!(import_sharded(&dir), Ok(blocks));
+
+		// swapped files break the chain.
+		fs::rename(dir.join("chain-0000.rlp"), dir.join("tmp.rlp")).unwrap();
+		fs::rename(dir.join("chain-0001.rlp"), dir.join("chain-0000.rlp")).unwrap();
+		fs::rename(dir.join("tmp.rlp"), dir.join("chain-0001.rlp")).unwrap();
+		assert!(import_sharded(&dir).unwrap_err().contains("out of order"));
+
+		fs::remove_file(dir.join("chain-0001.rlp")).unwrap();
+		assert!(import_sharded(&dir).unwrap_err().contains("Missing chain file chain-0001.rlp"));
+	}
+
+	#[test]
+	fn test_block_builder_decreasing_timestamps() {
+		let genesis = BlockBuilder::genesis().with_target_block_time(15).add_blocks(3);
+		let blocks = genesis.with_decreasing_timestamps(10).add_blocks(5);
+		let timestamps = BlockGenerator::new(vec![genesis.clone(), blocks])
+			.map(|block| block.header.timestamp())
+			.collect::<Vec<_>>();
+
+		assert!(timestamps[3] < timestamps[2]);
+		for pair in timestamps[3..].windows(2) {
+			assert!(pair[1] < pair[0] || pair[1] == 0, "{:?}", timestamps);
+		}
+		assert_eq!(timestamps[3], timestamps[2].saturating_sub(10));
+		assert_eq!(*timestamps.last().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_block_builder_logs_per_tx() {
+		use ethereum_types::BloomInput;
+
+		let topic = |i: usize| keccak(format!("topic {}", i));
+		let transactions = calls_to(Address::from(0x42), 3);
+
+		let block = BlockBuilder::genesis()
+			.with_logs_per_tx(5, &topic)
+			.add_block_with_unsigned_transactions(transactions)
+			.last()
+			.clone();
+
+		assert_eq!(block.receipts.len(), 3);
+		assert!(block.receipts.iter().all(|receipt| receipt.logs.len() == 5));
+		assert_eq!(block.receipts.iter().map(|receipt| receipt.logs.len()).sum::<usize>(), 15);
+		assert!(*block.header.receipts_root() != EMPTY_TRIE_ROOT);
+
+		let bloom = block.header.log_bloom();
+		assert!(bloom.contains_input(BloomInput::Raw(&Address::from(0x42))));
+		for i in 0..5 {
+			assert!(bloom.contains_input(BloomInput::Raw(&topic(i))));
+		}
+		assert!(!bloom.contains_input(BloomInput::Raw(&topic(5))));
+	}
+
+	#[test]
+	fn test_block_builder_log_addresses() {
+		use ethereum_types::BloomInput;
+
+		let topic = |i: usize| keccak(format!("topic {}", i));
+		let addresses = vec![Address::from(0xa1), Address::from(0xa2), Address::from(0xa3)];
+		let transactions = || calls_to(Address::from(0x42), 2);
+		let sources = |block: &Block| block.receipts.iter()
+			.flat_map(|receipt| receipt.logs.iter().map(|log| log.address))
+			.collect::<Vec<_>>();
+
+		// 2 transactions with 2 logs each per block, so turns straddle blocks.
+		let mut builder = BlockBuilder::genesis()
+			.with_logs_per_tx(2, &topic)
+			.with_log_addresses(addresses.clone());
+		let mut all_sources = Vec::new();
+		for _ in 0..3 {
+			builder = builder.add_block_with_unsigned_transactions(transactions());
+			let block = builder.last();
+			let block_sources = sources(block);
+			assert_eq!(block_sources.len(), 4);
+
+			let bloom = block.header.log_bloom();
+			for address in &block_sources {
+				assert!(bloom.contains_input(BloomInput::Raw(address)));
+			}
+			assert!(!bloom.contains_input(BloomInput::Raw(&Address::from(0x42))));
+			all_sources.extend(block_sources);
+		}
+
+		for (i, source) in all_sources.iter().enumerate() {
+			assert_eq!(*source, addresses[i % 3]);
+		}
+
+		// without addresses, logs come from the transaction destination again.
+		let block = builder.with_log_addresses(Vec::new()).add_block_with_unsigned_transactions(transactions()).last().clone();
+		assert!(sources(&block).iter().all(|address| *address == Address::from(0x42)));
+	}
+
+	#[test]
+	fn test_canonical_head() {
+		let genesis = BlockBuilder::genesis();
+
+		// longer but lighter chain against a shorter, heavier one.
+		let light = encoded_blocks(genesis.add_blocks(5));
+		let heavy = encoded_blocks(genesis.add_block_with_difficulty(100).add_block_with_difficulty(100));
+		let heavy_head = view!(BlockView, heavy.last().unwrap()).header().hash();
+		let light_head = view!(BlockView, light.last().unwrap()).header().hash();
+		let chains = vec![light, heavy];
+
+		assert_eq!(canonical_head(&chains, ForkChoiceRule::HighestTotalDifficulty), (1, heavy_head));
+		assert_eq!(canonical_head(&chains, ForkChoiceRule::LongestChain), (0, light_head));
+
+		// equal chains are told apart by the lowest head hash.
+		let a = encoded_blocks(genesis.add_blocks(3));
+		let b = encoded_blocks(genesis.with_author(Address::from(1)).add_blocks(3));
+		let hashes = [&a, &b].iter().map(|chain| view!(BlockView, chain.last().unwrap()).header().hash()).collect::<Vec<_>>();
+		let expected = if hashes[0] < hashes[1] { 0 } else { 1 };
+		assert_eq!(canonical_head(&[a, b], ForkChoiceRule::HighestTotalDifficulty), (expected, hashes[expected]));
+	}
+
+	#[test]
+	fn test_block_builder_noncanonical_rlp() {
+		let canonical = BlockBuilder::genesis().add_block_with_difficulty(0x1234).last().encoded().into_inner();
+		let quirky = BlockBuilder::genesis()
+			.with_noncanonical_rlp(RlpQuirk::LeadingZeroDifficulty)
+			.add_block_with_difficulty(0x1234)
+			.last().encoded().into_inner();
+		assert!(quirky != canonical);
+		assert_eq!(quirky.len(), canonical.len() + 1);
+
+		// read leniently, field by field, both headers hold the same values.
+		let canonical = rlp::Rlp::new(&canonical).at(0).unwrap();
+		let quirky = rlp::Rlp::new(&quirky).at(0).unwrap();
+		let strip = |data: &[u8]| data.iter().skip_while(|b| **b == 0).cloned().collect::<Vec<_>>();
+		assert_eq!(quirky.item_count().unwrap(), canonical.item_count().unwrap());
+		for index in 0..canonical.item_count().unwrap() {
+			let (canonical, quirky) = (canonical.at(index).unwrap(), quirky.at(index).unwrap());
+			assert_eq!(strip(quirky.data().unwrap()), strip(canonical.data().unwrap()));
+			assert_eq!(quirky.as_raw() != canonical.as_raw(), index == RlpQuirk::LeadingZeroDifficulty.field_index());
+		}
+
+		let difficulty = quirky.at(RlpQuirk::LeadingZeroDifficulty.field_index()).unwrap();
+		assert_eq!(difficulty.data().unwrap(), &[0x00, 0x12, 0x34]);
+		assert_eq!(U256::from(difficulty.data().unwrap()), U256::from(0x1234));
+	}
+
+	#[test]
+	fn test_block_builder_merge_fields() {
+		use ethereum::ethash::Seal;
+
+		let prev_randao = H256::from(0x5eed);
+		let pre_merge = BlockBuilder::genesis().with_target_block_time(13).add_blocks(3);
+		let post_merge = pre_merge.with_merge_fields(prev_randao).add_blocks(3);
+
+		let merge_block = post_merge.blocks.front().unwrap();
+		assert!(merge_block.difficulty().is_zero());
+		let seal = Seal::parse_seal(merge_block.header.seal()).unwrap();
+		assert_eq!(seal.mix_hash, prev_randao);
+		assert!(seal.nonce.is_zero());
+
+		let total_difficulty = |blocks: &[&Block]| blocks.iter().fold(U256::zero(), |td, block| td + block.difficulty());
+		let pre_merge_blocks = pre_merge.blocks.iter().collect::<Vec<_>>();
+		let all_blocks = pre_merge.blocks.iter().chain(post_merge.blocks.iter()).collect::<Vec<_>>();
+		assert!(!total_difficulty(&pre_merge_blocks).is_zero());
+		assert_eq!(total_difficulty(&all_blocks), total_difficulty(&pre_merge_blocks));
+	}
+
+	#[test]
+	fn test_block_builder_alternating_fill() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.alternating_fill(3).take(6).collect::<Vec<_>>();
+		let counts = blocks.iter().map(|block| block.transactions.len()).collect::<Vec<_>>();
+		assert_eq!(counts, vec![0, 3, 0, 3, 0, 3]);
+		assert_eq!(blocks.iter().map(Block::number).collect::<Vec<_>>(), (1..7).collect::<Vec<_>>());
+
+		let pool = signed_calls(5);
+		let blocks = genesis.alternating_fill(2).starting_full().with_pool(pool.clone()).collect::<Vec<_>>();
+		let counts = blocks.iter().map(|block| block.transactions.len()).collect::<Vec<_>>();
+		assert_eq!(counts, vec![2, 0, 2, 0]);
+		assert_eq!(blocks[2].transactions, pool[2..4].to_vec());
+	}
+
+	#[test]
+	fn test_block_builder_continue_from_snapshot() {
+		let pivot_hash = keccak("pivot");
+		let pivot = BlockBuilder::continue_from_snapshot(1_000_000, pivot_hash, 5_000_000.into());
+		assert_eq!(pivot.total_difficulty(), 5_000_000.into());
+
+		let blocks = pivot.add_blocks_with(3, || BlockOptions {
+			difficulty: 1000.into(),
+			..Default::default()
+		});
+		let first = &blocks.blocks[0];
+		assert_eq!(*first.header.parent_hash(), pivot_hash);
+		assert_eq!(first.number(), 1_000_001);
+		for pair in blocks.blocks.iter().collect::<Vec<_>>().windows(2) {
+			assert_eq!(*pair[1].header.parent_hash(), pair[0].hash());
+			assert_eq!(pair[1].number(), pair[0].number() + 1);
+		}
+		assert_eq!(blocks.last().number(), 1_000_003);
+		assert_eq!(blocks.total_difficulty(), 5_003_000.into());
+
+		// total difficulty carries on through further blocks.
+		assert_eq!(blocks.add_block_with_difficulty(7).total_difficulty(), 5_003_007.into());
+	}
+
+	#[test]
+	fn test_block_builder_genesis_hash() {
+		let genesis = BlockBuilder::preset(NetworkPreset::Ropsten);
+		let blocks = genesis.add_blocks(5);
+		assert_eq!(blocks.genesis_hash(), genesis.genesis_hash());
+
+		let first = BlockGenerator::new(vec![genesis.clone(), blocks]).next().unwrap();
+		let hash = view!(BlockView, &first.encoded().into_inner()).header_view().hash();
+		assert_eq!(genesis.genesis_hash(), hash);
+		assert!(BlockBuilder::genesis().genesis_hash() != hash);
+	}
+
+	#[test]
+	fn test_block_builder_difficulty_sequence() {
+		let genesis = BlockBuilder::genesis().with_target_block_time(13);
+		let difficulties: Vec<U256> = vec![1000.into(), 1001.into(), 999.into()];
+		let mut sequence = genesis.with_difficulty_sequence(difficulties.clone());
+		let blocks = sequence.by_ref().collect::<Vec<_>>();
+
+		assert_eq!(blocks.iter().map(Block::difficulty).collect::<Vec<_>>(), difficulties);
+		assert_eq!(sequence.total_difficulty(), 3000.into());
+		assert!(sequence.next().is_none());
+		for pair in blocks.windows(2) {
+			assert_eq!(*pair[1].header.parent_hash(), pair[0].hash());
+		}
+
+		let cycled = genesis.with_difficulty_sequence(difficulties).cycling().take(5).map(|block| block.difficulty()).collect::<Vec<_>>();
+		assert_eq!(cycled, vec![1000.into(), 1001.into(), 999.into(), 1000.into(), 1001.into()]);
+	}
+
+	#[test]
+	fn test_block_builder_authority_seal() {
+		use ethereum_types::H520;
+		use ethkey::{recover, KeyPair, Secret};
+
+		let keypair = KeyPair::from_secret(Secret::from(H256::from(1))).unwrap();
+		let seal = AuthoritySeal::new(keypair.secret().clone(), 5);
+		let genesis = BlockBuilder::genesis().with_target_block_time(10);
+
+		let blocks = genesis.with_authority_seal(seal.clone()).add_blocks(2);
+		let header = &blocks.last().header;
+		assert_eq!(header.seal().len(), 2);
+		assert_eq!(rlp::Rlp::new(&header.seal()[0]).as_val::<u64>().unwrap(), header.timestamp() / 5);
+		let signature: H520 = rlp::Rlp::new(&header.seal()[1]).as_val().unwrap();
+		assert_eq!(recover(&signature.into(), &header.bare_hash()).unwrap(), *keypair.public());
+
+		let short = genesis.with_authority_seal(seal.clone().with_seal_field_count(1)).add_block();
+		assert_eq!(short.last().header.seal().len(), 1);
+		let long = genesis.with_authority_seal(seal.with_seal_field_count(3)).add_block();
+		assert_eq!(long.last().header.seal().len(), 3);
+	}
+
+	#[test]
+	fn test_block_builder_pow_seal() {
+		use ethereum::ethash::Seal;
+		use ethereum_types::H64;
+
+		let genesis = BlockBuilder::genesis();
+		let ethash = genesis.with_pow_seal(PowSealFormat::default()).add_blocks(2);
+		let seal = Seal::parse_seal(ethash.last().header.seal()).unwrap();
+		assert_eq!(seal.mix_hash, H256::from(2));
+		assert_eq!(seal.nonce, H64::from(2));
+
+		let layouts = vec![
+			(4, NonceOrder::BigEndian, vec![0, 0, 1, 2]),
+			(4, NonceOrder::LittleEndian, vec![2, 1, 0, 0]),
+			(8, NonceOrder::LittleEndian, vec![2, 1, 0, 0, 0, 0, 0, 0]),
+			(10, NonceOrder::BigEndian, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 2]),
+			(1, NonceOrder::BigEndian, vec![2]),
+		];
+		let parent = genesis.add_blocks(0x0101);
+		for (width, order, expected) in layouts {
+			let format = PowSealFormat { nonce_width: width, nonce_order: order };
+			let blocks = parent.with_pow_seal(format).add_block();
+			let seal = blocks.last().header.seal().to_vec();
+			assert_eq!(seal.len(), 2);
+			assert_eq!(rlp::Rlp::new(&seal[0]).as_val::<H256>().unwrap(), H256::from(0x0102));
+			assert_eq!(rlp::Rlp::new(&seal[1]).data().unwrap(), &expected[..]);
+		}
+	}
+
+	#[test]
+	fn test_block_builder_minimal_header() {
+		use ethereum::ethash::Seal;
+
+		let genesis = BlockBuilder::genesis().with_minimal_header();
+		let wide_nonce = PowSealFormat { nonce_width: 10, nonce_order: NonceOrder::LittleEndian };
+		for blocks in vec![genesis.add_blocks(2), genesis.with_pow_seal(wide_nonce).add_blocks(2)] {
+			let block = blocks.last();
+			let encoded = block.encoded();
+			let header_rlp = encoded.rlp().at(0).unwrap().as_raw().to_vec();
+			assert_eq!(rlp::Rlp::new(&header_rlp).item_count().unwrap(), 15);
+
+			let header: Header = rlp::decode(&header_rlp).unwrap();
+			assert_eq!(header, block.header);
+			let seal = Seal::parse_seal(header.seal()).unwrap();
+			assert_eq!(seal.mix_hash, H256::from(2));
+			assert_eq!(seal.nonce, ::ethereum_types::H64::from(2));
+		}
+
+		let post_merge = genesis.with_merge_fields(H256::from(0x5eed)).add_block();
+		let header: Header = rlp::decode(&rlp::encode(&post_merge.last().header)).unwrap();
+		assert_eq!(header.seal().len(), 2);
+		assert_eq!(Seal::parse_seal(header.seal()).unwrap().mix_hash, H256::from(0x5eed));
+	}
+
+	#[test]
+	fn test_tx_inclusion_proof() {
+		use ethtrie::verify_proof;
+
+		let transactions = signed_calls(3);
+
+		let blocks = BlockBuilder::genesis().add_block_with_transactions(transactions.clone());
+		let block = blocks.last();
+		let block_rlp = block.encoded().into_inner();
+		assert!(*block.header.transactions_root() != EMPTY_TRIE_ROOT);
+
+		let proof = tx_inclusion_proof(&block_rlp, 1).unwrap();
+		let proven = verify_proof(*block.header.transactions_root(), &rlp::encode(&1usize), &proof).unwrap();
+		assert_eq!(proven, Some(rlp::encode(&transactions[1]).into_vec()));
+
+		assert!(tx_inclusion_proof(&block_rlp, 3).is_err());
+	}
+
+	#[test]
+	fn test_block_builder_order_transactions() {
+		use triehash::ordered_trie_root;
+
+		let signer = FixedKeySigner::default();
+		let transactions = vec![(0u64, 20u64), (1, 50), (2, 10)].into_iter()
+			.map(|(nonce, gas_price)| signer.sign(Transaction { gas_price: gas_price.into(), ..call(nonce) }))
+			.collect::<Vec<_>>();
+
+		let genesis = BlockBuilder::genesis();
+		let gas_prices = |ordering: TxOrdering| {
+			let blocks = genesis.order_transactions(ordering).add_block_with_transactions(transactions.clone());
+			let block = blocks.last();
+			let encoded = block.encoded();
+			let view = view!(BlockView, encoded.raw());
+			let root = ordered_trie_root(view.transactions().iter().map(|tx| rlp::encode(tx)));
+			assert_eq!(*block.header.transactions_root(), root);
+			view.transactions().into_iter().map(|tx| tx.gas_price.low_u64()).collect::<Vec<_>>()
+		};
+
+		assert_eq!(gas_prices(TxOrdering::InsertionOrder), vec![20, 50, 10]);
+		assert_eq!(gas_prices(TxOrdering::GasPriceDescending), vec![50, 20, 10]);
+		assert_eq!(gas_prices(TxOrdering::NonceThenGasPrice), vec![20, 50, 10]);
+	}
+
+	#[test]
+	fn test_block_builder_gas_distribution() {
+		let distribution = GasDistribution::Bimodal {
+			simple: (21_000, 30_000),
+			complex: (200_000, 250_000),
+			ratio: 0.25,
+		};
+		let transactions = (0..200).map(|nonce| Transaction { gas: U256::zero(), ..call(nonce) }).collect::<Vec<_>>();
+
+		let genesis = BlockBuilder::genesis().with_gas_distribution(distribution);
+		let blocks = genesis.add_block_with_unsigned_transactions(transactions.clone());
+		let block = blocks.last();
+		let gas = block.transactions.iter().map(|tx| tx.gas.low_u64()).collect::<Vec<_>>();
+
+		let complex = gas.iter().filter(|&&gas| gas >= 200_000 && gas <= 250_000).count();
+		let simple = gas.iter().filter(|&&gas| gas >= 21_000 && gas <= 30_000).count();
+		assert_eq!(simple + complex, gas.len());
+		assert!(complex > 20 && complex < 80, "{} of 200 transactions are complex", complex);
+
+		let total = gas.iter().sum::<u64>();
+		assert_eq!(*block.header.gas_used(), U256::from(total));
+		assert_eq!(*block.header.gas_limit(), U256::from(total));
+
+		// draws are reproducible.
+		let again = genesis.add_block_with_unsigned_transactions(transactions);
+		assert_eq!(again.last().hash(), block.hash());
+	}
+
+	#[test]
+	fn test_block_builder_zero_gas_price_transaction() {
+		use ethkey::{KeyPair, public_to_address};
+		use triehash::ordered_trie_root;
+
+		let tx = Transaction::zero_gas_price_call(Address::from(7), vec![0, 1, 2]);
+		let blocks = BlockBuilder::genesis().add_block_with_unsigned_transactions(vec![tx]);
+		let encoded = blocks.last().encoded();
+		let view = view!(BlockView, encoded.raw());
+
+		let transactions = view.transactions();
+		assert_eq!(transactions.len(), 1);
+		assert_eq!(transactions[0].gas_price, U256::zero());
+		assert_eq!(transactions[0].gas, U256::from(21_000 + 4 + 68 * 2));
+		assert_eq!(view.header_view().transactions_root(), ordered_trie_root(transactions.iter().map(|tx| rlp::encode(tx))));
+
+		let signed = SignedTransaction::new(transactions[0].clone()).unwrap();
+		let signer = KeyPair::from_secret(keccak("").into()).unwrap();
+		assert_eq!(signed.sender(), public_to_address(signer.public()));
+	}
+
+	#[test]
+	fn test_block_hash() {
+		let genesis = BlockBuilder::genesis();
+		let transactions = signed_calls(3);
+		let blocks = genesis.add_blocks(5);
+		let with_transactions = blocks.add_block_with_transactions(transactions);
+		let sealed = with_transactions.with_pow_seal(PowSealFormat::default()).add_blocks(3);
+
+		for block in BlockGenerator::new(vec![genesis, blocks, with_transactions, sealed]) {
+			let rlp = block.encoded().into_inner();
+			assert_eq!(block_hash(&rlp), view!(BlockView, &rlp).header_view().hash());
+		}
+	}
+
+	#[test]
+	fn test_block_builder_generate_with_headers() {
+		let genesis = BlockBuilder::genesis();
+		let blocks = genesis.generate_with_headers(4, |block| block.header.set_extra_data(b"finalized".to_vec()));
+		assert_eq!(blocks.len(), 4);
+
+		let mut parent_hash = genesis.last().hash();
+		for (rlp, header) in blocks {
+			let view = view!(BlockView, &rlp);
+			assert_eq!(header.hash(), view.header_view().hash());
+			assert_eq!(header.parent_hash(), &parent_hash);
+			assert_eq!(header.extra_data(), &b"finalized".to_vec());
+			parent_hash = header.hash();
+		}
+
+		// quirked blocks chain up by the hash of their quirked header bytes.
+		let quirked = genesis.with_noncanonical_rlp(RlpQuirk::LeadingZeroNumber).generate_with_headers(2, |_| {});
+		let mut parent_hash = genesis.last().hash();
+		for (number, (rlp, header)) in (1..).zip(quirked) {
+			let raw_header = rlp::Rlp::new(&rlp).at(0).unwrap().as_raw().to_vec();
+			assert!(raw_header != rlp::encode(&header).into_vec());
+			assert_eq!(header.number(), number);
+			assert_eq!(header.parent_hash(), &parent_hash);
+			parent_hash = keccak(&raw_header);
+			assert_eq!(parent_hash, view!(BlockView, &rlp).header_view().hash());
+		}
+	}
 }
@@ -25,7 +25,7 @@ mod extras;
 mod import_route;
 mod update;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-helpers"))]
 pub mod generator;
 
 pub use self::blockchain::{BlockProvider, BlockChain, BlockChainDB, BlockChainDBHandler};
@@ -0,0 +1,116 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Online backup of the client database.
+
+use std::{fs, io};
+use std::path::Path;
+use std::time::Duration;
+
+use ethereum_types::H256;
+use header::BlockNumber;
+use kvdb::DBTransaction;
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use snapshot::service::available_space;
+
+// number of entries written to the copy at once.
+const BATCH_ENTRIES: usize = 10_000;
+
+/// Entries of every column of the database, read from a single point in time.
+pub type ColumnIterators<'a> = Vec<Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a>>;
+
+/// Outcome of a database backup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupReport {
+	/// Number of the best block of the copy.
+	pub best_block_number: BlockNumber,
+	/// Hash of the best block of the copy.
+	pub best_block_hash: H256,
+	/// Number of bytes copied, keys, values and bloom files alike.
+	pub size: u64,
+	/// Time taken by the backup.
+	pub duration: Duration,
+}
+
+/// Check that a backup of a database of `required` bytes can be written to `path`.
+///
+/// The target must be missing or an empty directory, which is created, on a disk with at least
+/// `required` bytes free. The free space isn't checked on platforms which can't tell it.
+pub fn prepare_target(path: &Path, required: u64) -> io::Result<()> {
+	if path.exists() && fs::read_dir(path)?.next().is_some() {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Backup target {} is not empty", path.display())));
+	}
+
+	fs::create_dir_all(path)?;
+	match available_space(path) {
+		Ok(available) if available < required => {
+			let _ = fs::remove_dir(path);
+			Err(io::Error::new(io::ErrorKind::Other, format!(
+				"Not enough space for the backup at {}: {} bytes needed, {} available",
+				path.display(), required, available,
+			)))
+		},
+		Ok(_) => Ok(()),
+		Err(e) => {
+			warn!(target: "client", "Unable to check free space for the backup at {}: {}", path.display(), e);
+			Ok(())
+		},
+	}
+}
+
+/// Size in bytes of the files under `path`.
+pub fn disk_usage(path: &Path) -> io::Result<u64> {
+	let mut size = 0;
+	for entry in fs::read_dir(path)? {
+		let entry = entry?;
+		let metadata = entry.metadata()?;
+		if metadata.is_dir() {
+			size += disk_usage(&entry.path())?;
+		} else {
+			size += metadata.len();
+		}
+	}
+	Ok(size)
+}
+
+/// Write the entries of `columns` to a new database at `path`, returning the number of bytes written.
+///
+/// The iterator at index `i` is written to column `i`.
+pub fn copy_columns(columns: ColumnIterators, path: &Path) -> io::Result<u64> {
+	let config = DatabaseConfig::with_columns(Some(columns.len() as u32));
+	let db = Database::open(&config, &path.to_string_lossy())?;
+
+	let mut size = 0;
+	for (index, column) in columns.into_iter().enumerate() {
+		let col = Some(index as u32);
+		let mut batch = DBTransaction::new();
+		let mut entries = 0;
+		for (key, value) in column {
+			size += (key.len() + value.len()) as u64;
+			batch.put(col, &key, &value);
+			entries += 1;
+			if entries == BATCH_ENTRIES {
+				db.write(batch)?;
+				batch = DBTransaction::new();
+				entries = 0;
+			}
+		}
+		db.write(batch)?;
+	}
+
+	db.flush()?;
+	Ok(size)
+}
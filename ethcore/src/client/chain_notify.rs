@@ -18,6 +18,7 @@ use bytes::Bytes;
 use ethereum_types::H256;
 use transaction::UnverifiedTransaction;
 use blockchain::ImportRoute;
+use trace::LocalizedTrace;
 use std::time::Duration;
 use std::collections::HashMap;
 
@@ -130,6 +131,12 @@ pub trait ChainNotify : Send + Sync {
 		// does nothing by default
 	}
 
+	/// fires after `new_blocks` if tracing is enabled, with the traces of each block of the route, in
+	/// the order of the route. Traces of imported blocks are handed over as they were written.
+	fn new_traces(&self, _route: &[(H256, ChainRouteType, Vec<LocalizedTrace>)]) {
+		// does nothing by default
+	}
+
 	/// fires when chain achieves active mode
 	fn start(&self) {
 		// does nothing by default
@@ -14,8 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashSet, BTreeMap, VecDeque};
+use std::collections::{HashSet, HashMap, BTreeMap, VecDeque};
 use std::cmp;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
@@ -25,8 +26,9 @@ use std::time::{Instant, Duration};
 use hash::keccak;
 use bytes::Bytes;
 use itertools::Itertools;
-use journaldb;
+use journaldb::{self, JournalMemStats};
 use trie::{TrieSpec, TrieFactory, Trie};
+use ethtrie::Result as TrieResult;
 use kvdb::{DBValue, KeyValueDB, DBTransaction};
 
 // other
@@ -34,6 +36,7 @@ use ethereum_types::{H256, Address, U256};
 use block::{IsBlock, LockedBlock, Drain, ClosedBlock, OpenBlock, enact_verified, SealedBlock};
 use blockchain::{BlockChain, BlockChainDB, BlockProvider, TreeRoute, ImportRoute, TransactionAddress, ExtrasInsert};
 use client::ancient_import::AncientVerifier;
+use client::backup::{self, BackupReport};
 use client::{
 	Nonce, Balance, ChainInfo, BlockInfo, CallContract, TransactionInfo,
 	RegistryInfo, ReopenBlock, PrepareOpenBlock, ScheduleInfo, ImportSealedBlock,
@@ -68,13 +71,15 @@ use rand::OsRng;
 use receipt::{Receipt, LocalizedReceipt};
 use snapshot::{self, io as snapshot_io};
 use spec::Spec;
-use state_db::StateDB;
+use state_db::{StateDB, StateCacheStats, BloomReport};
 use state::{self, State};
 use trace;
 use trace::{TraceDB, ImportRequest as TraceImportRequest, LocalizedTrace, Database as TraceDatabase};
+use trace::flat::FlatBlockTraces;
 use transaction::{self, LocalizedTransaction, UnverifiedTransaction, SignedTransaction, Transaction, Action};
 use types::filter::Filter;
 use types::ancestry_action::AncestryAction;
+use types::ancient_download::AncientDownloadProgress;
 use verification;
 use verification::{PreverifiedBlock, Verifier, BlockQueue};
 use verification::queue::kind::blocks::Unverified;
@@ -93,6 +98,10 @@ const MAX_ANCIENT_BLOCKS_QUEUE_SIZE: usize = 4096;
 const MAX_ANCIENT_BLOCKS_TO_IMPORT: usize = 4;
 const MAX_QUEUE_SIZE_TO_SLEEP_ON: usize = 2;
 const MIN_HISTORY_SIZE: u64 = 8;
+// Number of eras pruned per write once the state journal exceeds its memory limit.
+const HISTORY_FLUSH_BATCH_ERAS: usize = 32;
+// Key of the ancient blocks download progress in the extras column.
+const ANCIENT_DOWNLOAD_KEY: &'static [u8] = b"ancient_download";
 
 /// Report on the status of a client.
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
@@ -105,6 +114,8 @@ pub struct ClientReport {
 	pub gas_processed: U256,
 	/// Memory used by state DB
 	pub state_db_mem: usize,
+	/// Memory held by the state journal, per era.
+	pub state_journal: JournalMemStats,
 }
 
 impl ClientReport {
@@ -271,11 +282,12 @@ impl Importer {
 		}
 
 		let max_blocks_to_import = 4;
-		let (imported_blocks, import_results, invalid_blocks, imported, proposed_blocks, duration, is_empty) = {
+		let (imported_blocks, import_results, imported_traces, invalid_blocks, imported, proposed_blocks, duration, is_empty) = {
 			let mut imported_blocks = Vec::with_capacity(max_blocks_to_import);
 			let mut invalid_blocks = HashSet::new();
 			let mut proposed_blocks = Vec::with_capacity(max_blocks_to_import);
 			let mut import_results = Vec::with_capacity(max_blocks_to_import);
+			let mut imported_traces = HashMap::with_capacity(max_blocks_to_import);
 
 			let _import_lock = self.import_lock.lock();
 			let blocks = self.block_queue.drain(max_blocks_to_import);
@@ -307,8 +319,9 @@ impl Importer {
 
 							let transactions_len = closed_block.transactions().len();
 
-							let route = self.commit_block(closed_block, &header, encoded::Block::new(bytes), client);
+							let (route, traces) = self.commit_block(closed_block, &header, encoded::Block::new(bytes), client);
 							import_results.push(route);
+							imported_traces.insert(hash, traces);
 
 							client.report.write().accrue_block(&header, transactions_len);
 						}
@@ -327,7 +340,7 @@ impl Importer {
 				self.block_queue.mark_as_bad(&invalid_blocks);
 			}
 			let is_empty = self.block_queue.mark_as_good(&imported_blocks);
-			(imported_blocks, import_results, invalid_blocks, imported, proposed_blocks, start.elapsed(), is_empty)
+			(imported_blocks, import_results, imported_traces, invalid_blocks, imported, proposed_blocks, start.elapsed(), is_empty)
 		};
 
 		{
@@ -348,6 +361,7 @@ impl Importer {
 						duration,
 					);
 				});
+				client.notify_traces(&route, imported_traces);
 			}
 		}
 
@@ -468,7 +482,7 @@ impl Importer {
 	// it is for reconstructing the state transition.
 	//
 	// The header passed is from the original block data and is sealed.
-	fn commit_block<B>(&self, block: B, header: &Header, block_data: encoded::Block, client: &Client) -> ImportRoute where B: Drain {
+	fn commit_block<B>(&self, block: B, header: &Header, block_data: encoded::Block, client: &Client) -> (ImportRoute, FlatBlockTraces) where B: Drain {
 		let hash = &header.hash();
 		let number = header.number();
 		let parent = header.parent_hash();
@@ -484,7 +498,7 @@ impl Importer {
 		let ancestry_actions = self.engine.ancestry_actions(&block, &mut chain.ancestry_with_metadata_iter(*parent));
 
 		let receipts = block.receipts;
-		let traces = block.traces.drain();
+		let traces: FlatBlockTraces = block.traces.drain().into();
 		let best_hash = chain.best_block_hash();
 
 		let new = ExtendedHeader {
@@ -546,7 +560,7 @@ impl Importer {
 		});
 
 		client.tracedb.read().import(&mut batch, TraceImportRequest {
-			traces: traces.into(),
+			traces: traces.clone(),
 			block_hash: hash.clone(),
 			block_number: number,
 			enacted: route.enacted.clone(),
@@ -555,6 +569,9 @@ impl Importer {
 
 		let is_canon = route.enacted.last().map_or(false, |h| h == hash);
 		state.sync_cache(&route.enacted, &route.retracted, is_canon);
+		if let Some(ref adaptive) = client.config.adaptive_state_cache {
+			state.adapt_account_cache(adaptive, chain.cache_size().total());
+		}
 		// Final commit to the DB
 		client.db.read().key_value().write_buffered(batch);
 		chain.commit();
@@ -567,7 +584,7 @@ impl Importer {
 			warn!("Failed to prune ancient state data: {}", e);
 		}
 
-		route
+		(route, traces)
 	}
 
 	// check for epoch end signal and write pending transition if it occurs.
@@ -865,6 +882,36 @@ impl Client {
 		}
 	}
 
+	// hand the traces of the blocks on `route` to the listeners, using the traces of the blocks just
+	// imported as they were written and reading only those of blocks imported before from the database.
+	fn notify_traces(&self, route: &ChainRoute, mut imported: HashMap<H256, FlatBlockTraces>) {
+		if !self.tracedb.read().tracing_enabled() || self.notify.read().is_empty() {
+			return;
+		}
+
+		let route_traces = {
+			let chain = self.chain.read();
+			let tracedb = self.tracedb.read();
+			route.route().iter()
+				.filter_map(|&(hash, ref route_type)| {
+					let number = chain.block_number(&hash)?;
+					let tx_hashes = chain.block_body(&hash)?.transaction_hashes();
+					let traces = imported.remove(&hash).or_else(|| tracedb.traces(&hash))?;
+					Some((hash, route_type.clone(), traces.localize(hash, number, &tx_hashes)))
+				})
+				.collect::<Vec<_>>()
+		};
+
+		self.notify(|notify| notify.new_traces(&route_traces));
+	}
+
+	/// Rewrite the trace blooms of the canonical chain from the stored traces, returning the number of
+	/// blocks rewritten. A one-off for databases whose trace blooms are missing or stale.
+	pub fn rebuild_trace_blooms(&self) -> u64 {
+		let best_block = self.chain.read().best_block_number();
+		self.tracedb.read().rebuild_blooms(best_block)
+	}
+
 	/// Register an action to be done if a mode/spec_name change happens.
 	pub fn on_user_defaults_change<F>(&self, f: F) where F: 'static + FnMut(Option<Mode>) + Send {
 		*self.on_user_defaults_change.lock() = Some(Box::new(f));
@@ -978,6 +1025,17 @@ impl Client {
 			}
 		}
 
+		// past the hard limit, prune within the history too, keeping only the minimum.
+		if let Some(limit) = self.config.history_mem_limit {
+			if number >= MIN_HISTORY_SIZE {
+				let canonical = |era| chain.block_hash(era);
+				let pruned = state_db.flush_journal(limit, number - MIN_HISTORY_SIZE, HISTORY_FLUSH_BATCH_ERAS, &canonical)?;
+				if pruned > 0 {
+					debug!(target: "client", "Pruned {} eras within history, state journal over {} bytes", pruned, limit);
+				}
+			}
+		}
+
 		Ok(())
 	}
 
@@ -1056,6 +1114,30 @@ impl Client {
 		}
 	}
 
+	/// Check the account bloom against the state of the given block, see `StateDB::verify_account_bloom`.
+	///
+	/// Returns `None` if the block is unknown or its state was pruned.
+	pub fn verify_account_bloom(&self, id: BlockId, progress: &Fn(u64)) -> Option<TrieResult<BloomReport>> {
+		let block_number = self.block_number(id)?;
+		let header = self.block_header(id)?;
+		let db = self.state_db.read().boxed_clone();
+		if db.is_pruned() && self.pruning_info().earliest_state > block_number {
+			return None;
+		}
+
+		Some(db.verify_account_bloom(&header.state_root(), progress))
+	}
+
+	/// Rebuild the account bloom from the state of the best block, see `StateDB::rebuild_account_bloom`.
+	///
+	/// Block import is held until the rebuild completes.
+	pub fn rebuild_account_bloom(&self, progress: &Fn(u64)) -> Result<u64, EthcoreError> {
+		let _import_lock = self.importer.import_lock.lock();
+		let state_root = *self.best_block_header().state_root();
+		let db = self.state_db.read().boxed_clone();
+		db.rebuild_account_bloom(&state_root, progress)
+	}
+
 	/// Get a copy of the best block's state.
 	pub fn state(&self) -> Box<StateInfo> {
 		Box::new(self.latest_state()) as Box<_>
@@ -1066,10 +1148,19 @@ impl Client {
 		self.chain.read().cache_size()
 	}
 
+	/// Get the counters of the state caches.
+	pub fn state_cache_stats(&self) -> StateCacheStats {
+		self.state_db.read().cache_stats()
+	}
+
 	/// Get the report.
 	pub fn report(&self) -> ClientReport {
 		let mut report = self.report.read().clone();
-		report.state_db_mem = self.state_db.read().mem_used();
+		{
+			let state_db = self.state_db.read();
+			report.state_db_mem = state_db.mem_used();
+			report.state_journal = state_db.journal_db().mem_stats();
+		}
 		report
 	}
 
@@ -1077,6 +1168,7 @@ impl Client {
 	// TODO: manage by real events.
 	pub fn tick(&self, prevent_sleep: bool) {
 		self.check_garbage();
+		self.prune_traces();
 		if !prevent_sleep {
 			self.check_snooze();
 		}
@@ -1088,6 +1180,13 @@ impl Client {
 		self.tracedb.read().collect_garbage();
 	}
 
+	// prune traces falling out of the trace history, but none of blocks within the
+	// pruning history as those may still be re-enacted by a reorg.
+	fn prune_traces(&self) {
+		let best_block = self.chain.read().best_block_number();
+		self.tracedb.read().prune(best_block, self.history);
+	}
+
 	fn check_snooze(&self) {
 		let mode = self.mode.lock().clone();
 		match mode {
@@ -1225,12 +1324,14 @@ impl Client {
 		state: &mut State<StateDB>,
 		t: &SignedTransaction,
 		analytics: CallAnalytics,
+		max_storage_reads: usize,
 	) -> Result<Executed, CallError> {
 		fn call<V, T>(
 			state: &mut State<StateDB>,
 			env_info: &EnvInfo,
 			machine: &::machine::EthereumMachine,
 			state_diff: bool,
+			storage_reads: Option<usize>,
 			transaction: &SignedTransaction,
 			options: TransactOptions<T, V>,
 		) -> Result<Executed<T::Output, V::Output>, CallError> where
@@ -1243,21 +1344,30 @@ impl Client {
 			let original_state = if state_diff { Some(state.clone()) } else { None };
 			let schedule = machine.schedule(env_info.number);
 
-			let mut ret = Executive::new(state, env_info, &machine, &schedule).transact_virtual(transaction, options)?;
+			if let Some(limit) = storage_reads {
+				state.record_storage_reads(limit);
+			}
+
+			let ret = Executive::new(state, env_info, &machine, &schedule).transact_virtual(transaction, options);
+			let reads = state.drain_storage_reads();
+			let mut ret = ret?;
 
 			if let Some(original) = original_state {
-				ret.state_diff = Some(state.diff_from(original).map_err(ExecutionError::from)?);
+				let mut diff = state.diff_from(original).map_err(ExecutionError::from)?;
+				diff.reads = reads;
+				ret.state_diff = Some(diff);
 			}
 			Ok(ret)
 		}
 
-		let state_diff = analytics.state_diffing;
+		let state_diff = analytics.state_diffing || analytics.state_diffing_with_reads;
+		let storage_reads = if analytics.state_diffing_with_reads { Some(max_storage_reads) } else { None };
 
 		match (analytics.transaction_tracing, analytics.vm_tracing) {
-			(true, true) => call(state, env_info, machine, state_diff, t, TransactOptions::with_tracing_and_vm_tracing()),
-			(true, false) => call(state, env_info, machine, state_diff, t, TransactOptions::with_tracing()),
-			(false, true) => call(state, env_info, machine, state_diff, t, TransactOptions::with_vm_tracing()),
-			(false, false) => call(state, env_info, machine, state_diff, t, TransactOptions::with_no_tracing()),
+			(true, true) => call(state, env_info, machine, state_diff, storage_reads, t, TransactOptions::with_tracing_and_vm_tracing()),
+			(true, false) => call(state, env_info, machine, state_diff, storage_reads, t, TransactOptions::with_tracing()),
+			(false, true) => call(state, env_info, machine, state_diff, storage_reads, t, TransactOptions::with_vm_tracing()),
+			(false, false) => call(state, env_info, machine, state_diff, storage_reads, t, TransactOptions::with_no_tracing()),
 		}
 	}
 
@@ -1308,6 +1418,13 @@ impl snapshot::DatabaseRestore for Client {
 	}
 }
 
+impl snapshot::BusySignal for Client {
+	/// Busy while blocks are being verified or imported.
+	fn is_busy(&self) -> bool {
+		self.importer.import_lock.try_lock().is_none() || !self.importer.block_queue.queue_info().is_empty()
+	}
+}
+
 impl Nonce for Client {
 	fn nonce(&self, address: &Address, id: BlockId) -> Option<U256> {
 		self.state_at(id).and_then(|s| s.nonce(address).ok())
@@ -1444,7 +1561,7 @@ impl Call for Client {
 		};
 		let machine = self.engine.machine();
 
-		Self::do_virtual_call(&machine, &env_info, state, transaction, analytics)
+		Self::do_virtual_call(&machine, &env_info, state, transaction, analytics, self.config.tracing.max_storage_reads)
 	}
 
 	fn call_many(&self, transactions: &[(SignedTransaction, CallAnalytics)], state: &mut Self::State, header: &Header) -> Result<Vec<Executed>, CallError> {
@@ -1462,7 +1579,7 @@ impl Call for Client {
 		let machine = self.engine.machine();
 
 		for &(ref t, analytics) in transactions {
-			let ret = Self::do_virtual_call(machine, &env_info, state, t, analytics)?;
+			let ret = Self::do_virtual_call(machine, &env_info, state, t, analytics, self.config.tracing.max_storage_reads)?;
 			env_info.gas_used = ret.cumulative_gas_used;
 			results.push(ret);
 		}
@@ -1571,6 +1688,7 @@ impl BlockChainClient for Client {
 		let mut state = self.state_at_beginning(block).ok_or(CallError::StatePruned)?;
 		let txs = body.transactions();
 		let engine = self.engine.clone();
+		let max_storage_reads = self.config.tracing.max_storage_reads;
 
 		const PROOF: &'static str = "Transactions fetched from blockchain; blockchain transactions are valid; qed";
 		const EXECUTE_PROOF: &'static str = "Transaction replayed; qed";
@@ -1580,7 +1698,7 @@ impl BlockChainClient for Client {
 				let transaction_hash = t.hash();
 				let t = SignedTransaction::new(t).expect(PROOF);
 				let machine = engine.machine();
-				let x = Self::do_virtual_call(machine, &env_info, &mut state, &t, analytics).expect(EXECUTE_PROOF);
+				let x = Self::do_virtual_call(machine, &env_info, &mut state, &t, analytics, max_storage_reads).expect(EXECUTE_PROOF);
 				env_info.gas_used = env_info.gas_used + x.gas_used;
 				(transaction_hash, x)
 			})))
@@ -1654,6 +1772,24 @@ impl BlockChainClient for Client {
 		}
 	}
 
+	fn ancient_download_progress(&self) -> Option<AncientDownloadProgress> {
+		self.db.read().key_value().get(::db::COL_EXTRA, ANCIENT_DOWNLOAD_KEY)
+			.expect("Low level database error when fetching ancient download progress. Some issue with disk?")
+			.and_then(|bytes| ::rlp::decode(&bytes).ok())
+	}
+
+	fn set_ancient_download_progress(&self, progress: Option<AncientDownloadProgress>) {
+		let db = self.db.read();
+		let mut batch = db.key_value().transaction();
+		match progress {
+			Some(ref progress) => batch.put(::db::COL_EXTRA, ANCIENT_DOWNLOAD_KEY, &::rlp::encode(progress)),
+			None => batch.delete(::db::COL_EXTRA, ANCIENT_DOWNLOAD_KEY),
+		}
+		if let Err(e) = db.key_value().write(batch) {
+			warn!(target: "client", "Failed to persist ancient download progress: {}", e);
+		}
+	}
+
 	fn block_total_difficulty(&self, id: BlockId) -> Option<U256> {
 		let chain = self.chain.read();
 
@@ -1947,6 +2083,8 @@ impl BlockChainClient for Client {
 			range: start as usize..end as usize,
 			from_address: filter.from_address.into(),
 			to_address: filter.to_address.into(),
+			kind: filter.kind,
+			status: filter.status,
 		};
 
 		let traces = self.tracedb.read()
@@ -1992,6 +2130,11 @@ impl BlockChainClient for Client {
 			.and_then(|number| self.tracedb.read().block_traces(number))
 	}
 
+	fn trace_horizon(&self) -> Option<BlockNumber> {
+		let tracedb = self.tracedb.read();
+		if tracedb.tracing_enabled() { Some(tracedb.trace_horizon()) } else { None }
+	}
+
 	fn last_hashes(&self) -> LastHashes {
 		(*self.build_last_hashes(&self.chain.read().best_block_hash())).clone()
 	}
@@ -2063,6 +2206,41 @@ impl BlockChainClient for Client {
 	fn registrar_address(&self) -> Option<Address> {
 		self.registrar_address.clone()
 	}
+
+	fn backup_database(&self, path: &Path) -> Result<BackupReport, EthcoreError> {
+		let started = Instant::now();
+		let db = self.db.read().clone();
+		let required = match db.path() {
+			Some(source) => backup::disk_usage(source)?,
+			None => 0,
+		};
+		backup::prepare_target(path, required)?;
+
+		let (columns, best_block_number, best_block_hash) = {
+			let _import_lock = self.importer.import_lock.lock();
+			let _ancient_import_lock = self.ancient_blocks_import_lock.lock();
+			db.key_value().flush()?;
+			let columns: backup::ColumnIterators = (0..::db::NUM_COLUMNS.unwrap_or(0))
+				.map(|col| db.key_value().iter(Some(col)))
+				.collect();
+			let chain = self.chain.read();
+			(columns, chain.best_block_number(), chain.best_block_hash())
+		};
+
+		let mut size = backup::copy_columns(columns, path)?;
+		// blooms are only ever added to, so copying them after the columns can't miss any.
+		size += db.blooms().copy_to(path.join("blooms"))?;
+		size += db.trace_blooms().copy_to(path.join("trace_blooms"))?;
+
+		let report = BackupReport {
+			best_block_number,
+			best_block_hash,
+			size,
+			duration: started.elapsed(),
+		};
+		info!(target: "client", "Backed the database up to {} at block #{} ({} bytes)", path.display(), best_block_number, size);
+		Ok(report)
+	}
 }
 
 impl IoClient for Client {
@@ -2242,7 +2420,7 @@ impl ImportSealedBlock for Client {
 	fn import_sealed_block(&self, block: SealedBlock) -> ImportResult {
 		let h = block.header().hash();
 		let start = Instant::now();
-		let route = {
+		let (route, traces) = {
 			// scope for self.import_lock
 			let _import_lock = self.importer.import_lock.lock();
 			trace_time!("import_sealed_block");
@@ -2251,10 +2429,10 @@ impl ImportSealedBlock for Client {
 			let block_data = block.rlp_bytes();
 			let header = block.header().clone();
 
-			let route = self.importer.commit_block(block, &header, encoded::Block::new(block_data), self);
+			let (route, traces) = self.importer.commit_block(block, &header, encoded::Block::new(block_data), self);
 			trace!(target: "client", "Imported sealed block #{} ({})", number, h);
 			self.state_db.write().sync_cache(&route.enacted, &route.retracted, false);
-			route
+			(route, traces)
 		};
 		let route = ChainRoute::from([route].as_ref());
 		self.importer.miner.chain_new_blocks(
@@ -2275,6 +2453,7 @@ impl ImportSealedBlock for Client {
 				start.elapsed(),
 			);
 		});
+		self.notify_traces(&route, hash_map![h => traces]);
 		self.db.read().key_value().flush().expect("DB flush failed.");
 		Ok(h)
 	}
@@ -19,6 +19,7 @@ use std::fmt::{Display, Formatter, Error as FmtError};
 
 use verification::{VerifierType, QueueConfig};
 use journaldb;
+use state_db::AdaptiveCacheConfig;
 
 pub use std::time::Duration;
 pub use blockchain::Config as BlockChainConfig;
@@ -110,12 +111,17 @@ pub struct ClientConfig {
 	pub verifier_type: VerifierType,
 	/// State db cache-size.
 	pub state_cache_size: usize,
+	/// Growth of the state db account cache during import, if any.
+	pub adaptive_state_cache: Option<AdaptiveCacheConfig>,
 	/// EVM jump-tables cache size.
 	pub jump_table_size: usize,
 	/// Minimum state pruning history size.
 	pub history: u64,
 	/// Ideal memory usage for state pruning history.
 	pub history_mem: usize,
+	/// Hard limit on the memory of the state journal, if any. Past it, states within `history`
+	/// are pruned too, down to the minimal history the client keeps for reorgs.
+	pub history_mem_limit: Option<usize>,
 	/// Check seal valididity on block import
 	pub check_seal: bool,
 	/// Maximal number of transactions queued for verification in a separate thread.
@@ -139,9 +145,11 @@ impl Default for ClientConfig {
 			spec_name: "".into(),
 			verifier_type: VerifierType::Canon,
 			state_cache_size: 1 * mb,
+			adaptive_state_cache: None,
 			jump_table_size: 1 * mb,
 			history: 64,
 			history_mem: 32 * mb,
+			history_mem_limit: None,
 			check_seal: true,
 			transaction_verification_queue_size: 8192,
 		}
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::fmt;
+use std::path::PathBuf;
 use bytes::Bytes;
 use client::Client;
 use ethereum_types::H256;
@@ -29,6 +30,8 @@ pub enum ClientIoMessage {
 	BlockVerified,
 	/// Begin snapshot restoration
 	BeginRestoration(ManifestData),
+	/// Restore the snapshot at the given path from disk
+	RestoreFromPath(PathBuf),
 	/// Feed a state chunk to the snapshot service
 	FeedStateChunk(H256, Bytes),
 	/// Feed a block chunk to the snapshot service
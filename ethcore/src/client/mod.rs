@@ -17,6 +17,7 @@
 //! Blockchain database client.
 
 mod ancient_import;
+mod backup;
 mod bad_blocks;
 mod client;
 mod config;
@@ -27,6 +28,7 @@ mod io_message;
 mod test_client;
 mod trace;
 
+pub use self::backup::BackupReport;
 pub use self::client::*;
 pub use self::config::{Mode, ClientConfig, DatabaseCompactionProfile, BlockChainConfig, VMType};
 #[cfg(any(test, feature = "test-helpers"))]
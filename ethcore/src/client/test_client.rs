@@ -20,6 +20,8 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrder};
 use std::sync::Arc;
 use std::collections::{HashMap, BTreeMap};
 use std::mem;
+use std::path::Path;
+use std::time::Duration;
 use itertools::Itertools;
 use rustc_hex::FromHex;
 use hash::keccak;
@@ -40,7 +42,7 @@ use client::{
 	TransactionId, UncleId, TraceId, TraceFilter, LastHashes, CallAnalytics, BlockImportError,
 	ProvingBlockChainClient, ScheduleInfo, ImportSealedBlock, BroadcastProposalBlock, ImportBlock, StateOrBlock,
 	Call, StateClient, EngineInfo, AccountData, BlockChain, BlockProducer, SealedBlockImporter, IoClient,
-	BadBlocks,
+	BadBlocks, BackupReport,
 };
 use db::{NUM_COLUMNS, COL_STATE};
 use header::{Header as BlockHeader, BlockNumber};
@@ -53,6 +55,7 @@ use miner::{self, Miner, MinerService};
 use spec::Spec;
 use types::basic_account::BasicAccount;
 use types::pruning_info::PruningInfo;
+use types::ancient_download::AncientDownloadProgress;
 use verification::queue::QueueInfo;
 use verification::queue::kind::blocks::Unverified;
 use block::{OpenBlock, SealedBlock, ClosedBlock};
@@ -109,8 +112,12 @@ pub struct TestBlockChainClient {
 	pub ancient_block: RwLock<Option<(H256, u64)>>,
 	/// First block info.
 	pub first_block: RwLock<Option<(H256, u64)>>,
+	/// Persisted ancient blocks download progress.
+	pub ancient_download_progress: RwLock<Option<AncientDownloadProgress>>,
 	/// Traces to return
 	pub traces: RwLock<Option<Vec<LocalizedTrace>>>,
+	/// Oldest block traces are kept for.
+	pub trace_horizon: RwLock<BlockNumber>,
 	/// Pruning history size to report.
 	pub history: RwLock<Option<u64>>,
 	/// Is disabled
@@ -178,7 +185,9 @@ impl TestBlockChainClient {
 			latest_block_timestamp: RwLock::new(10_000_000),
 			ancient_block: RwLock::new(None),
 			first_block: RwLock::new(None),
+			ancient_download_progress: RwLock::new(None),
 			traces: RwLock::new(None),
+			trace_horizon: RwLock::new(0),
 			history: RwLock::new(None),
 			disabled: AtomicBool::new(false),
 			error_on_logs: RwLock::new(None),
@@ -740,6 +749,14 @@ impl BlockChainClient for TestBlockChainClient {
 		}
 	}
 
+	fn ancient_download_progress(&self) -> Option<AncientDownloadProgress> {
+		self.ancient_download_progress.read().clone()
+	}
+
+	fn set_ancient_download_progress(&self, progress: Option<AncientDownloadProgress>) {
+		*self.ancient_download_progress.write() = progress;
+	}
+
 	// works only if blocks are one after another 1 -> 2 -> 3
 	fn tree_route(&self, from: &H256, to: &H256) -> Option<TreeRoute> {
 		Some(TreeRoute {
@@ -834,6 +851,10 @@ impl BlockChainClient for TestBlockChainClient {
 		self.traces.read().clone()
 	}
 
+	fn trace_horizon(&self) -> Option<BlockNumber> {
+		self.traces.read().as_ref().map(|_| *self.trace_horizon.read())
+	}
+
 	fn transactions_to_propagate(&self) -> Vec<Arc<VerifiedTransaction>> {
 		self.miner.ready_transactions(self, 4096, miner::PendingOrdering::Priority)
 	}
@@ -874,6 +895,16 @@ impl BlockChainClient for TestBlockChainClient {
 	}
 
 	fn registrar_address(&self) -> Option<Address> { None }
+
+	fn backup_database(&self, _path: &Path) -> Result<BackupReport, Error> {
+		let chain_info = self.chain_info();
+		Ok(BackupReport {
+			best_block_number: chain_info.best_block_number,
+			best_block_hash: chain_info.best_block_hash,
+			size: 0,
+			duration: Duration::from_secs(0),
+		})
+	}
 }
 
 impl IoClient for TestBlockChainClient {
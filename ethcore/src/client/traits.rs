@@ -15,13 +15,14 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use itertools::Itertools;
 
 use block::{OpenBlock, SealedBlock, ClosedBlock};
 use blockchain::TreeRoute;
-use client::Mode;
+use client::{BackupReport, Mode};
 use encoded;
 use vm::LastHashes;
 use error::{Error, ImportResult, CallError, BlockImportError};
@@ -51,6 +52,7 @@ use types::call_analytics::CallAnalytics;
 use types::blockchain_info::BlockChainInfo;
 use types::block_status::BlockStatus;
 use types::pruning_info::PruningInfo;
+use types::ancient_download::AncientDownloadProgress;
 
 /// State information to be used during client query
 pub enum StateOrBlock {
@@ -230,6 +232,12 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// Get block status by block header hash.
 	fn block_status(&self, id: BlockId) -> BlockStatus;
 
+	/// Get the persisted progress of the ancient blocks download.
+	fn ancient_download_progress(&self) -> Option<AncientDownloadProgress>;
+
+	/// Persist progress of the ancient blocks download. `None` clears it.
+	fn set_ancient_download_progress(&self, progress: Option<AncientDownloadProgress>);
+
 	/// Get block total difficulty.
 	fn block_total_difficulty(&self, id: BlockId) -> Option<U256>;
 
@@ -324,6 +332,10 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 	/// Returns traces created by transaction from block.
 	fn block_traces(&self, trace: BlockId) -> Option<Vec<LocalizedTrace>>;
 
+	/// Returns the number of the oldest block traces are kept for, if tracing is enabled.
+	/// Traces of older blocks were pruned.
+	fn trace_horizon(&self) -> Option<BlockNumber>;
+
 	/// Get last hashes starting from best block.
 	fn last_hashes(&self) -> LastHashes;
 
@@ -384,6 +396,14 @@ pub trait BlockChainClient : Sync + Send + AccountData + BlockChain + CallContra
 
 	/// Get the address of the registry itself.
 	fn registrar_address(&self) -> Option<Address>;
+
+	/// Back the database up to `path`, which must be missing or an empty directory.
+	///
+	/// The copy is read from a single point in time while block import is held, so it ends on a
+	/// fully imported block with its state committed, as a restarted node would find it. Import
+	/// resumes while the copy is written out. Fails before writing anything if the disk holding
+	/// `path` has less free space than the database takes.
+	fn backup_database(&self, path: &Path) -> Result<BackupReport, Error>;
 }
 
 /// Provides `reopen_block` method
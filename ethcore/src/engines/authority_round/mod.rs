@@ -1420,6 +1420,8 @@ mod tests {
 	use engines::{Seal, Engine, EngineError, EthEngine};
 	use engines::validator_set::TestSet;
 	use error::{Error, ErrorKind};
+	use trace::{RewardType, Tracing};
+	use trace::trace::Action as TraceAction;
 	use super::{AuthorityRoundParams, AuthorityRound, EmptyStep, SealedEmptyStep, calculate_score};
 
 	#[test]
@@ -2050,7 +2052,7 @@ mod tests {
 		let b2 = OpenBlock::new(
 			engine,
 			Default::default(),
-			false,
+			true,
 			db2,
 			&genesis_header,
 			last_hashes.clone(),
@@ -2070,6 +2072,25 @@ mod tests {
 		assert_eq!(
 			b2.block().state().balance(&addr1).unwrap(),
 			addr1_balance + (1000 + 0) + (1000 + 2),
-		)
+		);
+
+		// rewards credited by the contract are traced as external and reconcile with the balance
+		let traced: Vec<_> = match b2.block().traces {
+			Tracing::Enabled(ref traces) => traces.iter()
+				.cloned()
+				.flat_map(|tx_traces| Into::<Vec<_>>::into(tx_traces))
+				.map(|trace| match trace.action {
+					TraceAction::Reward(reward) => reward,
+					action => panic!("Unexpected trace {:?}", action),
+				})
+				.collect(),
+			Tracing::Disabled => panic!("Tracing is enabled"),
+		};
+		assert_eq!(traced.len(), 2);
+		assert!(traced.iter().all(|reward| reward.author == addr1 && reward.reward_type == RewardType::External));
+		assert_eq!(
+			traced.iter().fold(U256::zero(), |total, reward| total + reward.value),
+			U256::from((1000 + 0) + (1000 + 2)),
+		);
 	}
 }
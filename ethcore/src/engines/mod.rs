@@ -479,6 +479,14 @@ pub trait EthEngine: Engine<::machine::EthereumMachine> {
 		self.machine().verify_transaction_unordered(t, header)
 	}
 
+	/// Verify the transactions of a block like `verify_transaction_unordered`, all at once so that
+	/// their signatures are recovered in a single batch. Results are in the order of `ts`.
+	///
+	/// NOTE Engines overriding `verify_transaction_unordered` must override this as well.
+	fn verify_transactions_unordered(&self, ts: Vec<UnverifiedTransaction>, header: &Header) -> Vec<Result<SignedTransaction, transaction::Error>> {
+		self.machine().verify_transactions_unordered(ts, header)
+	}
+
 	/// Perform basic/cheap transaction verification.
 	///
 	/// This should include all cheap checks that can be done before
@@ -630,6 +630,63 @@ mod tests {
 		assert_eq!(b.state().balance(&uncle_author).unwrap(), "3cb71f51fc558000".into());
 	}
 
+	#[test]
+	fn on_close_block_traces_rewards_of_two_uncles() {
+		use trace::{RewardType, Tracing};
+		use trace::trace::Action;
+
+		let spec = test_spec();
+		let engine = &*spec.engine;
+		let genesis_header = spec.genesis_header();
+		let db = spec.ensure_db_good(get_temp_state_db(), &Default::default()).unwrap();
+		let last_hashes = Arc::new(vec![genesis_header.hash()]);
+		let author: Address = "0000000000000000000000000000000000000aaa".into();
+		let mut b = OpenBlock::new(engine, Default::default(), true, db, &genesis_header, last_hashes, author, (3141562.into(), 31415620.into()), vec![], false, &mut Vec::new().into_iter()).unwrap();
+		let uncle_authors: Vec<Address> = vec![
+			"ef2d6d194084c2de36e0dabfce45d046b37d1106".into(),
+			"ef2d6d194084c2de36e0dabfce45d046b37d1107".into(),
+		];
+		for uncle_author in &uncle_authors {
+			let mut uncle = Header::new();
+			uncle.set_author(*uncle_author);
+			b.push_uncle(uncle).unwrap();
+		}
+
+		let b = b.close().unwrap();
+		// uncles at depth `block - uncle` get `(8 - depth) / 8` of the block reward, the author `1 / 32` per uncle on top.
+		let block_reward = U256::from_str("4563918244f40000").unwrap();
+		let depth = U256::from(b.header().number() - Header::new().number());
+		let uncle_reward = block_reward * (U256::from(8) - depth) / U256::from(8);
+		let author_reward = block_reward + block_reward / U256::from(32) * U256::from(uncle_authors.len());
+		let traces = match b.block().traces {
+			Tracing::Enabled(ref traces) => traces.clone(),
+			Tracing::Disabled => panic!("Tracing is enabled"),
+		};
+
+		let mut traced = BTreeMap::new();
+		for trace in traces.into_iter().flat_map(|tx_traces| Into::<Vec<_>>::into(tx_traces)) {
+			match trace.action {
+				Action::Reward(reward) => {
+					let expected_type = if reward.author == author { RewardType::Block } else { RewardType::Uncle };
+					assert_eq!(reward.reward_type, expected_type);
+					*traced.entry(reward.author).or_insert_with(U256::zero) += reward.value;
+				},
+				action => panic!("Unexpected trace {:?}", action),
+			}
+		}
+
+		assert_eq!(traced.len(), 3);
+		assert_eq!(traced[&author], author_reward);
+		for uncle_author in &uncle_authors {
+			assert_eq!(traced[uncle_author], uncle_reward);
+		}
+
+		// every account started out empty, so traced rewards add up to the balances.
+		for (address, value) in traced {
+			assert_eq!(b.state().balance(&address).unwrap(), value);
+		}
+	}
+
 	#[test]
 	fn has_valid_mcip3_era_block_rewards() {
 		let spec = new_mcip3_test();
@@ -69,6 +69,13 @@ pub struct Executed<T = FlatTrace, V = VMTrace> {
 	pub state_diff: Option<StateDiff>,
 }
 
+impl<T, V> Executed<T, V> {
+	/// Gas used by execution before refunds were applied, `gas_used` being what's left after.
+	pub fn gas_used_before_refund(&self) -> U256 {
+		self.gas_used + self.refunded
+	}
+}
+
 /// Result of executing the transaction.
 #[derive(PartialEq, Debug, Clone)]
 pub enum ExecutionError {
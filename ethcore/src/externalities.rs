@@ -121,6 +121,10 @@ impl<'a, T: 'a, V: 'a, B: 'a> Ext for Externalities<'a, T, V, B>
 		self.state.storage_at(&self.origin_info.address, key).map_err(Into::into)
 	}
 
+	fn storage_read(&self, key: &H256) -> vm::Result<H256> {
+		self.state.storage_read(&self.origin_info.address, key).map_err(Into::into)
+	}
+
 	fn set_storage(&mut self, key: H256, value: H256) -> vm::Result<()> {
 		if self.static_flag {
 			Err(vm::Error::MutableCallInStaticContext)
@@ -396,10 +400,12 @@ impl<'a, T: 'a, V: 'a, B: 'a> Ext for Externalities<'a, T, V, B>
 
 	fn add_sstore_refund(&mut self, value: U256) {
 		self.substate.sstore_clears_refund = self.substate.sstore_clears_refund.saturating_add(value);
+		self.vm_tracer.trace_refund(self.substate.sstore_clears_refund);
 	}
 
 	fn sub_sstore_refund(&mut self, value: U256) {
 		self.substate.sstore_clears_refund = self.substate.sstore_clears_refund.saturating_sub(value);
+		self.vm_tracer.trace_refund(self.substate.sstore_clears_refund);
 	}
 
 	fn trace_next_instruction(&mut self, pc: usize, instruction: u8, current_gas: U256) -> bool {
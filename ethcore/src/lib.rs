@@ -82,6 +82,7 @@ extern crate itertools;
 extern crate kvdb;
 extern crate kvdb_memorydb;
 extern crate kvdb_rocksdb;
+extern crate libc;
 extern crate lru_cache;
 extern crate num_cpus;
 extern crate num;
@@ -105,6 +106,7 @@ extern crate ethabi;
 extern crate rustc_hex;
 extern crate stats;
 extern crate stop_guard;
+extern crate tar;
 extern crate using_queue;
 extern crate vm;
 extern crate wasm;
@@ -112,6 +114,8 @@ extern crate memory_cache;
 extern crate journaldb;
 #[cfg(any(test, feature = "json-tests", feature = "test-helpers"))]
 extern crate tempdir;
+#[cfg(feature = "snapshot-zstd")]
+extern crate zstd;
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows", target_os = "android"))]
 extern crate hardware_wallet;
@@ -347,6 +347,11 @@ impl EthereumMachine {
 		Ok(SignedTransaction::new(t)?)
 	}
 
+	/// Verify transactions are valid, regardless of order, recovering all their senders at once.
+	pub fn verify_transactions_unordered(&self, ts: Vec<UnverifiedTransaction>, _header: &Header) -> Vec<Result<SignedTransaction, transaction::Error>> {
+		SignedTransaction::new_batch(ts).into_iter().map(|t| Ok(t?)).collect()
+	}
+
 	/// Does basic verification of the transaction.
 	pub fn verify_transaction_basic(&self, t: &UnverifiedTransaction, header: &Header) -> Result<(), transaction::Error> {
 		let check_low_s = match self.ethash_extensions {
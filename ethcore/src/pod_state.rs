@@ -80,7 +80,8 @@ pub fn diff_pod(pre: &PodState, post: &PodState) -> StateDiff {
 		raw: pre.get().keys()
 			.merge(post.get().keys())
 			.filter_map(|acc| pod_account::diff_pod(pre.get().get(acc), post.get().get(acc)).map(|d| (acc.clone(), d)))
-			.collect()
+			.collect(),
+		reads: BTreeMap::new(),
 	}
 }
 
@@ -109,7 +110,7 @@ mod test {
 				code: Diff::Died(vec![]),
 				storage: map![],
 			}
-		]});
+		], reads: BTreeMap::new() });
 		assert_eq!(super::diff_pod(&PodState::new(), &a), StateDiff{ raw: map![
 			1.into() => AccountDiff{
 				balance: Diff::Born(69.into()),
@@ -117,7 +118,7 @@ mod test {
 				code: Diff::Born(vec![]),
 				storage: map![],
 			}
-		]});
+		], reads: BTreeMap::new() });
 	}
 
 	#[test]
@@ -151,7 +152,7 @@ mod test {
 				code: Diff::Born(vec![]),
 				storage: map![],
 			}
-		]});
+		], reads: BTreeMap::new() });
 		assert_eq!(super::diff_pod(&b, &a), StateDiff { raw: map![
 			2.into() => AccountDiff{
 				balance: Diff::Died(69.into()),
@@ -159,7 +160,7 @@ mod test {
 				code: Diff::Died(vec![]),
 				storage: map![],
 			}
-		]});
+		], reads: BTreeMap::new() });
 	}
 
 	#[test]
@@ -199,7 +200,7 @@ mod test {
 				code: Diff::Same,
 				storage: map![],
 			}
-		]});
+		], reads: BTreeMap::new() });
 	}
 
 }
@@ -0,0 +1,201 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compression of snapshot chunks.
+//!
+//! Snappy is always available, Zstandard requires the `snapshot-zstd` feature.
+
+use bytes::Bytes;
+use snappy;
+
+use super::{Error, MAX_CHUNK_SIZE};
+
+pub use types::snapshot_manifest::{Compression, COMPRESSION_VERSION};
+
+// Zstandard level used for snapshot chunks, favouring ratio over speed.
+#[cfg(feature = "snapshot-zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// Whether chunks compressed with `compression` can be read and written.
+pub fn is_supported(compression: Compression) -> bool {
+	match compression {
+		Compression::None | Compression::Snappy => true,
+		Compression::Zstd => cfg!(feature = "snapshot-zstd"),
+	}
+}
+
+/// Manifest version of a snapshot with chunks of `chunk_version`, compressed with `compression`.
+///
+/// Snappy-compressed snapshots keep the version of their chunks, so older clients can still
+/// read them. Any other compression needs a manifest recording it, which older clients reject
+/// as an unsupported version.
+pub fn manifest_version(chunk_version: u64, compression: Compression) -> u64 {
+	match compression {
+		Compression::Snappy => chunk_version,
+		_ => ::std::cmp::max(chunk_version, COMPRESSION_VERSION),
+	}
+}
+
+/// Compresses and decompresses chunks, reusing its buffer across chunks.
+pub struct Compressor {
+	compression: Compression,
+	buffer: Bytes,
+}
+
+impl Compressor {
+	/// Create a new compressor for the given compression.
+	pub fn new(compression: Compression) -> Self {
+		Compressor {
+			compression: compression,
+			buffer: Vec::new(),
+		}
+	}
+
+	/// Get the compression used.
+	pub fn compression(&self) -> Compression {
+		self.compression
+	}
+
+	/// Compress a raw chunk.
+	pub fn compress(&mut self, raw: &[u8]) -> Result<&[u8], Error> {
+		let len = match self.compression {
+			Compression::None => {
+				self.buffer.clear();
+				self.buffer.extend_from_slice(raw);
+				raw.len()
+			},
+			Compression::Snappy => {
+				let max_len = snappy::max_compressed_len(raw.len());
+				if self.buffer.len() < max_len {
+					self.buffer.resize(max_len, 0);
+				}
+				snappy::compress_into(raw, &mut self.buffer)
+			},
+			Compression::Zstd => {
+				self.buffer = zstd_compress(raw)?;
+				self.buffer.len()
+			},
+		};
+
+		Ok(&self.buffer[..len])
+	}
+
+	/// Decompress a chunk, failing if it would exceed `MAX_CHUNK_SIZE`.
+	pub fn decompress(&mut self, chunk: &[u8]) -> Result<&[u8], ::error::Error> {
+		let len = match self.compression {
+			Compression::None => {
+				check_size(chunk.len())?;
+				self.buffer.clear();
+				self.buffer.extend_from_slice(chunk);
+				chunk.len()
+			},
+			Compression::Snappy => {
+				check_size(snappy::decompressed_len(chunk)?)?;
+				snappy::decompress_into(chunk, &mut self.buffer)?
+			},
+			Compression::Zstd => {
+				zstd_decompress(chunk, &mut self.buffer)?;
+				self.buffer.len()
+			},
+		};
+
+		Ok(&self.buffer[..len])
+	}
+}
+
+fn check_size(len: usize) -> Result<(), Error> {
+	if len > MAX_CHUNK_SIZE {
+		trace!(target: "snapshot", "Discarding large chunk: {} vs {}", len, MAX_CHUNK_SIZE);
+		return Err(Error::ChunkTooLarge);
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "snapshot-zstd")]
+fn zstd_compress(raw: &[u8]) -> Result<Bytes, Error> {
+	Ok(::zstd::stream::encode_all(raw, ZSTD_LEVEL)?)
+}
+
+#[cfg(feature = "snapshot-zstd")]
+fn zstd_decompress(chunk: &[u8], buffer: &mut Bytes) -> Result<(), Error> {
+	use std::io::Read;
+
+	buffer.clear();
+	// read one byte past the limit to tell oversized chunks apart.
+	::zstd::stream::Decoder::new(chunk)?
+		.take(MAX_CHUNK_SIZE as u64 + 1)
+		.read_to_end(buffer)?;
+	check_size(buffer.len())
+}
+
+#[cfg(not(feature = "snapshot-zstd"))]
+fn zstd_compress(_raw: &[u8]) -> Result<Bytes, Error> {
+	Err(Error::CompressionNotSupported(Compression::Zstd))
+}
+
+#[cfg(not(feature = "snapshot-zstd"))]
+fn zstd_decompress(_chunk: &[u8], _buffer: &mut Bytes) -> Result<(), Error> {
+	Err(Error::CompressionNotSupported(Compression::Zstd))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Compression, Compressor, MAX_CHUNK_SIZE};
+
+	fn round_trip(compression: Compression) {
+		let raw: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+		let mut compressor = Compressor::new(compression);
+
+		let compressed = compressor.compress(&raw).unwrap().to_vec();
+		if compression != Compression::None {
+			assert!(compressed.len() < raw.len());
+		}
+
+		assert_eq!(compressor.decompress(&compressed).unwrap(), &raw[..]);
+	}
+
+	#[test]
+	fn round_trips_uncompressed() {
+		round_trip(Compression::None);
+	}
+
+	#[test]
+	fn round_trips_snappy() {
+		round_trip(Compression::Snappy);
+	}
+
+	#[cfg(feature = "snapshot-zstd")]
+	#[test]
+	fn round_trips_zstd() {
+		round_trip(Compression::Zstd);
+	}
+
+	#[cfg(not(feature = "snapshot-zstd"))]
+	#[test]
+	fn rejects_zstd_without_feature() {
+		assert!(!super::is_supported(Compression::Zstd));
+		assert!(Compressor::new(Compression::Zstd).compress(&[1, 2, 3]).is_err());
+	}
+
+	#[test]
+	fn rejects_oversized_chunks() {
+		let raw = vec![0u8; MAX_CHUNK_SIZE + 1];
+		let mut compressor = Compressor::new(Compression::Snappy);
+		let compressed = compressor.compress(&raw).unwrap().to_vec();
+		assert!(compressor.decompress(&compressed).is_err());
+	}
+}
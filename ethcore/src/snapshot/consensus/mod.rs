@@ -32,6 +32,15 @@ mod work;
 pub use self::authority::*;
 pub use self::work::*;
 
+/// Blocks linked by a secondary chunk, as decoded by `SnapshotComponents::block_range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRange {
+	/// Number and hash of the parent of the first block in the chunk.
+	pub parent: (u64, H256),
+	/// Number and hash of the last block in the chunk.
+	pub last: (u64, H256),
+}
+
 /// A sink for produced chunks.
 pub type ChunkSink<'a> = FnMut(&[u8]) -> ::std::io::Result<()> + 'a;
 
@@ -71,6 +80,13 @@ pub trait SnapshotComponents: Send {
 
 	/// Current version number
 	fn current_version(&self) -> u64;
+
+	/// Decode an uncompressed secondary chunk without importing it, returning the blocks it links.
+	///
+	/// `None` if the chunks of this engine don't form a chain of blocks.
+	fn block_range(&self, _chunk: &[u8]) -> Result<Option<BlockRange>, ::error::Error> {
+		Ok(None)
+	}
 }
 
 /// Restore from secondary snapshot chunks.
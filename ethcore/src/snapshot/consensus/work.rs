@@ -20,7 +20,7 @@
 //! The secondary chunks in this instance are 30,000 "abridged blocks" from the head
 //! of the chain, which serve as an indication of valid chain.
 
-use super::{SnapshotComponents, Rebuilder, ChunkSink};
+use super::{SnapshotComponents, Rebuilder, ChunkSink, BlockRange};
 
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -87,6 +87,30 @@ impl SnapshotComponents for PowSnapshot {
 
 	fn min_supported_version(&self) -> u64 { ::snapshot::MIN_SUPPORTED_STATE_CHUNK_VERSION }
 	fn current_version(&self) -> u64 { ::snapshot::STATE_CHUNK_VERSION }
+
+	fn block_range(&self, chunk: &[u8]) -> Result<Option<BlockRange>, ::error::Error> {
+		use triehash::ordered_trie_root;
+
+		let rlp = Rlp::new(chunk);
+		let item_count = rlp.item_count()?;
+		if item_count <= 3 {
+			return Err(Error::WrongChunkFormat("Block chunk without blocks".into()).into());
+		}
+
+		let parent = (rlp.val_at::<u64>(0)?, rlp.val_at::<H256>(1)?);
+		let mut last = parent;
+		for idx in 3..item_count {
+			let pair = rlp.at(idx)?;
+			let abridged_block = AbridgedBlock::from_raw(pair.at(0)?.as_raw().to_owned());
+			let _: Vec<::receipt::Receipt> = pair.list_at(1)?;
+			let receipts_root = ordered_trie_root(pair.at(1)?.iter().map(|r| r.as_raw()));
+
+			let block = abridged_block.to_block(last.1, last.0 + 1, receipts_root)?;
+			last = (last.0 + 1, block.header.hash());
+		}
+
+		Ok(Some(BlockRange { parent, last }))
+	}
 }
 
 /// Used to build block chunks.
@@ -24,6 +24,8 @@ use ethereum_types::H256;
 use ethtrie::TrieError;
 use rlp::DecoderError;
 
+use super::compression::Compression;
+
 /// Snapshot-related errors.
 #[derive(Debug)]
 pub enum Error {
@@ -65,6 +67,12 @@ pub enum Error {
 	BadEpochProof(u64),
 	/// Wrong chunk format.
 	WrongChunkFormat(String),
+	/// Restoration journal refers to a chunk which is missing or corrupt.
+	MissingJournalChunk(H256),
+	/// Chunk compression is not supported by this build.
+	CompressionNotSupported(Compression),
+	/// Chunk of a written snapshot is missing or doesn't match its hash.
+	CorruptChunk(H256),
 }
 
 impl fmt::Display for Error {
@@ -91,6 +99,9 @@ impl fmt::Display for Error {
 			Error::SnapshotsUnsupported => write!(f, "Snapshots unsupported by consensus engine."),
 			Error::BadEpochProof(i) => write!(f, "Bad epoch proof for transition to epoch {}", i),
 			Error::WrongChunkFormat(ref msg) => write!(f, "Wrong chunk format: {}", msg),
+			Error::MissingJournalChunk(ref hash) => write!(f, "Restoration journal chunk {:?} is missing or corrupt", hash),
+			Error::CompressionNotSupported(ref compression) => write!(f, "Snapshot compression {:?} is not supported.", compression),
+			Error::CorruptChunk(ref hash) => write!(f, "Snapshot chunk {:?} is missing or corrupt.", hash),
 		}
 	}
 }
@@ -18,7 +18,8 @@
 //! Ways of writing and reading snapshots. This module supports writing and reading
 //! snapshots of two different formats: packed and loose.
 //! Packed snapshots are written to a single file, and loose snapshots are
-//! written to multiple files in one directory.
+//! written to multiple files in one directory. Loose snapshots can also be
+//! read from a tar archive of their directory.
 
 use std::collections::HashMap;
 use std::io::{self, Read, Seek, SeekFrom, Write};
@@ -28,8 +29,10 @@ use std::path::{Path, PathBuf};
 use bytes::Bytes;
 use ethereum_types::H256;
 use rlp::{RlpStream, Rlp};
+use tar::{Archive, EntryType};
 
 use super::ManifestData;
+use super::compression::{self, Compression, COMPRESSION_VERSION};
 
 const SNAPSHOT_VERSION: u64 = 2;
 
@@ -46,6 +49,9 @@ pub trait SnapshotWriter {
 	/// Complete writing. The manifest's chunk lists must be consistent
 	/// with the chunks written.
 	fn finish(self, manifest: ManifestData) -> io::Result<()> where Self: Sized;
+
+	/// Compression the chunks should be written with.
+	fn compression(&self) -> Compression { Compression::Snappy }
 }
 
 // (hash, len, offset)
@@ -67,6 +73,7 @@ pub struct PackedWriter {
 	state_hashes: Vec<ChunkInfo>,
 	block_hashes: Vec<ChunkInfo>,
 	cur_len: u64,
+	compression: Compression,
 }
 
 impl PackedWriter {
@@ -77,8 +84,15 @@ impl PackedWriter {
 			state_hashes: Vec::new(),
 			block_hashes: Vec::new(),
 			cur_len: 0,
+			compression: Compression::Snappy,
 		})
 	}
+
+	/// Compress the chunks with the given compression, snappy by default.
+	pub fn with_compression(mut self, compression: Compression) -> Self {
+		self.compression = compression;
+		self
+	}
 }
 
 impl SnapshotWriter for PackedWriter {
@@ -105,15 +119,20 @@ impl SnapshotWriter for PackedWriter {
 	fn finish(mut self, manifest: ManifestData) -> io::Result<()> {
 		// we ignore the hashes fields of the manifest under the assumption that
 		// they are consistent with ours.
-		let mut stream = RlpStream::new_list(6);
+		let version = compression::manifest_version(SNAPSHOT_VERSION, self.compression);
+		let mut stream = RlpStream::new_list(if version >= COMPRESSION_VERSION { 7 } else { 6 });
 		stream
-			.append(&SNAPSHOT_VERSION)
+			.append(&version)
 			.append_list(&self.state_hashes)
 			.append_list(&self.block_hashes)
 			.append(&manifest.state_root)
 			.append(&manifest.block_number)
 			.append(&manifest.block_hash);
 
+		if version >= COMPRESSION_VERSION {
+			stream.append(&self.compression);
+		}
+
 		let manifest_rlp = stream.out();
 
 		self.file.write_all(&manifest_rlp)?;
@@ -136,11 +155,16 @@ impl SnapshotWriter for PackedWriter {
 
 		Ok(())
 	}
+
+	fn compression(&self) -> Compression {
+		self.compression
+	}
 }
 
 /// A "loose" writer writes chunk files into a directory.
 pub struct LooseWriter {
 	dir: PathBuf,
+	compression: Compression,
 }
 
 impl LooseWriter {
@@ -151,9 +175,16 @@ impl LooseWriter {
 
 		Ok(LooseWriter {
 			dir: path,
+			compression: Compression::Snappy,
 		})
 	}
 
+	/// Compress the chunks with the given compression, snappy by default.
+	pub fn with_compression(mut self, compression: Compression) -> Self {
+		self.compression = compression;
+		self
+	}
+
 	// writing logic is the same for both kinds of chunks.
 	fn write_chunk(&mut self, hash: H256, chunk: &[u8]) -> io::Result<()> {
 		let file_path = self.dir.join(format!("{:x}", hash));
@@ -182,6 +213,10 @@ impl SnapshotWriter for LooseWriter {
 
 		Ok(())
 	}
+
+	fn compression(&self) -> Compression {
+		self.compression
+	}
 }
 
 /// Something which can read compressed snapshots.
@@ -245,10 +280,20 @@ impl PackedReader {
 			(1, rlp.val_at(0)?)
 		};
 
-		if version > SNAPSHOT_VERSION {
+		if version > COMPRESSION_VERSION {
 			return Err(::snapshot::error::Error::VersionNotSupported(version));
 		}
 
+		let compression = if version >= COMPRESSION_VERSION {
+			rlp.val_at(5 + start)?
+		} else {
+			Compression::Snappy
+		};
+
+		if !compression::is_supported(compression) {
+			return Err(::snapshot::error::Error::CompressionNotSupported(compression));
+		}
+
 		let state: Vec<ChunkInfo> = rlp.list_at(0 + start)?;
 		let blocks: Vec<ChunkInfo> = rlp.list_at(1 + start)?;
 
@@ -259,6 +304,7 @@ impl PackedReader {
 			state_root: rlp.val_at(2 + start)?,
 			block_number: rlp.val_at(3 + start)?,
 			block_hash: rlp.val_at(4 + start)?,
+			compression: compression,
 		};
 
 		Ok(Some(PackedReader {
@@ -331,13 +377,102 @@ impl SnapshotReader for LooseReader {
 	}
 }
 
+/// Reader for "loose" snapshots packed into a tar archive, e.g. of a snapshot directory
+/// copied from another node. Entries may be nested in a directory of the archive.
+pub struct TarReader {
+	file: File,
+	chunks: HashMap<H256, (u64, u64)>, // len, offset
+	manifest: ManifestData,
+}
+
+impl TarReader {
+	/// Create a new `TarReader` for the archive at the given path.
+	/// Returns `None` if the file is not a tar archive, fails if it
+	/// is one but doesn't hold a manifest.
+	pub fn new(path: &Path) -> Result<Option<Self>, ::error::Error> {
+		let mut file = File::open(path)?;
+
+		// ustar magic of the first header.
+		let mut header = [0u8; 512];
+		if file.read_exact(&mut header).is_err() || &header[257..262] != b"ustar" {
+			return Ok(None);
+		}
+		file.seek(SeekFrom::Start(0))?;
+
+		let mut manifest = None;
+		let mut chunks = HashMap::new();
+		for entry in Archive::new(&file).entries()? {
+			let mut entry = entry?;
+			if entry.header().entry_type() != EntryType::Regular { continue }
+
+			let name = match entry.path()?.file_name().and_then(|name| name.to_str()) {
+				Some(name) => name.to_owned(),
+				None => continue,
+			};
+
+			if name == "MANIFEST" {
+				let mut manifest_buf = Vec::new();
+				entry.read_to_end(&mut manifest_buf)?;
+				manifest = Some(ManifestData::from_rlp(&manifest_buf)?);
+			} else if let Ok(hash) = name.parse::<H256>() {
+				chunks.insert(hash, (entry.header().entry_size()?, entry.raw_file_position()));
+			}
+		}
+
+		let manifest = manifest.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No MANIFEST in snapshot archive"))?;
+
+		Ok(Some(TarReader {
+			file: file,
+			chunks: chunks,
+			manifest: manifest,
+		}))
+	}
+}
+
+impl SnapshotReader for TarReader {
+	fn manifest(&self) -> &ManifestData {
+		&self.manifest
+	}
+
+	fn chunk(&self, hash: H256) -> io::Result<Bytes> {
+		let &(len, off) = self.chunks.get(&hash)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Chunk {:x} not in snapshot archive", hash)))?;
+
+		let mut file = &self.file;
+
+		file.seek(SeekFrom::Start(off))?;
+		let mut buf = vec![0; len as usize];
+
+		file.read_exact(&mut buf[..])?;
+
+		Ok(buf)
+	}
+}
+
+/// Open the snapshot at the given path: a directory of loose chunks,
+/// a tar archive of such a directory or a packed snapshot file.
+pub fn open_reader(path: &Path) -> Result<Box<SnapshotReader>, ::error::Error> {
+	if path.is_dir() {
+		return Ok(Box::new(LooseReader::new(path.to_owned())?));
+	}
+
+	if let Some(reader) = TarReader::new(path)? {
+		return Ok(Box::new(reader));
+	}
+
+	match PackedReader::new(path)? {
+		Some(reader) => Ok(Box::new(reader)),
+		None => Err(io::Error::new(io::ErrorKind::InvalidData, "Not a snapshot").into()),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use tempdir::TempDir;
 	use hash::keccak;
 
-	use snapshot::ManifestData;
-	use super::{SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, SNAPSHOT_VERSION};
+	use snapshot::{Compression, ManifestData};
+	use super::{COMPRESSION_VERSION, SnapshotWriter, SnapshotReader, PackedWriter, PackedReader, LooseWriter, LooseReader, TarReader, SNAPSHOT_VERSION};
 
 	const STATE_CHUNKS: &'static [&'static [u8]] = &[b"dog", b"cat", b"hello world", b"hi", b"notarealchunk"];
 	const BLOCK_CHUNKS: &'static [&'static [u8]] = &[b"hello!", b"goodbye!", b"abcdefg", b"hijklmnop", b"qrstuvwxy", b"and", b"z"];
@@ -370,6 +505,7 @@ mod tests {
 			state_root: keccak(b"notarealroot"),
 			block_number: 12345678987654321,
 			block_hash: keccak(b"notarealblock"),
+			compression: Compression::Snappy,
 		};
 
 		writer.finish(manifest.clone()).unwrap();
@@ -409,6 +545,7 @@ mod tests {
 			state_root: keccak(b"notarealroot"),
 			block_number: 12345678987654321,
 			block_hash: keccak(b"notarealblock)"),
+			compression: Compression::Snappy,
 		};
 
 		writer.finish(manifest.clone()).unwrap();
@@ -420,4 +557,74 @@ mod tests {
 			reader.chunk(hash.clone()).unwrap();
 		}
 	}
+
+	#[test]
+	fn tar_read() {
+		use std::fs::File;
+		use tar::Builder;
+
+		let tempdir = TempDir::new("").unwrap();
+		let dir = tempdir.path().join("snapshot");
+		let mut writer = LooseWriter::new(dir.clone()).unwrap();
+
+		let mut state_hashes = Vec::new();
+		for chunk in STATE_CHUNKS {
+			let hash = keccak(&chunk);
+			state_hashes.push(hash);
+			writer.write_state_chunk(hash, chunk).unwrap();
+		}
+
+		let manifest = ManifestData {
+			version: SNAPSHOT_VERSION,
+			state_hashes: state_hashes,
+			block_hashes: Vec::new(),
+			state_root: keccak(b"notarealroot"),
+			block_number: 12345678987654321,
+			block_hash: keccak(b"notarealblock"),
+			compression: Compression::Snappy,
+		};
+
+		writer.finish(manifest.clone()).unwrap();
+
+		let path = tempdir.path().join("snapshot.tar");
+		let mut builder = Builder::new(File::create(&path).unwrap());
+		builder.append_dir_all("snapshot", &dir).unwrap();
+		builder.finish().unwrap();
+
+		let reader = TarReader::new(&path).unwrap().unwrap();
+		assert_eq!(reader.manifest(), &manifest);
+
+		for (hash, chunk) in manifest.state_hashes.iter().zip(STATE_CHUNKS) {
+			assert_eq!(&reader.chunk(*hash).unwrap()[..], *chunk);
+		}
+
+		assert!(reader.chunk(keccak(b"notachunk")).is_err());
+		assert!(TarReader::new(&dir.join("MANIFEST")).unwrap().is_none());
+	}
+
+	#[test]
+	fn packed_records_compression() {
+		let tempdir = TempDir::new("").unwrap();
+		let path = tempdir.path().join("packed");
+		let mut writer = PackedWriter::new(&path).unwrap().with_compression(Compression::None);
+
+		let hash = keccak(STATE_CHUNKS[0]);
+		writer.write_state_chunk(hash, STATE_CHUNKS[0]).unwrap();
+
+		let manifest = ManifestData {
+			version: COMPRESSION_VERSION,
+			state_hashes: vec![hash],
+			block_hashes: Vec::new(),
+			state_root: keccak(b"notarealroot"),
+			block_number: 12345678987654321,
+			block_hash: keccak(b"notarealblock"),
+			compression: Compression::None,
+		};
+
+		writer.finish(manifest.clone()).unwrap();
+
+		let reader = PackedReader::new(&path).unwrap().unwrap();
+		assert_eq!(reader.manifest(), &manifest);
+		assert_eq!(&reader.chunk(hash).unwrap()[..], STATE_CHUNKS[0]);
+	}
 }
@@ -34,7 +34,6 @@ use ethereum_types::{H256, U256};
 use hashdb::HashDB;
 use keccak_hasher::KeccakHasher;
 use kvdb::DBValue;
-use snappy;
 use bytes::Bytes;
 use parking_lot::Mutex;
 use journaldb::{self, Algorithm, JournalDB};
@@ -44,6 +43,7 @@ use ethtrie::{TrieDB, TrieDBMut};
 use rlp::{RlpStream, Rlp};
 use bloom_journal::Bloom;
 
+use self::compression::Compressor;
 use self::io::SnapshotWriter;
 
 use super::state_db::StateDB;
@@ -52,16 +52,20 @@ use super::state::Account as StateAccount;
 use crossbeam::scope;
 use rand::{Rng, OsRng};
 
+pub use self::compression::Compression;
 pub use self::error::Error;
 
 pub use self::consensus::*;
-pub use self::service::{Service, DatabaseRestore};
+pub use self::service::{Service, DatabaseRestore, RetentionPolicy};
 pub use self::traits::SnapshotService;
+pub use self::verification::{ChunkFailure, ChunkKind, VerificationReport};
+pub use self::throttle::{BusySignal, Throttle, ThrottleState};
 pub use self::watcher::Watcher;
 pub use types::snapshot_manifest::ManifestData;
 pub use types::restoration_status::RestorationStatus;
 pub use types::basic_account::BasicAccount;
 
+pub mod compression;
 pub mod io;
 pub mod service;
 
@@ -69,6 +73,8 @@ mod account;
 mod block;
 mod consensus;
 mod error;
+mod throttle;
+mod verification;
 mod watcher;
 
 #[cfg(test)]
@@ -96,6 +102,7 @@ pub struct Progress {
 	blocks: AtomicUsize,
 	size: AtomicUsize, // Todo [rob] use Atomicu64 when it stabilizes.
 	done: AtomicBool,
+	throttle: Throttle,
 }
 
 impl Progress {
@@ -104,6 +111,7 @@ impl Progress {
 		self.accounts.store(0, Ordering::Release);
 		self.blocks.store(0, Ordering::Release);
 		self.size.store(0, Ordering::Release);
+		self.throttle.reset();
 
 		// atomic fence here to ensure the others are written first?
 		// logs might very rarely get polluted if not.
@@ -122,7 +130,23 @@ impl Progress {
 	/// Whether the snapshot is complete.
 	pub fn done(&self) -> bool  { self.done.load(Ordering::Acquire) }
 
+	/// Get the throttle limiting the reads of the snapshot.
+	pub fn throttle(&self) -> &Throttle { &self.throttle }
 }
+
+/// Status of a snapshot being taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreationStatus {
+	/// Number of accounts snapshotted thus far.
+	pub accounts: usize,
+	/// Number of blocks snapshotted thus far.
+	pub blocks: usize,
+	/// Written size of the snapshot in bytes.
+	pub size: usize,
+	/// Current state of the throttle.
+	pub throttle: ThrottleState,
+}
+
 /// Take a snapshot using the given blockchain, starting block hash, and database, writing into the given writer.
 pub fn take_snapshot<W: SnapshotWriter + Send>(
 	engine: &EthEngine,
@@ -139,6 +163,7 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 
 	info!("Taking snapshot starting at block {}", number);
 
+	let compression = writer.compression();
 	let writer = Mutex::new(writer);
 	let chunker = engine.snapshot_components().ok_or(Error::SnapshotsUnsupported)?;
 	let snapshot_version = chunker.current_version();
@@ -155,12 +180,13 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 	info!("produced {} state chunks and {} block chunks.", state_hashes.len(), block_hashes.len());
 
 	let manifest_data = ManifestData {
-		version: snapshot_version,
+		version: compression::manifest_version(snapshot_version, compression),
 		state_hashes: state_hashes,
 		block_hashes: block_hashes,
 		state_root: state_root,
 		block_number: number,
 		block_hash: block_at,
+		compression: compression,
 	};
 
 	writer.into_inner().finish(manifest_data)?;
@@ -178,21 +204,22 @@ pub fn take_snapshot<W: SnapshotWriter + Send>(
 /// Returns a list of chunk hashes, with the first having the blocks furthest from the genesis.
 pub fn chunk_secondary<'a>(mut chunker: Box<SnapshotComponents>, chain: &'a BlockChain, start_hash: H256, writer: &Mutex<SnapshotWriter + 'a>, progress: &'a Progress) -> Result<Vec<H256>, Error> {
 	let mut chunk_hashes = Vec::new();
-	let mut snappy_buffer = vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)];
+	let mut compressor = Compressor::new(writer.lock().compression());
 
 	{
 		let mut chunk_sink = |raw_data: &[u8]| {
-			let compressed_size = snappy::compress_into(raw_data, &mut snappy_buffer);
-			let compressed = &snappy_buffer[..compressed_size];
+			let compressed = compressor.compress(raw_data)?;
 			let hash = keccak(&compressed);
 			let size = compressed.len();
 
+			progress.throttle.read(raw_data.len());
 			writer.lock().write_block_chunk(hash, compressed)?;
 			trace!(target: "snapshot", "wrote secondary chunk. hash: {:x}, size: {}, uncompressed size: {}",
 				hash, size, raw_data.len());
 
 			progress.size.fetch_add(size, Ordering::SeqCst);
 			chunk_hashes.push(hash);
+			progress.throttle.yield_if_busy();
 			Ok(())
 		};
 
@@ -212,7 +239,7 @@ struct StateChunker<'a> {
 	hashes: Vec<H256>,
 	rlps: Vec<Bytes>,
 	cur_size: usize,
-	snappy_buffer: Vec<u8>,
+	compressor: Compressor,
 	writer: &'a Mutex<SnapshotWriter + 'a>,
 	progress: &'a Progress,
 }
@@ -239,8 +266,8 @@ impl<'a> StateChunker<'a> {
 
 		let raw_data = stream.out();
 
-		let compressed_size = snappy::compress_into(&raw_data, &mut self.snappy_buffer);
-		let compressed = &self.snappy_buffer[..compressed_size];
+		let compressed = self.compressor.compress(&raw_data)?;
+		let compressed_size = compressed.len();
 		let hash = keccak(&compressed);
 
 		self.writer.lock().write_state_chunk(hash, compressed)?;
@@ -252,6 +279,9 @@ impl<'a> StateChunker<'a> {
 		self.hashes.push(hash);
 		self.cur_size = 0;
 
+		// give way to the client between chunks.
+		self.progress.throttle.yield_if_busy();
+
 		Ok(())
 	}
 
@@ -273,7 +303,7 @@ pub fn chunk_state<'a>(db: &HashDB<KeccakHasher>, root: &H256, writer: &Mutex<Sn
 		hashes: Vec::new(),
 		rlps: Vec::new(),
 		cur_size: 0,
-		snappy_buffer: vec![0; snappy::max_compressed_len(PREFERRED_CHUNK_SIZE)],
+		compressor: Compressor::new(writer.lock().compression()),
 		writer: writer,
 		progress: progress,
 	};
@@ -289,6 +319,7 @@ pub fn chunk_state<'a>(db: &HashDB<KeccakHasher>, root: &H256, writer: &Mutex<Sn
 		let account_db = AccountDB::from_hash(db, account_key_hash);
 
 		let fat_rlps = account::to_fat_rlps(&account_key_hash, &account, &account_db, &mut used_code, PREFERRED_CHUNK_SIZE - chunker.chunk_size(), PREFERRED_CHUNK_SIZE)?;
+		progress.throttle.read(fat_rlps.iter().map(|rlp| rlp.len()).sum());
 		for (i, fat_rlp) in fat_rlps.into_iter().enumerate() {
 			if i > 0 {
 				chunker.write_chunk()?;
@@ -17,14 +17,16 @@
 //! Snapshot network service implementation.
 
 use std::collections::HashSet;
-use std::io::{self, Read, ErrorKind};
+use std::io::{self, Read, Write, ErrorKind};
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-use super::{ManifestData, StateRebuilder, Rebuilder, RestorationStatus, SnapshotService, MAX_CHUNK_SIZE};
-use super::io::{SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
+use super::compression::{self, Compressor, COMPRESSION_VERSION};
+use super::{ManifestData, StateRebuilder, Rebuilder, RestorationStatus, CreationStatus, SnapshotService, Throttle, ThrottleState, VerificationReport};
+use super::verification;
+use super::io::{self as snapshot_io, SnapshotReader, LooseReader, SnapshotWriter, LooseWriter};
 
 use blockchain::{BlockChain, BlockChainDB, BlockChainDBHandler};
 use client::{Client, ChainInfo, ClientIoMessage};
@@ -40,7 +42,7 @@ use ethereum_types::H256;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use bytes::Bytes;
 use journaldb::Algorithm;
-use snappy;
+use rlp::{RlpStream, Rlp};
 
 /// Helper for removing directories in case of error.
 struct Guard(bool, PathBuf);
@@ -62,6 +64,48 @@ impl Drop for Guard {
 	}
 }
 
+/// Which completed snapshots are kept on disk, and when to skip taking one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+	/// Number of completed snapshots to keep, including the one being served.
+	pub keep: usize,
+	/// Minimum free space in bytes on the snapshot disk, below which snapshots are skipped.
+	pub min_free_space: u64,
+}
+
+impl Default for RetentionPolicy {
+	fn default() -> Self {
+		RetentionPolicy {
+			keep: 1,
+			min_free_space: 0,
+		}
+	}
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// Free space in bytes on the disk holding `path`.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+	use std::ffi::CString;
+	use std::os::unix::ffi::OsStrExt;
+
+	let path = CString::new(path.as_os_str().as_bytes())
+		.map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+	let mut stat: ::libc::statvfs = unsafe { ::std::mem::zeroed() };
+	if unsafe { ::libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Free space in bytes on the disk holding `path`.
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> io::Result<u64> {
+	Err(io::Error::new(ErrorKind::Other, "Free space check is not supported on this platform"))
+}
+
 /// External database restoration handler
 pub trait DatabaseRestore: Send + Sync {
 	/// Restart with a new backend. Takes ownership of passed database and moves it to a new location.
@@ -76,10 +120,12 @@ struct Restoration {
 	state: StateRebuilder,
 	secondary: Box<Rebuilder>,
 	writer: Option<LooseWriter>,
-	snappy_buffer: Bytes,
+	compressor: Compressor,
 	final_state_root: H256,
 	guard: Guard,
 	db: Arc<BlockChainDB>,
+	recover: bool,
+	journal: Option<PathBuf>,
 }
 
 struct RestorationParams<'a> {
@@ -90,6 +136,8 @@ struct RestorationParams<'a> {
 	genesis: &'a [u8], // genesis block of the chain.
 	guard: Guard, // guard for the restoration directory.
 	engine: &'a EthEngine,
+	recover: bool, // whether to keep the restored snapshot.
+	journal: Option<PathBuf>, // file to record restoration progress in.
 }
 
 impl Restoration {
@@ -117,24 +165,20 @@ impl Restoration {
 			state: StateRebuilder::new(raw_db.key_value().clone(), params.pruning),
 			secondary: secondary,
 			writer: params.writer,
-			snappy_buffer: Vec::new(),
+			compressor: Compressor::new(manifest.compression),
 			final_state_root: root,
 			guard: params.guard,
 			db: raw_db,
+			recover: params.recover,
+			journal: params.journal,
 		})
 	}
 
 	// feeds a state chunk, aborts early if `flag` becomes false.
 	fn feed_state(&mut self, hash: H256, chunk: &[u8], flag: &AtomicBool) -> Result<(), Error> {
 		if self.state_chunks_left.contains(&hash) {
-			let expected_len = snappy::decompressed_len(chunk)?;
-			if expected_len > MAX_CHUNK_SIZE {
-				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
-				return Err(::snapshot::Error::ChunkTooLarge.into());
-			}
-			let len = snappy::decompress_into(chunk, &mut self.snappy_buffer)?;
-
-			self.state.feed(&self.snappy_buffer[..len], flag)?;
+			let raw = self.compressor.decompress(chunk)?;
+			self.state.feed(raw, flag)?;
 
 			if let Some(ref mut writer) = self.writer.as_mut() {
 				writer.write_state_chunk(hash, chunk)?;
@@ -149,14 +193,8 @@ impl Restoration {
 	// feeds a block chunk
 	fn feed_blocks(&mut self, hash: H256, chunk: &[u8], engine: &EthEngine, flag: &AtomicBool) -> Result<(), Error> {
 		if self.block_chunks_left.contains(&hash) {
-			let expected_len = snappy::decompressed_len(chunk)?;
-			if expected_len > MAX_CHUNK_SIZE {
-				trace!(target: "snapshot", "Discarding large chunk: {} vs {}", expected_len, MAX_CHUNK_SIZE);
-				return Err(::snapshot::Error::ChunkTooLarge.into());
-			}
-			let len = snappy::decompress_into(chunk, &mut self.snappy_buffer)?;
-
-			self.secondary.feed(&self.snappy_buffer[..len], engine, flag)?;
+			let raw = self.compressor.decompress(chunk)?;
+			self.secondary.feed(raw, engine, flag)?;
 			if let Some(ref mut writer) = self.writer.as_mut() {
 				 writer.write_block_chunk(hash, chunk)?;
 			}
@@ -186,8 +224,10 @@ impl Restoration {
 		// connect out-of-order chunks and verify chain integrity.
 		self.secondary.finalize(engine)?;
 
-		if let Some(writer) = self.writer {
-			writer.finish(self.manifest)?;
+		if self.recover {
+			if let Some(writer) = self.writer {
+				writer.finish(self.manifest)?;
+			}
 		}
 
 		self.guard.disarm();
@@ -198,6 +238,40 @@ impl Restoration {
 	fn is_done(&self) -> bool {
 		self.block_chunks_left.is_empty() && self.state_chunks_left.is_empty()
 	}
+
+	// record the manifest and the chunks applied so far, so the restoration
+	// can be picked up again after a restart.
+	fn write_journal(&self) -> io::Result<()> {
+		let path = match self.journal {
+			Some(ref path) => path,
+			None => return Ok(()),
+		};
+
+		let state_done: Vec<_> = self.manifest.state_hashes.iter()
+			.filter(|h| !self.state_chunks_left.contains(h))
+			.cloned()
+			.collect();
+		let block_done: Vec<_> = self.manifest.block_hashes.iter()
+			.filter(|h| !self.block_chunks_left.contains(h))
+			.cloned()
+			.collect();
+
+		let mut stream = RlpStream::new_list(4);
+		stream
+			.append(&self.manifest.clone().into_rlp())
+			.append(&self.recover)
+			.append_list(&state_done)
+			.append_list(&block_done);
+
+		// write out-of-place so a crash never leaves a truncated journal behind.
+		let temp_path = path.with_extension("tmp");
+		{
+			let mut file = File::create(&temp_path)?;
+			file.write_all(&stream.out())?;
+			file.sync_all()?;
+		}
+		fs::rename(temp_path, path)
+	}
 }
 
 /// Type alias for client io channel.
@@ -240,6 +314,7 @@ pub struct Service {
 	progress: super::Progress,
 	taking_snapshot: AtomicBool,
 	restoring_snapshot: AtomicBool,
+	retention: RwLock<RetentionPolicy>,
 }
 
 impl Service {
@@ -261,6 +336,7 @@ impl Service {
 			progress: Default::default(),
 			taking_snapshot: AtomicBool::new(false),
 			restoring_snapshot: AtomicBool::new(false),
+			retention: RwLock::new(RetentionPolicy::default()),
 		};
 
 		// create the root snapshot dir if it doesn't exist.
@@ -287,6 +363,19 @@ impl Service {
 		let reader = LooseReader::new(service.snapshot_dir()).ok();
 		*service.reader.get_mut() = reader;
 
+		// pick up an interrupted restoration if its journal is intact,
+		// otherwise start over from a clean restoration dir.
+		if service.journal_path().exists() {
+			let resumed = service.read_journal()
+				.and_then(|(manifest, recover)| service.init_restore(manifest, recover));
+
+			if let Err(e) = resumed {
+				warn!("Discarding interrupted snapshot restoration: {}", e);
+				service.abort_restore();
+				let _ = fs::remove_dir_all(service.restoration_dir());
+			}
+		}
+
 		Ok(service)
 	}
 
@@ -304,6 +393,13 @@ impl Service {
 		dir
 	}
 
+	// get the directory of superseded snapshots kept by the retention policy.
+	fn history_dir(&self) -> PathBuf {
+		let mut dir = self.snapshot_root.clone();
+		dir.push("history");
+		dir
+	}
+
 	// get the restoration directory.
 	fn restoration_dir(&self) -> PathBuf {
 		let mut dir = self.snapshot_root.clone();
@@ -332,6 +428,46 @@ impl Service {
 		dir
 	}
 
+	// restoration journal path.
+	fn journal_path(&self) -> PathBuf {
+		let mut path = self.restoration_dir();
+		path.push("journal");
+		path
+	}
+
+	// read the journal of an interrupted restoration, checking that every
+	// chunk it records is still present and intact.
+	fn read_journal(&self) -> Result<(ManifestData, bool), Error> {
+		let mut raw = Vec::new();
+		File::open(self.journal_path())?.read_to_end(&mut raw)?;
+
+		let rlp = Rlp::new(&raw);
+		let manifest = ManifestData::from_rlp(&rlp.val_at::<Bytes>(0)?)?;
+		let recover: bool = rlp.val_at(1)?;
+		let state_done: Vec<H256> = rlp.list_at(2)?;
+		let block_done: Vec<H256> = rlp.list_at(3)?;
+
+		let recovery_temp = self.temp_recovery_dir();
+		for hash in state_done.iter().chain(block_done.iter()) {
+			let known = manifest.state_hashes.contains(hash) || manifest.block_hashes.contains(hash);
+			let intact = known && Self::read_chunk(&recovery_temp.join(format!("{:x}", hash)))
+				.map_or(false, |chunk| keccak(&chunk) == *hash);
+
+			if !intact {
+				return Err(SnapshotError::MissingJournalChunk(*hash).into());
+			}
+		}
+
+		Ok((manifest, recover))
+	}
+
+	// read a raw chunk from disk.
+	fn read_chunk(path: &Path) -> io::Result<Bytes> {
+		let mut buffer = Vec::new();
+		File::open(path)?.read_to_end(&mut buffer)?;
+		Ok(buffer)
+	}
+
 	// replace one the client's database with our own.
 	fn replace_client_db(&self) -> Result<(), Error> {
 		let our_db = self.restoration_db();
@@ -345,13 +481,21 @@ impl Service {
 		self.reader.read()
 	}
 
+	/// Get the throttle limiting the IO of snapshot creation.
+	pub fn throttle(&self) -> &Throttle {
+		self.progress.throttle()
+	}
+
 	/// Tick the snapshot service. This will log any active snapshot
 	/// being taken.
 	pub fn tick(&self) {
 		if self.progress.done() || !self.taking_snapshot.load(Ordering::SeqCst) { return }
 
 		let p = &self.progress;
-		info!("Snapshot: {} accounts {} blocks {} bytes", p.accounts(), p.blocks(), p.size());
+		match p.throttle().state() {
+			ThrottleState::Running => info!("Snapshot: {} accounts {} blocks {} bytes", p.accounts(), p.blocks(), p.size()),
+			state => info!("Snapshot: {} accounts {} blocks {} bytes ({:?})", p.accounts(), p.blocks(), p.size(), state),
+		}
 	}
 
 	/// Take a snapshot at the block with the given number.
@@ -359,6 +503,19 @@ impl Service {
 	/// will lead to a race condition where the first one to finish will
 	/// have their produced snapshot overwritten.
 	pub fn take_snapshot(&self, client: &Client, num: u64) -> Result<(), Error> {
+		let retention = self.retention.read().clone();
+		if retention.min_free_space > 0 {
+			match available_space(&self.snapshot_root) {
+				Ok(free) if free < retention.min_free_space => {
+					warn!("Skipping snapshot at #{}: {} MB free on the snapshot disk, at least {} MB required.",
+						num, free / MB, retention.min_free_space / MB);
+					return Ok(());
+				},
+				Ok(_) => {},
+				Err(e) => trace!(target: "snapshot", "Unable to check free disk space: {}", e),
+			}
+		}
+
 		if self.taking_snapshot.compare_and_swap(false, true, Ordering::SeqCst) {
 			info!("Skipping snapshot at #{} as another one is currently in-progress.", num);
 			return Ok(());
@@ -393,20 +550,83 @@ impl Service {
 
 		info!("Finished taking snapshot at #{}", num);
 
-		let mut reader = self.reader.write();
+		// nothing is deleted before the new snapshot is known to be complete.
+		Self::verify_snapshot(&LooseReader::new(temp_dir.clone())?)?;
 
-		// destroy the old snapshot reader.
-		*reader = None;
+		{
+			let mut reader = self.reader.write();
 
-		if snapshot_dir.exists() {
-			fs::remove_dir_all(&snapshot_dir)?;
-		}
+			// destroy the old snapshot reader, keeping its snapshot if the policy asks for more than one.
+			let superseded = reader.take().map(|r| r.manifest().block_number);
 
-		fs::rename(temp_dir, &snapshot_dir)?;
+			if snapshot_dir.exists() {
+				match superseded {
+					Some(number) if retention.keep > 1 => {
+						let kept = self.history_dir().join(number.to_string());
+						fs::create_dir_all(self.history_dir())?;
+						let _ = fs::remove_dir_all(&kept);
+						fs::rename(&snapshot_dir, kept)?;
+					},
+					_ => fs::remove_dir_all(&snapshot_dir)?,
+				}
+			}
 
-		*reader = Some(LooseReader::new(snapshot_dir)?);
+			fs::rename(temp_dir, &snapshot_dir)?;
+
+			*reader = Some(LooseReader::new(snapshot_dir)?);
+		}
 
 		guard.disarm();
+		self.prune_history(retention.keep)
+	}
+
+	/// Set which completed snapshots to keep and when to skip taking one.
+	/// Superseded snapshots are pruned when the next snapshot completes.
+	pub fn set_retention(&self, policy: RetentionPolicy) {
+		*self.retention.write() = policy;
+	}
+
+	/// Block numbers of the completed snapshots kept on disk, newest first.
+	/// The first one is the snapshot being served.
+	pub fn retained_snapshots(&self) -> Vec<u64> {
+		let current = self.reader.read().as_ref().map(|r| r.manifest().block_number);
+		current.into_iter().chain(self.history().into_iter().map(|(number, _)| number)).collect()
+	}
+
+	// superseded snapshots kept on disk, newest first.
+	fn history(&self) -> Vec<(u64, PathBuf)> {
+		let mut history: Vec<(u64, PathBuf)> = match fs::read_dir(self.history_dir()) {
+			Ok(entries) => entries
+				.filter_map(|entry| entry.ok())
+				.filter_map(|entry| entry.file_name().to_str().and_then(|name| name.parse().ok()).map(|number| (number, entry.path())))
+				.collect(),
+			Err(_) => Vec::new(),
+		};
+
+		history.sort_by(|a, b| b.0.cmp(&a.0));
+		history
+	}
+
+	// delete the oldest superseded snapshots, keeping `keep` snapshots including the served one.
+	fn prune_history(&self, keep: usize) -> Result<(), Error> {
+		for (number, path) in self.history().into_iter().skip(keep.saturating_sub(1)) {
+			trace!(target: "snapshot", "Deleting superseded snapshot at #{}", number);
+			fs::remove_dir_all(path)?;
+		}
+
+		Ok(())
+	}
+
+	// check that every chunk of a written snapshot is present and matches its hash.
+	fn verify_snapshot(reader: &LooseReader) -> Result<(), Error> {
+		let manifest = reader.manifest();
+		for hash in manifest.state_hashes.iter().chain(&manifest.block_hashes) {
+			let intact = reader.chunk(*hash).map_or(false, |chunk| keccak(&chunk) == *hash);
+			if !intact {
+				return Err(SnapshotError::CorruptChunk(*hash).into());
+			}
+		}
+
 		Ok(())
 	}
 
@@ -415,6 +635,16 @@ impl Service {
 	pub fn init_restore(&self, manifest: ManifestData, recover: bool) -> Result<(), Error> {
 		let mut res = self.restoration.lock();
 
+		// already restoring this exact snapshot: keep the chunks applied so far.
+		if res.as_ref().map_or(false, |r| r.manifest == manifest && r.recover == recover) {
+			trace!(target: "snapshot", "Continuing ongoing restoration of #{}", manifest.block_number);
+			return Ok(());
+		}
+
+		if !compression::is_supported(manifest.compression) {
+			return Err(::snapshot::Error::CompressionNotSupported(manifest.compression).into());
+		}
+
 		let rest_dir = self.restoration_dir();
 		let rest_db = self.restoration_db();
 		let recovery_temp = self.temp_recovery_dir();
@@ -454,20 +684,20 @@ impl Service {
 
 		fs::create_dir_all(&rest_dir)?;
 
-		// make new restoration.
-		let writer = match recover {
-			true => Some(LooseWriter::new(recovery_temp)?),
-			false => None
-		};
+		// make new restoration. chunks are always kept so that the
+		// restoration can be resumed, but only promoted when recovering.
+		let writer = LooseWriter::new(recovery_temp)?.with_compression(manifest.compression);
 
 		let params = RestorationParams {
 			manifest: manifest.clone(),
 			pruning: self.pruning,
 			db: self.restoration_db_handler.open(&rest_db)?,
-			writer: writer,
+			writer: Some(writer),
 			genesis: &self.genesis_block,
 			guard: Guard::new(rest_db),
 			engine: &*self.engine,
+			recover: recover,
+			journal: Some(self.journal_path()),
 		};
 
 		let state_chunks = manifest.state_hashes.len();
@@ -525,9 +755,7 @@ impl Service {
 		let file = file?;
 		let path = file.path();
 
-		let mut file = File::open(path.clone())?;
-		let mut buffer = Vec::new();
-		file.read_to_end(&mut buffer)?;
+		let buffer = Self::read_chunk(&path)?;
 
 		let hash = keccak(&buffer);
 
@@ -552,7 +780,7 @@ impl Service {
 	fn finalize_restoration(&self, rest: &mut Option<Restoration>) -> Result<(), Error> {
 		trace!(target: "snapshot", "finalizing restoration");
 
-		let recover = rest.as_ref().map_or(false, |rest| rest.writer.is_some());
+		let recover = rest.as_ref().map_or(false, |rest| rest.recover);
 
 		// destroy the restoration before replacing databases and snapshot.
 		rest.take()
@@ -634,7 +862,14 @@ impl Service {
 									drop(db);
 									return self.finalize_restoration(&mut *restoration);
 								},
-								false => Ok(())
+								false => {
+									if let Some(ref r) = *restoration {
+										if let Err(e) = r.write_journal() {
+											warn!(target: "snapshot", "Failed to write restoration journal: {}", e);
+										}
+									}
+									Ok(())
+								}
 							}
 						}
 						other => other.map(drop),
@@ -658,6 +893,62 @@ impl Service {
 	pub fn feed_block_chunk(&self, hash: H256, chunk: &[u8]) {
 		self.feed_chunk(hash, chunk, false);
 	}
+
+	/// Restore the snapshot at `path` synchronously, reading its chunks from disk,
+	/// and keep it as the local snapshot. See `io::open_reader` for the formats read.
+	pub fn restore_from_disk(&self, path: &Path) -> Result<(), Error> {
+		info!("Restoring snapshot from {}", path.display());
+		let reader = snapshot_io::open_reader(path)?;
+		self.restore_using(&*reader, true)
+	}
+
+	/// Restore the snapshot read by `reader` synchronously, verifying every chunk against its hash.
+	/// The recover flag indicates whether to recover the restored snapshot.
+	///
+	/// Progress is reported by `status` just like for chunks fed by the network.
+	pub fn restore_using<R: SnapshotReader + ?Sized>(&self, reader: &R, recover: bool) -> Result<(), Error> {
+		let manifest = reader.manifest().clone();
+		info!("Restoring to block #{} ({:?})", manifest.block_number, manifest.block_hash);
+
+		self.init_restore(manifest.clone(), recover)?;
+
+		let chunks = manifest.state_hashes.iter().map(|hash| (*hash, true))
+			.chain(manifest.block_hashes.iter().map(|hash| (*hash, false)));
+
+		for (hash, is_state) in chunks {
+			if !self.restoring_snapshot.load(Ordering::SeqCst) {
+				return Err(SnapshotError::RestorationAborted.into());
+			}
+
+			if self.status() == RestorationStatus::Failed {
+				return Err("Snapshot restoration failed.".into());
+			}
+
+			let chunk = match reader.chunk(hash) {
+				Ok(chunk) => chunk,
+				Err(e) => {
+					self.abort_restore();
+					return Err(e.into());
+				}
+			};
+
+			if keccak(&chunk) != hash {
+				self.abort_restore();
+				return Err(SnapshotError::CorruptChunk(hash).into());
+			}
+
+			self.feed_chunk(hash, &chunk, is_state);
+		}
+
+		match self.status() {
+			RestorationStatus::Inactive => {
+				info!("Restoration complete.");
+				Ok(())
+			},
+			RestorationStatus::Failed => Err("Snapshot restoration failed.".into()),
+			_ => Err("Snapshot is incomplete and missing chunks.".into()),
+		}
+	}
 }
 
 impl SnapshotService for Service {
@@ -667,7 +958,7 @@ impl SnapshotService for Service {
 
 	fn supported_versions(&self) -> Option<(u64, u64)> {
 		self.engine.snapshot_components()
-			.map(|c| (c.min_supported_version(), c.current_version()))
+			.map(|c| (c.min_supported_version(), ::std::cmp::max(c.current_version(), COMPRESSION_VERSION)))
 	}
 
 	fn chunk(&self, hash: H256) -> Option<Bytes> {
@@ -727,6 +1018,12 @@ impl SnapshotService for Service {
 		*self.status.lock() = RestorationStatus::Inactive;
 	}
 
+	fn restore_from_path(&self, path: PathBuf) {
+		if let Err(e) = self.io_channel.lock().send(ClientIoMessage::RestoreFromPath(path)) {
+			trace!("Error sending snapshot service message: {:?}", e);
+		}
+	}
+
 	fn restore_state_chunk(&self, hash: H256, chunk: Bytes) {
 		if let Err(e) = self.io_channel.lock().send(ClientIoMessage::FeedStateChunk(hash, chunk)) {
 			trace!("Error sending snapshot service message: {:?}", e);
@@ -739,6 +1036,33 @@ impl SnapshotService for Service {
 		}
 	}
 
+	fn creation_status(&self) -> Option<CreationStatus> {
+		if self.progress.done() || !self.taking_snapshot.load(Ordering::SeqCst) { return None }
+
+		let p = &self.progress;
+		Some(CreationStatus {
+			accounts: p.accounts(),
+			blocks: p.blocks(),
+			size: p.size(),
+			throttle: p.throttle().state(),
+		})
+	}
+
+	fn pause_creation(&self) {
+		trace!(target: "snapshot", "Pausing snapshot creation");
+		self.progress.throttle().pause();
+	}
+
+	fn resume_creation(&self) {
+		trace!(target: "snapshot", "Resuming snapshot creation");
+		self.progress.throttle().resume();
+	}
+
+	fn verify_manifest(&self, path: &Path) -> VerificationReport {
+		let components = self.engine.snapshot_components();
+		verification::verify_path(path, components.as_ref().map(|c| &**c))
+	}
+
 	fn shutdown(&self) {
 		self.abort_restore();
 	}
@@ -801,6 +1125,7 @@ mod tests {
 			state_root: Default::default(),
 			block_number: 0,
 			block_hash: Default::default(),
+			compression: Default::default(),
 		};
 
 		service.begin_restore(manifest);
@@ -831,6 +1156,7 @@ mod tests {
 				state_root: H256::default(),
 				block_number: 100000,
 				block_hash: H256::default(),
+				compression: Default::default(),
 			},
 			pruning: Algorithm::Archive,
 			db: restoration_db_handler(db_config).open(&tempdir.path().to_owned()).unwrap(),
@@ -838,6 +1164,8 @@ mod tests {
 			genesis: &gb,
 			guard: Guard::benign(),
 			engine: &*spec.engine.clone(),
+			recover: false,
+			journal: None,
 		};
 
 		let mut restoration = Restoration::new(params).unwrap();
@@ -76,6 +76,7 @@ fn chunk_and_restore(amount: u64) {
 		state_root: ::hash::KECCAK_NULL_RLP,
 		block_number: amount,
 		block_hash: best_hash,
+		compression: Default::default(),
 	};
 
 	writer.into_inner().finish(manifest.clone()).unwrap();
@@ -138,6 +139,7 @@ fn checks_flag() {
 		state_root: ::hash::KECCAK_NULL_RLP,
 		block_number: 102,
 		block_hash: H256::default(),
+		compression: Default::default(),
 	};
 
 	let mut rebuilder = SNAPSHOT_MODE.rebuilder(chain, db.clone(), &manifest).unwrap();
@@ -21,7 +21,7 @@ use std::sync::Arc;
 use tempdir::TempDir;
 use client::{Client, BlockInfo};
 use ids::BlockId;
-use snapshot::service::{Service, ServiceParams};
+use snapshot::service::{Service, ServiceParams, RetentionPolicy};
 use snapshot::{self, ManifestData, SnapshotService};
 use spec::Spec;
 use test_helpers::{generate_dummy_client_with_spec_and_data, restoration_db_handler};
@@ -127,6 +127,7 @@ fn guards_delete_folders() {
 		block_hashes: vec![],
 		block_number: 0,
 		block_hash: Default::default(),
+		compression: Default::default(),
 		state_root: Default::default(),
 	};
 
@@ -146,3 +147,287 @@ fn guards_delete_folders() {
 	assert!(!path.join("db").exists());
 	assert!(path.join("temp").exists());
 }
+
+#[test]
+fn restoration_resumes_after_restart() {
+	const NUM_BLOCKS: u32 = 400;
+	const TX_PER: usize = 5;
+
+	let gas_prices = vec![1.into(), 2.into(), 3.into(), 999.into()];
+
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, NUM_BLOCKS, TX_PER, &gas_prices);
+
+	let tempdir = TempDir::new("").unwrap();
+	let client_db = tempdir.path().join("client_db");
+	let path = tempdir.path().join("snapshot");
+
+	let db_config = DatabaseConfig::with_columns(::db::NUM_COLUMNS);
+	let blockchain_db = restoration_db_handler(db_config).open(&client_db).unwrap();
+
+	let spec = Spec::new_null();
+	let client2 = Client::new(
+		Default::default(),
+		&spec,
+		blockchain_db,
+		Arc::new(::miner::Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+
+	let service_params = |path| ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: path,
+		db_restore: client2.clone(),
+	};
+
+	let service = Service::new(service_params(path.clone())).unwrap();
+	service.take_snapshot(&client, NUM_BLOCKS as u64).unwrap();
+
+	let manifest = service.manifest().unwrap();
+	service.init_restore(manifest.clone(), false).unwrap();
+
+	// apply only the state before going away mid-restoration.
+	for hash in &manifest.state_hashes {
+		let chunk = service.chunk(*hash).unwrap();
+		service.feed_state_chunk(*hash, &chunk);
+	}
+	drop(service);
+
+	let service = Service::new(service_params(path.clone())).unwrap();
+	match service.status() {
+		::snapshot::RestorationStatus::Ongoing { .. } => {},
+		other => panic!("restoration not resumed: {:?}", other),
+	}
+
+	let mut completed = service.completed_chunks().unwrap();
+	let mut expected = manifest.state_hashes.clone();
+	completed.sort();
+	expected.sort();
+	assert_eq!(completed, expected);
+
+	// sync asks again for the same snapshot; progress must be kept.
+	service.init_restore(manifest.clone(), false).unwrap();
+	assert_eq!(service.completed_chunks().unwrap().len(), manifest.state_hashes.len());
+
+	for hash in &manifest.block_hashes {
+		let chunk = service.chunk(*hash).unwrap();
+		service.feed_block_chunk(*hash, &chunk);
+	}
+
+	assert_eq!(service.status(), ::snapshot::RestorationStatus::Inactive);
+	assert!(!path.join("restoration").exists());
+
+	let best = client2.block_header(BlockId::Latest).unwrap();
+	assert_eq!(best.hash(), client.block_header(BlockId::Latest).unwrap().hash());
+	assert_eq!(best.state_root(), manifest.state_root);
+}
+
+#[test]
+fn corrupt_journal_restarts_restoration() {
+	use std::fs;
+
+	let spec = Spec::new_null();
+	let tempdir = TempDir::new("").unwrap();
+	let service_params = || ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().to_owned(),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let path = tempdir.path().join("restoration");
+	fs::create_dir_all(&path).unwrap();
+	fs::write(path.join("journal"), b"definitely not a journal").unwrap();
+
+	let service = Service::new(service_params()).unwrap();
+	assert_eq!(service.status(), ::snapshot::RestorationStatus::Inactive);
+	assert!(service.completed_chunks().is_none());
+	assert!(!path.exists());
+}
+
+#[test]
+fn periodic_snapshots_keep_the_most_recent() {
+	const NUM_BLOCKS: u32 = 100;
+	const PERIOD: u64 = 10;
+	const KEEP: usize = 3;
+
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, NUM_BLOCKS, 1, &[1.into()]);
+
+	let spec = Spec::new_null();
+	let tempdir = TempDir::new("").unwrap();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().to_owned(),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let service = Service::new(service_params).unwrap();
+	service.set_retention(RetentionPolicy { keep: KEEP, ..Default::default() });
+
+	// every period within the state history of the client.
+	let numbers: Vec<u64> = (5..11).map(|i| i * PERIOD).collect();
+	for (i, &num) in numbers.iter().enumerate() {
+		service.take_snapshot(&client, num).unwrap();
+
+		let manifest = service.manifest().unwrap();
+		assert_eq!(manifest.block_number, num);
+		assert!(service.chunk(manifest.state_hashes[0]).is_some());
+
+		let retained = service.retained_snapshots();
+		let expected: Vec<u64> = numbers[..i + 1].iter().rev().take(KEEP).cloned().collect();
+		assert_eq!(retained, expected);
+	}
+
+	assert_eq!(service.retained_snapshots(), vec![100, 90, 80]);
+	assert_eq!(::std::fs::read_dir(tempdir.path().join("history")).unwrap().count(), KEEP - 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn skips_snapshot_without_free_space() {
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, 10, 1, &[1.into()]);
+
+	let spec = Spec::new_null();
+	let tempdir = TempDir::new("").unwrap();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().to_owned(),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let service = Service::new(service_params).unwrap();
+	service.set_retention(RetentionPolicy { min_free_space: u64::max_value(), ..Default::default() });
+	service.take_snapshot(&client, 10).unwrap();
+	assert!(service.manifest().is_none());
+
+	service.set_retention(RetentionPolicy::default());
+	service.take_snapshot(&client, 10).unwrap();
+	assert_eq!(service.manifest().unwrap().block_number, 10);
+}
+
+#[test]
+fn verification_reports_every_corrupt_chunk() {
+	use std::fs;
+	use snapshot::ChunkKind;
+
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, 400, 5, &[1.into(), 2.into()]);
+
+	let spec = Spec::new_null();
+	let tempdir = TempDir::new("").unwrap();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::Archive,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().to_owned(),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let service = Service::new(service_params).unwrap();
+	service.take_snapshot(&client, 400).unwrap();
+	let manifest = service.manifest().unwrap();
+	let path = tempdir.path().join("current");
+
+	let report = service.verify_manifest(&path);
+	assert!(report.is_valid(), "{:?}", report.failures);
+	assert_eq!(report.manifest, Some(manifest.clone()));
+	assert_eq!(report.chunks_checked, manifest.state_hashes.len() + manifest.block_hashes.len());
+
+	let state_hash = manifest.state_hashes[0];
+	let block_hash = manifest.block_hashes[0];
+	fs::write(path.join(format!("{:x}", state_hash)), b"not a state chunk").unwrap();
+	fs::write(path.join(format!("{:x}", block_hash)), b"not a block chunk").unwrap();
+
+	let report = service.verify_manifest(&path);
+	assert!(!report.is_valid());
+	let failed: Vec<_> = report.failures.iter().map(|f| (f.hash, f.kind)).collect();
+	assert_eq!(failed, vec![(state_hash, ChunkKind::State), (block_hash, ChunkKind::Block)]);
+
+	let report = service.verify_manifest(&tempdir.path().join("missing"));
+	assert!(report.manifest_error.is_some());
+}
+
+#[test]
+fn restores_from_disk() {
+	use std::fs::File;
+	use client::{ChainInfo, Nonce, Balance};
+	use ethkey::KeyPair;
+	use hash::keccak;
+	use tar::Builder;
+
+	const NUM_BLOCKS: u32 = 200;
+	const TX_PER: usize = 5;
+
+	let client = generate_dummy_client_with_spec_and_data(Spec::new_null, NUM_BLOCKS, TX_PER, &[1.into(), 2.into()]);
+
+	let spec = Spec::new_null();
+	let tempdir = TempDir::new("").unwrap();
+	let service_params = ServiceParams {
+		engine: spec.engine.clone(),
+		genesis_block: spec.genesis_block(),
+		restoration_db_handler: restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS)),
+		pruning: ::journaldb::Algorithm::OverlayRecent,
+		channel: IoChannel::disconnected(),
+		snapshot_root: tempdir.path().join("snapshot"),
+		db_restore: Arc::new(NoopDBRestore),
+	};
+
+	let service = Service::new(service_params).unwrap();
+	service.take_snapshot(&client, NUM_BLOCKS as u64).unwrap();
+
+	// the snapshot directory as copied from another node, and a tar archive of it.
+	let dir = tempdir.path().join("snapshot").join("current");
+	let archive = tempdir.path().join("snapshot.tar");
+	let mut builder = Builder::new(File::create(&archive).unwrap());
+	builder.append_dir_all("current", &dir).unwrap();
+	builder.finish().unwrap();
+
+	// the account sending all transactions of the generated chain.
+	let sender = KeyPair::from_secret_slice(&keccak("")).unwrap().address();
+
+	for (i, path) in vec![dir, archive].into_iter().enumerate() {
+		let restore_dir = tempdir.path().join(format!("restore_{}", i));
+		let restoration = restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS));
+		let client2 = Client::new(
+			Default::default(),
+			&spec,
+			restoration.open(&restore_dir.join("client_db")).unwrap(),
+			Arc::new(::miner::Miner::new_for_tests(&spec, None)),
+			IoChannel::disconnected(),
+		).unwrap();
+
+		let service2 = Service::new(ServiceParams {
+			engine: spec.engine.clone(),
+			genesis_block: spec.genesis_block(),
+			restoration_db_handler: restoration,
+			pruning: ::journaldb::Algorithm::OverlayRecent,
+			channel: IoChannel::disconnected(),
+			snapshot_root: restore_dir.join("snapshot"),
+			db_restore: client2.clone(),
+		}).unwrap();
+
+		service2.restore_from_disk(&path).unwrap();
+		assert_eq!(service2.status(), ::snapshot::RestorationStatus::Inactive);
+		assert_eq!(service2.manifest(), service.manifest());
+
+		assert_eq!(client2.chain_info().best_block_number, NUM_BLOCKS as u64);
+		assert_eq!(client2.chain_info().best_block_hash, client.chain_info().best_block_hash);
+		assert_eq!(client2.latest_nonce(&sender), (NUM_BLOCKS as usize * TX_PER).into());
+		assert_eq!(client2.latest_balance(&sender), client.latest_balance(&sender));
+	}
+}
@@ -62,6 +62,7 @@ fn snap_and_restore() {
 		state_root: state_root,
 		block_number: 1000,
 		block_hash: H256::default(),
+		compression: Default::default(),
 	}).unwrap();
 
 	let db_path = tempdir.path().join("db");
@@ -173,6 +174,7 @@ fn checks_flag() {
 		state_root: state_root,
 		block_number: 0,
 		block_hash: H256::default(),
+		compression: Default::default(),
 	}).unwrap();
 
 	let tempdir = TempDir::new("").unwrap();
@@ -0,0 +1,284 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Throttling of snapshot creation, keeping it from starving block import and RPC of IO.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+
+// interval at which a paused snapshot checks whether it was resumed.
+const PAUSE_POLL_MS: u64 = 50;
+
+// length of a single yield to the busy client.
+const BUSY_YIELD_MS: u64 = 50;
+
+// maximum number of consecutive yields, so a constantly busy client can't stall snapshots forever.
+const MAX_BUSY_YIELDS: usize = 20;
+
+/// Signals whether the client is busy, in which case snapshot creation yields to it.
+pub trait BusySignal: Send + Sync {
+	/// Whether the client is currently busy, e.g. importing blocks.
+	fn is_busy(&self) -> bool;
+}
+
+/// What the throttle is currently doing to snapshot creation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleState {
+	/// Reading at full speed.
+	Running,
+	/// Waiting to stay within the IO budget.
+	Limited,
+	/// Yielding to the busy client.
+	Yielding,
+	/// Paused until resumed.
+	Paused,
+}
+
+/// Throttle for the reads of snapshot creation.
+///
+/// Reads are limited to a budget of bytes per second, creation yields between chunks
+/// while the client is busy and can be paused altogether.
+pub struct Throttle {
+	budget: AtomicUsize, // bytes per second, 0 for unlimited.
+	paused: AtomicBool,
+	state: Mutex<ThrottleState>,
+	window: Mutex<(Instant, usize)>, // start of the budget window and bytes read since.
+	busy: RwLock<Option<Arc<BusySignal>>>,
+}
+
+impl Default for Throttle {
+	fn default() -> Self {
+		Throttle {
+			budget: AtomicUsize::new(0),
+			paused: AtomicBool::new(false),
+			state: Mutex::new(ThrottleState::Running),
+			window: Mutex::new((Instant::now(), 0)),
+			busy: RwLock::new(None),
+		}
+	}
+}
+
+impl fmt::Debug for Throttle {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Throttle")
+			.field("budget", &self.budget())
+			.field("state", &self.state())
+			.finish()
+	}
+}
+
+impl Throttle {
+	/// Limit reads to `bytes_per_sec`, or lift the limit with `None`.
+	pub fn set_budget(&self, bytes_per_sec: Option<usize>) {
+		self.budget.store(bytes_per_sec.unwrap_or(0), Ordering::SeqCst);
+		self.reset();
+	}
+
+	/// Get the number of bytes which may be read per second, if limited.
+	pub fn budget(&self) -> Option<usize> {
+		match self.budget.load(Ordering::SeqCst) {
+			0 => None,
+			budget => Some(budget),
+		}
+	}
+
+	/// Pause creation at the next read until resumed.
+	pub fn pause(&self) {
+		self.paused.store(true, Ordering::SeqCst);
+	}
+
+	/// Resume paused creation.
+	pub fn resume(&self) {
+		self.paused.store(false, Ordering::SeqCst);
+	}
+
+	/// Whether creation is paused.
+	pub fn is_paused(&self) -> bool {
+		self.paused.load(Ordering::SeqCst)
+	}
+
+	/// Use `signal` to tell whether to yield to the client.
+	pub fn set_busy_signal(&self, signal: Arc<BusySignal>) {
+		*self.busy.write() = Some(signal);
+	}
+
+	/// Get the current state of the throttle.
+	pub fn state(&self) -> ThrottleState {
+		*self.state.lock()
+	}
+
+	/// Start a new budget window.
+	pub fn reset(&self) {
+		*self.window.lock() = (Instant::now(), 0);
+	}
+
+	/// Account for `bytes` read, blocking while paused or while ahead of the budget.
+	pub fn read(&self, bytes: usize) {
+		self.wait_while_paused();
+
+		let budget = self.budget.load(Ordering::SeqCst) as u64;
+		if budget == 0 { return }
+
+		let wait = {
+			let mut window = self.window.lock();
+			window.1 += bytes;
+			let due = Duration::from_millis((window.1 as u64).saturating_mul(1000) / budget);
+			let elapsed = window.0.elapsed();
+			if due > elapsed { Some(due - elapsed) } else { None }
+		};
+
+		if let Some(wait) = wait {
+			self.set_state(ThrottleState::Limited);
+			thread::sleep(wait);
+			self.set_state(ThrottleState::Running);
+		}
+	}
+
+	/// Yield to the client while it is busy, for a bounded time.
+	pub fn yield_if_busy(&self) {
+		self.wait_while_paused();
+
+		let signal = match *self.busy.read() {
+			Some(ref signal) => signal.clone(),
+			None => return,
+		};
+
+		let mut yielded = false;
+		for _ in 0..MAX_BUSY_YIELDS {
+			if !signal.is_busy() { break }
+
+			self.set_state(ThrottleState::Yielding);
+			thread::sleep(Duration::from_millis(BUSY_YIELD_MS));
+			yielded = true;
+		}
+
+		if yielded {
+			// time spent yielding is no credit towards the budget.
+			self.reset();
+			self.set_state(ThrottleState::Running);
+		}
+	}
+
+	fn wait_while_paused(&self) {
+		if !self.is_paused() { return }
+
+		trace!(target: "snapshot", "Snapshot creation paused");
+		self.set_state(ThrottleState::Paused);
+		while self.is_paused() {
+			thread::sleep(Duration::from_millis(PAUSE_POLL_MS));
+		}
+
+		trace!(target: "snapshot", "Snapshot creation resumed");
+		self.reset();
+		self.set_state(ThrottleState::Running);
+	}
+
+	fn set_state(&self, state: ThrottleState) {
+		*self.state.lock() = state;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+	use std::thread;
+	use std::time::{Duration, Instant};
+
+	use super::{BusySignal, Throttle, ThrottleState};
+
+	const CHUNK: usize = 1000;
+
+	// reads `chunks` chunks through the throttle, as slow as a disk would.
+	fn slow_reader(throttle: Arc<Throttle>, chunks: usize, read: Arc<AtomicUsize>) -> thread::JoinHandle<()> {
+		thread::spawn(move || {
+			for _ in 0..chunks {
+				thread::sleep(Duration::from_millis(1));
+				throttle.read(CHUNK);
+				throttle.yield_if_busy();
+				read.fetch_add(1, Ordering::SeqCst);
+			}
+		})
+	}
+
+	fn wait_for<F: Fn() -> bool>(condition: F) {
+		let start = Instant::now();
+		while !condition() {
+			assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+			thread::sleep(Duration::from_millis(5));
+		}
+	}
+
+	#[test]
+	fn enforces_budget() {
+		let throttle = Arc::new(Throttle::default());
+		throttle.set_budget(Some(50 * CHUNK));
+		assert_eq!(throttle.budget(), Some(50 * CHUNK));
+
+		let read = Arc::new(AtomicUsize::new(0));
+		let start = Instant::now();
+		slow_reader(throttle.clone(), 10, read.clone()).join().unwrap();
+
+		// 10 chunks at 50 chunks per second take at least 200ms.
+		assert_eq!(read.load(Ordering::SeqCst), 10);
+		assert!(start.elapsed() >= Duration::from_millis(200));
+		assert_eq!(throttle.state(), ThrottleState::Running);
+	}
+
+	#[test]
+	fn pauses_and_resumes() {
+		let throttle = Arc::new(Throttle::default());
+		let read = Arc::new(AtomicUsize::new(0));
+
+		throttle.pause();
+		let reader = slow_reader(throttle.clone(), 10, read.clone());
+		wait_for(|| throttle.state() == ThrottleState::Paused);
+
+		thread::sleep(Duration::from_millis(100));
+		assert_eq!(read.load(Ordering::SeqCst), 0);
+
+		throttle.resume();
+		reader.join().unwrap();
+		assert_eq!(read.load(Ordering::SeqCst), 10);
+		assert_eq!(throttle.state(), ThrottleState::Running);
+	}
+
+	#[test]
+	fn yields_to_busy_client() {
+		struct Busy(AtomicBool);
+		impl BusySignal for Busy {
+			fn is_busy(&self) -> bool { self.0.load(Ordering::SeqCst) }
+		}
+
+		let busy = Arc::new(Busy(AtomicBool::new(true)));
+		let throttle = Arc::new(Throttle::default());
+		throttle.set_busy_signal(busy.clone());
+
+		let read = Arc::new(AtomicUsize::new(0));
+		let reader = slow_reader(throttle.clone(), 10, read.clone());
+		wait_for(|| throttle.state() == ThrottleState::Yielding);
+		assert_eq!(read.load(Ordering::SeqCst), 0);
+
+		busy.0.store(false, Ordering::SeqCst);
+		reader.join().unwrap();
+		assert_eq!(read.load(Ordering::SeqCst), 10);
+	}
+}
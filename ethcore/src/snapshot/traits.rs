@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::{ManifestData, RestorationStatus};
+use std::path::{Path, PathBuf};
+
+use super::{CreationStatus, ManifestData, RestorationStatus, VerificationReport};
 use ethereum_types::H256;
 use bytes::Bytes;
 
@@ -47,6 +49,11 @@ pub trait SnapshotService : Sync + Send {
 	/// Abort an in-progress restoration if there is one.
 	fn abort_restore(&self);
 
+	/// Begin restoring the snapshot at `path`, a directory of chunks, a tar archive of one
+	/// or a packed snapshot file, reading its chunks from disk rather than from peers.
+	/// Restoration proceeds asynchronously and is reported by `status`.
+	fn restore_from_path(&self, path: PathBuf);
+
 	/// Feed a raw state chunk to the service to be processed asynchronously.
 	/// no-op if not currently restoring.
 	fn restore_state_chunk(&self, hash: H256, chunk: Bytes);
@@ -55,6 +62,19 @@ pub trait SnapshotService : Sync + Send {
 	/// no-op if currently restoring.
 	fn restore_block_chunk(&self, hash: H256, chunk: Bytes);
 
+	/// Ask the snapshot service for the status of the snapshot being taken, if any.
+	fn creation_status(&self) -> Option<CreationStatus>;
+
+	/// Pause taking snapshots until resumed.
+	fn pause_creation(&self);
+
+	/// Resume taking snapshots after a pause.
+	fn resume_creation(&self);
+
+	/// Verify the snapshot at `path`, a directory of chunks, a tar archive of one or a packed snapshot file,
+	/// without restoring it.
+	fn verify_manifest(&self, path: &Path) -> VerificationReport;
+
 	/// Shutdown the Snapshot Service by aborting any ongoing restore
 	fn shutdown(&self);
 }
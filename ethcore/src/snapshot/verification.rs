@@ -0,0 +1,177 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verification of snapshots on disk without restoring them.
+
+use std::path::Path;
+
+use account_db::AccountDBMut;
+use ethereum_types::H256;
+use hash::keccak;
+use keccak_hasher::KeccakHasher;
+use memorydb::MemoryDB;
+use rlp::Rlp;
+
+use super::{account, BlockRange, ManifestData, SnapshotComponents};
+use super::compression::Compressor;
+use super::io::{SnapshotReader, open_reader};
+
+/// Kind of a snapshot chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+	/// State chunk, holding accounts.
+	State,
+	/// Secondary chunk, holding blocks.
+	Block,
+}
+
+/// A chunk failing verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkFailure {
+	/// Hash of the chunk, as listed in the manifest.
+	pub hash: H256,
+	/// Kind of the chunk.
+	pub kind: ChunkKind,
+	/// What is wrong with the chunk.
+	pub reason: String,
+}
+
+/// Outcome of verifying a snapshot.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VerificationReport {
+	/// Manifest of the snapshot, if it could be read.
+	pub manifest: Option<ManifestData>,
+	/// Why the manifest couldn't be read, if it couldn't.
+	pub manifest_error: Option<String>,
+	/// Number of chunks checked.
+	pub chunks_checked: usize,
+	/// Every chunk failing verification.
+	pub failures: Vec<ChunkFailure>,
+}
+
+impl VerificationReport {
+	/// Whether the snapshot passed verification.
+	pub fn is_valid(&self) -> bool {
+		self.manifest_error.is_none() && self.failures.is_empty()
+	}
+}
+
+/// Verify the snapshot at `path`, see `io::open_reader` for the formats read.
+pub fn verify_path(path: &Path, components: Option<&SnapshotComponents>) -> VerificationReport {
+	match open_reader(path) {
+		Ok(reader) => verify(&*reader, components),
+		Err(e) => VerificationReport {
+			manifest_error: Some(format!("{}", e)),
+			..Default::default()
+		},
+	}
+}
+
+/// Verify every chunk of the snapshot read by `reader`, reporting all failures rather than just the first.
+///
+/// Chunks must be present and match their hashes, state chunks must decode as accounts and block chunks
+/// as blocks. If `components` decode the block chunks into ranges of blocks and all of them decode, the
+/// ranges must link by parent hash up to the block of the manifest.
+pub fn verify<R: SnapshotReader + ?Sized>(reader: &R, components: Option<&SnapshotComponents>) -> VerificationReport {
+	let manifest = reader.manifest().clone();
+	let mut compressor = Compressor::new(manifest.compression);
+	let mut failures = Vec::new();
+	let mut ranges = Vec::new();
+	let mut blocks_decoded = true;
+
+	{
+		let mut check = |hash: H256, kind: ChunkKind| -> Result<Option<BlockRange>, String> {
+			let chunk = reader.chunk(hash).map_err(|e| format!("Missing chunk: {}", e))?;
+			if keccak(&chunk) != hash {
+				return Err("Chunk content doesn't match its hash".into());
+			}
+
+			let raw = compressor.decompress(&chunk).map_err(|e| format!("Corrupt compression: {}", e))?;
+			match kind {
+				ChunkKind::State => check_state_chunk(raw).map(|_| None).map_err(|e| format!("Invalid accounts: {}", e)),
+				ChunkKind::Block => match components {
+					Some(components) => components.block_range(raw).map_err(|e| format!("Invalid blocks: {}", e)),
+					None => Ok(None),
+				},
+			}
+		};
+
+		for &hash in &manifest.state_hashes {
+			if let Err(reason) = check(hash, ChunkKind::State) {
+				failures.push(ChunkFailure { hash, kind: ChunkKind::State, reason });
+			}
+		}
+
+		for &hash in &manifest.block_hashes {
+			match check(hash, ChunkKind::Block) {
+				Ok(Some(range)) => ranges.push((hash, range)),
+				Ok(None) => blocks_decoded = false,
+				Err(reason) => {
+					blocks_decoded = false;
+					failures.push(ChunkFailure { hash, kind: ChunkKind::Block, reason });
+				},
+			}
+		}
+	}
+
+	// gaps around failed chunks are already reported.
+	if blocks_decoded {
+		failures.extend(check_links(ranges, &manifest));
+	}
+
+	VerificationReport {
+		chunks_checked: manifest.state_hashes.len() + manifest.block_hashes.len(),
+		manifest: Some(manifest),
+		manifest_error: None,
+		failures,
+	}
+}
+
+// decode every account of a state chunk, rebuilding its storage in a throwaway database.
+fn check_state_chunk(raw: &[u8]) -> Result<(), ::error::Error> {
+	let rlp = Rlp::new(raw);
+	rlp.item_count()?;
+
+	let mut db = MemoryDB::<KeccakHasher>::new();
+	for entry in rlp.iter() {
+		let hash: H256 = entry.val_at(0)?;
+		let mut acct_db = AccountDBMut::from_hash(&mut db, hash);
+		account::from_fat_rlp(&mut acct_db, entry.at(1)?, H256::zero())?;
+	}
+
+	Ok(())
+}
+
+// check that block ranges follow each other up to the block of the manifest.
+fn check_links(mut ranges: Vec<(H256, BlockRange)>, manifest: &ManifestData) -> Vec<ChunkFailure> {
+	let failure = |hash, reason: &str| ChunkFailure { hash, kind: ChunkKind::Block, reason: reason.into() };
+	let mut failures = Vec::new();
+
+	ranges.sort_by_key(|&(_, ref range)| range.parent.0);
+	for pair in ranges.windows(2) {
+		if pair[1].1.parent != pair[0].1.last {
+			failures.push(failure(pair[1].0, "Blocks don't link to the preceding chunk"));
+		}
+	}
+
+	if let Some(&(hash, ref range)) = ranges.last() {
+		if range.last != (manifest.block_number, manifest.block_hash) {
+			failures.push(failure(hash, "Blocks don't end at the block of the manifest"));
+		}
+	}
+
+	failures
+}
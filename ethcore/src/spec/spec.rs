@@ -949,6 +949,14 @@ impl Spec {
 		load_bundled!("authority_round_block_reward_contract")
 	}
 
+	/// Create a new Spec with AuthorityRound consensus which changes its validator set at block 1.
+	/// Accounts with secrets keccak("0") and keccak("1") are the initial validators, the epoch
+	/// transition at block 1 removes keccak("0").
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_round_validator_change() -> Self {
+		load_bundled!("authority_round_validator_change")
+	}
+
 	/// Create a new Spec with Tendermint consensus which does internal sealing (not requiring
 	/// work).
 	/// Account keccak("0") and keccak("1") are a authorities.
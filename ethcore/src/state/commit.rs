@@ -0,0 +1,127 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parallel commit of account storage tries.
+//!
+//! Storage tries of different accounts are independent, so they are hashed on a worker pool.
+//! Each worker writes into its own overlay, reading through to the state database, and the
+//! overlays are applied to the database once all workers are done.
+
+use std::cmp;
+use std::collections::HashMap;
+
+use crossbeam;
+use ethereum_types::{Address, H256};
+use ethtrie::Result as TrieResult;
+use factory::Factories;
+use hashdb::{AsHashDB, HashDB, DBValue};
+use keccak_hasher::KeccakHasher;
+use memorydb::MemoryDB;
+use num_cpus;
+
+use super::Account;
+
+/// Number of dirty accounts from which `State::commit` commits storage tries in parallel.
+pub const PARALLEL_COMMIT_THRESHOLD: usize = 512;
+
+// changes of the accounts committed by a single worker, reading through to the state database.
+struct Overlay<'a> {
+	base: &'a HashDB<KeccakHasher>,
+	changed: MemoryDB<KeccakHasher>,
+}
+
+impl<'a> AsHashDB<KeccakHasher> for Overlay<'a> {
+	fn as_hashdb(&self) -> &HashDB<KeccakHasher> { self }
+	fn as_hashdb_mut(&mut self) -> &mut HashDB<KeccakHasher> { self }
+}
+
+impl<'a> HashDB<KeccakHasher> for Overlay<'a> {
+	fn keys(&self) -> HashMap<H256, i32> {
+		let mut keys = self.base.keys();
+		for (key, refs) in self.changed.keys() {
+			*keys.entry(key).or_insert(0) += refs;
+		}
+		keys
+	}
+
+	fn get(&self, key: &H256) -> Option<DBValue> {
+		match self.changed.raw(key) {
+			Some((value, refs)) if refs > 0 => Some(value),
+			_ => self.base.get(key),
+		}
+	}
+
+	fn contains(&self, key: &H256) -> bool {
+		self.get(key).is_some()
+	}
+
+	fn insert(&mut self, value: &[u8]) -> H256 {
+		self.changed.insert(value)
+	}
+
+	fn emplace(&mut self, key: H256, value: DBValue) {
+		self.changed.emplace(key, value)
+	}
+
+	fn remove(&mut self, key: &H256) {
+		self.changed.remove(key)
+	}
+}
+
+/// Commit storage and code of `accounts` into `db`, hashing the storage tries on a worker pool.
+///
+/// Leaves `db` with the same contents as committing the accounts one after another.
+pub fn commit_storage(accounts: &mut [(Address, &mut Account)], db: &mut HashDB<KeccakHasher>, factories: &Factories) -> TrieResult<()> {
+	if accounts.is_empty() {
+		return Ok(());
+	}
+
+	let threads = cmp::min(num_cpus::get(), accounts.len());
+	let chunk_size = (accounts.len() + threads - 1) / threads;
+	let overlays = {
+		let base = &*db;
+		let trie_factory = &factories.trie;
+		let accountdb_factory = &factories.accountdb;
+		crossbeam::scope(|scope| {
+			let workers = accounts.chunks_mut(chunk_size).map(|chunk| scope.spawn(move || -> TrieResult<MemoryDB<KeccakHasher>> {
+				let mut overlay = Overlay { base, changed: MemoryDB::new() };
+				for &mut (ref address, ref mut account) in chunk.iter_mut() {
+					let addr_hash = account.address_hash(address);
+					let mut account_db = accountdb_factory.create(&mut overlay, addr_hash);
+					account.commit_storage(trie_factory, account_db.as_hashdb_mut())?;
+					account.commit_code(account_db.as_hashdb_mut());
+				}
+				Ok(overlay.changed)
+			})).collect::<Vec<_>>();
+
+			workers.into_iter().map(|worker| worker.join()).collect::<TrieResult<Vec<_>>>()
+		})?
+	};
+
+	// only net reference counts matter to the database, so the order of the writes doesn't.
+	for mut overlay in overlays {
+		for (key, (value, refs)) in overlay.drain() {
+			for _ in 0..refs {
+				db.emplace(key, value.clone());
+			}
+			for _ in refs..0 {
+				db.remove(&key);
+			}
+		}
+	}
+
+	Ok(())
+}
@@ -37,6 +37,7 @@ use pod_account::*;
 use pod_state::{self, PodState};
 use types::basic_account::BasicAccount;
 use executed::{Executed, ExecutionError};
+use types::account_diff::StorageReads;
 use types::state_diff::StateDiff;
 use transaction::SignedTransaction;
 use state_db::StateDB;
@@ -52,12 +53,14 @@ use trie::{Trie, TrieError, Recorder};
 use ethtrie::{TrieDB, Result as TrieResult};
 
 mod account;
+mod commit;
 mod substate;
 
 pub mod backend;
 
 pub use self::account::Account;
 pub use self::backend::Backend;
+pub use self::commit::PARALLEL_COMMIT_THRESHOLD;
 pub use self::substate::Substate;
 
 /// Used to return information about an `State::apply` operation.
@@ -315,6 +318,14 @@ pub struct State<B> {
 	checkpoints: RefCell<Vec<HashMap<Address, Option<AccountEntry>>>>,
 	account_start_nonce: U256,
 	factories: Factories,
+	storage_reads: RefCell<Option<StorageReadLog>>,
+}
+
+// storage reads noted while recording, see `State::record_storage_reads`.
+struct StorageReadLog {
+	limit: usize,
+	count: usize,
+	reads: BTreeMap<Address, StorageReads>,
 }
 
 #[derive(Copy, Clone)]
@@ -377,6 +388,7 @@ impl<B: Backend> State<B> {
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: account_start_nonce,
 			factories: factories,
+			storage_reads: RefCell::new(None),
 		}
 	}
 
@@ -392,7 +404,8 @@ impl<B: Backend> State<B> {
 			cache: RefCell::new(HashMap::new()),
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: account_start_nonce,
-			factories: factories
+			factories: factories,
+			storage_reads: RefCell::new(None),
 		};
 
 		Ok(state)
@@ -700,6 +713,43 @@ impl<B: Backend> State<B> {
 		)
 	}
 
+	/// Get the value of storage at `key` read by the code of account `address`.
+	///
+	/// Same as `storage_at`, but notes the read while recording reads.
+	pub fn storage_read(&self, address: &Address, key: &H256) -> TrieResult<H256> {
+		let value = self.storage_at(address, key)?;
+
+		if let Some(ref mut log) = *self.storage_reads.borrow_mut() {
+			let reads = log.reads.entry(*address).or_insert_with(StorageReads::default);
+			if !reads.values.contains_key(key) {
+				if log.count < log.limit {
+					reads.values.insert(*key, value);
+					log.count += 1;
+				} else {
+					reads.truncated = true;
+				}
+			}
+		}
+
+		Ok(value)
+	}
+
+	/// Start recording storage reads of contract code, keeping the first value read from each slot.
+	///
+	/// At most `limit` reads are kept in total, accounts with reads dropped beyond it are marked truncated.
+	pub fn record_storage_reads(&self, limit: usize) {
+		*self.storage_reads.borrow_mut() = Some(StorageReadLog {
+			limit: limit,
+			count: 0,
+			reads: BTreeMap::new(),
+		});
+	}
+
+	/// Stop recording storage reads, returning those recorded so far.
+	pub fn drain_storage_reads(&self) -> BTreeMap<Address, StorageReads> {
+		self.storage_reads.borrow_mut().take().map_or_else(BTreeMap::new, |log| log.reads)
+	}
+
 	/// Get the value of storage after last state commitment.
 	pub fn original_storage_at(&self, address: &Address, key: &H256) -> TrieResult<H256> {
 		self.storage_at_inner(
@@ -869,18 +919,36 @@ impl<B: Backend> State<B> {
 	}
 
 	/// Commits our cached account changes into the trie.
+	///
+	/// Storage tries are committed on a worker pool once there are `PARALLEL_COMMIT_THRESHOLD` dirty accounts.
 	pub fn commit(&mut self) -> Result<(), Error> {
+		let dirty = self.cache.borrow().values().filter(|a| a.is_dirty()).count();
+		self.commit_with(dirty >= PARALLEL_COMMIT_THRESHOLD)
+	}
+
+	fn commit_with(&mut self, parallel: bool) -> Result<(), Error> {
 		assert!(self.checkpoints.borrow().is_empty());
 		// first, commit the sub trees.
 		let mut accounts = self.cache.borrow_mut();
-		for (address, ref mut a) in accounts.iter_mut().filter(|&(_, ref a)| a.is_dirty()) {
-			if let Some(ref mut account) = a.account {
-				let addr_hash = account.address_hash(address);
-				{
+		if parallel {
+			let mut dirty = accounts.iter_mut()
+				.filter(|&(_, ref a)| a.is_dirty())
+				.filter_map(|(address, a)| a.account.as_mut().map(|account| (*address, account)))
+				.collect::<Vec<_>>();
+			commit::commit_storage(&mut dirty, self.db.as_hashdb_mut(), &self.factories)?;
+		} else {
+			for (address, ref mut a) in accounts.iter_mut().filter(|&(_, ref a)| a.is_dirty()) {
+				if let Some(ref mut account) = a.account {
+					let addr_hash = account.address_hash(address);
 					let mut account_db = self.factories.accountdb.create(self.db.as_hashdb_mut(), addr_hash);
 					account.commit_storage(&self.factories.trie, account_db.as_hashdb_mut())?;
 					account.commit_code(account_db.as_hashdb_mut());
 				}
+			}
+		}
+
+		for (address, a) in accounts.iter().filter(|&(_, ref a)| a.is_dirty()) {
+			if let Some(ref account) = a.account {
 				if !account.is_empty() {
 					self.db.note_non_null_account(address);
 				}
@@ -1022,6 +1090,75 @@ impl<B: Backend> State<B> {
 		Ok(pod_state::diff_pod(&pod_state_pre, &pod_state_post))
 	}
 
+	/// Returns a `StateDiff` describing the changes made since checkpoint `index` was created,
+	/// without committing.
+	///
+	/// The changes are read from the checkpoint journal rather than by comparing tries: the accounts
+	/// altered since the checkpoint are those backed up by it or any later checkpoint, and the
+	/// earliest backup holds the account as it was at the checkpoint. Storage is compared over the
+	/// slots altered since the last commit, as in `diff_from`. Reverted checkpoints leave no backups
+	/// behind and discarded ones are merged into their parent, so nested checkpoints compose.
+	/// The diff is empty for an unknown checkpoint.
+	pub fn diff_since_checkpoint(&self, index: usize) -> TrieResult<StateDiff> {
+		// backed-up account of every address altered since the checkpoint, `None` if it wasn't cached.
+		let altered = {
+			let checkpoints = self.checkpoints.borrow();
+			let mut altered = BTreeMap::new();
+			for checkpoint in checkpoints.iter().skip(index) {
+				for (address, entry) in checkpoint.iter() {
+					altered.entry(*address).or_insert_with(|| entry.as_ref().map(|entry| entry.account.as_ref().map(Account::clone_dirty)));
+				}
+			}
+			altered
+		};
+
+		let mut pre = BTreeMap::new();
+		let mut post = BTreeMap::new();
+		for (address, backup) in altered {
+			let original = match backup {
+				Some(account) => account,
+				// not cached at the checkpoint, so unaltered since the last commit.
+				None => {
+					let db = self.factories.trie.readonly(self.db.as_hashdb(), &self.root)?;
+					let from_rlp = |b: &[u8]| Account::from_rlp(b).expect("decoding db value failed");
+					db.get_with(&address, from_rlp)?
+				},
+			};
+
+			let current = self.ensure_cached(&address, RequireCache::Code, true, |acc| acc.map(PodAccount::from_account))?;
+
+			if let Some(mut account) = original {
+				let code = {
+					let account_db = self.factories.accountdb.readonly(self.db.as_hashdb(), account.address_hash(&address));
+					account.cache_code(account_db.as_hashdb()).map(|code| code.to_vec())
+				};
+
+				let keys = account.storage_changes().keys()
+					.chain(current.iter().flat_map(|current| current.storage.keys()))
+					.cloned()
+					.collect::<BTreeSet<_>>();
+				let mut storage = BTreeMap::new();
+				for key in keys {
+					let value = self.checkpoint_storage_at(index, &address, &key)?.unwrap_or_else(H256::new);
+					storage.insert(key, value);
+				}
+
+				pre.insert(address, PodAccount {
+					balance: *account.balance(),
+					nonce: *account.nonce(),
+					storage,
+					code,
+				});
+			}
+
+			if let Some(current) = current {
+				post.insert(address, current);
+			}
+		}
+
+		Ok(pod_state::diff_pod(&PodState::from(pre), &PodState::from(post)))
+	}
+
 	/// Load required account data from the databases. Returns whether the cache succeeds.
 	#[must_use]
 	fn update_account_cache(require: RequireCache, account: &mut Account, state_db: &B, db: &HashDB<KeccakHasher>) -> bool {
@@ -1242,6 +1379,7 @@ impl Clone for State<StateDB> {
 			checkpoints: RefCell::new(Vec::new()),
 			account_start_nonce: self.account_start_nonce.clone(),
 			factories: self.factories.clone(),
+			storage_reads: RefCell::new(None),
 		}
 	}
 }
@@ -1263,6 +1401,8 @@ mod tests {
 	use ethcore_logger::init_log;
 	use trace::{FlatTrace, TraceError, trace};
 	use evm::CallType;
+	use types::account_diff::Diff;
+	use state_db::StateDB;
 
 	fn secret() -> Secret {
 		keccak("").into()
@@ -2484,6 +2624,93 @@ mod tests {
 		new_state.diff_from(state).unwrap();
 	}
 
+	// check the diff since `checkpoint` against comparing `state` with a copy taken at the checkpoint.
+	fn assert_diff_since_checkpoint(state: &State<StateDB>, checkpoint: usize, at_checkpoint: &State<StateDB>) {
+		let diff = state.diff_since_checkpoint(checkpoint).unwrap();
+		assert_eq!(diff, state.clone().diff_from(at_checkpoint.clone()).unwrap());
+	}
+
+	#[test]
+	fn diff_since_checkpoint_skips_reverted_creation() {
+		let mut state = get_temp_state();
+		let a: Address = 0xa.into();
+		let b: Address = 0xb.into();
+		let c: Address = 0xc.into();
+		state.add_balance(&a, &10.into(), CleanupMode::NoEmpty).unwrap();
+		state.commit().unwrap();
+
+		let c0 = state.checkpoint();
+		let at_c0 = state.clone();
+		state.add_balance(&b, &5.into(), CleanupMode::NoEmpty).unwrap();
+		state.checkpoint();
+		state.new_contract(&c, 1.into(), 0.into()).unwrap();
+		state.init_code(&c, vec![0x60, 0x00]).unwrap();
+		state.set_storage(&c, 1.into(), 2.into()).unwrap();
+		state.add_balance(&a, &1.into(), CleanupMode::NoEmpty).unwrap();
+		state.revert_to_checkpoint();
+
+		let diff = state.diff_since_checkpoint(c0).unwrap();
+		assert_eq!(diff.raw.keys().cloned().collect::<Vec<_>>(), vec![b]);
+		assert_eq!(diff.raw[&b].balance, Diff::Born(5.into()));
+		assert_diff_since_checkpoint(&state, c0, &at_c0);
+
+		state.revert_to_checkpoint();
+		assert!(state.diff_since_checkpoint(c0).unwrap().raw.is_empty());
+	}
+
+	#[test]
+	fn diff_since_checkpoint_overwritten_storage() {
+		let mut state = get_temp_state();
+		let a: Address = 0xa.into();
+		let k = H256::from(U256::from(1));
+		let k2 = H256::from(U256::from(2));
+		state.init_code(&a, vec![0x60, 0x00]).unwrap();
+		state.set_storage(&a, k, H256::from(U256::from(1))).unwrap();
+		state.commit().unwrap();
+
+		state.set_storage(&a, k, H256::from(U256::from(2))).unwrap();
+		let c0 = state.checkpoint();
+		let at_c0 = state.clone();
+		state.set_storage(&a, k, H256::from(U256::from(3))).unwrap();
+		let c1 = state.checkpoint();
+		let at_c1 = state.clone();
+		state.set_storage(&a, k, H256::from(U256::from(4))).unwrap();
+		state.set_storage(&a, k2, H256::from(U256::from(5))).unwrap();
+		assert_diff_since_checkpoint(&state, c1, &at_c1);
+		state.discard_checkpoint();
+
+		let diff = state.diff_since_checkpoint(c0).unwrap();
+		assert_eq!(diff.raw[&a].storage, vec![
+			(k, Diff::Changed(H256::from(U256::from(2)), H256::from(U256::from(4)))),
+			(k2, Diff::Changed(H256::new(), H256::from(U256::from(5)))),
+		].into_iter().collect());
+		assert!(diff.raw[&a].balance.is_same());
+		assert_diff_since_checkpoint(&state, c0, &at_c0);
+	}
+
+	#[test]
+	fn diff_since_checkpoint_suicide() {
+		let mut state = get_temp_state();
+		let a: Address = 0xa.into();
+		let k = H256::from(U256::from(1));
+		state.init_code(&a, vec![0x60, 0x00]).unwrap();
+		state.add_balance(&a, &7.into(), CleanupMode::NoEmpty).unwrap();
+		state.set_storage(&a, k, H256::from(U256::from(1))).unwrap();
+		state.commit().unwrap();
+
+		let c0 = state.checkpoint();
+		let at_c0 = state.clone();
+		state.checkpoint();
+		state.set_storage(&a, k, H256::from(U256::from(2))).unwrap();
+		state.kill_account(&a);
+		state.discard_checkpoint();
+
+		let diff = state.diff_since_checkpoint(c0).unwrap();
+		assert_eq!(diff.raw[&a].balance, Diff::Died(7.into()));
+		assert_eq!(diff.raw[&a].code, Diff::Died(vec![0x60, 0x00]));
+		assert_diff_since_checkpoint(&state, c0, &at_c0);
+	}
+
 	#[test]
 	fn should_kill_garbage() {
 		let a = 10.into();
@@ -2592,4 +2819,89 @@ mod tests {
 						   .into_iter().collect(),
 				   })).as_ref());
 	}
+
+
+	#[test]
+	fn should_record_first_storage_reads() {
+		let slot = |n: u64| H256::from(&U256::from(n));
+		let a: Address = 0xa.into();
+
+		let mut state = get_temp_state();
+		let mut info = EnvInfo::default();
+		info.gas_limit = 1_000_000.into();
+		let machine = make_frontier_machine(5);
+
+		// sstore(2, sload(0) + sload(1) + sload(1))
+		state.init_code(&a, FromHex::from_hex("600054600154016001540160025500").unwrap()).unwrap();
+		state.set_storage(&a, slot(0), slot(3)).unwrap();
+		state.set_storage(&a, slot(1), slot(4)).unwrap();
+		state.commit().unwrap();
+
+		let transaction = |nonce: u64| Transaction {
+			nonce: nonce.into(),
+			gas_price: 0.into(),
+			gas: 100_000.into(),
+			action: Action::Call(a),
+			value: 0.into(),
+			data: vec![],
+		}.sign(&secret(), None);
+
+		let original = state.clone();
+		state.record_storage_reads(10);
+		state.apply(&info, &machine, &transaction(0), false).unwrap();
+		let reads = state.drain_storage_reads();
+		let diff = state.diff_from(original).unwrap();
+
+		assert_eq!(reads.len(), 1);
+		assert_eq!(reads[&a].values, vec![(slot(0), slot(3)), (slot(1), slot(4))].into_iter().collect());
+		assert!(!reads[&a].truncated);
+		assert_eq!(diff.get()[&a].storage.keys().collect::<Vec<_>>(), vec![&slot(2)]);
+
+		// reads beyond the limit are dropped.
+		state.record_storage_reads(1);
+		state.apply(&info, &machine, &transaction(1), false).unwrap();
+		let reads = state.drain_storage_reads();
+		assert_eq!(reads[&a].values, vec![(slot(0), slot(3))].into_iter().collect());
+		assert!(reads[&a].truncated);
+
+		// nothing is recorded unless asked.
+		state.apply(&info, &machine, &transaction(2), false).unwrap();
+		assert!(state.drain_storage_reads().is_empty());
+	}
+
+	#[test]
+	fn parallel_commit_matches_sequential() {
+		fn slot(i: u64, j: u64) -> H256 { keccak(&[i, j].iter().map(|x| *x as u8).collect::<Vec<_>>()) }
+
+		fn build(parallel: bool) -> (H256, Vec<H256>) {
+			let address = |i: u64| Address::from(keccak(H256::from(i)));
+			let mut state = get_temp_state();
+			for i in 0..(PARALLEL_COMMIT_THRESHOLD as u64 * 2) {
+				state.add_balance(&address(i), &(i + 1).into(), CleanupMode::NoEmpty).unwrap();
+				for j in 0..4 {
+					state.set_storage(&address(i), slot(i, j), slot(j, i)).unwrap();
+				}
+				if i % 16 == 0 {
+					state.init_code(&address(i), vec![i as u8; 32]).unwrap();
+				}
+			}
+			state.commit_with(false).unwrap();
+
+			// clear some slots, overwrite others and create new accounts on top.
+			for i in 0..(PARALLEL_COMMIT_THRESHOLD as u64 * 3) {
+				state.set_storage(&address(i), slot(i, i % 4), H256::zero()).unwrap();
+				state.set_storage(&address(i), slot(i, 4), slot(i, i)).unwrap();
+			}
+			state.commit_with(parallel).unwrap();
+
+			let mut keys = state.db.as_hashdb().keys().into_iter()
+				.filter(|&(_, refs)| refs > 0)
+				.map(|(key, _)| key)
+				.collect::<Vec<_>>();
+			keys.sort();
+			(state.root().clone(), keys)
+		}
+
+		assert_eq!(build(true), build(false));
+	}
 }
@@ -16,6 +16,7 @@
 
 //! State database abstraction. For more info, see the doc for `StateDB`
 
+use std::cmp;
 use std::collections::{VecDeque, HashSet};
 use std::io;
 use std::sync::Arc;
@@ -23,7 +24,7 @@ use std::sync::Arc;
 use bloom_journal::{Bloom, BloomJournal};
 use byteorder::{LittleEndian, ByteOrder};
 use db::COL_ACCOUNT_BLOOM;
-use ethereum_types::{H256, Address};
+use ethereum_types::{H256, U256, Address};
 use hash::keccak;
 use hashdb::HashDB;
 use keccak_hasher::KeccakHasher;
@@ -34,6 +35,8 @@ use lru_cache::LruCache;
 use memory_cache::MemoryLruCache;
 use parking_lot::Mutex;
 use state::{self, Account};
+use trie::Trie;
+use ethtrie::{TrieDB, Result as TrieResult};
 
 /// Value used to initialize bloom bitmap size.
 ///
@@ -53,6 +56,88 @@ const STATE_CACHE_BLOCKS: usize = 12;
 // The percentage of supplied cache size to go to accounts.
 const ACCOUNT_CACHE_RATIO: usize = 90;
 
+// Number of accounts walked between progress reports while checking or rebuilding the account bloom.
+const BLOOM_PROGRESS_INTERVAL: u64 = 100_000;
+
+// Number of bloom parts written per batch while rebuilding the account bloom.
+const BLOOM_REBUILD_BATCH: usize = 8192;
+
+/// Accounts of a state trie missing from the account bloom, see `StateDB::verify_account_bloom`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BloomReport {
+	/// Number of non-empty accounts in the trie.
+	pub accounts: u64,
+	/// Address hashes of the non-empty accounts absent from the bloom, which are wrongly reported as nonexistent.
+	pub missing: Vec<H256>,
+}
+
+impl BloomReport {
+	/// Whether every non-empty account of the trie is in the bloom.
+	pub fn is_consistent(&self) -> bool {
+		self.missing.is_empty()
+	}
+}
+
+/// Counters of a cache of `StateDB`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+	/// Lookups served from the cache.
+	pub hits: u64,
+	/// Lookups not served from the cache.
+	pub misses: u64,
+	/// Entries inserted into the cache.
+	pub inserts: u64,
+	/// Entries evicted to make room for new ones.
+	pub evictions: u64,
+}
+
+impl CacheStats {
+	/// Number of lookups.
+	pub fn lookups(&self) -> u64 {
+		self.hits + self.misses
+	}
+
+	/// Percentage of lookups served from the cache, 100 if there were none.
+	pub fn hit_rate(&self) -> u64 {
+		match self.lookups() {
+			0 => 100,
+			lookups => self.hits * 100 / lookups,
+		}
+	}
+
+	fn record_lookup(&mut self, hit: bool) {
+		if hit {
+			self.hits += 1;
+		} else {
+			self.misses += 1;
+		}
+	}
+}
+
+/// Counters of the caches of `StateDB`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateCacheStats {
+	/// Counters of the account cache.
+	pub accounts: CacheStats,
+	/// Counters of the code cache.
+	pub code: CacheStats,
+	/// Cached accounts invalidated because a reorg enacted or retracted a block modifying them.
+	pub reorg_invalidations: u64,
+	/// Reorgs clearing the whole account cache, because their route wasn't among the recently committed blocks.
+	pub reorg_wipes: u64,
+}
+
+/// Growth of the account cache when it serves too few lookups, see `StateDB::adapt_account_cache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveCacheConfig {
+	/// Number of account lookups the hit rate is measured over.
+	pub window: u64,
+	/// Hit rate in percent below which the account cache grows.
+	pub min_hit_rate: u64,
+	/// Memory in bytes shared by the account cache and the blockchain caches.
+	pub budget: usize,
+}
+
 /// Shared canonical state cache.
 struct AccountCache {
 	/// DB Account cache. `None` indicates that account is known to be missing.
@@ -62,6 +147,80 @@ struct AccountCache {
 	/// Information on the modifications in recently committed blocks; specifically which addresses
 	/// changed in which block. Ordered by block number.
 	modifications: VecDeque<BlockChanges>,
+	/// Counters since the cache was created.
+	stats: CacheStats,
+	/// Lookups since the current window of the adaptive mode started.
+	window: CacheStats,
+	/// Accounts invalidated by reorgs since the cache was created.
+	reorg_invalidations: u64,
+	/// Reorgs clearing the cache since it was created.
+	reorg_wipes: u64,
+}
+
+impl AccountCache {
+	fn record_lookup(&mut self, hit: bool) {
+		self.stats.record_lookup(hit);
+		self.window.record_lookup(hit);
+	}
+
+	/// Invalidate the accounts modified by the blocks of a reorg route, marking the enacted blocks
+	/// canonical and the retracted ones not. Clears the whole cache if the route isn't covered by
+	/// the modifications of the recently committed blocks.
+	fn invalidate_route(&mut self, enacted: &[&H256], retracted: &[H256]) {
+		if enacted.is_empty() && retracted.is_empty() {
+			return;
+		}
+
+		// union of the accounts modified along the route, if all of its blocks are known.
+		let mut touched = HashSet::new();
+		let mut covered = enacted.len() + retracted.len() <= STATE_CACHE_BLOCKS;
+		if covered {
+			for hash in enacted.iter().cloned().chain(retracted) {
+				match self.modifications.iter().find(|m| &m.hash == hash) {
+					Some(m) => touched.extend(m.accounts.iter().cloned()),
+					None => {
+						trace!("Unknown block {:?} in reorg route", hash);
+						covered = false;
+						break;
+					},
+				}
+			}
+		}
+
+		if !covered {
+			// We don't know anything about the block; clear everything
+			trace!("Wiping cache");
+			self.accounts.clear();
+			self.modifications.clear();
+			self.reorg_wipes += 1;
+			return;
+		}
+
+		for m in self.modifications.iter_mut() {
+			if enacted.contains(&&m.hash) {
+				trace!("Reverting enacted block {:?}", m.hash);
+				m.is_canon = true;
+			} else if retracted.contains(&m.hash) {
+				trace!("Retracting block {:?}", m.hash);
+				m.is_canon = false;
+			}
+		}
+
+		for address in &touched {
+			if self.accounts.remove(address).is_some() {
+				trace!("Invalidated address {:?}", address);
+				self.reorg_invalidations += 1;
+			}
+		}
+	}
+}
+
+/// Shared code cache.
+struct CodeCache {
+	/// Maps code hashes to shared bytes.
+	code: MemoryLruCache<H256, Arc<Vec<u8>>>,
+	/// Counters since the cache was created.
+	stats: CacheStats,
 }
 
 /// Buffered account cache item.
@@ -109,8 +268,8 @@ pub struct StateDB {
 	db: Box<JournalDB>,
 	/// Shared canonical state cache.
 	account_cache: Arc<Mutex<AccountCache>>,
-	/// DB Code cache. Maps code hashes to shared bytes.
-	code_cache: Arc<Mutex<MemoryLruCache<H256, Arc<Vec<u8>>>>>,
+	/// Shared code cache.
+	code_cache: Arc<Mutex<CodeCache>>,
 	/// Local dirty cache.
 	local_cache: Vec<CacheQueueItem>,
 	/// Shared account bloom. Does not handle chain reorganizations.
@@ -142,8 +301,15 @@ impl StateDB {
 			account_cache: Arc::new(Mutex::new(AccountCache {
 				accounts: LruCache::new(cache_items),
 				modifications: VecDeque::new(),
+				stats: CacheStats::default(),
+				window: CacheStats::default(),
+				reorg_invalidations: 0,
+				reorg_wipes: 0,
+			})),
+			code_cache: Arc::new(Mutex::new(CodeCache {
+				code: MemoryLruCache::new(code_cache_size),
+				stats: CacheStats::default(),
 			})),
-			code_cache: Arc::new(Mutex::new(MemoryLruCache::new(code_cache_size))),
 			local_cache: Vec::new(),
 			account_bloom: Arc::new(Mutex::new(bloom)),
 			cache_size: cache_size,
@@ -196,6 +362,75 @@ impl StateDB {
 		Ok(())
 	}
 
+	/// Walk the account trie at `state_root`, reporting the accounts missing from the account bloom.
+	///
+	/// Empty accounts are never noted in the bloom and are skipped. `progress` is called with the
+	/// number of accounts walked so far.
+	pub fn verify_account_bloom(&self, state_root: &H256, progress: &Fn(u64)) -> TrieResult<BloomReport> {
+		let mut missing = Vec::new();
+		let accounts = self.walk_accounts(state_root, progress, |hash| {
+			if !self.account_bloom.lock().check(hash) {
+				missing.push(H256::from_slice(hash));
+			}
+		})?;
+
+		Ok(BloomReport { accounts, missing })
+	}
+
+	/// Rebuild the account bloom from the account trie at `state_root`, replacing the bloom in the
+	/// database and the one shared by all clones of this instance. Returns the number of accounts noted.
+	///
+	/// The bloom is written in several batches, so block import must be held meanwhile. An
+	/// interrupted rebuild leaves a mix of the old and new blooms behind and must be run again.
+	pub fn rebuild_account_bloom(&self, state_root: &H256, progress: &Fn(u64)) -> Result<u64, ::error::Error> {
+		let mut bloom = Bloom::new(ACCOUNT_BLOOM_SPACE, DEFAULT_ACCOUNT_PRESET);
+		let accounts = self.walk_accounts(state_root, progress, |hash| bloom.set(hash)).map_err(|e| *e)?;
+
+		// write every part, clearing those the old bloom set and the new one doesn't.
+		let journal = bloom.drain_journal();
+		let mut parts = vec![0u64; ACCOUNT_BLOOM_SPACE / 8];
+		for &(index, value) in &journal.entries {
+			parts[index] = value;
+		}
+
+		for (i, chunk) in parts.chunks(BLOOM_REBUILD_BATCH).enumerate() {
+			let mut batch = DBTransaction::new();
+			Self::commit_bloom(&mut batch, BloomJournal {
+				hash_functions: journal.hash_functions,
+				entries: chunk.iter().enumerate().map(|(j, value)| (i * BLOOM_REBUILD_BATCH + j, *value)).collect(),
+			})?;
+			self.db.backing().write(batch)?;
+		}
+
+		*self.account_bloom.lock() = Bloom::from_parts(&parts, journal.hash_functions);
+		info!(target: "account_bloom", "Rebuilt account bloom with {} accounts", accounts);
+		Ok(accounts)
+	}
+
+	// call `f` with the address hash of every non-empty account of the trie at `state_root`.
+	fn walk_accounts<F>(&self, state_root: &H256, progress: &Fn(u64), mut f: F) -> TrieResult<u64>
+		where F: FnMut(&[u8])
+	{
+		let empty_rlp = Account::new_basic(U256::zero(), U256::zero()).rlp();
+		let trie = TrieDB::new(self.as_hashdb(), state_root)?;
+		let mut accounts = 0;
+		for item in trie.iter()? {
+			let (hash, rlp) = item?;
+			if rlp[..] == empty_rlp[..] {
+				continue;
+			}
+
+			f(&hash);
+			accounts += 1;
+			if accounts % BLOOM_PROGRESS_INTERVAL == 0 {
+				progress(accounts);
+			}
+		}
+
+		progress(accounts);
+		Ok(accounts)
+	}
+
 	/// Journal all recent operations under the given era and ID.
 	pub fn journal_under(&mut self, batch: &mut DBTransaction, now: u64, id: &H256) -> io::Result<u32> {
 		{
@@ -214,6 +449,12 @@ impl StateDB {
 		self.db.mark_canonical(batch, end_era, canon_id)
 	}
 
+	/// Mark the earliest eras canonical, `batch_eras` at a time, until the journal holds at most
+	/// `budget` bytes or the next era is later than `last_era`. Returns the number of eras marked.
+	pub fn flush_journal(&mut self, budget: usize, last_era: u64, batch_eras: usize, canonical: &Fn(u64) -> Option<H256>) -> io::Result<usize> {
+		self.db.flush_to_budget(budget, last_era, batch_eras, canonical)
+	}
+
 	/// Propagate local cache into the global cache and synchonize
 	/// the global cache with the best block state.
 	/// This function updates the global cache by removing entries
@@ -227,44 +468,8 @@ impl StateDB {
 
 		// Purge changes from re-enacted and retracted blocks.
 		// Filter out commiting block if any.
-		let mut clear = false;
-		for block in enacted.iter().filter(|h| self.commit_hash.as_ref().map_or(true, |p| *h != p)) {
-			clear = clear || {
-				if let Some(ref mut m) = cache.modifications.iter_mut().find(|m| &m.hash == block) {
-					trace!("Reverting enacted block {:?}", block);
-					m.is_canon = true;
-					for a in &m.accounts {
-						trace!("Reverting enacted address {:?}", a);
-						cache.accounts.remove(a);
-					}
-					false
-				} else {
-					true
-				}
-			};
-		}
-
-		for block in retracted {
-			clear = clear || {
-				if let Some(ref mut m) = cache.modifications.iter_mut().find(|m| &m.hash == block) {
-					trace!("Retracting block {:?}", block);
-					m.is_canon = false;
-					for a in &m.accounts {
-						trace!("Retracted address {:?}", a);
-						cache.accounts.remove(a);
-					}
-					false
-				} else {
-					true
-				}
-			};
-		}
-		if clear {
-			// We don't know anything about the block; clear everything
-			trace!("Wiping cache");
-			cache.accounts.clear();
-			cache.modifications.clear();
-		}
+		let enacted: Vec<_> = enacted.iter().filter(|h| self.commit_hash.as_ref().map_or(true, |p| *h != p)).collect();
+		cache.invalidate_route(&enacted, retracted);
 
 		// Propagate cache only if committing on top of the latest canonical state
 		// blocks are ordered by number and only one block with a given number is marked as canonical
@@ -289,7 +494,13 @@ impl StateDB {
 							continue;
 						}
 					}
+					let len = cache.accounts.len();
+					let is_new = !cache.accounts.contains_key(&account.address);
 					cache.accounts.insert(account.address, acc);
+					cache.stats.inserts += 1;
+					if is_new && cache.accounts.len() == len {
+						cache.stats.evictions += 1;
+					}
 				}
 			}
 
@@ -361,7 +572,7 @@ impl StateDB {
 		// TODO: account for LRU-cache overhead; this is a close approximation.
 		self.db.mem_used() + {
 			let accounts = self.account_cache.lock().accounts.len();
-			let code_size = self.code_cache.lock().current_size();
+			let code_size = self.code_cache.lock().code.current_size();
 			code_size + accounts * ::std::mem::size_of::<Option<Account>>()
 		}
 	}
@@ -376,6 +587,47 @@ impl StateDB {
 		self.cache_size
 	}
 
+	/// Counters of the account and code caches, shared by all clones of this instance.
+	pub fn cache_stats(&self) -> StateCacheStats {
+		let account_cache = self.account_cache.lock();
+		StateCacheStats {
+			accounts: account_cache.stats,
+			code: self.code_cache.lock().stats,
+			reorg_invalidations: account_cache.reorg_invalidations,
+			reorg_wipes: account_cache.reorg_wipes,
+		}
+	}
+
+	/// Grow the account cache if the hit rate over the last window of lookups is below the threshold
+	/// of `config`. The cache at most doubles and, with `other_caches` bytes used by the blockchain
+	/// caches, stays within the budget. Returns whether the cache grew.
+	///
+	/// Does nothing until the window is complete, a new window starts after each check.
+	pub fn adapt_account_cache(&self, config: &AdaptiveCacheConfig, other_caches: usize) -> bool {
+		let mut cache = self.account_cache.lock();
+		if cache.window.lookups() < config.window {
+			return false;
+		}
+
+		let hit_rate = cache.window.hit_rate();
+		cache.window = CacheStats::default();
+		if hit_rate >= config.min_hit_rate {
+			return false;
+		}
+
+		let max_items = config.budget.saturating_sub(other_caches) / ::std::mem::size_of::<Option<Account>>();
+		let capacity = cache.accounts.capacity();
+		let grown = cmp::min(cmp::max(capacity * 2, 1), max_items);
+		if grown <= capacity {
+			trace!(target: "state_db", "Account cache hit rate {}% but no room to grow", hit_rate);
+			return false;
+		}
+
+		trace!(target: "state_db", "Account cache hit rate {}%, growing from {} to {} entries", hit_rate, capacity, grown);
+		cache.accounts.set_capacity(grown);
+		true
+	}
+
 	/// Check if the account can be returned from cache by matching current block parent hash against canonical
 	/// state and filtering out account modified in later blocks.
 	fn is_allowed(addr: &Address, parent_hash: &Option<H256>, modifications: &VecDeque<BlockChanges>) -> bool {
@@ -428,31 +680,44 @@ impl state::Backend for StateDB {
 
 	fn cache_code(&self, hash: H256, code: Arc<Vec<u8>>) {
 		let mut cache = self.code_cache.lock();
+		let cache = &mut *cache;
 
-		cache.insert(hash, code);
+		let is_new = cache.code.get_mut(&hash).is_none();
+		let expected_len = cache.code.backstore().len() + is_new as usize;
+		cache.code.insert(hash, code);
+		cache.stats.inserts += 1;
+		cache.stats.evictions += expected_len.saturating_sub(cache.code.backstore().len()) as u64;
 	}
 
 	fn get_cached_account(&self, addr: &Address) -> Option<Option<Account>> {
 		let mut cache = self.account_cache.lock();
 		if !Self::is_allowed(addr, &self.parent_hash, &cache.modifications) {
+			cache.record_lookup(false);
 			return None;
 		}
-		cache.accounts.get_mut(addr).map(|a| a.as_ref().map(|a| a.clone_basic()))
+		let account = cache.accounts.get_mut(addr).map(|a| a.as_ref().map(|a| a.clone_basic()));
+		cache.record_lookup(account.is_some());
+		account
 	}
 
 	fn get_cached<F, U>(&self, a: &Address, f: F) -> Option<U>
 		where F: FnOnce(Option<&mut Account>) -> U {
 		let mut cache = self.account_cache.lock();
 		if !Self::is_allowed(a, &self.parent_hash, &cache.modifications) {
+			cache.record_lookup(false);
 			return None;
 		}
-		cache.accounts.get_mut(a).map(|c| f(c.as_mut()))
+		let result = cache.accounts.get_mut(a).map(|c| f(c.as_mut()));
+		cache.record_lookup(result.is_some());
+		result
 	}
 
 	fn get_cached_code(&self, hash: &H256) -> Option<Arc<Vec<u8>>> {
 		let mut cache = self.code_cache.lock();
 
-		cache.get_mut(hash).map(|code| code.clone())
+		let code = cache.code.get_mut(hash).map(|code| code.clone());
+		cache.stats.record_lookup(code.is_some());
+		code
 	}
 
 	fn note_non_null_account(&self, address: &Address) {
@@ -481,8 +746,10 @@ mod tests {
 	use ethereum_types::{H256, U256, Address};
 	use kvdb::DBTransaction;
 	use test_helpers::get_temp_state_db;
+	use std::sync::Arc;
 	use state::{Account, Backend};
 	use ethcore_logger::init_log;
+	use super::{AdaptiveCacheConfig, CacheStats};
 
 	#[test]
 	fn state_db_smoke() {
@@ -550,4 +817,184 @@ mod tests {
 		let s = state_db.boxed_clone_canon(&h3a);
 		assert!(s.get_cached_account(&address).is_none());
 	}
+
+	#[test]
+	fn reorg_invalidates_only_touched_accounts() {
+		let state_db = get_temp_state_db();
+		let touched = Address::from(1);
+		let unrelated = Address::from(2);
+		let root_parent = H256::random();
+		let h0 = H256::random();
+		let h1a = H256::random();
+		let h1b = H256::random();
+		let h2b = H256::random();
+		let h3b = H256::random();
+		let mut batch = DBTransaction::new();
+
+		// blocks  [ 2b 1b 1a(c) 0 ], `touched` is modified in 1a and 1b.
+		let mut s = state_db.boxed_clone_canon(&root_parent);
+		s.add_to_account_cache(touched, Some(Account::new_basic(1.into(), 0.into())), false);
+		s.add_to_account_cache(unrelated, Some(Account::new_basic(10.into(), 0.into())), false);
+		s.journal_under(&mut batch, 0, &h0).unwrap();
+		s.sync_cache(&[], &[], true);
+
+		let mut s = state_db.boxed_clone_canon(&h0);
+		s.add_to_account_cache(touched, Some(Account::new_basic(2.into(), 0.into())), true);
+		s.journal_under(&mut batch, 1, &h1a).unwrap();
+		s.sync_cache(&[], &[], true);
+
+		let mut s = state_db.boxed_clone_canon(&h0);
+		s.add_to_account_cache(touched, Some(Account::new_basic(3.into(), 0.into())), true);
+		s.journal_under(&mut batch, 1, &h1b).unwrap();
+		s.sync_cache(&[], &[], false);
+
+		// reorg to 2b
+		let mut s = state_db.boxed_clone_canon(&h1b);
+		s.journal_under(&mut batch, 2, &h2b).unwrap();
+		s.sync_cache(&[h1b.clone(), h2b.clone()], &[h1a.clone()], true);
+
+		let stats = state_db.cache_stats();
+		assert_eq!((stats.reorg_invalidations, stats.reorg_wipes), (1, 0));
+
+		let s = state_db.boxed_clone_canon(&h2b);
+		assert_eq!(s.get_cached_account(&unrelated).unwrap().unwrap().balance(), &U256::from(10));
+		assert!(s.get_cached_account(&touched).is_none());
+		assert_eq!(state_db.cache_stats().accounts.hits, stats.accounts.hits + 1);
+
+		// a reorg from an unknown block clears the cache.
+		let mut s = state_db.boxed_clone_canon(&h2b);
+		s.journal_under(&mut batch, 3, &h3b).unwrap();
+		s.sync_cache(&[H256::random(), h3b.clone()], &[h2b.clone()], true);
+		assert_eq!(state_db.cache_stats().reorg_wipes, 1);
+
+		let s = state_db.boxed_clone_canon(&h3b);
+		assert!(s.get_cached_account(&unrelated).is_none());
+	}
+
+	#[test]
+	fn cache_stats_count_lookups_inserts_and_evictions() {
+		let state_db = get_temp_state_db();
+		state_db.account_cache.lock().accounts.set_capacity(1);
+		let a = Address::from(1);
+		let b = Address::from(2);
+		let root_parent = H256::random();
+		let h0 = H256::random();
+		let h1 = H256::random();
+		let mut batch = DBTransaction::new();
+
+		let mut s = state_db.boxed_clone_canon(&root_parent);
+		assert!(s.get_cached_account(&a).is_none());
+		s.add_to_account_cache(a, Some(Account::new_basic(1.into(), 0.into())), false);
+		s.journal_under(&mut batch, 0, &h0).unwrap();
+		s.sync_cache(&[], &[], true);
+
+		let mut s = state_db.boxed_clone_canon(&h0);
+		assert!(s.get_cached_account(&a).is_some());
+		s.add_to_account_cache(b, Some(Account::new_basic(2.into(), 0.into())), false);
+		s.journal_under(&mut batch, 1, &h1).unwrap();
+		s.sync_cache(&[], &[], true);
+
+		// `b` took the only slot.
+		let s = state_db.boxed_clone_canon(&h1);
+		assert!(s.get_cached_account(&a).is_none());
+		assert_eq!(state_db.cache_stats().accounts, CacheStats { hits: 1, misses: 2, inserts: 2, evictions: 1 });
+
+		let code = Arc::new(vec![1, 2, 3]);
+		s.cache_code(H256::from(1), code.clone());
+		assert_eq!(s.get_cached_code(&H256::from(1)), Some(code));
+		assert!(s.get_cached_code(&H256::from(2)).is_none());
+		assert_eq!(state_db.cache_stats().code, CacheStats { hits: 1, misses: 1, inserts: 1, evictions: 0 });
+	}
+
+	#[test]
+	fn adaptive_account_cache_grows_within_budget() {
+		let state_db = get_temp_state_db();
+		state_db.account_cache.lock().accounts.set_capacity(2);
+		let item_size = ::std::mem::size_of::<Option<Account>>();
+		let other_caches = 1000;
+		let config = AdaptiveCacheConfig {
+			window: 4,
+			min_hit_rate: 50,
+			budget: 5 * item_size + other_caches,
+		};
+		let capacity = || state_db.account_cache.lock().accounts.capacity();
+		let s = state_db.boxed_clone_canon(&H256::random());
+		let miss = |count| for _ in 0..count {
+			assert!(s.get_cached_account(&Address::from(1)).is_none());
+		};
+
+		// no decision before the window is complete.
+		miss(3);
+		assert!(!s.adapt_account_cache(&config, other_caches));
+		miss(1);
+		assert!(s.adapt_account_cache(&config, other_caches));
+		assert_eq!(capacity(), 4);
+
+		// doubling would exceed the budget.
+		miss(4);
+		assert!(s.adapt_account_cache(&config, other_caches));
+		assert_eq!(capacity(), 5);
+
+		miss(4);
+		assert!(!s.adapt_account_cache(&config, other_caches));
+		assert_eq!(capacity(), 5);
+	}
+
+	#[test]
+	fn rebuilds_corrupted_account_bloom() {
+		use bloom_journal::Bloom;
+		use hash::keccak;
+		use state::{CleanupMode, State};
+		use super::{StateDB, ACCOUNT_BLOOM_SPACE, DEFAULT_ACCOUNT_PRESET};
+
+		let addresses = (1..6u64).map(Address::from).collect::<Vec<_>>();
+		let empty = Address::from(0xe);
+		let mut state = State::new(get_temp_state_db(), U256::zero(), Default::default());
+		for address in &addresses {
+			state.add_balance(address, &1.into(), CleanupMode::NoEmpty).unwrap();
+		}
+		state.add_balance(&empty, &0.into(), CleanupMode::ForceCreate).unwrap();
+		state.commit().unwrap();
+		let (root, mut db) = state.drop();
+		let mut batch = DBTransaction::new();
+		db.journal_under(&mut batch, 0, &H256::zero()).unwrap();
+		db.journal_db().backing().write(batch).unwrap();
+
+		let state_at = |db: &StateDB| State::from_existing(db.boxed_clone(), root, U256::zero(), Default::default()).unwrap();
+		assert!(db.verify_account_bloom(&root, &|_| {}).unwrap().is_consistent());
+
+		// lose two of the accounts.
+		{
+			let mut bloom = Bloom::new(ACCOUNT_BLOOM_SPACE, DEFAULT_ACCOUNT_PRESET);
+			for address in &addresses[2..] {
+				bloom.set(&*keccak(address));
+			}
+			*db.account_bloom.lock() = bloom;
+		}
+		assert_eq!(state_at(&db).balance(&addresses[0]).unwrap(), U256::zero());
+
+		let report = db.verify_account_bloom(&root, &|_| {}).unwrap();
+		assert_eq!(report.accounts, 5);
+		let mut missing = report.missing.clone();
+		missing.sort();
+		let mut lost = addresses[..2].iter().map(|address| keccak(address)).collect::<Vec<_>>();
+		lost.sort();
+		assert_eq!(missing, lost);
+
+		let progress = ::std::cell::Cell::new(0);
+		assert_eq!(db.rebuild_account_bloom(&root, &|accounts| progress.set(accounts)).unwrap(), 5);
+		assert_eq!(progress.get(), 5);
+		assert!(db.verify_account_bloom(&root, &|_| {}).unwrap().is_consistent());
+
+		let state = state_at(&db);
+		for address in &addresses {
+			assert!(state.exists(address).unwrap());
+			assert_eq!(state.balance(address).unwrap(), 1.into());
+		}
+		assert!(state.exists(&empty).unwrap());
+
+		// the rebuilt bloom is persisted.
+		let persisted = StateDB::load_bloom(&**db.journal_db().backing());
+		assert!(addresses.iter().all(|address| persisted.check(&*keccak(address))));
+	}
 }
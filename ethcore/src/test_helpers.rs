@@ -16,7 +16,7 @@
 
 //! Set of different helpers for client tests
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{fs, io};
 use account_provider::AccountProvider;
@@ -46,6 +46,8 @@ use tempdir::TempDir;
 use verification::queue::kind::blocks::Unverified;
 use encoded;
 
+pub use blockchain::generator;
+
 /// Creates test block with corresponding header
 pub fn create_test_block(header: &Header) -> Bytes {
 	let mut rlp = RlpStream::new_list(3);
@@ -311,6 +313,7 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 		blooms: blooms_db::Database,
 		trace_blooms: blooms_db::Database,
 		key_value: Arc<KeyValueDB>,
+		path: PathBuf,
 	}
 
 	impl BlockChainDB for RestorationDB {
@@ -325,6 +328,10 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 		fn trace_blooms(&self) -> &blooms_db::Database {
 			&self.trace_blooms
 		}
+
+		fn path(&self) -> Option<&Path> {
+			Some(&self.path)
+		}
 	}
 
 	impl BlockChainDBHandler for RestorationDBHandler {
@@ -340,6 +347,7 @@ pub fn restoration_db_handler(config: kvdb_rocksdb::DatabaseConfig) -> Box<Block
 				blooms,
 				trace_blooms,
 				key_value,
+				path: db_path.to_path_buf(),
 			};
 			Ok(Arc::new(db))
 		}
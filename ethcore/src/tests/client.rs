@@ -365,3 +365,61 @@ fn transaction_proof() {
 	assert_eq!(state.balance(&Address::default()).unwrap(), 5.into());
 	assert_eq!(state.balance(&address).unwrap(), 95.into());
 }
+
+#[test]
+fn backs_database_up_during_import() {
+	use std::thread;
+	use client::Nonce;
+	use kvdb_rocksdb::DatabaseConfig;
+
+	const TX_PER: usize = 2;
+
+	let source = test_helpers::generate_dummy_client_with_spec_and_data(Spec::new_null, 20, TX_PER, &[1.into()]);
+	let block = |n| Unverified::from_rlp(source.block(BlockId::Number(n)).unwrap().into_inner()).unwrap();
+
+	let tempdir = TempDir::new("").unwrap();
+	let db_handler = test_helpers::restoration_db_handler(DatabaseConfig::with_columns(::db::NUM_COLUMNS));
+	let spec = Spec::new_null();
+	let open_client = |path: &::std::path::Path| Client::new(
+		ClientConfig::default(),
+		&spec,
+		db_handler.open(path).unwrap(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+
+	let client = open_client(&tempdir.path().join("db"));
+	for n in 1..11 {
+		client.import_block(block(n)).unwrap();
+	}
+	client.flush_queue();
+	client.import_verified_blocks();
+
+	for n in 11..21 {
+		client.import_block(block(n)).unwrap();
+	}
+	client.flush_queue();
+
+	let importer = {
+		let client = client.clone();
+		thread::spawn(move || client.import_verified_blocks())
+	};
+	let backup_path = tempdir.path().join("backup");
+	let report = client.backup_database(&backup_path).unwrap();
+	importer.join().unwrap();
+
+	assert!(report.best_block_number >= 10 && report.best_block_number <= 20);
+	assert_eq!(report.best_block_hash, source.block_hash(BlockId::Number(report.best_block_number)).unwrap());
+	assert!(report.size > 0);
+	assert!(client.backup_database(&backup_path).is_err());
+	drop(client);
+
+	let copy = open_client(&backup_path);
+	assert_eq!(copy.chain_info().best_block_number, report.best_block_number);
+	assert_eq!(copy.chain_info().best_block_hash, report.best_block_hash);
+
+	let sender = KeyPair::from_secret_slice(&keccak("")).unwrap().address();
+	let nonce = copy.nonce(&sender, BlockId::Latest).unwrap();
+	assert_eq!(nonce, U256::from(report.best_block_number as usize * TX_PER));
+	assert_eq!(Some(nonce), source.nonce(&sender, BlockId::Number(report.best_block_number)));
+}
@@ -185,6 +185,8 @@ fn can_trace_block_and_uncle_reward() {
 		range: (BlockId::Number(1)..BlockId::Number(3)),
 		from_address: vec![],
 		to_address: vec![],
+		kind: None,
+		status: None,
 		after: None,
 		count: None,
 	};
@@ -207,3 +209,60 @@ fn can_trace_block_and_uncle_reward() {
 	let traces = client.block_traces(BlockId::Number(3));
 	assert_eq!(traces.unwrap().len(), 3);
 }
+
+#[test]
+fn prunes_traces_beyond_history() {
+	let spec = Spec::new_test_with_reward();
+
+	let mut client_config = ClientConfig::default();
+	client_config.tracing.enabled = true;
+	client_config.tracing.history = Some(100);
+	let client = Client::new(
+		client_config,
+		&spec,
+		test_helpers::new_db(),
+		Arc::new(Miner::new_for_tests(&spec, None)),
+		IoChannel::disconnected(),
+	).unwrap();
+
+	// blocks #1 to #251, each with the reward trace of its author.
+	for _ in 0..251 {
+		let block = client.prepare_open_block(Address::default(), (3141562.into(), 31415620.into()), vec![]).unwrap();
+		let block = block.close_and_lock().unwrap().seal(&*spec.engine, vec![]).unwrap();
+		client.import_sealed_block(block).unwrap();
+	}
+	assert_eq!(client.chain_info().best_block_number, 251);
+	assert_eq!(client.trace_horizon(), Some(0));
+
+	let filter = || TraceFilter {
+		range: BlockId::Number(1)..BlockId::Latest,
+		from_address: vec![],
+		to_address: vec![],
+		kind: None,
+		status: None,
+		after: None,
+		count: None,
+	};
+	let traced_blocks = || client.filter_traces(filter()).unwrap().into_iter().map(|trace| {
+		match trace.action {
+			Reward(ref reward) => assert_eq!(reward.reward_type, RewardType::Block),
+			_ => panic!("blocks without transactions only have reward traces"),
+		}
+		trace.block_number
+	}).collect::<Vec<_>>();
+	assert_eq!(traced_blocks(), (1..252).collect::<Vec<_>>());
+
+	// keeps the traces of the last 100 blocks.
+	client.tick(true);
+	assert_eq!(client.trace_horizon(), Some(152));
+	assert!(client.block_traces(BlockId::Number(1)).is_none());
+	assert!(client.block_traces(BlockId::Number(151)).is_none());
+	assert_eq!(client.block_traces(BlockId::Number(152)).map(|traces| traces.len()), Some(1));
+	assert_eq!(client.block_traces(BlockId::Number(251)).map(|traces| traces.len()), Some(1));
+	assert_eq!(traced_blocks(), (152..252).collect::<Vec<_>>());
+
+	// nothing more to prune until new blocks are imported.
+	client.tick(true);
+	assert_eq!(client.trace_horizon(), Some(152));
+	assert_eq!(traced_blocks(), (152..252).collect::<Vec<_>>());
+}
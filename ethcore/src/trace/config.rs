@@ -26,6 +26,10 @@ pub struct Config {
 	pub pref_cache_size: usize,
 	/// Max cache-size.
 	pub max_cache_size: usize,
+	/// Max number of storage reads recorded in a state diff of a single transaction.
+	pub max_storage_reads: usize,
+	/// Number of recent blocks to keep traces of, `None` to keep the traces of all blocks.
+	pub history: Option<u64>,
 }
 
 impl Default for Config {
@@ -34,6 +38,8 @@ impl Default for Config {
 			enabled: false,
 			pref_cache_size: 15 * 1024 * 1024,
 			max_cache_size: 20 * 1024 * 1024,
+			max_storage_reads: 10_000,
+			history: None,
 		}
 	}
 }
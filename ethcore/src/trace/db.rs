@@ -15,11 +15,12 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Trace database.
+use std::cmp;
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use blockchain::{BlockChainDB};
 use heapsize::HeapSizeOf;
-use ethereum_types::{H256, H264};
+use ethereum_types::{H256, H264, Bloom};
 use kvdb::{DBTransaction};
 use parking_lot::RwLock;
 use header::BlockNumber;
@@ -30,6 +31,12 @@ use cache_manager::CacheManager;
 
 const TRACE_DB_VER: &'static [u8] = b"1.0";
 
+// key of the number of the oldest block traces are kept for.
+const HORIZON_KEY: &'static [u8] = b"horizon";
+
+// maximal number of blocks pruned at once.
+const PRUNE_BATCH: u64 = 1000;
+
 #[derive(Debug, Copy, Clone)]
 enum TraceDBIndex {
 	/// Block traces index.
@@ -64,6 +71,10 @@ pub struct TraceDB<T> where T: DatabaseExtras {
 	enabled: bool,
 	/// extras
 	extras: Arc<T>,
+	/// number of recent blocks to keep traces of
+	history: Option<u64>,
+	/// oldest block traces are kept for
+	horizon: RwLock<BlockNumber>,
 }
 
 impl<T> TraceDB<T> where T: DatabaseExtras {
@@ -76,12 +87,18 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 		batch.put(db::COL_TRACE, b"version", TRACE_DB_VER);
 		db.key_value().write(batch).expect("failed to update version");
 
+		let horizon = db.key_value().get(db::COL_TRACE, HORIZON_KEY)
+			.expect("Low level database error when fetching trace horizon. Some issue with disk?")
+			.map_or(0, |raw| ::rlp::decode(&raw).expect("trace horizon is written by this module; qed"));
+
 		TraceDB {
 			traces: RwLock::new(HashMap::new()),
 			cache_manager: RwLock::new(CacheManager::new(config.pref_cache_size, config.max_cache_size, 10 * 1024)),
 			db,
 			enabled: config.enabled,
 			extras: extras,
+			history: config.history,
+			horizon: RwLock::new(horizon),
 		}
 	}
 
@@ -112,8 +129,78 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 		});
 	}
 
-	/// Returns traces for block with hash.
-	fn traces(&self, block_hash: &H256) -> Option<FlatBlockTraces> {
+	/// Prunes the traces of canonical blocks falling out of the configured history, along with their
+	/// blooms. The last `min_history` blocks before `best_block` are kept in any case, as they may still
+	/// be re-enacted. At most `PRUNE_BATCH` blocks are pruned per call, returns the number pruned.
+	pub fn prune(&self, best_block: BlockNumber, min_history: u64) -> u64 {
+		let history = match self.history {
+			Some(history) if self.enabled => cmp::max(history, min_history),
+			_ => return 0,
+		};
+
+		let mut horizon = self.horizon.write();
+		let start = *horizon;
+		let end = cmp::min((best_block + 1).saturating_sub(history), start + PRUNE_BATCH);
+		if end <= start {
+			return 0;
+		}
+
+		// cleared blooms no longer match any filter, leftovers of a crash are skipped by `filter`.
+		let empty_blooms = vec![Bloom::default(); (end - start) as usize];
+		self.db.trace_blooms()
+			.insert_blooms(start, empty_blooms.iter())
+			.expect("Low level database error. Some issue with disk?");
+
+		let mut batch = DBTransaction::new();
+		{
+			let mut traces = self.traces.write();
+			for number in start..end {
+				if let Some(hash) = self.extras.block_hash(number) {
+					batch.delete(db::COL_TRACE, &Key::<FlatBlockTraces>::key(&hash));
+					traces.remove(&hash);
+				}
+			}
+		}
+		batch.put(db::COL_TRACE, HORIZON_KEY, &::rlp::encode(&end));
+		self.db.key_value().write(batch).expect("Low level database error. Some issue with disk?");
+
+		trace!(target: "trace", "Pruned traces of blocks #{}..#{}", start, end);
+		*horizon = end;
+		end - start
+	}
+
+	/// Rewrites the trace blooms of canonical blocks from the oldest kept up to `best_block` from their
+	/// stored traces, e.g. for databases whose blooms were lost. Returns the number of blocks rewritten.
+	pub fn rebuild_blooms(&self, best_block: BlockNumber) -> u64 {
+		if !self.enabled {
+			return 0;
+		}
+
+		let start = self.trace_horizon();
+		let mut from = start;
+		while from <= best_block {
+			let to = cmp::min(from + PRUNE_BATCH, best_block + 1);
+			// read past the cache, so rebuilding doesn't evict the traces of recent blocks.
+			let blooms = (from..to)
+				.map(|number| self.extras.block_hash(number)
+					.and_then(|hash| self.db.key_value().read::<FlatBlockTraces, _>(db::COL_TRACE, &hash))
+					.map(|traces| traces.bloom())
+					.unwrap_or_default())
+				.collect::<Vec<_>>();
+
+			self.db.trace_blooms()
+				.insert_blooms(from, blooms.iter())
+				.expect("Low level database error. Some issue with disk?");
+
+			trace!(target: "trace", "Rebuilt trace blooms of blocks #{}..#{}", from, to);
+			from = to;
+		}
+
+		(best_block + 1).saturating_sub(start)
+	}
+
+	/// Returns traces for block with hash, whether the block is canonical or not.
+	pub fn traces(&self, block_hash: &H256) -> Option<FlatBlockTraces> {
 		let result = self.db.key_value().read_with_cache(db::COL_TRACE, &self.traces, block_hash);
 		self.note_trace_used(*block_hash);
 		result
@@ -311,8 +398,13 @@ impl<T> TraceDatabase for TraceDB<T> where T: DatabaseExtras {
 
 	fn filter(&self, filter: &Filter) -> Vec<LocalizedTrace> {
 		let possibilities = filter.bloom_possibilities();
+		let start = cmp::max(filter.range.start as u64, self.trace_horizon());
+		if start > filter.range.end as u64 {
+			return Vec::new();
+		}
+
 		let numbers = self.db.trace_blooms()
-			.filter(filter.range.start as u64, filter.range.end as u64, &possibilities)
+			.filter(start, filter.range.end as u64, &possibilities)
 			.expect("Low level database error. Some issue with disk?");
 
 		numbers.into_iter()
@@ -326,18 +418,22 @@ impl<T> TraceDatabase for TraceDB<T> where T: DatabaseExtras {
 			})
 			.collect()
 	}
+
+	fn trace_horizon(&self) -> BlockNumber {
+		*self.horizon.read()
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use std::collections::HashMap;
 	use std::sync::Arc;
-	use ethereum_types::{H256, U256, Address};
+	use ethereum_types::{H256, U256, Address, Bloom};
 	use kvdb::{DBTransaction};
 	use header::BlockNumber;
 	use trace::{Config, TraceDB, Database as TraceDatabase, DatabaseExtras, ImportRequest};
-	use trace::{Filter, LocalizedTrace, AddressesFilter, TraceError};
-	use trace::trace::{Call, Action, Res};
+	use trace::{Filter, LocalizedTrace, AddressesFilter, TraceError, TraceKind, TraceStatus};
+	use trace::trace::{Call, CallResult, Create, CreateResult, Suicide, Action, Res};
 	use trace::flat::{FlatTrace, FlatBlockTraces, FlatTransactionTraces};
 	use evm::CallType;
 	use test_helpers::new_db;
@@ -533,6 +629,8 @@ mod tests {
 			range: (1..1),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let traces = tracedb.filter(&filter);
@@ -549,6 +647,8 @@ mod tests {
 			range: (1..2),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let traces = tracedb.filter(&filter);
@@ -633,4 +733,190 @@ mod tests {
 
 		assert_eq!(traces.len(), 0);
 	}
+
+	// imports `count` canonical blocks, block `n` calling from `n % 97` to `n` and creating contract `n + 1_000_000`.
+	fn import_address_blocks(count: u64) -> (Arc<::blockchain::BlockChainDB>, TraceDB<Extras>) {
+		let db = new_db();
+		let mut config = Config::default();
+		config.enabled = true;
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, H256::default());
+		for n in 1..count + 1 {
+			extras.block_hashes.insert(n, H256::from(n));
+			extras.transaction_hashes.insert(n, vec![H256::from(n + count), H256::from(n + 2 * count)]);
+		}
+
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+		for n in 1..count + 1 {
+			let call = FlatTrace {
+				trace_address: Default::default(),
+				subtraces: 0,
+				action: Action::Call(Call {
+					from: Address::from(n % 97),
+					to: Address::from(n),
+					value: 0.into(),
+					gas: 0.into(),
+					input: vec![],
+					call_type: CallType::Call,
+				}),
+				result: Res::FailedCall(TraceError::OutOfGas),
+			};
+			let create = FlatTrace {
+				trace_address: Default::default(),
+				subtraces: 0,
+				action: Action::Create(Create {
+					from: Address::from(n % 97),
+					value: 0.into(),
+					gas: 0.into(),
+					init: vec![],
+				}),
+				result: Res::Create(CreateResult {
+					gas_used: 0.into(),
+					code: vec![],
+					address: Address::from(n + 1_000_000),
+				}),
+			};
+
+			let mut batch = DBTransaction::new();
+			tracedb.import(&mut batch, ImportRequest {
+				traces: FlatBlockTraces::from(vec![
+					FlatTransactionTraces::from(vec![call]),
+					FlatTransactionTraces::from(vec![create]),
+				]),
+				block_hash: H256::from(n),
+				block_number: n,
+				enacted: vec![H256::from(n)],
+				retracted: 0,
+			});
+			db.key_value().write(batch).unwrap();
+		}
+
+		(db, tracedb)
+	}
+
+	// every trace matching `filter`, found by decoding the traces of every block.
+	fn scan(tracedb: &TraceDB<Extras>, filter: &Filter) -> Vec<LocalizedTrace> {
+		(filter.range.start as u64..filter.range.end as u64 + 1)
+			.flat_map(|n| tracedb.block_traces(n).unwrap_or_default())
+			.filter(|trace| filter.matches_action(&trace.action, &trace.result))
+			.collect()
+	}
+
+	#[test]
+	fn filter_by_address_has_no_false_negatives() {
+		let count = 5_000;
+		let (db, tracedb) = import_address_blocks(count);
+
+		let filters = vec![
+			(vec![Address::from(5)], vec![]),
+			(vec![], vec![Address::from(4_321)]),
+			(vec![], vec![Address::from(1_000_042)]),
+			(vec![Address::from(96)], vec![Address::from(96), Address::from(193)]),
+			(vec![Address::from(0xdead)], vec![]),
+		];
+
+		for (from, to) in filters {
+			let filter = Filter {
+				range: (1..count as usize),
+				from_address: AddressesFilter::from(from),
+				to_address: AddressesFilter::from(to),
+				kind: None,
+				status: None,
+			};
+
+			assert_eq!(tracedb.filter(&filter), scan(&tracedb, &filter));
+		}
+
+		// blooms narrow a single address down to a small fraction of the blocks to decode.
+		let filter = Filter {
+			range: (1..count as usize),
+			from_address: AddressesFilter::from(vec![]),
+			to_address: AddressesFilter::from(vec![Address::from(4_321)]),
+			kind: None,
+			status: None,
+		};
+		let candidates = db.trace_blooms().filter(1, count, &filter.bloom_possibilities()).unwrap();
+		assert!(candidates.contains(&4_321));
+		assert!(candidates.len() < (count / 10) as usize, "{} candidate blocks", candidates.len());
+	}
+
+	#[test]
+	fn rebuilds_lost_blooms() {
+		let count = 1_500;
+		let (db, tracedb) = import_address_blocks(count);
+		let filter = Filter {
+			range: (1..count as usize),
+			from_address: AddressesFilter::from(vec![Address::from(7)]),
+			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
+		};
+		let expected = scan(&tracedb, &filter);
+		// blocks 7, 104, .., 1462 each hold a call and a create from 7.
+		assert_eq!(expected.len(), 32);
+
+		let empty_blooms = vec![Bloom::default(); count as usize + 1];
+		db.trace_blooms().insert_blooms(0, empty_blooms.iter()).unwrap();
+		assert!(tracedb.filter(&filter).is_empty());
+
+		assert_eq!(tracedb.rebuild_blooms(count), count + 1);
+		assert_eq!(tracedb.filter(&filter), expected);
+	}
+
+	#[test]
+	fn filter_by_kind_and_status() {
+		let db = new_db();
+		let mut config = Config::default();
+		config.enabled = true;
+		let block_1 = H256::from(0xa1);
+
+		let mut extras = Extras::default();
+		extras.block_hashes.insert(0, H256::default());
+		extras.block_hashes.insert(1, block_1);
+		extras.transaction_hashes.insert(1, vec![H256::from(0xff)]);
+		let tracedb = TraceDB::new(config, db.clone(), Arc::new(extras));
+
+		let trace = |action, result, trace_address: Vec<usize>| FlatTrace {
+			trace_address: trace_address.into_iter().collect(),
+			subtraces: 0,
+			action,
+			result,
+		};
+		let traces = vec![
+			trace(Action::Create(Create { from: 1.into(), value: 0.into(), gas: 4.into(), init: vec![] }),
+				Res::FailedCreate(TraceError::OutOfGas), vec![]),
+			trace(Action::Call(Call { from: 1.into(), to: 2.into(), value: 0.into(), gas: 4.into(), input: vec![], call_type: CallType::DelegateCall }),
+				Res::Call(CallResult { gas_used: 1.into(), output: vec![] }), vec![0]),
+			trace(Action::Suicide(Suicide { address: 2.into(), refund_address: 1.into(), balance: 3.into() }),
+				Res::None, vec![1]),
+		];
+
+		let mut batch = DBTransaction::new();
+		tracedb.import(&mut batch, ImportRequest {
+			traces: FlatBlockTraces::from(vec![FlatTransactionTraces::from(traces)]),
+			block_hash: block_1,
+			block_number: 1,
+			enacted: vec![block_1],
+			retracted: 0,
+		});
+		db.key_value().write(batch).unwrap();
+
+		let filtered = |kind, status| -> Vec<Vec<usize>> {
+			tracedb.filter(&Filter {
+				range: (1..1),
+				from_address: AddressesFilter::from(vec![]),
+				to_address: AddressesFilter::from(vec![]),
+				kind,
+				status,
+			}).into_iter().map(|trace| trace.trace_address).collect()
+		};
+
+		assert_eq!(filtered(None, None).len(), 3);
+		assert_eq!(filtered(Some(TraceKind::Create), Some(TraceStatus::Failed)), vec![Vec::<usize>::new()]);
+		assert_eq!(filtered(Some(TraceKind::DelegateCall), None), vec![vec![0]]);
+		assert_eq!(filtered(Some(TraceKind::Suicide), Some(TraceStatus::Success)), vec![vec![1]]);
+		assert_eq!(filtered(None, Some(TraceStatus::Failed)), vec![Vec::<usize>::new()]);
+		assert!(filtered(Some(TraceKind::StaticCall), None).is_empty());
+	}
 }
@@ -180,6 +180,7 @@ impl Tracer for ExecutiveTracer {
 /// Simple VM tracer. Traces all operations.
 pub struct ExecutiveVMTracer {
 	data: VMTrace,
+	refund: Option<U256>, // refund counter altered by the instruction being executed.
 }
 
 impl ExecutiveVMTracer {
@@ -191,7 +192,8 @@ impl ExecutiveVMTracer {
 				code: vec![],
 				operations: vec![Default::default()],	// prefill with a single entry so that prepare_subtrace can get the parent_step
 				subs: vec![],
-			}
+			},
+			refund: None,
 		}
 	}
 }
@@ -216,17 +218,25 @@ impl VMTracer for ExecutiveVMTracer {
 			stack_push: stack_push.iter().cloned().collect(),
 			mem_diff: mem_diff.map(|(s, r)| MemoryDiff{ offset: s, data: r.iter().cloned().collect() }),
 			store_diff: store_diff.map(|(l, v)| StorageDiff{ location: l, value: v }),
+			refund: self.refund.take(),
 		};
 		self.data.operations.last_mut().expect("trace_executed is always called after a trace_prepare_execute").executed = Some(ex);
 	}
 
+	fn trace_refund(&mut self, refund: U256) {
+		self.refund = Some(refund);
+	}
+
 	fn prepare_subtrace(&self, code: &[u8]) -> Self {
-		ExecutiveVMTracer { data: VMTrace {
-			parent_step: self.data.operations.len() - 1,	// won't overflow since we must already have pushed an operation in trace_prepare_execute.
-			code: code.to_vec(),
-			operations: vec![],
-			subs: vec![],
-		}}
+		ExecutiveVMTracer {
+			data: VMTrace {
+				parent_step: self.data.operations.len() - 1,	// won't overflow since we must already have pushed an operation in trace_prepare_execute.
+				code: code.to_vec(),
+				operations: vec![],
+				subs: vec![],
+			},
+			refund: None,
+		}
 	}
 
 	fn done_subtrace(&mut self, sub: Self) {
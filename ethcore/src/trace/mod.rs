@@ -34,7 +34,7 @@ pub use self::types::{filter, flat, localized, trace, Tracing};
 pub use self::types::error::Error as TraceError;
 pub use self::types::trace::{VMTrace, VMOperation, VMExecutedOperation, MemoryDiff, StorageDiff, RewardType};
 pub use self::types::flat::{FlatTrace, FlatTransactionTraces, FlatBlockTraces};
-pub use self::types::filter::{Filter, AddressesFilter};
+pub use self::types::filter::{Filter, AddressesFilter, TraceKind, TraceStatus};
 
 use ethereum_types::{H256, U256, Address};
 use kvdb::DBTransaction;
@@ -120,6 +120,9 @@ pub trait VMTracer: Send {
 	/// Trace the finalised execution of a single valid instruction.
 	fn trace_executed(&mut self, _gas_used: U256, _stack_push: &[U256], _mem_diff: Option<(usize, &[u8])>, _store_diff: Option<(U256, U256)>) {}
 
+	/// Trace a change of the refund counter of the current call frame by the instruction being executed.
+	fn trace_refund(&mut self, _refund: U256) {}
+
 	/// Spawn subtracer which will be used to trace deeper levels of execution.
 	fn prepare_subtrace(&self, code: &[u8]) -> Self where Self: Sized;
 
@@ -159,4 +162,7 @@ pub trait Database {
 
 	/// Filter traces matching given filter.
 	fn filter(&self, filter: &Filter) -> Vec<LocalizedTrace>;
+
+	/// Returns the number of the oldest block traces are kept for. Traces of older blocks were pruned.
+	fn trace_horizon(&self) -> BlockNumber;
 }
@@ -19,8 +19,11 @@
 use std::ops::Range;
 use ethereum_types::{Address, Bloom, BloomInput};
 use trace::flat::FlatTrace;
+use vm::CallType;
 use super::trace::{Action, Res};
 
+pub use types::trace_filter::{TraceKind, TraceStatus};
+
 /// Addresses filter.
 ///
 /// Used to create bloom possibilities and match filters.
@@ -85,6 +88,12 @@ pub struct Filter {
 
 	/// To address filter.
 	pub to_address: AddressesFilter,
+
+	/// Kind of the traces, any if not set.
+	pub kind: Option<TraceKind>,
+
+	/// Outcome of the traces, any if not set.
+	pub status: Option<TraceStatus>,
 }
 
 impl Filter {
@@ -95,7 +104,39 @@ impl Filter {
 
 	/// Returns true if given trace matches the filter.
 	pub fn matches(&self, trace: &FlatTrace) -> bool {
-		match trace.action {
+		self.matches_action(&trace.action, &trace.result)
+	}
+
+	/// Returns true if a trace of `action` resulting in `result` matches the filter.
+	pub fn matches_action(&self, action: &Action, result: &Res) -> bool {
+		self.matches_kind(action) && self.matches_status(result) && self.matches_addresses(action, result)
+	}
+
+	fn matches_kind(&self, action: &Action) -> bool {
+		let kind = match *action {
+			Action::Call(ref call) => match call.call_type {
+				CallType::None | CallType::Call => TraceKind::Call,
+				CallType::DelegateCall => TraceKind::DelegateCall,
+				CallType::StaticCall => TraceKind::StaticCall,
+				CallType::CallCode => TraceKind::CallCode,
+			},
+			Action::Create(_) => TraceKind::Create,
+			Action::Suicide(_) => TraceKind::Suicide,
+			Action::Reward(_) => TraceKind::Reward,
+		};
+		self.kind.map_or(true, |k| k == kind)
+	}
+
+	fn matches_status(&self, result: &Res) -> bool {
+		let status = match *result {
+			Res::FailedCall(_) | Res::FailedCreate(_) => TraceStatus::Failed,
+			Res::Call(_) | Res::Create(_) | Res::None => TraceStatus::Success,
+		};
+		self.status.map_or(true, |s| s == status)
+	}
+
+	fn matches_addresses(&self, action: &Action, result: &Res) -> bool {
+		match *action {
 			Action::Call(ref call) => {
 				let from_matches = self.from_address.matches(&call.from);
 				let to_matches = self.to_address.matches(&call.to);
@@ -104,7 +145,7 @@ impl Filter {
 			Action::Create(ref create) => {
 				let from_matches = self.from_address.matches(&create.from);
 
-				let to_matches = match trace.result {
+				let to_matches = match *result {
 					Res::Create(ref create_result) => self.to_address.matches(&create_result.address),
 					_ => false
 				};
@@ -126,9 +167,9 @@ impl Filter {
 #[cfg(test)]
 mod tests {
 	use ethereum_types::{Address, Bloom, BloomInput};
-	use trace::trace::{Action, Call, Res, Create, CreateResult, Suicide, Reward};
+	use trace::trace::{Action, Call, CallResult, Res, Create, CreateResult, Suicide, Reward};
 	use trace::flat::FlatTrace;
-	use trace::{Filter, AddressesFilter, TraceError, RewardType};
+	use trace::{Filter, AddressesFilter, TraceError, RewardType, TraceKind, TraceStatus};
 	use evm::CallType;
 
 	#[test]
@@ -137,6 +178,8 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let blooms = filter.bloom_possibilities();
@@ -149,6 +192,8 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![Address::from(2)]),
+			kind: None,
+			status: None,
 		};
 
 		let blooms = filter.bloom_possibilities();
@@ -165,6 +210,8 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let blooms = filter.bloom_possibilities();
@@ -180,6 +227,8 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![]),
 			to_address: AddressesFilter::from(vec![Address::from(1)]),
+			kind: None,
+			status: None,
 		};
 
 		let blooms = filter.bloom_possibilities();
@@ -195,6 +244,8 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1), Address::from(3)]),
 			to_address: AddressesFilter::from(vec![Address::from(2), Address::from(4)]),
+			kind: None,
+			status: None,
 		};
 
 		let blooms = filter.bloom_possibilities();
@@ -227,42 +278,56 @@ mod tests {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let f1 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(3), Address::from(1)]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let f2 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![]),
 			to_address: AddressesFilter::from(vec![]),
+			kind: None,
+			status: None,
 		};
 
 		let f3 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![]),
 			to_address: AddressesFilter::from(vec![Address::from(2)]),
+			kind: None,
+			status: None,
 		};
 
 		let f4 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![]),
 			to_address: AddressesFilter::from(vec![Address::from(2), Address::from(3)]),
+			kind: None,
+			status: None,
 		};
 
 		let f5 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![Address::from(2), Address::from(3)]),
+			kind: None,
+			status: None,
 		};
 
 		let f6 = Filter {
 			range: (0..0),
 			from_address: AddressesFilter::from(vec![Address::from(1)]),
 			to_address: AddressesFilter::from(vec![Address::from(4)]),
+			kind: None,
+			status: None,
 		};
 
 		let trace = FlatTrace {
@@ -356,18 +421,24 @@ mod tests {
 			range: (0..0),
 			from_address: vec![1.into()].into(),
 			to_address: vec![].into(),
+			kind: None,
+			status: None,
 		};
 
 		let f1 = Filter {
 			range: (0..0),
 			from_address: vec![].into(),
 			to_address: vec![].into(),
+			kind: None,
+			status: None,
 		};
 
 		let f2 = Filter {
 			range: (0..0),
 			from_address: vec![].into(),
 			to_address: vec![2.into()].into(),
+			kind: None,
+			status: None,
 		};
 
 		let trace = FlatTrace {
@@ -385,4 +456,74 @@ mod tests {
 		assert!(f1.matches(&trace));
 		assert!(f2.matches(&trace));
 	}
+
+	#[test]
+	fn filter_match_kind_and_status() {
+		let filter = |kind, status| Filter {
+			range: (0..0),
+			from_address: vec![].into(),
+			to_address: vec![].into(),
+			kind,
+			status,
+		};
+
+		let failed_create = FlatTrace {
+			action: Action::Create(Create {
+				from: 1.into(),
+				value: 3.into(),
+				gas: 4.into(),
+				init: vec![0x5],
+			}),
+			result: Res::FailedCreate(TraceError::OutOfGas),
+			trace_address: vec![].into_iter().collect(),
+			subtraces: 0,
+		};
+
+		let delegate_call = FlatTrace {
+			action: Action::Call(Call {
+				from: 1.into(),
+				to: 2.into(),
+				value: 0.into(),
+				gas: 4.into(),
+				input: vec![0x5],
+				call_type: CallType::DelegateCall,
+			}),
+			result: Res::Call(CallResult {
+				gas_used: 1.into(),
+				output: vec![],
+			}),
+			trace_address: vec![0].into_iter().collect(),
+			subtraces: 0,
+		};
+
+		let suicide = FlatTrace {
+			action: Action::Suicide(Suicide {
+				address: 1.into(),
+				refund_address: 2.into(),
+				balance: 3.into(),
+			}),
+			result: Res::None,
+			trace_address: vec![1].into_iter().collect(),
+			subtraces: 0,
+		};
+
+		let matching = |filter: &Filter| -> Vec<bool> {
+			vec![filter.matches(&failed_create), filter.matches(&delegate_call), filter.matches(&suicide)]
+		};
+
+		assert_eq!(matching(&filter(None, None)), vec![true, true, true]);
+		assert_eq!(matching(&filter(Some(TraceKind::Create), None)), vec![true, false, false]);
+		assert_eq!(matching(&filter(Some(TraceKind::DelegateCall), None)), vec![false, true, false]);
+		assert_eq!(matching(&filter(Some(TraceKind::Call), None)), vec![false, false, false]);
+		assert_eq!(matching(&filter(Some(TraceKind::Suicide), None)), vec![false, false, true]);
+		assert_eq!(matching(&filter(None, Some(TraceStatus::Failed))), vec![true, false, false]);
+		assert_eq!(matching(&filter(None, Some(TraceStatus::Success))), vec![false, true, true]);
+		assert_eq!(matching(&filter(Some(TraceKind::Create), Some(TraceStatus::Success))), vec![false, false, false]);
+
+		// predicates combine with the address filters.
+		let mut f = filter(Some(TraceKind::DelegateCall), Some(TraceStatus::Success));
+		assert_eq!(matching(&f), vec![false, true, false]);
+		f.to_address = vec![3.into()].into();
+		assert_eq!(matching(&f), vec![false, false, false]);
+	}
 }
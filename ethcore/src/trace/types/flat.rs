@@ -19,7 +19,9 @@
 use std::collections::VecDeque;
 use rlp::{Rlp, RlpStream, Decodable, Encodable, DecoderError};
 use heapsize::HeapSizeOf;
-use ethereum_types::Bloom;
+use ethereum_types::{Bloom, H256};
+use header::BlockNumber;
+use super::localized::LocalizedTrace;
 use super::trace::{Action, Res};
 
 /// Trace localized in vector of traces produced by a single transaction.
@@ -126,6 +128,29 @@ impl FlatBlockTraces {
 	pub fn bloom(&self) -> Bloom {
 		self.0.iter().fold(Default::default(), | bloom, tx_traces | bloom | tx_traces.bloom())
 	}
+
+	/// Localizes the traces of block `block_hash`, whose transactions hash to `tx_hashes`.
+	///
+	/// Traces following those of the transactions, e.g. rewards, belong to no transaction.
+	pub fn localize(self, block_hash: H256, block_number: BlockNumber, tx_hashes: &[H256]) -> Vec<LocalizedTrace> {
+		self.0.into_iter()
+			.enumerate()
+			.flat_map(move |(tx_number, tx_traces)| {
+				let transaction_hash = tx_hashes.get(tx_number).cloned();
+				let transaction_number = transaction_hash.map(|_| tx_number);
+				tx_traces.0.into_iter().map(move |trace| LocalizedTrace {
+					action: trace.action,
+					result: trace.result,
+					subtraces: trace.subtraces,
+					trace_address: trace.trace_address.into_iter().collect(),
+					transaction_number,
+					transaction_hash,
+					block_number,
+					block_hash,
+				})
+			})
+			.collect()
+	}
 }
 
 impl Into<Vec<FlatTransactionTraces>> for FlatBlockTraces {
@@ -401,6 +401,8 @@ pub struct VMExecutedOperation {
 	pub mem_diff: Option<MemoryDiff>,
 	/// The altered storage value, if any.
 	pub store_diff: Option<StorageDiff>,
+	/// The refund counter of the call frame, if altered.
+	pub refund: Option<U256>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default, RlpEncodable, RlpDecodable)]
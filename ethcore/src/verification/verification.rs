@@ -100,10 +100,10 @@ pub fn verify_block_unordered(block: Unverified, engine: &EthEngine, check_seal:
 		None
 	};
 
-	let transactions = block.transactions
+	let transactions = engine.verify_transactions_unordered(block.transactions, &header)
 		.into_iter()
 		.map(|t| {
-			let t = engine.verify_transaction_unordered(t, &header)?;
+			let t = t?;
 			if let Some(max_nonce) = nonce_cap {
 				if t.nonce >= max_nonce {
 					return Err(BlockError::TooManyTransactions(t.sender()).into());
@@ -797,4 +797,34 @@ mod tests {
 		check_fail(unordered_test(&create_test_block_with_data(&header, &bad_transactions, &[]), &engine), TooManyTransactions(keypair.address()));
 		unordered_test(&create_test_block_with_data(&header, &good_transactions, &[]), &engine).unwrap();
 	}
+
+	#[test]
+	fn unordered_verification_recovers_senders_in_order() {
+		let spec = Spec::new_null();
+		let mut header = Header::default();
+		header.set_number(1);
+
+		let transactions: Vec<_> = (0..40u64).map(|i| {
+			let transaction = Transaction {
+				action: Action::Create,
+				value: U256::zero(),
+				data: Vec::new(),
+				gas: 0.into(),
+				gas_price: U256::zero(),
+				nonce: 0.into(),
+			};
+			match i {
+				7 => transaction.null_sign(1),
+				_ => transaction.sign(Random.generate().unwrap().secret(), None),
+			}
+		}).collect();
+
+		let block = Unverified::from_rlp(create_test_block_with_data(&header, &transactions, &[])).unwrap();
+		let expected = block.transactions.iter()
+			.map(|t| SignedTransaction::new(t.clone()).unwrap().sender())
+			.collect::<Vec<_>>();
+		let verified = verify_block_unordered(block, &*spec.engine, false).unwrap();
+		assert_eq!(verified.transactions.iter().map(SignedTransaction::sender).collect::<Vec<_>>(), expected);
+		assert_eq!(expected, transactions.iter().map(SignedTransaction::sender).collect::<Vec<_>>());
+	}
 }
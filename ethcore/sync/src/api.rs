@@ -48,6 +48,7 @@ use light::net::{
 };
 use network::IpFilter;
 use private_tx::PrivateTxHandler;
+use snapshot_selection::WarpSnapshotPolicy;
 use transaction::UnverifiedTransaction;
 
 /// Parity sync protocol
@@ -109,8 +110,14 @@ pub struct SyncConfig {
 	pub fork_block: Option<(BlockNumber, H256)>,
 	/// Enable snapshot sync
 	pub warp_sync: WarpSync,
+	/// Requirements for the snapshot to warp sync from
+	pub warp_snapshot_policy: WarpSnapshotPolicy,
+	/// Time without any import progress after which the block downloader is considered stalled and reset
+	pub stall_timeout: Duration,
 	/// Enable light client server.
 	pub serve_light: bool,
+	/// Max number of transactions remembered per peer as known to it. Should cover the transaction pool.
+	pub max_known_transactions: usize,
 }
 
 impl Default for SyncConfig {
@@ -123,7 +130,10 @@ impl Default for SyncConfig {
 			light_subprotocol_name: LIGHT_PROTOCOL,
 			fork_block: None,
 			warp_sync: WarpSync::Disabled,
+			warp_snapshot_policy: WarpSnapshotPolicy::default(),
+			stall_timeout: Duration::from_secs(10 * 60),
 			serve_light: false,
+			max_known_transactions: 8192,
 		}
 	}
 }
@@ -141,6 +151,9 @@ pub trait SyncProvider: Send + Sync {
 
 	/// Returns propagation count for pending transactions.
 	fn transactions_stats(&self) -> BTreeMap<H256, TransactionStats>;
+
+	/// Get the request credit parameters announced to light peers, if serving them.
+	fn light_server_costs(&self) -> Option<LightServerCosts>;
 }
 
 /// Transaction stats
@@ -203,6 +216,39 @@ impl From<light_net::Status> for PipProtocolInfo {
 	}
 }
 
+/// Request credit parameters of the light server.
+#[derive(Debug)]
+pub struct LightServerCosts {
+	/// Maximum amount of credits a peer can hold
+	pub limit: U256,
+	/// Credits recharged per second
+	pub recharge: U256,
+	/// Cost of every request packet
+	pub base: U256,
+	/// Cost of each served request kind
+	pub costs: Vec<(light::request::Kind, U256)>,
+}
+
+impl<'a> From<&'a light_net::request_credits::FlowParams> for LightServerCosts {
+	fn from(params: &'a light_net::request_credits::FlowParams) -> Self {
+		use light::request::Kind;
+
+		let kinds = [
+			Kind::Headers, Kind::HeaderProof, Kind::TransactionIndex, Kind::Body, Kind::Receipts,
+			Kind::Account, Kind::Storage, Kind::Code, Kind::Execution, Kind::Signal,
+		];
+
+		LightServerCosts {
+			limit: *params.limit(),
+			recharge: *params.recharge_rate(),
+			base: params.base_cost(),
+			costs: kinds.iter()
+				.filter_map(|&kind| params.cost_table().cost_of(kind).map(|cost| (kind, cost)))
+				.collect(),
+		}
+	}
+}
+
 /// Configuration to attach alternate protocol handlers.
 /// Only works when IPC is disabled.
 pub struct AttachedProtocol {
@@ -379,11 +425,16 @@ impl SyncProvider for EthSync {
 			.map(|(hash, stats)| (*hash, stats.into()))
 			.collect()
 	}
+
+	fn light_server_costs(&self) -> Option<LightServerCosts> {
+		self.light_proto.as_ref().map(|lp| (&*lp.flow_params()).into())
+	}
 }
 
 const PEERS_TIMER: TimerToken = 0;
 const SYNC_TIMER: TimerToken = 1;
 const TX_TIMER: TimerToken = 2;
+const ANNOUNCE_TIMER: TimerToken = 3;
 
 struct SyncProtocolHandler {
 	/// Shared blockchain client.
@@ -402,6 +453,7 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 			io.register_timer(PEERS_TIMER, Duration::from_millis(700)).expect("Error registering peers timer");
 			io.register_timer(SYNC_TIMER, Duration::from_millis(1100)).expect("Error registering sync timer");
 			io.register_timer(TX_TIMER, Duration::from_millis(1300)).expect("Error registering transactions timer");
+			io.register_timer(ANNOUNCE_TIMER, Duration::from_millis(200)).expect("Error registering block announcements timer");
 		}
 	}
 
@@ -435,6 +487,7 @@ impl NetworkProtocolHandler for SyncProtocolHandler {
 			TX_TIMER => {
 				self.sync.write().propagate_new_transactions(&mut io);
 			},
+			ANNOUNCE_TIMER => self.sync.write().flush_block_announcements(&mut io),
 			_ => warn!("Unknown timer {} triggered.", timer),
 		}
 	}
@@ -558,6 +611,10 @@ pub trait ManageNetwork : Send + Sync {
 	fn remove_reserved_peer(&self, peer: String) -> Result<(), String>;
 	/// Add reserved peer
 	fn add_reserved_peer(&self, peer: String) -> Result<(), String>;
+	/// Returns ids of currently banned peers
+	fn banned_peers(&self) -> Vec<String>;
+	/// Lift the ban of the peer
+	fn unban_peer(&self, peer: String) -> Result<(), String>;
 	/// Start network
 	fn start_network(&self);
 	/// Stop network
@@ -587,6 +644,14 @@ impl ManageNetwork for EthSync {
 		self.network.add_reserved_peer(&peer).map_err(|e| format!("{:?}", e))
 	}
 
+	fn banned_peers(&self) -> Vec<String> {
+		self.network.banned_peers()
+	}
+
+	fn unban_peer(&self, peer: String) -> Result<(), String> {
+		self.network.unban_peer(&peer).map_err(|e| format!("{:?}", e))
+	}
+
 	fn start_network(&self) {
 		self.start();
 	}
@@ -857,6 +922,14 @@ impl ManageNetwork for LightSync {
 		self.network.add_reserved_peer(&peer).map_err(|e| format!("{:?}", e))
 	}
 
+	fn banned_peers(&self) -> Vec<String> {
+		self.network.banned_peers()
+	}
+
+	fn unban_peer(&self, peer: String) -> Result<(), String> {
+		self.network.unban_peer(&peer).map_err(|e| format!("{:?}", e))
+	}
+
 	fn start_network(&self) {
 		match self.network.start() {
 			Err((err, listen_address)) => {
@@ -76,13 +76,15 @@ pub enum DownloadAction {
 pub enum BlockDownloaderImportError {
 	/// Imported data is rejected as invalid. Peer should be dropped.
 	Invalid,
+	/// Imported data could not be decoded. Peer should be dropped.
+	Malformed,
 	/// Imported data is valid but rejected cause the downloader does not need it.
 	Useless,
 }
 
 impl From<rlp::DecoderError> for BlockDownloaderImportError {
 	fn from(_: rlp::DecoderError) -> BlockDownloaderImportError {
-		BlockDownloaderImportError::Invalid
+		BlockDownloaderImportError::Malformed
 	}
 }
 
@@ -219,6 +221,20 @@ impl BlockDownloader {
 		self.last_imported_block
 	}
 
+	/// Returns hash of the last imported block.
+	pub fn last_imported_block_hash(&self) -> &H256 {
+		&self.last_imported_hash
+	}
+
+	/// Returns a short description of the downloader state for diagnostics.
+	pub fn summary(&self) -> String {
+		format!(
+			"state={:?}, last imported=#{} ({}), round start=#{} ({}), imported this round={:?}, highest={:?}, target={:?}",
+			self.state, self.last_imported_block, self.last_imported_hash, self.last_round_start,
+			self.last_round_start_hash, self.imported_this_round, self.highest_block, self.target_hash
+		)
+	}
+
 	/// Add new block headers.
 	pub fn import_headers(&mut self, io: &mut SyncIo, r: &Rlp, expected_hash: Option<H256>) -> Result<DownloadAction, BlockDownloaderImportError> {
 		let item_count = r.item_count().unwrap_or(0);
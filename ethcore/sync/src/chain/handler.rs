@@ -20,11 +20,12 @@ use bytes::Bytes;
 use ethcore::client::{BlockStatus, BlockId, BlockImportError, BlockImportErrorKind};
 use ethcore::error::*;
 use ethcore::header::BlockNumber;
-use ethcore::snapshot::{ManifestData, RestorationStatus};
+use ethcore::snapshot::{compression, ManifestData, RestorationStatus};
 use ethcore::verification::queue::kind::blocks::Unverified;
 use ethereum_types::{H256, U256};
 use hash::keccak;
 use network::PeerId;
+use reputation::Offense;
 use rlp::Rlp;
 use snapshot::ChunkType;
 use std::cmp;
@@ -92,6 +93,17 @@ impl SyncHandler {
 		match result {
 			Err(DownloaderImportError::Invalid) => {
 				debug!(target:"sync", "{} -> Invalid packet {}", peer, packet_id);
+				let offense = match packet_id {
+					BLOCK_BODIES_PACKET | RECEIPTS_PACKET | SNAPSHOT_DATA_PACKET => Offense::UnrequestedData,
+					_ => Offense::InvalidBlock,
+				};
+				sync.report_offense(io, peer, offense);
+				io.disable_peer(peer);
+				sync.deactivate_peer(io, peer);
+			},
+			Err(DownloaderImportError::Malformed) => {
+				debug!(target:"sync", "{} -> Malformed packet {}", peer, packet_id);
+				sync.report_offense(io, peer, Offense::BadRlp);
 				io.disable_peer(peer);
 				sync.deactivate_peer(io, peer);
 			},
@@ -174,6 +186,7 @@ impl SyncHandler {
 
 		if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
 			peer.latest_hash = hash;
+			peer.latest_number = Some(number);
 		}
 
 		let last_imported_number = sync.new_blocks.last_imported_block_number();
@@ -225,8 +238,9 @@ impl SyncHandler {
 		if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
 			// Peer has new blocks with unknown difficulty
 			peer.difficulty = None;
-			if let Some(&(Ok(ref h), _)) = hashes.last() {
+			if let Some(&(Ok(ref h), ref n)) = hashes.last() {
 				peer.latest_hash = h.clone();
+				peer.latest_number = n.as_ref().ok().cloned();
 			}
 		}
 		if sync.state != SyncState::Idle {
@@ -267,6 +281,7 @@ impl SyncHandler {
 						trace!(target: "sync", "New unknown block hash {:?}", hash);
 						if let Some(ref mut peer) = sync.peers.get_mut(&peer_id) {
 							peer.latest_hash = hash.clone();
+							peer.latest_number = Some(number);
 						}
 						max_height = number;
 					}
@@ -317,6 +332,9 @@ impl SyncHandler {
 				};
 				downloader.import_bodies(r)?;
 			}
+			if block_set == BlockSet::OldBlocks {
+				sync.note_ancient_provider(io, peer_id);
+			}
 			sync.collect_blocks(io, block_set);
 			Ok(())
 		}
@@ -467,6 +485,11 @@ impl SyncHandler {
 			trace!(target: "sync", "{}: Snapshot manifest version not supported: {}", peer_id, manifest.version);
 			return Err(DownloaderImportError::Invalid);
 		}
+
+		if !compression::is_supported(manifest.compression) {
+			trace!(target: "sync", "{}: Snapshot compression not supported: {:?}", peer_id, manifest.compression);
+			return Err(DownloaderImportError::Invalid);
+		}
 		sync.snapshot.reset_to(&manifest, &keccak(manifest_rlp.as_raw()));
 		io.snapshot_service().begin_restore(manifest);
 		sync.state = SyncState::SnapshotData;
@@ -546,12 +569,14 @@ impl SyncHandler {
 			network_id: r.val_at(1)?,
 			difficulty: Some(r.val_at(2)?),
 			latest_hash: r.val_at(3)?,
+			latest_number: None,
 			genesis: r.val_at(4)?,
 			asking: PeerAsking::Nothing,
 			asking_blocks: Vec::new(),
 			asking_hash: None,
 			ask_time: Instant::now(),
-			last_sent_transactions: HashSet::new(),
+			last_sent_transactions: PeerInfo::known_transactions(sync.max_known_transactions),
+			last_propagated_transaction: None,
 			last_sent_private_transactions: HashSet::new(),
 			expired: false,
 			confirmation: if sync.fork_block.is_none() { ForkConfirmation::Confirmed } else { ForkConfirmation::Unconfirmed },
@@ -783,4 +808,37 @@ mod tests {
 
 		assert!(result.is_ok());
 	}
+
+	#[test]
+	fn bans_peer_sending_malformed_data_repeatedly() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Uncle);
+		let queue = RwLock::new(VecDeque::new());
+		let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, Some(0));
+
+		for _ in 0..3 {
+			SyncHandler::on_packet(&mut sync, &mut io, 0, NEW_BLOCK_PACKET, &[0xc0]);
+			assert!(io.banned.is_empty());
+		}
+		SyncHandler::on_packet(&mut sync, &mut io, 0, NEW_BLOCK_PACKET, &[0xc0]);
+		assert!(io.banned.contains(&0));
+	}
+
+	#[test]
+	fn does_not_ban_reserved_peer() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Uncle);
+		let queue = RwLock::new(VecDeque::new());
+		let mut sync = dummy_sync_with_peer(client.block_hash_delta_minus(5), &client);
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, Some(0));
+		io.reserved_peers.insert(0);
+
+		for _ in 0..10 {
+			SyncHandler::on_packet(&mut sync, &mut io, 0, NEW_BLOCK_PACKET, &[0xc0]);
+		}
+		assert!(io.banned.is_empty());
+	}
 }
@@ -100,13 +100,15 @@ use hash::keccak;
 use heapsize::HeapSizeOf;
 use ethereum_types::{H256, U256};
 use fastmap::H256FastMap;
+use lru_cache::LruCache;
 use parking_lot::RwLock;
 use bytes::Bytes;
 use rlp::{Rlp, RlpStream, DecoderError};
-use network::{self, PeerId, PacketId};
+use network::{self, PeerId, PacketId, NodeId};
 use ethcore::header::{BlockNumber};
 use ethcore::client::{BlockChainClient, BlockStatus, BlockId, BlockChainInfo, BlockQueueInfo};
 use ethcore::snapshot::{RestorationStatus};
+use types::ancient_download::{AncientDownloadProgress, BlockRange};
 use sync_io::SyncIo;
 use super::{WarpSync, SyncConfig};
 use block_sync::{BlockDownloader, BlockDownloaderImportError as DownloaderImportError};
@@ -114,6 +116,8 @@ use rand::Rng;
 use snapshot::{Snapshot};
 use api::{EthProtocolInfo as PeerInfoDigest, WARP_SYNC_PROTOCOL_ID};
 use private_tx::PrivateTxHandler;
+use reputation::{PeerReputation, Offense, BAN_DURATION};
+use snapshot_selection::{WarpSnapshotPolicy, SnapshotCandidate, SnapshotSelection};
 use transactions_stats::{TransactionsStats, Stats as TransactionStats};
 use transaction::UnverifiedTransaction;
 
@@ -147,12 +151,17 @@ const MAX_PEERS_PROPAGATION: usize = 128;
 const MAX_PEER_LAG_PROPAGATION: BlockNumber = 20;
 const MAX_NEW_HASHES: usize = 64;
 const MAX_NEW_BLOCK_AGE: BlockNumber = 20;
+// maximal distance of the peer head from our best block for the peer to receive full new blocks.
+const MAX_PEER_DISTANCE_NEW_BLOCK: BlockNumber = 16;
+// new block announcements made within this period are batched together.
+const BLOCK_ANNOUNCEMENT_BATCH_WINDOW: Duration = Duration::from_millis(200);
 // maximal packet size with transactions (cannot be greater than 16MB - protocol limitation).
 // keep it under 8MB as well, cause it seems that it may result oversized after compression.
 const MAX_TRANSACTION_PACKET_SIZE: usize = 5 * 1024 * 1024;
+// maximal number of transactions packets sent to a single peer during one propagation round.
+const MAX_TRANSACTION_PACKETS_PER_PEER: usize = 4;
 // Min number of blocks to be behind for a snapshot sync
 const SNAPSHOT_RESTORE_THRESHOLD: BlockNumber = 30000;
-const SNAPSHOT_MIN_PEERS: usize = 3;
 
 const STATUS_PACKET: u8 = 0x00;
 const NEW_BLOCK_HASHES_PACKET: u8 = 0x01;
@@ -178,7 +187,6 @@ pub const SIGNED_PRIVATE_TRANSACTION_PACKET: u8 = 0x17;
 
 const MAX_SNAPSHOT_CHUNKS_DOWNLOAD_AHEAD: usize = 3;
 
-const WAIT_PEERS_TIMEOUT: Duration = Duration::from_secs(5);
 const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
 const HEADERS_TIMEOUT: Duration = Duration::from_secs(15);
 const BODIES_TIMEOUT: Duration = Duration::from_secs(20);
@@ -239,6 +247,16 @@ pub struct SyncStatus {
 	pub snapshot_chunks_done: usize,
 	/// Last fully downloaded and imported ancient block number (if any).
 	pub last_imported_old_block_number: Option<BlockNumber>,
+	/// Total number of transactions sent to peers.
+	pub transactions_propagated: u64,
+	/// Total size of transactions packets sent to peers in bytes.
+	pub transactions_bytes_propagated: u64,
+	/// Number of transactions not sent, because the peer already knew them.
+	pub transactions_duplicates_avoided: u64,
+	/// Outcome of the last warp sync snapshot selection (if any).
+	pub snapshot_selection: Option<SnapshotSelection>,
+	/// Number of times the block downloader was reset after stalling.
+	pub downloader_resets: u64,
 }
 
 impl SyncStatus {
@@ -310,6 +328,8 @@ pub struct PeerInfo {
 	network_id: u64,
 	/// Peer best block hash
 	latest_hash: H256,
+	/// Peer best block number if announced by the peer or by us
+	latest_number: Option<BlockNumber>,
 	/// Peer total difficulty if known
 	difficulty: Option<U256>,
 	/// Type of data currenty being requested from peer.
@@ -322,8 +342,10 @@ pub struct PeerInfo {
 	asking_snapshot_data: Option<H256>,
 	/// Request timestamp
 	ask_time: Instant,
-	/// Holds a set of transactions recently sent to this peer to avoid spamming.
-	last_sent_transactions: HashSet<H256>,
+	/// Holds a set of transactions recently sent to or received from this peer to avoid spamming.
+	last_sent_transactions: LruCache<H256, ()>,
+	/// Last transaction sent to this peer by a propagation round cut short, the next round resumes after it.
+	last_propagated_transaction: Option<H256>,
 	/// Holds a set of private transactions and their signatures recently sent to this peer to avoid spamming.
 	last_sent_private_transactions: HashSet<H256>,
 	/// Pending request is expired and result should be ignored
@@ -339,6 +361,10 @@ pub struct PeerInfo {
 }
 
 impl PeerInfo {
+	fn known_transactions(capacity: usize) -> LruCache<H256, ()> {
+		LruCache::new(capacity)
+	}
+
 	fn can_sync(&self) -> bool {
 		self.confirmation == ForkConfirmation::Confirmed && !self.expired
 	}
@@ -372,6 +398,37 @@ pub mod random {
 	pub fn new() -> rand::XorShiftRng { rand::XorShiftRng::from_seed([0, 1, 2, 3]) }
 }
 
+/// Transactions propagation counters.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PropagationStats {
+	/// Number of transactions sent.
+	pub transactions: u64,
+	/// Size of sent packets in bytes.
+	pub bytes: u64,
+	/// Number of transactions skipped, because the peer already knew them.
+	pub duplicates_avoided: u64,
+}
+
+/// Import progress last observed by the stall detection.
+struct ImportProgress {
+	/// Best block number
+	best_block: BlockNumber,
+	/// Number of blocks in the import queue
+	queue_size: usize,
+	/// Time the progress was observed at
+	since: Instant,
+}
+
+impl ImportProgress {
+	fn new(chain: &BlockChainClient) -> Self {
+		ImportProgress {
+			best_block: chain.chain_info().best_block_number,
+			queue_size: chain.queue_info().total_queue_size(),
+			since: Instant::now(),
+		}
+	}
+}
+
 pub type RlpResponseResult = Result<Option<(PacketId, RlpStream)>, PacketDecodeError>;
 pub type Peers = HashMap<PeerId, PeerInfo>;
 
@@ -394,6 +451,10 @@ pub struct ChainSync {
 	old_blocks: Option<BlockDownloader>,
 	/// Last propagated block number
 	last_sent_block_number: BlockNumber,
+	/// Time of the last new block announcement
+	last_block_announcement: Option<Instant>,
+	/// New blocks were imported since the last announcement
+	block_announcement_pending: bool,
 	/// Network ID
 	network_id: u64,
 	/// Optional fork block to check
@@ -407,12 +468,32 @@ pub struct ChainSync {
 	sync_start_time: Option<Instant>,
 	/// Transactions propagation statistics
 	transactions_stats: TransactionsStats,
+	/// Transactions propagation counters
+	propagation_stats: PropagationStats,
+	/// Max number of transactions remembered per peer as known to it.
+	max_known_transactions: usize,
+	/// Reputation of nodes, maintained across reconnects
+	reputation: PeerReputation,
 	/// Enable ancient block downloading
 	download_old_blocks: bool,
+	/// Progress of the ancient block download
+	ancient_progress: Option<AncientDownloadProgress>,
+	/// Nodes which served ancient blocks successfully
+	ancient_providers: HashSet<NodeId>,
 	/// Shared private tx service.
 	private_tx_handler: Arc<PrivateTxHandler>,
 	/// Enable warp sync.
 	warp_sync: WarpSync,
+	/// Requirements for the snapshot to warp sync from.
+	warp_snapshot_policy: WarpSnapshotPolicy,
+	/// Outcome of the last snapshot selection.
+	snapshot_selection: Option<SnapshotSelection>,
+	/// Time without import progress after which the block download is considered stalled.
+	stall_timeout: Duration,
+	/// Import progress last observed.
+	import_progress: ImportProgress,
+	/// Number of block downloader resets after stalling.
+	downloader_resets: u64,
 }
 
 impl ChainSync {
@@ -432,14 +513,26 @@ impl ChainSync {
 			new_blocks: BlockDownloader::new(false, &chain_info.best_block_hash, chain_info.best_block_number),
 			old_blocks: None,
 			last_sent_block_number: 0,
+			last_block_announcement: None,
+			block_announcement_pending: false,
 			network_id: config.network_id,
 			fork_block: config.fork_block,
 			download_old_blocks: config.download_old_blocks,
+			ancient_progress: None,
+			ancient_providers: HashSet::new(),
 			snapshot: Snapshot::new(),
 			sync_start_time: None,
 			transactions_stats: TransactionsStats::default(),
+			propagation_stats: PropagationStats::default(),
+			max_known_transactions: config.max_known_transactions,
+			reputation: PeerReputation::default(),
 			private_tx_handler,
 			warp_sync: config.warp_sync,
+			warp_snapshot_policy: config.warp_snapshot_policy,
+			snapshot_selection: None,
+			stall_timeout: config.stall_timeout,
+			import_progress: ImportProgress::new(chain),
+			downloader_resets: 0,
 		};
 		sync.update_targets(chain);
 		sync
@@ -471,6 +564,11 @@ impl ChainSync {
 			num_active_peers: self.peers.values().filter(|p| p.is_allowed() && p.asking != PeerAsking::Nothing).count(),
 			num_snapshot_chunks: self.snapshot.total_chunks(),
 			snapshot_chunks_done: self.snapshot.done_chunks(),
+			transactions_propagated: self.propagation_stats.transactions,
+			transactions_bytes_propagated: self.propagation_stats.bytes,
+			transactions_duplicates_avoided: self.propagation_stats.duplicates_avoided,
+			snapshot_selection: self.snapshot_selection,
+			downloader_resets: self.downloader_resets,
 			mem_used:
 				self.new_blocks.heap_size()
 				+ self.old_blocks.as_ref().map_or(0, |d| d.heap_size())
@@ -497,7 +595,9 @@ impl ChainSync {
 	/// Updates transactions were received by a peer
 	pub fn transactions_received(&mut self, txs: &[UnverifiedTransaction], peer_id: PeerId) {
 		if let Some(peer_info) = self.peers.get_mut(&peer_id) {
-			peer_info.last_sent_transactions.extend(txs.iter().map(|tx| tx.hash()));
+			for tx in txs {
+				peer_info.last_sent_transactions.insert(tx.hash(), ());
+			}
 		}
 	}
 
@@ -538,6 +638,22 @@ impl ChainSync {
 		self.continue_sync(io);
 	}
 
+	/// Lower the reputation of a peer and ban it once it misbehaves too often.
+	/// Reserved peers are never banned.
+	fn report_offense(&mut self, io: &mut SyncIo, peer_id: PeerId, offense: Offense) {
+		if io.is_reserved_peer(peer_id) {
+			return;
+		}
+		let node_id = match io.peer_node_id(peer_id) {
+			Some(node_id) => node_id,
+			None => return,
+		};
+		if self.reputation.report(&node_id, offense) {
+			debug!(target: "sync", "Banning peer {} for {:?} after repeated offenses", peer_id, BAN_DURATION);
+			io.ban_peer(peer_id, BAN_DURATION);
+		}
+	}
+
 	/// Remove peer from active peer set. Peer will be reactivated on the next sync
 	/// round.
 	fn deactivate_peer(&mut self, _io: &mut SyncIo, peer_id: PeerId) {
@@ -559,56 +675,50 @@ impl ChainSync {
 		let our_best_block = io.chain().chain_info().best_block_number;
 		let fork_block = self.fork_block.map_or(0, |(n, _)| n);
 
-		let (best_hash, max_peers, snapshot_peers) = {
+		let candidates = {
 			let expected_warp_block = match self.warp_sync {
 				WarpSync::OnlyAndAfter(block) => block,
 				_ => 0,
 			};
 			//collect snapshot infos from peers
-			let snapshots = self.peers.iter()
-				.filter(|&(_, p)| p.is_allowed() && p.snapshot_number.map_or(false, |sn|
+			self.peers.iter()
+				.filter(|&(_, p)| p.is_allowed())
+				.filter_map(|(p, peer)| match (peer.snapshot_hash, peer.snapshot_number) {
+					(Some(hash), Some(block_number)) => Some(SnapshotCandidate { peer: *p, hash, block_number }),
+					_ => None,
+				})
+				.filter(|c|
 					// Snapshot must be old enough that it's usefull to sync with it
-					our_best_block < sn && (sn - our_best_block) > SNAPSHOT_RESTORE_THRESHOLD &&
+					our_best_block < c.block_number && (c.block_number - our_best_block) > SNAPSHOT_RESTORE_THRESHOLD &&
 					// Snapshot must have been taken after the Fork
-					sn > fork_block &&
+					c.block_number > fork_block &&
 					// Snapshot must be greater than the warp barrier if any
-					sn > expected_warp_block &&
-					// If we know a highest block, snapshot must be recent enough
-					self.highest_block.map_or(true, |highest| {
-						highest < sn || (highest - sn) <= SNAPSHOT_RESTORE_THRESHOLD
-					})
-				))
-				.filter_map(|(p, peer)| peer.snapshot_hash.map(|hash| (p, hash.clone())))
-				.filter(|&(_, ref hash)| !self.snapshot.is_known_bad(hash));
-
-			let mut snapshot_peers = HashMap::new();
-			let mut max_peers: usize = 0;
-			let mut best_hash = None;
-			for (p, hash) in snapshots {
-				let peers = snapshot_peers.entry(hash).or_insert_with(Vec::new);
-				peers.push(*p);
-				if peers.len() > max_peers {
-					max_peers = peers.len();
-					best_hash = Some(hash);
-				}
-			}
-			(best_hash, max_peers, snapshot_peers)
+					c.block_number > expected_warp_block &&
+					!self.snapshot.is_known_bad(&c.hash)
+				)
+				.collect::<Vec<_>>()
 		};
 
-		let timeout = (self.state == SyncState::WaitingPeers) && self.sync_start_time.map_or(false, |t| t.elapsed() > WAIT_PEERS_TIMEOUT);
+		let selection = self.warp_snapshot_policy.select(&candidates, self.highest_block);
+		self.snapshot_selection = Some(selection);
 
-		if let (Some(hash), Some(peers)) = (best_hash, best_hash.map_or(None, |h| snapshot_peers.get(&h))) {
-			if max_peers >= SNAPSHOT_MIN_PEERS {
-				trace!(target: "sync", "Starting confirmed snapshot sync {:?} with {:?}", hash, peers);
-				self.start_snapshot_sync(io, peers);
-			} else if timeout {
-				trace!(target: "sync", "Starting unconfirmed snapshot sync {:?} with {:?}", hash, peers);
-				self.start_snapshot_sync(io, peers);
-			}
-		} else if timeout && !self.warp_sync.is_warp_only() {
-			trace!(target: "sync", "No snapshots found, starting full sync");
-			self.state = SyncState::Idle;
-			self.continue_sync(io);
+		let timeout = (self.state == SyncState::WaitingPeers) && self.sync_start_time.map_or(false, |t| t.elapsed() > self.warp_snapshot_policy.timeout);
+
+		match selection {
+			SnapshotSelection::Chosen { hash, block_number, .. } => {
+				let peers = candidates.iter().filter(|c| c.hash == hash).map(|c| c.peer).collect::<Vec<_>>();
+				trace!(target: "sync", "Starting snapshot sync {:?} (#{}) with {:?}", hash, block_number, peers);
+				self.start_snapshot_sync(io, &peers);
+			},
+			SnapshotSelection::Rejected(reason) => {
+				if timeout && !self.warp_sync.is_warp_only() {
+					trace!(target: "sync", "No acceptable snapshot ({:?}), starting full sync", reason);
+					self.state = SyncState::Idle;
+					self.continue_sync(io);
+				} else {
+					trace!(target: "sync", "No acceptable snapshot yet: {:?}", reason);
+				}
+			},
 		}
 	}
 
@@ -634,23 +744,96 @@ impl ChainSync {
 	}
 
 	/// Update sync after the blockchain has been changed externally.
-	pub fn update_targets(&mut self, chain: &BlockChainClient) {
+	pub fn update_targets(&mut self, client: &BlockChainClient) {
 		// Do not assume that the block queue/chain still has our last_imported_block
-		let chain = chain.chain_info();
+		let chain = client.chain_info();
 		self.new_blocks = BlockDownloader::new(false, &chain.best_block_hash, chain.best_block_number);
 		self.old_blocks = None;
+		self.ancient_progress = None;
 		if self.download_old_blocks {
 			if let (Some(ancient_block_hash), Some(ancient_block_number)) = (chain.ancient_block_hash, chain.ancient_block_number) {
+				let (start_hash, start_number) = ChainSync::resume_ancient_download(client, ancient_block_hash, ancient_block_number);
 
-				trace!(target: "sync", "Downloading old blocks from {:?} (#{}) till {:?} (#{:?})", ancient_block_hash, ancient_block_number, chain.first_block_hash, chain.first_block_number);
-				let mut downloader = BlockDownloader::with_unlimited_reorg(true, &ancient_block_hash, ancient_block_number);
+				trace!(target: "sync", "Downloading old blocks from {:?} (#{}) till {:?} (#{:?})", start_hash, start_number, chain.first_block_hash, chain.first_block_number);
+				let mut downloader = BlockDownloader::with_unlimited_reorg(true, &start_hash, start_number);
 				if let Some(hash) = chain.first_block_hash {
 					trace!(target: "sync", "Downloader target set to {:?}", hash);
 					downloader.set_target(&hash);
 				}
 				self.old_blocks = Some(downloader);
+				self.ancient_progress = Some(AncientDownloadProgress {
+					cursor_hash: start_hash,
+					cursor_number: start_number,
+					pending: Vec::new(),
+				});
+			}
+		}
+	}
+
+	/// Picks the block to resume the ancient block download from.
+	/// Starts past the persisted cursor and any pending batches which made it to the database before the restart.
+	fn resume_ancient_download(client: &BlockChainClient, hash: H256, number: BlockNumber) -> (H256, BlockNumber) {
+		let progress = match client.ancient_download_progress() {
+			Some(progress) => progress,
+			None => return (hash, number),
+		};
+
+		let mut start = (hash, number);
+		if progress.cursor_number > number && client.block_hash(BlockId::Number(progress.cursor_number)) == Some(progress.cursor_hash) {
+			start = (progress.cursor_hash, progress.cursor_number);
+		}
+		for range in progress.pending.iter().filter(|range| range.end > start.1) {
+			match client.block_hash(BlockId::Number(range.end)) {
+				Some(hash) if range.start <= start.1 + 1 => start = (hash, range.end),
+				_ => break,
+			}
+		}
+
+		if start.1 > number {
+			debug!(target: "sync", "Resuming ancient block download from #{} instead of #{}", start.1, number);
+		}
+		start
+	}
+
+	/// Records a batch of ancient blocks handed over for import and persists the download progress.
+	fn persist_ancient_progress(&mut self, io: &mut SyncIo, previous: BlockNumber) {
+		let number = match self.old_blocks {
+			Some(ref downloader) if downloader.last_imported_block_number() > previous => downloader.last_imported_block_number(),
+			_ => return,
+		};
+		let progress = match self.ancient_progress {
+			Some(ref mut progress) => progress,
+			None => return,
+		};
+
+		progress.pending.push(BlockRange { start: previous + 1, end: number });
+		// move the cursor past the batches which are already in the database
+		loop {
+			let flushed = match progress.pending.first() {
+				Some(range) => io.chain().block_hash(BlockId::Number(range.end)).map(|hash| (hash, range.end)),
+				None => None,
+			};
+			match flushed {
+				Some((hash, number)) => {
+					progress.pending.remove(0);
+					progress.cursor_hash = hash;
+					progress.cursor_number = number;
+				},
+				None => break,
 			}
 		}
+		io.chain().set_ancient_download_progress(Some(progress.clone()));
+	}
+
+	/// Remembers the peer as a good source of ancient blocks.
+	fn note_ancient_provider(&mut self, io: &SyncIo, peer_id: PeerId) {
+		if let Some(node_id) = io.peer_node_id(peer_id) {
+			self.ancient_providers.insert(node_id);
+		}
+	}
+
+	fn is_ancient_provider(&self, io: &SyncIo, peer_id: PeerId) -> bool {
+		io.peer_node_id(peer_id).map_or(false, |node_id| self.ancient_providers.contains(&node_id))
 	}
 
 	/// Resume downloading
@@ -682,6 +865,10 @@ impl ChainSync {
 			random::new().shuffle(&mut peers); //TODO: sort by rating
 			// prefer peers with higher protocol version
 			peers.sort_by(|&(_, ref v1), &(_, ref v2)| v1.cmp(v2));
+			if self.old_blocks.is_some() {
+				// let peers which served ancient blocks before pick up the ancient block requests first
+				peers.sort_by_key(|&(peer_id, _)| !self.is_ancient_provider(&*io, peer_id));
+			}
 
 			for (peer_id, _) in peers {
 				self.sync_peer(io, peer_id, false);
@@ -860,11 +1047,16 @@ impl ChainSync {
 				}
 			},
 			BlockSet::OldBlocks => {
+				let previous = self.old_blocks.as_ref().map_or(0, |downloader| downloader.last_imported_block_number());
 				if self.old_blocks.as_mut().map_or(false, |downloader| { downloader.collect_blocks(io, false) == Err(DownloaderImportError::Invalid) }) {
 					self.restart(io);
 				} else if self.old_blocks.as_ref().map_or(false, |downloader| { downloader.is_complete() }) {
 					trace!(target: "sync", "Background block download is complete");
 					self.old_blocks = None;
+					self.ancient_progress = None;
+					io.chain().set_ancient_download_progress(None);
+				} else {
+					self.persist_ancient_progress(io, previous);
 				}
 			}
 		}
@@ -931,6 +1123,7 @@ impl ChainSync {
 			}
 		}
 		for p in aborting {
+			self.report_offense(io, p, Offense::Timeout);
 			SyncHandler::on_peer_aborting(self, io, p);
 		}
 
@@ -1077,6 +1270,54 @@ impl ChainSync {
 	pub fn maintain_sync(&mut self, io: &mut SyncIo) {
 		self.maybe_start_snapshot_sync(io);
 		self.check_resume(io);
+		self.check_stall(io);
+	}
+
+	/// Resets the block downloader if nothing was imported for a while, even though peers with a better chain are connected.
+	fn check_stall(&mut self, io: &mut SyncIo) {
+		let chain_info = io.chain().chain_info();
+		let queue_size = io.chain().queue_info().total_queue_size();
+		let syncing = self.state == SyncState::Blocks || self.state == SyncState::NewBlocks;
+		let better_peers = self.peers.values()
+			.any(|p| p.can_sync() && p.difficulty.map_or(false, |d| d > chain_info.pending_total_difficulty));
+		let progress = chain_info.best_block_number != self.import_progress.best_block || queue_size != self.import_progress.queue_size;
+
+		if !syncing || !better_peers || progress {
+			self.import_progress = ImportProgress {
+				best_block: chain_info.best_block_number,
+				queue_size,
+				since: Instant::now(),
+			};
+			return;
+		}
+
+		if self.import_progress.since.elapsed() < self.stall_timeout {
+			return;
+		}
+
+		warn!(target: "sync", "Block download stalled at #{} for {:?}, resetting downloader", chain_info.best_block_number, self.stall_timeout);
+		debug!(target: "sync", "Stalled downloader: {}, state={:?}, queue size={}", self.new_blocks.summary(), self.state, queue_size);
+		for (peer_id, peer) in &self.peers {
+			debug!(
+				target: "sync",
+				"Stalled peer {}: asking={:?}, set={:?}, for={:?}, latest={}, td={:?}",
+				peer_id, peer.asking, peer.block_set, peer.ask_time.elapsed(), peer.latest_hash, peer.difficulty
+			);
+		}
+
+		self.downloader_resets += 1;
+		self.import_progress.since = Instant::now();
+		// restart the download from our best block, but keep the peers and the ancient block download
+		self.new_blocks = BlockDownloader::new(false, &chain_info.best_block_hash, chain_info.best_block_number);
+		for peer in self.peers.values_mut().filter(|p| p.block_set != Some(BlockSet::OldBlocks)) {
+			peer.asking = PeerAsking::Nothing;
+			peer.asking_blocks.clear();
+			peer.asking_hash = None;
+			peer.block_set = None;
+			peer.expired = false;
+		}
+		self.active_peers = self.peers.keys().cloned().collect();
+		self.continue_sync(io);
 	}
 
 	/// called when block is imported to chain - propagates the blocks and updates transactions sent to peers
@@ -1136,6 +1377,11 @@ impl ChainSync {
 		SyncPropagator::propagate_new_transactions(self, io)
 	}
 
+	/// Sends new block announcements batched since the last announcement.
+	pub fn flush_block_announcements(&mut self, io: &mut SyncIo) {
+		SyncPropagator::flush_block_announcements(self, io);
+	}
+
 	/// Broadcast consensus message to peers.
 	pub fn propagate_consensus_packet(&mut self, io: &mut SyncIo, packet: Bytes) {
 		SyncPropagator::propagate_consensus_packet(self, io, packet);
@@ -1165,6 +1411,7 @@ pub mod tests {
 	use ethcore::client::{BlockChainClient, EachBlockWith, TestBlockChainClient, ChainInfo, BlockInfo};
 	use ethcore::miner::{MinerService, PendingOrdering};
 	use private_tx::NoopPrivateTxHandler;
+	use block_sync::BlockRequest;
 
 	pub fn get_dummy_block(order: u32, parent_hash: H256) -> Bytes {
 		let mut header = Header::new();
@@ -1232,6 +1479,11 @@ pub mod tests {
 			num_snapshot_chunks: 0,
 			snapshot_chunks_done: 0,
 			last_imported_old_block_number: None,
+			transactions_propagated: 0,
+			transactions_bytes_propagated: 0,
+			transactions_duplicates_avoided: 0,
+			snapshot_selection: None,
+			downloader_resets: 0,
 		}
 	}
 
@@ -1260,12 +1512,14 @@ pub mod tests {
 				genesis: H256::zero(),
 				network_id: 0,
 				latest_hash: peer_latest_hash,
+				latest_number: None,
 				difficulty: None,
 				asking: PeerAsking::Nothing,
 				asking_blocks: Vec::new(),
 				asking_hash: None,
 				ask_time: Instant::now(),
-				last_sent_transactions: HashSet::new(),
+				last_sent_transactions: PeerInfo::known_transactions(sync.max_known_transactions),
+				last_propagated_transaction: None,
 				last_sent_private_transactions: HashSet::new(),
 				expired: false,
 				confirmation: super::ForkConfirmation::Confirmed,
@@ -1420,4 +1674,85 @@ pub mod tests {
 		let status = io.chain.miner.queue_status();
 		assert_eq!(status.status.transaction_count, 0);
 	}
+
+	#[test]
+	fn resumes_ancient_download_from_persisted_cursor() {
+		let client = TestBlockChainClient::new();
+		client.add_blocks(30, EachBlockWith::Nothing);
+		let hash = |n| client.block_hash(BlockId::Number(n)).unwrap();
+		*client.ancient_block.write() = Some((hash(10), 10));
+		*client.first_block.write() = Some((hash(30), 30));
+		// blocks past #30 were queued, but never made it to the database
+		client.set_ancient_download_progress(Some(AncientDownloadProgress {
+			cursor_hash: hash(15),
+			cursor_number: 15,
+			pending: vec![
+				BlockRange { start: 16, end: 20 },
+				BlockRange { start: 21, end: 25 },
+				BlockRange { start: 26, end: 40 },
+			],
+		}));
+
+		let mut sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		assert_eq!(sync.old_blocks.as_ref().unwrap().last_imported_block_number(), 25);
+
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&client, &ss, &queue, None);
+		match sync.old_blocks.as_mut().unwrap().request_blocks(&mut io, 0) {
+			Some(BlockRequest::Headers { start, .. }) => assert_eq!(start, hash(25)),
+			_ => panic!("Expected headers request"),
+		}
+	}
+
+	#[test]
+	fn ignores_invalid_ancient_download_cursor() {
+		let client = TestBlockChainClient::new();
+		client.add_blocks(20, EachBlockWith::Nothing);
+		let hash = |n| client.block_hash(BlockId::Number(n)).unwrap();
+		*client.ancient_block.write() = Some((hash(10), 10));
+		client.set_ancient_download_progress(Some(AncientDownloadProgress {
+			cursor_hash: H256::from(1),
+			cursor_number: 15,
+			pending: vec![BlockRange { start: 16, end: 20 }],
+		}));
+
+		let sync = ChainSync::new(SyncConfig::default(), &client, Arc::new(NoopPrivateTxHandler));
+		assert_eq!(sync.old_blocks.as_ref().unwrap().last_imported_block_number(), 10);
+	}
+
+	#[test]
+	fn resets_stalled_downloader_without_dropping_peers() {
+		let mut config = SyncConfig::default();
+		config.stall_timeout = Duration::from_secs(0);
+		let client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let mut sync = ChainSync::new(config, &client, Arc::new(NoopPrivateTxHandler));
+		insert_dummy_peer(&mut sync, 0, H256::random());
+		sync.state = SyncState::Blocks;
+		sync.active_peers.insert(0);
+		{
+			// the peer was asked for a range it never answers
+			let peer = sync.peers.get_mut(&0).unwrap();
+			peer.difficulty = Some(U256::from(1_000_000));
+			peer.asking = PeerAsking::BlockBodies;
+			peer.block_set = Some(BlockSet::NewBlocks);
+		}
+
+		let queue = RwLock::new(VecDeque::new());
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&client, &ss, &queue, None);
+		sync.maintain_sync(&mut io);
+
+		assert_eq!(sync.status().downloader_resets, 1);
+		assert!(sync.peers.contains_key(&0));
+		assert!(io.to_disconnect.is_empty());
+		assert_eq!(sync.peers[&0].asking, PeerAsking::BlockHeaders);
+		assert!(io.packets.iter().any(|p| p.recipient == 0 && p.packet_id == GET_BLOCK_HEADERS_PACKET));
+
+		// no further resets while there is progress to wait for
+		client.add_blocks(1, EachBlockWith::Nothing);
+		sync.maintain_sync(&mut io);
+		assert_eq!(sync.status().downloader_resets, 1);
+	}
 }
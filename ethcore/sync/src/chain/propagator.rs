@@ -16,7 +16,7 @@
 
 use bytes::Bytes;
 use ethereum_types::H256;
-use ethcore::client::BlockChainInfo;
+use ethcore::client::{BlockChainClient, BlockChainInfo, BlockId};
 use ethcore::header::BlockNumber;
 use network::{PeerId, PacketId};
 use rand::Rng;
@@ -24,13 +24,18 @@ use rlp::{Encodable, RlpStream};
 use sync_io::SyncIo;
 use std::cmp;
 use std::collections::HashSet;
+use std::time::Instant;
 use transaction::SignedTransaction;
 
 use super::{
 	random,
 	ChainSync,
+	PeerInfo,
+	BLOCK_ANNOUNCEMENT_BATCH_WINDOW,
 	MAX_TRANSACTION_PACKET_SIZE,
+	MAX_TRANSACTION_PACKETS_PER_PEER,
 	MAX_PEER_LAG_PROPAGATION,
+	MAX_PEER_DISTANCE_NEW_BLOCK,
 	MAX_PEERS_PROPAGATION,
 	MIN_PEERS_PROPAGATION,
 	CONSENSUS_DATA_PACKET,
@@ -82,6 +87,7 @@ impl SyncPropagator {
 			}
 			if let Some(ref mut peer) = sync.peers.get_mut(peer_id) {
 				peer.latest_hash = chain_info.best_block_hash.clone();
+				peer.latest_number = Some(chain_info.best_block_number);
 			}
 			sent += 1;
 		}
@@ -94,11 +100,19 @@ impl SyncPropagator {
 		let mut sent = 0;
 		let last_parent = *io.chain().best_block_header().parent_hash();
 		for peer_id in peers {
-			sent += match ChainSync::create_new_hashes_rlp(io.chain(), &last_parent, &chain_info.best_block_hash) {
+			// announce all blocks since the peer head, so that the peer doesn't get hashes it already knows
+			let from = sync.peers.get(peer_id)
+				.and_then(|peer| match io.chain().block_number(BlockId::Hash(peer.latest_hash)) {
+					Some(number) if number < chain_info.best_block_number && chain_info.best_block_number - number < MAX_PEER_LAG_PROPAGATION => Some(peer.latest_hash),
+					_ => None,
+				})
+				.unwrap_or(last_parent);
+			sent += match ChainSync::create_new_hashes_rlp(io.chain(), &from, &chain_info.best_block_hash) {
 				Some(rlp) => {
 					{
 						if let Some(ref mut peer) = sync.peers.get_mut(peer_id) {
 							peer.latest_hash = chain_info.best_block_hash.clone();
+							peer.latest_number = Some(chain_info.best_block_number);
 						}
 					}
 					SyncPropagator::send_packet(io, *peer_id, NEW_BLOCK_HASHES_PACKET, rlp);
@@ -110,6 +124,65 @@ impl SyncPropagator {
 		sent
 	}
 
+	/// Splits peers into those close enough to our head to receive full new blocks and the rest.
+	/// Peers which announced higher total difficulty than ours are skipped.
+	fn select_block_recipients(sync: &ChainSync, chain_info: &BlockChainInfo, chain: &BlockChainClient, peers: &[PeerId]) -> (Vec<PeerId>, Vec<PeerId>) {
+		let mut near = Vec::new();
+		let mut far = Vec::new();
+		for peer_id in peers {
+			let peer = match sync.peers.get(peer_id) {
+				Some(peer) => peer,
+				None => continue,
+			};
+			if peer.difficulty.map_or(false, |difficulty| difficulty > chain_info.total_difficulty) {
+				trace!(target: "sync", "Not announcing blocks to {}, its total difficulty is higher", peer_id);
+				continue;
+			}
+			match SyncPropagator::peer_head_number(chain, peer) {
+				Some(number) if chain_info.best_block_number.saturating_sub(number) <= MAX_PEER_DISTANCE_NEW_BLOCK => near.push(*peer_id),
+				_ => far.push(*peer_id),
+			}
+		}
+		(near, far)
+	}
+
+	/// Returns the best block number of the peer, if known.
+	fn peer_head_number(chain: &BlockChainClient, peer: &PeerInfo) -> Option<BlockNumber> {
+		chain.block_number(BlockId::Hash(peer.latest_hash)).or(peer.latest_number)
+	}
+
+	/// Sends full latest block to a random subset of peers close to our head and hashes to the other lagging peers.
+	fn announce_latest_blocks(sync: &mut ChainSync, chain_info: &BlockChainInfo, io: &mut SyncIo) {
+		sync.block_announcement_pending = false;
+		sync.last_block_announcement = Some(Instant::now());
+
+		let peers = sync.get_lagging_peers(chain_info);
+		let (near, far) = SyncPropagator::select_block_recipients(sync, chain_info, io.chain(), &peers);
+		let lucky = ChainSync::select_random_peers(&near);
+		let rest = near.into_iter()
+			.filter(|peer_id| !lucky.contains(peer_id))
+			.chain(far)
+			.collect::<Vec<_>>();
+
+		let blocks = SyncPropagator::propagate_blocks(sync, chain_info, io, &[], &lucky);
+		let hashes = SyncPropagator::propagate_new_hashes(sync, chain_info, io, &rest);
+		if blocks != 0 || hashes != 0 {
+			trace!(target: "sync", "Sent latest {} blocks and {} hashes to peers.", blocks, hashes);
+		}
+	}
+
+	/// Sends announcements of blocks imported within the last batching window.
+	pub fn flush_block_announcements(sync: &mut ChainSync, io: &mut SyncIo) {
+		if !sync.block_announcement_pending {
+			return;
+		}
+		if sync.last_block_announcement.map_or(false, |time| time.elapsed() < BLOCK_ANNOUNCEMENT_BATCH_WINDOW) {
+			return;
+		}
+		let chain_info = io.chain().chain_info();
+		SyncPropagator::announce_latest_blocks(sync, &chain_info, io);
+	}
+
 	/// propagates new transactions to all peers
 	pub fn propagate_new_transactions(sync: &mut ChainSync, io: &mut SyncIo) -> usize {
 		// Early out if nobody to send to.
@@ -148,116 +221,149 @@ impl SyncPropagator {
 		let all_transactions_hashes = transactions.iter()
 			.map(|tx| tx.hash())
 			.collect::<HashSet<H256>>();
-		let all_transactions_rlp = {
-			let mut packet = RlpStream::new_list(transactions.len());
-			for tx in &transactions { packet.append(&**tx); }
-			packet.out()
-		};
+		let all_transactions_rlp = transactions.iter()
+			.map(|tx| {
+				let mut transaction = RlpStream::new();
+				tx.rlp_append(&mut transaction);
+				transaction.out()
+			})
+			.collect::<Vec<_>>();
 
 		// Clear old transactions from stats
 		sync.transactions_stats.retain(&all_transactions_hashes);
 
-		// sqrt(x)/x scaled to max u32
 		let block_number = io.chain().chain_info().best_block_number;
 
-		let lucky_peers = {
-			peers.into_iter()
-				.filter_map(|peer_id| {
-					let stats = &mut sync.transactions_stats;
-					let peer_info = sync.peers.get_mut(&peer_id)
-						.expect("peer_id is form peers; peers is result of select_peers_for_transactions; select_peers_for_transactions selects peers from self.peers; qed");
-
-					// Send all transactions
-					if peer_info.last_sent_transactions.is_empty() {
-						// update stats
-						for hash in &all_transactions_hashes {
-							let id = io.peer_session_info(peer_id).and_then(|info| info.id);
-							stats.propagated(hash, id, block_number);
-						}
-						peer_info.last_sent_transactions = all_transactions_hashes.clone();
-						return Some((peer_id, all_transactions_hashes.len(), all_transactions_rlp.clone()));
+		let mut peers_sent = HashSet::new();
+		let mut max_sent = 0;
+		for peer_id in peers {
+			let (packets, sent) = {
+				let stats = &mut sync.transactions_stats;
+				let propagation_stats = &mut sync.propagation_stats;
+				let peer_info = sync.peers.get_mut(&peer_id)
+					.expect("peer_id is form peers; peers is result of select_peers_for_transactions; select_peers_for_transactions selects peers from self.peers; qed");
+
+				// Resume after the last transaction sent by the previous round, so that all transactions
+				// eventually reach the peer even if each round is cut short. Start over if it's gone.
+				let offset = peer_info.last_propagated_transaction
+					.and_then(|last| transactions.iter().position(|tx| tx.hash() == last))
+					.map_or(0, |index| index + 1);
+				let mut to_send = Vec::new();
+				for index in (offset..transactions.len()).chain(0..offset) {
+					let hash = transactions[index].hash();
+					if peer_info.last_sent_transactions.contains_key(&hash) {
+						propagation_stats.duplicates_avoided += 1;
+					} else {
+						to_send.push(index);
 					}
+				}
 
-					// Get hashes of all transactions to send to this peer
-					let to_send = all_transactions_hashes.difference(&peer_info.last_sent_transactions)
-						.cloned()
-						.collect::<HashSet<_>>();
-					if to_send.is_empty() {
-						return None;
-					}
+				if to_send.is_empty() {
+					continue;
+				}
 
-					// Construct RLP
-					let (packet, to_send) = {
-						let mut to_send = to_send;
-						let mut packet = RlpStream::new();
-						packet.begin_unbounded_list();
-						let mut pushed = 0;
-						for tx in &transactions {
-							let hash = tx.hash();
-							if to_send.contains(&hash) {
-								let mut transaction = RlpStream::new();
-								tx.rlp_append(&mut transaction);
-								let appended = packet.append_raw_checked(&transaction.drain(), 1, MAX_TRANSACTION_PACKET_SIZE);
-								if !appended {
-									// Maximal packet size reached just proceed with sending
-									debug!("Transaction packet size limit reached. Sending incomplete set of {}/{} transactions.", pushed, to_send.len());
-									to_send = to_send.into_iter().take(pushed).collect();
-									break;
-								}
-								pushed += 1;
-							}
-						}
-						packet.complete_unbounded_list();
-						(packet, to_send)
-					};
-
-					// Update stats
-					let id = io.peer_session_info(peer_id).and_then(|info| info.id);
-					for hash in &to_send {
-						// update stats
-						stats.propagated(hash, id, block_number);
-					}
+				let (packets, sent) = SyncPropagator::split_transactions_packets(
+					to_send.iter().map(|index| &*all_transactions_rlp[*index]),
+					MAX_TRANSACTION_PACKET_SIZE,
+					MAX_TRANSACTION_PACKETS_PER_PEER,
+				);
+				if sent < to_send.len() {
+					debug!(target: "sync", "Transaction packets limit reached. Sending incomplete set of {}/{} transactions.", sent, to_send.len());
+				}
+				peer_info.last_propagated_transaction = match sent < to_send.len() {
+					true => to_send[..sent].last().map(|index| transactions[*index].hash()),
+					false => None,
+				};
+
+				// Update stats
+				let id = io.peer_session_info(peer_id).and_then(|info| info.id);
+				for index in &to_send[..sent] {
+					let hash = transactions[*index].hash();
+					stats.propagated(&hash, id, block_number);
+					peer_info.last_sent_transactions.insert(hash, ());
+				}
 
-					peer_info.last_sent_transactions = all_transactions_hashes
-						.intersection(&peer_info.last_sent_transactions)
-						.chain(&to_send)
-						.cloned()
-						.collect();
-					Some((peer_id, to_send.len(), packet.out()))
-				})
-				.collect::<Vec<_>>()
-		};
+				(packets, sent)
+			};
 
-		// Send RLPs
-		let mut peers = HashSet::new();
-		if lucky_peers.len() > 0 {
-			let mut max_sent = 0;
-			let lucky_peers_len = lucky_peers.len();
-			for (peer_id, sent, rlp) in lucky_peers {
-				peers.insert(peer_id);
+			if sent == 0 {
+				continue;
+			}
+
+			peers_sent.insert(peer_id);
+			max_sent = cmp::max(max_sent, sent);
+			sync.propagation_stats.transactions += sent as u64;
+			for (entries, rlp) in packets {
 				let size = rlp.len();
+				sync.propagation_stats.bytes += size as u64;
 				SyncPropagator::send_packet(io, peer_id, TRANSACTIONS_PACKET, rlp);
-				trace!(target: "sync", "{:02} <- Transactions ({} entries; {} bytes)", peer_id, sent, size);
-				max_sent = cmp::max(max_sent, sent);
+				trace!(target: "sync", "{:02} <- Transactions ({} entries; {} bytes)", peer_id, entries, size);
 			}
-			debug!(target: "sync", "Sent up to {} transactions to {} peers.", max_sent, lucky_peers_len);
 		}
 
-		peers
+		if !peers_sent.is_empty() {
+			debug!(target: "sync", "Sent up to {} transactions to {} peers.", max_sent, peers_sent.len());
+		}
+
+		peers_sent
+	}
+
+	/// Packs RLP-encoded transactions into at most `max_packets` packets no greater than `max_packet_size` each.
+	/// Returns the packets along with the number of transactions in each of them and the number of transactions packed.
+	fn split_transactions_packets<'a, I>(transactions: I, max_packet_size: usize, max_packets: usize) -> (Vec<(usize, Bytes)>, usize)
+		where I: IntoIterator<Item = &'a [u8]> {
+		let mut packets = Vec::new();
+		let mut packed = 0;
+		let mut packet = RlpStream::new();
+		packet.begin_unbounded_list();
+		let mut entries = 0;
+
+		for transaction in transactions {
+			if !packet.append_raw_checked(transaction, 1, max_packet_size) {
+				if entries == 0 {
+					// A single transaction exceeding the limit can never be sent.
+					debug!(target: "sync", "Transaction of {} bytes exceeds the packet size limit.", transaction.len());
+					break;
+				}
+
+				packet.complete_unbounded_list();
+				packets.push((entries, packet.out()));
+				if packets.len() == max_packets {
+					return (packets, packed);
+				}
+
+				packet = RlpStream::new();
+				packet.begin_unbounded_list();
+				entries = 0;
+				if !packet.append_raw_checked(transaction, 1, max_packet_size) {
+					debug!(target: "sync", "Transaction of {} bytes exceeds the packet size limit.", transaction.len());
+					return (packets, packed);
+				}
+			}
+			entries += 1;
+			packed += 1;
+		}
+
+		if entries > 0 {
+			packet.complete_unbounded_list();
+			packets.push((entries, packet.out()));
+		}
+
+		(packets, packed)
 	}
 
 	pub fn propagate_latest_blocks(sync: &mut ChainSync, io: &mut SyncIo, sealed: &[H256]) {
 		let chain_info = io.chain().chain_info();
 		if (((chain_info.best_block_number as i64) - (sync.last_sent_block_number as i64)).abs() as BlockNumber) < MAX_PEER_LAG_PROPAGATION {
-			let mut peers = sync.get_lagging_peers(&chain_info);
 			if sealed.is_empty() {
-				let hashes = SyncPropagator::propagate_new_hashes(sync, &chain_info, io, &peers);
-				peers = ChainSync::select_random_peers(&peers);
-				let blocks = SyncPropagator::propagate_blocks(sync, &chain_info, io, sealed, &peers);
-				if blocks != 0 || hashes != 0 {
-					trace!(target: "sync", "Sent latest {} blocks and {} hashes to peers.", blocks, hashes);
+				if sync.last_block_announcement.map_or(false, |time| time.elapsed() < BLOCK_ANNOUNCEMENT_BATCH_WINDOW) {
+					trace!(target: "sync", "Batching announcement of block {}", chain_info.best_block_number);
+					sync.block_announcement_pending = true;
+				} else {
+					SyncPropagator::announce_latest_blocks(sync, &chain_info, io);
 				}
 			} else {
+				let peers = sync.get_lagging_peers(&chain_info);
 				SyncPropagator::propagate_blocks(sync, &chain_info, io, sealed, &peers);
 				SyncPropagator::propagate_new_hashes(sync, &chain_info, io, &peers);
 				trace!(target: "sync", "Sent sealed block to all peers");
@@ -333,7 +439,7 @@ mod tests {
 	use private_tx::NoopPrivateTxHandler;
 	use rlp::{Rlp};
 	use std::collections::{VecDeque};
-	use tests::helpers::{TestIo};
+	use tests::helpers::{TestIo, TestPacket};
 	use tests::snapshot::TestSnapshotService;
 
 	use super::{*, super::{*, tests::*}};
@@ -379,6 +485,56 @@ mod tests {
 		assert_eq!(0x07, io.packets[0].packet_id);
 	}
 
+	#[test]
+	fn announces_blocks_according_to_peer_head() {
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(100, EachBlockWith::Uncle);
+		let queue = RwLock::new(VecDeque::new());
+		let near_head = client.block_hash_delta_minus(2);
+		let far_head = client.block_hash_delta_minus(50);
+		let mut sync = dummy_sync_with_peer(near_head, &client);
+		for peer_id in 1..4 {
+			insert_dummy_peer(&mut sync, peer_id, near_head);
+		}
+		insert_dummy_peer(&mut sync, 4, far_head);
+		insert_dummy_peer(&mut sync, 5, H256::from(0xdead));
+		insert_dummy_peer(&mut sync, 6, near_head);
+		let chain_info = client.chain_info();
+		sync.peers.get_mut(&6).unwrap().difficulty = Some(chain_info.total_difficulty + U256::from(1));
+		sync.last_sent_block_number = chain_info.best_block_number;
+		let ss = TestSnapshotService::new();
+		let mut io = TestIo::new(&mut client, &ss, &queue, None);
+
+		let count_packets = |packets: &[TestPacket], packet_id: PacketId, peers: &[PeerId]|
+			packets.iter().filter(|p| p.packet_id == packet_id && peers.contains(&p.recipient)).count();
+
+		SyncPropagator::propagate_latest_blocks(&mut sync, &mut io, &[]);
+
+		// peers close to our head get the block, the others only hashes
+		assert_eq!(6, io.packets.len());
+		assert_eq!(4, count_packets(&io.packets, NEW_BLOCK_PACKET, &[0, 1, 2, 3]));
+		assert_eq!(2, count_packets(&io.packets, NEW_BLOCK_HASHES_PACKET, &[4, 5]));
+		// peer with higher total difficulty gets nothing
+		assert_eq!(0, io.packets.iter().filter(|p| p.recipient == 6).count());
+
+		// blocks imported shortly after the announcement are batched
+		io.packets.clear();
+		io.chain.add_blocks(2, EachBlockWith::Nothing);
+		SyncPropagator::propagate_latest_blocks(&mut sync, &mut io, &[]);
+		SyncPropagator::flush_block_announcements(&mut sync, &mut io);
+		assert!(io.packets.is_empty());
+		assert!(sync.block_announcement_pending);
+
+		sync.last_block_announcement = Some(Instant::now() - BLOCK_ANNOUNCEMENT_BATCH_WINDOW);
+		SyncPropagator::flush_block_announcements(&mut sync, &mut io);
+		assert!(!sync.block_announcement_pending);
+		// all peers are close to our head now
+		assert_eq!(6, io.packets.len());
+		assert_eq!(4, count_packets(&io.packets, NEW_BLOCK_PACKET, &[0, 1, 2, 3, 4, 5]));
+		assert_eq!(2, count_packets(&io.packets, NEW_BLOCK_HASHES_PACKET, &[0, 1, 2, 3, 4, 5]));
+		assert_eq!(0, io.packets.iter().filter(|p| p.recipient == 6).count());
+	}
+
 	#[test]
 	fn sends_sealed_block() {
 		let mut client = TestBlockChainClient::new();
@@ -414,12 +570,14 @@ mod tests {
 				genesis: H256::zero(),
 				network_id: 0,
 				latest_hash: client.block_hash_delta_minus(1),
+				latest_number: None,
 				difficulty: None,
 				asking: PeerAsking::Nothing,
 				asking_blocks: Vec::new(),
 				asking_hash: None,
 				ask_time: Instant::now(),
-				last_sent_transactions: HashSet::new(),
+				last_sent_transactions: PeerInfo::known_transactions(sync.max_known_transactions),
+				last_propagated_transaction: None,
 				last_sent_private_transactions: HashSet::new(),
 				expired: false,
 				confirmation: ForkConfirmation::Confirmed,
@@ -631,4 +789,32 @@ mod tests {
 		assert!(sent_transactions.iter().any(|tx| tx.hash() == tx1_hash));
 		assert!(sent_transactions.iter().any(|tx| tx.hash() == tx2_hash));
 	}
+
+	#[test]
+	fn should_split_transactions_packets_within_budget() {
+		let transactions = (0..10u8).map(|i| vec![0x80 + 8, i, i, i, i, i, i, i, i]).collect::<Vec<_>>();
+
+		let (packets, packed) = SyncPropagator::split_transactions_packets(
+			transactions.iter().map(|tx| &**tx),
+			32,
+			10,
+		);
+
+		assert_eq!(packed, 10);
+		assert!(packets.len() > 1);
+		assert_eq!(packets.iter().map(|&(entries, _)| entries).sum::<usize>(), 10);
+		for &(entries, ref packet) in &packets {
+			assert!(packet.len() <= 32);
+			assert_eq!(Rlp::new(packet).item_count().unwrap(), entries);
+		}
+
+		// the number of packets is limited as well
+		let (packets, packed) = SyncPropagator::split_transactions_packets(
+			transactions.iter().map(|tx| &**tx),
+			32,
+			1,
+		);
+		assert_eq!(packets.len(), 1);
+		assert_eq!(packed, packets[0].0);
+	}
 }
@@ -42,6 +42,7 @@ extern crate keccak_hash as hash;
 extern crate keccak_hasher;
 extern crate triehash_ethereum;
 extern crate kvdb;
+extern crate lru_cache;
 
 extern crate ethcore_light as light;
 
@@ -64,7 +65,9 @@ mod blocks;
 mod block_sync;
 mod sync_io;
 mod private_tx;
+mod reputation;
 mod snapshot;
+mod snapshot_selection;
 mod transactions_stats;
 
 pub mod light_sync;
@@ -78,4 +81,5 @@ pub use api::*;
 pub use chain::{SyncStatus, SyncState};
 pub use devp2p::validate_node_url;
 pub use network::{NonReservedPeerMode, Error, ErrorKind, ConnectionFilter, ConnectionDirection};
+pub use snapshot_selection::{WarpSnapshotPolicy, SnapshotSelection, SnapshotRejection};
 pub use private_tx::{PrivateTxHandler, NoopPrivateTxHandler, SimplePrivateTxHandler};
@@ -0,0 +1,146 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Peer reputation tracking.
+//! Offenses committed by a node accumulate penalty points which slowly decay over time.
+//! Once the penalty crosses the threshold the node should be banned.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ethereum_types::H512;
+
+type NodeId = H512;
+
+/// Penalty at which the node gets banned.
+const BAN_THRESHOLD: u64 = 100;
+/// Time it takes to forgive a single penalty point.
+const DECAY_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a banned node is refused.
+pub const BAN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Misbehaviour of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offense {
+	/// Sent a block that failed verification (e.g. invalid seal).
+	InvalidBlock,
+	/// Sent data which could not be decoded.
+	BadRlp,
+	/// Sent data which does not match our request.
+	UnrequestedData,
+	/// Did not answer a request in time.
+	Timeout,
+}
+
+impl Offense {
+	fn penalty(&self) -> u64 {
+		match *self {
+			Offense::InvalidBlock => 50,
+			Offense::BadRlp => 25,
+			Offense::UnrequestedData => 20,
+			Offense::Timeout => 10,
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Penalty {
+	points: u64,
+	updated: Instant,
+}
+
+impl Penalty {
+	fn decay(&mut self, now: Instant) {
+		let elapsed = now.duration_since(self.updated).as_secs();
+		let forgiven = elapsed / DECAY_INTERVAL.as_secs();
+		if forgiven > 0 {
+			self.points = self.points.saturating_sub(forgiven);
+			self.updated = now;
+		}
+	}
+}
+
+/// Tracks penalties of nodes, keyed by node id so that they survive reconnects.
+#[derive(Debug, Default)]
+pub struct PeerReputation {
+	penalties: HashMap<NodeId, Penalty>,
+}
+
+impl PeerReputation {
+	/// Records an offense of the node. Returns `true` if the node should be banned.
+	pub fn report(&mut self, id: &NodeId, offense: Offense) -> bool {
+		self.report_at(id, offense, Instant::now())
+	}
+
+	fn report_at(&mut self, id: &NodeId, offense: Offense, now: Instant) -> bool {
+		// drop penalties which have been forgiven completely
+		self.penalties.retain(|_, penalty| {
+			penalty.decay(now);
+			penalty.points > 0
+		});
+
+		let ban = {
+			let penalty = self.penalties.entry(*id).or_insert_with(|| Penalty { points: 0, updated: now });
+			penalty.points += offense.penalty();
+			penalty.points >= BAN_THRESHOLD
+		};
+
+		if ban {
+			// start from scratch once the ban is over
+			self.penalties.remove(id);
+		}
+		ban
+	}
+
+	/// Returns current penalty of the node.
+	#[cfg(test)]
+	pub fn penalty(&self, id: &NodeId) -> u64 {
+		self.penalties.get(id).map_or(0, |penalty| penalty.points)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+	use super::{PeerReputation, Offense, NodeId};
+
+	#[test]
+	fn should_ban_after_repeated_offenses() {
+		let mut reputation = PeerReputation::default();
+		let id = NodeId::from(1);
+		let now = Instant::now();
+
+		assert!(!reputation.report_at(&id, Offense::InvalidBlock, now));
+		assert!(!reputation.report_at(&id, Offense::BadRlp, now));
+		assert!(!reputation.report_at(&id, Offense::Timeout, now));
+		assert!(reputation.report_at(&id, Offense::UnrequestedData, now));
+		assert_eq!(reputation.penalty(&id), 0);
+	}
+
+	#[test]
+	fn should_forgive_offenses_over_time() {
+		let mut reputation = PeerReputation::default();
+		let id = NodeId::from(1);
+		let other = NodeId::from(2);
+		let now = Instant::now();
+
+		assert!(!reputation.report_at(&id, Offense::InvalidBlock, now));
+		assert!(!reputation.report_at(&other, Offense::Timeout, now + Duration::from_secs(100)));
+		assert_eq!(reputation.penalty(&id), 40);
+		assert!(!reputation.report_at(&id, Offense::InvalidBlock, now + Duration::from_secs(100)));
+		assert_eq!(reputation.penalty(&id), 90);
+		assert_eq!(reputation.penalty(&other), 10);
+	}
+}
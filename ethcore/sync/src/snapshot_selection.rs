@@ -0,0 +1,195 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Selection of the snapshot manifest to warp sync from.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::time::Duration;
+use ethereum_types::H256;
+use ethcore::header::BlockNumber;
+use network::PeerId;
+
+/// Policy deciding whether a snapshot advertised by peers is trustworthy enough to restore.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarpSnapshotPolicy {
+	/// Minimal number of distinct peers advertising the same manifest hash.
+	pub min_peers: usize,
+	/// Minimal percentage of peers with a recent snapshot which have to advertise the same manifest hash.
+	pub min_agreement_percent: usize,
+	/// Maximal distance of the snapshot block from the best block advertised by peers.
+	pub max_age: BlockNumber,
+	/// Time to wait for an acceptable manifest before falling back to full sync.
+	pub timeout: Duration,
+}
+
+impl Default for WarpSnapshotPolicy {
+	fn default() -> Self {
+		WarpSnapshotPolicy {
+			min_peers: 3,
+			min_agreement_percent: 50,
+			max_age: 30000,
+			timeout: Duration::from_secs(5),
+		}
+	}
+}
+
+/// Snapshot advertised by a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotCandidate {
+	/// Advertising peer.
+	pub peer: PeerId,
+	/// Manifest hash.
+	pub hash: H256,
+	/// Block number of the snapshot.
+	pub block_number: BlockNumber,
+}
+
+/// Reason for rejecting all advertised manifests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotRejection {
+	/// No peer advertises a usable snapshot.
+	NoSnapshots,
+	/// All advertised snapshots are older than allowed.
+	Stale,
+	/// The best manifest is not advertised by enough peers.
+	NotEnoughPeers {
+		/// Manifest hash.
+		hash: H256,
+		/// Number of peers advertising it.
+		peers: usize,
+	},
+	/// The best manifest is not advertised by a large enough share of peers.
+	NotEnoughAgreement {
+		/// Manifest hash.
+		hash: H256,
+		/// Number of peers advertising it.
+		peers: usize,
+		/// Number of peers advertising any recent snapshot.
+		snapshot_peers: usize,
+	},
+}
+
+/// Outcome of the last manifest selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotSelection {
+	/// Manifest was chosen to restore from.
+	Chosen {
+		/// Manifest hash.
+		hash: H256,
+		/// Block number of the snapshot.
+		block_number: BlockNumber,
+		/// Number of peers advertising it.
+		peers: usize,
+	},
+	/// No manifest was acceptable.
+	Rejected(SnapshotRejection),
+}
+
+impl WarpSnapshotPolicy {
+	/// Picks the manifest advertised by most peers among the recent ones, preferring newer snapshots on a tie.
+	///
+	/// Snapshots are considered stale if they are older than `max_age` blocks relative to the best block
+	/// advertised by peers, either as their head or as their snapshot.
+	pub fn select(&self, candidates: &[SnapshotCandidate], highest_block: Option<BlockNumber>) -> SnapshotSelection {
+		if candidates.is_empty() {
+			return SnapshotSelection::Rejected(SnapshotRejection::NoSnapshots);
+		}
+
+		let best_snapshot = candidates.iter().map(|c| c.block_number).max().unwrap_or(0);
+		let best_block = cmp::max(best_snapshot, highest_block.unwrap_or(0));
+		let recent = candidates.iter()
+			.filter(|c| best_block - c.block_number <= self.max_age)
+			.collect::<Vec<_>>();
+
+		if recent.is_empty() {
+			return SnapshotSelection::Rejected(SnapshotRejection::Stale);
+		}
+
+		let mut manifests: HashMap<H256, (BlockNumber, usize)> = HashMap::new();
+		for candidate in &recent {
+			let entry = manifests.entry(candidate.hash).or_insert((candidate.block_number, 0));
+			entry.1 += 1;
+		}
+
+		let (hash, block_number, peers) = manifests.into_iter()
+			.map(|(hash, (block_number, peers))| (hash, block_number, peers))
+			.max_by_key(|&(hash, block_number, peers)| (peers, block_number, hash))
+			.expect("recent is not empty; qed");
+
+		if peers < self.min_peers {
+			return SnapshotSelection::Rejected(SnapshotRejection::NotEnoughPeers { hash, peers });
+		}
+
+		if peers * 100 < self.min_agreement_percent * recent.len() {
+			return SnapshotSelection::Rejected(SnapshotRejection::NotEnoughAgreement {
+				hash,
+				peers,
+				snapshot_peers: recent.len(),
+			});
+		}
+
+		SnapshotSelection::Chosen { hash, block_number, peers }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethereum_types::H256;
+	use super::*;
+
+	fn candidates(hash: H256, block_number: u64, peers: ::std::ops::Range<usize>) -> Vec<SnapshotCandidate> {
+		peers.map(|peer| SnapshotCandidate { peer, hash, block_number }).collect()
+	}
+
+	#[test]
+	fn should_pick_majority_among_recent_snapshots() {
+		let policy = WarpSnapshotPolicy::default();
+		let mut advertised = candidates(H256::from(1), 100_000, 0..5);
+		advertised.extend(candidates(H256::from(2), 500_000, 5..8));
+		advertised.extend(candidates(H256::from(3), 500_000, 8..9));
+
+		assert_eq!(policy.select(&advertised, Some(500_100)), SnapshotSelection::Chosen {
+			hash: H256::from(2),
+			block_number: 500_000,
+			peers: 3,
+		});
+	}
+
+	#[test]
+	fn should_reject_unconfirmed_snapshots() {
+		let policy = WarpSnapshotPolicy::default();
+		assert_eq!(policy.select(&[], None), SnapshotSelection::Rejected(SnapshotRejection::NoSnapshots));
+
+		let advertised = candidates(H256::from(1), 100_000, 0..5);
+		assert_eq!(policy.select(&advertised, Some(500_000)), SnapshotSelection::Rejected(SnapshotRejection::Stale));
+
+		let mut advertised = candidates(H256::from(1), 500_000, 0..2);
+		assert_eq!(policy.select(&advertised, None), SnapshotSelection::Rejected(SnapshotRejection::NotEnoughPeers {
+			hash: H256::from(1),
+			peers: 2,
+		}));
+
+		advertised.extend(candidates(H256::from(1), 500_000, 2..3));
+		advertised.extend(candidates(H256::from(2), 500_000, 3..5));
+		advertised.extend(candidates(H256::from(3), 500_000, 5..7));
+		assert_eq!(policy.select(&advertised, None), SnapshotSelection::Rejected(SnapshotRejection::NotEnoughAgreement {
+			hash: H256::from(1),
+			peers: 3,
+			snapshot_peers: 7,
+		}));
+	}
+}
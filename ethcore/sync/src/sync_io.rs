@@ -15,7 +15,8 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
-use network::{NetworkContext, PeerId, PacketId, Error, SessionInfo, ProtocolId};
+use std::time::Duration;
+use network::{NetworkContext, PeerId, PacketId, Error, SessionInfo, ProtocolId, NodeId};
 use bytes::Bytes;
 use ethcore::client::BlockChainClient;
 use ethcore::header::BlockNumber;
@@ -30,6 +31,10 @@ pub trait SyncIo {
 	fn disable_peer(&mut self, peer_id: PeerId);
 	/// Disconnect peer
 	fn disconnect_peer(&mut self, peer_id: PeerId);
+	/// Disconnect peer and refuse its node for given time
+	fn ban_peer(&mut self, peer_id: PeerId, duration: Duration);
+	/// Returns if the peer is reserved
+	fn is_reserved_peer(&self, peer_id: PeerId) -> bool;
 	/// Respond to current request with a packet. Can be called from an IO handler for incoming packet.
 	fn respond(&mut self, packet_id: PacketId, data: Vec<u8>) -> Result<(), Error>;
 	/// Send a packet to a peer.
@@ -46,6 +51,10 @@ pub trait SyncIo {
 	}
 	/// Returns information on p2p session
 	fn peer_session_info(&self, peer_id: PeerId) -> Option<SessionInfo>;
+	/// Returns node id of the peer
+	fn peer_node_id(&self, peer_id: PeerId) -> Option<NodeId> {
+		self.peer_session_info(peer_id).and_then(|info| info.id)
+	}
 	/// Maximum mutually supported ETH protocol version
 	fn eth_protocol_version(&self, peer_id: PeerId) -> u8;
 	/// Maximum mutually supported version of a gien protocol.
@@ -92,6 +101,14 @@ impl<'s> SyncIo for NetSyncIo<'s> {
 		self.network.disconnect_peer(peer_id);
 	}
 
+	fn ban_peer(&mut self, peer_id: PeerId, duration: Duration) {
+		self.network.ban_peer(peer_id, duration);
+	}
+
+	fn is_reserved_peer(&self, peer_id: PeerId) -> bool {
+		self.network.is_reserved_peer(peer_id)
+	}
+
 	fn respond(&mut self, packet_id: PacketId, data: Vec<u8>) -> Result<(), Error>{
 		self.network.respond(packet_id, data)
 	}
@@ -15,7 +15,10 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::Arc;
+use std::collections::HashSet;
 use ethcore::client::{TestBlockChainClient, BlockChainClient, BlockId, EachBlockWith, ChainInfo, BlockInfo};
+use rlp::Rlp;
+use transaction::UnverifiedTransaction;
 use chain::{SyncState};
 use super::helpers::*;
 use {SyncConfig, WarpSync};
@@ -191,8 +194,8 @@ fn propagate_hashes() {
 	net.trigger_chain_new_blocks(0); //first event just sets the marker
 	net.trigger_chain_new_blocks(0);
 
-	// 5 peers with NewHahses, 4 with blocks
-	assert_eq!(9, net.peer(0).queue.read().len());
+	// peer 1 is close to our head and gets the block, the others still at genesis get NewHashes
+	assert_eq!(5, net.peer(0).queue.read().len());
 	let mut hashes = 0;
 	let mut blocks = 0;
 	for i in 0..net.peer(0).queue.read().len() {
@@ -203,8 +206,8 @@ fn propagate_hashes() {
 			blocks += 1;
 		}
 	}
-	assert_eq!(blocks, 4);
-	assert_eq!(hashes, 5);
+	assert_eq!(blocks, 1);
+	assert_eq!(hashes, 4);
 }
 
 #[test]
@@ -266,3 +269,34 @@ fn disconnect_on_unrelated_chain() {
 	net.sync();
 	assert_eq!(net.disconnect_events, vec![(0, 0)]);
 }
+
+#[test]
+fn transactions_are_never_sent_to_the_same_peer_twice() {
+	::env_logger::try_init().ok();
+	let mut net = TestNet::new(3);
+	net.sync();
+
+	let mut received = HashSet::new();
+	for _ in 0..5 {
+		net.peer(0).chain.insert_transaction_to_queue();
+		// every propagation round is followed by another one, which must not resend anything
+		for _ in 0..2 {
+			net.sync_step_peer(0);
+			while let Some(packet) = net.peer(0).pending_message() {
+				if packet.packet_id == 0x02 { // TRANSACTIONS_PACKET
+					for tx in Rlp::new(&*packet.data).iter() {
+						let tx: UnverifiedTransaction = tx.as_val().unwrap();
+						assert!(received.insert((packet.recipient, tx.hash())), "Peer received the same transaction twice");
+					}
+				}
+				net.peer(packet.recipient).receive_message(0, packet);
+			}
+		}
+	}
+	assert_eq!(received.len(), 10);
+
+	let status = net.peer(0).sync.read().status();
+	assert_eq!(status.transactions_propagated, 10);
+	assert!(status.transactions_duplicates_avoided > 0);
+	assert!(status.transactions_bytes_propagated > 0);
+}
@@ -20,7 +20,7 @@ use std::time::Duration;
 use ethereum_types::H256;
 use parking_lot::{RwLock, Mutex};
 use bytes::Bytes;
-use network::{self, PeerId, ProtocolId, PacketId, SessionInfo};
+use network::{self, PeerId, ProtocolId, PacketId, SessionInfo, NodeId};
 use tests::snapshot::*;
 use ethcore::client::{TestBlockChainClient, BlockChainClient, Client as EthcoreClient,
 	ClientConfig, ChainNotify, ChainRoute, ChainMessageType, ClientIoMessage};
@@ -55,6 +55,8 @@ pub struct TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 	pub queue: &'p RwLock<VecDeque<TestPacket>>,
 	pub sender: Option<PeerId>,
 	pub to_disconnect: HashSet<PeerId>,
+	pub banned: HashSet<PeerId>,
+	pub reserved_peers: HashSet<PeerId>,
 	pub packets: Vec<TestPacket>,
 	pub peers_info: HashMap<PeerId, String>,
 	overlay: RwLock<HashMap<BlockNumber, Bytes>>,
@@ -68,6 +70,8 @@ impl<'p, C> TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 			queue: queue,
 			sender: sender,
 			to_disconnect: HashSet::new(),
+			banned: HashSet::new(),
+			reserved_peers: HashSet::new(),
 			overlay: RwLock::new(HashMap::new()),
 			packets: Vec::new(),
 			peers_info: HashMap::new(),
@@ -90,6 +94,15 @@ impl<'p, C> SyncIo for TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 		self.to_disconnect.insert(peer_id);
 	}
 
+	fn ban_peer(&mut self, peer_id: PeerId, _duration: Duration) {
+		self.banned.insert(peer_id);
+		self.disconnect_peer(peer_id);
+	}
+
+	fn is_reserved_peer(&self, peer_id: PeerId) -> bool {
+		self.reserved_peers.contains(&peer_id)
+	}
+
 	fn is_expired(&self) -> bool {
 		false
 	}
@@ -134,6 +147,10 @@ impl<'p, C> SyncIo for TestIo<'p, C> where C: FlushingBlockChainClient, C: 'p {
 		None
 	}
 
+	fn peer_node_id(&self, peer_id: PeerId) -> Option<NodeId> {
+		Some(NodeId::from(peer_id as u64))
+	}
+
 	fn eth_protocol_version(&self, _peer: PeerId) -> u8 {
 		ETH_PROTOCOL_VERSION_63.0
 	}
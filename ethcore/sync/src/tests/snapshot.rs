@@ -15,16 +15,17 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use hash::keccak;
 use ethereum_types::H256;
 use parking_lot::Mutex;
 use bytes::Bytes;
-use ethcore::snapshot::{SnapshotService, ManifestData, RestorationStatus};
+use ethcore::snapshot::{SnapshotService, ManifestData, RestorationStatus, CreationStatus, VerificationReport};
 use ethcore::header::BlockNumber;
 use ethcore::client::EachBlockWith;
 use super::helpers::*;
-use {SyncConfig, WarpSync};
+use {SyncConfig, WarpSync, SnapshotSelection};
 
 pub struct TestSnapshotService {
 	manifest: Option<ManifestData>,
@@ -58,6 +59,7 @@ impl TestSnapshotService {
 			state_root: H256::new(),
 			block_number: block_number,
 			block_hash: block_hash,
+			compression: Default::default(),
 		};
 		let mut chunks: HashMap<H256, Bytes> = state_chunks.into_iter().map(|data| (keccak(&data), data)).collect();
 		chunks.extend(block_chunks.into_iter().map(|data| (keccak(&data), data)));
@@ -122,6 +124,8 @@ impl SnapshotService for TestSnapshotService {
 		self.block_restoration_chunks.lock().clear();
 	}
 
+	fn restore_from_path(&self, _path: PathBuf) { }
+
 	fn restore_state_chunk(&self, hash: H256, chunk: Bytes) {
 		if self.restoration_manifest.lock().as_ref().map_or(false, |m| m.state_hashes.iter().any(|h| h == &hash)) {
 			self.state_restoration_chunks.lock().insert(hash, chunk);
@@ -134,6 +138,18 @@ impl SnapshotService for TestSnapshotService {
 		}
 	}
 
+	fn creation_status(&self) -> Option<CreationStatus> {
+		None
+	}
+
+	fn pause_creation(&self) { }
+
+	fn resume_creation(&self) { }
+
+	fn verify_manifest(&self, _path: &Path) -> VerificationReport {
+		VerificationReport::default()
+	}
+
 	fn shutdown(&self) {
 		self.abort_restore();
 	}
@@ -154,3 +170,35 @@ fn snapshot_sync() {
 	assert_eq!(net.peer(4).snapshot_service.state_restoration_chunks.lock().len(), net.peer(0).snapshot_service.manifest.as_ref().unwrap().state_hashes.len());
 	assert_eq!(net.peer(4).snapshot_service.block_restoration_chunks.lock().len(), net.peer(0).snapshot_service.manifest.as_ref().unwrap().block_hashes.len());
 }
+
+#[test]
+fn snapshot_sync_picks_majority_of_recent_manifests() {
+	::env_logger::try_init().ok();
+	let mut config = SyncConfig::default();
+	config.warp_sync = WarpSync::Enabled;
+	let mut net = TestNet::new_with_config(9, config);
+	let majority = Arc::new(TestSnapshotService::new_with_snapshot(16, H256::new(), 500000));
+	let minority = Arc::new(TestSnapshotService::new_with_snapshot(16, H256::new(), 500000));
+	let stale = Arc::new(TestSnapshotService::new_with_snapshot(16, H256::new(), 100000));
+	for i in 0..8 {
+		net.peer_mut(i).snapshot_service = match i {
+			0...2 => majority.clone(),
+			3 => minority.clone(),
+			_ => stale.clone(),
+		};
+		net.peer(i).chain.add_blocks(1, EachBlockWith::Nothing);
+	}
+	net.sync_steps(50);
+
+	let manifest = majority.manifest.as_ref().unwrap();
+	let restored = net.peer(8).snapshot_service.restoration_manifest.lock().as_ref().map(|m| m.block_hashes.clone());
+	assert_eq!(restored, Some(manifest.block_hashes.clone()));
+	match net.peer(8).sync.read().status().snapshot_selection {
+		Some(SnapshotSelection::Chosen { hash, block_number, peers }) => {
+			assert_eq!(hash, keccak(manifest.clone().into_rlp()));
+			assert_eq!(block_number, 500000);
+			assert_eq!(peers, 3);
+		},
+		other => panic!("Unexpected snapshot selection: {:?}", other),
+	}
+}
@@ -0,0 +1,92 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Memory use of streaming block generation. Lives in its own test binary, because counting
+//! allocations requires replacing the global allocator of the whole binary.
+
+extern crate ethcore;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io;
+use ethcore::test_helpers::generator::{BlockBuilder, generate_to_writer};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+	// bytes allocated and not yet freed by this thread, and the highest value since reset.
+	static LIVE_BYTES: Cell<isize> = Cell::new(0);
+	static PEAK_BYTES: Cell<isize> = Cell::new(0);
+}
+
+/// System allocator counting the live bytes of every thread, see `peak_allocation`.
+struct CountingAllocator;
+
+impl CountingAllocator {
+	fn record(delta: isize) {
+		// counters are gone while the thread is torn down.
+		let _ = LIVE_BYTES.try_with(|live| {
+			live.set(live.get() + delta);
+			let _ = PEAK_BYTES.try_with(|peak| if live.get() > peak.get() { peak.set(live.get()) });
+		});
+	}
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		let ptr = System.alloc(layout);
+		if !ptr.is_null() {
+			CountingAllocator::record(layout.size() as isize);
+		}
+		ptr
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout);
+		CountingAllocator::record(-(layout.size() as isize));
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		let new_ptr = System.realloc(ptr, layout, new_size);
+		if !new_ptr.is_null() {
+			CountingAllocator::record(new_size as isize - layout.size() as isize);
+		}
+		new_ptr
+	}
+}
+
+/// Run `f`, returning its result and the peak of the bytes it allocated on this thread and
+/// hadn't freed yet.
+fn peak_allocation<F, R>(f: F) -> (R, usize) where F: FnOnce() -> R {
+	let baseline = LIVE_BYTES.with(|live| live.get());
+	PEAK_BYTES.with(|peak| peak.set(baseline));
+	let result = f();
+	let peak = PEAK_BYTES.with(|peak| peak.get());
+	(result, (peak - baseline) as usize)
+}
+
+#[test]
+#[cfg_attr(not(feature = "test-heavy"), ignore)]
+fn streams_generation_in_bounded_memory() {
+	let genesis = BlockBuilder::genesis().with_target_block_time(15);
+	let generate = |count| peak_allocation(|| generate_to_writer(&genesis, count, &mut io::sink()).unwrap());
+
+	let (small_written, small_peak) = generate(10_000);
+	let (large_written, large_peak) = generate(100_000);
+	assert!(large_written > small_written * 9);
+	assert!(large_peak <= small_peak * 2, "peak allocation grew from {} to {} bytes", small_peak, large_peak);
+}
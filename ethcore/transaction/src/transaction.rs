@@ -20,7 +20,7 @@ use std::ops::Deref;
 use ethereum_types::{H256, H160, Address, U256};
 use error;
 use ethjson;
-use ethkey::{self, Signature, Secret, Public, recover, public_to_address};
+use ethkey::{self, Signature, Secret, Public, public_to_address};
 use evm::Schedule;
 use hash::keccak;
 use heapsize::HeapSizeOf;
@@ -77,25 +77,6 @@ pub enum Condition {
 	Timestamp(u64),
 }
 
-/// Replay protection logic for v part of transaction's signature
-pub mod signature {
-	/// Adds chain id into v
-	pub fn add_chain_replay_protection(v: u64, chain_id: Option<u64>) -> u64 {
-		v + if let Some(n) = chain_id { 35 + n * 2 } else { 27 }
-	}
-
-	/// Returns refined v
-	/// 0 if `v` would have been 27 under "Electrum" notation, 1 if 28 or 4 if invalid.
-	pub fn check_replay_protection(v: u64) -> u8 {
-		match v {
-			v if v == 27 => 0,
-			v if v == 28 => 1,
-			v if v > 36 => ((v - 1) % 2) as u8,
-			 _ => 4
-		}
-	}
-}
-
 /// A set of information describing an externally-originating message call
 /// or contract creation operation.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
@@ -193,7 +174,7 @@ impl Transaction {
 
 	/// Signs the transaction as coming from `sender`.
 	pub fn sign(self, secret: &Secret, chain_id: Option<u64>) -> SignedTransaction {
-		let sig = ::ethkey::sign(secret, &self.hash(chain_id))
+		let sig = ethkey::sign_with_chain_id(secret, &self.hash(chain_id), chain_id)
 			.expect("data is valid and context has signing capabilities; qed");
 		SignedTransaction::new(self.with_signature(sig, chain_id))
 			.expect("secret is valid so it's recoverable")
@@ -205,7 +186,8 @@ impl Transaction {
 			unsigned: self,
 			r: sig.r().into(),
 			s: sig.s().into(),
-			v: signature::add_chain_replay_protection(sig.v() as u64, chain_id),
+			v: ethkey::to_eip155_v(sig.v(), chain_id)
+				.expect("v is the recovery id of a signature and chain ids are far below u64::max_value() / 2; qed"),
 			hash: 0.into(),
 		}.compute_hash()
 	}
@@ -252,6 +234,19 @@ impl Transaction {
 		}
 	}
 
+	/// Call to `to` with `data` paying no gas price, with just enough gas for its intrinsic cost.
+	/// Useful to check the minimal gas price policy of the miner.
+	pub fn zero_gas_price_call(to: Address, data: Bytes) -> Transaction {
+		Transaction {
+			nonce: U256::zero(),
+			gas_price: U256::zero(),
+			gas: Self::gas_required_for(false, &data, &Schedule::new_homestead()).into(),
+			action: Action::Call(to),
+			value: U256::zero(),
+			data: data,
+		}
+	}
+
 	/// Get the transaction cost in gas for the given params.
 	pub fn gas_required_for(is_create: bool, data: &[u8], schedule: &Schedule) -> u64 {
 		data.iter().fold(
@@ -355,7 +350,8 @@ impl UnverifiedTransaction {
 		&self.unsigned
 	}
 
-	pub fn standard_v(&self) -> u8 { signature::check_replay_protection(self.v) }
+	/// 0 if `v` would have been 27 under "Electrum" notation, 1 if 28 or 4 if invalid.
+	pub fn standard_v(&self) -> u8 { ethkey::recovery_id_from_v(self.v).unwrap_or(4) }
 
 	/// The `v` value that appears in the RLP.
 	pub fn original_v(&self) -> u64 { self.v }
@@ -364,8 +360,7 @@ impl UnverifiedTransaction {
 	pub fn chain_id(&self) -> Option<u64> {
 		match self.v {
 			v if self.is_unsigned() => Some(v),
-			v if v > 36 => Some((v - 35) / 2),
-			_ => None,
+			v => ethkey::chain_id_from_v(v).unwrap_or(None),
 		}
 	}
 
@@ -390,7 +385,15 @@ impl UnverifiedTransaction {
 
 	/// Recovers the public key of the sender.
 	pub fn recover_public(&self) -> Result<Public, ethkey::Error> {
-		Ok(recover(&self.signature(), &self.unsigned.hash(self.chain_id()))?)
+		let (signature, message, chain_id) = self.recovery_input()?;
+		ethkey::recover_with_chain_id(&signature, &message, chain_id)
+	}
+
+	/// Signature with the recovery id of `v`, the message it signs and the chain id it's for, to recover the sender from.
+	fn recovery_input(&self) -> Result<(Signature, H256, Option<u64>), ethkey::Error> {
+		let chain_id = ethkey::chain_id_from_v(self.v)?;
+		let signature = ethkey::signature_from_v(&Signature::from_rsv(&self.r.into(), &self.s.into(), 0), self.v, chain_id)?;
+		Ok((signature, self.unsigned.hash(chain_id), chain_id))
 	}
 
 	/// Verify basic signature params. Does not attempt sender recovery.
@@ -466,6 +469,39 @@ impl SignedTransaction {
 		}
 	}
 
+	/// Verify transactions and recover their senders all at once, see `ethkey::recover_batch`.
+	///
+	/// Results are in the order of `transactions` and are the same as those of `new` on each of them.
+	pub fn new_batch(transactions: Vec<UnverifiedTransaction>) -> Vec<Result<Self, ethkey::Error>> {
+		let mut inputs = Vec::with_capacity(transactions.len());
+		// whether each transaction is signed and has an entry in `inputs`.
+		let signed = transactions.iter().map(|transaction| {
+			if transaction.is_unsigned() {
+				return Ok(false);
+			}
+			let (signature, message, _) = transaction.recovery_input()?;
+			inputs.push((signature, message));
+			Ok(true)
+		}).collect::<Vec<Result<bool, ethkey::Error>>>();
+
+		let mut publics = ethkey::recover_batch(&inputs).into_iter();
+		transactions.into_iter().zip(signed).map(|(transaction, signed)| match signed? {
+			false => Ok(SignedTransaction {
+				transaction: transaction,
+				sender: UNSIGNED_SENDER,
+				public: None,
+			}),
+			true => {
+				let public = publics.next().expect("an input is recovered for each signed transaction; qed")?;
+				Ok(SignedTransaction {
+					transaction: transaction,
+					sender: public_to_address(&public),
+					public: Some(public),
+				})
+			},
+		}).collect()
+	}
+
 	/// Returns transaction sender.
 	pub fn sender(&self) -> Address {
 		self.sender
@@ -564,6 +600,7 @@ impl From<SignedTransaction> for PendingTransaction {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use error;
 	use ethereum_types::U256;
 	use hash::keccak;
 
@@ -634,6 +671,82 @@ mod tests {
 		assert_eq!(t.chain_id(), Some(69));
 	}
 
+	#[test]
+	fn should_round_trip_signing_across_chain_ids() {
+		use ethkey::{Random, Generator};
+		let key = Random.generate().unwrap();
+		for &chain_id in &[None, Some(0), Some(1), Some(61), Some(128), Some(1 << 40)] {
+			let t = Transaction {
+				action: Action::Create,
+				nonce: U256::from(42),
+				gas_price: U256::from(3000),
+				gas: U256::from(50_000),
+				value: U256::from(1),
+				data: b"Hello!".to_vec()
+			}.sign(&key.secret(), chain_id);
+			assert_eq!(t.chain_id(), chain_id);
+			assert!(t.standard_v() < 2);
+
+			let decoded: UnverifiedTransaction = rlp::decode(&rlp::encode(&t)).unwrap();
+			assert_eq!(decoded.chain_id(), chain_id);
+			assert_eq!(SignedTransaction::new(decoded).unwrap().sender(), t.sender());
+		}
+	}
+
+	#[test]
+	fn should_reject_chain_id_zero_transaction_on_other_chains() {
+		use ethkey::{Random, Generator};
+		let key = Random.generate().unwrap();
+		let t = Transaction {
+			action: Action::Create,
+			nonce: U256::from(42),
+			gas_price: U256::from(3000),
+			gas: U256::from(50_000),
+			value: U256::from(1),
+			data: b"Hello!".to_vec()
+		}.sign(&key.secret(), Some(0));
+		assert!(t.original_v() == 35 || t.original_v() == 36);
+		assert_eq!(t.chain_id(), Some(0));
+
+		assert!(t.verify_basic(false, Some(0), false).is_ok());
+		assert_eq!(t.verify_basic(false, Some(1), false), Err(error::Error::InvalidChainId));
+		assert_eq!(t.verify_basic(false, None, false), Err(error::Error::InvalidChainId));
+	}
+
+	#[test]
+	fn batch_recovery_matches_single_recovery() {
+		use ethkey::{Random, Generator};
+
+		let transaction = |nonce: u64| Transaction {
+			action: Action::Create,
+			nonce: U256::from(nonce),
+			gas_price: U256::from(3000),
+			gas: U256::from(50_000),
+			value: U256::from(1),
+			data: b"Hello!".to_vec()
+		};
+		let transactions = (0..30u64).map(|nonce| match nonce {
+			5 => transaction(nonce).null_sign(1).into(),
+			11 => transaction(nonce).invalid_sign(),
+			19 => transaction(nonce).with_signature(Signature::from_rsv(&1.into(), &1.into(), 0), Some(1)),
+			_ => transaction(nonce).sign(Random.generate().unwrap().secret(), if nonce % 2 == 0 { Some(1) } else { None }).into(),
+		}).collect::<Vec<UnverifiedTransaction>>();
+
+		let batch = SignedTransaction::new_batch(transactions.clone());
+		assert_eq!(batch.len(), transactions.len());
+		for (i, (batched, transaction)) in batch.into_iter().zip(transactions).enumerate() {
+			match (batched, SignedTransaction::new(transaction)) {
+				(Ok(batched), Ok(single)) => {
+					assert_eq!(batched, single);
+					assert_eq!(batched.sender(), single.sender());
+				},
+				(Err(_), Err(_)) => assert!(i == 11 || i == 19),
+				(batched, single) => panic!("Transaction {} recovered as {:?} in batch and {:?} alone", i, batched, single),
+			}
+		}
+		assert!(SignedTransaction::new_batch(Vec::new()).is_empty());
+	}
+
 	#[test]
 	fn should_agree_with_vitalik() {
 		use rustc_hex::FromHex;
@@ -68,6 +68,15 @@ pub struct AccountDiff {
 	pub storage: BTreeMap<H256, Diff<H256>>,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+/// Storage slots read by the code of an account.
+pub struct StorageReads {
+	/// Value observed on the first read of each slot.
+	pub values: BTreeMap<H256, H256>,
+	/// Whether further reads were dropped on reaching the limit of recorded reads.
+	pub truncated: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 /// Change in existance type.
 // TODO: include other types of change.
@@ -0,0 +1,58 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Progress of the background download of ancient blocks, persisted across restarts.
+
+use ethereum_types::H256;
+use BlockNumber;
+
+/// Range of block numbers, inclusive on both ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct BlockRange {
+	/// First block of the range.
+	pub start: BlockNumber,
+	/// Last block of the range.
+	pub end: BlockNumber,
+}
+
+/// Progress of the ancient blocks download.
+#[derive(Debug, Clone, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct AncientDownloadProgress {
+	/// Hash of the last block known to be imported into the database.
+	pub cursor_hash: H256,
+	/// Number of the last block known to be imported into the database.
+	pub cursor_number: BlockNumber,
+	/// Batches of verified blocks past the cursor which might not have been flushed to the database yet.
+	pub pending: Vec<BlockRange>,
+}
+
+#[cfg(test)]
+mod tests {
+	use rlp;
+	use super::{AncientDownloadProgress, BlockRange};
+
+	#[test]
+	fn should_encode_and_decode_progress() {
+		let progress = AncientDownloadProgress {
+			cursor_hash: 5.into(),
+			cursor_number: 120,
+			pending: vec![BlockRange { start: 101, end: 120 }],
+		};
+
+		let encoded = rlp::encode(&progress);
+		assert_eq!(rlp::decode::<AncientDownloadProgress>(&encoded).unwrap(), progress);
+	}
+}
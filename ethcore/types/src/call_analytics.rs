@@ -25,4 +25,6 @@ pub struct CallAnalytics {
 	pub vm_tracing: bool,
 	/// Make a diff.
 	pub state_diffing: bool,
+	/// Include the storage read by every account in the diff.
+	pub state_diffing_with_reads: bool,
 }
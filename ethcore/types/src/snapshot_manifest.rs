@@ -17,9 +17,52 @@
 //! Snapshot manifest type definition
 
 use ethereum_types::H256;
-use rlp::{Rlp, RlpStream, DecoderError};
+use rlp::{Rlp, RlpStream, DecoderError, Encodable, Decodable};
 use bytes::Bytes;
 
+/// First manifest version recording the compression of its chunks.
+/// Chunks of earlier snapshots are always compressed with snappy.
+pub const COMPRESSION_VERSION: u64 = 4;
+
+/// Compression of snapshot chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+	/// Chunks are stored as is, for fast local restores.
+	None,
+	/// Snappy, the default and the only compression of older snapshots.
+	Snappy,
+	/// Zstandard, trading speed for a higher ratio.
+	Zstd,
+}
+
+impl Default for Compression {
+	fn default() -> Self {
+		Compression::Snappy
+	}
+}
+
+impl Encodable for Compression {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		let code: u8 = match *self {
+			Compression::None => 0,
+			Compression::Snappy => 1,
+			Compression::Zstd => 2,
+		};
+		s.append(&code);
+	}
+}
+
+impl Decodable for Compression {
+	fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		match rlp.as_val::<u8>()? {
+			0 => Ok(Compression::None),
+			1 => Ok(Compression::Snappy),
+			2 => Ok(Compression::Zstd),
+			_ => Err(DecoderError::Custom("Unknown snapshot compression")),
+		}
+	}
+}
+
 /// Manifest data.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ManifestData {
@@ -35,18 +78,24 @@ pub struct ManifestData {
 	pub block_number: u64,
 	/// Block hash this snapshot was taken at.
 	pub block_hash: H256,
+	/// Compression of the chunks, recorded since `COMPRESSION_VERSION`.
+	pub compression: Compression,
 }
 
 impl ManifestData {
 	/// Encode the manifest data to rlp.
 	pub fn into_rlp(self) -> Bytes {
-		let mut stream = RlpStream::new_list(6);
+		let with_compression = self.version >= COMPRESSION_VERSION;
+		let mut stream = RlpStream::new_list(if with_compression { 7 } else { 6 });
 		stream.append(&self.version);
 		stream.append_list(&self.state_hashes);
 		stream.append_list(&self.block_hashes);
 		stream.append(&self.state_root);
 		stream.append(&self.block_number);
 		stream.append(&self.block_hash);
+		if with_compression {
+			stream.append(&self.compression);
+		}
 
 		stream.out()
 	}
@@ -65,6 +114,10 @@ impl ManifestData {
 		let state_root: H256 = decoder.val_at(start + 2)?;
 		let block_number: u64 = decoder.val_at(start + 3)?;
 		let block_hash: H256 = decoder.val_at(start + 4)?;
+		let compression = match version >= COMPRESSION_VERSION {
+			true => decoder.val_at(start + 5)?,
+			false => Compression::Snappy,
+		};
 
 		Ok(ManifestData {
 			version: version,
@@ -73,6 +126,47 @@ impl ManifestData {
 			state_root: state_root,
 			block_number: block_number,
 			block_hash: block_hash,
+			compression: compression,
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use rustc_hex::FromHex;
+	use ethereum_types::H256;
+	use super::{Compression, ManifestData, COMPRESSION_VERSION};
+
+	fn manifest(version: u64, compression: Compression) -> ManifestData {
+		ManifestData {
+			version,
+			state_hashes: vec![H256::from([1u8; 32])],
+			block_hashes: vec![H256::from([2u8; 32])],
+			state_root: H256::from([3u8; 32]),
+			block_number: 1000,
+			block_hash: H256::from([4u8; 32]),
+			compression,
+		}
+	}
+
+	#[test]
+	fn reads_legacy_manifest_as_snappy() {
+		// version 2 manifest, written before compression was recorded.
+		let legacy: Vec<u8> = "f88a02e1a00101010101010101010101010101010101010101010101010101010101010101\
+			e1a00202020202020202020202020202020202020202020202020202020202020202\
+			a00303030303030303030303030303030303030303030303030303030303030303\
+			8203e8a00404040404040404040404040404040404040404040404040404040404040404".from_hex().unwrap();
+
+		let decoded = ManifestData::from_rlp(&legacy).unwrap();
+		assert_eq!(decoded, manifest(2, Compression::Snappy));
+		assert_eq!(decoded.into_rlp(), legacy);
+	}
+
+	#[test]
+	fn round_trips_compression() {
+		for &compression in &[Compression::None, Compression::Snappy, Compression::Zstd] {
+			let original = manifest(COMPRESSION_VERSION, compression);
+			assert_eq!(ManifestData::from_rlp(&original.clone().into_rlp()).unwrap(), original);
+		}
+	}
+}
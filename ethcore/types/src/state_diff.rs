@@ -27,7 +27,10 @@ use account_diff::*;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StateDiff {
 	/// Raw diff key-value
-	pub raw: BTreeMap<Address, AccountDiff>
+	pub raw: BTreeMap<Address, AccountDiff>,
+	/// Storage read by the code of every account, if reads were recorded.
+	/// Accounts may be read without being altered.
+	pub reads: BTreeMap<Address, StorageReads>,
 }
 
 impl StateDiff {
@@ -20,6 +20,34 @@ use std::ops::Range;
 use ethereum_types::Address;
 use ids::BlockId;
 
+/// Kind of a traced action, calls being told apart by their call type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceKind {
+	/// `CALL` or a message call transaction.
+	Call,
+	/// `DELEGATECALL`.
+	DelegateCall,
+	/// `STATICCALL`.
+	StaticCall,
+	/// `CALLCODE`.
+	CallCode,
+	/// `CREATE` or a contract creation transaction.
+	Create,
+	/// `SUICIDE`.
+	Suicide,
+	/// Block reward.
+	Reward,
+}
+
+/// Outcome of a traced action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceStatus {
+	/// The action succeeded, suicides and rewards always do.
+	Success,
+	/// The call or creation failed.
+	Failed,
+}
+
 /// Easy to use trace filter.
 pub struct Filter {
 	/// Range of filtering.
@@ -28,6 +56,10 @@ pub struct Filter {
 	pub from_address: Vec<Address>,
 	/// To address.
 	pub to_address: Vec<Address>,
+	/// Kind of the traces, any if not set.
+	pub kind: Option<TraceKind>,
+	/// Outcome of the traces, any if not set.
+	pub status: Option<TraceStatus>,
 	/// Output offset
 	pub after: Option<usize>,
 	/// Output amount
@@ -69,6 +69,12 @@ pub trait Ext {
 	/// Returns a value for given key.
 	fn storage_at(&self, key: &H256) -> Result<H256>;
 
+	/// Returns a value for given key, read by the executing code, e.g. with `SLOAD`.
+	/// Unlike `storage_at` this may be recorded for tracing.
+	fn storage_read(&self, key: &H256) -> Result<H256> {
+		self.storage_at(key)
+	}
+
 	/// Stores a value for given key.
 	fn set_storage(&mut self, key: H256, value: H256) -> Result<()>;
 
@@ -258,7 +258,7 @@ impl<'a> Runtime<'a> {
 		let key = self.h256_at(args.nth_checked(0)?)?;
 		let val_ptr: u32 = args.nth_checked(1)?;
 
-		let val = self.ext.storage_at(&key).map_err(|_| Error::StorageReadError)?;
+		let val = self.ext.storage_read(&key).map_err(|_| Error::StorageReadError)?;
 
 		self.adjusted_charge(|schedule| schedule.sload_gas as u64)?;
 
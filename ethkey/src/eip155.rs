@@ -0,0 +1,166 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chain id aware signatures, as defined by EIP-155.
+//!
+//! The `v` value of a signature is `27 + recovery id` without a chain id and
+//! `35 + 2 * chain id + recovery id` with one. It doesn't fit a byte for chain ids
+//! above 109, so it's kept apart from the `Signature`, whose `v` is the recovery id.
+//!
+//! Chain id 0 is encoded as `v` 35 or 36 like any other chain id. Such signatures used to
+//! be rejected as invalid even though `v` was produced for them when signing, so signing
+//! for chain id 0 gave transactions whose sender couldn't be recovered. They are valid on
+//! chains with chain id 0 only, all other chains reject them for their chain id.
+
+use ethereum_types::H256;
+use {sign, recover, Error, Message, Public, Secret, Signature};
+
+quick_error! {
+	/// Inconsistent EIP-155 `v` value.
+	#[derive(Debug, PartialEq, Clone)]
+	pub enum Eip155Error {
+		InvalidRecoveryId(id: u8) {
+			display("recovery id {} is neither 0 nor 1", id)
+		}
+		InvalidV(v: u64) {
+			display("v {} is neither 27, 28 nor at least 35", v)
+		}
+		ChainIdMismatch(v: u64, chain_id: Option<u64>) {
+			display("v {} is not for chain id {:?}", v, chain_id)
+		}
+		ChainIdTooLarge(chain_id: u64) {
+			display("chain id {} does not fit v", chain_id)
+		}
+	}
+}
+
+/// EIP-155 `v` of a signature with `recovery_id`, for `chain_id` if any.
+pub fn to_eip155_v(recovery_id: u8, chain_id: Option<u64>) -> Result<u64, Eip155Error> {
+	if recovery_id > 1 {
+		return Err(Eip155Error::InvalidRecoveryId(recovery_id));
+	}
+
+	match chain_id {
+		Some(chain_id) => chain_id.checked_mul(2)
+			.and_then(|v| v.checked_add(35 + recovery_id as u64))
+			.ok_or(Eip155Error::ChainIdTooLarge(chain_id)),
+		None => Ok(27 + recovery_id as u64),
+	}
+}
+
+/// Recovery id of a signature with EIP-155 `v`.
+pub fn recovery_id_from_v(v: u64) -> Result<u8, Eip155Error> {
+	match v {
+		27 | 28 => Ok((v - 27) as u8),
+		v if v >= 35 => Ok(((v - 35) % 2) as u8),
+		v => Err(Eip155Error::InvalidV(v)),
+	}
+}
+
+/// Chain id of a signature with EIP-155 `v`, or `None` if it's valid on all chains.
+pub fn chain_id_from_v(v: u64) -> Result<Option<u64>, Eip155Error> {
+	match v {
+		27 | 28 => Ok(None),
+		v if v >= 35 => Ok(Some((v - 35) / 2)),
+		v => Err(Eip155Error::InvalidV(v)),
+	}
+}
+
+/// Sign `message` for `chain_id`. The `v` of the signature is the recovery id, its EIP-155 `v`
+/// is `to_eip155_v(signature.v(), chain_id)`. Fails if `chain_id` doesn't fit a `v`.
+pub fn sign_with_chain_id(secret: &Secret, message: &Message, chain_id: Option<u64>) -> Result<Signature, Error> {
+	let signature = sign(secret, message)?;
+	to_eip155_v(signature.v(), chain_id)?;
+	Ok(signature)
+}
+
+/// Recover the public key which signed `message` for `chain_id`. The `v` of `signature` is the
+/// recovery id, see `recovery_id_from_v`. Fails if `chain_id` doesn't fit a `v`.
+pub fn recover_with_chain_id(signature: &Signature, message: &Message, chain_id: Option<u64>) -> Result<Public, Error> {
+	to_eip155_v(signature.v(), chain_id)?;
+	recover(signature, message)
+}
+
+/// Signature with the `r` and `s` of `signature` and the recovery id of EIP-155 `v`, failing
+/// unless `v` is for `chain_id`.
+pub fn signature_from_v(signature: &Signature, v: u64, chain_id: Option<u64>) -> Result<Signature, Eip155Error> {
+	if chain_id_from_v(v)? != chain_id {
+		return Err(Eip155Error::ChainIdMismatch(v, chain_id));
+	}
+
+	Ok(Signature::from_rsv(&H256::from_slice(signature.r()), &H256::from_slice(signature.s()), recovery_id_from_v(v)?))
+}
+
+#[cfg(test)]
+mod tests {
+	use {Generator, Random, Message};
+	use super::{sign_with_chain_id, recover_with_chain_id, signature_from_v, to_eip155_v, recovery_id_from_v, chain_id_from_v, Eip155Error};
+
+	#[test]
+	fn v_round_trips() {
+		for &chain_id in &[None, Some(0), Some(1), Some(61), Some(109), Some(110), Some(127), Some(128), Some(1 << 40)] {
+			for recovery_id in 0..2 {
+				let v = to_eip155_v(recovery_id, chain_id).unwrap();
+				assert_eq!(recovery_id_from_v(v), Ok(recovery_id));
+				assert_eq!(chain_id_from_v(v), Ok(chain_id));
+			}
+		}
+
+		assert_eq!(to_eip155_v(0, Some(0)), Ok(35));
+		assert_eq!(to_eip155_v(1, Some(1)), Ok(38));
+		assert_eq!(to_eip155_v(1, Some(128)), Ok(292));
+	}
+
+	#[test]
+	fn chain_id_zero_is_not_global() {
+		assert_eq!(to_eip155_v(0, Some(0)), Ok(35));
+		assert_eq!(to_eip155_v(1, Some(0)), Ok(36));
+		assert_eq!(chain_id_from_v(35), Ok(Some(0)));
+		assert_eq!(chain_id_from_v(36), Ok(Some(0)));
+		assert_eq!(recovery_id_from_v(36), Ok(1));
+		assert_eq!(chain_id_from_v(27), Ok(None));
+	}
+
+	#[test]
+	fn rejects_inconsistent_values() {
+		assert_eq!(to_eip155_v(2, None), Err(Eip155Error::InvalidRecoveryId(2)));
+		assert_eq!(to_eip155_v(0, Some(u64::max_value() / 2)), Err(Eip155Error::ChainIdTooLarge(u64::max_value() / 2)));
+		for v in (0..27).chain(29..35) {
+			assert_eq!(recovery_id_from_v(v), Err(Eip155Error::InvalidV(v)));
+			assert_eq!(chain_id_from_v(v), Err(Eip155Error::InvalidV(v)));
+		}
+	}
+
+	#[test]
+	fn sign_and_recover_across_chain_ids() {
+		for &chain_id in &[None, Some(0), Some(1), Some(61), Some(127), Some(128), Some(300), Some(1 << 32)] {
+			for i in 0..8u64 {
+				let keypair = Random.generate().unwrap();
+				let message = Message::from(i);
+				let signature = sign_with_chain_id(keypair.secret(), &message, chain_id).unwrap();
+				let v = to_eip155_v(signature.v(), chain_id).unwrap();
+
+				assert_eq!(chain_id_from_v(v), Ok(chain_id));
+				assert_eq!(recovery_id_from_v(v), Ok(signature.v()));
+				assert_eq!(&recover_with_chain_id(&signature, &message, chain_id).unwrap(), keypair.public());
+
+				let decoded = signature_from_v(&signature, v, chain_id).unwrap();
+				assert_eq!(decoded, signature);
+				assert!(signature_from_v(&signature, v, chain_id.map_or(Some(1), |_| None)).is_err());
+			}
+		}
+	}
+}
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{fmt, error};
+use eip155::Eip155Error;
 
 #[derive(Debug)]
 /// Crypto error
@@ -29,6 +30,8 @@ pub enum Error {
 	InvalidSignature,
 	/// Invalid AES message
 	InvalidMessage,
+	/// Inconsistent EIP-155 signature
+	Eip155(Eip155Error),
 	/// IO Error
 	Io(::std::io::Error),
 	/// Custom
@@ -43,6 +46,7 @@ impl fmt::Display for Error {
 			Error::InvalidAddress => "Invalid address".into(),
 			Error::InvalidSignature => "Invalid EC signature".into(),
 			Error::InvalidMessage => "Invalid AES message".into(),
+			Error::Eip155(ref err) => format!("Invalid EIP-155 signature: {}", err),
 			Error::Io(ref err) => format!("I/O error: {}", err),
 			Error::Custom(ref s) => s.clone(),
 		};
@@ -74,6 +78,12 @@ impl From<::secp256k1::Error> for Error {
 	}
 }
 
+impl From<Eip155Error> for Error {
+	fn from(err: Eip155Error) -> Error {
+		Error::Eip155(err)
+	}
+}
+
 impl From<::std::io::Error> for Error {
 	fn from(err: ::std::io::Error) -> Error {
 		Error::Io(err)
@@ -25,6 +25,8 @@ extern crate parity_wordlist;
 #[macro_use]
 extern crate quick_error;
 extern crate rand;
+#[cfg(feature = "parallel-recovery")]
+extern crate rayon;
 extern crate rustc_hex;
 extern crate secp256k1;
 extern crate serde;
@@ -39,6 +41,7 @@ extern crate serde_derive;
 
 mod brain;
 mod brain_prefix;
+mod eip155;
 mod error;
 mod keypair;
 mod keccak;
@@ -56,13 +59,16 @@ pub mod math;
 pub use self::parity_wordlist::Error as WordlistError;
 pub use self::brain::Brain;
 pub use self::brain_prefix::BrainPrefix;
+pub use self::eip155::{
+	sign_with_chain_id, recover_with_chain_id, signature_from_v, to_eip155_v, recovery_id_from_v, chain_id_from_v, Eip155Error,
+};
 pub use self::error::Error;
 pub use self::keypair::{KeyPair, public_to_address};
 pub use self::math::public_is_valid;
 pub use self::password::Password;
 pub use self::prefix::Prefix;
 pub use self::random::Random;
-pub use self::signature::{sign, verify_public, verify_address, recover, Signature};
+pub use self::signature::{sign, verify_public, verify_address, recover, recover_batch, Signature};
 pub use self::secret::Secret;
 pub use self::extended::{ExtendedPublic, ExtendedSecret, ExtendedKeyPair, DerivationError, Derivation};
 
@@ -19,7 +19,7 @@ use std::cmp::PartialEq;
 use std::fmt;
 use std::str::FromStr;
 use std::hash::{Hash, Hasher};
-use secp256k1::{Message as SecpMessage, RecoverableSignature, RecoveryId, Error as SecpError};
+use secp256k1::{Message as SecpMessage, RecoverableSignature, RecoveryId, Error as SecpError, Secp256k1};
 use secp256k1::key::{SecretKey, PublicKey};
 use rustc_hex::{ToHex, FromHex};
 use ethereum_types::{H520, H256};
@@ -226,8 +226,34 @@ pub fn verify_address(address: &Address, signature: &Signature, message: &Messag
 	Ok(address == &recovered_address)
 }
 
+/// Batches at least this large are recovered in parallel with the `parallel-recovery` feature.
+#[cfg(feature = "parallel-recovery")]
+const PARALLEL_RECOVERY_THRESHOLD: usize = 16;
+
 pub fn recover(signature: &Signature, message: &Message) -> Result<Public, Error> {
-	let context = &SECP256K1;
+	recover_with(&SECP256K1, signature, message)
+}
+
+/// Recover the public key which signed each message of `items` with the paired signature.
+///
+/// Results are in the order of `items` and each item fails on its own. The context is set up once for
+/// the whole batch, and with the `parallel-recovery` feature large batches are spread over the threads
+/// of the global `rayon` pool.
+pub fn recover_batch(items: &[(Signature, Message)]) -> Vec<Result<Public, Error>> {
+	let context: &Secp256k1 = &SECP256K1;
+
+	#[cfg(feature = "parallel-recovery")]
+	{
+		if items.len() >= PARALLEL_RECOVERY_THRESHOLD {
+			use rayon::prelude::*;
+			return items.par_iter().map(|&(ref signature, ref message)| recover_with(context, signature, message)).collect();
+		}
+	}
+
+	items.iter().map(|&(ref signature, ref message)| recover_with(context, signature, message)).collect()
+}
+
+fn recover_with(context: &Secp256k1, signature: &Signature, message: &Message) -> Result<Public, Error> {
 	let rsig = RecoverableSignature::from_compact(context, &signature[0..64], RecoveryId::from_i32(signature[64] as i32)?)?;
 	let pubkey = context.recover(&SecpMessage::from_slice(&message[..])?, &rsig)?;
 	let serialized = pubkey.serialize_vec(context, false);
@@ -240,8 +266,9 @@ pub fn recover(signature: &Signature, message: &Message) -> Result<Public, Error
 #[cfg(test)]
 mod tests {
 	use std::str::FromStr;
+	use ethereum_types::H256;
 	use {Generator, Random, Message};
-	use super::{sign, verify_public, verify_address, recover, Signature};
+	use super::{sign, verify_public, verify_address, recover, recover_batch, Signature};
 
 	#[test]
 	fn vrs_conversion() {
@@ -276,6 +303,29 @@ mod tests {
 		assert_eq!(keypair.public(), &recover(&signature, &message).unwrap());
 	}
 
+	#[test]
+	fn recover_batch_matches_single_recovery() {
+		let mut items = (0..40u64).map(|i| {
+			let keypair = Random.generate().unwrap();
+			let message = Message::from(i);
+			(sign(keypair.secret(), &message).unwrap(), message)
+		}).collect::<Vec<_>>();
+		// an invalid recovery id and an unrecoverable signature
+		let invalid_id = Signature::from_rsv(&H256::from_slice(items[3].0.r()), &H256::from_slice(items[3].0.s()), 4);
+		items[3].0 = invalid_id;
+		items[17].0 = Signature::default();
+
+		let recovered = recover_batch(&items);
+		assert_eq!(recovered.len(), items.len());
+		for (i, (result, &(ref signature, ref message))) in recovered.into_iter().zip(items.iter()).enumerate() {
+			match i {
+				3 | 17 => assert!(result.is_err()),
+				_ => assert_eq!(result.unwrap(), recover(signature, message).unwrap()),
+			}
+		}
+		assert!(recover_batch(&[]).is_empty());
+	}
+
 	#[test]
 	fn sign_and_verify_public() {
 		let keypair = Random.generate().unwrap();
@@ -284,8 +284,7 @@ fn execute<S, I>(command: I) -> Result<String, Error> where I: IntoIterator<Item
 	} else if args.cmd_change_vault_pwd {
 		let old_pwd = load_password(&args.arg_old_pwd)?;
 		let new_pwd = load_password(&args.arg_new_pwd)?;
-		store.open_vault(&args.arg_vault, &old_pwd)?;
-		store.change_vault_password(&args.arg_vault, &new_pwd)?;
+		store.change_vault_password(&args.arg_vault, Some(&old_pwd), &new_pwd)?;
 		Ok("OK".to_owned())
 	} else if args.cmd_move_to_vault {
 		let address = args.arg_address.parse().map_err(|_| ethstore::Error::InvalidAccount)?;
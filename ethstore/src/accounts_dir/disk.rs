@@ -22,7 +22,7 @@ use time;
 use {json, SafeAccount, Error};
 use json::Uuid;
 use super::{KeyDirectory, VaultKeyDirectory, VaultKeyDirectoryProvider, VaultKey};
-use super::vault::{VAULT_FILE_NAME, VaultDiskDirectory};
+use super::vault::{self, VAULT_FILE_NAME, OLD_VAULT_SUFFIX, VaultDiskDirectory};
 
 const IGNORED_FILES: &'static [&'static str] = &[
 	"thumbs.db",
@@ -297,12 +297,25 @@ impl<T> VaultKeyDirectoryProvider for DiskDirectory<T> where T: KeyFileManager {
 	}
 
 	fn list_vaults(&self) -> Result<Vec<String>, Error> {
+		// restore vaults whose password change was interrupted
+		for entry in fs::read_dir(&self.path)?.flat_map(Result::ok) {
+			let file_name = entry.file_name();
+			let name = file_name.to_string_lossy();
+			if name.starts_with(".") && name.ends_with(OLD_VAULT_SUFFIX) && name.len() > OLD_VAULT_SUFFIX.len() + 1 {
+				let vault_name = &name[1..name.len() - OLD_VAULT_SUFFIX.len()];
+				if let Err(err) = vault::recover_key_change(&self.path, vault_name) {
+					warn!("Unable to restore vault {}: {}", vault_name, err);
+				}
+			}
+		}
+
 		Ok(fs::read_dir(&self.path)?
 			.filter_map(|e| e.ok().map(|e| e.path()))
 			.filter_map(|path| {
 				let mut vault_file_path = path.clone();
 				vault_file_path.push(VAULT_FILE_NAME);
-				if vault_file_path.is_file() {
+				let is_hidden = path.file_name().map_or(true, |f| f.to_string_lossy().starts_with("."));
+				if vault_file_path.is_file() && !is_hidden {
 					path.file_name().and_then(|f| f.to_str()).map(|f| f.to_owned())
 				} else {
 					None
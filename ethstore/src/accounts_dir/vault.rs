@@ -27,6 +27,10 @@ use super::disk::{self, DiskDirectory, KeyFileManager};
 pub const VAULT_FILE_NAME: &'static str = "vault.json";
 /// Name of temporary vault metadata file
 pub const VAULT_TEMP_FILE_NAME: &'static str = "vault_temp.json";
+/// Suffix of the hidden directory a vault is re-encrypted into when its key is changed
+const NEW_VAULT_SUFFIX: &'static str = ".new";
+/// Suffix of the hidden directory a vault is moved to while it's replaced with its re-encrypted copy
+pub const OLD_VAULT_SUFFIX: &'static str = ".old";
 
 /// Vault directory implementation
 pub type VaultDiskDirectory = DiskDirectory<VaultKeyFileManager>;
@@ -61,6 +65,7 @@ impl VaultDiskDirectory {
 	/// Open existing vault directory with given key
 	pub fn at<P>(root: P, name: &str, key: VaultKey) -> Result<Self, Error> where P: AsRef<Path> {
 		// check that vault directory exists
+		recover_key_change(root.as_ref(), name)?;
 		let vault_dir_path = make_vault_dir_path(root, name, true)?;
 		if !vault_dir_path.is_dir() {
 			return Err(Error::CreationFailed);
@@ -75,6 +80,7 @@ impl VaultDiskDirectory {
 	/// Read vault meta without actually opening the vault
 	pub fn meta_at<P>(root: P, name: &str) -> Result<String, Error> where P: AsRef<Path> {
 		// check that vault directory exists
+		recover_key_change(root.as_ref(), name)?;
 		let vault_dir_path = make_vault_dir_path(root, name, true)?;
 		if !vault_dir_path.is_dir() {
 			return Err(Error::VaultNotFound);
@@ -84,37 +90,58 @@ impl VaultDiskDirectory {
 		read_vault_file(&vault_dir_path, None)
 	}
 
-	fn create_temp_vault(&self, key: VaultKey) -> Result<VaultDiskDirectory, Error> {
-		let original_path = self.path().expect("self is instance of DiskDirectory; DiskDirectory always returns path; qed");
-		let mut path: PathBuf = original_path.clone();
-		let name = self.name();
-
-		path.push(name); // to jump to the next level
-
-		let mut index = 0;
-		loop {
-			let name = format!("{}_temp_{}", name, index);
-			path.set_file_name(&name);
-			if !path.exists() {
-				return VaultDiskDirectory::create(original_path, &name, key);
-			}
-
-			index += 1;
+	/// Re-encrypt the vault with `new_key` into a hidden copy, which is swapped in place of the vault once
+	/// complete. `before_write` is called with the filename of each key file before it's copied.
+	fn set_key_with<F>(&self, new_key: VaultKey, before_write: F) -> Result<(), SetKeyError>
+		where F: FnMut(&str) -> Result<(), Error>
+	{
+		let vault_path = self.path().expect("self is instance of DiskDirectory; DiskDirectory always returns path; qed").clone();
+		let root = vault_path.parent().expect("vault directory is created in the root directory; qed").to_owned();
+		let new_path = swap_dir_path(&root, self.name(), NEW_VAULT_SUFFIX);
+		let old_path = swap_dir_path(&root, self.name(), OLD_VAULT_SUFFIX);
+
+		// leftovers of an interrupted key change are stale by now
+		recover_key_change(&root, self.name()).map_err(SetKeyError::NonFatalOld)?;
+
+		self.copy_with_key(&new_path, new_key, before_write).map_err(|err| {
+			// ignore error, as we already processing error
+			let _ = fs::remove_dir_all(&new_path);
+			SetKeyError::NonFatalOld(err)
+		})?;
+
+		fs::rename(&vault_path, &old_path).map_err(|err| {
+			let _ = fs::remove_dir_all(&new_path);
+			SetKeyError::NonFatalOld(err.into())
+		})?;
+
+		if let Err(err) = fs::rename(&new_path, &vault_path) {
+			// if the backup can't be moved back either, it's restored when the vault is next opened
+			return match fs::rename(&old_path, &vault_path) {
+				Ok(_) => {
+					let _ = fs::remove_dir_all(&new_path);
+					Err(SetKeyError::NonFatalOld(err.into()))
+				},
+				Err(_) => Err(SetKeyError::Fatal(err.into())),
+			};
 		}
+
+		fs::remove_dir_all(&old_path).map_err(|err| SetKeyError::NonFatalNew(err.into()))
 	}
 
-	fn copy_to_vault(&self, vault: &VaultDiskDirectory) -> Result<(), Error> {
+	fn copy_with_key<F>(&self, path: &Path, key: VaultKey, mut before_write: F) -> Result<(), Error>
+		where F: FnMut(&str) -> Result<(), Error>
+	{
+		fs::create_dir_all(path)?;
+		let meta = self.meta();
+		let copy = DiskDirectory::new(path, VaultKeyFileManager::new(self.name(), key.clone(), &meta));
 		for account in self.load()? {
 			let filename = account.filename.clone().expect("self is instance of DiskDirectory; DiskDirectory fills filename in load; qed");
-			vault.insert_with_filename(account, filename, true)?;
+			before_write(&filename)?;
+			copy.insert_with_filename(account, filename, false)?;
 		}
 
-		Ok(())
-	}
-
-	fn delete(&self) -> Result<(), Error> {
-		let path = self.path().expect("self is instance of DiskDirectory; DiskDirectory always returns path; qed");
-		fs::remove_dir_all(path).map_err(Into::into)
+		// vault file is written last => an incomplete copy can never be opened
+		create_vault_file(path, &key, &meta)
 	}
 }
 
@@ -132,39 +159,7 @@ impl VaultKeyDirectory for VaultDiskDirectory {
 	}
 
 	fn set_key(&self, new_key: VaultKey) -> Result<(), SetKeyError> {
-		let temp_vault = VaultDiskDirectory::create_temp_vault(self, new_key.clone()).map_err(|err| SetKeyError::NonFatalOld(err))?;
-		let mut source_path = temp_vault.path().expect("temp_vault is instance of DiskDirectory; DiskDirectory always returns path; qed").clone();
-		let mut target_path = self.path().expect("self is instance of DiskDirectory; DiskDirectory always returns path; qed").clone();
-
-		// preserve meta
-		temp_vault.set_meta(&self.meta()).map_err(SetKeyError::NonFatalOld)?;
-
-		// jump to next fs level
-		source_path.push("next");
-		target_path.push("next");
-
-		let temp_accounts = self.copy_to_vault(&temp_vault)
-			.and_then(|_| temp_vault.load())
-			.map_err(|err| {
-				// ignore error, as we already processing error
-				let _ = temp_vault.delete();
-				SetKeyError::NonFatalOld(err)
-			})?;
-
-		// we can't just delete temp vault until all files moved, because
-		// original vault content has already been partially replaced
-		// => when error or crash happens here, we can't do anything
-		for temp_account in temp_accounts {
-			let filename = temp_account.filename.expect("self is instance of DiskDirectory; DiskDirectory fills filename in load; qed");
-			source_path.set_file_name(&filename);
-			target_path.set_file_name(&filename);
-			fs::rename(&source_path, &target_path).map_err(|err| SetKeyError::Fatal(err.into()))?;
-		}
-		source_path.set_file_name(VAULT_FILE_NAME);
-		target_path.set_file_name(VAULT_FILE_NAME);
-		fs::rename(source_path, target_path).map_err(|err| SetKeyError::Fatal(err.into()))?;
-
-		temp_vault.delete().map_err(|err| SetKeyError::NonFatalNew(err))
+		self.set_key_with(new_key, |_| Ok(()))
 	}
 
 	fn meta(&self) -> String {
@@ -221,6 +216,35 @@ fn make_vault_dir_path<P>(root: P, name: &str, check_name: bool) -> Result<PathB
 	Ok(vault_dir_path)
 }
 
+/// Path of the hidden directory vault `name` is moved to, or copied into, while its key is changed.
+/// Vault names can't contain dots => it never collides with another vault
+fn swap_dir_path(root: &Path, name: &str, suffix: &str) -> PathBuf {
+	root.join(format!(".{}{}", name, suffix))
+}
+
+/// Roll back or finish a key change of vault `name` which was interrupted by a crash.
+/// The vault is restored from its backup unless the re-encrypted copy was already moved in place.
+pub fn recover_key_change(root: &Path, name: &str) -> Result<(), Error> {
+	let vault_dir_path = make_vault_dir_path(root, name, true)?;
+	let old_path = swap_dir_path(root, name, OLD_VAULT_SUFFIX);
+	let new_path = swap_dir_path(root, name, NEW_VAULT_SUFFIX);
+
+	if old_path.is_dir() {
+		if vault_dir_path.is_dir() {
+			fs::remove_dir_all(&old_path)?;
+		} else {
+			warn!("Restoring vault {} after an interrupted password change", name);
+			fs::rename(&old_path, &vault_dir_path)?;
+		}
+	}
+
+	if new_path.is_dir() {
+		fs::remove_dir_all(&new_path)?;
+	}
+
+	Ok(())
+}
+
 /// Every vault must have unique name => we rely on filesystem to check this
 /// => vault name must not contain any fs-special characters to avoid directory traversal
 /// => we only allow alphanumeric + separator characters in vault name.
@@ -283,8 +307,11 @@ mod test {
 	use std::fs;
 	use std::io::Write;
 	use std::path::PathBuf;
-	use super::VaultKey;
-	use super::{VAULT_FILE_NAME, check_vault_name, make_vault_dir_path, create_vault_file, read_vault_file, VaultDiskDirectory};
+	use ethkey::{Generator, Random};
+	use {Error, SafeAccount};
+	use super::{KeyDirectory, VaultKeyDirectory, VaultKey, SetKeyError};
+	use super::{VAULT_FILE_NAME, NEW_VAULT_SUFFIX, OLD_VAULT_SUFFIX, check_vault_name, make_vault_dir_path, create_vault_file,
+		read_vault_file, swap_dir_path, VaultDiskDirectory};
 	use self::tempdir::TempDir;
 
 	#[test]
@@ -438,4 +465,50 @@ mod test {
 		// then
 		assert!(vault.is_err());
 	}
+
+	#[test]
+	fn interrupted_key_change_leaves_vault_intact() {
+		// given
+		let temp_path = TempDir::new("").unwrap();
+		let dir: PathBuf = temp_path.path().into();
+		let key = VaultKey::new(&"password".into(), 1024);
+		let new_key = VaultKey::new(&"new_password".into(), 1024);
+		let vault = VaultDiskDirectory::create(&dir, "vault", key.clone()).unwrap();
+		for i in 0..3 {
+			let account = SafeAccount::create(&Random.generate().unwrap(), [i; 16], &"account".into(), 1024, "".to_owned(), "{}".to_owned()).unwrap();
+			vault.insert(account).unwrap();
+		}
+
+		// when the copy fails after the first key file
+		let mut written = 0;
+		let result = vault.set_key_with(new_key.clone(), |_| {
+			written += 1;
+			if written > 1 { Err(Error::Custom("injected failure".into())) } else { Ok(()) }
+		});
+
+		// then
+		match result {
+			Err(SetKeyError::NonFatalOld(_)) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		assert!(VaultDiskDirectory::at(&dir, "vault", new_key.clone()).is_err());
+		assert_eq!(VaultDiskDirectory::at(&dir, "vault", key.clone()).unwrap().load().unwrap().len(), 3);
+		assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+		// and when the process dies after moving the vault aside for its complete copy
+		vault.copy_with_key(&swap_dir_path(&dir, "vault", NEW_VAULT_SUFFIX), new_key.clone(), |_| Ok(())).unwrap();
+		fs::rename(dir.join("vault"), swap_dir_path(&dir, "vault", OLD_VAULT_SUFFIX)).unwrap();
+
+		// then the vault is restored when opened
+		assert_eq!(VaultDiskDirectory::at(&dir, "vault", key.clone()).unwrap().load().unwrap().len(), 3);
+		assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+		// and when the key change completes
+		vault.set_key(new_key.clone()).unwrap();
+
+		// then
+		assert!(VaultDiskDirectory::at(&dir, "vault", key).is_err());
+		assert_eq!(VaultDiskDirectory::at(&dir, "vault", new_key).unwrap().load().unwrap().len(), 3);
+		assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+	}
 }
@@ -137,8 +137,8 @@ impl SimpleSecretStore for EthStore {
 		self.store.list_opened_vaults()
 	}
 
-	fn change_vault_password(&self, name: &str, new_password: &Password) -> Result<(), Error> {
-		self.store.change_vault_password(name, new_password)
+	fn change_vault_password(&self, name: &str, old_password: Option<&Password>, new_password: &Password) -> Result<(), Error> {
+		self.store.change_vault_password(name, old_password, new_password)
 	}
 
 	fn change_account_vault(&self, vault: SecretVaultRef, account: StoreAccountRef) -> Result<StoreAccountRef, Error> {
@@ -629,22 +629,27 @@ impl SimpleSecretStore for EthMultiStore {
 		Ok(self.vaults.lock().keys().cloned().collect())
 	}
 
-	fn change_vault_password(&self, name: &str, new_password: &Password) -> Result<(), Error> {
-		let old_key = self.vaults.lock().get(name).map(|v| v.key()).ok_or(Error::VaultNotFound)?;
+	fn change_vault_password(&self, name: &str, old_password: Option<&Password>, new_password: &Password) -> Result<(), Error> {
+		let old_key = match old_password {
+			Some(old_password) => VaultKey::new(old_password, self.iterations),
+			None => self.vaults.lock().get(name).map(|v| v.key()).ok_or(Error::VaultNotFound)?,
+		};
 		let vault_provider = self.dir.as_vault_provider().ok_or(Error::VaultsAreNotSupported)?;
 		let vault = vault_provider.open(name, old_key)?;
+		// sessions opened with the old password are closed once the vault is re-encrypted,
+		// a vault changed without the old password stays opened with the new one
+		let reopen = || match old_password {
+			Some(_) => Ok(()),
+			None => self.open_vault(name, new_password),
+		};
 		match vault.set_key(VaultKey::new(new_password, self.iterations)) {
-			Ok(_) => {
-				self.close_vault(name)
-					.and_then(|_| self.open_vault(name, new_password))
-			},
+			Ok(_) => self.close_vault(name).and_then(|_| reopen()),
 			Err(SetKeyError::Fatal(err)) => {
 				let _ = self.close_vault(name);
 				Err(err)
 			},
 			Err(SetKeyError::NonFatalNew(err)) => {
-				let _ = self.close_vault(name)
-					.and_then(|_| self.open_vault(name, new_password));
+				let _ = self.close_vault(name).and_then(|_| reopen());
 				Err(err)
 			},
 			Err(SetKeyError::NonFatalOld(err)) => Err(err),
@@ -989,17 +994,73 @@ mod tests {
 		// then
 		assert_eq!(store.accounts().unwrap().len(), 1);
 		let new_password = "new_password".into();
-		store.change_vault_password(name, &new_password).unwrap();
+		store.change_vault_password(name, Some(&password), &new_password).unwrap();
+
+		// then the vault is closed
+		assert_eq!(store.accounts().unwrap().len(), 0);
+		assert!(store.list_opened_vaults().unwrap().is_empty());
+
+		// and when
+		assert!(store.open_vault(name, &password).is_err());
+		store.open_vault(name, &new_password).unwrap();
+
+		// then
 		assert_eq!(store.accounts().unwrap().len(), 1);
+		assert_eq!(store.list_vaults().unwrap(), vec![name.to_owned()]);
+	}
+
+	#[test]
+	fn should_change_opened_vault_password_without_old_password() {
+		// given
+		let mut dir = RootDiskDirectoryGuard::new();
+		let store = EthStore::open(dir.key_dir.take().unwrap()).unwrap();
+		let name = "vault"; let password = "password".into();
+		let keypair = keypair();
+		store.create_vault(name, &password).unwrap();
+		store.insert_account(SecretVaultRef::Vault(name.to_owned()), keypair.secret().clone(), &password).unwrap();
+
+		// when
+		let new_password = "new_password".into();
+		store.change_vault_password(name, None, &new_password).unwrap();
+
+		// then the vault stays opened
+		assert_eq!(store.accounts().unwrap().len(), 1);
+		assert_eq!(store.list_opened_vaults().unwrap(), vec![name.to_owned()]);
 
 		// and when
 		store.close_vault(name).unwrap();
 
 		// then
+		assert!(store.change_vault_password(name, None, &password).is_err());
+		assert!(store.open_vault(name, &password).is_err());
 		store.open_vault(name, &new_password).unwrap();
 		assert_eq!(store.accounts().unwrap().len(), 1);
 	}
 
+	#[test]
+	fn should_not_change_vault_password_with_wrong_old_password() {
+		// given
+		let mut dir = RootDiskDirectoryGuard::new();
+		let store = EthStore::open(dir.key_dir.take().unwrap()).unwrap();
+		let name = "vault"; let password = "password".into();
+		let keypair = keypair();
+		store.create_vault(name, &password).unwrap();
+		store.insert_account(SecretVaultRef::Vault(name.to_owned()), keypair.secret().clone(), &password).unwrap();
+
+		// when
+		let result = store.change_vault_password(name, Some(&"wrong".into()), &"new_password".into());
+
+		// then
+		match result {
+			Err(::Error::InvalidPassword) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		assert_eq!(store.accounts().unwrap().len(), 1);
+		store.close_vault(name).unwrap();
+		store.open_vault(name, &password).unwrap();
+		assert_eq!(store.accounts().unwrap().len(), 1);
+	}
+
 	#[test]
 	fn should_have_different_passwords_for_vault_secret_and_meta() {
 		// given
@@ -1106,7 +1167,7 @@ mod tests {
 		// when
 		store.create_vault(name, &password).unwrap();
 		store.set_vault_meta(name, "OldMeta").unwrap();
-		store.change_vault_password(name, &new_password).unwrap();
+		store.change_vault_password(name, Some(&password), &new_password).unwrap();
 
 		// then
 		assert_eq!(store.get_vault_meta(name).unwrap(), "OldMeta".to_owned());
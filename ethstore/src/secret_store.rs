@@ -92,8 +92,9 @@ pub trait SimpleSecretStore: Send + Sync {
 	fn list_vaults(&self) -> Result<Vec<String>, Error>;
 	/// List all currently opened vaults
 	fn list_opened_vaults(&self) -> Result<Vec<String>, Error>;
-	/// Change vault password
-	fn change_vault_password(&self, name: &str, new_password: &Password) -> Result<(), Error>;
+	/// Change vault password, re-encrypting the vault with the new one. Given the current password the vault
+	/// is closed if it was opened, otherwise the vault must be opened and is reopened with the new password.
+	fn change_vault_password(&self, name: &str, old_password: Option<&Password>, new_password: &Password) -> Result<(), Error>;
 	/// Cnage account' vault
 	fn change_account_vault(&self, vault: SecretVaultRef, account: StoreAccountRef) -> Result<StoreAccountRef, Error>;
 	/// Get vault metadata string.
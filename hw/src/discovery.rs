@@ -0,0 +1,346 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Discovery of the accounts of hardware wallets along derivation path templates.
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use ethereum_types::Address;
+
+/// Number of addresses queried from a device at once.
+pub const DISCOVERY_BATCH_SIZE: usize = 5;
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// BIP-32 derivation path of a key, e.g. `m/44'/60'/0'/0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+	/// Path of child `indices`, hardened ones having the highest bit set.
+	pub fn new(indices: Vec<u32>) -> Self {
+		DerivationPath(indices)
+	}
+
+	/// Child indices, hardened ones having the highest bit set.
+	pub fn indices(&self) -> &[u32] {
+		&self.0
+	}
+
+	/// Number of indices followed by each index in big endian, as expected by Ledger.
+	pub fn to_be_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(1 + 4 * self.0.len());
+		bytes.push(self.0.len() as u8);
+		for index in &self.0 {
+			bytes.extend_from_slice(&[(index >> 24) as u8, (index >> 16) as u8, (index >> 8) as u8, *index as u8]);
+		}
+		bytes
+	}
+}
+
+impl fmt::Display for DerivationPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "m")?;
+		for index in &self.0 {
+			if index & HARDENED != 0 {
+				write!(f, "/{}'", index & !HARDENED)?;
+			} else {
+				write!(f, "/{}", index)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Derivation path with a single variable index, marked `x`, e.g. `m/44'/60'/x'/0/0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+	/// Child indices, the variable one holding just its hardening bit.
+	indices: Vec<u32>,
+	/// Position of the variable index.
+	variable: usize,
+}
+
+impl PathTemplate {
+	/// Path with the variable index set to `index`.
+	pub fn path(&self, index: u32) -> DerivationPath {
+		debug_assert!(index < HARDENED, "Hardening is given by the template");
+		let mut indices = self.indices.clone();
+		indices[self.variable] |= index;
+		DerivationPath(indices)
+	}
+}
+
+impl FromStr for PathTemplate {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.split('/');
+		if parts.next() != Some("m") {
+			return Err(format!("Derivation path {} does not start with m/", s));
+		}
+
+		let mut indices = Vec::new();
+		let mut variable = None;
+		for part in parts {
+			let (body, hardened) = match part.trim_right_matches(|c: char| c == '\'' || c == 'h') {
+				body if body.len() + 1 == part.len() => (body, HARDENED),
+				body if body.len() == part.len() => (body, 0),
+				_ => return Err(format!("Invalid index {} in derivation path {}", part, s)),
+			};
+			if body == "x" {
+				if variable.is_some() {
+					return Err(format!("Derivation path {} has more than one variable index", s));
+				}
+				variable = Some(indices.len());
+				indices.push(hardened);
+			} else {
+				match body.parse::<u32>() {
+					Ok(index) if index < HARDENED => indices.push(index | hardened),
+					_ => return Err(format!("Invalid index {} in derivation path {}", part, s)),
+				}
+			}
+		}
+
+		match variable {
+			Some(variable) => Ok(PathTemplate { indices, variable }),
+			None => Err(format!("Derivation path {} has no variable index x", s)),
+		}
+	}
+}
+
+impl fmt::Display for PathTemplate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "m")?;
+		for (i, index) in self.indices.iter().enumerate() {
+			let hardened = if index & HARDENED != 0 { "'" } else { "" };
+			if i == self.variable {
+				write!(f, "/x{}", hardened)?;
+			} else {
+				write!(f, "/{}{}", index & !HARDENED, hardened)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Accounts discovered on a device so far.
+struct DeviceAccounts {
+	/// Addresses along with their paths, in discovery order.
+	accounts: Vec<(Address, DerivationPath)>,
+	/// Next index to query along each template.
+	next: Vec<usize>,
+}
+
+/// Accounts of the devices of a wallet along derivation path templates, each explored up to its own depth.
+///
+/// Devices are told apart by their primary address, the one at index 0 of the first template, so
+/// their accounts are kept when they are disconnected and don't have to be queried again when they are back.
+pub struct Discovery {
+	templates: Vec<(PathTemplate, usize)>,
+	devices: HashMap<Address, DeviceAccounts>,
+}
+
+impl Discovery {
+	/// Discovery along `templates`, paired with their depth.
+	pub fn new(templates: Vec<(PathTemplate, usize)>) -> Self {
+		Discovery {
+			templates,
+			devices: HashMap::new(),
+		}
+	}
+
+	/// Explore `templates` from now on, forgetting the accounts discovered along previous ones.
+	pub fn set_templates(&mut self, templates: Vec<(PathTemplate, usize)>) {
+		self.templates = templates;
+		self.devices.clear();
+	}
+
+	/// Path of the primary address of devices, if there is any template.
+	pub fn primary_path(&self) -> Option<DerivationPath> {
+		self.templates.first().map(|&(ref template, _)| template.path(0))
+	}
+
+	/// Query the accounts of the device with `primary` address which aren't discovered yet, up to
+	/// `DISCOVERY_BATCH_SIZE` at once with `query`, which returns the address at each of the given paths.
+	///
+	/// Stops at the first failing query, keeping the accounts discovered until then and resuming
+	/// from there on next call.
+	pub fn discover<F, E>(&mut self, primary: Address, mut query: F) -> Result<(), E>
+		where F: FnMut(&[DerivationPath]) -> Result<Vec<Address>, E>
+	{
+		let templates = &self.templates;
+		let device = self.devices.entry(primary).or_insert_with(|| DeviceAccounts {
+			accounts: Vec::new(),
+			next: vec![0; templates.len()],
+		});
+
+		for (t, &(ref template, depth)) in templates.iter().enumerate() {
+			while device.next[t] < depth {
+				let end = min(device.next[t] + DISCOVERY_BATCH_SIZE, depth);
+				let paths = (device.next[t]..end).map(|index| template.path(index as u32)).collect::<Vec<_>>();
+				let addresses = query(&paths)?;
+				debug_assert_eq!(addresses.len(), paths.len(), "An address is returned for each path");
+				device.accounts.extend(addresses.into_iter().zip(paths));
+				device.next[t] = end;
+			}
+		}
+		Ok(())
+	}
+
+	/// Accounts discovered so far on the device with `primary` address, along with their paths.
+	pub fn accounts(&self, primary: &Address) -> Vec<(Address, DerivationPath)> {
+		self.devices.get(primary).map_or_else(Vec::new, |device| device.accounts.clone())
+	}
+
+	/// Primary address of the device holding the account at `address` and the path of that account.
+	/// Primary addresses themselves are found even before any discovery.
+	pub fn find(&self, primary_addresses: &[Address], address: &Address) -> Option<(Address, DerivationPath)> {
+		if primary_addresses.contains(address) {
+			return self.primary_path().map(|path| (*address, path));
+		}
+
+		primary_addresses.iter()
+			.filter_map(|primary| self.devices.get(primary).map(|device| (primary, device)))
+			.filter_map(|(primary, device)| device.accounts.iter()
+				.find(|&&(ref account, _)| account == address)
+				.map(|&(_, ref path)| (*primary, path.clone())))
+			.next()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethereum_types::Address;
+	use super::{DerivationPath, Discovery, PathTemplate, DISCOVERY_BATCH_SIZE};
+
+	// Address a mocked device derives at `path`.
+	fn mock_address(seed: u64, path: &DerivationPath) -> Address {
+		let hash = path.indices().iter().fold(seed, |acc, index| acc.wrapping_mul(31).wrapping_add(*index as u64));
+		Address::from(hash)
+	}
+
+	fn templates(paths: &[(&str, usize)]) -> Vec<(PathTemplate, usize)> {
+		paths.iter().map(|&(path, depth)| (path.parse().unwrap(), depth)).collect()
+	}
+
+	#[test]
+	fn parses_and_formats_templates() {
+		let legacy: PathTemplate = "m/44'/60'/0'/x".parse().unwrap();
+		assert_eq!(legacy.to_string(), "m/44'/60'/0'/x");
+		assert_eq!(legacy.path(3), DerivationPath::new(vec![0x8000_002C, 0x8000_003C, 0x8000_0000, 3]));
+		assert_eq!(legacy.path(3).to_string(), "m/44'/60'/0'/3");
+
+		let live: PathTemplate = "m/44h/60h/xh/0/0".parse().unwrap();
+		assert_eq!(live.to_string(), "m/44'/60'/x'/0/0");
+		assert_eq!(live.path(2).indices(), &[0x8000_002C, 0x8000_003C, 0x8000_0002, 0, 0]);
+
+		assert!("44'/60'/x".parse::<PathTemplate>().is_err());
+		assert!("m/44'/60'/0'/0".parse::<PathTemplate>().is_err());
+		assert!("m/44'/x/x".parse::<PathTemplate>().is_err());
+		assert!("m/44''/60'/x".parse::<PathTemplate>().is_err());
+		assert!("m/2147483648/x".parse::<PathTemplate>().is_err());
+	}
+
+	#[test]
+	fn encodes_paths_for_ledger() {
+		let path: PathTemplate = "m/44'/60'/0'/x".parse().unwrap();
+		assert_eq!(path.path(0).to_be_bytes(), vec![4, 0x80, 0, 0, 44, 0x80, 0, 0, 60, 0x80, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(path.path(258).to_be_bytes(), vec![4, 0x80, 0, 0, 44, 0x80, 0, 0, 60, 0x80, 0, 0, 0, 0, 0, 1, 2]);
+	}
+
+	#[test]
+	fn discovers_multiple_paths_in_batches_up_to_their_depth() {
+		let mut discovery = Discovery::new(templates(&[("m/44'/60'/0'/x", 7), ("m/44'/60'/x'/0/0", 2)]));
+		let primary = mock_address(1, &discovery.primary_path().unwrap());
+		let mut queries = Vec::new();
+
+		discovery.discover(primary, |paths| -> Result<_, ()> {
+			queries.push(paths.len());
+			Ok(paths.iter().map(|path| mock_address(1, path)).collect())
+		}).unwrap();
+
+		assert_eq!(queries, vec![DISCOVERY_BATCH_SIZE, 2, 2]);
+		let accounts = discovery.accounts(&primary);
+		let paths = accounts.iter().map(|&(_, ref path)| path.to_string()).collect::<Vec<_>>();
+		assert_eq!(paths, vec![
+			"m/44'/60'/0'/0", "m/44'/60'/0'/1", "m/44'/60'/0'/2", "m/44'/60'/0'/3", "m/44'/60'/0'/4", "m/44'/60'/0'/5", "m/44'/60'/0'/6",
+			"m/44'/60'/0'/0/0", "m/44'/60'/1'/0/0",
+		]);
+		assert!(accounts.iter().all(|&(ref address, ref path)| *address == mock_address(1, path)));
+		assert_eq!(accounts[0].0, primary);
+
+		// everything is discovered, the device isn't queried again.
+		discovery.discover(primary, |_| -> Result<Vec<Address>, ()> { panic!("No query expected") }).unwrap();
+		assert_eq!(discovery.accounts(&primary).len(), 9);
+	}
+
+	#[test]
+	fn resumes_failed_discovery_and_keeps_accounts_across_reconnections() {
+		let mut discovery = Discovery::new(templates(&[("m/44'/60'/0'/x", 12)]));
+		let primary = mock_address(2, &discovery.primary_path().unwrap());
+
+		// device is unplugged after the first batch.
+		let mut batches = 0;
+		assert_eq!(discovery.discover(primary, |paths| {
+			batches += 1;
+			if batches > 1 { Err("unplugged") } else { Ok(paths.iter().map(|path| mock_address(2, path)).collect()) }
+		}), Err("unplugged"));
+		assert_eq!(discovery.accounts(&primary).len(), DISCOVERY_BATCH_SIZE);
+
+		// once it's back, discovery goes on from where it stopped.
+		let mut queried = Vec::new();
+		discovery.discover(primary, |paths| -> Result<_, ()> {
+			queried.extend(paths.iter().map(|path| path.indices()[3]));
+			Ok(paths.iter().map(|path| mock_address(2, path)).collect())
+		}).unwrap();
+		assert_eq!(queried, (5..12).collect::<Vec<_>>());
+		assert_eq!(discovery.accounts(&primary).len(), 12);
+
+		// other templates start over.
+		discovery.set_templates(templates(&[("m/44'/61'/0'/0/x", 1)]));
+		assert!(discovery.accounts(&primary).is_empty());
+	}
+
+	#[test]
+	fn finds_path_of_accounts_to_sign_with() {
+		let mut discovery = Discovery::new(templates(&[("m/44'/60'/0'/x", 3), ("m/44'/60'/x'/0/0", 3)]));
+		let primary_path = discovery.primary_path().unwrap();
+		let (first, second) = (mock_address(1, &primary_path), mock_address(2, &primary_path));
+		let primaries = vec![first, second];
+
+		// primary addresses are known before discovery.
+		assert_eq!(discovery.find(&primaries, &second), Some((second, primary_path.clone())));
+
+		for &(seed, primary) in &[(1, first), (2, second)] {
+			discovery.discover(primary, |paths| -> Result<_, ()> {
+				Ok(paths.iter().map(|path| mock_address(seed, path)).collect())
+			}).unwrap();
+		}
+
+		let live_path = "m/44'/60'/x'/0/0".parse::<PathTemplate>().unwrap().path(2);
+		assert_eq!(discovery.find(&primaries, &mock_address(2, &live_path)), Some((second, live_path)));
+		let legacy_path = "m/44'/60'/0'/x".parse::<PathTemplate>().unwrap().path(1);
+		assert_eq!(discovery.find(&primaries, &mock_address(1, &legacy_path)), Some((first, legacy_path)));
+
+		// accounts of disconnected devices can't be signed with.
+		assert_eq!(discovery.find(&primaries[..1], &mock_address(2, &live_path)), None);
+		assert_eq!(discovery.find(&primaries, &Address::from(42)), None);
+	}
+}
@@ -29,14 +29,15 @@ use hidapi;
 use libusb;
 use parking_lot::{Mutex, RwLock};
 use semver::Version as FirmwareVersion;
-use super::{WalletInfo, KeyPath, Device, DeviceDirection, Wallet, USB_DEVICE_CLASS_DEVICE, POLLING_DURATION};
+use super::{WalletInfo, KeyPath, Device, DeviceDirection, Wallet, HardwareAccount, DerivationPath, PathTemplate, Discovery,
+	find_account, USB_DEVICE_CLASS_DEVICE, POLLING_DURATION};
 
 const APDU_TAG: u8 = 0x05;
 const APDU_CLA: u8 = 0xe0;
 const APDU_PAYLOAD_HEADER_LEN: usize = 7;
 
-const ETH_DERIVATION_PATH_BE: [u8; 17] = [4, 0x80, 0, 0, 44, 0x80, 0, 0, 60, 0x80, 0, 0, 0, 0, 0, 0, 0]; // 44'/60'/0'/0
-const ETC_DERIVATION_PATH_BE: [u8; 21] = [5, 0x80, 0, 0, 44, 0x80, 0, 0, 60, 0x80, 0x02, 0x73, 0xd0, 0x80, 0, 0, 0, 0, 0, 0, 0]; // 44'/60'/160720'/0'/0
+const ETH_DERIVATION_PATH: &str = "m/44'/60'/0'/x";
+const ETC_DERIVATION_PATH: &str = "m/44'/60'/160720'/0'/x";
 
 /// Ledger vendor ID
 const LEDGER_VID: u16 = 0x2c97;
@@ -113,7 +114,7 @@ impl From<libusb::Error> for Error {
 pub (crate) struct Manager {
 	usb: Arc<Mutex<hidapi::HidApi>>,
 	devices: RwLock<Vec<Device>>,
-	key_path: RwLock<KeyPath>,
+	discovery: RwLock<Discovery>,
 }
 
 impl Manager {
@@ -122,7 +123,7 @@ impl Manager {
 		let manager = Arc::new(Self {
 			usb: hidapi,
 			devices: RwLock::new(Vec::new()),
-			key_path: RwLock::new(KeyPath::Ethereum),
+			discovery: RwLock::new(Discovery::new(key_path_templates(KeyPath::Ethereum))),
 		});
 
 		let usb_context = Arc::new(libusb::Context::new()?);
@@ -300,17 +301,10 @@ impl Manager {
 		Ok(FirmwareVersion::new(ver[1].into(), ver[2].into(), ver[3].into()))
 	}
 
-	fn get_derivation_path(&self) -> &[u8] {
-		match *self.key_path.read() {
-			KeyPath::Ethereum => &ETH_DERIVATION_PATH_BE,
-			KeyPath::EthereumClassic => &ETC_DERIVATION_PATH_BE,
-		}
-	}
-	
 	fn signer_helper(&self, address: &Address, data: &[u8], command: u8) -> Result<Signature, Error> {
 		let usb = self.usb.lock();
 		let devices = self.devices.read();
-		let device = devices.iter().find(|d| &d.info.address == address).ok_or(Error::KeyNotFound)?;
+		let (device, path) = find_account(&devices, &self.discovery.read(), address).ok_or(Error::KeyNotFound)?;
 		let handle = self.open_path(|| usb.open_path(&device.path))?;
 
 		// Signing personal messages are only support by Ledger firmware version 1.0.8 or newer
@@ -322,10 +316,10 @@ impl Manager {
 		}
 
 		let mut chunk= [0_u8; MAX_CHUNK_SIZE];
-		let derivation_path = self.get_derivation_path();
+		let derivation_path = path.to_be_bytes();
 
 		// Copy the address of the key (only done once)
-		chunk[0..derivation_path.len()].copy_from_slice(derivation_path);
+		chunk[0..derivation_path.len()].copy_from_slice(&derivation_path);
 		
 		let key_length = derivation_path.len();
 		let max_payload_size = MAX_CHUNK_SIZE - key_length;
@@ -363,6 +357,15 @@ impl Manager {
 	}
 }
 
+// Templates of the single account of `key_path`.
+fn key_path_templates(key_path: KeyPath) -> Vec<(PathTemplate, usize)> {
+	let template = match key_path {
+		KeyPath::Ethereum => ETH_DERIVATION_PATH,
+		KeyPath::EthereumClassic => ETC_DERIVATION_PATH,
+	};
+	vec![(template.parse().expect("Key derivation path templates are valid; qed"), 1)]
+}
+
 // Try to connect to the device using polling in at most the time specified by the `timeout`
 fn try_connect_polling(ledger: &Manager, timeout: &Duration, device_direction: DeviceDirection) -> bool {
 	let start_time = Instant::now();
@@ -384,7 +387,14 @@ impl <'a>Wallet<'a> for Manager {
 	}
 	
 	fn set_key_path(&self, key_path: KeyPath) {
-		*self.key_path.write() = key_path;
+		self.set_derivation_paths(key_path_templates(key_path));
+	}
+
+	fn set_derivation_paths(&self, templates: Vec<(PathTemplate, usize)>) {
+		self.discovery.write().set_templates(templates);
+		// Primary addresses of the connected devices depend on the templates,
+		// read them again, which fails when no device arrived
+		let _ = self.update_devices(DeviceDirection::Arrived);
 	}
 
 	fn update_devices(&self, device_direction: DeviceDirection) -> Result<usize, Self::Error> {
@@ -432,7 +442,8 @@ impl <'a>Wallet<'a> for Manager {
 		let manufacturer = dev_info.manufacturer_string.clone().unwrap_or_else(|| "Unknown".to_owned());
 		let name = dev_info.product_string.clone().unwrap_or_else(|| "Unknown".to_owned());
 		let serial = dev_info.serial_number.clone().unwrap_or_else(|| "Unknown".to_owned());
-		match self.get_address(&handle) {
+		let primary_path = self.discovery.read().primary_path().ok_or(Error::KeyNotFound)?;
+		match self.get_address(&handle, &primary_path) {
 			Ok(Some(addr)) => {
 				Ok(Device {
 					path: dev_info.path.clone(),
@@ -459,19 +470,41 @@ impl <'a>Wallet<'a> for Manager {
 		vec![]
 	}
 
+	fn list_accounts(&self) -> Vec<HardwareAccount> {
+		let usb = self.usb.lock();
+		let devices = self.devices.read();
+		let mut discovery = self.discovery.write();
+		let mut accounts = Vec::new();
+		for device in devices.iter() {
+			let discovered = discovery.discover(device.info.address, |paths| -> Result<Vec<Address>, Error> {
+				let handle = self.open_path(|| usb.open_path(&device.path))?;
+				paths.iter().map(|path| self.get_address(&handle, path)?.ok_or(Error::Impossible)).collect()
+			});
+			if let Err(e) = discovered {
+				debug!(target: "hw", "Ledger account discovery stopped, error: {}", e);
+			}
+			accounts.extend(discovery.accounts(&device.info.address).into_iter().map(|(address, path)| HardwareAccount {
+				address,
+				path,
+				wallet: device.info.clone(),
+			}));
+		}
+		accounts
+	}
+
 	fn get_wallet(&self, address: &Address) -> Option<WalletInfo> {
-		self.devices.read().iter().find(|d| &d.info.address == address).map(|d| d.info.clone())
+		let devices = self.devices.read();
+		let discovery = self.discovery.read();
+		find_account(&devices, &discovery, address).map(|(d, _)| WalletInfo { address: *address, ..d.info.clone() })
 	}
 
-	fn get_address(&self, device: &hidapi::HidDevice) -> Result<Option<Address>, Self::Error> {
+	fn get_address(&self, device: &hidapi::HidDevice, path: &DerivationPath) -> Result<Option<Address>, Self::Error> {
 		let ledger_version = Self::get_firmware_version(&device)?;
 		if ledger_version < FirmwareVersion::new(1, 0, 3) {
 			return Err(Error::Protocol("Ledger version 1.0.3 is required"));
 		}
 
-		let derivation_path = self.get_derivation_path();
-
-		let key_and_address = Self::send_apdu(device, commands::GET_ETH_PUBLIC_ADDRESS, 0, 0, derivation_path)?;
+		let key_and_address = Self::send_apdu(device, commands::GET_ETH_PUBLIC_ADDRESS, 0, 0, &path.to_be_bytes())?;
 		if key_and_address.len() != 107 { // 1 + 65 PK + 1 + 40 Addr (ascii-hex)
 			return Err(Error::Protocol("Key packet size mismatch"));
 		}
@@ -31,6 +31,7 @@ extern crate trezor_sys;
 #[macro_use] extern crate log;
 #[cfg(test)] extern crate rustc_hex;
 
+mod discovery;
 mod ledger;
 mod trezor;
 
@@ -41,6 +42,10 @@ use ethereum_types::U256;
 use ethkey::{Address, Signature};
 use parking_lot::Mutex;
 
+use discovery::Discovery;
+
+pub use discovery::{DerivationPath, PathTemplate, DISCOVERY_BATCH_SIZE};
+
 const USB_DEVICE_CLASS_DEVICE: u8 = 0;
 const POLLING_DURATION: Duration = Duration::from_millis(500);
 
@@ -51,6 +56,13 @@ pub struct Device {
 	info: WalletInfo,
 }
 
+/// Connected device holding the account at `address`, along with the derivation path of that account.
+fn find_account<'a>(devices: &'a [Device], discovery: &Discovery, address: &Address) -> Option<(&'a Device, DerivationPath)> {
+	let primary_addresses = devices.iter().map(|d| d.info.address).collect::<Vec<_>>();
+	let (primary, path) = discovery.find(&primary_addresses, address)?;
+	devices.iter().find(|d| d.info.address == primary).map(|d| (d, path))
+}
+
 /// `Wallet` trait
 pub trait Wallet<'a> {
 	/// Error
@@ -64,6 +76,9 @@ pub trait Wallet<'a> {
 	/// Set key derivation path for a chain.
 	fn set_key_path(&self, key_path: KeyPath);
 
+	/// Discover accounts along derivation path `templates`, each paired with the number of accounts to look for.
+	fn set_derivation_paths(&self, templates: Vec<(PathTemplate, usize)>);
+
 	/// Re-populate device list
 	/// Note, this assumes all devices are iterated over and updated
 	fn update_devices(&self, device_direction: DeviceDirection) -> Result<usize, Self::Error>;
@@ -79,11 +94,14 @@ pub trait Wallet<'a> {
 	/// Then this method return a empty vector
 	fn list_locked_devices(&self) -> Vec<String>;
 
+	/// List the accounts of connected wallets, querying the ones not discovered yet.
+	fn list_accounts(&self) -> Vec<HardwareAccount>;
+
 	/// Get wallet info.
 	fn get_wallet(&self, address: &Address) -> Option<WalletInfo>;
 
-	/// Generate ethereum address for a Wallet
-	fn get_address(&self, device: &hidapi::HidDevice) -> Result<Option<Address>, Self::Error>;
+	/// Generate ethereum address at derivation `path` for a Wallet
+	fn get_address(&self, device: &hidapi::HidDevice, path: &DerivationPath) -> Result<Option<Address>, Self::Error>;
 
 	/// Open a device using `device path`
 	/// Note, f - is a closure that borrows HidResult<HidDevice>
@@ -143,6 +161,17 @@ pub struct WalletInfo {
 	pub address: Address,
 }
 
+/// Account of a hardware wallet.
+#[derive(Debug, Clone)]
+pub struct HardwareAccount {
+	/// Ethereum address.
+	pub address: Address,
+	/// Derivation path of the account key.
+	pub path: DerivationPath,
+	/// Wallet holding the account.
+	pub wallet: WalletInfo,
+}
+
 /// Key derivation paths used on hardware wallets.
 #[derive(Debug, Clone, Copy)]
 pub enum KeyPath {
@@ -228,14 +257,31 @@ impl HardwareWalletManager {
 		})
 	}
 
-	/// Select key derivation path for a chain.
-	/// Currently, only one hard-coded keypath is supported
+	/// Select key derivation path for a chain, discovering just its first account.
 	/// It is managed by `ethcore/account_provider`
 	pub fn set_key_path(&self, key_path: KeyPath) {
 		self.ledger.set_key_path(key_path);
 		self.trezor.set_key_path(key_path);
 	}
 
+	/// Discover accounts along derivation path `templates`, each paired with the number of accounts to look for,
+	/// instead of the single account of the key derivation path.
+	pub fn set_derivation_paths(&self, templates: Vec<(PathTemplate, usize)>) {
+		self.ledger.set_derivation_paths(templates.clone());
+		self.trezor.set_derivation_paths(templates);
+	}
+
+	/// List the accounts of connected wallets along with their derivation paths.
+	///
+	/// Accounts are discovered on first listing, `DISCOVERY_BATCH_SIZE` at a time, and remembered
+	/// while wallets are disconnected.
+	pub fn list_accounts(&self) -> Vec<HardwareAccount> {
+		let mut accounts = Vec::new();
+		accounts.extend(self.ledger.list_accounts());
+		accounts.extend(self.trezor.list_accounts());
+		accounts
+	}
+
 	/// List connected wallets. This only returns wallets that are ready to be used.
 	pub fn list_wallets(&self) -> Vec<WalletInfo> {
 		let mut wallets = Vec::new();
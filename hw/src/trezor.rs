@@ -19,18 +19,19 @@
 //! and <https://github.com/trezor/trezor-common/blob/master/protob/protocol.md>
 //! for protocol details.
 
-use std::cmp::{min, max};
+use std::cmp::min;
 use std::sync::{atomic, atomic::AtomicBool, Arc, Weak};
 use std::time::{Duration, Instant};
 use std::{fmt, thread};
 
 use ethereum_types::{U256, H256, Address};
-use ethkey::Signature;
+use ethkey::{self, Signature};
 use hidapi;
 use libusb;
 use parking_lot::{Mutex, RwLock};
 use protobuf::{self, Message, ProtobufEnum};
-use super::{DeviceDirection, WalletInfo, TransactionInfo, KeyPath, Wallet, Device, USB_DEVICE_CLASS_DEVICE, POLLING_DURATION};
+use super::{DeviceDirection, WalletInfo, TransactionInfo, KeyPath, Wallet, Device, HardwareAccount, DerivationPath, PathTemplate,
+	Discovery, find_account, USB_DEVICE_CLASS_DEVICE, POLLING_DURATION};
 use trezor_sys::messages::{EthereumAddress, PinMatrixAck, MessageType, EthereumTxRequest, EthereumSignTx, EthereumGetAddress, EthereumTxAck, ButtonAck};
 
 /// Trezor v1 vendor ID
@@ -38,8 +39,8 @@ const TREZOR_VID: u16 = 0x534c;
 /// Trezor product IDs
 const TREZOR_PIDS: [u16; 1] = [0x0001];
 
-const ETH_DERIVATION_PATH: [u32; 5] = [0x8000_002C, 0x8000_003C, 0x8000_0000, 0, 0]; // m/44'/60'/0'/0/0
-const ETC_DERIVATION_PATH: [u32; 5] = [0x8000_002C, 0x8000_003D, 0x8000_0000, 0, 0]; // m/44'/61'/0'/0/0
+const ETH_DERIVATION_PATH: &str = "m/44'/60'/0'/0/x";
+const ETC_DERIVATION_PATH: &str = "m/44'/61'/0'/0/x";
 
 /// Hardware wallet error.
 #[derive(Debug)]
@@ -97,7 +98,7 @@ pub (crate) struct Manager {
 	usb: Arc<Mutex<hidapi::HidApi>>,
 	devices: RwLock<Vec<Device>>,
 	locked_devices: RwLock<Vec<String>>,
-	key_path: RwLock<KeyPath>,
+	discovery: RwLock<Discovery>,
 }
 
 /// HID Version used for the Trezor device
@@ -113,7 +114,7 @@ impl Manager {
 			usb: hidapi,
 			devices: RwLock::new(Vec::new()),
 			locked_devices: RwLock::new(Vec::new()),
-			key_path: RwLock::new(KeyPath::Ethereum),
+			discovery: RwLock::new(Discovery::new(key_path_templates(KeyPath::Ethereum))),
 		});
 
 		let usb_context = Arc::new(libusb::Context::new()?);
@@ -197,17 +198,13 @@ impl Manager {
 					let v = resp.get_signature_v();
 					let r = H256::from_slice(resp.get_signature_r());
 					let s = H256::from_slice(resp.get_signature_s());
-					if let Some(c_id) = *chain_id {
-						// If there is a chain_id supplied, Trezor will return a v
-						// part of the signature that is already adjusted for EIP-155,
-						// so v' = v + 2 * chain_id + 35, but code further down the
-						// pipeline will already do this transformation, so remove it here
-						let adjustment = 35 + 2 * c_id as u32;
-						Ok(Signature::from_rsv(&r, &s, (max(v, adjustment) - adjustment) as u8))
-					} else {
-						// If there isn't a chain_id, v will be returned as v + 27
-						let adjusted_v = if v < 27 { v } else { v - 27 };
-						Ok(Signature::from_rsv(&r, &s, adjusted_v as u8))
+					match *chain_id {
+						// Older firmware returns the bare recovery id for signatures without a chain id.
+						None if v < 27 => Ok(Signature::from_rsv(&r, &s, v as u8)),
+						// Otherwise v is EIP-155 encoded, while code further down the pipeline
+						// expects the recovery id and does the encoding itself.
+						_ => ethkey::signature_from_v(&Signature::from_rsv(&r, &s, 0), v as u64, *chain_id)
+							.map_err(|_| Error::Protocol("Trezor returned a signature for another chain")),
 					}
 				}
 			}
@@ -292,14 +289,11 @@ impl <'a>Wallet<'a> for Manager {
 		Result<Signature, Error> {
 		let usb = self.usb.lock();
 		let devices = self.devices.read();
-		let device = devices.iter().find(|d| &d.info.address == address).ok_or(Error::KeyNotFound)?;
+		let (device, path) = find_account(&devices, &self.discovery.read(), address).ok_or(Error::KeyNotFound)?;
 		let handle = self.open_path(|| usb.open_path(&device.path))?;
 		let msg_type = MessageType::MessageType_EthereumSignTx;
 		let mut message = EthereumSignTx::new();
-		match *self.key_path.read() {
-			KeyPath::Ethereum => message.set_address_n(ETH_DERIVATION_PATH.to_vec()),
-			KeyPath::EthereumClassic => message.set_address_n(ETC_DERIVATION_PATH.to_vec()),
-		}
+		message.set_address_n(path.indices().to_vec());
 		message.set_nonce(self.u256_to_be_vec(&t_info.nonce));
 		message.set_gas_limit(self.u256_to_be_vec(&t_info.gas_limit));
 		message.set_gas_price(self.u256_to_be_vec(&t_info.gas_price));
@@ -322,7 +316,14 @@ impl <'a>Wallet<'a> for Manager {
 	}
 
 	fn set_key_path(&self, key_path: KeyPath) {
-		*self.key_path.write() = key_path;
+		self.set_derivation_paths(key_path_templates(key_path));
+	}
+
+	fn set_derivation_paths(&self, templates: Vec<(PathTemplate, usize)>) {
+		self.discovery.write().set_templates(templates);
+		// Primary addresses of the connected devices depend on the templates,
+		// read them again, which fails when no device arrived
+		let _ = self.update_devices(DeviceDirection::Arrived);
 	}
 
 	fn update_devices(&self, device_direction: DeviceDirection) -> Result<usize, Error> {
@@ -376,7 +377,8 @@ impl <'a>Wallet<'a> for Manager {
 		let manufacturer = dev_info.manufacturer_string.clone().unwrap_or_else(|| "Unknown".to_owned());
 		let name = dev_info.product_string.clone().unwrap_or_else(|| "Unknown".to_owned());
 		let serial = dev_info.serial_number.clone().unwrap_or_else(|| "Unknown".to_owned());
-		match self.get_address(&handle) {
+		let primary_path = self.discovery.read().primary_path().ok_or(Error::KeyNotFound)?;
+		match self.get_address(&handle, &primary_path) {
 			Ok(Some(addr)) => {
 				Ok(Device {
 					path: dev_info.path.clone(),
@@ -401,17 +403,40 @@ impl <'a>Wallet<'a> for Manager {
 		(*self.locked_devices.read()).clone()
 	}
 
+	fn list_accounts(&self) -> Vec<HardwareAccount> {
+		let usb = self.usb.lock();
+		let devices = self.devices.read();
+		let mut discovery = self.discovery.write();
+		let mut accounts = Vec::new();
+		for device in devices.iter() {
+			let discovered = discovery.discover(device.info.address, |paths| -> Result<Vec<Address>, Error> {
+				let handle = self.open_path(|| usb.open_path(&device.path))?;
+				paths.iter()
+					.map(|path| self.get_address(&handle, path)?.ok_or_else(|| Error::LockedDevice(device.path.clone())))
+					.collect()
+			});
+			if let Err(e) = discovered {
+				debug!(target: "hw", "Trezor account discovery stopped, error: {}", e);
+			}
+			accounts.extend(discovery.accounts(&device.info.address).into_iter().map(|(address, path)| HardwareAccount {
+				address,
+				path,
+				wallet: device.info.clone(),
+			}));
+		}
+		accounts
+	}
+
 	fn get_wallet(&self, address: &Address) -> Option<WalletInfo> {
-		self.devices.read().iter().find(|d| &d.info.address == address).map(|d| d.info.clone())
+		let devices = self.devices.read();
+		let discovery = self.discovery.read();
+		find_account(&devices, &discovery, address).map(|(d, _)| WalletInfo { address: *address, ..d.info.clone() })
 	}
 
-	fn get_address(&self, device: &hidapi::HidDevice) -> Result<Option<Address>, Error> {
+	fn get_address(&self, device: &hidapi::HidDevice, path: &DerivationPath) -> Result<Option<Address>, Error> {
 		let typ = MessageType::MessageType_EthereumGetAddress;
 		let mut message = EthereumGetAddress::new();
-		match *self.key_path.read() {
-			KeyPath::Ethereum => message.set_address_n(ETH_DERIVATION_PATH.to_vec()),
-			KeyPath::EthereumClassic => message.set_address_n(ETC_DERIVATION_PATH.to_vec()),
-		}
+		message.set_address_n(path.indices().to_vec());
 		message.set_show_display(false);
 		self.send_device_message(&device, &typ, &message)?;
 
@@ -432,6 +457,15 @@ impl <'a>Wallet<'a> for Manager {
 	}
 }
 
+// Templates of the single account of `key_path`.
+fn key_path_templates(key_path: KeyPath) -> Vec<(PathTemplate, usize)> {
+	let template = match key_path {
+		KeyPath::Ethereum => ETH_DERIVATION_PATH,
+		KeyPath::EthereumClassic => ETC_DERIVATION_PATH,
+	};
+	vec![(template.parse().expect("Key derivation path templates are valid; qed"), 1)]
+}
+
 // Try to connect to the device using polling in at most the time specified by the `timeout`
 fn try_connect_polling(trezor: &Manager, duration: &Duration, dir: DeviceDirection) -> bool {
 	let start_time = Instant::now();
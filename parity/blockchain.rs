@@ -71,6 +71,8 @@ pub enum BlockchainCmd {
 	Import(ImportBlockchain),
 	Export(ExportBlockchain),
 	ExportState(ExportState),
+	RebuildTraceBlooms(RebuildTraceBlooms),
+	Migrate(MigrateBlockchain),
 }
 
 #[derive(Debug, PartialEq)]
@@ -80,6 +82,28 @@ pub struct KillBlockchain {
 	pub pruning: Pruning,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct MigrateBlockchain {
+	pub spec: SpecType,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub compaction: DatabaseCompactionProfile,
+	pub dry_run: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RebuildTraceBlooms {
+	pub spec: SpecType,
+	pub cache_config: CacheConfig,
+	pub dirs: Directories,
+	pub pruning: Pruning,
+	pub pruning_history: u64,
+	pub pruning_memory: usize,
+	pub compaction: DatabaseCompactionProfile,
+	pub fat_db: Switch,
+	pub tracing: Switch,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ImportBlockchain {
 	pub spec: SpecType,
@@ -150,6 +174,8 @@ pub fn execute(cmd: BlockchainCmd) -> Result<(), String> {
 		}
 		BlockchainCmd::Export(export_cmd) => execute_export(export_cmd),
 		BlockchainCmd::ExportState(export_cmd) => execute_export_state(export_cmd),
+		BlockchainCmd::RebuildTraceBlooms(rebuild_cmd) => execute_rebuild_trace_blooms(rebuild_cmd),
+		BlockchainCmd::Migrate(migrate_cmd) => execute_migrate(migrate_cmd),
 	}
 }
 
@@ -197,6 +223,7 @@ fn execute_import_light(cmd: ImportBlockchain) -> Result<(), String> {
 		verify_full: true,
 		check_seal: cmd.check_seal,
 		no_hardcoded_sync: true,
+		header_retention: ::light::client::DEFAULT_HEADER_RETENTION,
 	};
 
 	config.queue.max_mem_use = cmd.cache_config.queue() as usize * 1024 * 1024;
@@ -701,6 +728,45 @@ fn execute_export_state(cmd: ExportState) -> Result<(), String> {
 	Ok(())
 }
 
+fn execute_rebuild_trace_blooms(cmd: RebuildTraceBlooms) -> Result<(), String> {
+	let service = start_client(
+		cmd.dirs,
+		cmd.spec,
+		cmd.pruning,
+		cmd.pruning_history,
+		cmd.pruning_memory,
+		cmd.tracing,
+		cmd.fat_db,
+		cmd.compaction,
+		cmd.cache_config,
+		false
+	)?;
+
+	let client = service.client();
+	if client.trace_horizon().is_none() {
+		return Err("This command requires Parity to be synced with --tracing on.".to_owned());
+	}
+
+	info!("Rebuilding trace blooms...");
+	let rebuilt = client.rebuild_trace_blooms();
+	info!("Rebuilt trace blooms of {} blocks.", rebuilt);
+	Ok(())
+}
+
+fn execute_migrate(cmd: MigrateBlockchain) -> Result<(), String> {
+	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
+	let genesis_hash = spec.genesis_header().hash();
+	let db_dirs = cmd.dirs.database(genesis_hash, None, spec.data_dir);
+	let user_defaults = UserDefaults::load(&db_dirs.user_defaults_path())?;
+	let algorithm = cmd.pruning.to_algorithm(&user_defaults);
+
+	if cmd.dry_run {
+		db::check_migration(&db_dirs.db_path(algorithm), &cmd.compaction).map_err(|e| format!("{}", e))
+	} else {
+		execute_upgrades(&cmd.dirs.base, &db_dirs, algorithm, &cmd.compaction)
+	}
+}
+
 pub fn kill_db(cmd: KillBlockchain) -> Result<(), String> {
 	let spec = cmd.spec.spec(&cmd.dirs.cache)?;
 	let genesis_hash = spec.genesis_header().hash();
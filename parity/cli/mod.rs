@@ -182,6 +182,10 @@ usage! {
 			"--at=[BLOCK]",
 			"Take a snapshot at the given block, which may be an index, hash, or latest. Note that taking snapshots at non-recent blocks will only work with --pruning archive",
 
+			ARG arg_snapshot_compression: (String) = "snappy",
+			"--compression=[ALGO]",
+			"Compress the snapshot chunks with ALGO. ALGO may be one of: snappy - readable by all clients; zstd - smaller, needs a client built with Zstandard support; none - fastest to take and restore locally.",
+
 			ARG arg_snapshot_file: (Option<String>) = None,
 			"<FILE>",
 			"Path to the file to export to",
@@ -193,7 +197,7 @@ usage! {
 
 			ARG arg_restore_file: (Option<String>) = None,
 			"[FILE]",
-			"Path to the file to restore from",
+			"Path to the snapshot to restore from: a packed snapshot file, a snapshot directory or a tar archive of one",
 		}
 
 		CMD cmd_tools
@@ -217,6 +221,19 @@ usage! {
 			CMD cmd_db_kill {
 				"Clean the database of the given --chain (default: mainnet)",
 			}
+
+			CMD cmd_db_rebuild_trace_blooms {
+				"Rebuild the trace blooms of the given --chain (default: mainnet) from the stored traces",
+			}
+
+			CMD cmd_db_migrate
+			{
+				"Migrate the database of the given --chain (default: mainnet) to the current version, reporting progress",
+
+				FLAG flag_db_migrate_dry_run: (bool) = false,
+				"--dry-run",
+				"Walk the database through the migrations without writing anything, checking that it can be migrated.",
+			}
 		}
 
 		CMD cmd_export_hardcoded_sync
@@ -575,10 +592,26 @@ usage! {
 			"--no-secretstore-auto-migrate",
 			"Do not run servers set change session automatically when servers set changes. This option has no effect when servers set is read from configuration file.",
 
+			FLAG flag_secretstore_tls_required: (bool) = false, or |c: &Config| c.secretstore.as_ref()?.tls_required.clone(),
+			"--secretstore-tls-required",
+			"Refuse plaintext connections from other Secret Store Key Servers. Requires --secretstore-tls-cert.",
+
 			ARG arg_secretstore_acl_contract: (Option<String>) = Some("registry".into()), or |c: &Config| c.secretstore.as_ref()?.acl_contract.clone(),
 			"--secretstore-acl-contract=[SOURCE]",
 			"Secret Store permissioning contract address source: none, registry (contract address is read from 'secretstore_acl_checker' entry in registry) or address.",
 
+			ARG arg_secretstore_acl_cache_ttl: (u64) = 60u64, or |c: &Config| c.secretstore.as_ref()?.acl_cache_ttl.clone(),
+			"--secretstore-acl-cache-ttl=[SECS]",
+			"Specify the number of seconds Secret Store permissioning contract results are cached for. Results are dropped earlier when the contract emits logs. 0 disables caching.",
+
+			ARG arg_secretstore_audit_log_retention: (usize) = 1000000usize, or |c: &Config| c.secretstore.as_ref()?.audit_log_retention.clone(),
+			"--secretstore-audit-log-retention=[ENTRIES]",
+			"Specify the max number of entries in the Secret Store audit log of completed key sessions. Oldest entries are pruned first.",
+
+			ARG arg_secretstore_key_retirement_grace_period: (u64) = 86400u64, or |c: &Config| c.secretstore.as_ref()?.key_retirement_grace_period.clone(),
+			"--secretstore-key-retirement-grace-period=[SECS]",
+			"Specify the number of seconds Secret Store keeps shares of keys, retired by administrator, before erasing them.",
+
 			ARG arg_secretstore_contract: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.service_contract.clone(),
 			"--secretstore-contract=[SOURCE]",
 			"Secret Store Service contract address source: none, registry (contract address is read from 'secretstore_service' entry in registry) or address.",
@@ -623,6 +656,26 @@ usage! {
 			"--secretstore-http-port=[PORT]",
 			"Specify the port portion for listening to Secret Store Key Server HTTP requests.",
 
+			ARG arg_secretstore_http_tls_cert: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.http_tls_cert.clone(),
+			"--secretstore-http-tls-cert=[FILE]",
+			"Specify PEM-encoded certificate chain for serving Secret Store Key Server HTTP requests over TLS. The file is read again on SIGHUP. Must be used with --secretstore-http-tls-key.",
+
+			ARG arg_secretstore_http_tls_key: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.http_tls_key.clone(),
+			"--secretstore-http-tls-key=[FILE]",
+			"Specify PEM-encoded private key of the Secret Store Key Server HTTP certificate. The file is read again on SIGHUP.",
+
+			ARG arg_secretstore_tls_cert: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.tls_cert.clone(),
+			"--secretstore-tls-cert=[FILE]",
+			"Specify PEM-encoded certificate chain for connections to other Secret Store Key Servers. The certificate must be issued for DNS name <hex address of node key>.secretstore. The file is read again on SIGHUP. Must be used with --secretstore-tls-key and --secretstore-tls-ca.",
+
+			ARG arg_secretstore_tls_key: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.tls_key.clone(),
+			"--secretstore-tls-key=[FILE]",
+			"Specify PEM-encoded private key of the Secret Store Key Server certificate. The file is read again on SIGHUP.",
+
+			ARG arg_secretstore_tls_ca: (Option<String>) = None, or |c: &Config| c.secretstore.as_ref()?.tls_ca.clone(),
+			"--secretstore-tls-ca=[FILE]",
+			"Specify PEM-encoded certificates of authorities issuing Secret Store Key Server certificates. The file is read again on SIGHUP.",
+
 			ARG arg_secretstore_path: (String) = "$BASE/secretstore", or |c: &Config| c.secretstore.as_ref()?.path.clone(),
 			"--secretstore-path=[PATH]",
 			"Specify directory where Secret Store should save its data.",
@@ -811,6 +864,10 @@ usage! {
 			"--tracing=[BOOL]",
 			"Indicates if full transaction tracing should be enabled. Works only if client had been fully synced with tracing enabled. BOOL may be one of auto, on, off. auto uses last used value of this option (off if it does not exist).", // footprint option
 
+			ARG arg_tracing_history: (Option<u64>) = None, or |c: &Config| c.footprint.as_ref()?.tracing_history.clone(),
+			"--tracing-history=[NUM]",
+			"Set the number of recent blocks to keep traces of when tracing is enabled. Traces of older blocks are pruned. Keeps the traces of all blocks if not set.",
+
 			ARG arg_pruning: (String) = "auto", or |c: &Config| c.footprint.as_ref()?.pruning.clone(),
 			"--pruning=[METHOD]",
 			"Configure pruning of the state/storage trie. METHOD may be one of auto, archive, fast: archive - keep all state trie data. No pruning. fast - maintain journal overlay. Fast but 50MB used. auto - use the method most recently synced or default to fast if none synced.",
@@ -865,6 +922,26 @@ usage! {
 			"--no-periodic-snapshot",
 			"Disable automated snapshots which usually occur once every 10000 blocks.",
 
+			ARG arg_snapshot_io_budget: (Option<u32>) = None, or |c: &Config| c.snapshots.as_ref()?.io_budget.clone(),
+			"--snapshot-io-budget=[MB]",
+			"Limit the reads of automated snapshots to MB megabytes per second, sparing block import and RPC on slow disks.",
+
+			ARG arg_snapshot_period: (u64) = 5000u64, or |c: &Config| c.snapshots.as_ref()?.period.clone(),
+			"--snapshot-period=[BLOCKS]",
+			"Take an automated snapshot once every BLOCKS blocks.",
+
+			ARG arg_snapshot_keep: (usize) = 1usize, or |c: &Config| c.snapshots.as_ref()?.keep.clone(),
+			"--snapshot-keep=[NUM]",
+			"Keep the NUM most recent automated snapshots on disk, deleting older ones once a new snapshot is complete.",
+
+			ARG arg_snapshot_min_free_space: (u64) = 0u64, or |c: &Config| c.snapshots.as_ref()?.min_free_space.clone(),
+			"--snapshot-min-free-space=[MB]",
+			"Skip automated snapshots while less than MB megabytes are free on the disk holding them.",
+
+			ARG arg_import_snapshot: (Option<String>) = None, or |_| None,
+			"--import-snapshot=[PATH]",
+			"Restore the snapshot at PATH on startup instead of warp syncing from peers. PATH may be a packed snapshot file, a snapshot directory or a tar archive of one.",
+
 		["Whisper Options"]
 			FLAG flag_whisper: (bool) = false, or |c: &Config| c.whisper.as_ref()?.enabled,
 			"--whisper",
@@ -874,6 +951,10 @@ usage! {
 			"--whisper-pool-size=[MB]",
 			"Target size of the whisper message pool in megabytes.",
 
+			FLAG flag_whisper_persist_pool: (bool) = false, or |c: &Config| c.whisper.as_ref()?.persist_pool,
+			"--whisper-persist-pool",
+			"Save the whisper message pool in the base directory and restore its unexpired messages on startup.",
+
 		["Legacy Options"]
 		    // Options that are hidden from config, but are still unique for its functionality.
 
@@ -1251,6 +1332,9 @@ struct SecretStore {
 	disable_http: Option<bool>,
 	disable_auto_migrate: Option<bool>,
 	acl_contract: Option<String>,
+	acl_cache_ttl: Option<u64>,
+	audit_log_retention: Option<usize>,
+	key_retirement_grace_period: Option<u64>,
 	service_contract: Option<String>,
 	service_contract_srv_gen: Option<String>,
 	service_contract_srv_retr: Option<String>,
@@ -1264,6 +1348,12 @@ struct SecretStore {
 	port: Option<u16>,
 	http_interface: Option<String>,
 	http_port: Option<u16>,
+	http_tls_cert: Option<String>,
+	http_tls_key: Option<String>,
+	tls_cert: Option<String>,
+	tls_key: Option<String>,
+	tls_ca: Option<String>,
+	tls_required: Option<bool>,
 	path: Option<String>,
 }
 
@@ -1326,6 +1416,7 @@ struct Stratum {
 #[serde(deny_unknown_fields)]
 struct Footprint {
 	tracing: Option<String>,
+	tracing_history: Option<u64>,
 	pruning: Option<String>,
 	pruning_history: Option<u64>,
 	pruning_memory: Option<usize>,
@@ -1345,6 +1436,10 @@ struct Footprint {
 #[serde(deny_unknown_fields)]
 struct Snapshots {
 	disable_periodic: Option<bool>,
+	io_budget: Option<u32>,
+	period: Option<u64>,
+	keep: Option<usize>,
+	min_free_space: Option<u64>,
 }
 
 #[derive(Default, Debug, PartialEq, Deserialize)]
@@ -1362,6 +1457,7 @@ struct Misc {
 struct Whisper {
 	enabled: Option<bool>,
 	pool_size: Option<usize>,
+	persist_pool: Option<bool>,
 }
 
 #[cfg(test)]
@@ -1556,6 +1652,8 @@ mod tests {
 			cmd_tools_hash: false,
 			cmd_db: false,
 			cmd_db_kill: false,
+			cmd_db_rebuild_trace_blooms: false,
+			cmd_db_migrate: false,
 			cmd_export_hardcoded_sync: false,
 
 			// Arguments
@@ -1566,9 +1664,11 @@ mod tests {
 			arg_export_blocks_format: None,
 			arg_export_state_file: None,
 			arg_export_state_format: None,
+			arg_snapshot_compression: "snappy".into(),
 			arg_snapshot_file: None,
 			arg_restore_file: None,
 			arg_tools_hash_file: None,
+			flag_db_migrate_dry_run: false,
 
 			arg_signer_sign_id: None,
 			arg_signer_reject_id: None,
@@ -1680,7 +1780,11 @@ mod tests {
 			flag_no_secretstore: false,
 			flag_no_secretstore_http: false,
 			flag_no_secretstore_auto_migrate: false,
+			flag_secretstore_tls_required: false,
 			arg_secretstore_acl_contract: Some("registry".into()),
+			arg_secretstore_acl_cache_ttl: 60u64,
+			arg_secretstore_audit_log_retention: 1000000usize,
+			arg_secretstore_key_retirement_grace_period: 86400u64,
 			arg_secretstore_contract: Some("none".into()),
 			arg_secretstore_srv_gen_contract: Some("none".into()),
 			arg_secretstore_srv_retr_contract: Some("none".into()),
@@ -1694,6 +1798,11 @@ mod tests {
 			arg_secretstore_port: 8083u16,
 			arg_secretstore_http_interface: "local".into(),
 			arg_secretstore_http_port: 8082u16,
+			arg_secretstore_http_tls_cert: None,
+			arg_secretstore_http_tls_key: None,
+			arg_secretstore_tls_cert: None,
+			arg_secretstore_tls_key: None,
+			arg_secretstore_tls_ca: None,
 			arg_secretstore_path: "$HOME/.parity/secretstore".into(),
 
 			// IPFS
@@ -1744,6 +1853,7 @@ mod tests {
 
 			// -- Footprint Options
 			arg_tracing: "auto".into(),
+			arg_tracing_history: None,
 			arg_pruning: "auto".into(),
 			arg_pruning_history: 64u64,
 			arg_pruning_memory: 500usize,
@@ -1771,9 +1881,15 @@ mod tests {
 			arg_export_state_at: "latest".into(),
 			arg_snapshot_at: "latest".into(),
 			flag_no_periodic_snapshot: false,
+			arg_snapshot_io_budget: None,
+			arg_snapshot_period: 5000u64,
+			arg_snapshot_keep: 1usize,
+			arg_snapshot_min_free_space: 0u64,
+			arg_import_snapshot: None,
 
 			// -- Whisper options.
 			flag_whisper: false,
+			flag_whisper_persist_pool: false,
 			arg_whisper_pool_size: 20,
 
 			// -- Legacy Options
@@ -1946,6 +2062,9 @@ mod tests {
 				disable_http: None,
 				disable_auto_migrate: None,
 				acl_contract: None,
+				acl_cache_ttl: None,
+				audit_log_retention: None,
+				key_retirement_grace_period: None,
 				service_contract: None,
 				service_contract_srv_gen: None,
 				service_contract_srv_retr: None,
@@ -1959,6 +2078,12 @@ mod tests {
 				port: Some(8083),
 				http_interface: None,
 				http_port: Some(8082),
+				http_tls_cert: None,
+				http_tls_key: None,
+				tls_cert: None,
+				tls_key: None,
+				tls_ca: None,
+				tls_required: None,
 				path: None,
 			}),
 			private_tx: None,
@@ -2005,6 +2130,7 @@ mod tests {
 			}),
 			footprint: Some(Footprint {
 				tracing: Some("on".into()),
+				tracing_history: None,
 				pruning: Some("fast".into()),
 				pruning_history: Some(64),
 				pruning_memory: None,
@@ -2021,6 +2147,10 @@ mod tests {
 			}),
 			snapshots: Some(Snapshots {
 				disable_periodic: Some(true),
+				io_budget: None,
+				period: None,
+				keep: None,
+				min_free_space: None,
 			}),
 			misc: Some(Misc {
 				logging: Some("own_tx=trace".into()),
@@ -2032,6 +2162,7 @@ mod tests {
 			whisper: Some(Whisper {
 				enabled: Some(true),
 				pool_size: Some(50),
+				persist_pool: None,
 			}),
 			stratum: None,
 		});
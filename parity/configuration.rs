@@ -30,13 +30,14 @@ use sync::{NetworkConfiguration, validate_node_url, self};
 use ethcore::ethstore::ethkey::{Secret, Public};
 use ethcore::client::{VMType};
 use ethcore::miner::{stratum, MinerOptions};
+use ethcore::snapshot::RetentionPolicy;
 use ethcore::verification::queue::VerifierSettings;
 use miner::pool;
 
 use rpc::{IpcConfiguration, HttpConfiguration, WsConfiguration};
 use parity_rpc::NetworkSettings;
 use cache::CacheConfig;
-use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_queue_strategy, to_queue_penalization, passwords_from_files};
+use helpers::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_snapshot_compression, to_price, geth_ipc_path, parity_ipc_path, to_bootnodes, to_addresses, to_address, to_queue_strategy, to_queue_penalization, passwords_from_files};
 use dir::helpers::{replace_home, replace_home_and_local};
 use params::{ResealPolicy, AccountsConfig, GasPricerConfig, MinerExtras, SpecType};
 use ethcore_logger::Config as LogConfig;
@@ -46,7 +47,7 @@ use ethcore_private_tx::{ProviderConfig, EncryptorConfig};
 use secretstore::{NodeSecretKey, Configuration as SecretStoreConfiguration, ContractAddress as SecretStoreContractAddress};
 use updater::{UpdatePolicy, UpdateFilter, ReleaseTrack};
 use run::RunCmd;
-use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, ExportState, DataFormat};
+use blockchain::{BlockchainCmd, ImportBlockchain, ExportBlockchain, KillBlockchain, RebuildTraceBlooms, MigrateBlockchain, ExportState, DataFormat};
 use export_hardcoded_sync::ExportHsyncCmd;
 use presale::ImportWallet;
 use account::{AccountCmd, NewAccount, ListAccounts, ImportAccounts, ImportFromGethAccounts};
@@ -178,6 +179,26 @@ impl Configuration {
 				dirs: dirs,
 				pruning: pruning,
 			}))
+		} else if self.args.cmd_db && self.args.cmd_db_rebuild_trace_blooms {
+			Cmd::Blockchain(BlockchainCmd::RebuildTraceBlooms(RebuildTraceBlooms {
+				spec: spec,
+				cache_config: cache_config,
+				dirs: dirs,
+				pruning: pruning,
+				pruning_history: pruning_history,
+				pruning_memory: self.args.arg_pruning_memory,
+				compaction: compaction,
+				fat_db: fat_db,
+				tracing: tracing,
+			}))
+		} else if self.args.cmd_db && self.args.cmd_db_migrate {
+			Cmd::Blockchain(BlockchainCmd::Migrate(MigrateBlockchain {
+				spec: spec,
+				dirs: dirs,
+				pruning: pruning,
+				compaction: compaction,
+				dry_run: self.args.flag_db_migrate_dry_run,
+			}))
 		} else if self.args.cmd_account {
 			let account_cmd = if self.args.cmd_account_new {
 				let new_acc = NewAccount {
@@ -298,6 +319,7 @@ impl Configuration {
 				file_path: self.args.arg_snapshot_file.clone(),
 				kind: snapshot::Kind::Take,
 				block_at: to_block_id(&self.args.arg_snapshot_at)?,
+				compression: to_snapshot_compression(&self.args.arg_snapshot_compression)?,
 			};
 			Cmd::Snapshot(snapshot_cmd)
 		} else if self.args.cmd_restore {
@@ -314,6 +336,7 @@ impl Configuration {
 				file_path: self.args.arg_restore_file.clone(),
 				kind: snapshot::Kind::Restore,
 				block_at: to_block_id("latest")?, // unimportant.
+				compression: Default::default(), // read from the snapshot.
 			};
 			Cmd::Snapshot(restore_cmd)
 		} else if self.args.cmd_export_hardcoded_sync {
@@ -360,6 +383,7 @@ impl Configuration {
 				update_policy: update_policy,
 				mode: mode,
 				tracing: tracing,
+				tracing_history: self.args.arg_tracing_history,
 				fat_db: fat_db,
 				compaction: compaction,
 				vm_type: vm_type,
@@ -375,6 +399,10 @@ impl Configuration {
 				name: self.args.arg_identity,
 				custom_bootnodes: self.args.arg_bootnodes.is_some(),
 				no_periodic_snapshot: self.args.flag_no_periodic_snapshot,
+				snapshot_io_budget: self.args.arg_snapshot_io_budget.map(|mb| mb as usize * 1024 * 1024),
+				snapshot_period: self.snapshot_period()?,
+				snapshot_retention: self.snapshot_retention()?,
+				import_snapshot: self.args.arg_import_snapshot.clone().map(Into::into),
 				check_seal: !self.args.flag_no_seal_check,
 				download_old_blocks: !self.args.flag_no_ancient_blocks,
 				verifier_settings: verifier_settings,
@@ -514,6 +542,24 @@ impl Configuration {
 		} else { Ok(None) }
 	}
 
+	fn snapshot_period(&self) -> Result<u64, String> {
+		match self.args.arg_snapshot_period {
+			0 => Err("Snapshot period must be at least 1 block".into()),
+			period => Ok(period),
+		}
+	}
+
+	fn snapshot_retention(&self) -> Result<RetentionPolicy, String> {
+		if self.args.arg_snapshot_keep == 0 {
+			return Err("At least 1 snapshot must be kept".into());
+		}
+
+		Ok(RetentionPolicy {
+			keep: self.args.arg_snapshot_keep,
+			min_free_space: self.args.arg_snapshot_min_free_space * 1024 * 1024,
+		})
+	}
+
 	fn miner_options(&self) -> Result<MinerOptions, String> {
 		let is_dev_chain = self.chain()? == SpecType::Dev;
 		if is_dev_chain && self.args.flag_force_sealing && self.args.arg_reseal_min_period == 0 {
@@ -580,6 +626,9 @@ impl Configuration {
 			http_enabled: self.secretstore_http_enabled(),
 			auto_migrate_enabled: self.secretstore_auto_migrate_enabled(),
 			acl_check_contract_address: self.secretstore_acl_check_contract_address()?,
+			acl_cache_ttl: self.args.arg_secretstore_acl_cache_ttl,
+			audit_log_retention: self.args.arg_secretstore_audit_log_retention,
+			key_retirement_grace_period: self.args.arg_secretstore_key_retirement_grace_period,
 			service_contract_address: self.secretstore_service_contract_address()?,
 			service_contract_srv_gen_address: self.secretstore_service_contract_srv_gen_address()?,
 			service_contract_srv_retr_address: self.secretstore_service_contract_srv_retr_address()?,
@@ -592,6 +641,9 @@ impl Configuration {
 			port: self.args.arg_ports_shift + self.args.arg_secretstore_port,
 			http_interface: self.secretstore_http_interface(),
 			http_port: self.args.arg_ports_shift + self.args.arg_secretstore_http_port,
+			http_tls: self.secretstore_http_tls()?,
+			tls: self.secretstore_tls()?,
+			tls_required: self.args.flag_secretstore_tls_required,
 			data_path: self.directories().secretstore,
 			admin_public: self.secretstore_admin_public()?,
 		})
@@ -1008,6 +1060,23 @@ impl Configuration {
 		self.interface(&self.args.arg_secretstore_http_interface)
 	}
 
+	fn secretstore_http_tls(&self) -> Result<Option<(String, String)>, String> {
+		match (&self.args.arg_secretstore_http_tls_cert, &self.args.arg_secretstore_http_tls_key) {
+			(&Some(ref cert), &Some(ref key)) => Ok(Some((cert.clone(), key.clone()))),
+			(&None, &None) => Ok(None),
+			_ => Err("Both --secretstore-http-tls-cert and --secretstore-http-tls-key must be specified to serve Secret Store HTTP API over TLS".into()),
+		}
+	}
+
+	fn secretstore_tls(&self) -> Result<Option<(String, String, String)>, String> {
+		match (&self.args.arg_secretstore_tls_cert, &self.args.arg_secretstore_tls_key, &self.args.arg_secretstore_tls_ca) {
+			(&Some(ref cert), &Some(ref key), &Some(ref ca)) => Ok(Some((cert.clone(), key.clone(), ca.clone()))),
+			(&None, &None, &None) if self.args.flag_secretstore_tls_required => Err("--secretstore-tls-required requires --secretstore-tls-cert, --secretstore-tls-key and --secretstore-tls-ca".into()),
+			(&None, &None, &None) => Ok(None),
+			_ => Err("All of --secretstore-tls-cert, --secretstore-tls-key and --secretstore-tls-ca must be specified to connect to other Secret Store Key Servers over TLS".into()),
+		}
+	}
+
 	fn secretstore_self_secret(&self) -> Result<Option<NodeSecretKey>, String> {
 		match self.args.arg_secretstore_secret {
 			Some(ref s) if s.len() == 64 => Ok(Some(NodeSecretKey::Plain(s.parse()
@@ -1116,6 +1185,11 @@ impl Configuration {
 		::whisper::Config {
 			enabled: self.args.flag_whisper,
 			target_message_pool_size: self.args.arg_whisper_pool_size * 1024 * 1024,
+			pool_path: if self.args.flag_whisper_persist_pool {
+				Some(PathBuf::from(self.directories().base).join("whisper_pool"))
+			} else {
+				None
+			},
 		}
 	}
 }
@@ -1386,6 +1460,7 @@ mod tests {
 			},
 			mode: Default::default(),
 			tracing: Default::default(),
+			tracing_history: None,
 			compaction: Default::default(),
 			vm_type: Default::default(),
 			geth_compatibility: false,
@@ -1399,6 +1474,10 @@ mod tests {
 			custom_bootnodes: false,
 			fat_db: Default::default(),
 			no_periodic_snapshot: false,
+			snapshot_io_budget: None,
+			snapshot_period: 5000,
+			snapshot_retention: Default::default(),
+			import_snapshot: None,
 			stratum: None,
 			check_seal: true,
 			download_old_blocks: true,
@@ -1791,6 +1870,33 @@ mod tests {
 		assert_eq!(conf0.ipfs_config().hosts, None);
 	}
 
+	#[test]
+	fn should_parse_secretstore_http_tls() {
+		let plain = parse(&["parity"]);
+		let tls = parse(&["parity", "--secretstore-http-tls-cert", "ss.crt", "--secretstore-http-tls-key", "ss.key"]);
+		let cert_only = parse(&["parity", "--secretstore-http-tls-cert", "ss.crt"]);
+
+		assert_eq!(plain.secretstore_config().unwrap().http_tls, None);
+		assert_eq!(tls.secretstore_config().unwrap().http_tls, Some(("ss.crt".into(), "ss.key".into())));
+		assert!(cert_only.secretstore_config().is_err());
+	}
+
+	#[test]
+	fn should_parse_secretstore_tls() {
+		let plain = parse(&["parity"]);
+		let tls = parse(&["parity", "--secretstore-tls-cert", "node.crt", "--secretstore-tls-key", "node.key", "--secretstore-tls-ca", "ca.crt"]);
+		let required = parse(&["parity", "--secretstore-tls-cert", "node.crt", "--secretstore-tls-key", "node.key", "--secretstore-tls-ca", "ca.crt", "--secretstore-tls-required"]);
+		let no_ca = parse(&["parity", "--secretstore-tls-cert", "node.crt", "--secretstore-tls-key", "node.key"]);
+		let required_only = parse(&["parity", "--secretstore-tls-required"]);
+
+		assert_eq!(plain.secretstore_config().unwrap().tls, None);
+		assert_eq!(tls.secretstore_config().unwrap().tls, Some(("node.crt".into(), "node.key".into(), "ca.crt".into())));
+		assert!(!tls.secretstore_config().unwrap().tls_required);
+		assert!(required.secretstore_config().unwrap().tls_required);
+		assert!(no_ca.secretstore_config().is_err());
+		assert!(required_only.secretstore_config().is_err());
+	}
+
 	#[test]
 	fn allow_ips() {
 		let all = parse(&["parity", "--allow-ips", "all"]);
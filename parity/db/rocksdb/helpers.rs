@@ -14,10 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::path::Path;
-use ethcore::db::NUM_COLUMNS;
+use ethcore::db::{self, NUM_COLUMNS};
 use ethcore::client::{ClientConfig, DatabaseCompactionProfile};
-use super::kvdb_rocksdb::{CompactionProfile, DatabaseConfig};
+use super::kvdb_rocksdb::{ColumnConfig, CompactionProfile, CompactionStyle, DatabaseConfig};
 
 pub fn compaction_profile(profile: &DatabaseCompactionProfile, db_path: &Path) -> CompactionProfile {
 	match profile {
@@ -27,11 +28,46 @@ pub fn compaction_profile(profile: &DatabaseCompactionProfile, db_path: &Path) -
 	}
 }
 
+/// Options of the client database columns whose access patterns differ from the shared defaults.
+pub fn column_config(config: &DatabaseConfig) -> HashMap<u32, ColumnConfig> {
+	let column = |col: Option<u32>| col.expect("client database columns are not the default column; qed");
+	let mut columns = HashMap::new();
+
+	// trie nodes are read by random point lookups.
+	columns.insert(column(db::COL_STATE), ColumnConfig {
+		block_size: Some(4 * 1024),
+		bloom_filter_bits: Some(10),
+		write_buffer_size: Some(config.memory_budget_per_col() / 2),
+		.. ColumnConfig::default()
+	});
+	columns.insert(column(db::COL_HEADERS), ColumnConfig {
+		bloom_filter_bits: Some(10),
+		.. ColumnConfig::default()
+	});
+	columns.insert(column(db::COL_EXTRA), ColumnConfig {
+		bloom_filter_bits: Some(10),
+		.. ColumnConfig::default()
+	});
+	// bodies and traces are written once in large sequential batches and rarely read.
+	// level style columns of existing databases are switched over on open, their sorted files stay readable.
+	for col in &[db::COL_BODIES, db::COL_TRACE] {
+		columns.insert(column(*col), ColumnConfig {
+			block_size: Some(4 * config.compaction.block_size),
+			compaction_style: Some(CompactionStyle::Universal),
+			write_buffer_size: Some(config.memory_budget_per_col() / 2),
+			.. ColumnConfig::default()
+		});
+	}
+
+	columns
+}
+
 pub fn client_db_config(client_path: &Path, client_config: &ClientConfig) -> DatabaseConfig {
 	let mut client_db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
 
 	client_db_config.memory_budget = client_config.db_cache_size;
 	client_db_config.compaction = compaction_profile(&client_config.db_compaction, &client_path);
+	client_db_config.column_config = column_config(&client_db_config);
 
 	client_db_config
 }
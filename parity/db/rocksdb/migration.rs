@@ -18,7 +18,10 @@ use std::fs;
 use std::io::{Read, Write, Error as IoError, ErrorKind};
 use std::path::{Path, PathBuf};
 use std::fmt::{Display, Formatter, Error as FmtError};
-use super::migration_rocksdb::{Manager as MigrationManager, Config as MigrationConfig, ChangeColumns};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use super::migration_rocksdb::{Manager as MigrationManager, Config as MigrationConfig, ChangeColumns, MigrationProgress, ProgressFn};
 use super::kvdb_rocksdb::{CompactionProfile, DatabaseConfig};
 use ethcore::client::DatabaseCompactionProfile;
 use ethcore::{self, db};
@@ -52,6 +55,8 @@ const BLOOMS_DB_VERSION: u32 = 13;
 const BATCH_SIZE: usize = 1024;
 /// Version file name.
 const VERSION_FILE_NAME: &'static str = "db_version";
+/// Minimal interval between printed migration progress reports, in seconds.
+const PROGRESS_INTERVAL_SECS: u64 = 10;
 
 /// Migration related erorrs.
 #[derive(Debug)]
@@ -134,17 +139,41 @@ fn backup_database_path(path: &Path) -> PathBuf {
 	backup_path
 }
 
+/// Prints the progress of migrations, at most once every `PROGRESS_INTERVAL_SECS`.
+fn progress_printer() -> ProgressFn {
+	let last_printed = Mutex::new(None::<Instant>);
+	Arc::new(move |progress: &MigrationProgress| {
+		let mut last_printed = last_printed.lock();
+		if last_printed.map_or(false, |last| last.elapsed() < Duration::from_secs(PROGRESS_INTERVAL_SECS)) {
+			return;
+		}
+		*last_printed = Some(Instant::now());
+
+		let column = progress.column.map_or_else(|| "default".to_owned(), |col| col.to_string());
+		match (progress.estimated_keys, progress.eta()) {
+			(Some(total), Some(eta)) => println!("Migrating to version {}, column {}: {}/{} keys, about {} min left",
+				progress.version, column, progress.keys, total, eta.as_secs() / 60 + 1),
+			_ => println!("Migrating to version {}, column {}: {} keys", progress.version, column, progress.keys),
+		}
+	})
+}
+
 /// Default migration settings.
 pub fn default_migration_settings(compaction_profile: &CompactionProfile) -> MigrationConfig {
 	MigrationConfig {
 		batch_size: BATCH_SIZE,
 		compaction_profile: *compaction_profile,
+		progress: Some(progress_printer()),
+		dry_run: false,
 	}
 }
 
 /// Migrations on the consolidated database.
-fn consolidated_database_migrations(compaction_profile: &CompactionProfile) -> Result<MigrationManager, Error> {
-	let mut manager = MigrationManager::new(default_migration_settings(compaction_profile));
+fn consolidated_database_migrations(compaction_profile: &CompactionProfile, dry_run: bool) -> Result<MigrationManager, Error> {
+	let mut manager = MigrationManager::new(MigrationConfig {
+		dry_run: dry_run,
+		..default_migration_settings(compaction_profile)
+	});
 	manager.add_migration(TO_V11).map_err(|_| Error::MigrationImpossible)?;
 	manager.add_migration(TO_V12).map_err(|_| Error::MigrationImpossible)?;
 	Ok(manager)
@@ -209,7 +238,7 @@ pub fn migrate(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> R
 	// Further migrations
 	if version < CURRENT_VERSION && exists(&db_path) {
 		println!("Migrating database from version {} to {}", version, CURRENT_VERSION);
-		migrate_database(version, &db_path, consolidated_database_migrations(&compaction_profile)?)?;
+		migrate_database(version, &db_path, consolidated_database_migrations(&compaction_profile, false)?)?;
 
 		if version < BLOOMS_DB_VERSION {
 			println!("Migrating blooms to blooms-db...");
@@ -218,6 +247,7 @@ pub fn migrate(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> R
 				memory_budget: None,
 				compaction: compaction_profile,
 				columns: db::NUM_COLUMNS,
+				.. DatabaseConfig::default()
 			};
 
 			migrate_blooms(&db_path, &db_config).map_err(Error::BloomsDB)?;
@@ -229,3 +259,32 @@ pub fn migrate(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> R
 	// update version file.
 	update_version(path)
 }
+
+/// Walks the database through the pending migrations without writing anything,
+/// checking that it can be migrated.
+pub fn check_migration(path: &Path, compaction_profile: &DatabaseCompactionProfile) -> Result<(), Error> {
+	let compaction_profile = helpers::compaction_profile(&compaction_profile, path);
+	let version = current_version(path)?;
+	if version > CURRENT_VERSION {
+		return Err(Error::FutureDBVersion);
+	}
+
+	let db_path = consolidated_database_path(path);
+	if version == CURRENT_VERSION || !exists(&db_path) {
+		println!("Database is at version {}, no migration needed", CURRENT_VERSION);
+		return Ok(())
+	}
+
+	println!("Checking migration of database from version {} to {}", version, CURRENT_VERSION);
+	let mut migrations = consolidated_database_migrations(&compaction_profile, true)?;
+	if migrations.is_needed(version) {
+		migrations.execute(&db_path, version)?;
+	}
+
+	if version < BLOOMS_DB_VERSION {
+		println!("Blooms would be migrated to blooms-db");
+	}
+
+	println!("Database can be migrated");
+	Ok(())
+}
@@ -19,7 +19,7 @@ extern crate migration_rocksdb;
 
 use std::{io, fs};
 use std::sync::Arc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use blooms_db;
 use ethcore::{BlockChainDBHandler, BlockChainDB};
 use ethcore::db::NUM_COLUMNS;
@@ -33,12 +33,13 @@ mod blooms;
 mod migration;
 mod helpers;
 
-pub use self::migration::migrate;
+pub use self::migration::{migrate, check_migration};
 
 struct AppDB {
 	key_value: Arc<KeyValueDB>,
 	blooms: blooms_db::Database,
 	trace_blooms: blooms_db::Database,
+	path: PathBuf,
 }
 
 impl BlockChainDB for AppDB {
@@ -53,6 +54,10 @@ impl BlockChainDB for AppDB {
 	fn trace_blooms(&self) -> &blooms_db::Database {
 		&self.trace_blooms
 	}
+
+	fn path(&self) -> Option<&Path> {
+		Some(&self.path)
+	}
 }
 
 /// Open a secret store DB using the given secret store data path. The DB path is one level beneath the data path.
@@ -63,7 +68,8 @@ pub fn open_secretstore_db(data_path: &str) -> Result<Arc<KeyValueDB>, String> {
 	let mut db_path = PathBuf::from(data_path);
 	db_path.push("db");
 	let db_path = db_path.to_str().ok_or_else(|| "Invalid secretstore path".to_string())?;
-	Ok(Arc::new(Database::open_default(&db_path).map_err(|e| format!("Error opening database: {:?}", e))?))
+	let db_config = DatabaseConfig::with_columns(::ethcore_secretstore::NUM_DB_COLUMNS);
+	Ok(Arc::new(Database::open(&db_config, &db_path).map_err(|e| format!("Error opening database: {:?}", e))?))
 }
 
 /// Create a restoration db handler using the config generated by `client_path` and `client_config`.
@@ -89,11 +95,12 @@ pub fn restoration_db_handler(client_path: &Path, client_config: &ClientConfig)
 pub fn open_db(client_path: &str, cache_config: &CacheConfig, compaction: &DatabaseCompactionProfile) -> io::Result<Arc<BlockChainDB>> {
 	let path = Path::new(client_path);
 
-	let db_config = DatabaseConfig {
+	let mut db_config = DatabaseConfig {
 		memory_budget: Some(cache_config.blockchain() as usize * 1024 * 1024),
 		compaction: helpers::compaction_profile(&compaction, path),
 		.. DatabaseConfig::with_columns(NUM_COLUMNS)
 	};
+	db_config.column_config = helpers::column_config(&db_config);
 
 	open_database(client_path, &db_config)
 }
@@ -110,6 +117,7 @@ pub fn open_database(client_path: &str, config: &DatabaseConfig) -> io::Result<A
 		key_value: Arc::new(Database::open(&config, client_path)?),
 		blooms: blooms_db::Database::open(blooms_path)?,
 		trace_blooms: blooms_db::Database::open(trace_blooms_path)?,
+		path: path.to_path_buf(),
 	};
 
 	Ok(Arc::new(db))
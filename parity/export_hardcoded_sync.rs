@@ -81,6 +81,7 @@ pub fn execute(cmd: ExportHsyncCmd) -> Result<String, String> {
 		verify_full: true,
 		check_seal: true,
 		no_hardcoded_sync: true,
+		header_retention: light_client::DEFAULT_HEADER_RETENTION,
 	};
 
 	config.queue.max_mem_use = cmd.cache_config.queue() as usize * 1024 * 1024;
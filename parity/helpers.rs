@@ -22,6 +22,7 @@ use ethereum_types::{U256, clean_0x, Address};
 use journaldb::Algorithm;
 use ethcore::client::{Mode, BlockId, VMType, DatabaseCompactionProfile, ClientConfig, VerifierType};
 use ethcore::miner::{PendingSet, Penalization};
+use ethcore::snapshot::Compression;
 use miner::pool::PrioritizationStrategy;
 use cache::CacheConfig;
 use dir::DatabaseDirectories;
@@ -97,6 +98,15 @@ pub fn to_pending_set(s: &str) -> Result<PendingSet, String> {
 	}
 }
 
+pub fn to_snapshot_compression(s: &str) -> Result<Compression, String> {
+	match s {
+		"snappy" => Ok(Compression::Snappy),
+		"zstd" => Ok(Compression::Zstd),
+		"none" => Ok(Compression::None),
+		other => Err(format!("Invalid snapshot compression: {}", other)),
+	}
+}
+
 pub fn to_queue_strategy(s: &str) -> Result<PrioritizationStrategy, String> {
 	match s {
 		"gas_price" => Ok(PrioritizationStrategy::GasPriceOnly),
@@ -329,8 +339,9 @@ mod tests {
 	use ethereum_types::U256;
 	use ethcore::client::{Mode, BlockId};
 	use ethcore::miner::PendingSet;
+	use ethcore::snapshot::Compression;
 	use ethkey::Password;
-	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes, password_from_file};
+	use super::{to_duration, to_mode, to_block_id, to_u256, to_pending_set, to_snapshot_compression, to_address, to_addresses, to_price, geth_ipc_path, to_bootnodes, password_from_file};
 
 	#[test]
 	fn test_to_duration() {
@@ -374,6 +385,14 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_to_snapshot_compression() {
+		assert_eq!(to_snapshot_compression("snappy").unwrap(), Compression::Snappy);
+		assert_eq!(to_snapshot_compression("zstd").unwrap(), Compression::Zstd);
+		assert_eq!(to_snapshot_compression("none").unwrap(), Compression::None);
+		assert!(to_snapshot_compression("gzip").is_err());
+	}
+
 	#[test]
 	fn test_to_u256() {
 		assert_eq!(to_u256("0").unwrap(), U256::from(0));
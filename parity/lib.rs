@@ -99,6 +99,7 @@ mod light_helpers;
 mod modules;
 mod params;
 mod presale;
+mod reload;
 mod rpc;
 mod rpc_apis;
 mod run;
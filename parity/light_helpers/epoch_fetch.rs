@@ -24,10 +24,10 @@ use ethcore::receipt::Receipt;
 use sync::LightSync;
 
 use futures::{future, Future};
-use futures::future::Either;
+use futures::future::{Either, Loop};
 
 use light::client::fetch::ChainDataFetcher;
-use light::on_demand::{request, OnDemand};
+use light::on_demand::{request, OnDemand, Error as OnDemandError};
 
 use parking_lot::RwLock;
 use ethereum_types::H256;
@@ -46,22 +46,28 @@ pub struct EpochFetch {
 
 impl EpochFetch {
 	fn request<T>(&self, req: T) -> BoxFuture<T::Out, &'static str>
-		where T: Send + request::RequestAdapter + 'static, T::Out: Send + 'static
+		where T: Clone + Send + request::RequestAdapter + 'static, T::Out: Send + 'static
 	{
-		Box::new(match self.sync.read().upgrade() {
+		let (sync, on_demand) = (self.sync.clone(), self.on_demand.clone());
+
+		// import can't proceed without the data, so keep trying until served.
+		Box::new(future::loop_fn(req, move |req| match sync.read().upgrade() {
 			Some(sync) => {
-				let on_demand = &self.on_demand;
-				let maybe_future = sync.with_context(move |ctx| {
-					on_demand.request(ctx, req).expect(ALL_VALID_BACKREFS)
+				let maybe_future = sync.with_context(|ctx| {
+					on_demand.request(ctx, req.clone()).expect(ALL_VALID_BACKREFS)
 				});
 
 				match maybe_future {
-					Some(x) => Either::A(x.map_err(|_| "Request canceled")),
+					Some(x) => Either::A(x.then(move |res| match res {
+						Ok(out) => Ok(Loop::Break(out)),
+						Err(OnDemandError::Exhausted { .. }) => Ok(Loop::Continue(req)),
+						Err(OnDemandError::Canceled) => Err("Request canceled"),
+					})),
 					None => Either::B(future::err("Unable to access network.")),
 				}
 			}
 			None => Either::B(future::err("Unable to access network")),
-		})
+		}))
 	}
 }
 
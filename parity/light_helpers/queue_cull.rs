@@ -19,7 +19,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use ethcore::client::ClientIoMessage;
+use ethcore::client::{BlockId, ClientIoMessage};
 use sync::LightSync;
 use io::{IoContext, IoHandler, TimerToken};
 
@@ -40,7 +40,11 @@ const TIMEOUT: Duration = Duration::from_secs(60 * 10);
 // But make each attempt last only 9 minutes
 const PURGE_TIMEOUT: Duration = Duration::from_secs(60 * 9);
 
-/// Periodically culls the transaction queue of mined transactions.
+// Culled transactions not found in the chain within an hour are considered dropped.
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically culls the transaction queue of mined transactions
+/// and checks whether the culled ones were actually included in the chain.
 pub struct QueueCull<T> {
 	/// A handle to the client, for getting the latest block header.
 	pub client: Arc<T>,
@@ -63,25 +67,29 @@ impl<T: LightChainClient + 'static> IoHandler<ClientIoMessage> for QueueCull<T>
 		if timer != TOKEN { return }
 
 		let senders = self.txq.read().queued_senders();
-		if senders.is_empty() { return }
+		let (client, sync, on_demand, txq) = (self.client.clone(), self.sync.clone(), self.on_demand.clone(), self.txq.clone());
+		if senders.is_empty() {
+			self.remote.spawn_with_timeout(move |_| check_inclusion(client, sync, on_demand, txq), PURGE_TIMEOUT, || {});
+			return
+		}
 
-		let (sync, on_demand, txq) = (self.sync.clone(), self.on_demand.clone(), self.txq.clone());
 		let best_header = self.client.best_block_header();
 		let start_nonce = self.client.engine().account_start_nonce(best_header.number());
 
 		info!(target: "cull", "Attempting to cull queued transactions from {} senders.", senders.len());
 		self.remote.spawn_with_timeout(move |_| {
-			let maybe_fetching = sync.with_context(move |ctx| {
+			let (sync_ref, on_demand_ref, txq_ref) = (sync.clone(), on_demand.clone(), txq.clone());
+			let maybe_fetching = sync_ref.with_context(move |ctx| {
 				// fetch the nonce of each sender in the queue.
 				let nonce_reqs = senders.iter()
 					.map(|&address| request::Account { header: best_header.clone().into(), address: address })
 					.collect::<Vec<_>>();
 
 				// when they come in, update each sender to the new nonce.
-				on_demand.request(ctx, nonce_reqs)
+				on_demand_ref.request(ctx, nonce_reqs)
 					.expect("No back-references; therefore all back-references are valid; qed")
 					.map(move |accs| {
-						let txq = txq.write();
+						let txq = txq_ref.write();
 						let _ = accs.into_iter()
 							.map(|maybe_acc| maybe_acc.map_or(start_nonce, |acc| acc.nonce))
 							.zip(senders)
@@ -94,7 +102,7 @@ impl<T: LightChainClient + 'static> IoHandler<ClientIoMessage> for QueueCull<T>
 			});
 
 			match maybe_fetching {
-				Some(fut) => future::Either::A(fut),
+				Some(fut) => future::Either::A(fut.and_then(move |_| check_inclusion(client, sync, on_demand, txq))),
 				None => {
 					debug!(target: "cull", "Unable to acquire network context; qed");
 					future::Either::B(future::ok(()))
@@ -103,3 +111,41 @@ impl<T: LightChainClient + 'static> IoHandler<ClientIoMessage> for QueueCull<T>
 		}, PURGE_TIMEOUT, || {})
 	}
 }
+
+// Look the culled transactions up in the chain, marking the ones found in
+// the canonical chain as mined and the ones missing for too long as dropped.
+fn check_inclusion<T: LightChainClient + 'static>(
+	client: Arc<T>,
+	sync: Arc<LightSync>,
+	on_demand: Arc<OnDemand>,
+	txq: Arc<RwLock<TransactionQueue>>,
+) -> Box<Future<Item = (), Error = ()> + Send> {
+	txq.write().drop_unconfirmed(INCLUSION_TIMEOUT);
+
+	let unconfirmed = txq.read().unconfirmed_transactions();
+	if unconfirmed.is_empty() { return Box::new(future::ok(())) }
+
+	trace!(target: "cull", "Checking inclusion of {} culled transactions.", unconfirmed.len());
+	let maybe_fetching = sync.with_context(move |ctx| {
+		unconfirmed.into_iter().map(|hash| {
+			let (client, txq) = (client.clone(), txq.clone());
+			on_demand.request(ctx, request::TransactionIndex(hash.into()))
+				.expect("No back-references; therefore all back-references are valid; qed")
+				.then(move |res| -> Result<(), ()> {
+					match res {
+						// only trust indices which are in our view of the canonical chain.
+						Ok(index) => if client.block_hash(BlockId::Number(index.num)) == Some(index.hash) {
+							txq.write().mark_mined(&hash, index.num, index.hash, index.index);
+						},
+						Err(_) => debug!(target: "cull", "Unable to fetch index of transaction {}", hash),
+					}
+					Ok(())
+				})
+		}).collect::<Vec<_>>()
+	});
+
+	match maybe_fetching {
+		Some(lookups) => Box::new(future::join_all(lookups).map(|_| ())),
+		None => Box::new(future::ok(())),
+	}
+}
@@ -0,0 +1,59 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reload of files, which are read at startup, on SIGHUP.
+
+#[cfg(not(windows))]
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+#[cfg(not(windows))]
+use std::thread;
+#[cfg(not(windows))]
+use std::time::Duration;
+
+/// Set by the SIGHUP handler, cleared by the reload thread.
+#[cfg(not(windows))]
+static RELOAD_REQUESTED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Interval of checking whether SIGHUP has been received, in milliseconds.
+#[cfg(not(windows))]
+const RELOAD_CHECK_INTERVAL_MS: u64 = 500;
+
+/// Call `reload` from a dedicated thread after every SIGHUP. The thread stops once `reload` returns false.
+#[cfg(not(windows))]
+pub fn on_reload<F>(reload: F) -> Result<(), String> where F: Fn() -> bool + Send + 'static {
+	extern crate libc;
+
+	extern "C" fn request_reload(_: libc::c_int) {
+		RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+	}
+
+	unsafe {
+		libc::signal(libc::SIGHUP, request_reload as libc::sighandler_t);
+	}
+
+	thread::Builder::new().name("Reload".into()).spawn(move || loop {
+		thread::sleep(Duration::from_millis(RELOAD_CHECK_INTERVAL_MS));
+		if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) && !reload() {
+			break;
+		}
+	}).map(|_| ()).map_err(|e| format!("Couldn't start reload thread: {}", e))
+}
+
+/// There's no SIGHUP on Windows, so files are only read at startup.
+#[cfg(windows)]
+pub fn on_reload<F>(_reload: F) -> Result<(), String> where F: Fn() -> bool + Send + 'static {
+	Ok(())
+}
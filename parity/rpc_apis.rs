@@ -351,7 +351,11 @@ impl FullDependencies {
 						let mut rpc = MetaIoHandler::default();
 						let apis = ApiSet::List(apis.clone()).retain(ApiSet::PubSub).list_apis();
 						self.extend_api(&mut rpc, &apis, true);
-						handler.extend_with(PubSubClient::new(rpc, self.remote.clone()).to_delegate());
+						let client = PubSubClient::new(rpc, self.remote.clone());
+						if let Some(h) = client.traces_handler().upgrade() {
+							self.client.add_notify(h);
+						}
+						handler.extend_with(client.to_delegate());
 					}
 				},
 				Api::ParityAccounts => {
@@ -363,6 +367,7 @@ impl FullDependencies {
 						&self.miner,
 						&self.updater,
 						&self.net_service,
+						&self.snapshot,
 						self.fetch.clone(),
 						self.pool.clone(),
 					).to_delegate())
@@ -508,6 +513,7 @@ impl<C: LightChainClient + 'static> LightDependencies<C> {
 						self.cache.clone(),
 						self.gas_price_percentile,
 						self.poll_lifetime,
+						LogsLimits::default(),
 					);
 					handler.extend_with(Eth::to_delegate(client.clone()));
 
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::any::Any;
+use std::path::PathBuf;
 use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 use std::thread;
@@ -25,7 +26,7 @@ use ethcore::account_provider::{AccountProvider, AccountProviderSettings};
 use ethcore::client::{BlockId, CallContract, Client, Mode, DatabaseCompactionProfile, VMType, BlockChainClient, BlockInfo};
 use ethcore::ethstore::ethkey;
 use ethcore::miner::{stratum, Miner, MinerService, MinerOptions};
-use ethcore::snapshot;
+use ethcore::snapshot::{self, SnapshotService as _SnapshotService};
 use ethcore::spec::{SpecParams, OptimizeFor};
 use ethcore::verification::queue::VerifierSettings;
 use ethcore_logger::{Config as LogConfig, RotatingLogger};
@@ -61,14 +62,12 @@ use modules;
 use registrar::{RegistrarClient, Asynchronous};
 use rpc;
 use rpc_apis;
+use reload;
 use secretstore;
 use signer;
 use db;
 use ethkey::Password;
 
-// how often to take periodic snapshots.
-const SNAPSHOT_PERIOD: u64 = 5000;
-
 // how many blocks to wait before starting a periodic snapshot.
 const SNAPSHOT_HISTORY: u64 = 100;
 
@@ -106,6 +105,7 @@ pub struct RunCmd {
 	pub update_policy: UpdatePolicy,
 	pub mode: Option<Mode>,
 	pub tracing: Switch,
+	pub tracing_history: Option<u64>,
 	pub fat_db: Switch,
 	pub compaction: DatabaseCompactionProfile,
 	pub vm_type: VMType,
@@ -120,6 +120,10 @@ pub struct RunCmd {
 	pub custom_bootnodes: bool,
 	pub stratum: Option<stratum::Options>,
 	pub no_periodic_snapshot: bool,
+	pub snapshot_io_budget: Option<usize>,
+	pub snapshot_period: u64,
+	pub snapshot_retention: snapshot::RetentionPolicy,
+	pub import_snapshot: Option<PathBuf>,
 	pub check_seal: bool,
 	pub download_old_blocks: bool,
 	pub verifier_settings: VerifierSettings,
@@ -152,6 +156,20 @@ impl ::local_store::NodeInfo for FullNodeInfo {
 	}
 }
 
+// node info fetcher for the light client's local store.
+struct LightNodeInfo {
+	txq: Option<Arc<::parking_lot::RwLock<::light::TransactionQueue>>>,
+}
+
+impl ::local_store::NodeInfo for LightNodeInfo {
+	fn pending_transactions(&self) -> Vec<::transaction::PendingTransaction> {
+		match self.txq.as_ref() {
+			Some(txq) => txq.read().all_transactions(),
+			None => Vec::new(),
+		}
+	}
+}
+
 type LightClient = ::light::client::Client<::light_helpers::EpochFetch>;
 
 // helper for light execution.
@@ -200,6 +218,7 @@ fn execute_light_impl(cmd: RunCmd, logger: Arc<RotatingLogger>) -> Result<Runnin
 		verify_full: true,
 		check_seal: cmd.check_seal,
 		no_hardcoded_sync: cmd.no_hardcoded_sync,
+		header_retention: light_client::DEFAULT_HEADER_RETENTION,
 	};
 
 	config.queue.max_mem_use = cmd.cache_config.queue() as usize * 1024 * 1024;
@@ -219,10 +238,48 @@ fn execute_light_impl(cmd: RunCmd, logger: Arc<RotatingLogger>) -> Result<Runnin
 						 &cmd.cache_config,
 						 &cmd.compaction).map_err(|e| format!("Failed to open database {:?}", e))?;
 
-	let service = light_client::Service::start(config, &spec, fetch, db, cache.clone())
+	let service = light_client::Service::start(config, &spec, fetch, db.clone(), cache.clone())
 		.map_err(|e| format!("Error starting light client: {}", e))?;
 	let client = service.client().clone();
 	let txq = Arc::new(RwLock::new(::light::transaction_queue::TransactionQueue::default()));
+
+	// initialize the local node information store.
+	let store = {
+		let node_info = LightNodeInfo {
+			txq: match cmd.no_persistent_txqueue {
+				true => None,
+				false => Some(txq.clone()),
+			}
+		};
+
+		let store = ::local_store::create(db.key_value().clone(), ::ethcore::db::COL_NODE_INFO, node_info);
+
+		if cmd.no_persistent_txqueue {
+			info!("Running without a persistent transaction queue.");
+
+			if let Err(e) = store.clear() {
+				warn!("Error clearing persistent transaction queue: {}", e);
+			}
+		}
+
+		// re-queue pending transactions.
+		match store.pending_transactions() {
+			Ok(pending) => {
+				let mut txq = txq.write();
+				for pending_tx in pending {
+					if let Err(e) = txq.import(pending_tx) {
+						warn!("Error importing saved transaction: {}", e)
+					}
+				}
+			}
+			Err(e) => warn!("Error loading cached pending transactions from disk: {}", e),
+		}
+
+		Arc::new(store)
+	};
+
+	// register it as an IO service to update periodically.
+	service.register_handler(store).map_err(|_| "Unable to register local store handler".to_owned())?;
 	let provider = ::light::provider::LightProvider::new(client.clone(), txq.clone());
 
 	// start network.
@@ -234,7 +291,7 @@ fn execute_light_impl(cmd: RunCmd, logger: Arc<RotatingLogger>) -> Result<Runnin
 
 	let mut attached_protos = Vec::new();
 	let whisper_factory = if cmd.whisper.enabled {
-		let whisper_factory = ::whisper::setup(cmd.whisper.target_message_pool_size, &mut attached_protos)
+		let whisper_factory = ::whisper::setup(cmd.whisper.target_message_pool_size, cmd.whisper.pool_path.clone(), &mut attached_protos)
 			.map_err(|e| format!("Failed to initialize whisper: {}", e))?;
 		whisper_factory
 	} else {
@@ -448,6 +505,9 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		} else if algorithm != Algorithm::OverlayRecent {
 			warn!("Warning: Warp Sync is disabled because of non-default pruning mode.");
 			warp_sync = false;
+		} else if cmd.import_snapshot.is_some() {
+			info!("Warp Sync is disabled because a snapshot is imported from disk.");
+			warp_sync = false;
 		}
 	}
 	sync_config.warp_sync = match (warp_sync, cmd.warp_barrier) {
@@ -457,6 +517,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	};
 	sync_config.download_old_blocks = cmd.download_old_blocks;
 	sync_config.serve_light = cmd.serve_light;
+	sync_config.max_known_transactions = cmd.miner_options.pool_limits.max_count;
 
 	let passwords = passwords_from_files(&cmd.acc_conf.password_files)?;
 
@@ -530,6 +591,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	);
 
 	client_config.queue.verifier_settings = cmd.verifier_settings;
+	client_config.tracing.history = cmd.tracing_history;
 	client_config.transaction_verification_queue_size = ::std::cmp::max(2048, txpool_size / 4);
 
 	// set up bootnodes
@@ -624,7 +686,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 	let mut attached_protos = Vec::new();
 
 	let whisper_factory = if cmd.whisper.enabled {
-		let whisper_factory = ::whisper::setup(cmd.whisper.target_message_pool_size, &mut attached_protos)
+		let whisper_factory = ::whisper::setup(cmd.whisper.target_message_pool_size, cmd.whisper.pool_path.clone(), &mut attached_protos)
 			.map_err(|e| format!("Failed to initialize whisper: {}", e))?;
 
 		whisper_factory
@@ -632,6 +694,15 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		None
 	};
 
+	// light protocol data provider, building CHTs as the chain progresses.
+	// the light chain column is otherwise unused by a full node.
+	let light_provider = Arc::new(::light::provider::ChtProvider::new(
+		client.clone(),
+		service.db().key_value().clone(),
+		::ethcore::db::COL_LIGHT_CHAIN,
+	));
+	service.add_notify(light_provider.clone());
+
 	// create sync object
 	let (sync_provider, manage_network, chain_notify) = modules::sync(
 		sync_config,
@@ -639,7 +710,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		client.clone(),
 		snapshot_service.clone(),
 		private_tx_service.clone(),
-		client.clone(),
+		light_provider,
 		&cmd.logger_config,
 		attached_protos,
 		connection_filter.clone().map(|f| f as Arc<::sync::ConnectionFilter + 'static>),
@@ -742,7 +813,19 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		account_provider: account_provider,
 		accounts_passwords: &passwords,
 	};
-	let secretstore_key_server = secretstore::start(cmd.secretstore_conf.clone(), secretstore_deps)?;
+	let secretstore_key_server = secretstore::start(cmd.secretstore_conf.clone(), secretstore_deps)?.map(Arc::new);
+	if let Some(ref key_server) = secretstore_key_server {
+		let key_server = Arc::downgrade(key_server);
+		reload::on_reload(move || match key_server.upgrade() {
+			Some(key_server) => {
+				if let Err(e) = key_server.reload_tls() {
+					warn!("{}", e);
+				}
+				true
+			},
+			None => false,
+		})?;
+	}
 
 	// the ipfs server
 	let ipfs_server = ipfs::start_server(cmd.ipfs_conf.clone(), client.clone())?;
@@ -777,6 +860,14 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 		let _ = user_defaults.save(&user_defaults_path);	// discard failures - there's nothing we can do
 	});
 
+	service.snapshot_service().throttle().set_budget(cmd.snapshot_io_budget);
+	service.snapshot_service().set_retention(cmd.snapshot_retention);
+
+	if let Some(path) = cmd.import_snapshot {
+		info!("Importing snapshot from {}", path.display());
+		service.snapshot_service().restore_from_path(path);
+	}
+
 	// the watcher must be kept alive.
 	let watcher = match cmd.no_periodic_snapshot {
 		true => None,
@@ -787,7 +878,7 @@ fn execute_impl<Cr, Rr>(cmd: RunCmd, logger: Arc<RotatingLogger>, on_client_rq:
 				service.client(),
 				move || is_major_importing(Some(sync.status().state), client.queue_info()),
 				service.io().channel(),
-				SNAPSHOT_PERIOD,
+				cmd.snapshot_period,
 				SNAPSHOT_HISTORY,
 			));
 
@@ -942,6 +1033,7 @@ fn prepare_account_provider(spec: &SpecType, dirs: &Directories, data_dir: &str,
 	let account_settings = AccountProviderSettings {
 		enable_hardware_wallets: cfg.enable_hardware_wallets,
 		hardware_wallet_classic_key: spec == &SpecType::Classic,
+		hardware_wallet_paths: Vec::new(),
 		unlock_keep_secret: cfg.enable_fast_unlock,
 		blacklisted_accounts: 	match *spec {
 			SpecType::Morden | SpecType::Ropsten | SpecType::Kovan | SpecType::Sokol | SpecType::Dev => vec![],
@@ -54,6 +54,12 @@ pub struct Configuration {
 	pub auto_migrate_enabled: bool,
 	/// ACL check contract address.
 	pub acl_check_contract_address: Option<ContractAddress>,
+	/// Seconds ACL check results are cached for, 0 to disable caching.
+	pub acl_cache_ttl: u64,
+	/// Max number of entries in the audit log.
+	pub audit_log_retention: usize,
+	/// Seconds shares of retired keys are kept for before erasing.
+	pub key_retirement_grace_period: u64,
 	/// Service contract address.
 	pub service_contract_address: Option<ContractAddress>,
 	/// Server key generation service contract address.
@@ -78,6 +84,13 @@ pub struct Configuration {
 	pub http_interface: String,
 	/// Port to listen to
 	pub http_port: u16,
+	/// Paths to the TLS certificate and key of the HTTP listener. If None, HTTP API is served over plain HTTP.
+	pub http_tls: Option<(String, String)>,
+	/// Paths to the TLS certificate, key and authority certificates of connections to other key servers.
+	/// If None, connections are plaintext.
+	pub tls: Option<(String, String, String)>,
+	/// Refuse plaintext connections from other key servers.
+	pub tls_required: bool,
 	/// Data directory path for secret store
 	pub data_path: String,
 	/// Administrator public key.
@@ -110,12 +123,18 @@ mod server {
 		pub fn new(_conf: Configuration, _deps: Dependencies) -> Result<Self, String> {
 			Ok(KeyServer)
 		}
+
+		/// Read TLS certificates again
+		pub fn reload_tls(&self) -> Result<(), String> {
+			Ok(())
+		}
 	}
 }
 
 #[cfg(feature = "secretstore")]
 mod server {
 	use std::sync::Arc;
+	use std::time::Duration;
 	use ethcore_secretstore;
 	use ethkey::KeyPair;
 	use ansi_term::Colour::{Red, White};
@@ -131,7 +150,7 @@ mod server {
 
 	/// Key server
 	pub struct KeyServer {
-		_key_server: Box<ethcore_secretstore::KeyServer>,
+		key_server: Box<ethcore_secretstore::KeyServer>,
 	}
 
 	impl KeyServer {
@@ -172,12 +191,21 @@ mod server {
 					address: conf.http_interface.clone(),
 					port: conf.http_port,
 				}) } else { None },
+				listener_tls: conf.http_tls.map(|(cert_path, key_path)| ethcore_secretstore::TlsConfiguration {
+					cert_path: cert_path.into(),
+					key_path: key_path.into(),
+				}),
 				service_contract_address: conf.service_contract_address.map(into_service_contract_address),
 				service_contract_srv_gen_address: conf.service_contract_srv_gen_address.map(into_service_contract_address),
 				service_contract_srv_retr_address: conf.service_contract_srv_retr_address.map(into_service_contract_address),
 				service_contract_doc_store_address: conf.service_contract_doc_store_address.map(into_service_contract_address),
 				service_contract_doc_sretr_address: conf.service_contract_doc_sretr_address.map(into_service_contract_address),
 				acl_check_contract_address: conf.acl_check_contract_address.map(into_service_contract_address),
+				acl_cache_ttl: match conf.acl_cache_ttl {
+					0 => None,
+					secs => Some(Duration::from_secs(secs)),
+				},
+				audit_log_retention: conf.audit_log_retention,
 				cluster_config: ethcore_secretstore::ClusterConfiguration {
 					threads: 4,
 					listener_address: ethcore_secretstore::NodeAddress {
@@ -192,6 +220,15 @@ mod server {
 					allow_connecting_to_higher_nodes: true,
 					admin_public: conf.admin_public,
 					auto_migrate_enabled: conf.auto_migrate_enabled,
+					key_retirement_grace_period: Duration::from_secs(conf.key_retirement_grace_period),
+					tls: conf.tls.map(|(cert_path, key_path, ca_path)| ethcore_secretstore::ClusterTlsConfiguration {
+						node: ethcore_secretstore::TlsConfiguration {
+							cert_path: cert_path.into(),
+							key_path: key_path.into(),
+						},
+						ca_path: ca_path.into(),
+						required: conf.tls_required,
+					}),
 				},
 			};
 
@@ -202,9 +239,14 @@ mod server {
 				.map_err(|e| format!("Error starting KeyServer {}: {}", key_server_name, e))?;
 
 			Ok(KeyServer {
-				_key_server: key_server,
+				key_server: key_server,
 			})
 		}
+
+		/// Read TLS certificates of the HTTP listener and of the connections to other key servers again
+		pub fn reload_tls(&self) -> Result<(), String> {
+			self.key_server.reload_tls().map_err(|e| format!("Error reloading SecretStore TLS certificates: {}", e))
+		}
 	}
 }
 
@@ -219,6 +261,9 @@ impl Default for Configuration {
 			http_enabled: true,
 			auto_migrate_enabled: true,
 			acl_check_contract_address: Some(ContractAddress::Registry),
+			acl_cache_ttl: 60,
+			audit_log_retention: 1_000_000,
+			key_retirement_grace_period: 86400,
 			service_contract_address: None,
 			service_contract_srv_gen_address: None,
 			service_contract_srv_retr_address: None,
@@ -232,6 +277,9 @@ impl Default for Configuration {
 			port: 8083,
 			http_interface: "127.0.0.1".to_owned(),
 			http_port: 8082,
+			http_tls: None,
+			tls: None,
+			tls_required: false,
 			data_path: replace_home(&data_dir, "$BASE/secretstore"),
 		}
 	}
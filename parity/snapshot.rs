@@ -20,10 +20,9 @@ use std::time::Duration;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use hash::keccak;
 use ethcore::account_provider::AccountProvider;
-use ethcore::snapshot::{Progress, RestorationStatus, SnapshotService as SS};
-use ethcore::snapshot::io::{SnapshotReader, PackedReader, PackedWriter};
+use ethcore::snapshot::{Compression, Progress, RestorationStatus, SnapshotService as SS};
+use ethcore::snapshot::io::PackedWriter;
 use ethcore::snapshot::service::Service as SnapshotService;
 use ethcore::client::{Mode, DatabaseCompactionProfile, VMType};
 use ethcore::miner::Miner;
@@ -62,72 +61,20 @@ pub struct SnapshotCommand {
 	pub file_path: Option<String>,
 	pub kind: Kind,
 	pub block_at: BlockId,
+	pub compression: Compression,
 }
 
-// helper for reading chunks from arbitrary reader and feeding them into the
-// service.
-fn restore_using<R: SnapshotReader>(snapshot: Arc<SnapshotService>, reader: &R, recover: bool) -> Result<(), String> {
-	let manifest = reader.manifest();
-
-	info!("Restoring to block #{} (0x{:?})", manifest.block_number, manifest.block_hash);
-
-	snapshot.init_restore(manifest.clone(), recover).map_err(|e| {
-		format!("Failed to begin restoration: {}", e)
-	})?;
-
-	let (num_state, num_blocks) = (manifest.state_hashes.len(), manifest.block_hashes.len());
-
-	let informant_handle = snapshot.clone();
+// helper for logging restoration progress until it is done.
+fn spawn_informant(snapshot: Arc<SnapshotService>) {
 	::std::thread::spawn(move || {
- 		while let RestorationStatus::Ongoing { state_chunks_done, block_chunks_done, .. } = informant_handle.status() {
- 			info!("Processed {}/{} state chunks and {}/{} block chunks.",
- 				state_chunks_done, num_state, block_chunks_done, num_blocks);
- 			::std::thread::sleep(Duration::from_secs(5));
- 		}
- 	});
-
- 	info!("Restoring state");
- 	for &state_hash in &manifest.state_hashes {
- 		if snapshot.status() == RestorationStatus::Failed {
- 			return Err("Restoration failed".into());
- 		}
-
- 		let chunk = reader.chunk(state_hash)
-			.map_err(|e| format!("Encountered error while reading chunk {:?}: {}", state_hash, e))?;
-
-		let hash = keccak(&chunk);
-		if hash != state_hash {
-			return Err(format!("Mismatched chunk hash. Expected {:?}, got {:?}", state_hash, hash));
-		}
-
- 		snapshot.feed_state_chunk(state_hash, &chunk);
- 	}
-
-	info!("Restoring blocks");
-	for &block_hash in &manifest.block_hashes {
-		if snapshot.status() == RestorationStatus::Failed {
-			return Err("Restoration failed".into());
-		}
+		::std::thread::sleep(Duration::from_secs(5));
 
- 		let chunk = reader.chunk(block_hash)
-			.map_err(|e| format!("Encountered error while reading chunk {:?}: {}", block_hash, e))?;
-
-		let hash = keccak(&chunk);
-		if hash != block_hash {
-			return Err(format!("Mismatched chunk hash. Expected {:?}, got {:?}", block_hash, hash));
-		}
-		snapshot.feed_block_chunk(block_hash, &chunk);
-	}
-
-	match snapshot.status() {
-		RestorationStatus::Ongoing { .. } => Err("Snapshot file is incomplete and missing chunks.".into()),
-		RestorationStatus::Initializing { .. } => Err("Snapshot restoration is still initializing.".into()),
-		RestorationStatus::Failed => Err("Snapshot restoration failed.".into()),
-		RestorationStatus::Inactive => {
-			info!("Restoration complete.");
-			Ok(())
+		while let RestorationStatus::Ongoing { state_chunks, block_chunks, state_chunks_done, block_chunks_done } = snapshot.status() {
+			info!("Processed {}/{} state chunks and {}/{} block chunks.",
+				state_chunks_done, state_chunks, block_chunks_done, block_chunks);
+			::std::thread::sleep(Duration::from_secs(5));
 		}
-	}
+	});
 }
 
 impl SnapshotCommand {
@@ -211,22 +158,21 @@ impl SnapshotCommand {
 
 		let snapshot = service.snapshot_service();
 
+		spawn_informant(snapshot.clone());
+
 		if let Some(file) = file {
 			info!("Attempting to restore from snapshot at '{}'", file);
 
-			let reader = PackedReader::new(Path::new(&file))
-				.map_err(|e| format!("Couldn't open snapshot file: {}", e))
-				.and_then(|x| x.ok_or("Snapshot file has invalid format.".into()));
-
-			let reader = reader?;
-			restore_using(snapshot, &reader, true)?;
+			snapshot.restore_from_disk(Path::new(&file))
+				.map_err(|e| format!("Snapshot restoration failed: {}", e))?;
 		} else {
 			info!("Attempting to restore from local snapshot.");
 
 			// attempting restoration with recovery will lead to deadlock
 			// as we currently hold a read lock on the service's reader.
 			match *snapshot.reader() {
-				Some(ref reader) => restore_using(snapshot.clone(), reader, false)?,
+				Some(ref reader) => snapshot.restore_using(reader, false)
+					.map_err(|e| format!("Snapshot restoration failed: {}", e))?,
 				None => return Err("No local snapshot found.".into()),
 			}
 		}
@@ -244,7 +190,8 @@ impl SnapshotCommand {
 		warn!("Snapshots are currently experimental. File formats may be subject to change.");
 
 		let writer = PackedWriter::new(&file_path)
-			.map_err(|e| format!("Failed to open snapshot writer: {}", e))?;
+			.map_err(|e| format!("Failed to open snapshot writer: {}", e))?
+			.with_compression(self.compression);
 
 		let progress = Arc::new(Progress::default());
 		let p = progress.clone();
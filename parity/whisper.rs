@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::io;
+use std::path::PathBuf;
 
+use network::PeerId;
 use sync::{AttachedProtocol, ManageNetwork};
 use parity_rpc::Metadata;
 use parity_whisper::message::Message;
-use parity_whisper::net::{self as whisper_net, Network as WhisperNetwork};
+use parity_whisper::net::{self as whisper_net, Network as WhisperNetwork, PeerStats};
 use parity_whisper::rpc::{WhisperClient, PoolHandle, FilterManager};
 
 /// Whisper config.
@@ -28,6 +31,8 @@ use parity_whisper::rpc::{WhisperClient, PoolHandle, FilterManager};
 pub struct Config {
 	pub enabled: bool,
 	pub target_message_pool_size: usize,
+	/// File the message pool is persisted to, if any.
+	pub pool_path: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -35,6 +40,7 @@ impl Default for Config {
 		Config {
 			enabled: false,
 			target_message_pool_size: 10 * 1024 * 1024,
+			pool_path: None,
 		}
 	}
 }
@@ -62,6 +68,14 @@ impl PoolHandle for NetPoolHandle {
 	fn pool_status(&self) -> whisper_net::PoolStatus {
 		self.handle.pool_status()
 	}
+
+	fn set_topic_pow(&self, prefix: Vec<u8>, pow: f64) {
+		self.handle.set_topic_pow(prefix, pow)
+	}
+
+	fn peer_stats(&self) -> HashMap<PeerId, PeerStats> {
+		self.handle.peer_stats()
+	}
 }
 
 /// Factory for standard whisper RPC.
@@ -79,13 +93,17 @@ impl RpcFactory {
 
 /// Sets up whisper protocol and RPC handler.
 ///
-/// Will target the given pool size.
+/// Will target the given pool size, persisting the pool at `pool_path` if given.
 #[cfg(not(feature = "ipc"))]
-pub fn setup(target_pool_size: usize, protos: &mut Vec<AttachedProtocol>)
+pub fn setup(target_pool_size: usize, pool_path: Option<PathBuf>, protos: &mut Vec<AttachedProtocol>)
 	-> io::Result<Option<RpcFactory>>
 {
 	let manager = Arc::new(FilterManager::new()?);
-	let net = Arc::new(WhisperNetwork::new(target_pool_size, manager.clone()));
+	let net = WhisperNetwork::new(target_pool_size, manager.clone());
+	let net = Arc::new(match pool_path {
+		Some(path) => net.with_persistence(path),
+		None => net,
+	});
 
 	protos.push(AttachedProtocol {
 		handler: net.clone() as Arc<_>,
@@ -107,7 +125,7 @@ pub fn setup(target_pool_size: usize, protos: &mut Vec<AttachedProtocol>)
 
 // TODO: make it possible to attach generic protocols in IPC.
 #[cfg(feature = "ipc")]
-pub fn setup(_target_pool_size: usize, _protos: &mut Vec<AttachedProtocol>)
+pub fn setup(_target_pool_size: usize, _pool_path: Option<PathBuf>, _protos: &mut Vec<AttachedProtocol>)
 	-> io::Result<Option<RpcFactory>>
 {
 	Ok(None)
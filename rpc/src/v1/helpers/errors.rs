@@ -21,10 +21,11 @@ use std::fmt;
 use ethcore::account_provider::{SignError as AccountError};
 use ethcore::error::{Error as EthcoreError, ErrorKind, CallError};
 use ethcore::client::BlockId;
-use jsonrpc_core::{futures, Error, ErrorCode, Value};
+use jsonrpc_core::{Error, ErrorCode, Value};
 use rlp::DecoderError;
 use transaction::Error as TransactionError;
 use ethcore_private_tx::Error as PrivateTransactionError;
+use light::on_demand::Error as OnDemandError;
 use vm::Error as VMError;
 
 mod codes {
@@ -152,6 +153,14 @@ pub fn state_pruned() -> Error {
 	}
 }
 
+pub fn traces_pruned(horizon: u64) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::UNSUPPORTED_REQUEST),
+		message: "Traces of the requested blocks were pruned. Increase --tracing-history to keep more traces.".into(),
+		data: Some(Value::String(format!("Oldest traced block: 0x{:x}", horizon))),
+	}
+}
+
 pub fn state_corrupt() -> Error {
 	internal("State corrupt", "")
 }
@@ -444,7 +453,23 @@ pub fn filter_block_not_found(id: BlockId) -> Error {
 	}
 }
 
-// on-demand sender cancelled.
-pub fn on_demand_cancel(_cancel: futures::sync::oneshot::Canceled) -> Error {
-	internal("on-demand sender cancelled", "")
+// receipts of blocks matching a logs filter couldn't be fetched.
+pub fn receipts_unavailable(blocks: &[u64]) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::NO_LIGHT_PEERS),
+		message: "Receipts of some blocks could not be fetched from the network".into(),
+		data: Some(Value::String(blocks.iter().map(|number| format!("0x{:x}", number)).collect::<Vec<_>>().join(", "))),
+	}
+}
+
+// on-demand request failed.
+pub fn on_demand_error(err: OnDemandError) -> Error {
+	match err {
+		OnDemandError::Canceled => internal("on-demand sender cancelled", ""),
+		OnDemandError::Exhausted { attempts, peers_tried } => Error {
+			code: ErrorCode::ServerError(codes::NO_LIGHT_PEERS),
+			message: "No peers could serve the request".into(),
+			data: Some(Value::String(format!("{} attempts to {} peers", attempts, peers_tried))),
+		},
+	}
 }
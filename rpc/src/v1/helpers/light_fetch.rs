@@ -35,7 +35,7 @@ use light::client::LightChainClient;
 use light::{cht, MAX_HEADERS_PER_REQUEST};
 use light::on_demand::{
 	request, OnDemand, HeaderRef, Request as OnDemandRequest,
-	Response as OnDemandResponse, ExecutionResult,
+	Response as OnDemandResponse, Error as OnDemandError, ExecutionResult,
 };
 use light::request::Field;
 
@@ -65,6 +65,55 @@ pub struct LightFetch {
 	pub cache: Arc<Mutex<Cache>>,
 	/// Gas Price percentile
 	pub gas_price_percentile: usize,
+	/// Limits of `eth_getLogs` requests.
+	pub logs_limits: LogsLimits,
+}
+
+/// Limits of the work done by the light client to answer a logs request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogsLimits {
+	/// Maximal number of blocks in the requested range.
+	pub max_block_range: u64,
+	/// Maximal number of blocks with a bloom matching the filter, for which receipts are fetched.
+	pub max_candidate_blocks: usize,
+}
+
+impl Default for LogsLimits {
+	fn default() -> Self {
+		LogsLimits {
+			max_block_range: 1000,
+			max_candidate_blocks: 1000,
+		}
+	}
+}
+
+/// Extract logs matching the filter from receipts of the block with given number and hash.
+/// Logs are paired with their position in the block.
+fn block_logs(filter: &EthcoreFilter, num: u64, hash: H256, receipts: Vec<Receipt>) -> Vec<(usize, Log)> {
+	let mut logs = Vec::new();
+	let mut block_index = 0;
+	for (transaction_index, receipt) in receipts.into_iter().enumerate() {
+		for (transaction_log_index, log) in receipt.logs.into_iter().enumerate() {
+			if filter.matches(&log) {
+				logs.push((block_index, Log {
+					address: log.address.into(),
+					topics: log.topics.into_iter().map(Into::into).collect(),
+					data: log.data.into(),
+					block_hash: Some(hash.into()),
+					block_number: Some(num.into()),
+					// No way to easily retrieve transaction hash, so let's just skip it.
+					transaction_hash: None,
+					transaction_index: Some(transaction_index.into()),
+					log_index: Some(block_index.into()),
+					transaction_log_index: Some(transaction_log_index.into()),
+					log_type: "mined".into(),
+					removed: false,
+				}));
+			}
+			block_index += 1;
+		}
+	}
+	logs
 }
 
 /// Extract a transaction at given index.
@@ -301,60 +350,57 @@ impl LightFetch {
 		use std::collections::BTreeMap;
 		use jsonrpc_core::futures::stream::{self, Stream};
 
-		const MAX_BLOCK_RANGE: u64 = 1000;
-
+		let limits = self.logs_limits;
 		let fetcher = self.clone();
-		self.headers_range_by_block_id(filter.from_block, filter.to_block, MAX_BLOCK_RANGE)
+		self.headers_range_by_block_id(filter.from_block, filter.to_block, limits.max_block_range)
 			.and_then(move |mut headers| {
 				if headers.is_empty() {
 					return Either::A(future::ok(Vec::new()));
 				}
 
+				// find all headers which match the filter, the receipts are fetched for each one.
+				// match them with their numbers for easy sorting later.
+				let bit_combos = filter.bloom_possibilities();
+				let candidates: Vec<_> = headers.drain(..)
+					.filter(|ref hdr| {
+						let hdr_bloom = hdr.log_bloom();
+						bit_combos.iter().any(|bloom| hdr_bloom.contains_bloom(bloom))
+					})
+					.map(|hdr| (hdr.number(), hdr.hash(), request::BlockReceipts(hdr.into())))
+					.collect();
+
+				if candidates.len() > limits.max_candidate_blocks {
+					return Either::A(future::err(errors::request_rejected_param_limit(limits.max_candidate_blocks as u64, "blocks matching the filter")));
+				}
+
 				let on_demand = &fetcher.on_demand;
 
 				let maybe_future = fetcher.sync.with_context(move |ctx| {
-					// find all headers which match the filter, and fetch the receipts for each one.
-					// match them with their numbers for easy sorting later.
-					let bit_combos = filter.bloom_possibilities();
-					let receipts_futures: Vec<_> = headers.drain(..)
-						.filter(|ref hdr| {
-							let hdr_bloom = hdr.log_bloom();
-							bit_combos.iter().any(|bloom| hdr_bloom.contains_bloom(bloom))
-						})
-						.map(|hdr| (hdr.number(), hdr.hash(), request::BlockReceipts(hdr.into())))
-						.map(|(num, hash, req)| on_demand.request(ctx, req).expect(NO_INVALID_BACK_REFS).map(move |x| (num, hash, x)))
+					// receipts are verified against the receipts root of the header.
+					// note blocks for which they couldn't be fetched instead of failing right away.
+					let receipts_futures: Vec<_> = candidates.into_iter()
+						.map(|(num, hash, req)| on_demand.request(ctx, req).expect(NO_INVALID_BACK_REFS)
+							.then(move |receipts| Ok::<_, Error>((num, hash, receipts.ok()))))
 						.collect();
 
 					// as the receipts come in, find logs within them which match the filter.
 					// insert them into a BTreeMap to maintain order by number and block index.
 					stream::futures_unordered(receipts_futures)
-						.fold(BTreeMap::new(), move |mut matches, (num, hash, receipts)| {
-							let mut block_index = 0;
-							for (transaction_index, receipt) in receipts.into_iter().enumerate() {
-								for (transaction_log_index, log) in receipt.logs.into_iter().enumerate() {
-									if filter.matches(&log) {
-										matches.insert((num, block_index), Log {
-											address: log.address.into(),
-											topics: log.topics.into_iter().map(Into::into).collect(),
-											data: log.data.into(),
-											block_hash: Some(hash.into()),
-											block_number: Some(num.into()),
-											// No way to easily retrieve transaction hash, so let's just skip it.
-											transaction_hash: None,
-											transaction_index: Some(transaction_index.into()),
-											log_index: Some(block_index.into()),
-											transaction_log_index: Some(transaction_log_index.into()),
-											log_type: "mined".into(),
-											removed: false,
-										});
-									}
-									block_index += 1;
-								}
+						.fold((BTreeMap::new(), Vec::new()), move |(mut matches, mut missing), (num, hash, receipts)| {
+							match receipts {
+								Some(receipts) => for (block_index, log) in block_logs(&filter, num, hash, receipts) {
+									matches.insert((num, block_index), log);
+								},
+								None => missing.push(num),
 							}
-							future::ok(matches)
+							future::ok::<_, Error>((matches, missing))
 						}) // and then collect them into a vector.
-						.map(|matches| matches.into_iter().map(|(_, v)| v).collect())
-						.map_err(errors::on_demand_cancel)
+						.and_then(|(matches, mut missing)| if missing.is_empty() {
+							Ok(matches.into_iter().map(|(_, v)| v).collect())
+						} else {
+							missing.sort();
+							Err(errors::receipts_unavailable(&missing))
+						})
 				});
 
 				match maybe_future {
@@ -379,7 +425,7 @@ impl LightFetch {
 			});
 
 			let eventual_index = match maybe_future {
-				Some(e) => e.expect(NO_INVALID_BACK_REFS).map_err(errors::on_demand_cancel),
+				Some(e) => e.expect(NO_INVALID_BACK_REFS).map_err(errors::on_demand_error),
 				None => return Either::A(future::err(errors::network_disabled())),
 			};
 
@@ -430,8 +476,10 @@ impl LightFetch {
 		let maybe_future = self.sync.with_context(move |ctx| {
 			Box::new(self.on_demand.request_raw(ctx, reqs)
 					 .expect(NO_INVALID_BACK_REFS)
+					 .map_err(OnDemandError::from)
+					 .and_then(|responses| responses)
 					 .map(parse_response)
-					 .map_err(errors::on_demand_cancel))
+					 .map_err(errors::on_demand_error))
 		});
 
 		match maybe_future {
@@ -638,7 +686,7 @@ fn execute_tx(gas_known: bool, params: ExecuteParams) -> impl Future<Item = Exec
 			on_demand
 				.request(ctx, request)
 				.expect("no back-references; therefore all back-refs valid; qed")
-				.map_err(errors::on_demand_cancel)
+				.map_err(errors::on_demand_error)
 		});
 
 		match proved_future {
@@ -647,3 +695,53 @@ fn execute_tx(gas_known: bool, params: ExecuteParams) -> impl Future<Item = Exec
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use ethcore::filter::Filter as EthcoreFilter;
+	use ethcore::ids::BlockId;
+	use ethcore::log_entry::{LogEntry, LocalizedLogEntry};
+	use ethcore::receipt::{Receipt, TransactionOutcome};
+	use ethereum_types::Address;
+	use hash::H256;
+	use v1::types::Log;
+	use super::block_logs;
+
+	#[test]
+	fn should_localize_block_logs_like_full_client() {
+		let (watched, other) = (Address::from(1), Address::from(2));
+		let entry = |address| LogEntry { address, topics: vec![], data: vec![1, 2, 3] };
+		let receipts = vec![
+			Receipt::new(TransactionOutcome::Unknown, 21_000.into(), vec![entry(other), entry(watched)]),
+			Receipt::new(TransactionOutcome::Unknown, 42_000.into(), vec![]),
+			Receipt::new(TransactionOutcome::Unknown, 63_000.into(), vec![entry(watched)]),
+		];
+		let filter = EthcoreFilter {
+			from_block: BlockId::Earliest,
+			to_block: BlockId::Latest,
+			address: Some(vec![watched]),
+			topics: vec![],
+			limit: None,
+		};
+		let hash = H256::from(5);
+
+		// full client localizes logs the same way, it knows transaction hashes though.
+		let expected = vec![(1, 0, 1), (2, 2, 0)].into_iter()
+			.map(|(log_index, transaction_index, transaction_log_index)| {
+				let mut log: Log = LocalizedLogEntry {
+					entry: entry(watched),
+					block_hash: hash,
+					block_number: 10,
+					transaction_hash: H256::default(),
+					transaction_index,
+					log_index,
+					transaction_log_index,
+				}.into();
+				log.transaction_hash = None;
+				(log_index, log)
+			})
+			.collect::<Vec<_>>();
+
+		assert_eq!(block_logs(&filter, 10, hash, receipts), expected);
+	}
+}
@@ -107,6 +107,7 @@ impl EthPubSubClient<LightFetch> {
 			sync,
 			cache,
 			gas_price_percentile,
+			logs_limits: Default::default(),
 		};
 		EthPubSubClient::new(Arc::new(fetch), remote)
 	}
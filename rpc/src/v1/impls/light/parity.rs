@@ -23,12 +23,13 @@ use version::version_data;
 use crypto::DEFAULT_MAC;
 use ethkey::{crypto::ecies, Brain, Generator};
 use ethstore::random_phrase;
-use sync::LightSyncProvider;
+use sync::{LightSyncProvider, ManageNetwork};
 use ethcore::account_provider::AccountProvider;
 use ethcore_logger::RotatingLogger;
 use ethcore::ids::BlockId;
 
 use light::client::LightChainClient;
+use light::transaction_queue::FinishedStatus;
 
 use jsonrpc_core::{Result, BoxFuture};
 use jsonrpc_core::futures::Future;
@@ -43,8 +44,8 @@ use v1::types::{
 	Peers, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
-	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, Header, RichHeader,
+	OperationsInfo, ChainStatus, LightServerCosts,
+	AccountInfo, HwAccountInfo, HwAccount, Header, RichHeader,
 };
 use Host;
 
@@ -92,6 +93,7 @@ impl ParityClient {
 			sync: self.light_dispatch.sync.clone(),
 			cache: self.light_dispatch.cache.clone(),
 			gas_price_percentile: self.gas_price_percentile,
+			logs_limits: Default::default(),
 		}
 	}
 }
@@ -128,6 +130,19 @@ impl Parity for ParityClient {
 		)
 	}
 
+	fn list_hardware_accounts(&self) -> Result<Vec<HwAccount>> {
+		Ok(self.accounts.hardware_accounts_with_paths()
+			.into_iter()
+			.map(|account| HwAccount {
+				address: account.address.into(),
+				path: account.path.to_string(),
+				name: account.wallet.name,
+				manufacturer: account.wallet.manufacturer,
+			})
+			.collect()
+		)
+	}
+
 	fn locked_hardware_accounts_info(&self) -> Result<Vec<String>> {
 		let store = &self.accounts;
 		Ok(store.locked_hardware_accounts().map_err(|e| errors::account("Error communicating with hardware wallet.", e))?)
@@ -184,6 +199,7 @@ impl Parity for ParityClient {
 			connected: peer_numbers.connected,
 			max: peer_numbers.max as u32,
 			peers: peers,
+			banned: self.light_dispatch.sync.banned_peers(),
 		})
 	}
 
@@ -297,6 +313,10 @@ impl Parity for ParityClient {
 		)
 	}
 
+	fn light_server_costs(&self) -> Result<Option<LightServerCosts>> {
+		Ok(None)
+	}
+
 	fn local_transactions(&self) -> Result<BTreeMap<H256, LocalTransactionStatus>> {
 		let mut map = BTreeMap::new();
 		let chain_info = self.light_dispatch.client.chain_info();
@@ -311,7 +331,21 @@ impl Parity for ParityClient {
 			map.insert(future.hash().into(), LocalTransactionStatus::Future);
 		}
 
-		// TODO: other types?
+		for (tx, status) in txq.finished_transactions() {
+			let hash = tx.hash().into();
+			let mut tx = Transaction::from_pending(tx);
+			let status = match status {
+				FinishedStatus::Culled => LocalTransactionStatus::Culled(tx),
+				FinishedStatus::Mined { block_number, block_hash, index } => {
+					tx.block_number = Some(block_number.into());
+					tx.block_hash = Some(block_hash.into());
+					tx.transaction_index = Some(index.into());
+					LocalTransactionStatus::Mined(tx)
+				},
+				FinishedStatus::Dropped => LocalTransactionStatus::Dropped(tx),
+			};
+			map.insert(hash, status);
+		}
 
 		Ok(map)
 	}
@@ -29,7 +29,7 @@ use jsonrpc_core::{Result, BoxFuture};
 use jsonrpc_core::futures::Future;
 use v1::helpers::errors;
 use v1::traits::ParitySet;
-use v1::types::{Bytes, H160, H256, U256, ReleaseInfo, Transaction};
+use v1::types::{Bytes, H160, H256, U256, DatabaseBackup, ReleaseInfo, SnapshotVerification, Transaction};
 
 /// Parity-specific rpc interface for operations altering the settings.
 pub struct ParitySetClient<F> {
@@ -96,6 +96,13 @@ impl<F: Fetch> ParitySet for ParitySetClient<F> {
 		}
 	}
 
+	fn unban_peer(&self, peer: String) -> Result<bool> {
+		match self.net.unban_peer(peer) {
+			Ok(()) => Ok(true),
+			Err(e) => Err(errors::invalid_params("Peer id", e)),
+		}
+	}
+
 	fn drop_non_reserved_peers(&self) -> Result<bool> {
 		self.net.deny_unreserved_peers();
 		Ok(true)
@@ -148,4 +155,20 @@ impl<F: Fetch> ParitySet for ParitySetClient<F> {
 	fn remove_transaction(&self, _hash: H256) -> Result<Option<Transaction>> {
 		Err(errors::light_unimplemented(None))
 	}
+
+	fn snapshot_pause(&self) -> Result<bool> {
+		Err(errors::light_unimplemented(None))
+	}
+
+	fn snapshot_resume(&self) -> Result<bool> {
+		Err(errors::light_unimplemented(None))
+	}
+
+	fn verify_snapshot(&self, _path: String) -> Result<SnapshotVerification> {
+		Err(errors::light_unimplemented(None))
+	}
+
+	fn backup_database(&self, _path: String) -> Result<DatabaseBackup> {
+		Err(errors::light_unimplemented(None))
+	}
 }
@@ -44,8 +44,8 @@ use v1::types::{
 	Peers, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
-	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, RichHeader,
+	OperationsInfo, ChainStatus, LightServerCosts,
+	AccountInfo, HwAccountInfo, HwAccount, RichHeader,
 	block_number_to_id
 };
 use Host;
@@ -129,6 +129,19 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		)
 	}
 
+	fn list_hardware_accounts(&self) -> Result<Vec<HwAccount>> {
+		Ok(self.accounts.hardware_accounts_with_paths()
+			.into_iter()
+			.map(|account| HwAccount {
+				address: account.address.into(),
+				path: account.path.to_string(),
+				name: account.wallet.name,
+				manufacturer: account.wallet.manufacturer,
+			})
+			.collect()
+		)
+	}
+
 	fn locked_hardware_accounts_info(&self) -> Result<Vec<String>> {
 		self.accounts.locked_hardware_accounts().map_err(|e| errors::account("Error communicating with hardware wallet.", e))
 	}
@@ -191,7 +204,8 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 			active: sync_status.num_active_peers,
 			connected: sync_status.num_peers,
 			max: sync_status.current_max_peers(num_peers_range.start, num_peers_range.end - 1),
-			peers: peers
+			peers: peers,
+			banned: self.net.banned_peers(),
 		})
 	}
 
@@ -321,6 +335,10 @@ impl<C, M, U, S> Parity for ParityClient<C, M, U> where
 		)
 	}
 
+	fn light_server_costs(&self) -> Result<Option<LightServerCosts>> {
+		Ok(self.sync.light_server_costs().map(Into::into))
+	}
+
 	fn local_transactions(&self) -> Result<BTreeMap<H256, LocalTransactionStatus>> {
 		let transactions = self.miner.local_transactions();
 		Ok(transactions
@@ -23,6 +23,7 @@ use ethkey::{Brain, Generator, Secret};
 use ethstore::KeyFile;
 use ethcore::account_provider::AccountProvider;
 use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
 use v1::helpers::errors;
 use v1::traits::ParityAccounts;
 use v1::types::{H160 as RpcH160, H256 as RpcH256, H520 as RpcH520, Derive, DeriveHierarchical, DeriveHash, ExtAccountInfo};
@@ -187,9 +188,10 @@ impl ParityAccounts for ParityAccountsClient {
 			.map_err(|e| errors::account("Could not list vaults.", e))
 	}
 
-	fn change_vault_password(&self, name: String, new_password: Password) -> Result<bool> {
+	fn change_vault_password(&self, name: String, new_password: Password, old_password: Trailing<Password>) -> Result<bool> {
+		let old_password: Option<Password> = old_password.into();
 		self.accounts
-			.change_vault_password(&name, &new_password)
+			.change_vault_password(&name, old_password.as_ref(), &new_password)
 			.map_err(|e| errors::account("Could not change vault password.", e))
 			.map(|_| true)
 	}
@@ -16,11 +16,13 @@
 
 /// Parity-specific rpc interface for operations altering the settings.
 use std::io;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
 use ethcore::client::{BlockChainClient, Mode};
 use ethcore::miner::MinerService;
+use ethcore::snapshot::SnapshotService;
 use sync::ManageNetwork;
 use fetch::{self, Fetch};
 use futures_cpupool::CpuPool;
@@ -31,7 +33,7 @@ use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_core::futures::Future;
 use v1::helpers::errors;
 use v1::traits::ParitySet;
-use v1::types::{Bytes, H160, H256, U256, ReleaseInfo, Transaction};
+use v1::types::{Bytes, H160, H256, U256, DatabaseBackup, ReleaseInfo, SnapshotVerification, Transaction};
 
 /// Parity-specific rpc interface for operations altering the settings.
 pub struct ParitySetClient<C, M, U, F = fetch::Client> {
@@ -39,6 +41,7 @@ pub struct ParitySetClient<C, M, U, F = fetch::Client> {
 	miner: Arc<M>,
 	updater: Arc<U>,
 	net: Arc<ManageNetwork>,
+	snapshot: Arc<SnapshotService>,
 	fetch: F,
 	pool: CpuPool,
 }
@@ -52,6 +55,7 @@ impl<C, M, U, F> ParitySetClient<C, M, U, F>
 		miner: &Arc<M>,
 		updater: &Arc<U>,
 		net: &Arc<ManageNetwork>,
+		snapshot: &Arc<SnapshotService>,
 		fetch: F,
 		pool: CpuPool,
 	) -> Self {
@@ -60,6 +64,7 @@ impl<C, M, U, F> ParitySetClient<C, M, U, F>
 			miner: miner.clone(),
 			updater: updater.clone(),
 			net: net.clone(),
+			snapshot: snapshot.clone(),
 			fetch: fetch,
 			pool: pool,
 		}
@@ -131,6 +136,13 @@ impl<C, M, U, F> ParitySet for ParitySetClient<C, M, U, F> where
 		}
 	}
 
+	fn unban_peer(&self, peer: String) -> Result<bool> {
+		match self.net.unban_peer(peer) {
+			Ok(()) => Ok(true),
+			Err(e) => Err(errors::invalid_params("Peer id", e)),
+		}
+	}
+
 	fn drop_non_reserved_peers(&self) -> Result<bool> {
 		self.net.deny_unreserved_peers();
 		Ok(true)
@@ -195,4 +207,24 @@ impl<C, M, U, F> ParitySet for ParitySetClient<C, M, U, F> where
 		   .map(|t| Transaction::from_pending(t.pending().clone()))
 		)
 	}
+
+	fn snapshot_pause(&self) -> Result<bool> {
+		self.snapshot.pause_creation();
+		Ok(true)
+	}
+
+	fn snapshot_resume(&self) -> Result<bool> {
+		self.snapshot.resume_creation();
+		Ok(true)
+	}
+
+	fn verify_snapshot(&self, path: String) -> Result<SnapshotVerification> {
+		Ok(self.snapshot.verify_manifest(Path::new(&path)).into())
+	}
+
+	fn backup_database(&self, path: String) -> Result<DatabaseBackup> {
+		self.client.backup_database(Path::new(&path))
+			.map(Into::into)
+			.map_err(errors::database)
+	}
 }
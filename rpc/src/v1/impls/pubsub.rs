@@ -16,26 +16,35 @@
 
 //! Parity-specific PUB-SUB rpc implementation.
 
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 use parking_lot::RwLock;
 
 use jsonrpc_core::{self as core, Result, MetaIoHandler};
 use jsonrpc_core::futures::{Future, Stream, Sink};
 use jsonrpc_macros::Trailing;
-use jsonrpc_macros::pubsub::Subscriber;
+use jsonrpc_macros::pubsub::{self as macros_pubsub, Subscriber};
 use jsonrpc_pubsub::SubscriptionId;
+use serde_json;
 use tokio_timer;
 
+use ethcore::client::{ChainNotify, ChainRouteType};
+use ethcore::trace::{self, LocalizedTrace};
+use ethereum_types::H256;
 use parity_reactor::Remote;
-use v1::helpers::GenericPollManager;
+use v1::helpers::{GenericPollManager, Subscribers};
 use v1::metadata::Metadata;
 use v1::traits::PubSub;
+use v1::types::{BlockTraces, TraceAddressFilter};
+
+type TracesSubscribers = Subscribers<(macros_pubsub::Sink<core::Value>, trace::Filter)>;
 
 /// Parity PubSub implementation.
 pub struct PubSubClient<S: core::Middleware<Metadata>> {
 	poll_manager: Arc<RwLock<GenericPollManager<S>>>,
 	remote: Remote,
+	traces_handler: Arc<TracesNotificationHandler>,
+	traces_subscribers: Arc<RwLock<TracesSubscribers>>,
 }
 
 impl<S: core::Middleware<Metadata>> PubSubClient<S> {
@@ -55,9 +64,36 @@ impl<S: core::Middleware<Metadata>> PubSubClient<S> {
 			.for_each(move |_| pm2.read().tick())
 		);
 
+		let traces_subscribers = Arc::new(RwLock::new(Subscribers::default()));
+
 		PubSubClient {
 			poll_manager,
+			traces_handler: Arc::new(TracesNotificationHandler {
+				remote: remote.clone(),
+				subscribers: traces_subscribers.clone(),
+			}),
 			remote,
+			traces_subscribers,
+		}
+	}
+
+	/// Returns a chain notification handler feeding the `traces` subscriptions.
+	pub fn traces_handler(&self) -> Weak<TracesNotificationHandler> {
+		Arc::downgrade(&self.traces_handler)
+	}
+
+	fn subscribe_traces(&self, subscriber: Subscriber<core::Value>, params: core::Params) {
+		let filter = match params {
+			core::Params::None => Ok(TraceAddressFilter::default()),
+			core::Params::Array(ref params) if params.is_empty() => Ok(TraceAddressFilter::default()),
+			params => params.parse::<(TraceAddressFilter,)>().map(|(filter,)| filter),
+		};
+
+		match filter {
+			Ok(filter) => self.traces_subscribers.write().push(subscriber, filter.into()),
+			Err(err) => {
+				let _ = subscriber.reject(err);
+			},
 		}
 	}
 }
@@ -68,15 +104,61 @@ impl PubSubClient<core::NoopMiddleware> {
 	pub fn new_test(rpc: MetaIoHandler<Metadata, core::NoopMiddleware>, remote: Remote) -> Self {
 		let client = Self::new(MetaIoHandler::with_middleware(Default::default()), remote);
 		*client.poll_manager.write() = GenericPollManager::new_test(rpc);
+		*client.traces_subscribers.write() = Subscribers::new_test();
 		client
 	}
 }
 
+/// Pushes the traces of new blocks to `traces` subscribers.
+pub struct TracesNotificationHandler {
+	remote: Remote,
+	subscribers: Arc<RwLock<TracesSubscribers>>,
+}
+
+impl ChainNotify for TracesNotificationHandler {
+	fn new_traces(&self, route: &[(H256, ChainRouteType, Vec<LocalizedTrace>)]) {
+		for &(ref subscriber, ref filter) in self.subscribers.read().values() {
+			// We notify the traces of retracted and enacted blocks in the order of the route, like logs.
+			for &(hash, ref route_type, ref traces) in route {
+				let traces = traces.iter()
+					.filter(|trace| filter.matches_action(&trace.action, &trace.result))
+					.cloned()
+					.collect::<Vec<_>>();
+				let block_number = match traces.first() {
+					Some(trace) => trace.block_number,
+					None => continue,
+				};
+
+				let block_traces = BlockTraces {
+					block_hash: hash.into(),
+					block_number,
+					removed: match *route_type {
+						ChainRouteType::Enacted => false,
+						ChainRouteType::Retracted => true,
+					},
+					traces: traces.into_iter().map(Into::into).collect(),
+				};
+				let value = serde_json::to_value(block_traces).expect("Serialization of block traces is infallible; qed");
+				self.remote.spawn(subscriber
+					.notify(Ok(value))
+					.map(|_| ())
+					.map_err(|e| warn!(target: "rpc", "Unable to send notification: {}", e))
+				);
+			}
+		}
+	}
+}
+
 impl<S: core::Middleware<Metadata>> PubSub for PubSubClient<S> {
 	type Metadata = Metadata;
 
 	fn parity_subscribe(&self, mut meta: Metadata, subscriber: Subscriber<core::Value>, method: String, params: Trailing<core::Params>) {
 		let params = params.unwrap_or(core::Params::Array(vec![]));
+		// Traces are pushed as blocks are imported rather than polled.
+		if method == "traces" {
+			return self.subscribe_traces(subscriber, params);
+		}
+
 		// Make sure to get rid of PubSub session otherwise it will never be dropped.
 		meta.session = None;
 
@@ -96,6 +178,7 @@ impl<S: core::Middleware<Metadata>> PubSub for PubSubClient<S> {
 
 	fn parity_unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
 		let res = self.poll_manager.write().unsubscribe(&id);
-		Ok(res)
+		let res2 = self.traces_subscribers.write().remove(&id).is_some();
+		Ok(res || res2)
 	}
 }
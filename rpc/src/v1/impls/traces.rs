@@ -18,7 +18,7 @@
 
 use std::sync::Arc;
 
-use ethcore::client::{BlockChainClient, CallAnalytics, TransactionId, TraceId, StateClient, StateInfo, Call, BlockId};
+use ethcore::client::{BlockChainClient, CallAnalytics, TransactionId, TraceId, StateClient, StateInfo, Call, BlockId, TraceFilter as ClientTraceFilter};
 use rlp::Rlp;
 use transaction::SignedTransaction;
 
@@ -34,6 +34,7 @@ fn to_call_analytics(flags: TraceOptions) -> CallAnalytics {
 		transaction_tracing: flags.contains(&("trace".to_owned())),
 		vm_tracing: flags.contains(&("vmTrace".to_owned())),
 		state_diffing: flags.contains(&("stateDiff".to_owned())),
+		state_diffing_with_reads: flags.contains(&("stateDiffWithReads".to_owned())),
 	}
 }
 
@@ -51,6 +52,16 @@ impl<C> TracesClient<C> {
 	}
 }
 
+impl<C: BlockChainClient> TracesClient<C> {
+	// fail for blocks whose traces were pruned, rather than returning no traces.
+	fn ensure_not_pruned(&self, id: BlockId) -> Result<()> {
+		match (self.client.trace_horizon(), self.client.block_number(id)) {
+			(Some(horizon), Some(number)) if number < horizon => Err(errors::traces_pruned(horizon)),
+			_ => Ok(()),
+		}
+	}
+}
+
 impl<C, S> Traces for TracesClient<C> where
 	S: StateInfo + 'static,
 	C: BlockChainClient + StateClient<State=S> + Call<State=S> + 'static
@@ -58,7 +69,10 @@ impl<C, S> Traces for TracesClient<C> where
 	type Metadata = Metadata;
 
 	fn filter(&self, filter: TraceFilter) -> Result<Option<Vec<LocalizedTrace>>> {
-		Ok(self.client.filter_traces(filter.into())
+		let filter: ClientTraceFilter = filter.into();
+		self.ensure_not_pruned(filter.range.start)?;
+
+		Ok(self.client.filter_traces(filter)
 			.map(|traces| traces.into_iter().map(LocalizedTrace::from).collect()))
 	}
 
@@ -67,6 +81,7 @@ impl<C, S> Traces for TracesClient<C> where
 			BlockNumber::Pending => return Ok(None),
 			num => block_number_to_id(num)
 		};
+		self.ensure_not_pruned(id)?;
 
 		Ok(self.client.block_traces(id)
 			.map(|traces| traces.into_iter().map(LocalizedTrace::from).collect()))
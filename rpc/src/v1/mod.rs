@@ -44,6 +44,7 @@ pub mod traits;
 pub use self::traits::{Debug, Eth, EthFilter, EthPubSub, EthSigning, Net, Parity, ParityAccounts, ParitySet, ParitySigning, Personal, PubSub, Private, Rpc, SecretStore, Signer, Traces, Web3};
 pub use self::impls::*;
 pub use self::helpers::{NetworkSettings, block_import, dispatch};
+pub use self::helpers::light_fetch::LogsLimits;
 pub use self::metadata::Metadata;
 pub use self::types::Origin;
 pub use self::extractors::{RpcExtractor, WsExtractor, WsStats, WsDispatcher};
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use ethcore::snapshot::{ManifestData, RestorationStatus, SnapshotService};
+use std::path::{Path, PathBuf};
+
+use ethcore::snapshot::{CreationStatus, ManifestData, RestorationStatus, SnapshotService, VerificationReport};
 
 use bytes::Bytes;
 use ethereum_types::H256;
@@ -23,6 +25,8 @@ use parking_lot::Mutex;
 /// Mocked snapshot service (used for sync info extensions).
 pub struct TestSnapshotService {
 	status: Mutex<RestorationStatus>,
+	paused: Mutex<bool>,
+	verification: Mutex<VerificationReport>,
 }
 
 impl TestSnapshotService {
@@ -31,6 +35,8 @@ impl TestSnapshotService {
 	pub fn new() -> Self {
 		TestSnapshotService {
 			status: Mutex::new(RestorationStatus::Inactive),
+			paused: Mutex::new(false),
+			verification: Mutex::new(VerificationReport::default()),
 		}
 	}
 
@@ -38,6 +44,16 @@ impl TestSnapshotService {
 	pub fn set_status(&self, status: RestorationStatus) {
 		*self.status.lock() = status;
 	}
+
+	/// Set the report returned when verifying a snapshot.
+	pub fn set_verification(&self, report: VerificationReport) {
+		*self.verification.lock() = report;
+	}
+
+	/// Whether snapshot creation has been paused.
+	pub fn is_paused(&self) -> bool {
+		*self.paused.lock()
+	}
 }
 
 impl SnapshotService for TestSnapshotService {
@@ -48,7 +64,12 @@ impl SnapshotService for TestSnapshotService {
 	fn status(&self) -> RestorationStatus { self.status.lock().clone() }
 	fn begin_restore(&self, _manifest: ManifestData) { }
 	fn abort_restore(&self) { }
+	fn restore_from_path(&self, _path: PathBuf) { }
 	fn restore_state_chunk(&self, _hash: H256, _chunk: Bytes) { }
 	fn restore_block_chunk(&self, _hash: H256, _chunk: Bytes) { }
+	fn creation_status(&self) -> Option<CreationStatus> { None }
+	fn pause_creation(&self) { *self.paused.lock() = true; }
+	fn resume_creation(&self) { *self.paused.lock() = false; }
+	fn verify_manifest(&self, _path: &Path) -> VerificationReport { self.verification.lock().clone() }
 	fn shutdown(&self) { }
 }
@@ -19,7 +19,8 @@
 use std::collections::BTreeMap;
 use ethereum_types::H256;
 use parking_lot::RwLock;
-use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats};
+use light::request::Kind;
+use sync::{SyncProvider, EthProtocolInfo, SyncStatus, SyncState, PeerInfo, TransactionStats, LightServerCosts};
 
 /// TestSyncProvider config.
 pub struct Config {
@@ -54,6 +55,11 @@ impl TestSyncProvider {
 				num_snapshot_chunks: 0,
 				snapshot_chunks_done: 0,
 				last_imported_old_block_number: None,
+				transactions_propagated: 0,
+				transactions_bytes_propagated: 0,
+				transactions_duplicates_avoided: 0,
+				snapshot_selection: None,
+				downloader_resets: 0,
 			}),
 		}
 	}
@@ -122,4 +128,13 @@ impl SyncProvider for TestSyncProvider {
 			}
 		]
 	}
+
+	fn light_server_costs(&self) -> Option<LightServerCosts> {
+		Some(LightServerCosts {
+			limit: 1000.into(),
+			recharge: 10.into(),
+			base: 0.into(),
+			costs: vec![(Kind::Headers, 5.into()), (Kind::Signal, 100.into())],
+		})
+	}
 }
@@ -28,6 +28,8 @@ impl ManageNetwork for TestManageNetwork {
 	fn deny_unreserved_peers(&self) { }
 	fn remove_reserved_peer(&self, _peer: String) -> Result<(), String> { Ok(()) }
 	fn add_reserved_peer(&self, _peer: String) -> Result<(), String> { Ok(()) }
+	fn banned_peers(&self) -> Vec<String> { Vec::new() }
+	fn unban_peer(&self, _peer: String) -> Result<(), String> { Ok(()) }
 	fn start_network(&self) {}
 	fn stop_network(&self) {}
 	fn num_peers_range(&self) -> Range<u32> { 25 .. 51 }
@@ -219,10 +219,30 @@ fn rpc_parity_change_vault_password() {
 
 	assert!(tester.accounts.create_vault("vault1", &"password1".into()).is_ok());
 
+	// an opened vault stays opened with the new password.
 	let request = r#"{"jsonrpc": "2.0", "method": "parity_changeVaultPassword", "params":["vault1", "password2"], "id": 1}"#;
 	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+	assert_eq!(tester.accounts.list_opened_vaults().unwrap(), vec!["vault1".to_owned()]);
+	assert!(tester.accounts.close_vault("vault1").is_ok());
+	assert!(tester.accounts.open_vault("vault1", &"password1".into()).is_err());
+	assert!(tester.accounts.open_vault("vault1", &"password2".into()).is_ok());
+
+	// given the current password, the vault is closed.
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_changeVaultPassword", "params":["vault1", "password3", "password2"], "id": 1}"#;
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+	assert!(tester.accounts.list_opened_vaults().unwrap().is_empty());
+	assert!(tester.accounts.open_vault("vault1", &"password2".into()).is_err());
+
+	// a closed vault requires the current password, which must be right.
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_changeVaultPassword", "params":["vault1", "password4"], "id": 1}"#;
+	let response = tester.io.handle_request_sync(request).unwrap();
+	assert!(response.contains("\"error\""), "{}", response);
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_changeVaultPassword", "params":["vault1", "password4", "password1"], "id": 1}"#;
+	let response = tester.io.handle_request_sync(request).unwrap();
+	assert!(response.contains("\"error\""), "{}", response);
+	assert!(tester.accounts.open_vault("vault1", &"password3".into()).is_ok());
 }
 
 #[test]
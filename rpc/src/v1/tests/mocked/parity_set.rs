@@ -21,12 +21,13 @@ use ethereum_types::{U256, Address};
 
 use ethcore::miner::MinerService;
 use ethcore::client::TestBlockChainClient;
+use ethcore::snapshot::SnapshotService;
 use sync::ManageNetwork;
 use futures_cpupool::CpuPool;
 
 use jsonrpc_core::IoHandler;
 use v1::{ParitySet, ParitySetClient};
-use v1::tests::helpers::{TestMinerService, TestUpdater};
+use v1::tests::helpers::{TestMinerService, TestUpdater, TestSnapshotService};
 use super::manage_network::TestManageNetwork;
 
 use fake_fetch::FakeFetch;
@@ -54,9 +55,21 @@ fn parity_set_client(
 	miner: &Arc<TestMinerService>,
 	updater: &Arc<TestUpdater>,
 	net: &Arc<TestManageNetwork>,
+) -> TestParitySetClient {
+	parity_set_client_with_snapshot(client, miner, updater, net, &Arc::new(TestSnapshotService::new()))
+}
+
+fn parity_set_client_with_snapshot(
+	client: &Arc<TestBlockChainClient>,
+	miner: &Arc<TestMinerService>,
+	updater: &Arc<TestUpdater>,
+	net: &Arc<TestManageNetwork>,
+	snapshot: &Arc<TestSnapshotService>,
 ) -> TestParitySetClient {
 	let pool = CpuPool::new(1);
-	ParitySetClient::new(client, miner, updater, &(net.clone() as Arc<ManageNetwork>), FakeFetch::new(Some(1)), pool)
+	let net = net.clone() as Arc<ManageNetwork>;
+	let snapshot = snapshot.clone() as Arc<SnapshotService>;
+	ParitySetClient::new(client, miner, updater, &net, &snapshot, FakeFetch::new(Some(1)), pool)
 }
 
 #[test]
@@ -238,3 +251,71 @@ fn rpc_parity_remove_transaction() {
 	miner.pending_transactions.lock().insert(hash, signed);
 	assert_eq!(io.handle_request_sync(&request), Some(response.to_owned()));
 }
+
+#[test]
+fn rpc_parity_snapshot_pause_and_resume() {
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let updater = updater_service();
+	let snapshot = Arc::new(TestSnapshotService::new());
+	let mut io = IoHandler::new();
+	io.extend_with(parity_set_client_with_snapshot(&client, &miner, &updater, &network, &snapshot).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_snapshotPause", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+	assert!(snapshot.is_paused());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_snapshotResume", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+	assert!(!snapshot.is_paused());
+}
+
+#[test]
+fn rpc_parity_verify_snapshot() {
+	use ethcore::snapshot::{ChunkFailure, ChunkKind, VerificationReport};
+
+	let miner = miner_service();
+	let client = client_service();
+	let network = network_service();
+	let updater = updater_service();
+	let snapshot = Arc::new(TestSnapshotService::new());
+	snapshot.set_verification(VerificationReport {
+		manifest: None,
+		manifest_error: None,
+		chunks_checked: 3,
+		failures: vec![ChunkFailure {
+			hash: 5.into(),
+			kind: ChunkKind::State,
+			reason: "Chunk content doesn't match its hash".into(),
+		}],
+	});
+	let mut io = IoHandler::new();
+	io.extend_with(parity_set_client_with_snapshot(&client, &miner, &updater, &network, &snapshot).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_verifySnapshot", "params": ["/tmp/snapshot"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"blockHash":null,"blockNumber":null,"chunksChecked":3,"failures":[{"hash":"0x0000000000000000000000000000000000000000000000000000000000000005","kind":"state","reason":"Chunk content doesn't match its hash"}],"manifestError":null,"valid":false},"id":1}"#;
+	assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_parity_backup_database() {
+	use ethcore::client::{ChainInfo, EachBlockWith};
+
+	let miner = miner_service();
+	let client = client_service();
+	client.add_blocks(3, EachBlockWith::Nothing);
+	let network = network_service();
+	let updater = updater_service();
+	let mut io = IoHandler::new();
+	io.extend_with(parity_set_client(&client, &miner, &updater, &network).to_delegate());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_backupDatabase", "params": ["/tmp/backup"], "id": 1}"#;
+	let response = format!(
+		r#"{{"jsonrpc":"2.0","result":{{"blockHash":"0x{:x}","blockNumber":3,"duration":0,"size":0}},"id":1}}"#,
+		client.chain_info().best_block_hash,
+	);
+	assert_eq!(io.handle_request_sync(request), Some(response));
+}
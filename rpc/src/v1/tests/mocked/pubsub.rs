@@ -16,9 +16,14 @@
 
 use std::sync::{atomic, Arc};
 
+use ethcore::client::{ChainNotify, ChainRouteType};
+use ethcore::trace::LocalizedTrace;
+use ethcore::trace::trace::{Action, Res, Call};
 use jsonrpc_core::{self as core, MetaIoHandler};
 use jsonrpc_core::futures::{self, Stream, Future};
 use jsonrpc_pubsub::Session;
+use serde_json;
+use vm::CallType;
 
 use parity_reactor::EventLoop;
 use v1::{PubSub, PubSubClient, Metadata};
@@ -75,3 +80,81 @@ fn should_subscribe_to_a_method() {
 	let (res, _receiver) = receiver.into_future().wait().unwrap();
 	assert_eq!(res, None);
 }
+
+fn call_trace(to: u64, block_number: u64, block_hash: u64) -> LocalizedTrace {
+	LocalizedTrace {
+		action: Action::Call(Call {
+			from: 0xf.into(),
+			to: to.into(),
+			value: 0x1.into(),
+			gas: 0x100.into(),
+			input: vec![],
+			call_type: CallType::Call,
+		}),
+		result: Res::None,
+		subtraces: 0,
+		trace_address: vec![],
+		transaction_number: Some(0),
+		transaction_hash: Some(5.into()),
+		block_number: block_number,
+		block_hash: block_hash.into(),
+	}
+}
+
+#[test]
+fn should_stream_traces_of_reorganised_blocks() {
+	// given
+	let el = EventLoop::spawn();
+	let pubsub = PubSubClient::new_test(rpc(), el.remote());
+	let handler = pubsub.traces_handler().upgrade().unwrap();
+	let pubsub = pubsub.to_delegate();
+
+	let mut io = MetaIoHandler::default();
+	io.extend_with(pubsub);
+
+	let mut metadata = Metadata::default();
+	let (sender, receiver) = futures::sync::mpsc::channel(8);
+	metadata.session = Some(Arc::new(Session::new(sender)));
+
+	// Subscribe to calls to 0x10 only
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_subscribe", "params": ["traces", [{"toAddress": ["0x0000000000000000000000000000000000000010"]}]], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0x416d77337e24399d","id":1}"#;
+	assert_eq!(io.handle_request_sync(request, metadata.clone()), Some(response.to_owned()));
+
+	// Block 10 is replaced by a fork, block 11 doesn't call 0x10.
+	handler.new_traces(&[
+		(1.into(), ChainRouteType::Retracted, vec![call_trace(0x10, 10, 1), call_trace(0x11, 10, 1)]),
+		(2.into(), ChainRouteType::Enacted, vec![call_trace(0x10, 10, 2)]),
+		(3.into(), ChainRouteType::Enacted, vec![call_trace(0x11, 11, 3)]),
+	]);
+
+	// Check notifications
+	let notification = |res: Option<String>| -> serde_json::Value {
+		let value: serde_json::Value = serde_json::from_str(&res.unwrap()).unwrap();
+		assert_eq!(value["method"], "parity_subscription");
+		assert_eq!(value["params"]["subscription"], "0x416d77337e24399d");
+		value["params"]["result"].clone()
+	};
+
+	let (res, receiver) = receiver.into_future().wait().unwrap();
+	let result = notification(res);
+	assert_eq!(result["blockHash"], "0x0000000000000000000000000000000000000000000000000000000000000001");
+	assert_eq!(result["blockNumber"], 10);
+	assert_eq!(result["removed"], true);
+	assert_eq!(result["traces"].as_array().unwrap().len(), 1);
+
+	let (res, receiver) = receiver.into_future().wait().unwrap();
+	let result = notification(res);
+	assert_eq!(result["blockHash"], "0x0000000000000000000000000000000000000000000000000000000000000002");
+	assert_eq!(result["blockNumber"], 10);
+	assert_eq!(result["removed"], false);
+	assert_eq!(result["traces"].as_array().unwrap().len(), 1);
+
+	// And unsubscribe
+	let request = r#"{"jsonrpc": "2.0", "method": "parity_unsubscribe", "params": ["0x416d77337e24399d"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request_sync(request, metadata), Some(response.to_owned()));
+
+	let (res, _receiver) = receiver.into_future().wait().unwrap();
+	assert_eq!(res, None);
+}
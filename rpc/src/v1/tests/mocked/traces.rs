@@ -119,6 +119,25 @@ fn rpc_trace_block_missing_traces() {
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_trace_pruned() {
+	let tester = io();
+	*tester.client.trace_horizon.write() = 0x20;
+
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"Traces of the requested blocks were pruned. Increase --tracing-history to keep more traces.","data":"Oldest traced block: 0x20"},"id":1}"#;
+
+	let request = r#"{"jsonrpc":"2.0","method":"trace_block","params": ["0x10"],"id":1}"#;
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc":"2.0","method":"trace_filter","params": [{"fromBlock":"0x10"}],"id":1}"#;
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+
+	// blocks above the horizon are still traced.
+	let request = r#"{"jsonrpc":"2.0","method":"trace_block","params": ["0x20"],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"action":{"callType":"call","from":"0x000000000000000000000000000000000000000f","gas":"0x100","input":"0x010203","to":"0x0000000000000000000000000000000000000010","value":"0x1"},"blockHash":"0x000000000000000000000000000000000000000000000000000000000000000a","blockNumber":10,"result":null,"subtraces":0,"traceAddress":[0],"transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000005","transactionPosition":0,"type":"call"}],"id":1}"#;
+	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_trace_transaction() {
 	let tester = io();
@@ -165,7 +184,7 @@ fn rpc_trace_call() {
 	let tester = io();
 
 	let request = r#"{"jsonrpc":"2.0","method":"trace_call","params":[{}, ["stateDiff", "vmTrace", "trace"]],"id":1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":{"output":"0x010203","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"effectiveGasUsed":"0x2710","gasUsedBeforeRefund":"0x2710","output":"0x010203","refund":"0x0","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -175,7 +194,7 @@ fn rpc_trace_multi_call() {
 	let tester = io();
 
 	let request = r#"{"jsonrpc":"2.0","method":"trace_callMany","params":[[[{}, ["stateDiff", "vmTrace", "trace"]]]],"id":1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":[{"output":"0x010203","stateDiff":null,"trace":[],"vmTrace":null}],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"effectiveGasUsed":"0x2710","gasUsedBeforeRefund":"0x2710","output":"0x010203","refund":"0x0","stateDiff":null,"trace":[],"vmTrace":null}],"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -196,7 +215,7 @@ fn rpc_trace_raw_transaction() {
 	let tester = io();
 
 	let request = r#"{"jsonrpc":"2.0","method":"trace_rawTransaction","params":["0xf869018609184e72a0008276c094d46e8dd67c5d32be8058bb8eb970870f07244567849184e72a801ba0617f39c1a107b63302449c476d96a6cb17a5842fc98ff0c5bcf4d5c4d8166b95a009fdb6097c6196b9bbafc3a59f02f38d91baeef23d0c60a8e4f23c7714cea3a9", ["stateDiff", "vmTrace", "trace"]],"id":1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":{"output":"0x010203","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"effectiveGasUsed":"0x2710","gasUsedBeforeRefund":"0x2710","output":"0x010203","refund":"0x0","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -217,7 +236,7 @@ fn rpc_trace_replay_transaction() {
 	let tester = io();
 
 	let request = r#"{"jsonrpc":"2.0","method":"trace_replayTransaction","params":["0x0000000000000000000000000000000000000000000000000000000000000005", ["trace", "stateDiff", "vmTrace"]],"id":1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":{"output":"0x010203","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"effectiveGasUsed":"0x2710","gasUsedBeforeRefund":"0x2710","output":"0x010203","refund":"0x0","stateDiff":null,"trace":[],"vmTrace":null},"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
@@ -238,7 +257,7 @@ fn rpc_trace_replay_block_transactions() {
 	let tester = io();
 
 	let request = r#"{"jsonrpc":"2.0","method":"trace_replayBlockTransactions","params":["0x10", ["trace", "stateDiff", "vmTrace"]],"id":1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":[{"output":"0x010203","stateDiff":null,"trace":[],"transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000005","vmTrace":null}],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":[{"effectiveGasUsed":"0x2710","gasUsedBeforeRefund":"0x2710","output":"0x010203","refund":"0x0","stateDiff":null,"trace":[],"transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000005","vmTrace":null}],"id":1}"#;
 
 	assert_eq!(tester.io.handle_request_sync(request), Some(response.to_owned()));
 }
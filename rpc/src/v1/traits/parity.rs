@@ -26,8 +26,8 @@ use v1::types::{
 	Peers, Transaction, RpcSettings, Histogram,
 	TransactionStats, LocalTransactionStatus,
 	BlockNumber, ConsensusCapability, VersionInfo,
-	OperationsInfo, ChainStatus,
-	AccountInfo, HwAccountInfo, RichHeader,
+	OperationsInfo, ChainStatus, LightServerCosts,
+	AccountInfo, HwAccountInfo, HwAccount, RichHeader,
 };
 
 build_rpc_trait! {
@@ -43,6 +43,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_hardwareAccountsInfo")]
 		fn hardware_accounts_info(&self) -> Result<BTreeMap<H160, HwAccountInfo>>;
 
+		/// Returns accounts of connected hardware wallets along with their derivation paths.
+		#[rpc(name = "parity_listHardwareAccounts")]
+		fn list_hardware_accounts(&self) -> Result<Vec<HwAccount>>;
+
 		/// Get a list of paths to locked hardware wallets
 		#[rpc(name = "parity_lockedHardwareAccountsInfo")]
 		fn locked_hardware_accounts_info(&self) -> Result<Vec<String>>;
@@ -156,6 +160,11 @@ build_rpc_trait! {
 		#[rpc(name = "parity_pendingTransactionsStats")]
 		fn pending_transactions_stats(&self) -> Result<BTreeMap<H256, TransactionStats>>;
 
+		/// Returns the request credit parameters announced to light clients,
+		/// or `null` if not serving them.
+		#[rpc(name = "parity_lightServerCosts")]
+		fn light_server_costs(&self) -> Result<Option<LightServerCosts>>;
+
 		/// Returns a list of current and past local transactions with status details.
 		#[rpc(name = "parity_localTransactions")]
 		fn local_transactions(&self) -> Result<BTreeMap<H256, LocalTransactionStatus>>;
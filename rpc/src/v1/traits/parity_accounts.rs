@@ -18,6 +18,7 @@
 use std::collections::BTreeMap;
 
 use jsonrpc_core::Result;
+use jsonrpc_macros::Trailing;
 use ethkey::Password;
 use ethstore::KeyFile;
 use v1::types::{H160, H256, H520, DeriveHash, DeriveHierarchical, ExtAccountInfo};
@@ -100,9 +101,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_listOpenedVaults")]
 		fn list_opened_vaults(&self) -> Result<Vec<String>>;
 
-		/// Change vault password.
+		/// Change vault password. Given the current password as the last parameter the vault is closed
+		/// if it was opened, otherwise the vault must be opened and stays opened with the new password.
 		#[rpc(name = "parity_changeVaultPassword")]
-		fn change_vault_password(&self, String, Password) -> Result<bool>;
+		fn change_vault_password(&self, String, Password, Trailing<Password>) -> Result<bool>;
 
 		/// Change vault of the given address.
 		#[rpc(name = "parity_changeVault")]
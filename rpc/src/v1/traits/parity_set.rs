@@ -18,7 +18,7 @@
 
 use jsonrpc_core::{BoxFuture, Result};
 
-use v1::types::{Bytes, H160, H256, U256, ReleaseInfo, Transaction};
+use v1::types::{Bytes, H160, H256, U256, DatabaseBackup, ReleaseInfo, SnapshotVerification, Transaction};
 
 build_rpc_trait! {
 	/// Parity-specific rpc interface for operations altering the settings.
@@ -63,6 +63,10 @@ build_rpc_trait! {
 		#[rpc(name = "parity_removeReservedPeer")]
 		fn remove_reserved_peer(&self, String) -> Result<bool>;
 
+		/// Lift the temporary ban of a peer.
+		#[rpc(name = "parity_unbanPeer")]
+		fn unban_peer(&self, String) -> Result<bool>;
+
 		/// Drop all non-reserved peers.
 		#[rpc(name = "parity_dropNonReservedPeers")]
 		fn drop_non_reserved_peers(&self) -> Result<bool>;
@@ -111,5 +115,23 @@ build_rpc_trait! {
 		/// Returns `true` when transaction was removed, `false` if it was not found.
 		#[rpc(name = "parity_removeTransaction")]
 		fn remove_transaction(&self, H256) -> Result<Option<Transaction>>;
+
+		/// Pause taking the periodic snapshot until resumed.
+		#[rpc(name = "parity_snapshotPause")]
+		fn snapshot_pause(&self) -> Result<bool>;
+
+		/// Resume taking a paused periodic snapshot.
+		#[rpc(name = "parity_snapshotResume")]
+		fn snapshot_resume(&self) -> Result<bool>;
+
+		/// Verify the manifest and every chunk of the snapshot at the given path without restoring it.
+		/// The path is either a directory of snapshot chunks or a packed snapshot file.
+		#[rpc(name = "parity_verifySnapshot")]
+		fn verify_snapshot(&self, String) -> Result<SnapshotVerification>;
+
+		/// Back the database up to the given path, which must be missing or an empty directory.
+		/// The backup ends on a fully imported block, block import carries on while it's written.
+		#[rpc(name = "parity_backupDatabase")]
+		fn backup_database(&self, String) -> Result<DatabaseBackup>;
 	}
 }
@@ -28,6 +28,7 @@ build_rpc_trait! {
 
 		#[pubsub(name = "parity_subscription")] {
 			/// Subscribe to changes of any RPC method in Parity.
+			/// The `traces` method instead pushes the traces of imported blocks, optionally filtered by address.
 			#[rpc(name = "parity_subscribe")]
 			fn parity_subscribe(&self, Self::Metadata, Subscriber<Value>, String, Trailing<Params>);
 
@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use v1::types::H160;
+
 /// Account information.
 #[derive(Debug, Default, Clone, PartialEq, Serialize)]
 pub struct AccountInfo {
@@ -41,3 +43,16 @@ pub struct HwAccountInfo {
 	/// Device manufacturer.
 	pub manufacturer: String,
 }
+
+/// Hardware wallet account (used by `parity_listHardwareAccounts`).
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct HwAccount {
+	/// Account address.
+	pub address: H160,
+	/// Derivation path of the account key, e.g. `m/44'/60'/0'/1`.
+	pub path: String,
+	/// Device name.
+	pub name: String,
+	/// Device manufacturer.
+	pub manufacturer: String,
+}
@@ -0,0 +1,46 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database backup report.
+
+use ethcore::client::BackupReport;
+use v1::types::H256;
+
+/// Outcome of backing the database up.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DatabaseBackup {
+	/// Number of the best block of the backup.
+	#[serde(rename="blockNumber")]
+	pub block_number: u64,
+	/// Hash of the best block of the backup.
+	#[serde(rename="blockHash")]
+	pub block_hash: H256,
+	/// Number of bytes copied.
+	pub size: u64,
+	/// Time taken by the backup, in milliseconds.
+	pub duration: u64,
+}
+
+impl From<BackupReport> for DatabaseBackup {
+	fn from(r: BackupReport) -> Self {
+		DatabaseBackup {
+			block_number: r.best_block_number,
+			block_hash: r.best_block_hash.into(),
+			size: r.size,
+			duration: r.duration.as_secs() * 1000 + u64::from(r.duration.subsec_nanos()) / 1_000_000,
+		}
+	}
+}
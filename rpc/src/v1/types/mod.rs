@@ -23,6 +23,7 @@ mod bytes;
 mod call_request;
 mod confirmations;
 mod consensus_status;
+mod database_backup;
 mod derivation;
 mod filter;
 mod hash;
@@ -34,6 +35,7 @@ mod provenance;
 mod receipt;
 mod rpc_settings;
 mod secretstore;
+mod snapshot;
 mod sync;
 mod trace;
 mod trace_filter;
@@ -46,7 +48,7 @@ mod private_receipt;
 
 pub mod pubsub;
 
-pub use self::account_info::{AccountInfo, ExtAccountInfo, HwAccountInfo};
+pub use self::account_info::{AccountInfo, ExtAccountInfo, HwAccountInfo, HwAccount};
 pub use self::bytes::Bytes;
 pub use self::block::{RichBlock, Block, BlockTransactions, Header, RichHeader, Rich};
 pub use self::block_number::{BlockNumber, block_number_to_id};
@@ -56,6 +58,7 @@ pub use self::confirmations::{
 	TransactionModification, SignRequest, DecryptRequest, Either
 };
 pub use self::consensus_status::*;
+pub use self::database_backup::DatabaseBackup;
 pub use self::derivation::{DeriveHash, DeriveHierarchical, Derive};
 pub use self::filter::{Filter, FilterChanges};
 pub use self::hash::{H64, H160, H256, H512, H520, H2048};
@@ -67,12 +70,13 @@ pub use self::provenance::Origin;
 pub use self::receipt::Receipt;
 pub use self::rpc_settings::RpcSettings;
 pub use self::secretstore::EncryptedDocumentKey;
+pub use self::snapshot::{ChunkFailure, SnapshotVerification};
 pub use self::sync::{
 	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
-	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo,
+	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo, LightServerCosts,
 };
-pub use self::trace::{LocalizedTrace, TraceResults, TraceResultsWithTransactionHash};
-pub use self::trace_filter::TraceFilter;
+pub use self::trace::{BlockTraces, LocalizedTrace, TraceResults, TraceResultsWithTransactionHash};
+pub use self::trace_filter::{TraceFilter, TraceAddressFilter, TraceCallType, TraceResultStatus};
 pub use self::transaction::{Transaction, RichRawTransaction, LocalTransactionStatus};
 pub use self::transaction_request::TransactionRequest;
 pub use self::transaction_condition::TransactionCondition;
@@ -0,0 +1,78 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Snapshot verification report.
+
+use ethcore::snapshot::{self, ChunkKind};
+use v1::types::H256;
+
+/// Chunk failing snapshot verification.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ChunkFailure {
+	/// Hash of the chunk.
+	pub hash: H256,
+	/// Kind of the chunk, `state` or `block`.
+	pub kind: String,
+	/// What is wrong with the chunk.
+	pub reason: String,
+}
+
+impl From<snapshot::ChunkFailure> for ChunkFailure {
+	fn from(f: snapshot::ChunkFailure) -> Self {
+		ChunkFailure {
+			hash: f.hash.into(),
+			kind: match f.kind {
+				ChunkKind::State => "state".into(),
+				ChunkKind::Block => "block".into(),
+			},
+			reason: f.reason,
+		}
+	}
+}
+
+/// Outcome of verifying a snapshot.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SnapshotVerification {
+	/// Whether the snapshot passed verification.
+	pub valid: bool,
+	/// Number of the block the snapshot was taken at, if the manifest could be read.
+	#[serde(rename="blockNumber")]
+	pub block_number: Option<u64>,
+	/// Hash of the block the snapshot was taken at, if the manifest could be read.
+	#[serde(rename="blockHash")]
+	pub block_hash: Option<H256>,
+	/// Why the manifest couldn't be read, if it couldn't.
+	#[serde(rename="manifestError")]
+	pub manifest_error: Option<String>,
+	/// Number of chunks checked.
+	#[serde(rename="chunksChecked")]
+	pub chunks_checked: usize,
+	/// Every chunk failing verification.
+	pub failures: Vec<ChunkFailure>,
+}
+
+impl From<snapshot::VerificationReport> for SnapshotVerification {
+	fn from(r: snapshot::VerificationReport) -> Self {
+		SnapshotVerification {
+			valid: r.is_valid(),
+			block_number: r.manifest.as_ref().map(|m| m.block_number),
+			block_hash: r.manifest.as_ref().map(|m| m.block_hash.into()),
+			manifest_error: r.manifest_error,
+			chunks_checked: r.chunks_checked,
+			failures: r.failures.into_iter().map(Into::into).collect(),
+		}
+	}
+}
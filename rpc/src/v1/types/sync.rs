@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
+use light::request::Kind;
 use sync::{self, PeerInfo as SyncPeerInfo, TransactionStats as SyncTransactionStats};
 use serde::{Serialize, Serializer};
 use v1::types::{U256, H512};
@@ -50,6 +51,8 @@ pub struct Peers {
 	pub max: u32,
 	/// Detailed information on peers
 	pub peers: Vec<PeerInfo>,
+	/// Ids of temporarily banned nodes
+	pub banned: Vec<String>,
 }
 
 /// Peer connection information
@@ -189,6 +192,49 @@ impl From<SyncTransactionStats> for TransactionStats {
 	}
 }
 
+/// Request credit parameters announced by the light server.
+#[derive(Default, Debug, Serialize)]
+pub struct LightServerCosts {
+	/// Maximum amount of credits a peer can hold.
+	pub limit: U256,
+	/// Credits recharged per second.
+	pub recharge: U256,
+	/// Cost of every request packet.
+	pub base: U256,
+	/// Cost of each served request kind. Headers are charged per header
+	/// and executions per gas.
+	pub costs: BTreeMap<String, U256>,
+}
+
+impl From<sync::LightServerCosts> for LightServerCosts {
+	fn from(c: sync::LightServerCosts) -> Self {
+		fn kind_name(kind: Kind) -> &'static str {
+			match kind {
+				Kind::Headers => "headers",
+				Kind::HeaderProof => "headerProof",
+				Kind::TransactionIndex => "transactionIndex",
+				Kind::Body => "body",
+				Kind::Receipts => "receipts",
+				Kind::Account => "account",
+				Kind::Storage => "storage",
+				Kind::Code => "code",
+				Kind::Execution => "execution",
+				Kind::Signal => "signal",
+			}
+		}
+
+		LightServerCosts {
+			limit: c.limit.into(),
+			recharge: c.recharge.into(),
+			base: c.base.into(),
+			costs: c.costs
+				.into_iter()
+				.map(|(kind, cost)| (kind_name(kind).to_owned(), cost.into()))
+				.collect(),
+		}
+	}
+}
+
 /// Chain status.
 #[derive(Default, Debug, Serialize)]
 pub struct ChainStatus {
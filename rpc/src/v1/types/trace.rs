@@ -76,6 +76,9 @@ pub struct VMExecutedOperation {
 	/// The altered storage value, if any.
 	#[serde(rename="store")]
 	pub store: Option<StorageDiff>,
+	/// The refund counter of the call frame, if altered.
+	#[serde(skip_serializing_if="Option::is_none")]
+	pub refund: Option<U256>,
 }
 
 impl From<et::VMExecutedOperation> for VMExecutedOperation {
@@ -85,6 +88,7 @@ impl From<et::VMExecutedOperation> for VMExecutedOperation {
 			push: c.stack_push.into_iter().map(Into::into).collect(),
 			mem: c.mem_diff.map(Into::into),
 			store: c.store_diff.map(Into::into),
+			refund: c.refund.map(Into::into),
 		}
 	}
 }
@@ -183,6 +187,33 @@ pub struct AccountDiff {
 	pub nonce: Diff<U256>,
 	pub code: Diff<Bytes>,
 	pub storage: BTreeMap<H256, Diff<H256>>,
+	/// Value first read from each storage slot, only with `stateDiffWithReads`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reads: Option<BTreeMap<H256, H256>>,
+	/// Set if reads were dropped on reaching the limit of recorded reads.
+	#[serde(rename="readsTruncated", skip_serializing_if = "Option::is_none")]
+	pub reads_truncated: Option<bool>,
+}
+
+impl AccountDiff {
+	// diff of an account which was read, but not altered.
+	fn unchanged() -> Self {
+		AccountDiff {
+			balance: Diff::Same,
+			nonce: Diff::Same,
+			code: Diff::Same,
+			storage: BTreeMap::new(),
+			reads: None,
+			reads_truncated: None,
+		}
+	}
+
+	fn set_reads(&mut self, reads: account_diff::StorageReads) {
+		self.reads = Some(reads.values.into_iter().map(|(k, v)| (k.into(), v.into())).collect());
+		if reads.truncated {
+			self.reads_truncated = Some(true);
+		}
+	}
 }
 
 impl From<account_diff::AccountDiff> for AccountDiff {
@@ -192,6 +223,8 @@ impl From<account_diff::AccountDiff> for AccountDiff {
 			nonce: c.nonce.into(),
 			code: c.code.into(),
 			storage: c.storage.into_iter().map(|(k, v)| (k.into(), v.into())).collect(),
+			reads: None,
+			reads_truncated: None,
 		}
 	}
 }
@@ -209,7 +242,12 @@ impl Serialize for StateDiff {
 
 impl From<state_diff::StateDiff> for StateDiff {
 	fn from(c: state_diff::StateDiff) -> Self {
-		StateDiff(c.raw.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+		let mut diff: BTreeMap<H160, AccountDiff> = c.raw.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+		for (address, reads) in c.reads {
+			diff.entry(address.into()).or_insert_with(AccountDiff::unchanged).set_reads(reads);
+		}
+
+		StateDiff(diff)
 	}
 }
 
@@ -543,6 +581,21 @@ impl From<EthLocalizedTrace> for LocalizedTrace {
 	}
 }
 
+/// Traces of a block, pushed to traces subscribers.
+#[derive(Debug, Serialize)]
+pub struct BlockTraces {
+	/// Block Hash
+	#[serde(rename="blockHash")]
+	pub block_hash: H256,
+	/// Block Number
+	#[serde(rename="blockNumber")]
+	pub block_number: u64,
+	/// Whether the block was retracted by a reorg
+	pub removed: bool,
+	/// Traces of the block
+	pub traces: Vec<LocalizedTrace>,
+}
+
 /// Trace
 #[derive(Debug)]
 pub struct Trace {
@@ -619,15 +672,27 @@ pub struct TraceResults {
 	/// The transaction trace.
 	#[serde(rename="stateDiff")]
 	pub state_diff: Option<StateDiff>,
+	/// Gas used by execution before refunds were applied.
+	#[serde(rename="gasUsedBeforeRefund")]
+	pub gas_used_before_refund: U256,
+	/// Gas refunded at the end of the transaction.
+	pub refund: U256,
+	/// Gas charged for the transaction, as in its receipt.
+	#[serde(rename="effectiveGasUsed")]
+	pub effective_gas_used: U256,
 }
 
 impl From<Executed> for TraceResults {
 	fn from(t: Executed) -> Self {
+		let gas_used_before_refund = t.gas_used_before_refund();
 		TraceResults {
 			output: t.output.into(),
 			trace: t.trace.into_iter().map(Into::into).collect(),
 			vm_trace: t.vm_trace.map(Into::into),
 			state_diff: t.state_diff.map(Into::into),
+			gas_used_before_refund: gas_used_before_refund.into(),
+			refund: t.refunded.into(),
+			effective_gas_used: t.gas_used.into(),
 		}
 	}
 }
@@ -645,6 +710,14 @@ pub struct TraceResultsWithTransactionHash {
 	/// The transaction trace.
 	#[serde(rename="stateDiff")]
 	pub state_diff: Option<StateDiff>,
+	/// Gas used by execution before refunds were applied.
+	#[serde(rename="gasUsedBeforeRefund")]
+	pub gas_used_before_refund: U256,
+	/// Gas refunded at the end of the transaction.
+	pub refund: U256,
+	/// Gas charged for the transaction, as in its receipt.
+	#[serde(rename="effectiveGasUsed")]
+	pub effective_gas_used: U256,
 	/// The transaction Hash.
 	#[serde(rename="transactionHash")]
 	pub transaction_hash: H256,
@@ -652,11 +725,15 @@ pub struct TraceResultsWithTransactionHash {
 
 impl From<(EthH256, Executed)> for TraceResultsWithTransactionHash {
 	fn from(t: (EthH256, Executed)) -> Self {
+		let gas_used_before_refund = t.1.gas_used_before_refund();
 		TraceResultsWithTransactionHash {
 			output: t.1.output.into(),
 			trace: t.1.trace.into_iter().map(Into::into).collect(),
 			vm_trace: t.1.vm_trace.map(Into::into),
 			state_diff: t.1.state_diff.map(Into::into),
+			gas_used_before_refund: gas_used_before_refund.into(),
+			refund: t.1.refunded.into(),
+			effective_gas_used: t.1.gas_used.into(),
 			transaction_hash: t.0.into(),
 		}
 	}
@@ -677,9 +754,12 @@ mod tests {
 			trace: vec![],
 			vm_trace: None,
 			state_diff: None,
+			gas_used_before_refund: 26006.into(),
+			refund: 13003.into(),
+			effective_gas_used: 13003.into(),
 		};
 		let serialized = serde_json::to_string(&r).unwrap();
-		assert_eq!(serialized, r#"{"output":"0x60","trace":[],"vmTrace":null,"stateDiff":null}"#);
+		assert_eq!(serialized, r#"{"output":"0x60","trace":[],"vmTrace":null,"stateDiff":null,"gasUsedBeforeRefund":"0x6596","refund":"0x32cb","effectiveGasUsed":"0x32cb"}"#);
 	}
 
 	#[test]
@@ -817,6 +897,19 @@ mod tests {
 		assert_eq!(serialized, r#"{"type":"reward","action":{"author":"0x0000000000000000000000000000000000000004","value":"0x6","rewardType":"block"},"result":null,"traceAddress":[10],"subtraces":1,"transactionPosition":null,"transactionHash":null,"blockNumber":13,"blockHash":"0x000000000000000000000000000000000000000000000000000000000000000e"}"#);
 	}
 
+	#[test]
+	fn test_reward_type_serialize() {
+		let kinds = vec![
+			(RewardType::Block, r#""block""#),
+			(RewardType::Uncle, r#""uncle""#),
+			(RewardType::EmptyStep, r#""emptyStep""#),
+			(RewardType::External, r#""external""#),
+		];
+		for (kind, expected) in kinds {
+			assert_eq!(serde_json::to_string(&kind).unwrap(), expected);
+		}
+	}
+
 	#[test]
 	fn test_vmtrace_serialize() {
 		let t = VMTrace {
@@ -836,6 +929,7 @@ mod tests {
 						push: vec![69.into()],
 						mem: None,
 						store: None,
+						refund: None,
 					}),
 					sub: Some(VMTrace {
 						code: vec![0].into(),
@@ -848,6 +942,7 @@ mod tests {
 									push: vec![42.into()].into(),
 									mem: Some(MemoryDiff {off: 42, data: vec![1, 2, 3].into()}),
 									store: Some(StorageDiff {key: 69.into(), val: 42.into()}),
+									refund: Some(15000.into()),
 								}),
 								sub: None,
 							}
@@ -857,7 +952,7 @@ mod tests {
 			]
 		};
 		let serialized = serde_json::to_string(&t).unwrap();
-		assert_eq!(serialized, r#"{"code":"0x00010203","ops":[{"pc":0,"cost":10,"ex":null,"sub":null},{"pc":1,"cost":11,"ex":{"used":10,"push":["0x45"],"mem":null,"store":null},"sub":{"code":"0x00","ops":[{"pc":0,"cost":0,"ex":{"used":10,"push":["0x2a"],"mem":{"off":42,"data":"0x010203"},"store":{"key":"0x45","val":"0x2a"}},"sub":null}]}}]}"#);
+		assert_eq!(serialized, r#"{"code":"0x00010203","ops":[{"pc":0,"cost":10,"ex":null,"sub":null},{"pc":1,"cost":11,"ex":{"used":10,"push":["0x45"],"mem":null,"store":null},"sub":{"code":"0x00","ops":[{"pc":0,"cost":0,"ex":{"used":10,"push":["0x2a"],"mem":{"off":42,"data":"0x010203"},"store":{"key":"0x45","val":"0x2a"},"refund":"0x3a98"},"sub":null}]}}]}"#);
 	}
 
 	#[test]
@@ -869,16 +964,50 @@ mod tests {
 				code: Diff::Same,
 				storage: map![
 					42.into() => Diff::Same
-				]
+				],
+				reads: None,
+				reads_truncated: None,
 			},
 			69.into() => AccountDiff {
 				balance: Diff::Same,
 				nonce: Diff::Changed(ChangedType { from: 1.into(), to: 0.into() }),
 				code: Diff::Died(vec![96].into()),
 				storage: map![],
+				reads: None,
+				reads_truncated: None,
 			}
 		]);
 		let serialized = serde_json::to_string(&t).unwrap();
 		assert_eq!(serialized, r#"{"0x000000000000000000000000000000000000002a":{"balance":"=","nonce":{"+":"0x1"},"code":"=","storage":{"0x000000000000000000000000000000000000000000000000000000000000002a":"="}},"0x0000000000000000000000000000000000000045":{"balance":"=","nonce":{"*":{"from":"0x1","to":"0x0"}},"code":{"-":"0x60"},"storage":{}}}"#);
 	}
+
+
+	#[test]
+	fn test_statediff_with_reads_serialize() {
+		let diff = state_diff::StateDiff {
+			raw: map![
+				42.into() => account_diff::AccountDiff {
+					balance: account_diff::Diff::Same,
+					nonce: account_diff::Diff::Same,
+					code: account_diff::Diff::Same,
+					storage: map![
+						2.into() => account_diff::Diff::Born(11.into())
+					],
+				}
+			],
+			reads: map![
+				42.into() => account_diff::StorageReads {
+					values: map![0.into() => 3.into()],
+					truncated: false,
+				},
+				69.into() => account_diff::StorageReads {
+					values: map![],
+					truncated: true,
+				}
+			],
+		};
+
+		let serialized = serde_json::to_string(&StateDiff::from(diff)).unwrap();
+		assert_eq!(serialized, r#"{"0x000000000000000000000000000000000000002a":{"balance":"=","nonce":"=","code":"=","storage":{"0x0000000000000000000000000000000000000000000000000000000000000002":{"+":"0x000000000000000000000000000000000000000000000000000000000000000b"}},"reads":{"0x0000000000000000000000000000000000000000000000000000000000000000":"0x0000000000000000000000000000000000000000000000000000000000000003"}},"0x0000000000000000000000000000000000000045":{"balance":"=","nonce":"=","code":"=","storage":{},"reads":{},"readsTruncated":true}}"#);
+	}
 }
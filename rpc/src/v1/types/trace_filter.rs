@@ -18,8 +18,70 @@
 
 use ethcore::client::BlockId;
 use ethcore::client;
+use ethcore::trace::{self, TraceKind, TraceStatus};
+use ethereum_types::Address;
 use v1::types::{BlockNumber, H160};
 
+/// Call type of call traces, or kind of the other traces
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum TraceCallType {
+	/// Call
+	#[serde(rename="call")]
+	Call,
+	/// Delegate call
+	#[serde(rename="delegatecall")]
+	DelegateCall,
+	/// Static call
+	#[serde(rename="staticcall")]
+	StaticCall,
+	/// Call code
+	#[serde(rename="callcode")]
+	CallCode,
+	/// Contract creation
+	#[serde(rename="create")]
+	Create,
+	/// Suicide
+	#[serde(rename="suicide")]
+	Suicide,
+	/// Block reward
+	#[serde(rename="reward")]
+	Reward,
+}
+
+impl Into<TraceKind> for TraceCallType {
+	fn into(self) -> TraceKind {
+		match self {
+			TraceCallType::Call => TraceKind::Call,
+			TraceCallType::DelegateCall => TraceKind::DelegateCall,
+			TraceCallType::StaticCall => TraceKind::StaticCall,
+			TraceCallType::CallCode => TraceKind::CallCode,
+			TraceCallType::Create => TraceKind::Create,
+			TraceCallType::Suicide => TraceKind::Suicide,
+			TraceCallType::Reward => TraceKind::Reward,
+		}
+	}
+}
+
+/// Outcome of traced actions
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum TraceResultStatus {
+	/// Succeeded
+	#[serde(rename="success")]
+	Success,
+	/// Failed
+	#[serde(rename="failed")]
+	Failed,
+}
+
+impl Into<TraceStatus> for TraceResultStatus {
+	fn into(self) -> TraceStatus {
+		match self {
+			TraceResultStatus::Success => TraceStatus::Success,
+			TraceResultStatus::Failed => TraceStatus::Failed,
+		}
+	}
+}
+
 /// Trace filter
 #[derive(Debug, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -36,6 +98,11 @@ pub struct TraceFilter {
 	/// To address
 	#[serde(rename="toAddress")]
 	pub to_address: Option<Vec<H160>>,
+	/// Call type or kind of action
+	#[serde(rename="callType")]
+	pub call_type: Option<TraceCallType>,
+	/// Whether the action succeeded
+	pub status: Option<TraceResultStatus>,
 	/// Output offset
 	pub after: Option<usize>,
 	/// Output amount
@@ -59,17 +126,48 @@ impl Into<client::TraceFilter> for TraceFilter {
 			range: start..end,
 			from_address: self.from_address.map_or_else(Vec::new, |x| x.into_iter().map(Into::into).collect()),
 			to_address: self.to_address.map_or_else(Vec::new, |x| x.into_iter().map(Into::into).collect()),
+			kind: self.call_type.map(Into::into),
+			status: self.status.map(Into::into),
 			after: self.after,
 			count: self.count,
 		}
 	}
 }
 
+/// Address filter of a traces subscription.
+#[derive(Debug, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraceAddressFilter {
+	/// From address
+	#[serde(rename="fromAddress")]
+	pub from_address: Option<Vec<H160>>,
+	/// To address
+	#[serde(rename="toAddress")]
+	pub to_address: Option<Vec<H160>>,
+}
+
+impl Into<trace::Filter> for TraceAddressFilter {
+	fn into(self) -> trace::Filter {
+		let addresses = |list: Option<Vec<H160>>| -> trace::AddressesFilter {
+			list.map_or_else(Vec::new, |x| x.into_iter().map(Into::into).collect::<Vec<Address>>()).into()
+		};
+
+		trace::Filter {
+			// subscriptions follow the chain, there's no range to apply.
+			range: 0..0,
+			from_address: addresses(self.from_address),
+			to_address: addresses(self.to_address),
+			kind: None,
+			status: None,
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use serde_json;
 	use ethereum_types::Address;
-	use v1::types::{BlockNumber, TraceFilter};
+	use v1::types::{BlockNumber, TraceFilter, TraceCallType, TraceResultStatus};
 
 	#[test]
 	fn test_empty_trace_filter_deserialize() {
@@ -80,6 +178,8 @@ mod tests {
 			to_block: None,
 			from_address: None,
 			to_address: None,
+			call_type: None,
+			status: None,
 			after: None,
 			count: None,
 		});
@@ -92,6 +192,8 @@ mod tests {
 			"toBlock": "latest",
 			"fromAddress": ["0x0000000000000000000000000000000000000003"],
 			"toAddress": ["0x0000000000000000000000000000000000000005"],
+			"callType": "delegatecall",
+			"status": "failed",
 			"after": 50,
 			"count": 100
 		}"#;
@@ -101,6 +203,8 @@ mod tests {
 			to_block: Some(BlockNumber::Latest),
 			from_address: Some(vec![Address::from(3).into()]),
 			to_address: Some(vec![Address::from(5).into()]),
+			call_type: Some(TraceCallType::DelegateCall),
+			status: Some(TraceResultStatus::Failed),
 			after: 50.into(),
 			count: 100.into(),
 		});
@@ -16,9 +16,11 @@
 
 use std::sync::Arc;
 use std::collections::{HashMap, HashSet};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use parking_lot::{Mutex, RwLock};
-use ethcore::client::{BlockId, ChainNotify, ChainRoute, CallContract};
+use ethcore::client::{BlockId, BlockChainClient, ChainNotify, ChainRoute, CallContract};
+use ethcore::filter::Filter;
+use ethcore::header::BlockNumber;
 use ethereum_types::{H256, Address};
 use bytes::Bytes;
 use trusted_client::TrustedClient;
@@ -28,16 +30,57 @@ use_contract!(acl_storage, "AclStorage", "res/acl_storage.json");
 
 const ACL_CHECKER_CONTRACT_REGISTRY_NAME: &'static str = "secretstore_acl_checker";
 
+/// Maximal number of ACL check results kept in cache.
+const MAX_CACHED_RESULTS: usize = 16 * 1024;
+
 /// ACL storage of Secret Store
 pub trait AclStorage: Send + Sync {
 	/// Check if requestor can access document with hash `document`
 	fn check(&self, requester: Address, document: &ServerKeyId) -> Result<bool, Error>;
+	/// Get ACL check results cache counters, if results are cached.
+	fn cache_stats(&self) -> Option<AclCacheStats> { None }
+}
+
+/// ACL check results cache counters.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AclCacheStats {
+	/// Number of checks answered from cache.
+	pub hits: u64,
+	/// Number of checks which have called ACL contract.
+	pub misses: u64,
+	/// Number of cached results.
+	pub entries: usize,
 }
 
 /// On-chain ACL storage implementation.
 pub struct OnChainAclStorage {
 	/// Cached on-chain contract.
 	contract: Mutex<CachedContract>,
+	/// Cached contract call results, if caching is enabled.
+	cache: Option<Mutex<AclCache>>,
+}
+
+/// ACL check results, keyed by requester and document.
+///
+/// Results are dropped after TTL and when block with logs of ACL contract is imported.
+/// Negative results are only reused at the block they were evaluated at.
+struct AclCache {
+	/// Max age of cached result.
+	ttl: Duration,
+	/// Cached results.
+	results: HashMap<(Address, ServerKeyId), CachedResult>,
+	/// Cache counters.
+	stats: AclCacheStats,
+}
+
+/// Cached ACL check result.
+struct CachedResult {
+	/// Is access allowed?
+	allowed: bool,
+	/// Best block when result has been evaluated.
+	block: BlockNumber,
+	/// When result has been evaluated.
+	evaluated_at: Instant,
 }
 
 /// Cached on-chain ACL storage contract.
@@ -59,10 +102,11 @@ pub struct DummyAclStorage {
 }
 
 impl OnChainAclStorage {
-	pub fn new(trusted_client: TrustedClient, address_source: ContractAddress) -> Result<Arc<Self>, Error> {
+	pub fn new(trusted_client: TrustedClient, address_source: ContractAddress, cache_ttl: Option<Duration>) -> Result<Arc<Self>, Error> {
 		let client = trusted_client.get_untrusted();
 		let acl_storage = Arc::new(OnChainAclStorage {
 			contract: Mutex::new(CachedContract::new(trusted_client, address_source)),
+			cache: cache_ttl.map(|ttl| Mutex::new(AclCache::new(ttl))),
 		});
 		client
 			.ok_or_else(|| Error::Internal("Constructing OnChainAclStorage without active Client".into()))?
@@ -73,14 +117,38 @@ impl OnChainAclStorage {
 
 impl AclStorage for OnChainAclStorage {
 	fn check(&self, requester: Address, document: &ServerKeyId) -> Result<bool, Error> {
-		self.contract.lock().check(requester, document)
+		let mut contract = self.contract.lock();
+		match (self.cache.as_ref(), contract.best_block()) {
+			(Some(cache), Some(best_block)) => cache.lock()
+				.check(requester, document, best_block, Instant::now(), || contract.check(requester, document)),
+			_ => contract.check(requester, document),
+		}
+	}
+
+	fn cache_stats(&self) -> Option<AclCacheStats> {
+		self.cache.as_ref().map(|cache| cache.lock().stats())
 	}
 }
 
 impl ChainNotify for OnChainAclStorage {
 	fn new_blocks(&self, _imported: Vec<H256>, _invalid: Vec<H256>, route: ChainRoute, _sealed: Vec<H256>, _proposed: Vec<Bytes>, _duration: Duration) {
 		if !route.enacted().is_empty() || !route.retracted().is_empty() {
-			self.contract.lock().update_contract_address()
+			let mut contract = self.contract.lock();
+			let previous_contract_address = contract.contract_address;
+			contract.update_contract_address();
+
+			if let Some(ref cache) = self.cache {
+				let mut cache = cache.lock();
+				if !route.retracted().is_empty()
+					|| contract.contract_address != previous_contract_address
+					|| contract.has_logs(route.enacted()) {
+					cache.clear();
+				}
+
+				let stats = cache.stats();
+				debug!(target: "secretstore", "ACL cache: {} hits, {} misses, {} entries",
+					stats.hits, stats.misses, stats.entries);
+			}
 		}
 	}
 }
@@ -107,6 +175,26 @@ impl CachedContract {
 		}
 	}
 
+	pub fn best_block(&self) -> Option<BlockNumber> {
+		self.client.get().and_then(|client| client.block_number(BlockId::Latest))
+	}
+
+	pub fn has_logs(&self, blocks: &[H256]) -> bool {
+		let (client, contract_address) = match (self.client.get_untrusted(), self.contract_address) {
+			(Some(client), Some(contract_address)) => (client, contract_address),
+			_ => return false,
+		};
+
+		// when logs can't be read, assume the contract has changed
+		blocks.iter().any(|block| client.logs(Filter {
+			from_block: BlockId::Hash(block.clone()),
+			to_block: BlockId::Hash(block.clone()),
+			address: Some(vec![contract_address]),
+			topics: Vec::new(),
+			limit: Some(1),
+		}).map(|logs| !logs.is_empty()).unwrap_or(true))
+	}
+
 	pub fn check(&mut self, requester: Address, document: &ServerKeyId) -> Result<bool, Error> {
 		if let Some(client) = self.client.get() {
 			// call contract to check accesss
@@ -126,6 +214,56 @@ impl CachedContract {
 	}
 }
 
+impl AclCache {
+	pub fn new(ttl: Duration) -> Self {
+		AclCache {
+			ttl: ttl,
+			results: HashMap::new(),
+			stats: AclCacheStats::default(),
+		}
+	}
+
+	/// Check access using cached result, or result of `check` evaluated at `best_block`.
+	pub fn check<F>(&mut self, requester: Address, document: &ServerKeyId, best_block: BlockNumber, now: Instant, check: F) -> Result<bool, Error>
+		where F: FnOnce() -> Result<bool, Error>
+	{
+		let key = (requester, document.clone());
+		let cached = self.results.get(&key)
+			.filter(|result| now.duration_since(result.evaluated_at) < self.ttl)
+			.filter(|result| result.allowed || result.block >= best_block)
+			.map(|result| result.allowed);
+		if let Some(allowed) = cached {
+			self.stats.hits += 1;
+			return Ok(allowed);
+		}
+
+		self.stats.misses += 1;
+		let allowed = check()?;
+		if self.results.len() >= MAX_CACHED_RESULTS && !self.results.contains_key(&key) {
+			self.results.clear();
+		}
+		self.results.insert(key, CachedResult {
+			allowed: allowed,
+			block: best_block,
+			evaluated_at: now,
+		});
+		Ok(allowed)
+	}
+
+	/// Forget all cached results.
+	pub fn clear(&mut self) {
+		self.results.clear();
+	}
+
+	/// Get cache counters.
+	pub fn stats(&self) -> AclCacheStats {
+		AclCacheStats {
+			entries: self.results.len(),
+			..self.stats
+		}
+	}
+}
+
 impl DummyAclStorage {
 	/// Prohibit given requestor access to given documents
 	#[cfg(test)]
@@ -145,3 +283,50 @@ impl AclStorage for DummyAclStorage {
 			.unwrap_or(true))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+	use std::time::{Duration, Instant};
+	use ethereum_types::Address;
+	use types::ServerKeyId;
+	use super::{AclCache, AclCacheStats};
+
+	#[test]
+	fn revoked_permission_takes_effect_within_one_block() {
+		let mut cache = AclCache::new(Duration::from_secs(600));
+		let requester = Address::from(1);
+		let document = ServerKeyId::from(2);
+		let allowed = Cell::new(true);
+		let now = Instant::now();
+		let contract = || Ok(allowed.get());
+
+		assert_eq!(cache.check(requester, &document, 10, now, &contract), Ok(true));
+		assert_eq!(cache.check(requester, &document, 10, now, &contract), Ok(true));
+
+		// permission is revoked in block 11, which holds logs of ACL contract
+		allowed.set(false);
+		cache.clear();
+		assert_eq!(cache.check(requester, &document, 11, now, &contract), Ok(false));
+		assert_eq!(cache.check(requester, &document, 11, now, &contract), Ok(false));
+
+		// permission is granted back in block 12: negative result isn't reused even without invalidation
+		allowed.set(true);
+		assert_eq!(cache.check(requester, &document, 12, now, &contract), Ok(true));
+
+		assert_eq!(cache.stats(), AclCacheStats { hits: 2, misses: 3, entries: 1 });
+	}
+
+	#[test]
+	fn cached_results_expire() {
+		let mut cache = AclCache::new(Duration::from_secs(60));
+		let requester = Address::from(1);
+		let document = ServerKeyId::from(2);
+		let now = Instant::now();
+
+		assert_eq!(cache.check(requester, &document, 10, now, || Ok(true)), Ok(true));
+		assert_eq!(cache.check(requester, &document, 10, now + Duration::from_secs(59), || Ok(false)), Ok(true));
+		assert_eq!(cache.check(requester, &document, 10, now + Duration::from_secs(60), || Ok(false)), Ok(false));
+		assert_eq!(cache.stats(), AclCacheStats { hits: 1, misses: 2, entries: 1 });
+	}
+}
@@ -0,0 +1,342 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{BigEndian, ByteOrder};
+use parking_lot::Mutex;
+use serde_json;
+use ethereum_types::{H256, Address};
+use hash::keccak;
+use kvdb::KeyValueDB;
+use key_server_cluster::{ClusterSession, ClusterSessionsListener, SessionIdWithSubSession};
+use key_server_cluster::generation_session::SessionImpl as GenerationSession;
+use key_server_cluster::decryption_session::SessionImpl as DecryptionSession;
+use key_server_cluster::signing_session_schnorr::SessionImpl as SchnorrSigningSession;
+use key_server_cluster::signing_session_ecdsa::SessionImpl as EcdsaSigningSession;
+use serialization::SerializableAuditLogEntry;
+use types::{Error, NodeId, ServerKeyId, Requester, AuditedSession, AuditLogEntry, AuditLogQuery, AuditLogPage};
+
+/// Database column, where audit log entries are stored.
+pub const AUDIT_LOG_COLUMN: Option<u32> = Some(0);
+/// Max number of entries, returned in single page.
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Audit log of sessions, completed on this node.
+pub trait AuditLog: Send + Sync {
+	/// Append new entry to the log. Index of the entry is assigned by the log.
+	fn append(&self, entry: AuditLogEntry) -> Result<(), Error>;
+	/// Read page of entries, matching the query.
+	fn query(&self, query: &AuditLogQuery) -> Result<AuditLogPage, Error>;
+}
+
+/// Audit log, persisted in the separate column of key server database.
+/// Entries are keyed by big-endian index, so that database iteration order is the log order.
+pub struct PersistentAuditLog {
+	/// Database.
+	db: Arc<KeyValueDB>,
+	/// Max number of entries to keep. When exceeded, oldest entries are pruned.
+	retention: usize,
+	/// Indices of the first (oldest) entry and of the next entry.
+	indices: Mutex<(u64, u64)>,
+}
+
+/// Audit log sessions listener. Records entries for every completed session.
+pub struct AuditLogListener {
+	/// Audit log.
+	audit_log: Arc<AuditLog>,
+}
+
+impl PersistentAuditLog {
+	/// Open audit log, stored in given database.
+	pub fn new(db: Arc<KeyValueDB>, retention: usize) -> Result<Self, Error> {
+		let mut indices = (0, 0);
+		for (i, (key, _)) in db.iter(AUDIT_LOG_COLUMN).enumerate() {
+			let index = read_index(&key)?;
+			if i == 0 {
+				indices.0 = index;
+			}
+			indices.1 = index + 1;
+		}
+
+		Ok(PersistentAuditLog {
+			db: db,
+			retention: retention,
+			indices: Mutex::new(indices),
+		})
+	}
+}
+
+impl AuditLog for PersistentAuditLog {
+	fn append(&self, mut entry: AuditLogEntry) -> Result<(), Error> {
+		let mut indices = self.indices.lock();
+		let (mut first, next) = *indices;
+		entry.index = next;
+
+		let value = serde_json::to_vec(&SerializableAuditLogEntry::from(entry))
+			.map_err(|e| Error::Database(e.to_string()))?;
+		let mut batch = self.db.transaction();
+		batch.put(AUDIT_LOG_COLUMN, &index_key(next), &value);
+		while next + 1 - first > self.retention as u64 {
+			batch.delete(AUDIT_LOG_COLUMN, &index_key(first));
+			first += 1;
+		}
+		self.db.write(batch)?;
+
+		*indices = (first, next + 1);
+		Ok(())
+	}
+
+	fn query(&self, query: &AuditLogQuery) -> Result<AuditLogPage, Error> {
+		let limit = query.limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+		let start = query.after.map(|after| after + 1).unwrap_or(0);
+
+		let mut page = AuditLogPage::default();
+		for (key, value) in self.db.iter(AUDIT_LOG_COLUMN) {
+			if read_index(&key)? < start {
+				continue;
+			}
+
+			let entry: AuditLogEntry = serde_json::from_slice::<SerializableAuditLogEntry>(&value)
+				.map_err(|e| Error::Database(e.to_string()))?
+				.into();
+			if !is_matching(query, &entry) {
+				continue;
+			}
+
+			if page.entries.len() == limit {
+				page.next = page.entries.last().map(|entry| entry.index);
+				break;
+			}
+			page.entries.push(entry);
+		}
+
+		Ok(page)
+	}
+}
+
+impl AuditLogListener {
+	/// Create new listener, recording entries to given log.
+	pub fn new(audit_log: Arc<AuditLog>) -> Arc<Self> {
+		Arc::new(AuditLogListener {
+			audit_log: audit_log,
+		})
+	}
+
+	/// Record entry for completed session.
+	fn record(&self, session: AuditedSession, session_id: H256, document: ServerKeyId, requester: Option<Requester>,
+		requester_address: Option<Address>, outcome: Result<(), Error>, nodes: BTreeSet<NodeId>) {
+		let requester_public = requester.as_ref().and_then(|requester| requester.public(&document).ok());
+		let requester_address = requester_address
+			.or_else(|| requester.as_ref().and_then(|requester| requester.address(&document).ok()));
+		let entry = AuditLogEntry {
+			index: 0,
+			timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+			session: session,
+			session_id: session_id,
+			document: document,
+			requester_public: requester_public,
+			requester_address: requester_address,
+			error: outcome.err().map(|error| error.to_string()),
+			nodes: nodes,
+		};
+
+		if let Err(error) = self.audit_log.append(entry) {
+			warn!(target: "secretstore", "Failed to append {:?} session entry to audit log: {}", session, error);
+		}
+	}
+}
+
+impl ClusterSessionsListener<GenerationSession> for AuditLogListener {
+	fn on_session_removed(&self, session: Arc<GenerationSession>) {
+		let outcome = match session.joint_public_and_secret() {
+			Some(Ok(_)) => Ok(()),
+			Some(Err(error)) => Err(error),
+			None => Err(Error::InvalidStateForRequest),
+		};
+
+		let id = session.id();
+		self.record(AuditedSession::ServerKeyGeneration, id, id, None, session.author(), outcome, session.nodes());
+	}
+}
+
+impl ClusterSessionsListener<DecryptionSession> for AuditLogListener {
+	fn on_session_removed(&self, session: Arc<DecryptionSession>) {
+		let kind = match session.is_shadow_decryption() {
+			Some(true) => AuditedSession::DocumentKeyShadowRetrieval,
+			_ => AuditedSession::DocumentKeyRetrieval,
+		};
+
+		let id = session.id();
+		self.record(kind, sub_session_id(&id), id.id, session.requester(), None,
+			session.outcome(), session.participants());
+	}
+}
+
+impl ClusterSessionsListener<SchnorrSigningSession> for AuditLogListener {
+	fn on_session_removed(&self, session: Arc<SchnorrSigningSession>) {
+		let id = session.id();
+		self.record(AuditedSession::SchnorrSigning, sub_session_id(&id), id.id, session.requester(), None,
+			session.outcome(), session.participants());
+	}
+}
+
+impl ClusterSessionsListener<EcdsaSigningSession> for AuditLogListener {
+	fn on_session_removed(&self, session: Arc<EcdsaSigningSession>) {
+		let id = session.id();
+		self.record(AuditedSession::EcdsaSigning, sub_session_id(&id), id.id, session.requester(), None,
+			session.outcome(), session.participants());
+	}
+}
+
+/// Session id, recorded in the log for sessions with sub-session. Access key is secret, so only its hash is recorded.
+fn sub_session_id(id: &SessionIdWithSubSession) -> H256 {
+	let mut data = id.id.to_vec();
+	data.extend_from_slice(&**id.access_key);
+	keccak(data)
+}
+
+/// Check if entry matches the query.
+fn is_matching(query: &AuditLogQuery, entry: &AuditLogEntry) -> bool {
+	query.document.as_ref().map(|document| *document == entry.document).unwrap_or(true)
+		&& query.from.map(|from| entry.timestamp >= from).unwrap_or(true)
+		&& query.to.map(|to| entry.timestamp <= to).unwrap_or(true)
+}
+
+fn index_key(index: u64) -> [u8; 8] {
+	let mut key = [0u8; 8];
+	BigEndian::write_u64(&mut key, index);
+	key
+}
+
+fn read_index(key: &[u8]) -> Result<u64, Error> {
+	if key.len() != 8 {
+		return Err(Error::Database(format!("invalid audit log key length: {}", key.len())));
+	}
+
+	Ok(BigEndian::read_u64(key))
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::collections::BTreeSet;
+	use std::sync::Arc;
+	use parking_lot::Mutex;
+	use tempdir::TempDir;
+	use ethkey::{Random, Generator};
+	use kvdb_rocksdb::{Database, DatabaseConfig};
+	use types::{Error, AuditedSession, AuditLogEntry, AuditLogQuery, AuditLogPage};
+	use NUM_DB_COLUMNS;
+	use super::{AuditLog, PersistentAuditLog};
+
+	/// In-memory audit log.
+	#[derive(Default)]
+	pub struct DummyAuditLog {
+		entries: Mutex<Vec<AuditLogEntry>>,
+	}
+
+	impl DummyAuditLog {
+		/// Get all recorded entries.
+		pub fn entries(&self) -> Vec<AuditLogEntry> {
+			self.entries.lock().clone()
+		}
+	}
+
+	impl AuditLog for DummyAuditLog {
+		fn append(&self, mut entry: AuditLogEntry) -> Result<(), Error> {
+			let mut entries = self.entries.lock();
+			entry.index = entries.len() as u64;
+			entries.push(entry);
+			Ok(())
+		}
+
+		fn query(&self, query: &AuditLogQuery) -> Result<AuditLogPage, Error> {
+			Ok(AuditLogPage {
+				entries: self.entries.lock().iter()
+					.filter(|entry| super::is_matching(query, entry))
+					.cloned()
+					.collect(),
+				next: None,
+			})
+		}
+	}
+
+	fn open_db(path: &TempDir) -> Arc<Database> {
+		Arc::new(Database::open(&DatabaseConfig::with_columns(NUM_DB_COLUMNS), &path.path().display().to_string()).unwrap())
+	}
+
+	fn entry(document: u64, timestamp: u64) -> AuditLogEntry {
+		AuditLogEntry {
+			index: 0,
+			timestamp: timestamp,
+			session: AuditedSession::DocumentKeyRetrieval,
+			session_id: timestamp.into(),
+			document: document.into(),
+			requester_public: Some(Random.generate().unwrap().public().clone()),
+			requester_address: Some(1.into()),
+			error: None,
+			nodes: vec![Random.generate().unwrap().public().clone()].into_iter().collect::<BTreeSet<_>>(),
+		}
+	}
+
+	#[test]
+	fn persistent_audit_log_prunes_oldest_entries() {
+		let tempdir = TempDir::new("").unwrap();
+		{
+			let audit_log = PersistentAuditLog::new(open_db(&tempdir), 3).unwrap();
+			for i in 0..5 {
+				audit_log.append(entry(1, i)).unwrap();
+			}
+
+			let page = audit_log.query(&Default::default()).unwrap();
+			assert_eq!(page.entries.iter().map(|e| (e.index, e.timestamp)).collect::<Vec<_>>(), vec![(2, 2), (3, 3), (4, 4)]);
+			assert_eq!(page.next, None);
+		}
+
+		// indices are restored when log is reopened
+		let audit_log = PersistentAuditLog::new(open_db(&tempdir), 3).unwrap();
+		let recorded = entry(2, 5);
+		audit_log.append(recorded.clone()).unwrap();
+		let page = audit_log.query(&Default::default()).unwrap();
+		assert_eq!(page.entries.iter().map(|e| e.index).collect::<Vec<_>>(), vec![3, 4, 5]);
+		assert_eq!(page.entries[2], AuditLogEntry { index: 5, ..recorded });
+	}
+
+	#[test]
+	fn persistent_audit_log_filters_and_paginates() {
+		let tempdir = TempDir::new("").unwrap();
+		let audit_log = PersistentAuditLog::new(open_db(&tempdir), 100).unwrap();
+		for i in 0..10 {
+			audit_log.append(entry(i % 2, i)).unwrap();
+		}
+
+		let query = AuditLogQuery {
+			document: Some(1.into()),
+			from: Some(2),
+			to: Some(8),
+			after: None,
+			limit: Some(2),
+		};
+		let page = audit_log.query(&query).unwrap();
+		assert_eq!(page.entries.iter().map(|e| e.index).collect::<Vec<_>>(), vec![3, 5]);
+		assert_eq!(page.next, Some(5));
+
+		let page = audit_log.query(&AuditLogQuery { after: page.next, ..query }).unwrap();
+		assert_eq!(page.entries.iter().map(|e| e.index).collect::<Vec<_>>(), vec![7]);
+		assert_eq!(page.next, None);
+	}
+}
@@ -22,15 +22,16 @@ use futures::{self, Future};
 use parking_lot::Mutex;
 use tokio_core::reactor::Core;
 use crypto::DEFAULT_MAC;
-use ethkey::crypto;
+use ethkey::{crypto, verify_public};
 use super::acl_storage::AclStorage;
+use super::audit_log::{AuditLog, AuditLogListener};
 use super::key_storage::KeyStorage;
 use super::key_server_set::KeyServerSet;
 use key_server_cluster::{math, ClusterCore};
-use traits::{AdminSessionsServer, ServerKeyGenerator, DocumentKeyServer, MessageSigner, KeyServer, NodeKeyPair};
+use traits::{AdminSessionsServer, AuditLogServer, ServerKeyGenerator, DocumentKeyServer, MessageSigner, TlsReloader, KeyServer, NodeKeyPair};
 use types::{Error, Public, RequestSignature, Requester, ServerKeyId, EncryptedDocumentKey, EncryptedDocumentKeyShadow,
-	ClusterConfiguration, MessageHash, EncryptedMessageSignature, NodeId};
-use key_server_cluster::{ClusterClient, ClusterConfiguration as NetClusterConfiguration};
+	ClusterConfiguration, MessageHash, EncryptedMessageSignature, NodeId, AuditLogQuery, AuditLogPage};
+use key_server_cluster::{ClusterClient, ClusterTls, ClusterConfiguration as NetClusterConfiguration};
 
 /// Secret store key server implementation
 pub struct KeyServerImpl {
@@ -42,13 +43,17 @@ pub struct KeyServerCore {
 	close: Option<futures::Complete<()>>,
 	handle: Option<thread::JoinHandle<()>>,
 	cluster: Arc<ClusterClient>,
+	admin_public: Option<Public>,
+	audit_log: Arc<AuditLog>,
+	_audit_log_listener: Arc<AuditLogListener>,
+	tls: Option<Arc<ClusterTls>>,
 }
 
 impl KeyServerImpl {
 	/// Create new key server instance
-	pub fn new(config: &ClusterConfiguration, key_server_set: Arc<KeyServerSet>, self_key_pair: Arc<NodeKeyPair>, acl_storage: Arc<AclStorage>, key_storage: Arc<KeyStorage>) -> Result<Self, Error> {
+	pub fn new(config: &ClusterConfiguration, key_server_set: Arc<KeyServerSet>, self_key_pair: Arc<NodeKeyPair>, acl_storage: Arc<AclStorage>, key_storage: Arc<KeyStorage>, audit_log: Arc<AuditLog>) -> Result<Self, Error> {
 		Ok(KeyServerImpl {
-			data: Arc::new(Mutex::new(KeyServerCore::new(config, key_server_set, self_key_pair, acl_storage, key_storage)?)),
+			data: Arc::new(Mutex::new(KeyServerCore::new(config, key_server_set, self_key_pair, acl_storage, key_storage, audit_log)?)),
 		})
 	}
 
@@ -68,6 +73,31 @@ impl AdminSessionsServer for KeyServerImpl {
 			.expect("new_servers_set_change_session creates servers_set_change_session; qed")
 			.wait().map_err(Into::into)
 	}
+
+	fn retire_key(&self, admin_signature: RequestSignature, key_id: ServerKeyId) -> Result<(), Error> {
+		let key_retirement_session = self.data.lock().cluster
+			.new_key_retirement_session(key_id, admin_signature)?;
+		key_retirement_session.as_key_retirement()
+			.expect("new_key_retirement_session creates key_retirement_session; qed")
+			.wait(None).map_err(Into::into)
+	}
+}
+
+impl AuditLogServer for KeyServerImpl {
+	fn audit_log(&self, admin_signature: RequestSignature, query: AuditLogQuery) -> Result<AuditLogPage, Error> {
+		let (admin_public, audit_log) = {
+			let data = self.data.lock();
+			(data.admin_public.clone(), data.audit_log.clone())
+		};
+
+		// only administrator is allowed to read the audit log
+		let admin_public = admin_public.ok_or(Error::AccessDenied)?;
+		if !verify_public(&admin_public, &admin_signature, &query.hash()).unwrap_or(false) {
+			return Err(Error::AccessDenied);
+		}
+
+		audit_log.query(&query)
+	}
 }
 
 impl ServerKeyGenerator for KeyServerImpl {
@@ -174,8 +204,22 @@ impl MessageSigner for KeyServerImpl {
 	}
 }
 
+impl TlsReloader for KeyServerImpl {
+	fn reload_tls(&self) -> Result<(), Error> {
+		match self.data.lock().tls {
+			Some(ref tls) => tls.reload(),
+			None => Ok(()),
+		}
+	}
+}
+
 impl KeyServerCore {
-	pub fn new(config: &ClusterConfiguration, key_server_set: Arc<KeyServerSet>, self_key_pair: Arc<NodeKeyPair>, acl_storage: Arc<AclStorage>, key_storage: Arc<KeyStorage>) -> Result<Self, Error> {
+	pub fn new(config: &ClusterConfiguration, key_server_set: Arc<KeyServerSet>, self_key_pair: Arc<NodeKeyPair>, acl_storage: Arc<AclStorage>, key_storage: Arc<KeyStorage>, audit_log: Arc<AuditLog>) -> Result<Self, Error> {
+		let admin_public = config.admin_public.clone();
+		let tls = match config.tls {
+			Some(ref tls) => Some(Arc::new(ClusterTls::new(tls.clone())?)),
+			None => None,
+		};
 		let config = NetClusterConfiguration {
 			threads: config.threads,
 			self_key_pair: self_key_pair.clone(),
@@ -186,6 +230,8 @@ impl KeyServerCore {
 			key_storage: key_storage,
 			admin_public: config.admin_public.clone(),
 			auto_migrate_enabled: config.auto_migrate_enabled,
+			key_retirement_grace_period: config.key_retirement_grace_period,
+			tls: tls.clone(),
 		};
 
 		let (stop, stopped) = futures::oneshot();
@@ -208,10 +254,21 @@ impl KeyServerCore {
 		}).map_err(|e| Error::Internal(format!("{}", e)))?;
 		let cluster = rx.recv().map_err(|e| Error::Internal(format!("error initializing event loop: {}", e)))??;
 
+		// record every completed client session in the audit log
+		let audit_log_listener = AuditLogListener::new(audit_log.clone());
+		cluster.add_generation_listener(audit_log_listener.clone());
+		cluster.add_decryption_listener(audit_log_listener.clone());
+		cluster.add_schnorr_signing_listener(audit_log_listener.clone());
+		cluster.add_ecdsa_signing_listener(audit_log_listener.clone());
+
 		Ok(KeyServerCore {
 			close: Some(stop),
 			handle: Some(handle),
 			cluster: cluster,
+			admin_public: admin_public,
+			audit_log: audit_log,
+			_audit_log_listener: audit_log_listener,
+			tls: tls,
 		})
 	}
 }
@@ -226,23 +283,26 @@ impl Drop for KeyServerCore {
 #[cfg(test)]
 pub mod tests {
 	use std::collections::BTreeSet;
+	use std::thread;
 	use std::time;
 	use std::sync::Arc;
 	use std::net::SocketAddr;
 	use std::collections::BTreeMap;
 	use crypto::DEFAULT_MAC;
-	use ethkey::{self, crypto, Secret, Random, Generator, verify_public};
+	use ethkey::{self, crypto, Secret, KeyPair, Random, Generator, verify_public};
 	use acl_storage::DummyAclStorage;
+	use audit_log::tests::DummyAuditLog;
 	use key_storage::KeyStorage;
 	use key_storage::tests::DummyKeyStorage;
 	use node_key_pair::PlainNodeKeyPair;
 	use key_server_set::tests::MapKeyServerSet;
 	use key_server_cluster::math;
 	use ethereum_types::{H256, H520};
-	use types::{Error, Public, ClusterConfiguration, NodeAddress, RequestSignature, ServerKeyId,
+	use tls::tests::{node_secret, test_tls_config, test_tls_path};
+	use types::{Error, Public, ClusterConfiguration, ClusterTlsConfiguration, NodeAddress, RequestSignature, ServerKeyId,
 		EncryptedDocumentKey, EncryptedDocumentKeyShadow, MessageHash, EncryptedMessageSignature,
-		Requester, NodeId};
-	use traits::{AdminSessionsServer, ServerKeyGenerator, DocumentKeyServer, MessageSigner, KeyServer};
+		Requester, NodeId, AuditedSession, AuditLogQuery, AuditLogPage};
+	use traits::{AdminSessionsServer, AuditLogServer, ServerKeyGenerator, DocumentKeyServer, MessageSigner, TlsReloader, KeyServer};
 	use super::KeyServerImpl;
 
 	#[derive(Default)]
@@ -254,6 +314,16 @@ pub mod tests {
 		fn change_servers_set(&self, _old_set_signature: RequestSignature, _new_set_signature: RequestSignature, _new_servers_set: BTreeSet<NodeId>) -> Result<(), Error> {
 			unimplemented!("test-only")
 		}
+
+		fn retire_key(&self, _admin_signature: RequestSignature, _key_id: ServerKeyId) -> Result<(), Error> {
+			unimplemented!("test-only")
+		}
+	}
+
+	impl AuditLogServer for DummyKeyServer {
+		fn audit_log(&self, _admin_signature: RequestSignature, _query: AuditLogQuery) -> Result<AuditLogPage, Error> {
+			unimplemented!("test-only")
+		}
 	}
 
 	impl ServerKeyGenerator for DummyKeyServer {
@@ -290,9 +360,28 @@ pub mod tests {
 		}
 	}
 
-	fn make_key_servers(start_port: u16, num_nodes: usize) -> (Vec<KeyServerImpl>, Vec<Arc<DummyKeyStorage>>) {
+	impl TlsReloader for DummyKeyServer {
+		fn reload_tls(&self) -> Result<(), Error> {
+			Ok(())
+		}
+	}
+
+	pub fn make_key_servers(start_port: u16, num_nodes: usize) -> (Vec<KeyServerImpl>, Vec<Arc<DummyKeyStorage>>) {
+		let (key_servers, key_storages, _) = make_key_servers_with_audit_logs(start_port, num_nodes, None);
+		(key_servers, key_storages)
+	}
+
+	pub fn make_key_servers_with_audit_logs(start_port: u16, num_nodes: usize, admin_public: Option<Public>) -> (Vec<KeyServerImpl>, Vec<Arc<DummyKeyStorage>>, Vec<Arc<DummyAuditLog>>) {
 		let key_pairs: Vec<_> = (0..num_nodes).map(|_| Random.generate().unwrap()).collect();
-		let configs: Vec<_> = (0..num_nodes).map(|i| ClusterConfiguration {
+		let tls = (0..num_nodes).map(|_| None).collect();
+		let (key_servers, key_storages, audit_logs) = start_key_servers(start_port, key_pairs, admin_public, tls);
+		wait_key_servers_connected(&key_servers);
+		(key_servers, key_storages, audit_logs)
+	}
+
+	fn start_key_servers(start_port: u16, key_pairs: Vec<KeyPair>, admin_public: Option<Public>, tls: Vec<Option<ClusterTlsConfiguration>>) -> (Vec<KeyServerImpl>, Vec<Arc<DummyKeyStorage>>, Vec<Arc<DummyAuditLog>>) {
+		let num_nodes = key_pairs.len();
+		let configs: Vec<_> = tls.into_iter().enumerate().map(|(i, tls)| ClusterConfiguration {
 				threads: 1,
 				listener_address: NodeAddress {
 					address: "127.0.0.1".into(),
@@ -305,20 +394,30 @@ pub mod tests {
 					})).collect(),
 				key_server_set_contract_address: None,
 				allow_connecting_to_higher_nodes: false,
-				admin_public: None,
+				admin_public: admin_public.clone(),
 				auto_migrate_enabled: false,
+				key_retirement_grace_period: time::Duration::from_secs(86400),
+				tls: tls,
 			}).collect();
 		let key_servers_set: BTreeMap<Public, SocketAddr> = configs[0].nodes.iter()
 			.map(|(k, a)| (k.clone(), format!("{}:{}", a.address, a.port).parse().unwrap()))
 			.collect();
 		let key_storages = (0..num_nodes).map(|_| Arc::new(DummyKeyStorage::default())).collect::<Vec<_>>();
+		let audit_logs = (0..num_nodes).map(|_| Arc::new(DummyAuditLog::default())).collect::<Vec<_>>();
 		let key_servers: Vec<_> = configs.into_iter().enumerate().map(|(i, cfg)|
 			KeyServerImpl::new(&cfg, Arc::new(MapKeyServerSet::new(false, key_servers_set.clone())),
 				Arc::new(PlainNodeKeyPair::new(key_pairs[i].clone())),
 				Arc::new(DummyAclStorage::default()),
-				key_storages[i].clone()).unwrap()
+				key_storages[i].clone(),
+				audit_logs[i].clone()).unwrap()
 		).collect();
 
+		(key_servers, key_storages, audit_logs)
+	}
+
+	fn wait_key_servers_connected(key_servers: &[KeyServerImpl]) {
+		let num_nodes = key_servers.len();
+
 		// wait until connections are established. It is fast => do not bother with events here
 		let start = time::Instant::now();
 		let mut tried_reconnections = false;
@@ -329,7 +428,7 @@ pub mod tests {
 
 			let old_tried_reconnections = tried_reconnections;
 			let mut fully_connected = true;
-			for key_server in &key_servers {
+			for key_server in key_servers {
 				if key_server.cluster().cluster_state().connected.len() != num_nodes - 1 {
 					fully_connected = false;
 					if !old_tried_reconnections {
@@ -345,8 +444,14 @@ pub mod tests {
 				panic!("connections are not established in 1000ms");
 			}
 		}
+	}
 
-		(key_servers, key_storages)
+	fn cluster_tls_config(node_index: usize, required: bool) -> Option<ClusterTlsConfiguration> {
+		Some(ClusterTlsConfiguration {
+			node: test_tls_config(&format!("node{}.crt", node_index), &format!("node{}.key", node_index)),
+			ca_path: test_tls_path("cluster-ca.crt"),
+			required: required,
+		})
 	}
 
 	#[test]
@@ -526,6 +631,107 @@ pub mod tests {
 		assert!(verify_public(&server_public, &signature.into(), &message_hash).unwrap());
 	}
 
+	#[test]
+	fn document_key_retrieval_is_recorded_in_audit_log_of_all_nodes() {
+		//::logger::init_log();
+		let admin_key_pair = Random.generate().unwrap();
+		let (key_servers, _, audit_logs) = make_key_servers_with_audit_logs(6124, 3, Some(admin_key_pair.public().clone()));
+
+		// generate document key
+		let threshold = 2;
+		let document = Random.generate().unwrap().secret().clone();
+		let requester_key_pair = Random.generate().unwrap();
+		let signature = ethkey::sign(requester_key_pair.secret(), &document).unwrap();
+		key_servers[0].generate_document_key(&document, &signature.clone().into(), threshold).unwrap();
+
+		// retrieve it
+		key_servers[1].restore_document_key(&document, &signature.clone().into()).unwrap();
+
+		// all participating nodes record the same retrieval session
+		let retrieval_entries = |audit_log: &DummyAuditLog| audit_log.entries().into_iter()
+			.filter(|entry| entry.session == AuditedSession::DocumentKeyRetrieval)
+			.collect::<Vec<_>>();
+		let start = time::Instant::now();
+		while audit_logs.iter().any(|audit_log| retrieval_entries(audit_log).is_empty()) {
+			if time::Instant::now() - start > time::Duration::from_millis(3000) {
+				panic!("retrieval session is not recorded in 3000ms");
+			}
+			::std::thread::sleep(time::Duration::from_millis(10));
+		}
+
+		// master knows all participants, other nodes only know master and themselves
+		let master_entries = retrieval_entries(&audit_logs[1]);
+		assert_eq!(master_entries.len(), 1);
+		assert_eq!(master_entries[0].nodes.len(), 3);
+		for audit_log in &audit_logs {
+			let entries = retrieval_entries(audit_log);
+			assert_eq!(entries.len(), 1);
+			assert_eq!(entries[0].session_id, master_entries[0].session_id);
+			assert_eq!(entries[0].document, *document);
+			assert_eq!(entries[0].requester_public, Some(requester_key_pair.public().clone()));
+			assert_eq!(entries[0].requester_address, Some(requester_key_pair.address()));
+			assert_eq!(entries[0].error, None);
+			assert!(entries[0].nodes.len() >= 2 && entries[0].nodes.is_subset(&master_entries[0].nodes));
+		}
+
+		// audit log is only accessible to administrator
+		let query = AuditLogQuery { document: Some(*document), ..Default::default() };
+		let admin_signature = ethkey::sign(admin_key_pair.secret(), &query.hash()).unwrap();
+		let page = key_servers[0].audit_log(admin_signature, query.clone()).unwrap();
+		assert!(page.entries.iter().any(|entry| entry.session == AuditedSession::DocumentKeyRetrieval));
+		assert_eq!(key_servers[0].audit_log(signature, query), Err(Error::AccessDenied));
+	}
+
+	#[test]
+	fn server_key_generation_works_over_tls_connections() {
+		//::logger::init_log();
+		let key_pairs = vec![KeyPair::from_secret(node_secret(1)).unwrap(), KeyPair::from_secret(node_secret(2)).unwrap()];
+		let (key_servers, key_storages, _) = start_key_servers(6130, key_pairs, None, vec![cluster_tls_config(1, true), cluster_tls_config(2, true)]);
+		wait_key_servers_connected(&key_servers);
+
+		// generate server key, using both nodes
+		let server_key_id = Random.generate().unwrap().secret().clone();
+		let requestor_secret = Random.generate().unwrap().secret().clone();
+		let signature = ethkey::sign(&requestor_secret, &server_key_id).unwrap();
+		let server_public = key_servers[0].generate_key(&server_key_id, &signature.clone().into(), 1).unwrap();
+		for key_storage in &key_storages {
+			assert_eq!(key_storage.get(&server_key_id).unwrap().unwrap().public, server_public);
+		}
+
+		// certificates are read again on request
+		for key_server in &key_servers {
+			key_server.reload_tls().unwrap();
+		}
+		assert_eq!(key_servers[1].cluster().cluster_state().connected.len(), 1);
+	}
+
+	#[test]
+	fn key_servers_do_not_connect_when_certificate_is_issued_for_other_node() {
+		//::logger::init_log();
+		// node with the key of node 2 presents the certificate of node 1
+		let key_pairs = vec![KeyPair::from_secret(node_secret(1)).unwrap(), KeyPair::from_secret(node_secret(2)).unwrap()];
+		let (key_servers, _, _) = start_key_servers(6132, key_pairs, None, vec![cluster_tls_config(1, true), cluster_tls_config(1, true)]);
+		for key_server in &key_servers {
+			key_server.cluster().connect();
+		}
+
+		thread::sleep(time::Duration::from_millis(300));
+		assert!(key_servers.iter().all(|ks| ks.cluster().cluster_state().connected.is_empty()));
+	}
+
+	#[test]
+	fn plaintext_connections_are_refused_when_tls_is_required() {
+		//::logger::init_log();
+		let key_pairs = vec![KeyPair::from_secret(node_secret(1)).unwrap(), KeyPair::from_secret(node_secret(2)).unwrap()];
+		let (key_servers, _, _) = start_key_servers(6134, key_pairs, None, vec![None, cluster_tls_config(2, true)]);
+		for key_server in &key_servers {
+			key_server.cluster().connect();
+		}
+
+		thread::sleep(time::Duration::from_millis(300));
+		assert!(key_servers.iter().all(|ks| ks.cluster().cluster_state().connected.is_empty()));
+	}
+
 	#[test]
 	fn servers_set_change_session_works_over_network() {
 		// TODO [Test]
@@ -0,0 +1,565 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter, Error as FmtError};
+use std::sync::Arc;
+use std::time::{self, SystemTime, UNIX_EPOCH};
+use parking_lot::{Condvar, Mutex};
+use ethereum_types::H256;
+use ethkey::{Public, Signature, verify_public};
+use hash::keccak;
+use key_server_cluster::{Error, NodeId, SessionId, KeyStorage, KeyRetirement, DocumentKeyShare, ServerKeyId};
+use key_server_cluster::cluster::Cluster;
+use key_server_cluster::cluster_sessions::ClusterSession;
+use key_server_cluster::message::{Message, KeyRetirementMessage, PrepareKeyRetirement, ConfirmKeyRetirementPreparation,
+	CommitKeyRetirement, KeyRetirementError, ServersSetChangeMessage};
+
+/// Key retirement session.
+/// Brief overview:
+/// 1) master node (which has received administrator request) broadcasts administrator signature to all connected nodes
+/// 2) every node checks the signature and confirms that it is prepared to retire the key
+/// 3) if key can't be restored by owners which are unaware of retirement (i.e. at least owners - threshold owners
+///    of every key version are prepared), master commits retirement on all nodes. Otherwise nothing is changed
+/// 4) retired key is refused by all sessions and its share is erased after grace period
+/// 5) nodes that were offline during retirement receive all retired keys when connection is established
+pub struct SessionImpl {
+	/// Key id.
+	id: SessionId,
+	/// Public identifier of this node.
+	self_node_id: NodeId,
+	/// Id of node, which has started this session.
+	master_node_id: NodeId,
+	/// Administrator public key.
+	admin_public: Public,
+	/// Key storage.
+	key_storage: Arc<KeyStorage>,
+	/// Cluster which allows this node to send messages to other nodes in the cluster.
+	cluster: Arc<Cluster>,
+	/// Session nonce.
+	nonce: u64,
+	/// SessionImpl completion condvar.
+	completed: Condvar,
+	/// Mutable session data.
+	data: Mutex<SessionData>,
+}
+
+/// SessionImpl creation parameters
+pub struct SessionParams {
+	/// Key id.
+	pub id: SessionId,
+	/// Id of node, on which this session is running.
+	pub self_node_id: NodeId,
+	/// Id of node, which has started this session.
+	pub master_node_id: NodeId,
+	/// Administrator public key.
+	pub admin_public: Public,
+	/// Key storage.
+	pub key_storage: Arc<KeyStorage>,
+	/// Cluster
+	pub cluster: Arc<Cluster>,
+	/// Session nonce.
+	pub nonce: u64,
+}
+
+/// Mutable data of key retirement session.
+#[derive(Debug)]
+struct SessionData {
+	/// Current state of the session.
+	state: SessionState,
+	/// Key id, signed by administrator.
+	admin_signature: Option<Signature>,
+	/// Key share (on master node only).
+	key_share: Option<DocumentKeyShare>,
+	/// Nodes, participating in session, along with their preparation flags (on master node only).
+	nodes: BTreeMap<NodeId, bool>,
+	/// Key retirement session result.
+	result: Option<Result<(), Error>>,
+}
+
+/// Key retirement session state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionState {
+	/// Every node starts in this state.
+	WaitingForPreparation,
+	/// Master node waits for every other node to confirm preparation.
+	WaitingForPreparationConfirm,
+	/// Slave node is prepared and waits for retirement commit.
+	WaitingForCommit,
+	/// Key is retired.
+	Finished,
+	/// Key retirement has failed. Nothing is changed.
+	Failed,
+}
+
+impl SessionImpl {
+	/// Create new key retirement session.
+	pub fn new(params: SessionParams) -> Self {
+		SessionImpl {
+			id: params.id,
+			self_node_id: params.self_node_id,
+			master_node_id: params.master_node_id,
+			admin_public: params.admin_public,
+			key_storage: params.key_storage,
+			cluster: params.cluster,
+			nonce: params.nonce,
+			completed: Condvar::new(),
+			data: Mutex::new(SessionData {
+				state: SessionState::WaitingForPreparation,
+				admin_signature: None,
+				key_share: None,
+				nodes: BTreeMap::new(),
+				result: None,
+			}),
+		}
+	}
+
+	/// Get this node Id.
+	pub fn node(&self) -> &NodeId {
+		&self.self_node_id
+	}
+
+	/// Get session state.
+	pub fn state(&self) -> SessionState {
+		self.data.lock().state.clone()
+	}
+
+	/// Wait for session completion.
+	pub fn wait(&self, timeout: Option<time::Duration>) -> Result<(), Error> {
+		Self::wait_session(&self.completed, &self.data, timeout, |data| data.result.clone())
+			.expect("wait_session returns Some if called without timeout; qed")
+	}
+
+	/// Start new session initialization. This must be called on master node.
+	pub fn initialize(&self, admin_signature: Signature) -> Result<(), Error> {
+		debug_assert_eq!(self.self_node_id, self.master_node_id);
+
+		let mut data = self.data.lock();
+
+		// check state
+		if data.state != SessionState::WaitingForPreparation {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// check that the request is made by administrator && the key could be retired
+		check_admin_signature(&self.admin_public, &self.id, &admin_signature)?;
+		check_key_is_not_retired(&*self.key_storage, &self.id)?;
+		let key_share = self.key_storage.get(&self.id)?.ok_or(Error::ServerKeyIsNotFound)?;
+
+		// update state
+		data.state = SessionState::WaitingForPreparationConfirm;
+		data.admin_signature = Some(admin_signature.clone());
+		data.key_share = Some(key_share);
+		data.nodes.extend(self.cluster.nodes().into_iter().map(|n| {
+			let is_prepared = &n == self.node();
+			(n, is_prepared)
+		}));
+
+		// start preparation
+		if data.nodes.len() > 1 {
+			self.cluster.broadcast(Message::KeyRetirement(KeyRetirementMessage::PrepareKeyRetirement(PrepareKeyRetirement {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				admin_signature: admin_signature.into(),
+			})))
+		} else {
+			self.commit(&mut data)
+		}
+	}
+
+	/// When key retirement preparation is requested.
+	pub fn on_prepare_key_retirement(&self, sender: NodeId, message: &PrepareKeyRetirement) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check message
+		if sender != self.master_node_id {
+			return Err(Error::InvalidMessage);
+		}
+		if data.state != SessionState::WaitingForPreparation {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// every node checks that the request is made by administrator
+		let admin_signature: Signature = message.admin_signature.clone().into();
+		check_admin_signature(&self.admin_public, &self.id, &admin_signature)?;
+
+		// update state
+		data.state = SessionState::WaitingForCommit;
+		data.admin_signature = Some(admin_signature);
+
+		// confirm preparation
+		self.cluster.send(&sender, Message::KeyRetirement(KeyRetirementMessage::ConfirmKeyRetirementPreparation(ConfirmKeyRetirementPreparation {
+			session: self.id.clone().into(),
+			session_nonce: self.nonce,
+		})))
+	}
+
+	/// When key retirement preparation is confirmed.
+	pub fn on_confirm_key_retirement_preparation(&self, sender: NodeId, message: &ConfirmKeyRetirementPreparation) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check message
+		if self.self_node_id != self.master_node_id {
+			return Err(Error::InvalidMessage);
+		}
+		if data.state != SessionState::WaitingForPreparationConfirm {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// wait for all nodes to confirm preparation
+		match data.nodes.get_mut(&sender) {
+			Some(ref mut is_prepared) if !**is_prepared => **is_prepared = true,
+			_ => return Err(Error::InvalidNodeForRequest),
+		}
+		if !data.nodes.values().all(|is_prepared| *is_prepared) {
+			return Ok(());
+		}
+
+		self.commit(&mut data)
+	}
+
+	/// When key retirement is committed.
+	pub fn on_commit_key_retirement(&self, sender: NodeId, message: &CommitKeyRetirement) -> Result<(), Error> {
+		debug_assert!(self.id == *message.session);
+		debug_assert!(&sender != self.node());
+
+		let mut data = self.data.lock();
+
+		// check message
+		if sender != self.master_node_id {
+			return Err(Error::InvalidMessage);
+		}
+		if data.state != SessionState::WaitingForCommit {
+			return Err(Error::InvalidStateForRequest);
+		}
+
+		// retire key
+		self.key_storage.retire(self.id.clone(), KeyRetirement {
+			retired_at: message.retired_at,
+			admin_signature: data.admin_signature.clone().expect("admin_signature is filled when entering WaitingForCommit state; qed"),
+		})?;
+
+		// update state
+		data.state = SessionState::Finished;
+		data.result = Some(Ok(()));
+		self.completed.notify_all();
+
+		Ok(())
+	}
+
+	/// Commit key retirement if quorum of owners is prepared. This must be called on master node.
+	fn commit(&self, data: &mut SessionData) -> Result<(), Error> {
+		// key share can't be restored by unprepared owners only if there are at most threshold of them
+		let is_quorum_prepared = {
+			let key_share = data.key_share.as_ref().expect("key_share is filled on master when entering WaitingForPreparationConfirm state; qed");
+			key_share.versions.iter().all(|version| version.id_numbers.keys()
+				.filter(|owner| !data.nodes.get(*owner).cloned().unwrap_or(false))
+				.count() <= key_share.threshold)
+		};
+		if !is_quorum_prepared {
+			return Err(Error::ConsensusUnreachable);
+		}
+
+		// retire key
+		let retired_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+		self.key_storage.retire(self.id.clone(), KeyRetirement {
+			retired_at: retired_at,
+			admin_signature: data.admin_signature.clone().expect("admin_signature is filled on master when entering WaitingForPreparationConfirm state; qed"),
+		})?;
+
+		// update state
+		data.state = SessionState::Finished;
+		data.result = Some(Ok(()));
+		self.completed.notify_all();
+
+		// commit on all prepared nodes
+		if data.nodes.len() > 1 {
+			self.cluster.broadcast(Message::KeyRetirement(KeyRetirementMessage::CommitKeyRetirement(CommitKeyRetirement {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				retired_at: retired_at,
+			})))?;
+		}
+
+		Ok(())
+	}
+}
+
+impl ClusterSession for SessionImpl {
+	type Id = SessionId;
+
+	fn type_name() -> &'static str {
+		"key retirement"
+	}
+
+	fn id(&self) -> SessionId {
+		self.id.clone()
+	}
+
+	fn is_finished(&self) -> bool {
+		let data = self.data.lock();
+		data.state == SessionState::Failed
+			|| data.state == SessionState::Finished
+	}
+
+	fn on_node_timeout(&self, node: &NodeId) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key retirement session failed because {} connection has timeouted", self.node(), node);
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(Error::NodeDisconnected));
+		self.completed.notify_all();
+	}
+
+	fn on_session_timeout(&self) {
+		let mut data = self.data.lock();
+
+		warn!("{}: key retirement session failed with timeout", self.node());
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(Error::NodeDisconnected));
+		self.completed.notify_all();
+	}
+
+	fn on_session_error(&self, node: &NodeId, error: Error) {
+		// error in key retirement session is considered fatal
+		// => broadcast error if error occured on this node, so that prepared nodes are rolled back
+		if *node == self.self_node_id {
+			// do not bother processing send error, as we already processing error
+			let _ = self.cluster.broadcast(Message::KeyRetirement(KeyRetirementMessage::KeyRetirementError(KeyRetirementError {
+				session: self.id.clone().into(),
+				session_nonce: self.nonce,
+				error: error.clone().into(),
+			})));
+		}
+
+		let mut data = self.data.lock();
+
+		warn!("{}: key retirement session failed with error: {} from {}", self.node(), error, node);
+
+		data.state = SessionState::Failed;
+		data.result = Some(Err(error));
+		self.completed.notify_all();
+	}
+
+	fn on_message(&self, sender: &NodeId, message: &Message) -> Result<(), Error> {
+		if Some(self.nonce) != message.session_nonce() {
+			return Err(Error::ReplayProtection);
+		}
+
+		match message {
+			&Message::KeyRetirement(ref message) => match message {
+				&KeyRetirementMessage::PrepareKeyRetirement(ref message) =>
+					self.on_prepare_key_retirement(sender.clone(), message),
+				&KeyRetirementMessage::ConfirmKeyRetirementPreparation(ref message) =>
+					self.on_confirm_key_retirement_preparation(sender.clone(), message),
+				&KeyRetirementMessage::CommitKeyRetirement(ref message) =>
+					self.on_commit_key_retirement(sender.clone(), message),
+				&KeyRetirementMessage::KeyRetirementError(ref message) => {
+					self.on_session_error(sender, message.error.clone());
+					Ok(())
+				},
+			},
+			// cluster reports admin sessions creation errors using this message
+			&Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(ref message)) => {
+				self.on_session_error(sender, message.error.clone());
+				Ok(())
+			},
+			_ => unreachable!("cluster checks message to be correct before passing; qed"),
+		}
+	}
+}
+
+impl Debug for SessionImpl {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+		write!(f, "Key retirement session {} on {}", self.id, self.self_node_id)
+	}
+}
+
+/// Hash, which must be signed by administrator to retire the key. It differs from the key id, so that
+/// signatures of requests, made by administrator as a regular requester, can't be used to retire keys.
+pub fn key_retirement_hash(key_id: &ServerKeyId) -> H256 {
+	let mut data = b"key_retirement".to_vec();
+	data.extend_from_slice(&**key_id);
+	keccak(data)
+}
+
+/// Check that the key retirement is requested by administrator.
+pub fn check_admin_signature(admin_public: &Public, key_id: &ServerKeyId, admin_signature: &Signature) -> Result<(), Error> {
+	match verify_public(admin_public, admin_signature, &key_retirement_hash(key_id)) {
+		Ok(true) => Ok(()),
+		_ => Err(Error::AccessDenied),
+	}
+}
+
+/// Check that the key is not retired.
+pub fn check_key_is_not_retired(key_storage: &KeyStorage, key_id: &ServerKeyId) -> Result<(), Error> {
+	match key_storage.retirement(key_id)? {
+		Some(_) => Err(Error::KeyRetired),
+		None => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::collections::{BTreeMap, BTreeSet};
+	use ethkey::{Random, Generator, KeyPair, sign};
+	use key_server_cluster::{NodeId, SessionId, Error, KeyStorage, KeyRetirement, DummyKeyStorage, DocumentKeyShare, DocumentKeyShareVersion};
+	use key_server_cluster::math;
+	use key_server_cluster::cluster::tests::DummyCluster;
+	use key_server_cluster::cluster_sessions::ClusterSession;
+	use key_server_cluster::message::Message;
+	use super::{SessionImpl, SessionParams, SessionState, key_retirement_hash, check_key_is_not_retired};
+
+	struct Node {
+		pub cluster: Arc<DummyCluster>,
+		pub key_storage: Arc<DummyKeyStorage>,
+		pub session: SessionImpl,
+	}
+
+	struct MessageLoop {
+		pub admin_key_pair: KeyPair,
+		pub nodes: BTreeMap<NodeId, Node>,
+	}
+
+	impl MessageLoop {
+		/// Key is shared among `num_owners` nodes. Master (first owner) is connected to first `num_connected` owners only.
+		pub fn new(num_owners: usize, num_connected: usize, threshold: usize) -> Self {
+			let admin_key_pair = Random.generate().unwrap();
+			let owners: Vec<NodeId> = (0..num_owners).map(|_| math::generate_random_point().unwrap()).collect();
+			let connected: BTreeSet<NodeId> = owners.iter().take(num_connected).cloned().collect();
+			let key_share = DocumentKeyShare {
+				threshold: threshold,
+				versions: vec![DocumentKeyShareVersion::new(
+					owners.iter().map(|n| (n.clone(), math::generate_random_scalar().unwrap())).collect(),
+					math::generate_random_scalar().unwrap(),
+				)],
+				..Default::default()
+			};
+
+			MessageLoop {
+				nodes: owners.iter().map(|node_id| {
+					let key_storage = Arc::new(DummyKeyStorage::default());
+					key_storage.insert(Default::default(), key_share.clone()).unwrap();
+					let cluster = Arc::new(DummyCluster::new(node_id.clone()));
+					match connected.contains(node_id) {
+						true => cluster.add_nodes(connected.iter().cloned()),
+						false => cluster.add_node(node_id.clone()),
+					}
+
+					(node_id.clone(), Node {
+						cluster: cluster.clone(),
+						key_storage: key_storage.clone(),
+						session: SessionImpl::new(SessionParams {
+							id: Default::default(),
+							self_node_id: node_id.clone(),
+							master_node_id: owners[0].clone(),
+							admin_public: admin_key_pair.public().clone(),
+							key_storage: key_storage,
+							cluster: cluster,
+							nonce: 0,
+						}),
+					})
+				}).collect(),
+				admin_key_pair: admin_key_pair,
+			}
+		}
+
+		pub fn master(&self) -> &Node {
+			self.nodes.values().find(|n| n.session.master_node_id == n.session.self_node_id).unwrap()
+		}
+
+		pub fn take_message(&mut self) -> Option<(NodeId, NodeId, Message)> {
+			self.nodes.values()
+				.filter_map(|n| n.cluster.take_message().map(|m| (n.session.node().clone(), m.0, m.1)))
+				.nth(0)
+		}
+
+		pub fn run(&mut self) {
+			while let Some((from, to, message)) = self.take_message() {
+				// session errors are processed by cluster in the same way
+				let session = &self.nodes[&to].session;
+				if let Err(error) = session.on_message(&from, &message) {
+					session.on_session_error(&to, error);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn key_retirement_fails_if_not_signed_by_administrator() {
+		let ml = MessageLoop::new(3, 3, 1);
+		let signature = sign(Random.generate().unwrap().secret(), &key_retirement_hash(&SessionId::default())).unwrap();
+		assert_eq!(ml.master().session.initialize(signature), Err(Error::AccessDenied));
+
+		// signature of the key id itself (i.e. requester signature of administrator) is also rejected
+		let signature = sign(ml.admin_key_pair.secret(), &SessionId::default()).unwrap();
+		assert_eq!(ml.master().session.initialize(signature), Err(Error::AccessDenied));
+	}
+
+	#[test]
+	fn key_retirement_completes_when_quorum_is_prepared() {
+		// 5 owners, threshold 2 => at least 3 owners must prepare
+		let mut ml = MessageLoop::new(5, 3, 2);
+		let signature = sign(ml.admin_key_pair.secret(), &key_retirement_hash(&SessionId::default())).unwrap();
+		ml.master().session.initialize(signature).unwrap();
+		ml.run();
+
+		assert_eq!(ml.master().session.wait(None), Ok(()));
+		for (i, node) in ml.nodes.values().enumerate() {
+			let is_connected = node.cluster.nodes().len() > 1;
+			assert_eq!(node.session.state() == SessionState::Finished, is_connected, "{}", i);
+			assert_eq!(node.key_storage.retirement(&Default::default()).unwrap().is_some(), is_connected);
+			assert_eq!(check_key_is_not_retired(&*node.key_storage, &Default::default()).is_err(), is_connected);
+		}
+	}
+
+	#[test]
+	fn key_retirement_is_rolled_back_when_quorum_is_not_reached() {
+		// 5 owners, threshold 2 => at least 3 owners must prepare, but only 2 are connected
+		let mut ml = MessageLoop::new(5, 2, 2);
+		let signature = sign(ml.admin_key_pair.secret(), &key_retirement_hash(&SessionId::default())).unwrap();
+		ml.master().session.initialize(signature).unwrap();
+		ml.run();
+
+		assert_eq!(ml.master().session.wait(None), Err(Error::ConsensusUnreachable));
+		for node in ml.nodes.values() {
+			assert!(node.session.state() != SessionState::Finished);
+			assert_eq!(node.key_storage.retirement(&Default::default()), Ok(None));
+			assert!(node.key_storage.get(&Default::default()).unwrap().is_some());
+		}
+		assert!(ml.nodes.values().filter(|n| n.cluster.nodes().len() > 1).all(|n| n.session.state() == SessionState::Failed));
+	}
+
+	#[test]
+	fn key_retirement_fails_if_key_is_already_retired() {
+		let ml = MessageLoop::new(3, 3, 1);
+		let signature = sign(ml.admin_key_pair.secret(), &key_retirement_hash(&SessionId::default())).unwrap();
+		ml.master().key_storage.retire(Default::default(), KeyRetirement {
+			retired_at: 0,
+			admin_signature: signature.clone(),
+		}).unwrap();
+		assert_eq!(ml.master().session.initialize(signature), Err(Error::KeyRetired));
+	}
+}
@@ -1404,4 +1404,52 @@ pub mod tests {
 		// check that all sessions have finished
 		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
 	}
+
+	#[test]
+	fn cluster_of_4_grows_to_5_using_servers_set_change() {
+		// initial 2-of-4 session
+		let gml = generate_key(1, generate_nodes_ids(4));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// insert 1 node so that it becames 2-of-5 session
+		let nodes_to_add: BTreeSet<_> = (0..1).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, nodes_to_add.clone(), BTreeSet::new(), BTreeSet::new());
+		ml.nodes[&master_node_id].session.initialize(ml.nodes.keys().cloned().collect(), ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// check that added node owns key share of the new version
+		assert!(ml.nodes.iter().filter(|&(k, _)| nodes_to_add.contains(k)).all(|(_, v)| v.key_storage.get(&SessionId::default()).unwrap().is_some()));
+
+		// decrypt using every pair of the 5 nodes
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter().map(|(k, v)| (k.clone(), v.key_storage.clone())).collect());
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
+
+	#[test]
+	fn cluster_of_4_shrinks_to_3_using_servers_set_change() {
+		// initial 2-of-4 session
+		let gml = generate_key(1, generate_nodes_ids(4));
+		let master_node_id = gml.nodes.keys().cloned().nth(0).unwrap();
+
+		// remove 1 node so that session becames 2-of-3
+		let nodes_to_remove: BTreeSet<_> = gml.nodes.keys().cloned().skip(2).take(1).collect();
+		let new_nodes_set: BTreeSet<_> = gml.nodes.keys().cloned().filter(|n| !nodes_to_remove.contains(&n)).collect();
+		let mut ml = MessageLoop::new(&gml, master_node_id, None, BTreeSet::new(), nodes_to_remove.clone(), BTreeSet::new());
+		ml.nodes[&master_node_id].session.initialize(new_nodes_set, ml.all_set_signature.clone(), ml.new_set_signature.clone()).unwrap();
+		ml.run();
+
+		// decrypt using every pair of the 3 remaining nodes
+		check_secret_is_preserved(ml.original_key_pair.clone(), ml.nodes.iter()
+			.filter(|&(k, _)| !nodes_to_remove.contains(k))
+			.map(|(k, v)| (k.clone(), v.key_storage.clone()))
+			.collect());
+
+		// check that removed node does not own key share
+		assert!(ml.nodes.iter().filter(|&(k, _)| nodes_to_remove.contains(k)).all(|(_, v)| v.key_storage.get(&SessionId::default()).unwrap().is_none()));
+
+		// check that all sessions have finished
+		assert!(ml.nodes.values().all(|n| n.session.is_finished()));
+	}
 }
@@ -231,6 +231,28 @@ impl SessionImpl {
 		self.data.lock().origin.clone()
 	}
 
+	/// Is shadow decryption requested?
+	pub fn is_shadow_decryption(&self) -> Option<bool> {
+		self.data.lock().is_shadow_decryption.clone()
+	}
+
+	/// Get nodes, which are known to participate in the session.
+	pub fn participants(&self) -> BTreeSet<NodeId> {
+		self.data.lock().consensus_session.participants()
+	}
+
+	/// Get session outcome on this node. Only master node knows the decryption result, other nodes only know
+	/// whether the session has been completed.
+	pub fn outcome(&self) -> Result<(), Error> {
+		let data = self.data.lock();
+		match data.result {
+			Some(Ok(_)) => Ok(()),
+			Some(Err(ref error)) => Err(error.clone()),
+			None if data.consensus_session.state() == ConsensusSessionState::Finished => Ok(()),
+			None => Err(Error::InvalidStateForRequest),
+		}
+	}
+
 	/// Wait for session completion.
 	pub fn wait(&self, timeout: Option<time::Duration>) -> Option<Result<EncryptedDocumentKeyShadow, Error>> {
 		Self::wait_session(&self.core.completed, &self.data, timeout, |data| data.result.clone())
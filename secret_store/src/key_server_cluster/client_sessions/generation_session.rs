@@ -259,6 +259,16 @@ impl SessionImpl {
 		self.data.lock().origin.clone()
 	}
 
+	/// Get author of the generated key.
+	pub fn author(&self) -> Option<Address> {
+		self.data.lock().author.clone()
+	}
+
+	/// Get nodes, participating in the session.
+	pub fn nodes(&self) -> BTreeSet<NodeId> {
+		self.data.lock().nodes.keys().cloned().collect()
+	}
+
 	/// Wait for session completion.
 	pub fn wait(&self, timeout: Option<Duration>) -> Option<Result<Public, Error>> {
 		Self::wait_session(&self.completed, &self.data, timeout, |data| data.joint_public_and_secret.clone()
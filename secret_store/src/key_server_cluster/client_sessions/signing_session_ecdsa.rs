@@ -227,6 +227,28 @@ impl SessionImpl {
 			.expect("wait_session returns Some if called without timeout; qed")
 	}
 
+	/// Get key requester.
+	pub fn requester(&self) -> Option<Requester> {
+		self.data.lock().consensus_session.consensus_job().executor().requester().cloned()
+	}
+
+	/// Get nodes, which are known to participate in the session.
+	pub fn participants(&self) -> BTreeSet<NodeId> {
+		self.data.lock().consensus_session.participants()
+	}
+
+	/// Get session outcome on this node. Only master node knows the signing result, other nodes only know
+	/// whether the session has been completed.
+	pub fn outcome(&self) -> Result<(), Error> {
+		let data = self.data.lock();
+		match data.result {
+			Some(Ok(_)) => Ok(()),
+			Some(Err(ref error)) => Err(error.clone()),
+			None if data.consensus_session.state() == ConsensusSessionState::Finished => Ok(()),
+			None => Err(Error::InvalidStateForRequest),
+		}
+	}
+
 	/// Delegate session to other node.
 	pub fn delegate(&self, master: NodeId, version: H256, message_hash: H256) -> Result<(), Error> {
 		if self.core.meta.master_node_id != self.core.meta.self_node_id {
@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::io;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::sync::Arc;
 use std::collections::{BTreeMap, BTreeSet};
 use std::collections::btree_map::Entry;
@@ -28,7 +28,7 @@ use tokio_core::reactor::{Handle, Remote, Interval};
 use tokio_core::net::{TcpListener, TcpStream};
 use ethkey::{Public, KeyPair, Signature, Random, Generator};
 use ethereum_types::{Address, H256};
-use key_server_cluster::{Error, NodeId, SessionId, Requester, AclStorage, KeyStorage, KeyServerSet, NodeKeyPair};
+use key_server_cluster::{Error, NodeId, SessionId, Requester, AclStorage, KeyStorage, KeyServerSet, NodeKeyPair, KeyRetirement};
 use key_server_cluster::cluster_sessions::{ClusterSession, AdminSession, ClusterSessions, SessionIdWithSubSession,
 	ClusterSessionsContainer, SERVERS_SET_CHANGE_SESSION_ID, create_cluster_view, AdminSessionCreationData, ClusterSessionsListener};
 use key_server_cluster::cluster_sessions_creator::{ClusterSessionCreator, IntoSessionId};
@@ -40,8 +40,10 @@ use key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSessi
 use key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSession};
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
 	IsolatedSessionTransport as KeyVersionNegotiationSessionTransport, ContinueAction};
+use key_server_cluster::key_retirement_session::check_admin_signature;
 use key_server_cluster::io::{DeadlineStatus, ReadMessage, SharedTcpStream, read_encrypted_message, WriteMessage, write_encrypted_message};
-use key_server_cluster::net::{accept_connection as net_accept_connection, connect as net_connect, Connection as NetConnection};
+use key_server_cluster::net::{accept_connection as net_accept_connection, connect as net_connect, ClusterTls,
+	Connection as NetConnection};
 use key_server_cluster::connection_trigger::{Maintain, ConnectionTrigger, SimpleConnectionTrigger, ServersSetChangeSessionCreatorConnector};
 use key_server_cluster::connection_trigger_with_migration::ConnectionTriggerWithMigration;
 
@@ -79,11 +81,17 @@ pub trait ClusterClient: Send + Sync {
 	fn new_key_version_negotiation_session(&self, session_id: SessionId) -> Result<Arc<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>, Error>;
 	/// Start new servers set change session.
 	fn new_servers_set_change_session(&self, session_id: Option<SessionId>, migration_id: Option<H256>, new_nodes_set: BTreeSet<NodeId>, old_set_signature: Signature, new_set_signature: Signature) -> Result<Arc<AdminSession>, Error>;
+	/// Start new key retirement session.
+	fn new_key_retirement_session(&self, session_id: SessionId, admin_signature: Signature) -> Result<Arc<AdminSession>, Error>;
 
 	/// Listen for new generation sessions.
 	fn add_generation_listener(&self, listener: Arc<ClusterSessionsListener<GenerationSession>>);
 	/// Listen for new decryption sessions.
 	fn add_decryption_listener(&self, listener: Arc<ClusterSessionsListener<DecryptionSession>>);
+	/// Listen for new Schnorr signing sessions.
+	fn add_schnorr_signing_listener(&self, listener: Arc<ClusterSessionsListener<SchnorrSigningSession>>);
+	/// Listen for new ECDSA signing sessions.
+	fn add_ecdsa_signing_listener(&self, listener: Arc<ClusterSessionsListener<EcdsaSigningSession>>);
 	/// Listen for new key version negotiation sessions.
 	fn add_key_version_negotiation_listener(&self, listener: Arc<ClusterSessionsListener<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>>);
 
@@ -139,6 +147,10 @@ pub struct ClusterConfiguration {
 	/// Should key servers set change session should be started when servers set changes.
 	/// This will only work when servers set is configured using KeyServerSet contract.
 	pub auto_migrate_enabled: bool,
+	/// Time to keep shares of retired keys before erasing them.
+	pub key_retirement_grace_period: Duration,
+	/// TLS settings of connections to other nodes. Connections are plaintext when not set.
+	pub tls: Option<Arc<ClusterTls>>,
 }
 
 /// Cluster state.
@@ -292,16 +304,17 @@ impl ClusterCore {
 	}
 
 	/// Connect to peer.
-	fn connect(data: Arc<ClusterData>, node_address: SocketAddr) {
+	fn connect(data: Arc<ClusterData>, node_id: NodeId, node_address: SocketAddr) {
 		data.handle.clone().spawn(move |handle| {
-			data.pool.clone().spawn(ClusterCore::connect_future(handle, data, node_address))
+			data.pool.clone().spawn(ClusterCore::connect_future(handle, data, node_id, node_address))
 		})
 	}
 
 	/// Connect to socket using given context and handle.
-	fn connect_future(handle: &Handle, data: Arc<ClusterData>, node_address: SocketAddr) -> BoxedEmptyFuture {
+	fn connect_future(handle: &Handle, data: Arc<ClusterData>, node_id: NodeId, node_address: SocketAddr) -> BoxedEmptyFuture {
 		let disconnected_nodes = data.connections.disconnected_nodes().keys().cloned().collect();
-		Box::new(net_connect(&node_address, handle, data.self_key_pair.clone(), disconnected_nodes)
+		let tls = data.config.tls.clone();
+		Box::new(net_connect(&node_address, handle, data.self_key_pair.clone(), disconnected_nodes, node_id, tls)
 			.then(move |result| ClusterCore::process_connection_result(data, Some(node_address), result))
 			.then(|_| finished(())))
 	}
@@ -327,7 +340,8 @@ impl ClusterCore {
 
 	/// Accept connection future.
 	fn accept_connection_future(handle: &Handle, data: Arc<ClusterData>, stream: TcpStream, node_address: SocketAddr) -> BoxedEmptyFuture {
-		Box::new(net_accept_connection(node_address, stream, handle, data.self_key_pair.clone())
+		let tls = data.config.tls.clone();
+		Box::new(net_accept_connection(node_address, stream, handle, data.self_key_pair.clone(), tls)
 			.then(move |result| ClusterCore::process_connection_result(data, None, result))
 			.then(|_| finished(())))
 	}
@@ -350,6 +364,7 @@ impl ClusterCore {
 
 		ClusterCore::keep_alive(data.clone());
 		ClusterCore::connect_disconnected_nodes(data.clone());
+		ClusterCore::erase_retired_key_shares(data.clone());
 		data.sessions.stop_stalled_sessions();
 	}
 
@@ -409,17 +424,50 @@ impl ClusterCore {
 		// connect to disconnected nodes
 		for (node_id, node_address) in data.connections.disconnected_nodes() {
 			if data.config.allow_connecting_to_higher_nodes || data.self_key_pair.public() < &node_id {
-				ClusterCore::connect(data.clone(), node_address);
+				ClusterCore::connect(data.clone(), node_id, node_address);
 			}
 		}
 	}
 
+	/// Erase shares of keys, retired more than grace period ago.
+	fn erase_retired_key_shares(data: Arc<ClusterData>) {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+		let grace_period = data.config.key_retirement_grace_period.as_secs();
+		let expired_keys: Vec<_> = data.config.key_storage.iter_retired()
+			.filter(|&(_, ref retirement)| retirement.retired_at.saturating_add(grace_period) <= now)
+			.map(|(key_id, _)| key_id)
+			.filter(|key_id| data.config.key_storage.contains(key_id))
+			.collect();
+		for key_id in expired_keys {
+			match data.config.key_storage.remove(&key_id) {
+				Ok(_) => info!(target: "secretstore_net", "{}: erased share of retired key {}", data.self_key_pair.public(), key_id),
+				Err(err) => warn!(target: "secretstore_net", "{}: failed to erase share of retired key {}: {}", data.self_key_pair.public(), key_id, err),
+			}
+		}
+	}
+
+	/// Send all retired keys to newly connected node.
+	fn send_retired_keys(data: &Arc<ClusterData>, connection: &Arc<Connection>) {
+		let keys: BTreeMap<_, _> = data.config.key_storage.iter_retired()
+			.map(|(key_id, retirement)| (key_id.into(), message::RetiredKey {
+				retired_at: retirement.retired_at,
+				admin_signature: retirement.admin_signature.into(),
+			}))
+			.collect();
+		if !keys.is_empty() {
+			data.spawn(connection.send_message(Message::Cluster(ClusterMessage::RetiredKeys(message::RetiredKeys {
+				keys: keys,
+			}))));
+		}
+	}
+
 	/// Process connection future result.
 	fn process_connection_result(data: Arc<ClusterData>, outbound_addr: Option<SocketAddr>, result: Result<DeadlineStatus<Result<NetConnection, Error>>, io::Error>) -> IoFuture<Result<(), Error>> {
 		match result {
 			Ok(DeadlineStatus::Meet(Ok(connection))) => {
 				let connection = Connection::new(outbound_addr.is_none(), connection);
 				if data.connections.insert(data.clone(), connection.clone()) {
+					ClusterCore::send_retired_keys(&data, &connection);
 					ClusterCore::process_connection_messages(data.clone(), connection)
 				} else {
 					Box::new(finished(Ok(())))
@@ -478,6 +526,8 @@ impl ClusterCore {
 			},
 			Message::ShareAdd(message) => Self::process_message(&data, &data.sessions.admin_sessions, connection, Message::ShareAdd(message))
 				.map(|_| ()).unwrap_or_default(),
+			Message::KeyRetirement(message) => Self::process_message(&data, &data.sessions.admin_sessions, connection, Message::KeyRetirement(message))
+				.map(|_| ()).unwrap_or_default(),
 			Message::Cluster(message) => ClusterCore::process_cluster_message(data, connection, message),
 		}
 	}
@@ -643,6 +693,39 @@ impl ClusterCore {
 		}
 	}
 
+	/// Retire keys, which have been retired while this node was disconnected.
+	fn process_retired_keys(data: &Arc<ClusterData>, connection: &Arc<Connection>, message: message::RetiredKeys) {
+		let admin_public = match data.config.admin_public {
+			Some(ref admin_public) => admin_public,
+			None => {
+				warn!(target: "secretstore_net", "{}: ignoring retired keys from node {}: administrator key is not configured",
+					data.self_key_pair.public(), connection.node_id());
+				return;
+			},
+		};
+
+		for (key_id, retired_key) in message.keys {
+			let key_id: SessionId = key_id.into();
+			let admin_signature: Signature = retired_key.admin_signature.into();
+			if check_admin_signature(admin_public, &key_id, &admin_signature).is_err() {
+				warn!(target: "secretstore_net", "{}: ignoring retired key {} from node {}: invalid administrator signature",
+					data.self_key_pair.public(), key_id, connection.node_id());
+				continue;
+			}
+
+			match data.config.key_storage.retirement(&key_id) {
+				Ok(None) => if let Err(err) = data.config.key_storage.retire(key_id.clone(), KeyRetirement {
+					retired_at: retired_key.retired_at,
+					admin_signature: admin_signature,
+				}) {
+					warn!(target: "secretstore_net", "{}: failed to retire key {}: {}", data.self_key_pair.public(), key_id, err);
+				},
+				Ok(Some(_)) => (),
+				Err(err) => warn!(target: "secretstore_net", "{}: failed to read retirement of key {}: {}", data.self_key_pair.public(), key_id, err),
+			}
+		}
+	}
+
 	/// Process single cluster message from the connection.
 	fn process_cluster_message(data: Arc<ClusterData>, connection: Arc<Connection>, message: ClusterMessage) {
 		match message {
@@ -652,6 +735,7 @@ impl ClusterCore {
 			ClusterMessage::KeepAliveResponse(msg) => if let Some(session_id) = msg.session_id {
 				data.sessions.on_session_keep_alive(connection.node_id(), session_id.into());
 			},
+			ClusterMessage::RetiredKeys(msg) => ClusterCore::process_retired_keys(&data, &connection, msg),
 			_ => warn!(target: "secretstore_net", "{}: received unexpected message {} from node {} at {}", data.self_key_pair.public(), message, connection.node_id(), connection.node_address()),
 		}
 	}
@@ -1074,6 +1158,17 @@ impl ClusterClient for ClusterClientImpl {
 			session, &self.data.sessions.admin_sessions)
 	}
 
+	fn new_key_retirement_session(&self, session_id: SessionId, admin_signature: Signature) -> Result<Arc<AdminSession>, Error> {
+		let cluster = create_cluster_view(&self.data, false)?;
+		let session = self.data.sessions.admin_sessions.insert(cluster, self.data.self_key_pair.public().clone(), session_id, None, false, Some(AdminSessionCreationData::KeyRetirement))?;
+		let initialization_result = session.as_key_retirement().expect("key retirement session is created; qed")
+			.initialize(admin_signature);
+
+		Self::process_initialization_result(
+			initialization_result,
+			session, &self.data.sessions.admin_sessions)
+	}
+
 	fn add_generation_listener(&self, listener: Arc<ClusterSessionsListener<GenerationSession>>) {
 		self.data.sessions.generation_sessions.add_listener(listener);
 	}
@@ -1082,6 +1177,14 @@ impl ClusterClient for ClusterClientImpl {
 		self.data.sessions.decryption_sessions.add_listener(listener);
 	}
 
+	fn add_schnorr_signing_listener(&self, listener: Arc<ClusterSessionsListener<SchnorrSigningSession>>) {
+		self.data.sessions.schnorr_signing_sessions.add_listener(listener);
+	}
+
+	fn add_ecdsa_signing_listener(&self, listener: Arc<ClusterSessionsListener<EcdsaSigningSession>>) {
+		self.data.sessions.ecdsa_signing_sessions.add_listener(listener);
+	}
+
 	fn add_key_version_negotiation_listener(&self, listener: Arc<ClusterSessionsListener<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>>) {
 		self.data.sessions.negotiation_sessions.add_listener(listener);
 	}
@@ -1117,13 +1220,16 @@ pub mod tests {
 	use std::sync::Arc;
 	use std::sync::atomic::{AtomicUsize, Ordering};
 	use std::time::{Duration, Instant};
-	use std::collections::{BTreeSet, VecDeque};
+	use std::collections::{BTreeMap, BTreeSet, VecDeque};
+	use std::net::SocketAddr;
 	use parking_lot::Mutex;
 	use tokio_core::reactor::Core;
 	use ethereum_types::{Address, H256};
-	use ethkey::{Random, Generator, Public, Signature, sign};
+	use ethkey::{Random, Generator, KeyPair, Public, Secret, Signature, sign};
 	use key_server_cluster::{NodeId, SessionId, Requester, Error, DummyAclStorage, DummyKeyStorage,
-		MapKeyServerSet, PlainNodeKeyPair, KeyStorage};
+		MapKeyServerSet, PlainNodeKeyPair, KeyStorage, DocumentKeyShare, DocumentKeyShareVersion,
+		KeyServerSet, KeyServerSetSnapshot, KeyServerSetMigration};
+	use key_server_cluster::math;
 	use key_server_cluster::message::Message;
 	use key_server_cluster::cluster::{Cluster, ClusterCore, ClusterConfiguration, ClusterClient, ClusterState};
 	use key_server_cluster::cluster_sessions::{ClusterSession, AdminSession, ClusterSessionsListener};
@@ -1134,6 +1240,7 @@ pub mod tests {
 	use key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSession};
 	use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
 		IsolatedSessionTransport as KeyVersionNegotiationSessionTransport};
+	use key_server_cluster::key_retirement_session::key_retirement_hash;
 
 	const TIMEOUT: Duration = Duration::from_millis(300);
 
@@ -1154,6 +1261,20 @@ pub mod tests {
 		messages: VecDeque<(NodeId, Message)>,
 	}
 
+	/// Key server set of a single node, backed by the state shared by all nodes, like the KeyServerSet contract.
+	pub struct MigratingKeyServerSet {
+		self_node_id: NodeId,
+		contract: Arc<Mutex<MigratingKeyServerSetContract>>,
+	}
+
+	/// State of the key server set shared by all nodes. Migration moves the new set at its start to the
+	/// current set once every node of the migration set has confirmed it.
+	#[derive(Default)]
+	pub struct MigratingKeyServerSetContract {
+		snapshot: KeyServerSetSnapshot,
+		confirmations: BTreeSet<NodeId>,
+	}
+
 	impl ClusterClient for DummyClusterClient {
 		fn cluster_state(&self) -> ClusterState { unimplemented!("test-only") }
 		fn new_generation_session(&self, _session_id: SessionId, _origin: Option<Address>, _author: Address, _threshold: usize) -> Result<Arc<GenerationSession>, Error> {
@@ -1167,9 +1288,12 @@ pub mod tests {
 
 		fn new_key_version_negotiation_session(&self, _session_id: SessionId) -> Result<Arc<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>, Error> { unimplemented!("test-only") }
 		fn new_servers_set_change_session(&self, _session_id: Option<SessionId>, _migration_id: Option<H256>, _new_nodes_set: BTreeSet<NodeId>, _old_set_signature: Signature, _new_set_signature: Signature) -> Result<Arc<AdminSession>, Error> { unimplemented!("test-only") }
+		fn new_key_retirement_session(&self, _session_id: SessionId, _admin_signature: Signature) -> Result<Arc<AdminSession>, Error> { unimplemented!("test-only") }
 
 		fn add_generation_listener(&self, _listener: Arc<ClusterSessionsListener<GenerationSession>>) {}
 		fn add_decryption_listener(&self, _listener: Arc<ClusterSessionsListener<DecryptionSession>>) {}
+		fn add_schnorr_signing_listener(&self, _listener: Arc<ClusterSessionsListener<SchnorrSigningSession>>) {}
+		fn add_ecdsa_signing_listener(&self, _listener: Arc<ClusterSessionsListener<EcdsaSigningSession>>) {}
 		fn add_key_version_negotiation_listener(&self, _listener: Arc<ClusterSessionsListener<KeyVersionNegotiationSession<KeyVersionNegotiationSessionTransport>>>) {}
 
 		fn make_faulty_generation_sessions(&self) { unimplemented!("test-only") }
@@ -1241,6 +1365,50 @@ pub mod tests {
 		}
 	}
 
+	impl KeyServerSet for MigratingKeyServerSet {
+		fn is_isolated(&self) -> bool {
+			!self.contract.lock().snapshot.current_set.contains_key(&self.self_node_id)
+		}
+
+		fn snapshot(&self) -> KeyServerSetSnapshot {
+			let contract = self.contract.lock();
+			let mut snapshot = contract.snapshot.clone();
+			if let Some(ref mut migration) = snapshot.migration {
+				migration.is_confirmed = contract.confirmations.contains(&self.self_node_id);
+			}
+			snapshot
+		}
+
+		fn start_migration(&self, migration_id: H256) {
+			let mut contract = self.contract.lock();
+			if contract.snapshot.migration.is_none() {
+				let set = contract.snapshot.new_set.clone();
+				contract.snapshot.migration = Some(KeyServerSetMigration {
+					id: migration_id,
+					set: set,
+					master: self.self_node_id.clone(),
+					is_confirmed: false,
+				});
+			}
+		}
+
+		fn confirm_migration(&self, migration_id: H256) {
+			let mut contract = self.contract.lock();
+			let contract = &mut *contract;
+			let is_confirmed_by_all = match contract.snapshot.migration {
+				Some(ref migration) if migration.id == migration_id => {
+					contract.confirmations.insert(self.self_node_id.clone());
+					migration.set.keys().all(|node| contract.confirmations.contains(node))
+				},
+				_ => false,
+			};
+			if is_confirmed_by_all {
+				contract.snapshot.current_set = contract.snapshot.migration.take().expect("checked above; qed").set;
+				contract.confirmations.clear();
+			}
+		}
+	}
+
 	pub fn loop_until<F>(core: &mut Core, timeout: Duration, predicate: F) where F: Fn() -> bool {
 		let start = Instant::now();
 		loop {
@@ -1255,6 +1423,30 @@ pub mod tests {
 		}
 	}
 
+	/// Like `loop_until`, but also runs the connections maintenance of `clusters` every 50ms rather than
+	/// every `MAINTAIN_INTERVAL`, which is where the key server set is polled and migrations are driven.
+	pub fn maintain_until<F>(core: &mut Core, clusters: &[Arc<ClusterCore>], timeout: Duration, predicate: F) where F: Fn() -> bool {
+		let start = Instant::now();
+		loop {
+			for cluster in clusters {
+				cluster.client().connect();
+			}
+
+			let step = Instant::now();
+			while Instant::now() - step < Duration::from_millis(50) {
+				core.turn(Some(Duration::from_millis(1)));
+			}
+
+			if predicate() {
+				break;
+			}
+
+			if Instant::now() - start > timeout {
+				panic!("no result in {:?}", timeout);
+			}
+		}
+	}
+
 	pub fn all_connections_established(cluster: &Arc<ClusterCore>) -> bool {
 		cluster.config().key_server_set.snapshot().new_set.keys()
 			.filter(|p| *p != cluster.config().self_key_pair.public())
@@ -1262,6 +1454,10 @@ pub mod tests {
 	}
 
 	pub fn make_clusters(core: &Core, ports_begin: u16, num_nodes: usize) -> Vec<Arc<ClusterCore>> {
+		make_clusters_with_admin(core, ports_begin, num_nodes, None)
+	}
+
+	pub fn make_clusters_with_admin(core: &Core, ports_begin: u16, num_nodes: usize, admin_public: Option<Public>) -> Vec<Arc<ClusterCore>> {
 		let key_pairs: Vec<_> = (0..num_nodes).map(|_| Random.generate().unwrap()).collect();
 		let cluster_params: Vec<_> = (0..num_nodes).map(|i| ClusterConfiguration {
 			threads: 1,
@@ -1273,8 +1469,10 @@ pub mod tests {
 			allow_connecting_to_higher_nodes: false,
 			key_storage: Arc::new(DummyKeyStorage::default()),
 			acl_storage: Arc::new(DummyAclStorage::default()),
-			admin_public: None,
+			admin_public: admin_public.clone(),
 			auto_migrate_enabled: false,
+			key_retirement_grace_period: Duration::from_secs(86400),
+			tls: None,
 		}).collect();
 		let clusters: Vec<_> = cluster_params.into_iter().enumerate()
 			.map(|(_, params)| ClusterCore::new(core.handle(), params).unwrap())
@@ -1283,6 +1481,46 @@ pub mod tests {
 		clusters
 	}
 
+	/// Make a cluster for each of `key_pairs`, all of them auto-migrating when `contract` changes.
+	pub fn make_migrating_clusters(core: &Core, ports_begin: u16, key_pairs: &[KeyPair], contract: &Arc<Mutex<MigratingKeyServerSetContract>>) -> Vec<Arc<ClusterCore>> {
+		key_pairs.iter().enumerate().map(|(i, key_pair)| ClusterCore::new(core.handle(), ClusterConfiguration {
+			threads: 1,
+			self_key_pair: Arc::new(PlainNodeKeyPair::new(key_pair.clone())),
+			listen_address: ("127.0.0.1".to_owned(), ports_begin + i as u16),
+			key_server_set: Arc::new(MigratingKeyServerSet {
+				self_node_id: key_pair.public().clone(),
+				contract: contract.clone(),
+			}),
+			allow_connecting_to_higher_nodes: false,
+			key_storage: Arc::new(DummyKeyStorage::default()),
+			acl_storage: Arc::new(DummyAclStorage::default()),
+			admin_public: None,
+			auto_migrate_enabled: true,
+			key_retirement_grace_period: Duration::from_secs(86400),
+			tls: None,
+		}).unwrap()).collect()
+	}
+
+	/// Check that the key is shared by exactly the nodes of `clusters` and that any 2 of them recover `joint_secret`.
+	fn assert_key_is_shared_by(clusters: &[&Arc<ClusterCore>], joint_secret: &Secret) {
+		let nodes: Vec<_> = clusters.iter().map(|c| c.config().self_key_pair.public().clone()).collect();
+		let shares: Vec<_> = clusters.iter()
+			.map(|c| c.config().key_storage.get(&SessionId::default()).unwrap().unwrap().last_version().unwrap().clone())
+			.collect();
+		for share in &shares {
+			assert_eq!(share.id_numbers.keys().cloned().collect::<BTreeSet<_>>(), nodes.iter().cloned().collect());
+		}
+
+		for i in 0..shares.len() {
+			for j in i + 1..shares.len() {
+				let secret = math::compute_joint_secret_from_shares(1,
+					&[&shares[i].secret_share, &shares[j].secret_share],
+					&[&shares[i].id_numbers[&nodes[i]], &shares[j].id_numbers[&nodes[j]]]).unwrap();
+				assert_eq!(&secret, joint_secret);
+			}
+		}
+	}
+
 	pub fn run_clusters(clusters: &[Arc<ClusterCore>]) {
 		for cluster in clusters {
 			cluster.run_listener().unwrap();
@@ -1526,4 +1764,106 @@ pub mod tests {
 		loop_until(&mut core, Duration::from_millis(1000), || session.is_finished());
 		session1.wait().unwrap_err();
 	}
+
+	#[test]
+	fn key_retirement_is_delivered_to_node_which_was_offline() {
+		//::logger::init_log();
+		let mut core = Core::new().unwrap();
+		let admin_key_pair = Random.generate().unwrap();
+		let mut clusters = make_clusters_with_admin(&core, 6054, 3, Some(admin_key_pair.public().clone()));
+		// node with the lowest id connects to all other nodes when started
+		clusters.sort_by_key(|c| c.config().self_key_pair.public().clone());
+
+		// every node holds a share of the key
+		let key_share = DocumentKeyShare {
+			threshold: 1,
+			versions: vec![DocumentKeyShareVersion::new(
+				clusters.iter().map(|c| (c.config().self_key_pair.public().clone(), math::generate_random_scalar().unwrap())).collect(),
+				math::generate_random_scalar().unwrap(),
+			)],
+			..Default::default()
+		};
+		for cluster in &clusters {
+			cluster.config().key_storage.insert(SessionId::default(), key_share.clone()).unwrap();
+		}
+
+		// retire the key while the first node is offline
+		run_clusters(&clusters[1..]);
+		loop_until(&mut core, TIMEOUT, || clusters[1].connection(clusters[2].config().self_key_pair.public()).is_some());
+		let admin_signature = sign(admin_key_pair.secret(), &key_retirement_hash(&SessionId::default())).unwrap();
+		let session = clusters[1].client().new_key_retirement_session(SessionId::default(), admin_signature).unwrap();
+		loop_until(&mut core, TIMEOUT, || session.is_finished()
+			&& clusters[2].config().key_storage.retirement(&SessionId::default()).unwrap().is_some());
+		assert_eq!(session.as_key_retirement().unwrap().wait(None), Ok(()));
+		assert_eq!(clusters[0].config().key_storage.retirement(&SessionId::default()), Ok(None));
+
+		// when the first node is started, it learns about the retirement
+		clusters[0].run().unwrap();
+		loop_until(&mut core, TIMEOUT, || clusters.iter().all(all_connections_established)
+			&& clusters[0].config().key_storage.retirement(&SessionId::default()).unwrap().is_some());
+		let requester = sign(Random.generate().unwrap().secret(), &SessionId::default()).unwrap();
+		match clusters[0].client().new_decryption_session(SessionId::default(), Default::default(), requester.into(), None, false, false) {
+			Err(Error::KeyRetired) => (),
+			Err(e) => panic!("unexpected error {:?}", e),
+			_ => panic!("unexpected success"),
+		}
+	}
+
+	#[test]
+	fn servers_set_change_is_driven_by_key_server_set_when_cluster_grows_to_5_and_shrinks_to_3() {
+		//::logger::init_log();
+		let mut core = Core::new().unwrap();
+		let mut key_pairs: Vec<_> = (0..5).map(|_| Random.generate().unwrap()).collect();
+		// node with the lowest id connects to all other nodes when started
+		key_pairs.sort_by_key(|kp| kp.public().clone());
+		let addresses: BTreeMap<NodeId, SocketAddr> = key_pairs.iter().enumerate()
+			.map(|(i, kp)| (kp.public().clone(), format!("127.0.0.1:{}", 6060 + i).parse().unwrap()))
+			.collect();
+		let set_of = |nodes: &[usize]| nodes.iter()
+			.map(|&i| (key_pairs[i].public().clone(), addresses[key_pairs[i].public()].clone()))
+			.collect::<BTreeMap<_, _>>();
+
+		// the key is shared by the first 4 nodes, which are the current set
+		let contract = Arc::new(Mutex::new(MigratingKeyServerSetContract::default()));
+		contract.lock().snapshot.current_set = set_of(&[0, 1, 2, 3]);
+		contract.lock().snapshot.new_set = set_of(&[0, 1, 2, 3]);
+		let clusters = make_migrating_clusters(&core, 6060, &key_pairs, &contract);
+		let polynom = math::generate_random_polynom(1).unwrap();
+		let id_numbers: BTreeMap<_, _> = key_pairs[..4].iter()
+			.map(|kp| (kp.public().clone(), math::generate_random_scalar().unwrap()))
+			.collect();
+		for (cluster, key_pair) in clusters.iter().zip(&key_pairs[..4]) {
+			cluster.config().key_storage.insert(SessionId::default(), DocumentKeyShare {
+				threshold: 1,
+				public: math::compute_public_share(&polynom[0]).unwrap(),
+				versions: vec![DocumentKeyShareVersion::new(
+					id_numbers.clone(),
+					math::compute_polynom(&polynom, &id_numbers[key_pair.public()]).unwrap(),
+				)],
+				..Default::default()
+			}).unwrap();
+		}
+		run_clusters(&clusters[..4]);
+		maintain_until(&mut core, &clusters[..4], Duration::from_secs(5), || clusters[..4].iter().all(all_connections_established));
+
+		// the 5th node is added to the set while it is offline => migration starts, but can't complete
+		contract.lock().snapshot.new_set = set_of(&[0, 1, 2, 3, 4]);
+		maintain_until(&mut core, &clusters[..4], Duration::from_secs(5), || contract.lock().snapshot.migration.is_some());
+		assert!(contract.lock().snapshot.migration.is_some());
+		assert_eq!(clusters[4].config().key_storage.get(&SessionId::default()), Ok(None));
+
+		// once the 5th node is started, it receives its share and the migration completes
+		clusters[4].run().unwrap();
+		maintain_until(&mut core, &clusters, Duration::from_secs(10), || contract.lock().snapshot.migration.is_none());
+		assert_eq!(contract.lock().snapshot.current_set, set_of(&[0, 1, 2, 3, 4]));
+		assert_key_is_shared_by(&clusters.iter().collect::<Vec<_>>(), &polynom[0]);
+
+		// 2 nodes are removed from the set => the 3 remaining nodes reshare the key among themselves
+		contract.lock().snapshot.new_set = set_of(&[0, 2, 4]);
+		maintain_until(&mut core, &clusters, Duration::from_secs(10), || contract.lock().snapshot.current_set == set_of(&[0, 2, 4]));
+		assert!(contract.lock().snapshot.migration.is_none());
+		assert_key_is_shared_by(&[&clusters[0], &clusters[2], &clusters[4]], &polynom[0]);
+		assert!(clusters[1].config().key_server_set.is_isolated());
+		assert!(clusters[3].config().key_server_set.is_isolated());
+	}
 }
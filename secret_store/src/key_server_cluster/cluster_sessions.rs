@@ -32,6 +32,7 @@ use key_server_cluster::signing_session_ecdsa::{SessionImpl as EcdsaSigningSessi
 use key_server_cluster::signing_session_schnorr::{SessionImpl as SchnorrSigningSessionImpl};
 use key_server_cluster::share_add_session::{SessionImpl as ShareAddSessionImpl, IsolatedSessionTransport as ShareAddTransport};
 use key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetChangeSessionImpl};
+use key_server_cluster::key_retirement_session::{SessionImpl as KeyRetirementSessionImpl};
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	IsolatedSessionTransport as VersionNegotiationTransport};
 
@@ -108,6 +109,8 @@ pub enum AdminSession {
 	ShareAdd(ShareAddSessionImpl<ShareAddTransport>),
 	/// Servers set change session.
 	ServersSetChange(ServersSetChangeSessionImpl),
+	/// Key retirement session.
+	KeyRetirement(KeyRetirementSessionImpl),
 }
 
 /// Administrative session creation data.
@@ -116,6 +119,8 @@ pub enum AdminSessionCreationData {
 	ShareAdd(H256),
 	/// Servers set change session (block id, new_server_set).
 	ServersSetChange(Option<H256>, BTreeSet<NodeId>),
+	/// Key retirement session.
+	KeyRetirement,
 }
 
 /// Active sessions on this cluster.
@@ -500,6 +505,13 @@ impl AdminSession {
 			_ => None
 		}
 	}
+
+	pub fn as_key_retirement(&self) -> Option<&KeyRetirementSessionImpl> {
+		match *self {
+			AdminSession::KeyRetirement(ref session) => Some(session),
+			_ => None
+		}
+	}
 }
 
 impl ClusterSession for AdminSession {
@@ -513,6 +525,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.id().clone(),
 			AdminSession::ServersSetChange(ref session) => session.id().clone(),
+			AdminSession::KeyRetirement(ref session) => session.id().clone(),
 		}
 	}
 
@@ -520,6 +533,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.is_finished(),
 			AdminSession::ServersSetChange(ref session) => session.is_finished(),
+			AdminSession::KeyRetirement(ref session) => session.is_finished(),
 		}
 	}
 
@@ -527,6 +541,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_session_timeout(),
 			AdminSession::ServersSetChange(ref session) => session.on_session_timeout(),
+			AdminSession::KeyRetirement(ref session) => session.on_session_timeout(),
 		}
 	}
 
@@ -534,6 +549,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_node_timeout(node_id),
 			AdminSession::ServersSetChange(ref session) => session.on_node_timeout(node_id),
+			AdminSession::KeyRetirement(ref session) => session.on_node_timeout(node_id),
 		}
 	}
 
@@ -541,6 +557,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_session_error(node, error),
 			AdminSession::ServersSetChange(ref session) => session.on_session_error(node, error),
+			AdminSession::KeyRetirement(ref session) => session.on_session_error(node, error),
 		}
 	}
 
@@ -548,6 +565,7 @@ impl ClusterSession for AdminSession {
 		match *self {
 			AdminSession::ShareAdd(ref session) => session.on_message(sender, message),
 			AdminSession::ServersSetChange(ref session) => session.on_message(sender, message),
+			AdminSession::KeyRetirement(ref session) => session.on_message(sender, message),
 		}
 	}
 }
@@ -570,6 +588,7 @@ pub fn create_cluster_view(data: &Arc<ClusterData>, requires_all_connections: bo
 mod tests {
 	use std::sync::Arc;
 	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::time::Duration;
 	use ethkey::{Random, Generator};
 	use key_server_cluster::{Error, DummyAclStorage, DummyKeyStorage, MapKeyServerSet, PlainNodeKeyPair};
 	use key_server_cluster::cluster::ClusterConfiguration;
@@ -591,6 +610,8 @@ mod tests {
 			acl_storage: Arc::new(DummyAclStorage::default()),
 			admin_public: Some(Random.generate().unwrap().public().clone()),
 			auto_migrate_enabled: false,
+			key_retirement_grace_period: Duration::from_secs(86400),
+			tls: None,
 		};
 		ClusterSessions::new(&config, Arc::new(SimpleServersSetChangeSessionCreatorConnector {
 			admin_public: Some(Random.generate().unwrap().public().clone()),
@@ -24,7 +24,8 @@ use key_server_cluster::cluster::{Cluster, ClusterConfiguration};
 use key_server_cluster::connection_trigger::ServersSetChangeSessionCreatorConnector;
 use key_server_cluster::cluster_sessions::{ClusterSession, SessionIdWithSubSession, AdminSession, AdminSessionCreationData};
 use key_server_cluster::message::{self, Message, DecryptionMessage, SchnorrSigningMessage, ConsensusMessageOfShareAdd,
-	ShareAddMessage, ServersSetChangeMessage, ConsensusMessage, ConsensusMessageWithServersSet, EcdsaSigningMessage};
+	ShareAddMessage, ServersSetChangeMessage, ConsensusMessage, ConsensusMessageWithServersSet, EcdsaSigningMessage,
+	KeyRetirementMessage};
 use key_server_cluster::generation_session::{SessionImpl as GenerationSessionImpl, SessionParams as GenerationSessionParams};
 use key_server_cluster::decryption_session::{SessionImpl as DecryptionSessionImpl,
 	SessionParams as DecryptionSessionParams};
@@ -40,6 +41,8 @@ use key_server_cluster::servers_set_change_session::{SessionImpl as ServersSetCh
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSessionImpl,
 	SessionParams as KeyVersionNegotiationSessionParams, IsolatedSessionTransport as VersionNegotiationTransport,
 	FastestResultComputer as FastestResultKeyVersionsResultComputer};
+use key_server_cluster::key_retirement_session::{SessionImpl as KeyRetirementSessionImpl,
+	SessionParams as KeyRetirementSessionParams, check_key_is_not_retired};
 use key_server_cluster::admin_sessions::ShareChangeSessionMeta;
 
 /// Generic cluster session creator.
@@ -115,6 +118,7 @@ impl SessionCreatorCore {
 
 	/// Read key share && remove disconnected nodes.
 	fn read_key_share(&self, key_id: &SessionId) -> Result<Option<DocumentKeyShare>, Error> {
+		check_key_is_not_retired(&*self.key_storage, key_id)?;
 		self.key_storage.get(key_id)
 	}
 }
@@ -148,6 +152,8 @@ impl ClusterSessionCreator<GenerationSessionImpl, ()> for GenerationSessionCreat
 		if self.core.key_storage.contains(&id) {
 			return Err(Error::ServerKeyAlreadyGenerated);
 		}
+		// retired key id can't be reused, even when its shares are already erased
+		check_key_is_not_retired(&*self.core.key_storage, &id)?;
 
 		let nonce = self.core.check_session_nonce(&master, nonce)?;
 		Ok(GenerationSessionImpl::new(GenerationSessionParams {
@@ -412,6 +418,7 @@ impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSess
 				&ConsensusMessageOfShareAdd::InitializeConsensusSession(ref message) => Ok(Some(AdminSessionCreationData::ShareAdd(message.version.clone().into()))),
 				_ => Err(Error::InvalidMessage),
 			},
+			Message::KeyRetirement(KeyRetirementMessage::PrepareKeyRetirement(_)) => Ok(Some(AdminSessionCreationData::KeyRetirement)),
 			_ => Err(Error::InvalidMessage),
 		}
 	}
@@ -462,6 +469,17 @@ impl ClusterSessionCreator<AdminSession, AdminSessionCreationData> for AdminSess
 					migration_id: migration_id,
 				})?)
 			},
+			Some(AdminSessionCreationData::KeyRetirement) => {
+				AdminSession::KeyRetirement(KeyRetirementSessionImpl::new(KeyRetirementSessionParams {
+					id: id,
+					self_node_id: self.core.self_node_id.clone(),
+					master_node_id: master,
+					admin_public: self.admin_public.clone().ok_or(Error::AccessDenied)?,
+					key_storage: self.core.key_storage.clone(),
+					cluster: cluster,
+					nonce: nonce,
+				}))
+			},
 			None => unreachable!("expected to call with non-empty creation data; qed"),
 		}))
 	}
@@ -477,6 +495,7 @@ impl IntoSessionId<SessionId> for Message {
 			Message::EcdsaSigning(_) => Err(Error::InvalidMessage),
 			Message::ServersSetChange(ref message) => Ok(message.session_id().clone()),
 			Message::ShareAdd(ref message) => Ok(message.session_id().clone()),
+			Message::KeyRetirement(ref message) => Ok(message.session_id().clone()),
 			Message::KeyVersionNegotiation(_) => Err(Error::InvalidMessage),
 			Message::Cluster(_) => Err(Error::InvalidMessage),
 		}
@@ -493,6 +512,7 @@ impl IntoSessionId<SessionIdWithSubSession> for Message {
 			Message::EcdsaSigning(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
 			Message::ServersSetChange(_) => Err(Error::InvalidMessage),
 			Message::ShareAdd(_) => Err(Error::InvalidMessage),
+			Message::KeyRetirement(_) => Err(Error::InvalidMessage),
 			Message::KeyVersionNegotiation(ref message) => Ok(SessionIdWithSubSession::new(message.session_id().clone(), message.sub_session_id().clone())),
 			Message::Cluster(_) => Err(Error::InvalidMessage),
 		}
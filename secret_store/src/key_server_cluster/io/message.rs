@@ -25,7 +25,8 @@ use ethkey::math::curve_order;
 use ethereum_types::{H256, U256};
 use key_server_cluster::Error;
 use key_server_cluster::message::{Message, ClusterMessage, GenerationMessage, EncryptionMessage, DecryptionMessage,
-	SchnorrSigningMessage, EcdsaSigningMessage, ServersSetChangeMessage, ShareAddMessage, KeyVersionNegotiationMessage};
+	SchnorrSigningMessage, EcdsaSigningMessage, ServersSetChangeMessage, ShareAddMessage, KeyVersionNegotiationMessage,
+	KeyRetirementMessage};
 
 /// Size of serialized header.
 pub const MESSAGE_HEADER_SIZE: usize = 18;
@@ -68,6 +69,7 @@ pub fn serialize_message(message: Message) -> Result<SerializedMessage, Error> {
 		Message::Cluster(ClusterMessage::NodePrivateKeySignature(payload))					=> (2, serde_json::to_vec(&payload)),
 		Message::Cluster(ClusterMessage::KeepAlive(payload))								=> (3, serde_json::to_vec(&payload)),
 		Message::Cluster(ClusterMessage::KeepAliveResponse(payload))						=> (4, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::RetiredKeys(payload))								=> (5, serde_json::to_vec(&payload)),
 
 		Message::Generation(GenerationMessage::InitializeSession(payload))					=> (50, serde_json::to_vec(&payload)),
 		Message::Generation(GenerationMessage::ConfirmInitialization(payload))				=> (51, serde_json::to_vec(&payload)),
@@ -153,6 +155,12 @@ pub fn serialize_message(message: Message) -> Result<SerializedMessage, Error> {
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(payload))	=> (509, serde_json::to_vec(&payload)),
 		Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(payload))
 																							=> (510, serde_json::to_vec(&payload)),
+
+		Message::KeyRetirement(KeyRetirementMessage::PrepareKeyRetirement(payload))			=> (550, serde_json::to_vec(&payload)),
+		Message::KeyRetirement(KeyRetirementMessage::ConfirmKeyRetirementPreparation(payload))
+																							=> (551, serde_json::to_vec(&payload)),
+		Message::KeyRetirement(KeyRetirementMessage::CommitKeyRetirement(payload))			=> (552, serde_json::to_vec(&payload)),
+		Message::KeyRetirement(KeyRetirementMessage::KeyRetirementError(payload))			=> (553, serde_json::to_vec(&payload)),
 	};
 
 	let payload = payload.map_err(|err| Error::Serde(err.to_string()))?;
@@ -170,6 +178,7 @@ pub fn deserialize_message(header: &MessageHeader, payload: Vec<u8>) -> Result<M
 		2	=> Message::Cluster(ClusterMessage::NodePrivateKeySignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		3	=> Message::Cluster(ClusterMessage::KeepAlive(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		4	=> Message::Cluster(ClusterMessage::KeepAliveResponse(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		5	=> Message::Cluster(ClusterMessage::RetiredKeys(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 
 		50	=> Message::Generation(GenerationMessage::InitializeSession(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		51	=> Message::Generation(GenerationMessage::ConfirmInitialization(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
@@ -233,6 +242,11 @@ pub fn deserialize_message(header: &MessageHeader, payload: Vec<u8>) -> Result<M
 		509	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		510	=> Message::EcdsaSigning(EcdsaSigningMessage::EcdsaSigningSessionDelegationCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 
+		550	=> Message::KeyRetirement(KeyRetirementMessage::PrepareKeyRetirement(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		551	=> Message::KeyRetirement(KeyRetirementMessage::ConfirmKeyRetirementPreparation(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		552	=> Message::KeyRetirement(KeyRetirementMessage::CommitKeyRetirement(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		553	=> Message::KeyRetirement(KeyRetirementMessage::KeyRetirementError(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+
 		_ => return Err(Error::Serde(format!("unknown message type {}", header.kind))),
 	})
 }
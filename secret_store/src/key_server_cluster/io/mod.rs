@@ -30,5 +30,5 @@ pub use self::message::{MessageHeader, SerializedMessage, serialize_message, des
 pub use self::read_header::{read_header, ReadHeader};
 pub use self::read_payload::{read_payload, read_encrypted_payload, ReadPayload};
 pub use self::read_message::{read_message, read_encrypted_message, ReadMessage};
-pub use self::shared_tcp_stream::SharedTcpStream;
+pub use self::shared_tcp_stream::{SharedTcpStream, TlsIo};
 pub use self::write_message::{write_message, write_encrypted_message, WriteMessage};
@@ -18,18 +18,56 @@ use std::sync::Arc;
 use std::net::Shutdown;
 use std::io::{Read, Write, Error};
 use futures::Poll;
+use parking_lot::Mutex;
+use rustls::{Certificate, Session};
 use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_core::net::TcpStream;
+use tokio_rustls::TlsStream;
 
-/// Read+Write implementation for Arc<TcpStream>.
+/// Read+Write implementation for Arc<TcpStream>, optionally wrapped into TLS session.
 pub struct SharedTcpStream {
-	io: Arc<TcpStream>,
+	io: SharedIo,
+}
+
+/// Underlying stream of SharedTcpStream.
+enum SharedIo {
+	/// Plaintext TCP stream.
+	Plain(Arc<TcpStream>),
+	/// TLS session over TCP stream.
+	Tls(Arc<Mutex<Box<TlsIo>>>),
+}
+
+/// TLS session over TCP stream.
+pub trait TlsIo: AsyncRead + AsyncWrite + Send {
+	/// Certificate chain, presented by other side of the session.
+	fn peer_certificates(&self) -> Option<Vec<Certificate>>;
+}
+
+impl<C: Session + Send> TlsIo for TlsStream<TcpStream, C> {
+	fn peer_certificates(&self) -> Option<Vec<Certificate>> {
+		self.get_ref().1.get_peer_certificates()
+	}
 }
 
 impl SharedTcpStream {
 	pub fn new(a: Arc<TcpStream>) -> Self {
 		SharedTcpStream {
-			io: a,
+			io: SharedIo::Plain(a),
+		}
+	}
+
+	/// Wrap established TLS session.
+	pub fn new_tls<T: TlsIo + 'static>(a: T) -> Self {
+		SharedTcpStream {
+			io: SharedIo::Tls(Arc::new(Mutex::new(Box::new(a)))),
+		}
+	}
+
+	/// Certificate chain, presented by other side of TLS session. None for plaintext streams.
+	pub fn peer_certificates(&self) -> Option<Vec<Certificate>> {
+		match self.io {
+			SharedIo::Plain(_) => None,
+			SharedIo::Tls(ref io) => io.lock().peer_certificates(),
 		}
 	}
 }
@@ -44,28 +82,45 @@ impl AsyncRead for SharedTcpStream {}
 
 impl AsyncWrite for SharedTcpStream {
 	fn shutdown(&mut self) -> Poll<(), Error> {
-		self.io.shutdown(Shutdown::Both).map(Into::into)
+		match self.io {
+			SharedIo::Plain(ref io) => io.shutdown(Shutdown::Both).map(Into::into),
+			SharedIo::Tls(ref io) => io.lock().shutdown(),
+		}
 	}
 }
 
 impl Read for SharedTcpStream {
 	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-		Read::read(&mut (&*self.io as &TcpStream), buf)
+		match self.io {
+			SharedIo::Plain(ref io) => Read::read(&mut (&**io as &TcpStream), buf),
+			SharedIo::Tls(ref io) => io.lock().read(buf),
+		}
 	}
 }
 
 impl Write for SharedTcpStream {
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-		Write::write(&mut (&*self.io as &TcpStream), buf)
+		match self.io {
+			SharedIo::Plain(ref io) => Write::write(&mut (&**io as &TcpStream), buf),
+			SharedIo::Tls(ref io) => io.lock().write(buf),
+		}
 	}
 
 	fn flush(&mut self) -> Result<(), Error> {
-		Write::flush(&mut (&*self.io as &TcpStream))
+		match self.io {
+			SharedIo::Plain(ref io) => Write::flush(&mut (&**io as &TcpStream)),
+			SharedIo::Tls(ref io) => io.lock().flush(),
+		}
 	}
 }
 
 impl Clone for SharedTcpStream {
 	fn clone(&self) -> Self {
-		SharedTcpStream::new(self.io.clone())
+		SharedTcpStream {
+			io: match self.io {
+				SharedIo::Plain(ref io) => SharedIo::Plain(io.clone()),
+				SharedIo::Tls(ref io) => SharedIo::Tls(io.clone()),
+			},
+		}
 	}
 }
@@ -113,6 +113,19 @@ impl<ConsensusExecutor, ConsensusTransport, ComputationExecutor, ComputationTran
 			.collect()
 	}
 
+	/// Get nodes, which are known to participate in the session. This is the consensus group on master node
+	/// and master node + this node on other nodes.
+	pub fn participants(&self) -> BTreeSet<NodeId> {
+		let mut participants = BTreeSet::new();
+		participants.insert(self.meta.self_node_id.clone());
+		participants.insert(self.meta.master_node_id.clone());
+		if let Some(ref computation_job) = self.computation_job {
+			participants.extend(computation_job.requests().iter().cloned());
+			participants.extend(computation_job.responses().keys().cloned());
+		}
+		participants
+	}
+
 	/// Get computation job reference.
 	pub fn computation_job(&self) -> &JobSession<ComputationExecutor, ComputationTransport> {
 		self.computation_job.as_ref()
@@ -45,6 +45,8 @@ pub enum Message {
 	ShareAdd(ShareAddMessage),
 	/// Servers set change message.
 	ServersSetChange(ServersSetChangeMessage),
+	/// Key retirement message.
+	KeyRetirement(KeyRetirementMessage),
 }
 
 /// All possible cluster-level messages.
@@ -58,6 +60,8 @@ pub enum ClusterMessage {
 	KeepAlive(KeepAlive),
 	/// Keep alive message response.
 	KeepAliveResponse(KeepAliveResponse),
+	/// Keys, retired by administrator.
+	RetiredKeys(RetiredKeys),
 }
 
 /// All possible messages that can be sent during key generation session.
@@ -235,6 +239,19 @@ pub enum KeyVersionNegotiationMessage {
 	KeyVersionsError(KeyVersionsError),
 }
 
+/// All possible messages that can be sent during key retirement session.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum KeyRetirementMessage {
+	/// Prepare key retirement.
+	PrepareKeyRetirement(PrepareKeyRetirement),
+	/// Confirm key retirement preparation.
+	ConfirmKeyRetirementPreparation(ConfirmKeyRetirementPreparation),
+	/// Commit key retirement.
+	CommitKeyRetirement(CommitKeyRetirement),
+	/// When session error has occured.
+	KeyRetirementError(KeyRetirementError),
+}
+
 /// Introduce node public key.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NodePublicKey {
@@ -265,6 +282,22 @@ pub struct KeepAliveResponse {
 	pub session_id: Option<MessageSessionId>,
 }
 
+/// Keys, retired by administrator. Sent to every newly connected node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetiredKeys {
+	/// Retired keys.
+	pub keys: BTreeMap<MessageSessionId, RetiredKey>,
+}
+
+/// Single retired key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetiredKey {
+	/// Unix timestamp (in seconds) of the retirement.
+	pub retired_at: u64,
+	/// Key id, signed by administrator.
+	pub admin_signature: SerializableSignature,
+}
+
 /// Initialize new DKG session.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InitializeSession {
@@ -1055,6 +1088,48 @@ pub enum FailedKeyVersionContinueAction {
 	Decrypt(Option<SerializableAddress>, SerializableAddress),
 }
 
+/// Key retirement is requested.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrepareKeyRetirement {
+	/// Key id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Key id, signed by administrator.
+	pub admin_signature: SerializableSignature,
+}
+
+/// Node is prepared to retire the key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmKeyRetirementPreparation {
+	/// Key id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+}
+
+/// Quorum of nodes is prepared => key is retired.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitKeyRetirement {
+	/// Key id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Unix timestamp (in seconds) of the retirement.
+	pub retired_at: u64,
+}
+
+/// When key retirement session error has occured.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyRetirementError {
+	/// Key id.
+	pub session: MessageSessionId,
+	/// Session-level nonce.
+	pub session_nonce: u64,
+	/// Error message.
+	pub error: Error,
+}
+
 impl Message {
 	pub fn is_initialization_message(&self) -> bool {
 		match *self {
@@ -1082,6 +1157,7 @@ impl Message {
 				ConsensusMessageWithServersSet::InitializeConsensusSession(_) => true,
 				_ => false
 			},
+			Message::KeyRetirement(KeyRetirementMessage::PrepareKeyRetirement(_)) => true,
 			_ => false,
 		}
 	}
@@ -1105,6 +1181,7 @@ impl Message {
 			Message::KeyVersionNegotiation(KeyVersionNegotiationMessage::KeyVersionsError(_)) => true,
 			Message::ShareAdd(ShareAddMessage::ShareAddError(_)) => true,
 			Message::ServersSetChange(ServersSetChangeMessage::ServersSetChangeError(_)) => true,
+			Message::KeyRetirement(KeyRetirementMessage::KeyRetirementError(_)) => true,
 			_ => false,
 		}
 	}
@@ -1127,6 +1204,7 @@ impl Message {
 			Message::ShareAdd(ref message) => Some(message.session_nonce()),
 			Message::ServersSetChange(ref message) => Some(message.session_nonce()),
 			Message::KeyVersionNegotiation(ref message) => Some(message.session_nonce()),
+			Message::KeyRetirement(ref message) => Some(message.session_nonce()),
 		}
 	}
 }
@@ -1358,6 +1436,26 @@ impl ShareAddMessage {
 	}
 }
 
+impl KeyRetirementMessage {
+	pub fn session_id(&self) -> &SessionId {
+		match *self {
+			KeyRetirementMessage::PrepareKeyRetirement(ref msg) => &msg.session,
+			KeyRetirementMessage::ConfirmKeyRetirementPreparation(ref msg) => &msg.session,
+			KeyRetirementMessage::CommitKeyRetirement(ref msg) => &msg.session,
+			KeyRetirementMessage::KeyRetirementError(ref msg) => &msg.session,
+		}
+	}
+
+	pub fn session_nonce(&self) -> u64 {
+		match *self {
+			KeyRetirementMessage::PrepareKeyRetirement(ref msg) => msg.session_nonce,
+			KeyRetirementMessage::ConfirmKeyRetirementPreparation(ref msg) => msg.session_nonce,
+			KeyRetirementMessage::CommitKeyRetirement(ref msg) => msg.session_nonce,
+			KeyRetirementMessage::KeyRetirementError(ref msg) => msg.session_nonce,
+		}
+	}
+}
+
 impl KeyVersionNegotiationMessage {
 	pub fn session_id(&self) -> &SessionId {
 		match *self {
@@ -1396,6 +1494,7 @@ impl fmt::Display for Message {
 			Message::ServersSetChange(ref message) => write!(f, "ServersSetChange.{}", message),
 			Message::ShareAdd(ref message) => write!(f, "ShareAdd.{}", message),
 			Message::KeyVersionNegotiation(ref message) => write!(f, "KeyVersionNegotiation.{}", message),
+			Message::KeyRetirement(ref message) => write!(f, "KeyRetirement.{}", message),
 		}
 	}
 }
@@ -1407,6 +1506,7 @@ impl fmt::Display for ClusterMessage {
 			ClusterMessage::NodePrivateKeySignature(_) => write!(f, "NodePrivateKeySignature"),
 			ClusterMessage::KeepAlive(_) => write!(f, "KeepAlive"),
 			ClusterMessage::KeepAliveResponse(_) => write!(f, "KeepAliveResponse"),
+			ClusterMessage::RetiredKeys(_) => write!(f, "RetiredKeys"),
 		}
 	}
 }
@@ -1548,3 +1648,14 @@ impl fmt::Display for KeyVersionNegotiationMessage {
 		}
 	}
 }
+
+impl fmt::Display for KeyRetirementMessage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			KeyRetirementMessage::PrepareKeyRetirement(_) => write!(f, "PrepareKeyRetirement"),
+			KeyRetirementMessage::ConfirmKeyRetirementPreparation(_) => write!(f, "ConfirmKeyRetirementPreparation"),
+			KeyRetirementMessage::CommitKeyRetirement(_) => write!(f, "CommitKeyRetirement"),
+			KeyRetirementMessage::KeyRetirementError(ref msg) => write!(f, "KeyRetirementError({})", msg.error),
+		}
+	}
+}
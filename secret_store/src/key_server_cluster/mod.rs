@@ -19,12 +19,13 @@ use super::types::ServerKeyId;
 pub use super::traits::NodeKeyPair;
 pub use super::types::{Error, NodeId, Requester, EncryptedDocumentKeyShadow};
 pub use super::acl_storage::AclStorage;
-pub use super::key_storage::{KeyStorage, DocumentKeyShare, DocumentKeyShareVersion};
+pub use super::key_storage::{KeyStorage, DocumentKeyShare, DocumentKeyShareVersion, KeyRetirement};
 pub use super::key_server_set::{is_migration_required, KeyServerSet, KeyServerSetSnapshot, KeyServerSetMigration};
 pub use super::serialization::{SerializableSignature, SerializableH256, SerializableSecret, SerializablePublic,
 	SerializableRequester, SerializableMessageHash, SerializableAddress};
 pub use self::cluster::{ClusterCore, ClusterConfiguration, ClusterClient};
-pub use self::cluster_sessions::{ClusterSession, ClusterSessionsListener};
+pub use self::net::ClusterTls;
+pub use self::cluster_sessions::{ClusterSession, ClusterSessionsListener, SessionIdWithSubSession};
 #[cfg(test)]
 pub use self::cluster::tests::DummyClusterClient;
 
@@ -58,6 +59,7 @@ pub struct SessionMeta {
 mod admin_sessions;
 mod client_sessions;
 
+pub use self::admin_sessions::key_retirement_session;
 pub use self::admin_sessions::key_version_negotiation_session;
 pub use self::admin_sessions::servers_set_change_session;
 pub use self::admin_sessions::share_add_session;
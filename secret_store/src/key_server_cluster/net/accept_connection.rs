@@ -18,27 +18,46 @@ use std::io;
 use std::sync::Arc;
 use std::net::SocketAddr;
 use std::time::Duration;
-use futures::{Future, Poll};
+use futures::{Future, Poll, Async};
 use tokio_core::reactor::Handle;
 use tokio_core::net::TcpStream;
+use tokio_rustls::{AcceptAsync, ServerConfigExt};
 use key_server_cluster::{Error, NodeKeyPair};
-use key_server_cluster::io::{accept_handshake, Handshake, Deadline, deadline};
-use key_server_cluster::net::Connection;
+use key_server_cluster::io::{accept_handshake, Handshake, Deadline, deadline, SharedTcpStream};
+use key_server_cluster::net::{ClusterTls, Connection};
+use key_server_cluster::net::tls::{detect_tls, verify_peer_certificate, DetectTls};
 
-/// Create future for accepting incoming connection.
-pub fn accept_connection(address: SocketAddr, stream: TcpStream, handle: &Handle, self_key_pair: Arc<NodeKeyPair>) -> Deadline<AcceptConnection> {
+/// Create future for accepting incoming connection. When `tls` is set, connections starting with TLS handshake
+/// are wrapped into TLS session and the certificate of other node must be issued for the node it has authenticated
+/// as. Plaintext connections are only accepted when TLS is not required.
+pub fn accept_connection(address: SocketAddr, stream: TcpStream, handle: &Handle, self_key_pair: Arc<NodeKeyPair>, tls: Option<Arc<ClusterTls>>) -> Deadline<AcceptConnection> {
+	let state = match tls {
+		Some(_) => AcceptConnectionState::DetectTls(detect_tls(stream)),
+		None => AcceptConnectionState::Handshake(accept_handshake(SharedTcpStream::from(stream), self_key_pair.clone())),
+	};
 	let accept = AcceptConnection {
-		handshake: accept_handshake(stream, self_key_pair),
+		state: state,
 		address: address,
+		self_key_pair: self_key_pair,
+		tls: tls,
 	};
 
 	deadline(Duration::new(5, 0), handle, accept).expect("Failed to create timeout")
 }
 
+enum AcceptConnectionState {
+	DetectTls(DetectTls),
+	TlsAccept(AcceptAsync<TcpStream>),
+	Handshake(Handshake<SharedTcpStream>),
+	Accepted,
+}
+
 /// Future for accepting incoming connection.
 pub struct AcceptConnection {
-	handshake: Handshake<TcpStream>,
+	state: AcceptConnectionState,
 	address: SocketAddr,
+	self_key_pair: Arc<NodeKeyPair>,
+	tls: Option<Arc<ClusterTls>>,
 }
 
 impl Future for AcceptConnection {
@@ -46,17 +65,51 @@ impl Future for AcceptConnection {
 	type Error = io::Error;
 
 	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-		let (stream, result) = try_ready!(self.handshake.poll());
-		let result = match result {
-			Ok(result) => result,
-			Err(err) => return Ok(Err(err).into()),
+		let (next, result) = match self.state {
+			AcceptConnectionState::DetectTls(ref mut future) => {
+				let (stream, is_tls) = try_ready!(future.poll());
+				let tls = self.tls.as_ref().expect("DetectTls state is only entered when TLS is configured; qed");
+				if is_tls {
+					(AcceptConnectionState::TlsAccept(tls.server_config().accept_async(stream)), Async::NotReady)
+				} else if tls.is_required() {
+					return Ok(Async::Ready(Err(Error::Io("plaintext connection while TLS is required".into()))));
+				} else {
+					let handshake = accept_handshake(SharedTcpStream::from(stream), self.self_key_pair.clone());
+					(AcceptConnectionState::Handshake(handshake), Async::NotReady)
+				}
+			},
+			AcceptConnectionState::TlsAccept(ref mut future) => {
+				let stream = try_ready!(future.poll());
+				let handshake = accept_handshake(SharedTcpStream::new_tls(stream), self.self_key_pair.clone());
+				(AcceptConnectionState::Handshake(handshake), Async::NotReady)
+			},
+			AcceptConnectionState::Handshake(ref mut future) => {
+				let (stream, result) = try_ready!(future.poll());
+				let result = match result {
+					Ok(result) => result,
+					Err(err) => return Ok(Async::Ready(Err(err))),
+				};
+				if stream.peer_certificates().is_some() {
+					if let Err(err) = verify_peer_certificate(&stream, &result.node_id) {
+						return Ok(Async::Ready(Err(err)));
+					}
+				}
+				let connection = Connection {
+					stream: stream,
+					address: self.address,
+					node_id: result.node_id,
+					key: result.shared_key,
+				};
+				(AcceptConnectionState::Accepted, Async::Ready(Ok(connection)))
+			},
+			AcceptConnectionState::Accepted => panic!("poll AcceptConnection after it's done"),
 		};
-		let connection = Connection {
-			stream: stream.into(),
-			address: self.address,
-			node_id: result.node_id,
-			key: result.shared_key,
-		};
-		Ok(Ok(connection).into())
+
+		self.state = next;
+		match result {
+			// by polling again, we register new future
+			Async::NotReady => self.poll(),
+			result => Ok(result)
+		}
 	}
 }
@@ -22,17 +22,22 @@ use std::net::SocketAddr;
 use futures::{Future, Poll, Async};
 use tokio_core::reactor::Handle;
 use tokio_core::net::{TcpStream, TcpStreamNew};
+use tokio_rustls::{ClientConfigExt, ConnectAsync};
 use key_server_cluster::{Error, NodeId, NodeKeyPair};
-use key_server_cluster::io::{handshake, Handshake, Deadline, deadline};
-use key_server_cluster::net::Connection;
+use key_server_cluster::io::{handshake, Handshake, Deadline, deadline, SharedTcpStream};
+use key_server_cluster::net::{ClusterTls, Connection};
+use key_server_cluster::net::tls::{node_name, verify_peer_certificate};
 
-/// Create future for connecting to other node.
-pub fn connect(address: &SocketAddr, handle: &Handle, self_key_pair: Arc<NodeKeyPair>, trusted_nodes: BTreeSet<NodeId>) -> Deadline<Connect> {
+/// Create future for connecting to other node. When `tls` is set, the connection is wrapped into TLS session and
+/// the certificate of other node must be issued for the node it has authenticated as.
+pub fn connect(address: &SocketAddr, handle: &Handle, self_key_pair: Arc<NodeKeyPair>, trusted_nodes: BTreeSet<NodeId>, node_id: NodeId, tls: Option<Arc<ClusterTls>>) -> Deadline<Connect> {
 	let connect = Connect {
 		state: ConnectState::TcpConnect(TcpStream::connect(address, handle)),
 		address: address.clone(),
 		self_key_pair: self_key_pair,
 		trusted_nodes: trusted_nodes,
+		node_id: node_id,
+		tls: tls,
 	};
 
 	deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
@@ -40,7 +45,8 @@ pub fn connect(address: &SocketAddr, handle: &Handle, self_key_pair: Arc<NodeKey
 
 enum ConnectState {
 	TcpConnect(TcpStreamNew),
-	Handshake(Handshake<TcpStream>),
+	TlsConnect(ConnectAsync<TcpStream>),
+	Handshake(Handshake<SharedTcpStream>),
 	Connected,
 }
 
@@ -50,6 +56,8 @@ pub struct Connect {
 	address: SocketAddr,
 	self_key_pair: Arc<NodeKeyPair>,
 	trusted_nodes: BTreeSet<NodeId>,
+	node_id: NodeId,
+	tls: Option<Arc<ClusterTls>>,
 }
 
 impl Future for Connect {
@@ -60,7 +68,17 @@ impl Future for Connect {
 		let (next, result) = match self.state {
 			ConnectState::TcpConnect(ref mut future) => {
 				let stream = try_ready!(future.poll());
-				let handshake = handshake(stream, self.self_key_pair.clone(), self.trusted_nodes.clone());
+				match self.tls {
+					Some(ref tls) => (ConnectState::TlsConnect(tls.client_config().connect_async(&node_name(&self.node_id), stream)), Async::NotReady),
+					None => {
+						let handshake = handshake(SharedTcpStream::from(stream), self.self_key_pair.clone(), self.trusted_nodes.clone());
+						(ConnectState::Handshake(handshake), Async::NotReady)
+					},
+				}
+			},
+			ConnectState::TlsConnect(ref mut future) => {
+				let stream = try_ready!(future.poll());
+				let handshake = handshake(SharedTcpStream::new_tls(stream), self.self_key_pair.clone(), self.trusted_nodes.clone());
 				(ConnectState::Handshake(handshake), Async::NotReady)
 			},
 			ConnectState::Handshake(ref mut future) => {
@@ -69,8 +87,13 @@ impl Future for Connect {
 					Ok(result) => result,
 					Err(err) => return Ok(Async::Ready(Err(err))),
 				};
+				if self.tls.is_some() {
+					if let Err(err) = verify_peer_certificate(&stream, &result.node_id) {
+						return Ok(Async::Ready(Err(err)));
+					}
+				}
 				let connection = Connection {
-					stream: stream.into(),
+					stream: stream,
 					address: self.address,
 					node_id: result.node_id,
 					key: result.shared_key,
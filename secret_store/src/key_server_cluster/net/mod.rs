@@ -17,7 +17,9 @@
 mod accept_connection;
 mod connect;
 mod connection;
+mod tls;
 
 pub use self::accept_connection::{AcceptConnection, accept_connection};
 pub use self::connect::{Connect, connect};
 pub use self::connection::Connection;
+pub use self::tls::ClusterTls;
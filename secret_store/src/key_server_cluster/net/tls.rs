@@ -0,0 +1,165 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io;
+use std::sync::Arc;
+use futures::{Async, Future, Poll};
+use parking_lot::RwLock;
+use rustls::{ClientConfig, ServerConfig};
+use tokio_core::net::TcpStream;
+use untrusted;
+use webpki;
+use ethkey::public_to_address;
+use tls::{load_certified_key, read_certificates};
+use types::ClusterTlsConfiguration;
+use key_server_cluster::{Error, NodeId};
+use key_server_cluster::io::SharedTcpStream;
+
+/// Type of the first TLS record of every TLS connection. Plaintext connections start with message header version.
+const TLS_HANDSHAKE_RECORD_TYPE: u8 = 0x16;
+
+/// TLS settings of connections between key servers.
+pub struct ClusterTls {
+	/// Paths to the certificates and the key.
+	config: ClusterTlsConfiguration,
+	/// Client and server settings, built from files at `config`.
+	configs: RwLock<(Arc<ClientConfig>, Arc<ServerConfig>)>,
+}
+
+impl ClusterTls {
+	/// Read certificates and key from files.
+	pub fn new(config: ClusterTlsConfiguration) -> Result<Self, Error> {
+		let configs = load(&config)?;
+		Ok(ClusterTls {
+			config: config,
+			configs: RwLock::new(configs),
+		})
+	}
+
+	/// Are plaintext connections from other nodes rejected?
+	pub fn is_required(&self) -> bool {
+		self.config.required
+	}
+
+	/// Settings of outbound connections.
+	pub fn client_config(&self) -> Arc<ClientConfig> {
+		self.configs.read().0.clone()
+	}
+
+	/// Settings of inbound connections.
+	pub fn server_config(&self) -> Arc<ServerConfig> {
+		self.configs.read().1.clone()
+	}
+
+	/// Read certificates and key from files again. Previous settings are kept if files are unusable.
+	pub fn reload(&self) -> Result<(), Error> {
+		let configs = load(&self.config)?;
+		*self.configs.write() = configs;
+		info!(target: "secretstore_net", "Reloaded cluster TLS certificate from {}", self.config.node.cert_path.display());
+		Ok(())
+	}
+}
+
+/// DNS name, which certificate of the node must be issued for.
+pub fn node_name(node_id: &NodeId) -> String {
+	format!("{:x}.secretstore", public_to_address(node_id))
+}
+
+/// Check that the certificate, presented by other side of TLS stream, is issued for given node.
+/// The certificate chain itself is verified during TLS handshake.
+pub fn verify_peer_certificate(stream: &SharedTcpStream, node_id: &NodeId) -> Result<(), Error> {
+	let certs = stream.peer_certificates().ok_or(Error::InvalidNodeId)?;
+	let cert = certs.first().ok_or(Error::InvalidNodeId)?;
+	let cert = webpki::EndEntityCert::from(untrusted::Input::from(&cert.0)).map_err(|_| Error::InvalidNodeId)?;
+	cert.verify_is_valid_for_dns_name(untrusted::Input::from(node_name(node_id).as_bytes()))
+		.map_err(|_| Error::InvalidNodeId)
+}
+
+/// Create future, resolving to the stream and TLS flag once the first byte of inbound connection is received.
+pub fn detect_tls(stream: TcpStream) -> DetectTls {
+	DetectTls {
+		stream: Some(stream),
+	}
+}
+
+/// Future for detecting whether inbound connection starts with TLS handshake.
+pub struct DetectTls {
+	stream: Option<TcpStream>,
+}
+
+impl Future for DetectTls {
+	type Item = (TcpStream, bool);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		let mut first_byte = [0u8; 1];
+		let peeked = self.stream.as_ref().expect("poll DetectTls after it's done").peek(&mut first_byte);
+		match peeked {
+			Ok(read) => {
+				let stream = self.stream.take().expect("checked above; qed");
+				Ok(Async::Ready((stream, read == 1 && first_byte[0] == TLS_HANDSHAKE_RECORD_TYPE)))
+			},
+			Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+			Err(err) => Err(err),
+		}
+	}
+}
+
+fn load(config: &ClusterTlsConfiguration) -> Result<(Arc<ClientConfig>, Arc<ServerConfig>), Error> {
+	let (certs, key) = load_certified_key(&config.node)?;
+	let authorities = read_certificates(&config.ca_path)?;
+
+	let mut client_config = ClientConfig::new();
+	for authority in &authorities {
+		client_config.root_store.add(authority)
+			.map_err(|_| Error::Internal(format!("invalid TLS authority certificate in {}", config.ca_path.display())))?;
+	}
+	client_config.set_single_client_cert(certs.clone(), key.clone());
+
+	let mut server_config = ServerConfig::new();
+	server_config.set_client_auth_roots(authorities, true);
+	server_config.set_single_cert(certs, key);
+
+	Ok((Arc::new(client_config), Arc::new(server_config)))
+}
+
+#[cfg(test)]
+mod tests {
+	use ethkey::KeyPair;
+	use tls::tests::{node_secret, test_tls_config, test_tls_path};
+	use types::ClusterTlsConfiguration;
+	use super::{ClusterTls, node_name};
+
+	#[test]
+	fn node_name_is_address_of_node_public() {
+		let key_pair = KeyPair::from_secret(node_secret(1)).unwrap();
+		assert_eq!(node_name(key_pair.public()), "0d40a1680212f64e29abed5a28ff7c28a3a833fd.secretstore");
+	}
+
+	#[test]
+	fn cluster_tls_rejects_unknown_authority_file() {
+		assert!(ClusterTls::new(ClusterTlsConfiguration {
+			node: test_tls_config("node1.crt", "node1.key"),
+			ca_path: test_tls_path("missing.crt"),
+			required: false,
+		}).is_err());
+		assert!(ClusterTls::new(ClusterTlsConfiguration {
+			node: test_tls_config("node1.crt", "node1.key"),
+			ca_path: test_tls_path("cluster-ca.crt"),
+			required: true,
+		}).unwrap().is_required());
+	}
+}
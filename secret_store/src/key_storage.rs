@@ -19,11 +19,13 @@ use std::sync::Arc;
 use serde_json;
 use tiny_keccak::Keccak;
 use ethereum_types::{H256, Address};
-use ethkey::{Secret, Public, public_to_address};
+use ethkey::{Secret, Public, Signature, public_to_address};
 use kvdb::KeyValueDB;
 use types::{Error, ServerKeyId, NodeId};
-use serialization::{SerializablePublic, SerializableSecret, SerializableH256, SerializableAddress};
+use serialization::{SerializablePublic, SerializableSecret, SerializableH256, SerializableAddress, SerializableSignature};
 
+/// Database column, where retirements of keys are stored.
+pub const KEY_RETIREMENT_COLUMN: Option<u32> = Some(1);
 /// Key of version value.
 const DB_META_KEY_VERSION: &'static [u8; 7] = b"version";
 /// Current db version.
@@ -62,6 +64,15 @@ pub struct DocumentKeyShareVersion {
 	pub secret_share: Secret,
 }
 
+/// Retirement of the key. It is kept after the key share itself is erased, so that the key id is never served again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyRetirement {
+	/// Unix timestamp (in seconds) of the retirement.
+	pub retired_at: u64,
+	/// Key id, signed by administrator.
+	pub admin_signature: Signature,
+}
+
 /// Document encryption keys storage
 pub trait KeyStorage: Send + Sync {
 	/// Insert document encryption key
@@ -78,6 +89,12 @@ pub trait KeyStorage: Send + Sync {
 	fn contains(&self, document: &ServerKeyId) -> bool;
 	/// Iterate through storage
 	fn iter<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, DocumentKeyShare)> + 'a>;
+	/// Mark document encryption key as retired
+	fn retire(&self, document: ServerKeyId, retirement: KeyRetirement) -> Result<(), Error>;
+	/// Get retirement of document encryption key
+	fn retirement(&self, document: &ServerKeyId) -> Result<Option<KeyRetirement>, Error>;
+	/// Iterate through retired keys
+	fn iter_retired<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, KeyRetirement)> + 'a>;
 }
 
 /// Persistent document encryption keys storage
@@ -170,6 +187,15 @@ struct SerializableDocumentKeyShareV3 {
 /// V3 of encrypted key share version, as it is stored by key storage on the single key server.
 type SerializableDocumentKeyShareVersionV3 = SerializableDocumentKeyShareVersionV2;
 
+/// Key retirement, as it is stored by key storage on the single key server.
+#[derive(Serialize, Deserialize)]
+struct SerializableKeyRetirement {
+	/// Unix timestamp (in seconds) of the retirement.
+	pub retired_at: u64,
+	/// Key id, signed by administrator.
+	pub admin_signature: SerializableSignature,
+}
+
 impl PersistentKeyStorage {
 	/// Create new persistent document encryption keys storage
 	pub fn new(db: Arc<KeyValueDB>) -> Result<Self, Error> {
@@ -308,6 +334,33 @@ impl KeyStorage for PersistentKeyStorage {
 			iter: self.db.iter(None),
 		})
 	}
+
+	fn retire(&self, document: ServerKeyId, retirement: KeyRetirement) -> Result<(), Error> {
+		let retirement: SerializableKeyRetirement = retirement.into();
+		let retirement = serde_json::to_vec(&retirement).map_err(|e| Error::Database(e.to_string()))?;
+		let mut batch = self.db.transaction();
+		batch.put(KEY_RETIREMENT_COLUMN, &document, &retirement);
+		self.db.write(batch).map_err(Into::into)
+	}
+
+	fn retirement(&self, document: &ServerKeyId) -> Result<Option<KeyRetirement>, Error> {
+		self.db.get(KEY_RETIREMENT_COLUMN, document)
+			.map_err(|e| Error::Database(e.to_string()))
+			.and_then(|retirement| match retirement {
+				None => Ok(None),
+				Some(retirement) => serde_json::from_slice::<SerializableKeyRetirement>(&retirement)
+					.map_err(|e| Error::Database(e.to_string()))
+					.map(Into::into)
+					.map(Some),
+			})
+	}
+
+	fn iter_retired<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, KeyRetirement)> + 'a> {
+		Box::new(self.db.iter(KEY_RETIREMENT_COLUMN)
+			.filter_map(|(db_key, db_val)| serde_json::from_slice::<SerializableKeyRetirement>(&db_val)
+				.ok()
+				.map(|retirement| ((*db_key).into(), retirement.into()))))
+	}
 }
 
 impl<'a> Iterator for PersistentKeyStorageIterator<'a> {
@@ -387,6 +440,24 @@ impl From<DocumentKeyShareVersion> for SerializableDocumentKeyShareVersionV3 {
 	}
 }
 
+impl From<KeyRetirement> for SerializableKeyRetirement {
+	fn from(retirement: KeyRetirement) -> Self {
+		SerializableKeyRetirement {
+			retired_at: retirement.retired_at,
+			admin_signature: retirement.admin_signature.into(),
+		}
+	}
+}
+
+impl From<SerializableKeyRetirement> for KeyRetirement {
+	fn from(retirement: SerializableKeyRetirement) -> Self {
+		KeyRetirement {
+			retired_at: retirement.retired_at,
+			admin_signature: retirement.admin_signature.into(),
+		}
+	}
+}
+
 impl From<SerializableDocumentKeyShareV3> for DocumentKeyShare {
 	fn from(key: SerializableDocumentKeyShareV3) -> Self {
 		DocumentKeyShare {
@@ -416,17 +487,20 @@ pub mod tests {
 	use serde_json;
 	use self::tempdir::TempDir;
 	use ethereum_types::{Address, H256};
-	use ethkey::{Random, Generator, Public, Secret, public_to_address};
-	use kvdb_rocksdb::Database;
+	use ethkey::{Random, Generator, Public, Secret, sign, public_to_address};
+	use kvdb_rocksdb::{Database, DatabaseConfig};
 	use types::{Error, ServerKeyId};
+	use NUM_DB_COLUMNS;
 	use super::{DB_META_KEY_VERSION, CURRENT_VERSION, KeyStorage, PersistentKeyStorage, DocumentKeyShare,
 		DocumentKeyShareVersion, CurrentSerializableDocumentKeyShare, upgrade_db, SerializableDocumentKeyShareV0,
-		SerializableDocumentKeyShareV1, SerializableDocumentKeyShareV2, SerializableDocumentKeyShareVersionV2};
+		SerializableDocumentKeyShareV1, SerializableDocumentKeyShareV2, SerializableDocumentKeyShareVersionV2,
+		KeyRetirement};
 
 	/// In-memory document encryption keys storage
 	#[derive(Default)]
 	pub struct DummyKeyStorage {
 		keys: RwLock<HashMap<ServerKeyId, DocumentKeyShare>>,
+		retired: RwLock<HashMap<ServerKeyId, KeyRetirement>>,
 	}
 
 	impl KeyStorage for DummyKeyStorage {
@@ -461,6 +535,19 @@ pub mod tests {
 		fn iter<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, DocumentKeyShare)> + 'a> {
 			Box::new(self.keys.read().clone().into_iter())
 		}
+
+		fn retire(&self, document: ServerKeyId, retirement: KeyRetirement) -> Result<(), Error> {
+			self.retired.write().insert(document, retirement);
+			Ok(())
+		}
+
+		fn retirement(&self, document: &ServerKeyId) -> Result<Option<KeyRetirement>, Error> {
+			Ok(self.retired.read().get(document).cloned())
+		}
+
+		fn iter_retired<'a>(&'a self) -> Box<Iterator<Item=(ServerKeyId, KeyRetirement)> + 'a> {
+			Box::new(self.retired.read().clone().into_iter())
+		}
 	}
 
 	#[test]
@@ -516,6 +603,33 @@ pub mod tests {
 		assert_eq!(key_storage.get(&key3), Ok(None));
 	}
 
+	#[test]
+	fn persistent_key_storage_keeps_retirement_after_key_is_removed() {
+		let tempdir = TempDir::new("").unwrap();
+		let config = DatabaseConfig::with_columns(NUM_DB_COLUMNS);
+		let key1 = ServerKeyId::from(1);
+		let key2 = ServerKeyId::from(2);
+		let retirement = KeyRetirement {
+			retired_at: 1_500_000_000,
+			admin_signature: sign(Random.generate().unwrap().secret(), &key1).unwrap(),
+		};
+
+		let db = Database::open(&config, &tempdir.path().display().to_string()).unwrap();
+		let key_storage = PersistentKeyStorage::new(Arc::new(db)).unwrap();
+		key_storage.insert(key1.clone(), Default::default()).unwrap();
+		key_storage.retire(key1.clone(), retirement.clone()).unwrap();
+		key_storage.remove(&key1).unwrap();
+		key_storage.clear().unwrap();
+		drop(key_storage);
+
+		let db = Database::open(&config, &tempdir.path().display().to_string()).unwrap();
+		let key_storage = PersistentKeyStorage::new(Arc::new(db)).unwrap();
+		assert_eq!(key_storage.get(&key1), Ok(None));
+		assert_eq!(key_storage.retirement(&key1), Ok(Some(retirement.clone())));
+		assert_eq!(key_storage.retirement(&key2), Ok(None));
+		assert_eq!(key_storage.iter_retired().collect::<Vec<_>>(), vec![(key1, retirement)]);
+	}
+
 	#[test]
 	fn upgrade_db_from_0() {
 		let tempdir = TempDir::new("").unwrap();
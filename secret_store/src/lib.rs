@@ -30,6 +30,7 @@ extern crate keccak_hash as hash;
 extern crate kvdb;
 extern crate parking_lot;
 extern crate rustc_hex;
+extern crate rustls;
 extern crate serde;
 extern crate serde_json;
 extern crate tiny_keccak;
@@ -37,8 +38,11 @@ extern crate tokio;
 extern crate tokio_core;
 extern crate tokio_io;
 extern crate tokio_proto;
+extern crate tokio_rustls;
 extern crate tokio_service;
+extern crate untrusted;
 extern crate url;
+extern crate webpki;
 
 #[macro_use]
 extern crate ethabi_derive;
@@ -55,6 +59,8 @@ extern crate log;
 
 #[cfg(test)]
 extern crate kvdb_rocksdb;
+#[cfg(test)]
+extern crate tempdir;
 
 mod key_server_cluster;
 mod types;
@@ -62,6 +68,7 @@ mod helpers;
 
 mod traits;
 mod acl_storage;
+mod audit_log;
 mod key_server;
 mod key_storage;
 mod serialization;
@@ -69,6 +76,7 @@ mod key_server_set;
 mod node_key_pair;
 mod listener;
 mod trusted_client;
+mod tls;
 
 use std::sync::Arc;
 use kvdb::KeyValueDB;
@@ -77,28 +85,32 @@ use ethcore::miner::Miner;
 use sync::SyncProvider;
 
 pub use types::{ServerKeyId, EncryptedDocumentKey, RequestSignature, Public,
-	Error, NodeAddress, ContractAddress, ServiceConfiguration, ClusterConfiguration};
-pub use traits::{NodeKeyPair, KeyServer};
+	Error, NodeAddress, ContractAddress, ServiceConfiguration, ClusterConfiguration, TlsConfiguration, ClusterTlsConfiguration};
+pub use traits::{NodeKeyPair, KeyServer, TlsReloader};
 pub use self::node_key_pair::{PlainNodeKeyPair, KeyStoreNodeKeyPair};
 
+/// Number of columns in the key server database. Key shares are stored in the default column.
+pub const NUM_DB_COLUMNS: Option<u32> = Some(2);
+
 /// Start new key server instance
 pub fn start(client: Arc<Client>, sync: Arc<SyncProvider>, miner: Arc<Miner>, self_key_pair: Arc<NodeKeyPair>, mut config: ServiceConfiguration, db: Arc<KeyValueDB>) -> Result<Box<KeyServer>, Error> {
 	let trusted_client = trusted_client::TrustedClient::new(self_key_pair.clone(), client.clone(), sync, miner);
 	let acl_storage: Arc<acl_storage::AclStorage> = match config.acl_check_contract_address.take() {
-		Some(acl_check_contract_address) => acl_storage::OnChainAclStorage::new(trusted_client.clone(), acl_check_contract_address)?,
+		Some(acl_check_contract_address) => acl_storage::OnChainAclStorage::new(trusted_client.clone(), acl_check_contract_address, config.acl_cache_ttl)?,
 		None => Arc::new(acl_storage::DummyAclStorage::default()),
 	};
 
 	let key_server_set = key_server_set::OnChainKeyServerSet::new(trusted_client.clone(), config.cluster_config.key_server_set_contract_address.take(),
 		self_key_pair.clone(), config.cluster_config.auto_migrate_enabled, config.cluster_config.nodes.clone())?;
-	let key_storage = Arc::new(key_storage::PersistentKeyStorage::new(db)?);
-	let key_server = Arc::new(key_server::KeyServerImpl::new(&config.cluster_config, key_server_set.clone(), self_key_pair.clone(), acl_storage.clone(), key_storage.clone())?);
+	let key_storage = Arc::new(key_storage::PersistentKeyStorage::new(db.clone())?);
+	let audit_log = Arc::new(audit_log::PersistentAuditLog::new(db, config.audit_log_retention)?);
+	let key_server = Arc::new(key_server::KeyServerImpl::new(&config.cluster_config, key_server_set.clone(), self_key_pair.clone(), acl_storage.clone(), key_storage.clone(), audit_log)?);
 	let cluster = key_server.cluster();
 	let key_server: Arc<KeyServer> = key_server;
 
 	// prepare HTTP listener
 	let http_listener = match config.listener_address {
-		Some(listener_address) => Some(listener::http_listener::KeyServerHttpListener::start(listener_address, Arc::downgrade(&key_server))?),
+		Some(listener_address) => Some(listener::http_listener::KeyServerHttpListener::start(listener_address, config.listener_tls, Arc::downgrade(&key_server))?),
 		None => None,
 	};
 
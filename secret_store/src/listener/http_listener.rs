@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 use std::sync::{Arc, Weak};
 use hyper::{self, header, Chunk, Uri, Request as HttpRequest, Response as HttpResponse, Method as HttpMethod, StatusCode as HttpStatusCode};
 use hyper::server::Http;
@@ -23,14 +24,18 @@ use serde_json;
 use tokio;
 use tokio::net::TcpListener;
 use tokio::runtime::Runtime;
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_rustls::ServerConfigExt;
 use tokio_service::Service;
 use futures::{future, Future, Stream};
+use url::form_urlencoded;
 use url::percent_encoding::percent_decode;
 
+use listener::tls::TlsAcceptor;
 use traits::KeyServer;
-use serialization::{SerializableEncryptedDocumentKeyShadow, SerializableBytes, SerializablePublic};
+use serialization::{SerializableEncryptedDocumentKeyShadow, SerializableBytes, SerializablePublic, SerializableAuditLogPage};
 use types::{Error, Public, MessageHash, NodeAddress, RequestSignature, ServerKeyId,
-	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId};
+	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId, TlsConfiguration, AuditLogQuery, AuditLogPage};
 
 /// Key server http-requests listener. Available requests:
 /// To generate server key:							POST		/shadow/{server_key_id}/{signature}/{threshold}
@@ -41,10 +46,16 @@ use types::{Error, Public, MessageHash, NodeAddress, RequestSignature, ServerKey
 /// To generate Schnorr signature with server key:	GET			/schnorr/{server_key_id}/{signature}/{message_hash}
 /// To generate ECDSA signature with server key:	GET			/ecdsa/{server_key_id}/{signature}/{message_hash}
 /// To change servers set:							POST		/admin/servers_set_change/{old_signature}/{new_signature} + BODY: json array of hex-encoded nodes ids
+/// To retire server key:							POST		/admin/retire_key/{server_key_id}/{admin_signature}
+/// To read audit log of the node:					GET			/audit?document={server_key_id}&from={time}&to={time}&after={index}&limit={count}&signature={admin_signature}
+///   all parameters except signature are optional. Signature is the keccak of the query string without signature, signed by administrator.
+///
+/// When TLS is configured, requests are only accepted over HTTPS.
 
 pub struct KeyServerHttpListener {
 	_runtime: Runtime,
 	_handler: Arc<KeyServerSharedHttpHandler>,
+	tls_acceptor: Option<Arc<TlsAcceptor>>,
 }
 
 /// Parsed http request
@@ -68,6 +79,10 @@ enum Request {
 	EcdsaSignMessage(ServerKeyId, RequestSignature, MessageHash),
 	/// Change servers set.
 	ChangeServersSet(RequestSignature, RequestSignature, BTreeSet<NodeId>),
+	/// Retire server key.
+	RetireKey(ServerKeyId, RequestSignature),
+	/// Read audit log.
+	GetAuditLog(RequestSignature, AuditLogQuery),
 }
 
 /// Cloneable http handler
@@ -83,26 +98,34 @@ struct KeyServerSharedHttpHandler {
 
 impl KeyServerHttpListener {
 	/// Start KeyServer http listener
-	pub fn start(listener_address: NodeAddress, key_server: Weak<KeyServer>) -> Result<Self, Error> {
+	pub fn start(listener_address: NodeAddress, tls: Option<TlsConfiguration>, key_server: Weak<KeyServer>) -> Result<Self, Error> {
 		let shared_handler = Arc::new(KeyServerSharedHttpHandler {
 			key_server: key_server,
 		});
+		let tls_acceptor = match tls {
+			Some(tls) => Some(Arc::new(TlsAcceptor::new(tls)?)),
+			None => None,
+		};
 
 		let mut runtime = Runtime::new()?;
 		let listener_address = format!("{}:{}", listener_address.address, listener_address.port).parse()?;
 		let listener = TcpListener::bind(&listener_address)?;
 
 		let shared_handler2 = shared_handler.clone();
+		let tls_acceptor2 = tls_acceptor.clone();
 
 		let server = listener.incoming()
 			.map_err(|e| warn!("Key server listener error: {:?}", e))
 			.for_each(move |socket| {
-				let http: Http<Chunk> = Http::new();
-				let serve = http.serve_connection(socket, KeyServerHttpHandler {
+				let handler = KeyServerHttpHandler {
 					handler: shared_handler2.clone(),
-				}).map(|_| ()).map_err(|e| {
-					warn!("Key server handler error: {:?}", e);
-				});
+				};
+				let serve = match tls_acceptor2 {
+					Some(ref tls_acceptor) => future::Either::A(tls_acceptor.server_config().accept_async(socket)
+						.map_err(|e| warn!("Key server TLS handshake error: {:?}", e))
+						.and_then(move |socket| serve_connection(socket, handler))),
+					None => future::Either::B(serve_connection(socket, handler)),
+				};
 
 				tokio::spawn(serve)
 			});
@@ -112,15 +135,38 @@ impl KeyServerHttpListener {
 		let listener = KeyServerHttpListener {
 			_runtime: runtime,
 			_handler: shared_handler,
+			tls_acceptor: tls_acceptor,
 		};
 
 		Ok(listener)
 	}
+
+	/// Reload TLS certificate and key. Connections which are already established are kept.
+	pub fn reload_tls(&self) -> Result<(), Error> {
+		match self.tls_acceptor {
+			Some(ref tls_acceptor) => tls_acceptor.reload(),
+			None => Ok(()),
+		}
+	}
+}
+
+fn serve_connection<S>(socket: S, handler: KeyServerHttpHandler) -> impl Future<Item=(), Error=()>
+	where S: AsyncRead + AsyncWrite + 'static
+{
+	let http: Http<Chunk> = Http::new();
+	http.serve_connection(socket, handler).map(|_| ()).map_err(|e| {
+		warn!("Key server handler error: {:?}", e);
+	})
 }
 
 impl KeyServerHttpHandler {
 	fn process(self, req_method: HttpMethod, req_uri: Uri, path: &str, req_body: &[u8]) -> HttpResponse {
-		match parse_request(&req_method, &path, &req_body) {
+		let request = match path {
+			"/audit" => parse_audit_request(&req_method, req_uri.query()),
+			_ => parse_request(&req_method, &path, &req_body),
+		};
+
+		match request {
 			Request::GenerateServerKey(document, signature, threshold) => {
 				return_server_public_key(&req_uri, self.handler.key_server.upgrade()
 					.map(|key_server| key_server.generate_key(&document, &signature.into(), threshold))
@@ -193,6 +239,24 @@ impl KeyServerHttpHandler {
 						err
 					}))
 				},
+			Request::RetireKey(document, admin_signature) => {
+				return_empty(&req_uri, self.handler.key_server.upgrade()
+					.map(|key_server| key_server.retire_key(admin_signature, document))
+					.unwrap_or(Err(Error::Internal("KeyServer is already destroyed".into())))
+					.map_err(|err| {
+						warn!(target: "secretstore", "RetireKey request {} has failed with: {}", req_uri, err);
+						err
+					}))
+			},
+			Request::GetAuditLog(admin_signature, query) => {
+				return_audit_log(&req_uri, self.handler.key_server.upgrade()
+					.map(|key_server| key_server.audit_log(admin_signature, query))
+					.unwrap_or(Err(Error::Internal("KeyServer is already destroyed".into())))
+					.map_err(|err| {
+						warn!(target: "secretstore", "GetAuditLog request {} has failed with: {}", req_uri, err);
+						err
+					}))
+			},
 			Request::Invalid => {
 				warn!(target: "secretstore", "Ignoring invalid {}-request {}", req_method, req_uri);
 				HttpResponse::new().with_status(HttpStatusCode::BadRequest)
@@ -254,6 +318,10 @@ fn return_document_key_shadow(req_uri: &Uri, document_key_shadow: Result<Encrypt
 	})))
 }
 
+fn return_audit_log(req_uri: &Uri, page: Result<AuditLogPage, Error>) -> HttpResponse {
+	return_bytes(req_uri, page.map(|page| Some(SerializableAuditLogPage::from(page))))
+}
+
 fn return_bytes<T: Serialize>(req_uri: &Uri, result: Result<Option<T>, Error>) -> HttpResponse {
 	match result {
 		Ok(Some(result)) => match serde_json::to_vec(&result) {
@@ -276,6 +344,8 @@ fn return_error(err: Error) -> HttpResponse {
 			HttpStatusCode::Forbidden,
 		Error::ServerKeyIsNotFound | Error::DocumentKeyIsNotFound =>
 			HttpStatusCode::NotFound,
+		Error::KeyRetired =>
+			HttpStatusCode::Gone,
 		Error::InsufficientRequesterData(_) | Error::Hyper(_) | Error::Serde(_)
 			| Error::DocumentKeyAlreadyStored | Error::ServerKeyAlreadyGenerated =>
 			HttpStatusCode::BadRequest,
@@ -348,6 +418,12 @@ fn parse_request(method: &HttpMethod, uri_path: &str, body: &[u8]) -> Request {
 
 fn parse_admin_request(method: &HttpMethod, path: Vec<String>, body: &[u8]) -> Request {
 	let args_count = path.len();
+	if *method == HttpMethod::Post && args_count == 4 && path[1] == "retire_key" {
+		return match (path[2].parse(), path[3].parse()) {
+			(Ok(document), Ok(admin_signature)) => Request::RetireKey(document, admin_signature),
+			_ => Request::Invalid,
+		};
+	}
 	if *method != HttpMethod::Post || args_count != 4 || path[1] != "servers_set_change" {
 		return Request::Invalid;
 	}
@@ -371,24 +447,119 @@ fn parse_admin_request(method: &HttpMethod, path: Vec<String>, body: &[u8]) -> R
 		new_servers_set.into_iter().map(Into::into).collect())
 }
 
+fn parse_audit_request(method: &HttpMethod, query_string: Option<&str>) -> Request {
+	if *method != HttpMethod::Get {
+		return Request::Invalid;
+	}
+
+	let mut params = BTreeMap::new();
+	for (name, value) in form_urlencoded::parse(query_string.unwrap_or("").as_bytes()) {
+		if params.insert(name.into_owned(), value.into_owned()).is_some() {
+			return Request::Invalid;
+		}
+	}
+
+	let signature = match parse_param(&mut params, "signature") {
+		Ok(Some(signature)) => signature,
+		_ => return Request::Invalid,
+	};
+
+	match (parse_param(&mut params, "document"), parse_param(&mut params, "from"), parse_param(&mut params, "to"),
+		parse_param(&mut params, "after"), parse_param(&mut params, "limit")) {
+		(Ok(document), Ok(from), Ok(to), Ok(after), Ok(limit)) if params.is_empty() =>
+			Request::GetAuditLog(signature, AuditLogQuery {
+				document: document,
+				from: from,
+				to: to,
+				after: after,
+				limit: limit,
+			}),
+		_ => Request::Invalid,
+	}
+}
+
+fn parse_param<T: FromStr>(params: &mut BTreeMap<String, String>, name: &str) -> Result<Option<T>, T::Err> {
+	match params.remove(name) {
+		Some(value) => value.parse().map(Some),
+		None => Ok(None),
+	}
+}
+
 #[cfg(test)]
 mod tests {
+	use std::fs::File;
+	use std::io::BufReader;
 	use std::sync::Arc;
+	use futures::Future;
 	use hyper::Method as HttpMethod;
-	use ethkey::Public;
+	use rustc_hex::ToHex;
+	use rustls::ClientConfig;
+	use tokio::net::TcpStream;
+	use tokio::runtime::Runtime;
+	use tokio_io;
+	use tokio_rustls::ClientConfigExt;
+	use ethereum_types::H256;
+	use ethkey::{self, Generator, Public, Random};
+	use key_storage::KeyStorage;
 	use traits::KeyServer;
-	use key_server::tests::DummyKeyServer;
-	use types::NodeAddress;
-	use super::{parse_request, Request, KeyServerHttpListener};
+	use key_server::tests::{DummyKeyServer, make_key_servers};
+	use tls::tests::test_tls_path;
+	use types::{NodeAddress, TlsConfiguration, AuditLogQuery};
+	use super::{parse_request, parse_audit_request, Request, KeyServerHttpListener};
+
+	fn https_request(port: u16, request: String) -> String {
+		let mut config = ClientConfig::new();
+		config.root_store.add_pem_file(&mut BufReader::new(File::open(test_tls_path("ca.crt")).unwrap())).unwrap();
+		let config = Arc::new(config);
+
+		let address = format!("127.0.0.1:{}", port).parse().unwrap();
+		let response = TcpStream::connect(&address)
+			.and_then(move |socket| config.connect_async("localhost", socket))
+			.and_then(move |socket| tokio_io::io::write_all(socket, request.into_bytes()))
+			.and_then(|(socket, _)| tokio_io::io::read_to_end(socket, Vec::new()))
+			.map(|(_, response)| response);
+		let mut runtime = Runtime::new().unwrap();
+		String::from_utf8(runtime.block_on(response).unwrap()).unwrap()
+	}
 
 	#[test]
 	fn http_listener_successfully_drops() {
 		let key_server: Arc<KeyServer> = Arc::new(DummyKeyServer::default());
 		let address = NodeAddress { address: "127.0.0.1".into(), port: 9000 };
-		let listener = KeyServerHttpListener::start(address, Arc::downgrade(&key_server)).unwrap();
+		let listener = KeyServerHttpListener::start(address, None, Arc::downgrade(&key_server)).unwrap();
 		drop(listener);
 	}
 
+	#[test]
+	fn http_listener_fails_to_start_with_invalid_tls_configuration() {
+		let key_server: Arc<KeyServer> = Arc::new(DummyKeyServer::default());
+		let address = NodeAddress { address: "127.0.0.1".into(), port: 9001 };
+		let tls = TlsConfiguration { cert_path: test_tls_path("server.crt"), key_path: test_tls_path("ca.crt") };
+		assert!(KeyServerHttpListener::start(address, Some(tls), Arc::downgrade(&key_server)).is_err());
+	}
+
+	#[test]
+	fn server_key_generation_works_over_tls_with_2_nodes() {
+		let (key_servers, key_storages) = make_key_servers(6121, 2);
+		let key_servers: Vec<Arc<KeyServer>> = key_servers.into_iter().map(|ks| Arc::new(ks) as Arc<KeyServer>).collect();
+		let listeners: Vec<_> = key_servers.iter().enumerate().map(|(i, key_server)| KeyServerHttpListener::start(
+			NodeAddress { address: "127.0.0.1".into(), port: 9002 + i as u16 },
+			Some(TlsConfiguration { cert_path: test_tls_path("server.crt"), key_path: test_tls_path("server.key") }),
+			Arc::downgrade(key_server)).unwrap()).collect();
+
+		// generate server key through the listener of node1
+		let server_key_id = H256::random();
+		let requestor_secret = Random.generate().unwrap().secret().clone();
+		let signature = ethkey::sign(&requestor_secret, &server_key_id).unwrap();
+		let response = https_request(9003, format!("POST /shadow/{}/{}/1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+			server_key_id.to_hex(), signature));
+		assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {}", response);
+
+		// key shares are stored on both nodes
+		assert!(key_storages.iter().all(|key_storage| key_storage.get(&server_key_id).unwrap().is_some()));
+		drop(listeners);
+	}
+
 	#[test]
 	fn parse_request_successful() {
 		// POST		/shadow/{server_key_id}/{signature}/{threshold}						=> generate server key
@@ -440,6 +611,10 @@ mod tests {
 				"b199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01".parse().unwrap(),
 				nodes,
 			));
+		// POST		/admin/retire_key/{server_key_id}/{admin_signature}
+		assert_eq!(parse_request(&HttpMethod::Post, "/admin/retire_key/0000000000000000000000000000000000000000000000000000000000000001/a199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01", Default::default()),
+			Request::RetireKey("0000000000000000000000000000000000000000000000000000000000000001".into(),
+				"a199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01".parse().unwrap()));
 	}
 
 	#[test]
@@ -460,4 +635,26 @@ mod tests {
 		assert_eq!(parse_request(&HttpMethod::Post, "/admin/servers_set_change/a199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01/a199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01", "".as_bytes()),
 			Request::Invalid);
 	}
+
+	#[test]
+	fn parse_audit_request_works() {
+		let signature = "a199fb39e11eefb61c78a4074a53c0d4424600a3e74aad4fb9d93a26c30d067e1d4d29936de0c73f19827394a1dd049480a0d581aee7ae7546968da7d3d1c2fd01";
+		let query = AuditLogQuery {
+			document: Some("0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap()),
+			from: Some(100),
+			to: Some(200),
+			after: Some(5),
+			limit: Some(10),
+		};
+		assert_eq!(parse_audit_request(&HttpMethod::Get, Some(&format!("{}&signature={}", query.to_query_string(), signature))),
+			Request::GetAuditLog(signature.parse().unwrap(), query));
+		assert_eq!(parse_audit_request(&HttpMethod::Get, Some(&format!("signature={}", signature))),
+			Request::GetAuditLog(signature.parse().unwrap(), Default::default()));
+
+		assert_eq!(parse_audit_request(&HttpMethod::Get, None), Request::Invalid);
+		assert_eq!(parse_audit_request(&HttpMethod::Post, Some(&format!("signature={}", signature))), Request::Invalid);
+		assert_eq!(parse_audit_request(&HttpMethod::Get, Some(&format!("from=xxx&signature={}", signature))), Request::Invalid);
+		assert_eq!(parse_audit_request(&HttpMethod::Get, Some(&format!("from=1&from=2&signature={}", signature))), Request::Invalid);
+		assert_eq!(parse_audit_request(&HttpMethod::Get, Some(&format!("unknown=1&signature={}", signature))), Request::Invalid);
+	}
 }
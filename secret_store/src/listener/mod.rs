@@ -19,12 +19,13 @@ pub mod service_contract;
 pub mod service_contract_aggregate;
 pub mod service_contract_listener;
 mod tasks_queue;
+mod tls;
 
 use std::collections::BTreeSet;
 use std::sync::Arc;
-use traits::{ServerKeyGenerator, DocumentKeyServer, MessageSigner, AdminSessionsServer, KeyServer};
+use traits::{ServerKeyGenerator, DocumentKeyServer, MessageSigner, AdminSessionsServer, AuditLogServer, TlsReloader, KeyServer};
 use types::{Error, Public, MessageHash, EncryptedMessageSignature, RequestSignature, ServerKeyId,
-	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId, Requester};
+	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId, Requester, AuditLogQuery, AuditLogPage};
 
 /// Available API mask.
 #[derive(Debug, Default)]
@@ -42,7 +43,7 @@ pub struct ApiMask {
 /// Combined HTTP + service contract listener.
 pub struct Listener {
 	key_server: Arc<KeyServer>,
-	_http: Option<http_listener::KeyServerHttpListener>,
+	http: Option<http_listener::KeyServerHttpListener>,
 	_contract: Option<Arc<service_contract_listener::ServiceContractListener>>,
 }
 
@@ -63,7 +64,7 @@ impl Listener {
 	pub fn new(key_server: Arc<KeyServer>, http: Option<http_listener::KeyServerHttpListener>, contract: Option<Arc<service_contract_listener::ServiceContractListener>>) -> Self {
 		Self {
 			key_server: key_server,
-			_http: http,
+			http: http,
 			_contract: contract,
 		}
 	}
@@ -109,4 +110,23 @@ impl AdminSessionsServer for Listener {
 	fn change_servers_set(&self, old_set_signature: RequestSignature, new_set_signature: RequestSignature, new_servers_set: BTreeSet<NodeId>) -> Result<(), Error> {
 		self.key_server.change_servers_set(old_set_signature, new_set_signature, new_servers_set)
 	}
+
+	fn retire_key(&self, admin_signature: RequestSignature, key_id: ServerKeyId) -> Result<(), Error> {
+		self.key_server.retire_key(admin_signature, key_id)
+	}
+}
+
+impl AuditLogServer for Listener {
+	fn audit_log(&self, admin_signature: RequestSignature, query: AuditLogQuery) -> Result<AuditLogPage, Error> {
+		self.key_server.audit_log(admin_signature, query)
+	}
+}
+
+impl TlsReloader for Listener {
+	fn reload_tls(&self) -> Result<(), Error> {
+		if let Some(ref http) = self.http {
+			http.reload_tls()?;
+		}
+		self.key_server.reload_tls()
+	}
 }
@@ -29,6 +29,7 @@ use key_server_cluster::{NodeId, ClusterClient, ClusterSessionsListener, Cluster
 use key_server_cluster::math;
 use key_server_cluster::generation_session::SessionImpl as GenerationSession;
 use key_server_cluster::encryption_session::{check_encrypted_data, update_encrypted_data};
+use key_server_cluster::key_retirement_session::check_key_is_not_retired;
 use key_server_cluster::decryption_session::SessionImpl as DecryptionSession;
 use key_server_cluster::key_version_negotiation_session::{SessionImpl as KeyVersionNegotiationSession,
 	IsolatedSessionTransport as KeyVersionNegotiationTransport, FailedContinueAction};
@@ -335,7 +336,7 @@ impl ServiceContractListener {
 
 	/// Retrieve server key.
 	fn retrieve_server_key(data: &Arc<ServiceContractListenerData>, origin: Address, server_key_id: &ServerKeyId) -> Result<(), String> {
-		match data.key_storage.get(server_key_id) {
+		match check_key_is_not_retired(&*data.key_storage, server_key_id).and_then(|_| data.key_storage.get(server_key_id)) {
 			Ok(Some(server_key_share)) => {
 				data.contract.publish_retrieved_server_key(&origin, server_key_id, server_key_share.public, server_key_share.threshold)
 			},
@@ -355,7 +356,8 @@ impl ServiceContractListener {
 
 	/// Store document key.
 	fn store_document_key(data: &Arc<ServiceContractListenerData>, origin: Address, server_key_id: &ServerKeyId, author: &Address, common_point: &Public, encrypted_point: &Public) -> Result<(), String> {
-		let store_result = data.key_storage.get(server_key_id)
+		let store_result = check_key_is_not_retired(&*data.key_storage, server_key_id)
+			.and_then(|_| data.key_storage.get(server_key_id))
 			.and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound))
 			.and_then(|key_share| check_encrypted_data(Some(&key_share)).map(|_| key_share).map_err(Into::into))
 			.and_then(|key_share| update_encrypted_data(&data.key_storage, server_key_id.clone(), key_share,
@@ -379,6 +381,7 @@ impl ServiceContractListener {
 	fn retrieve_document_key_common(data: &Arc<ServiceContractListenerData>, origin: Address, server_key_id: &ServerKeyId, requester: &Address) -> Result<(), String> {
 		let retrieval_result = data.acl_storage.check(requester.clone(), server_key_id)
 			.and_then(|is_allowed| if !is_allowed { Err(Error::AccessDenied) } else { Ok(()) })
+			.and_then(|_| check_key_is_not_retired(&*data.key_storage, server_key_id))
 			.and_then(|_| data.key_storage.get(server_key_id).and_then(|key_share| key_share.ok_or(Error::ServerKeyIsNotFound)))
 			.and_then(|key_share| key_share.common_point
 				.ok_or(Error::DocumentKeyIsNotFound)
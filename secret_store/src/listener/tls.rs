@@ -0,0 +1,114 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use parking_lot::RwLock;
+use rustls::ServerConfig;
+use tls::load_certified_key;
+use types::{Error, TlsConfiguration};
+
+/// TLS server configuration of the HTTP listener. Certificate and key are only read again on `reload`.
+pub struct TlsAcceptor {
+	/// Paths of the certificate and the key.
+	config: TlsConfiguration,
+	/// Server configuration of new connections.
+	server_config: RwLock<Arc<ServerConfig>>,
+}
+
+impl TlsAcceptor {
+	/// Load certificate and key, failing if any of them is unusable.
+	pub fn new(config: TlsConfiguration) -> Result<Self, Error> {
+		let server_config = load(&config)?;
+		Ok(TlsAcceptor {
+			config: config,
+			server_config: RwLock::new(server_config),
+		})
+	}
+
+	/// Server configuration for the new connection.
+	pub fn server_config(&self) -> Arc<ServerConfig> {
+		self.server_config.read().clone()
+	}
+
+	/// Load certificate and key again. If they are unusable (e.g. certificate is already replaced, but the key
+	/// is not yet), previous configuration is kept.
+	pub fn reload(&self) -> Result<(), Error> {
+		let server_config = load(&self.config)?;
+		*self.server_config.write() = server_config;
+		info!(target: "secretstore", "Reloaded HTTP listener TLS certificate from {}", self.config.cert_path.display());
+		Ok(())
+	}
+}
+
+fn load(config: &TlsConfiguration) -> Result<Arc<ServerConfig>, Error> {
+	let (certs, key) = load_certified_key(config)?;
+	let mut server_config = ServerConfig::new();
+	server_config.set_single_cert(certs, key);
+	Ok(Arc::new(server_config))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use std::sync::Arc;
+	use tempdir::TempDir;
+	use tls::tests::{test_tls_path, test_tls_config};
+	use types::TlsConfiguration;
+	use super::TlsAcceptor;
+
+	#[test]
+	fn tls_acceptor_rejects_invalid_files() {
+		assert!(TlsAcceptor::new(test_tls_config("server.key", "server.key")).is_err());
+		assert!(TlsAcceptor::new(test_tls_config("server.crt", "server.crt")).is_err());
+		assert!(TlsAcceptor::new(test_tls_config("server.crt", "missing.key")).is_err());
+		assert!(TlsAcceptor::new(test_tls_config("server.crt", "node1.key")).is_err());
+	}
+
+	#[test]
+	fn tls_acceptor_reloads_files_on_request() {
+		let dir = TempDir::new("tls").unwrap();
+		let config = TlsConfiguration {
+			cert_path: dir.path().join("server.crt"),
+			key_path: dir.path().join("server.key"),
+		};
+		fs::copy(test_tls_path("server.crt"), &config.cert_path).unwrap();
+		fs::copy(test_tls_path("server.key"), &config.key_path).unwrap();
+
+		let acceptor = TlsAcceptor::new(config.clone()).unwrap();
+		let initial = acceptor.server_config();
+		assert!(Arc::ptr_eq(&initial, &acceptor.server_config()));
+
+		// modified files are not read until reload is requested
+		fs::write(&config.cert_path, b"not a certificate").unwrap();
+		assert!(Arc::ptr_eq(&initial, &acceptor.server_config()));
+
+		// broken certificate is not loaded
+		assert!(acceptor.reload().is_err());
+		assert!(Arc::ptr_eq(&initial, &acceptor.server_config()));
+
+		// certificate which does not match the key is not loaded
+		fs::copy(test_tls_path("node1.crt"), &config.cert_path).unwrap();
+		assert!(acceptor.reload().is_err());
+		assert!(Arc::ptr_eq(&initial, &acceptor.server_config()));
+
+		// fixed certificate is loaded
+		fs::copy(test_tls_path("server.crt"), &config.cert_path).unwrap();
+		acceptor.reload().unwrap();
+		let reloaded = acceptor.server_config();
+		assert!(!Arc::ptr_eq(&initial, &reloaded));
+		assert!(Arc::ptr_eq(&reloaded, &acceptor.server_config()));
+	}
+}
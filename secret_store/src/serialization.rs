@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeSet;
 use std::fmt;
 use std::ops::Deref;
 use rustc_hex::{ToHex, FromHex};
@@ -22,7 +23,7 @@ use serde::de::{Visitor, Error as SerdeError};
 use ethkey::{Public, Secret, Signature};
 use ethereum_types::{H160, H256};
 use bytes::Bytes;
-use types::Requester;
+use types::{Requester, AuditedSession, AuditLogEntry, AuditLogPage};
 
 macro_rules! impl_bytes_deserialize {
 	($name: ident, $value: expr, true) => {
@@ -156,6 +157,79 @@ impl From<Requester> for SerializableRequester {
 	}
 }
 
+/// Serializable audit log entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SerializableAuditLogEntry {
+	/// Index of the entry in the log of this node.
+	pub index: u64,
+	/// Unix time (in seconds) when the session has been completed on this node.
+	pub timestamp: u64,
+	/// Kind of the session.
+	pub session: AuditedSession,
+	/// Session id.
+	pub session_id: SerializableH256,
+	/// Id of the server key the session has been working with.
+	pub document: SerializableH256,
+	/// Public key of the requester (if known).
+	pub requester_public: Option<SerializablePublic>,
+	/// Address of the requester (if known).
+	pub requester_address: Option<SerializableAddress>,
+	/// Error, if session has failed on this node.
+	pub error: Option<String>,
+	/// Nodes, which are known to participate in the session.
+	pub nodes: BTreeSet<SerializablePublic>,
+}
+
+impl From<AuditLogEntry> for SerializableAuditLogEntry {
+	fn from(entry: AuditLogEntry) -> SerializableAuditLogEntry {
+		SerializableAuditLogEntry {
+			index: entry.index,
+			timestamp: entry.timestamp,
+			session: entry.session,
+			session_id: entry.session_id.into(),
+			document: entry.document.into(),
+			requester_public: entry.requester_public.map(Into::into),
+			requester_address: entry.requester_address.map(Into::into),
+			error: entry.error,
+			nodes: entry.nodes.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+impl From<SerializableAuditLogEntry> for AuditLogEntry {
+	fn from(entry: SerializableAuditLogEntry) -> AuditLogEntry {
+		AuditLogEntry {
+			index: entry.index,
+			timestamp: entry.timestamp,
+			session: entry.session,
+			session_id: entry.session_id.into(),
+			document: entry.document.into(),
+			requester_public: entry.requester_public.map(Into::into),
+			requester_address: entry.requester_address.map(Into::into),
+			error: entry.error,
+			nodes: entry.nodes.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// Serializable page of audit log entries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SerializableAuditLogPage {
+	/// Entries, ordered by index.
+	pub entries: Vec<SerializableAuditLogEntry>,
+	/// If there are more matching entries, index to request the next page `after`.
+	pub next: Option<u64>,
+}
+
+impl From<AuditLogPage> for SerializableAuditLogPage {
+	fn from(page: AuditLogPage) -> SerializableAuditLogPage {
+		SerializableAuditLogPage {
+			entries: page.entries.into_iter().map(Into::into).collect(),
+			next: page.next,
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use serde_json;
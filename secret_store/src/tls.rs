@@ -0,0 +1,112 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Loading of the TLS certificates and keys, shared by the HTTP listener and the cluster connections.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use rustls::{Certificate, PrivateKey, SignatureScheme};
+use rustls::internal::pemfile;
+use rustls::sign::{RSASigningKey, SigningKey};
+use untrusted;
+use webpki;
+use types::{Error, TlsConfiguration};
+
+/// Message signed with the key to check that it belongs to the certificate.
+const KEY_CHECK_MESSAGE: &'static [u8] = b"secret store TLS key check";
+
+/// Read the certificate chain and the key of `config`, failing unless the key belongs to the first certificate.
+pub fn load_certified_key(config: &TlsConfiguration) -> Result<(Vec<Certificate>, PrivateKey), Error> {
+	let certs = read_certificates(&config.cert_path)?;
+	let key = read_private_key(&config.key_path)?;
+	check_key_matches_certificate(&certs[0], &key)
+		.map_err(|_| Error::Internal(format!("TLS key in {} does not match certificate in {}", config.key_path.display(), config.cert_path.display())))?;
+	Ok((certs, key))
+}
+
+/// Read PEM-encoded certificates, failing if there are none.
+pub fn read_certificates(path: &Path) -> Result<Vec<Certificate>, Error> {
+	let certs = pemfile::certs(&mut BufReader::new(File::open(path)?))
+		.map_err(|_| Error::Internal(format!("invalid TLS certificate file {}", path.display())))?;
+	if certs.is_empty() {
+		return Err(Error::Internal(format!("no TLS certificates in {}", path.display())));
+	}
+
+	Ok(certs)
+}
+
+/// Read PEM-encoded PKCS#8 or RSA private key.
+pub fn read_private_key(path: &Path) -> Result<PrivateKey, Error> {
+	let invalid_key = || Error::Internal(format!("invalid TLS key file {}", path.display()));
+	let pkcs8_keys = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(path)?)).map_err(|_| invalid_key())?;
+	let rsa_keys = pemfile::rsa_private_keys(&mut BufReader::new(File::open(path)?)).map_err(|_| invalid_key())?;
+	pkcs8_keys.into_iter().chain(rsa_keys).next()
+		.ok_or_else(|| Error::Internal(format!("no TLS key in {}", path.display())))
+}
+
+// signs a message with `key` and verifies the signature with the public key of `cert`.
+fn check_key_matches_certificate(cert: &Certificate, key: &PrivateKey) -> Result<(), ()> {
+	let signer = RSASigningKey::new(key)?
+		.choose_scheme(&[SignatureScheme::RSA_PKCS1_SHA256])
+		.ok_or(())?;
+	let signature = signer.sign(KEY_CHECK_MESSAGE).map_err(|_| ())?;
+	let cert = webpki::EndEntityCert::from(untrusted::Input::from(&cert.0)).map_err(|_| ())?;
+	cert.verify_signature(&webpki::RSA_PKCS1_2048_8192_SHA256, untrusted::Input::from(KEY_CHECK_MESSAGE), untrusted::Input::from(&signature))
+		.map_err(|_| ())
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::path::PathBuf;
+	use ethkey::Secret;
+	use types::TlsConfiguration;
+	use super::load_certified_key;
+
+	pub fn test_tls_path(name: &str) -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("res").join("tls").join(name)
+	}
+
+	pub fn test_tls_config(cert: &str, key: &str) -> TlsConfiguration {
+		TlsConfiguration {
+			cert_path: test_tls_path(cert),
+			key_path: test_tls_path(key),
+		}
+	}
+
+	/// Secret of the node, which `node<index>.crt` is issued for.
+	pub fn node_secret(index: usize) -> Secret {
+		match index {
+			1 => "3a4b5bcfa8e5e1e6d1b1b6a0c1c7b4f1b2a6d1e0c3f7a9b8e2d4c6a1b3f5e7d9".parse().unwrap(),
+			2 => "7c1e2d3f4a5b6c7d8e9f0a1b2c3d4e5f60718293a4b5c6d7e8f9a0b1c2d3e4f5".parse().unwrap(),
+			_ => unreachable!("there are certificates of nodes 1 and 2 only"),
+		}
+	}
+
+	#[test]
+	fn loads_matching_certificate_and_key() {
+		assert!(load_certified_key(&test_tls_config("server.crt", "server.key")).is_ok());
+		assert!(load_certified_key(&test_tls_config("node1.crt", "node1.key")).is_ok());
+	}
+
+	#[test]
+	fn rejects_certificate_and_key_which_do_not_match() {
+		assert!(load_certified_key(&test_tls_config("server.crt", "node1.key")).is_err());
+		assert!(load_certified_key(&test_tls_config("node1.crt", "node2.key")).is_err());
+		assert!(load_certified_key(&test_tls_config("server.key", "server.key")).is_err());
+		assert!(load_certified_key(&test_tls_config("server.crt", "missing.key")).is_err());
+	}
+}
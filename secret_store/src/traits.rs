@@ -18,7 +18,7 @@ use std::collections::BTreeSet;
 use ethkey::{KeyPair, Signature, Error as EthKeyError};
 use ethereum_types::{H256, Address};
 use types::{Error, Public, ServerKeyId, MessageHash, EncryptedMessageSignature, RequestSignature, Requester,
-	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId};
+	EncryptedDocumentKey, EncryptedDocumentKeyShadow, NodeId, AuditLogQuery, AuditLogPage};
 
 /// Node key pair.
 pub trait NodeKeyPair: Send + Sync {
@@ -98,8 +98,25 @@ pub trait AdminSessionsServer {
 	/// WARNING: newly generated keys will be distributed among all cluster nodes. So this session
 	/// must be followed with cluster nodes change (either via contract, or config files).
 	fn change_servers_set(&self, old_set_signature: RequestSignature, new_set_signature: RequestSignature, new_servers_set: BTreeSet<NodeId>) -> Result<(), Error>;
+	/// Retire the key, so that it can't be used anymore. Shares of the key are erased after grace period.
+	/// `admin_signature` is the key retirement hash (`key_retirement_hash(key_id)`), signed with administrator key.
+	fn retire_key(&self, admin_signature: RequestSignature, key_id: ServerKeyId) -> Result<(), Error>;
+}
+
+/// Audit log server.
+pub trait AuditLogServer {
+	/// Read page of this node audit log entries, matching the query.
+	/// `admin_signature` is the hash of the query (`AuditLogQuery::hash`), signed with administrator key.
+	fn audit_log(&self, admin_signature: RequestSignature, query: AuditLogQuery) -> Result<AuditLogPage, Error>;
+}
+
+/// TLS certificates reloader.
+pub trait TlsReloader {
+	/// Read TLS certificates and keys of the HTTP listener and of the cluster connections again.
+	/// Connections which are already established are kept. Unusable certificates and keys are not loaded.
+	fn reload_tls(&self) -> Result<(), Error>;
 }
 
 /// Key server.
-pub trait KeyServer: AdminSessionsServer + DocumentKeyServer + MessageSigner + Send + Sync {
+pub trait KeyServer: AdminSessionsServer + AuditLogServer + DocumentKeyServer + MessageSigner + TlsReloader + Send + Sync {
 }
@@ -14,8 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
 
+use hash::keccak;
 use {ethkey, bytes, ethereum_types};
 
 /// Node id.
@@ -56,6 +59,8 @@ pub enum ContractAddress {
 pub struct ServiceConfiguration {
 	/// HTTP listener address. If None, HTTP API is disabled.
 	pub listener_address: Option<NodeAddress>,
+	/// HTTP listener TLS configuration. If None, HTTP API is served over plain HTTP.
+	pub listener_tls: Option<TlsConfiguration>,
 	/// Service contract address.
 	pub service_contract_address: Option<ContractAddress>,
 	/// Server key generation service contract address.
@@ -68,10 +73,36 @@ pub struct ServiceConfiguration {
 	pub service_contract_doc_sretr_address: Option<ContractAddress>,
 	/// ACL check contract address. If None, everyone has access to all keys. Useful for tests only.
 	pub acl_check_contract_address: Option<ContractAddress>,
+	/// Max age of cached ACL check results. If None, ACL contract is called for every check.
+	pub acl_cache_ttl: Option<Duration>,
+	/// Max number of entries in the audit log. Oldest entries are pruned first.
+	pub audit_log_retention: usize,
 	/// Cluster configuration.
 	pub cluster_config: ClusterConfiguration,
 }
 
+/// TLS certificate and key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfiguration {
+	/// Path to the PEM-encoded certificate chain, starting with the own certificate.
+	pub cert_path: PathBuf,
+	/// Path to the PEM-encoded private key (PKCS#8 or RSA) of the own certificate.
+	pub key_path: PathBuf,
+}
+
+/// TLS configuration of the connections between key servers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterTlsConfiguration {
+	/// Certificate and key of this node. The certificate must be issued for the DNS name
+	/// `<hex address of the node public key>.secretstore`, so that it can't be used by other key servers.
+	pub node: TlsConfiguration,
+	/// Path to the PEM-encoded certificates of the authorities issuing key server certificates.
+	pub ca_path: PathBuf,
+	/// Refuse plaintext connections from other key servers. Otherwise both TLS and plaintext
+	/// connections are accepted, while outbound connections always use TLS.
+	pub required: bool,
+}
+
 /// Key server cluster configuration
 #[derive(Debug)]
 pub struct ClusterConfiguration {
@@ -91,6 +122,10 @@ pub struct ClusterConfiguration {
 	/// Should key servers set change session should be started when servers set changes.
 	/// This will only work when servers set is configured using KeyServerSet contract.
 	pub auto_migrate_enabled: bool,
+	/// Time to keep shares of retired keys before erasing them.
+	pub key_retirement_grace_period: Duration,
+	/// TLS configuration of the connections to other key servers. If None, connections are plaintext.
+	pub tls: Option<ClusterTlsConfiguration>,
 }
 
 /// Shadow decryption result.
@@ -104,6 +139,97 @@ pub struct EncryptedDocumentKeyShadow {
 	pub decrypt_shadows: Option<Vec<Vec<u8>>>,
 }
 
+/// Kind of the session, recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditedSession {
+	/// Server key generation.
+	ServerKeyGeneration,
+	/// Document key retrieval.
+	DocumentKeyRetrieval,
+	/// Document key shadow retrieval.
+	DocumentKeyShadowRetrieval,
+	/// Schnorr signing.
+	SchnorrSigning,
+	/// ECDSA signing.
+	EcdsaSigning,
+}
+
+/// Audit log entry. Every node, participating in the session, records its own entry when session is completed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditLogEntry {
+	/// Index of the entry in the log of this node.
+	pub index: u64,
+	/// Unix time (in seconds) when the session has been completed on this node.
+	pub timestamp: u64,
+	/// Kind of the session.
+	pub session: AuditedSession,
+	/// Session id. It is the same on all nodes, participating in the session.
+	pub session_id: ethereum_types::H256,
+	/// Id of the server key the session has been working with.
+	pub document: ServerKeyId,
+	/// Public key of the requester (if known).
+	pub requester_public: Option<Public>,
+	/// Address of the requester (if known).
+	pub requester_address: Option<ethereum_types::Address>,
+	/// Error, if session has failed on this node.
+	pub error: Option<String>,
+	/// Nodes, which are known to participate in the session.
+	pub nodes: BTreeSet<NodeId>,
+}
+
+/// Audit log query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditLogQuery {
+	/// Only return entries for this server key.
+	pub document: Option<ServerKeyId>,
+	/// Only return entries, recorded at, or after this Unix time.
+	pub from: Option<u64>,
+	/// Only return entries, recorded at, or before this Unix time.
+	pub to: Option<u64>,
+	/// Only return entries with greater index. This is the `next` value of the previous page.
+	pub after: Option<u64>,
+	/// Max number of entries to return.
+	pub limit: Option<usize>,
+}
+
+impl AuditLogQuery {
+	/// Canonical query string: `document=..&from=..&to=..&after=..&limit=..`, omitting missing parameters.
+	pub fn to_query_string(&self) -> String {
+		let mut params = Vec::new();
+		if let Some(ref document) = self.document {
+			params.push(format!("document={:x}", document));
+		}
+		if let Some(from) = self.from {
+			params.push(format!("from={}", from));
+		}
+		if let Some(to) = self.to {
+			params.push(format!("to={}", to));
+		}
+		if let Some(after) = self.after {
+			params.push(format!("after={}", after));
+		}
+		if let Some(limit) = self.limit {
+			params.push(format!("limit={}", limit));
+		}
+		params.join("&")
+	}
+
+	/// Hash of the query, which is signed by administrator. This is keccak of the canonical query string.
+	pub fn hash(&self) -> ethereum_types::H256 {
+		keccak(self.to_query_string().as_bytes())
+	}
+}
+
+/// Page of audit log entries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditLogPage {
+	/// Entries, ordered by index.
+	pub entries: Vec<AuditLogEntry>,
+	/// If there are more matching entries, index to request the next page `after`.
+	pub next: Option<u64>,
+}
+
 /// Requester identification data.
 #[derive(Debug, Clone)]
 pub enum Requester {
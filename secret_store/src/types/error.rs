@@ -59,6 +59,8 @@ pub enum Error {
 	DocumentKeyAlreadyStored,
 	/// Document key with this ID is not yet stored.
 	DocumentKeyIsNotFound,
+	/// Key with this ID has been retired by administrator.
+	KeyRetired,
 	/// Consensus is temporary unreachable. Means that something is currently blocking us from either forming
 	/// consensus group (like disconnecting from too many nodes, which are AGREE to participate in consensus)
 	/// or from rejecting request (disconnecting from AccessDenied-nodes).
@@ -117,7 +119,7 @@ impl Error {
 			Error::InvalidNodeAddress | Error::InvalidNodeId |
 			// wrong session input params errors
 			Error::NotEnoughNodesForThreshold | Error::ServerKeyAlreadyGenerated | Error::ServerKeyIsNotFound |
-				Error::DocumentKeyAlreadyStored | Error::DocumentKeyIsNotFound | Error::InsufficientRequesterData(_) |
+				Error::DocumentKeyAlreadyStored | Error::DocumentKeyIsNotFound | Error::KeyRetired | Error::InsufficientRequesterData(_) |
 			// access denied/consensus error
 			Error::AccessDenied | Error::ConsensusUnreachable |
 			// indeterminate internal errors, which could be either fatal (db failure, invalid request), or not (network error),
@@ -146,6 +148,7 @@ impl fmt::Display for Error {
 			Error::ServerKeyIsNotFound => write!(f, "Server key with this ID is not found"),
 			Error::DocumentKeyAlreadyStored => write!(f, "Document key with this ID is already stored"),
 			Error::DocumentKeyIsNotFound => write!(f, "Document key with this ID is not found"),
+			Error::KeyRetired => write!(f, "Key with this ID is retired"),
 			Error::ConsensusUnreachable => write!(f, "Consensus unreachable"),
 			Error::ConsensusTemporaryUnreachable => write!(f, "Consensus temporary unreachable"),
 			Error::AccessDenied => write!(f, "Access denied"),
@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{error, io, fmt};
+use std::{error, io, fmt, fs};
 use std::path::{Path, PathBuf};
 
 use ethbloom;
 
 use file::{File, FileIterator};
 
+/// Names of the top, mid and bot level bloom files.
+const FILE_NAMES: [&'static str; 3] = ["top.bdb", "mid.bdb", "bot.bdb"];
+
 fn other_io_err<E>(e: E) -> io::Error where E: Into<Box<error::Error + Send + Sync>> {
 	io::Error::new(io::ErrorKind::Other, e)
 }
@@ -69,9 +72,9 @@ impl DatabaseFiles {
 	/// Open the blooms db files
 	pub fn open(path: &Path) -> io::Result<DatabaseFiles> {
 		Ok(DatabaseFiles {
-			top: File::open(path.join("top.bdb"))?,
-			mid: File::open(path.join("mid.bdb"))?,
-			bot: File::open(path.join("bot.bdb"))?,
+			top: File::open(path.join(FILE_NAMES[0]))?,
+			mid: File::open(path.join(FILE_NAMES[1]))?,
+			bot: File::open(path.join(FILE_NAMES[2]))?,
 		})
 	}
 
@@ -137,6 +140,21 @@ impl Database {
 		Ok(())
 	}
 
+	/// Copy the database files into the directory `path`. Returns the number of bytes copied.
+	pub fn copy_to(&mut self, path: &Path) -> io::Result<u64> {
+		match self.db_files {
+			Some(ref mut db_files) => db_files.flush()?,
+			None => return Err(other_io_err("Database is closed")),
+		}
+
+		fs::create_dir_all(path)?;
+		let mut size = 0;
+		for name in &FILE_NAMES {
+			size += fs::copy(self.path.join(name), path.join(name))?;
+		}
+		Ok(size)
+	}
+
 	/// Insert consecutive blooms into database starting at the given positon.
 	pub fn insert_blooms<'a, I, B>(&mut self, from: u64, blooms: I) -> io::Result<()>
 	where ethbloom::BloomRef<'a>: From<B>, I: Iterator<Item = B> {
@@ -366,4 +384,24 @@ mod tests {
 		database.reopen().unwrap();
 		assert!(database.insert_blooms(254, blooms.iter()).is_ok());
 	}
+
+	#[test]
+	fn test_db_copy() {
+		let tempdir = TempDir::new("").unwrap();
+		let blooms = vec![Bloom::from(0x100), Bloom::from(0x01), Bloom::from(0x10), Bloom::from(0x11)];
+		let mut database = Database::open(tempdir.path()).unwrap();
+		database.insert_blooms(254, blooms.iter()).unwrap();
+
+		let copy_dir = TempDir::new("").unwrap();
+		let copy_path = copy_dir.path().join("blooms");
+		// blooms up to 257 in the top, mid and bot files.
+		assert_eq!(database.copy_to(&copy_path).unwrap(), (2 + 17 + 258) * 256);
+
+		let mut copy = Database::open(&copy_path).unwrap();
+		let matches = copy.iterate_matching(0, 257, Some(&Bloom::from(0x01))).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+		assert_eq!(matches, vec![255, 257]);
+
+		database.close().unwrap();
+		assert!(database.copy_to(&copy_path).is_err());
+	}
 }
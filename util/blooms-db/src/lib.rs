@@ -64,6 +64,12 @@ impl Database {
 		self.database.lock().reopen()
 	}
 
+	/// Copies the database files into the directory `path`, holding off writes meanwhile.
+	/// Returns the number of bytes copied.
+	pub fn copy_to<P>(&self, path: P) -> io::Result<u64> where P: AsRef<Path> {
+		self.database.lock().copy_to(path.as_ref())
+	}
+
 	/// Inserts one or more blooms into database.
 	///
 	/// # Arguments
@@ -53,6 +53,28 @@ pub struct TransactionInfo {
 	pub chain_id: Option<u64>,
 }
 
+/// Derivation path of a key
+pub struct DerivationPath(Vec<u32>);
+
+impl fmt::Display for DerivationPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "m")?;
+		for index in &self.0 {
+			write!(f, "/{}", index)?;
+		}
+		Ok(())
+	}
+}
+
+/// Derivation path with a variable index
+pub struct PathTemplate;
+
+pub struct HardwareAccount {
+	pub address: Address,
+	pub path: DerivationPath,
+	pub wallet: WalletInfo,
+}
+
 pub enum KeyPath {
 	/// Ethereum.
 	Ethereum,
@@ -70,6 +92,12 @@ impl HardwareWalletManager {
 
 	pub fn set_key_path(&self, _key_path: KeyPath) {}
 
+	pub fn set_derivation_paths(&self, _templates: Vec<(PathTemplate, usize)>) {}
+
+	pub fn list_accounts(&self) -> Vec<HardwareAccount> {
+		Vec::with_capacity(0)
+	}
+
 	pub fn wallet_info(&self, _: &Address) -> Option<WalletInfo> { 
 		None 
 	}
@@ -31,7 +31,7 @@ use memorydb::*;
 use parking_lot::RwLock;
 use fastmap::H256FastMap;
 use rlp::{Rlp, RlpStream, encode, decode, DecoderError, Decodable, Encodable};
-use super::{DB_PREFIX_LEN, LATEST_ERA_KEY, JournalDB, error_negatively_reference_hash};
+use super::{DB_PREFIX_LEN, LATEST_ERA_KEY, JournalDB, JournalMemStats, EraMemStats, error_negatively_reference_hash};
 use util::DatabaseKey;
 
 /// Implementation of the `JournalDB` trait for a disk-backed database with a memory overlay
@@ -133,6 +133,7 @@ struct JournalEntry {
 	id: H256,
 	insertions: Vec<H256>,
 	deletions: Vec<H256>,
+	inserted_size: usize, // size of the values inserted.
 }
 
 impl HeapSizeOf for JournalEntry {
@@ -199,8 +200,10 @@ impl OverlayRecentDB {
 					let value = decode::<DatabaseValue>(&rlp_data).expect(&format!("read_overlay: Error decoding DatabaseValue era={}, index{}", era, db_key.index));
 					count += value.inserts.len();
 					let mut inserted_keys = Vec::new();
+					let mut inserted_size = 0;
 					for (k, v) in value.inserts {
 						let short_key = to_short_key(&k);
+						inserted_size += v.len();
 
 						if !overlay.contains(&short_key) {
 							cumulative_size += v.len();
@@ -213,6 +216,7 @@ impl OverlayRecentDB {
 						id: value.id,
 						insertions: inserted_keys,
 						deletions: value.deletes,
+						inserted_size,
 					});
 					db_key.index += 1;
 					earliest_era = Some(era);
@@ -263,6 +267,18 @@ impl JournalDB for OverlayRecentDB {
 
 	}
 
+	fn mem_stats(&self) -> JournalMemStats {
+		let journal_overlay = self.journal_overlay.read();
+		let mut eras = journal_overlay.journal.iter().map(|(era, records)| EraMemStats {
+			era: *era,
+			entries: records.iter().map(|r| r.insertions.len()).sum(),
+			bytes: records.iter().map(|r| r.inserted_size).sum(),
+		}).collect::<Vec<_>>();
+		eras.sort_by_key(|stats| stats.era);
+
+		JournalMemStats { eras }
+	}
+
 	fn is_empty(&self) -> bool {
 		self.backing.get(self.column, &LATEST_ERA_KEY).expect("Low level database error").is_none()
 	}
@@ -298,6 +314,7 @@ impl JournalDB for OverlayRecentDB {
 
 		// Increase counter for each inserted key no matter if the block is canonical or not.
 		let insertions: Vec<_> = tx.drain().filter_map(|(k, (v, c))| if c > 0 { Some((k, v)) } else { None }).collect();
+		let inserted_size = insertions.iter().map(|&(_, ref v)| v.len()).sum();
 
 		let encoded_value = {
 			let value_ref = DatabaseValueRef {
@@ -335,7 +352,12 @@ impl JournalDB for OverlayRecentDB {
 			journal_overlay.earliest_era = Some(now);
 		}
 
-		journal_overlay.journal.entry(now).or_insert_with(Vec::new).push(JournalEntry { id: id.clone(), insertions: inserted_keys, deletions: removed_keys });
+		journal_overlay.journal.entry(now).or_insert_with(Vec::new).push(JournalEntry {
+			id: id.clone(),
+			insertions: inserted_keys,
+			deletions: removed_keys,
+			inserted_size,
+		});
 		Ok(ops as u32)
 	}
 
@@ -1065,4 +1087,110 @@ mod tests {
 		let jdb = OverlayRecentDB::new(shared_db, None);
 		assert_eq!(jdb.earliest_era(), None);
 	}
+
+	#[test]
+	fn mem_stats_per_era() {
+		let mut jdb = new_db();
+		assert_eq!(jdb.mem_stats(), JournalMemStats::default());
+
+		jdb.insert(&[1u8; 100]);
+		jdb.commit_batch(1, &keccak(b"1"), None).unwrap();
+		jdb.insert(&[2u8; 50]);
+		jdb.insert(&[3u8; 30]);
+		jdb.commit_batch(2, &keccak(b"2a"), None).unwrap();
+		jdb.insert(&[4u8; 20]);
+		jdb.commit_batch(2, &keccak(b"2b"), None).unwrap();
+
+		let stats = jdb.mem_stats();
+		assert_eq!(stats.eras, vec![
+			EraMemStats { era: 1, entries: 1, bytes: 100 },
+			EraMemStats { era: 2, entries: 3, bytes: 100 },
+		]);
+		assert_eq!(stats.entries(), 4);
+		assert_eq!(stats.bytes(), jdb.journal_size());
+
+		jdb.commit_batch(3, &keccak(b"3"), Some((1, keccak(b"1")))).unwrap();
+		assert_eq!(jdb.mem_stats().eras.iter().map(|e| e.era).collect::<Vec<_>>(), vec![2, 3]);
+	}
+
+	#[test]
+	fn flush_to_budget_stops_within_budget() {
+		let mut jdb = new_db();
+		let canonical = |era: u64| Some(keccak(&[era as u8]));
+		for era in 1..6u64 {
+			jdb.insert(&[era as u8; 100]);
+			jdb.commit_batch(era, &keccak(&[era as u8]), None).unwrap();
+		}
+		assert_eq!(jdb.journal_size(), 500);
+
+		assert_eq!(jdb.flush_to_budget(250, 5, 2, &canonical).unwrap(), 3);
+		assert_eq!(jdb.journal_size(), 200);
+		assert_eq!(jdb.earliest_era(), Some(4));
+		assert!(jdb.can_reconstruct_refs());
+
+		// already within budget.
+		assert_eq!(jdb.flush_to_budget(250, 5, 2, &canonical).unwrap(), 0);
+	}
+
+	#[test]
+	fn flush_to_budget_batches_like_single_eras() {
+		let flushed = |batch_eras| {
+			let mut jdb = new_db();
+			let mut keys = Vec::new();
+			for era in 1..8u64 {
+				keys.push(jdb.insert(&[era as u8; 40]));
+				if era > 1 {
+					jdb.remove(&keys[era as usize - 2]);
+				}
+				jdb.commit_batch(era, &keccak(&[era as u8]), None).unwrap();
+			}
+
+			assert_eq!(jdb.flush_to_budget(0, 6, batch_eras, &|era: u64| Some(keccak(&[era as u8]))).unwrap(), 6);
+			assert!(jdb.can_reconstruct_refs());
+			let mut backing = jdb.backing().iter(None).collect::<Vec<_>>();
+			backing.sort();
+			(backing, jdb.mem_stats())
+		};
+
+		assert_eq!(flushed(1), flushed(4));
+	}
+
+	#[test]
+	fn flush_to_budget_then_reorg_at_boundary() {
+		init_log();
+		let mut jdb = new_db();
+		let canonical = |era: u64| Some(keccak(&[era as u8]));
+
+		let mut canon = Vec::new();
+		for era in 1..5u64 {
+			canon.push(jdb.insert(&[era as u8; 40]));
+			jdb.commit_batch(era, &keccak(&[era as u8]), None).unwrap();
+		}
+
+		// two forks branch off the last flushable era.
+		let a = jdb.insert(b"fork a");
+		jdb.commit_batch(5, &keccak(b"5a"), None).unwrap();
+		let b = jdb.insert(b"fork b");
+		jdb.remove(&canon[3]);
+		jdb.commit_batch(5, &keccak(b"5b"), None).unwrap();
+
+		assert_eq!(jdb.flush_to_budget(0, 4, 3, &canonical).unwrap(), 4);
+		assert_eq!(jdb.earliest_era(), Some(5));
+		assert!(jdb.can_reconstruct_refs());
+		for key in &canon {
+			assert!(jdb.contains(key));
+		}
+		assert!(jdb.contains(&a));
+		assert!(jdb.contains(&b));
+
+		// reorg onto the fork which wasn't first seen.
+		jdb.commit_batch(6, &keccak(b"6b"), Some((5, keccak(b"5b")))).unwrap();
+		assert!(jdb.can_reconstruct_refs());
+		assert!(!jdb.contains(&a));
+		assert!(jdb.contains(&b));
+		assert!(!jdb.contains(&canon[3]));
+		for key in &canon[..3] {
+			assert!(jdb.contains(key));
+		}
+	}
 }
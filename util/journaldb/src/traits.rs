@@ -25,6 +25,36 @@ use hashdb::*;
 use keccak_hasher::KeccakHasher;
 use kvdb::{self, DBTransaction};
 
+/// Estimated memory held by the journal overlay for the records of a single era.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct EraMemStats {
+	/// The era.
+	pub era: u64,
+	/// Number of overlay entries inserted by the records of the era.
+	pub entries: usize,
+	/// Estimated size in bytes of the values of these entries.
+	pub bytes: usize,
+}
+
+/// Estimated memory held by the journal overlay, per era.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct JournalMemStats {
+	/// Eras with records in the overlay, oldest first.
+	pub eras: Vec<EraMemStats>,
+}
+
+impl JournalMemStats {
+	/// Number of overlay entries across all eras.
+	pub fn entries(&self) -> usize {
+		self.eras.iter().map(|era| era.entries).sum()
+	}
+
+	/// Estimated size in bytes of the overlay entries across all eras.
+	pub fn bytes(&self) -> usize {
+		self.eras.iter().map(|era| era.bytes).sum()
+	}
+}
+
 /// A `HashDB` which can manage a short-term journal potentially containing many forks of mutually
 /// exclusive actions.
 pub trait JournalDB: HashDB<KeccakHasher> {
@@ -39,6 +69,10 @@ pub trait JournalDB: HashDB<KeccakHasher> {
 	/// it must be fast enough to call several times per block imported.
 	fn journal_size(&self) -> usize { 0 }
 
+	/// Returns the overlay entries and their estimated size per era, empty unless the journal
+	/// keeps recent history in memory.
+	fn mem_stats(&self) -> JournalMemStats { JournalMemStats::default() }
+
 	/// Check if this database has any commits
 	fn is_empty(&self) -> bool;
 
@@ -77,6 +111,43 @@ pub trait JournalDB: HashDB<KeccakHasher> {
 	/// to the backing strage
 	fn flush(&self) {}
 
+	/// Mark the earliest eras canonical until the journal holds at most `budget` bytes, never going
+	/// past `last_era`. Eras are marked `batch_eras` at a time, each batch written to the backing
+	/// database at once.
+	///
+	/// `canonical` returns the id of the canonical commit of an era, marking stops at the first era
+	/// it doesn't know. Returns the number of eras marked.
+	fn flush_to_budget(&mut self, budget: usize, last_era: u64, batch_eras: usize, canonical: &Fn(u64) -> Option<H256>) -> io::Result<usize> {
+		assert!(batch_eras > 0, "Eras must be flushed at least one at a time");
+
+		let mut marked = 0;
+		while self.journal_size() > budget {
+			let mut batch = self.backing().transaction();
+			let mut batched = 0;
+			while batched < batch_eras && self.journal_size() > budget {
+				let era = match self.earliest_era() {
+					Some(era) if era <= last_era => era,
+					_ => break,
+				};
+				let id = match canonical(era) {
+					Some(id) => id,
+					None => break,
+				};
+				self.mark_canonical(&mut batch, era, &id)?;
+				batched += 1;
+			}
+
+			if batched == 0 {
+				break;
+			}
+			self.backing().write_buffered(batch);
+			self.flush();
+			marked += batched;
+		}
+
+		Ok(marked)
+	}
+
 	/// Consolidate all the insertions and deletions in the given memory overlay.
 	fn consolidate(&mut self, overlay: ::memorydb::MemoryDB<KeccakHasher>);
 
@@ -0,0 +1,73 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! State-heavy import (random trie node writes followed by point lookups of them)
+//! into a column with the shared compaction profile and with state column tuning.
+
+#![feature(test)]
+extern crate test;
+
+extern crate ethereum_types;
+extern crate kvdb_rocksdb;
+extern crate tempdir;
+
+use ethereum_types::H256;
+use kvdb_rocksdb::{ColumnConfig, Database, DatabaseConfig};
+use tempdir::TempDir;
+use self::test::Bencher;
+
+const BATCHES: usize = 16;
+const NODES_PER_BATCH: usize = 4096;
+
+const STATE: Option<u32> = Some(0);
+
+fn import(b: &mut Bencher, config: DatabaseConfig) {
+	let nodes: Vec<Vec<H256>> = (0..BATCHES)
+		.map(|_| (0..NODES_PER_BATCH).map(|_| H256::random()).collect())
+		.collect();
+
+	b.iter(|| {
+		let tempdir = TempDir::new("column_config").unwrap();
+		let db = Database::open(&config, tempdir.path().to_str().unwrap()).unwrap();
+		for batch in &nodes {
+			let mut transaction = db.transaction();
+			for key in batch {
+				transaction.put(STATE, key, &[0x80u8; 96][..]);
+			}
+			db.write(transaction).unwrap();
+			for key in batch {
+				assert!(db.get(STATE, key).unwrap().is_some());
+			}
+		}
+	});
+}
+
+#[bench]
+fn state_import_shared_profile(b: &mut Bencher) {
+	import(b, DatabaseConfig::with_columns(Some(8)));
+}
+
+#[bench]
+fn state_import_state_column_tuning(b: &mut Bencher) {
+	let mut config = DatabaseConfig::with_columns(Some(8));
+	config.column_config.insert(0, ColumnConfig {
+		block_size: Some(4 * 1024),
+		bloom_filter_bits: Some(10),
+		write_buffer_size: Some(config.memory_budget_per_col()),
+		.. ColumnConfig::default()
+	});
+	import(b, config);
+}
@@ -0,0 +1,289 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Progress of a migration through the columns of a database, persisted so it can be resumed.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rustc_hex::{FromHex, ToHex};
+
+use ProgressFn;
+
+/// Progress of a migration through a column, as reported to `Config::progress`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationProgress {
+	/// Version of the database after the migration.
+	pub version: u32,
+	/// Column being migrated.
+	pub column: Option<u32>,
+	/// Number of keys of the column processed so far.
+	pub keys: u64,
+	/// Number of keys in the column, if known.
+	pub estimated_keys: Option<u64>,
+	/// Number of keys which were already processed when the column was resumed.
+	pub resumed_at: u64,
+	/// Time spent on the column since it was started or resumed.
+	pub elapsed: Duration,
+}
+
+impl MigrationProgress {
+	/// Estimated time left to finish the column, at the rate since it was started or resumed.
+	pub fn eta(&self) -> Option<Duration> {
+		let total = self.estimated_keys?;
+		let done = self.keys.saturating_sub(self.resumed_at);
+		if done == 0 {
+			return None;
+		}
+
+		let elapsed_ms = self.elapsed.as_secs() * 1000 + u64::from(self.elapsed.subsec_nanos()) / 1_000_000;
+		let remaining = total.saturating_sub(self.keys);
+		Some(Duration::from_millis(elapsed_ms.saturating_mul(remaining) / done))
+	}
+}
+
+// persisted position of a migration.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Position {
+	// version of the database after the migration.
+	pub version: u32,
+	// database being migrated.
+	pub source: PathBuf,
+	// database being migrated into.
+	pub destination: PathBuf,
+	// index of the column being migrated.
+	pub column: u32,
+	// last source key of the column whose batch was committed.
+	pub key: Option<Vec<u8>>,
+	// keys of the column processed up to `key`.
+	pub keys: u64,
+	// number of keys in the column, if known.
+	pub estimated_keys: Option<u64>,
+}
+
+impl Position {
+	pub fn new(version: u32, source: PathBuf, destination: PathBuf) -> Self {
+		Position {
+			version: version,
+			source: source,
+			destination: destination,
+			column: 0,
+			key: None,
+			keys: 0,
+			estimated_keys: None,
+		}
+	}
+
+	// load the position persisted at `path`, if there is a readable one.
+	pub fn load(path: &Path) -> io::Result<Option<Self>> {
+		let mut s = String::new();
+		match fs::File::open(path) {
+			Ok(mut file) => file.read_to_string(&mut s)?,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e),
+		};
+
+		let position = Self::decode(&s);
+		if position.is_none() {
+			warn!(target: "migration", "Ignoring unreadable migration cursor at {}", path.display());
+		}
+		Ok(position)
+	}
+
+	// persist the position at `path`, replacing any previous one at once.
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		let temp_path = path.with_extension("tmp");
+		{
+			let mut file = fs::File::create(&temp_path)?;
+			file.write_all(self.encode().as_bytes())?;
+			file.sync_all()?;
+		}
+		fs::rename(&temp_path, path)
+	}
+
+	fn encode(&self) -> String {
+		format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+			self.version,
+			self.source.display(),
+			self.destination.display(),
+			self.column,
+			self.keys,
+			self.estimated_keys.map_or_else(String::new, |keys| keys.to_string()),
+			self.key.as_ref().map_or_else(String::new, |key| key.to_hex()),
+		)
+	}
+
+	fn decode(s: &str) -> Option<Self> {
+		let mut lines = s.lines();
+		let position = Position {
+			version: lines.next()?.parse().ok()?,
+			source: lines.next()?.into(),
+			destination: lines.next()?.into(),
+			column: lines.next()?.parse().ok()?,
+			keys: lines.next()?.parse().ok()?,
+			estimated_keys: match lines.next()? {
+				"" => None,
+				keys => Some(keys.parse().ok()?),
+			},
+			key: match lines.next()? {
+				"" => None,
+				key => Some(key.from_hex().ok()?),
+			},
+		};
+
+		Some(position)
+	}
+}
+
+/// Position of a migration in the column it migrates.
+///
+/// Migrations writing through a `Batch` skip the source keys up to `resume_after` and record
+/// every committed batch with `committed`. The cursor is persisted on every commit, so an
+/// interrupted migration resumes from its last committed batch rather than from scratch.
+pub struct Cursor {
+	file: Option<PathBuf>,
+	position: Position,
+	column: Option<u32>,
+	resumed_at: u64,
+	started: Instant,
+	progress: Option<ProgressFn>,
+}
+
+impl Cursor {
+	// cursor at `position`, persisted at `file` if any.
+	pub(crate) fn new(file: Option<PathBuf>, position: Position, progress: Option<ProgressFn>) -> Self {
+		Cursor {
+			file: file,
+			resumed_at: position.keys,
+			position: position,
+			column: None,
+			started: Instant::now(),
+			progress: progress,
+		}
+	}
+
+	/// Cursor at the start of a column, which isn't persisted.
+	pub fn detached(version: u32, column: Option<u32>) -> Self {
+		let mut cursor = Cursor::new(None, Position::new(version, PathBuf::new(), PathBuf::new()), None);
+		cursor.column = column;
+		cursor
+	}
+
+	/// Last source key of the column whose batch was committed, the migration resumes after it.
+	pub fn resume_after(&self) -> Option<&[u8]> {
+		self.position.key.as_ref().map(|key| &key[..])
+	}
+
+	/// Record that the batch holding the results of `keys` more source keys, up to `last_key`, was committed.
+	pub fn committed(&mut self, last_key: &[u8], keys: u64) -> io::Result<()> {
+		self.position.key = Some(last_key.to_vec());
+		self.position.keys += keys;
+		self.save()?;
+		self.report();
+		Ok(())
+	}
+
+	// position of the cursor.
+	pub(crate) fn position(&self) -> &Position {
+		&self.position
+	}
+
+	// whether nothing was committed to the destination yet.
+	pub(crate) fn is_fresh(&self) -> bool {
+		self.position.column == 0 && self.position.key.is_none()
+	}
+
+	// move to the column at `index`, unless resuming it.
+	pub(crate) fn start_column<F>(&mut self, index: u32, column: Option<u32>, estimate: F) -> io::Result<()>
+		where F: FnOnce() -> Option<u64>
+	{
+		self.column = column;
+		if index != self.position.column || self.is_fresh() {
+			self.position.column = index;
+			self.position.key = None;
+			self.position.keys = 0;
+			self.position.estimated_keys = match self.progress {
+				Some(_) => estimate(),
+				None => None,
+			};
+			self.resumed_at = 0;
+			self.started = Instant::now();
+			self.save()?;
+		}
+
+		Ok(())
+	}
+
+	// persist the cursor.
+	pub(crate) fn save(&self) -> io::Result<()> {
+		match self.file {
+			Some(ref file) => self.position.save(file),
+			None => Ok(()),
+		}
+	}
+
+	// report progress through the column.
+	pub(crate) fn report(&self) {
+		if let Some(ref progress) = self.progress {
+			progress(&MigrationProgress {
+				version: self.position.version,
+				column: self.column,
+				keys: self.position.keys,
+				estimated_keys: self.position.estimated_keys,
+				resumed_at: self.resumed_at,
+				elapsed: self.started.elapsed(),
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::{MigrationProgress, Position};
+
+	#[test]
+	fn position_round_trips() {
+		let mut position = Position::new(13, "/tmp/db".into(), "/tmp/temp_migration_1".into());
+		assert_eq!(Position::decode(&position.encode()), Some(position.clone()));
+
+		position.column = 3;
+		position.key = Some(vec![0, 1, 0xff]);
+		position.keys = 1024;
+		position.estimated_keys = Some(4096);
+		assert_eq!(Position::decode(&position.encode()), Some(position.clone()));
+
+		assert_eq!(Position::decode("13\n/tmp/db\n"), None);
+	}
+
+	#[test]
+	fn estimates_time_left() {
+		let mut progress = MigrationProgress {
+			version: 1,
+			column: Some(0),
+			keys: 3000,
+			estimated_keys: Some(5000),
+			resumed_at: 1000,
+			elapsed: Duration::from_secs(10),
+		};
+		assert_eq!(progress.eta(), Some(Duration::from_secs(10)));
+
+		progress.estimated_keys = None;
+		assert_eq!(progress.eta(), None);
+	}
+}
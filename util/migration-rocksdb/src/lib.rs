@@ -23,6 +23,9 @@ extern crate macros;
 
 extern crate kvdb;
 extern crate kvdb_rocksdb;
+extern crate rustc_hex;
+
+mod cursor;
 
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -32,10 +35,20 @@ use std::{fs, io, error};
 use kvdb::DBTransaction;
 use kvdb_rocksdb::{CompactionProfile, Database, DatabaseConfig};
 
+use cursor::Position;
+
+pub use cursor::{Cursor, MigrationProgress};
+
+/// Name of the file holding the cursor of an interrupted migration.
+const CURSOR_FILE_NAME: &'static str = "migration_cursor";
+
 fn other_io_err<E>(e: E) -> io::Error where E: Into<Box<error::Error + Send + Sync>> {
 	io::Error::new(io::ErrorKind::Other, e)
 }
 
+/// Callback receiving the progress of migrations.
+pub type ProgressFn = Arc<Fn(&MigrationProgress) + Send + Sync>;
+
 /// Migration config.
 #[derive(Clone)]
 pub struct Config {
@@ -43,6 +56,11 @@ pub struct Config {
 	pub batch_size: usize,
 	/// Database compaction profile.
 	pub compaction_profile: CompactionProfile,
+	/// Called with the progress of a migration after every committed batch.
+	/// Setting it makes migrations count the keys of every column first, to estimate the time left.
+	pub progress: Option<ProgressFn>,
+	/// Walk the data without writing anything, checking that it can be migrated.
+	pub dry_run: bool,
 }
 
 impl Default for Config {
@@ -50,6 +68,8 @@ impl Default for Config {
 		Config {
 			batch_size: 1024,
 			compaction_profile: Default::default(),
+			progress: None,
+			dry_run: false,
 		}
 	}
 }
@@ -59,6 +79,7 @@ pub struct Batch {
 	inner: BTreeMap<Vec<u8>, Vec<u8>>,
 	batch_size: usize,
 	column: Option<u32>,
+	dry_run: bool,
 }
 
 impl Batch {
@@ -68,6 +89,7 @@ impl Batch {
 			inner: BTreeMap::new(),
 			batch_size: config.batch_size,
 			column: col,
+			dry_run: config.dry_run,
 		}
 	}
 
@@ -80,9 +102,19 @@ impl Batch {
 		Ok(())
 	}
 
+	/// Whether the batch holds no items, e.g. because `insert` just committed it.
+	pub fn is_empty(&self) -> bool {
+		self.inner.is_empty()
+	}
+
 	/// Commit all the items in the batch to the given database.
+	/// Dry runs discard the items instead.
 	pub fn commit(&mut self, dest: &mut Database) -> io::Result<()> {
 		if self.inner.is_empty() { return Ok(()) }
+		if self.dry_run {
+			self.inner.clear();
+			return Ok(())
+		}
 
 		let mut transaction = DBTransaction::new();
 
@@ -108,6 +140,11 @@ pub trait Migration: 'static {
 	fn version(&self) -> u32;
 	/// Migrate a source to a destination.
 	fn migrate(&mut self, source: Arc<Database>, config: &Config, destination: &mut Database, col: Option<u32>) -> io::Result<()>;
+	/// Migrate a column of a source to a destination, resuming from `cursor`, see `Cursor`.
+	/// By default the column is migrated from scratch by `migrate`.
+	fn migrate_from(&mut self, source: Arc<Database>, config: &Config, destination: &mut Database, col: Option<u32>, _cursor: &mut Cursor) -> io::Result<()> {
+		self.migrate(source, config, destination, col)
+	}
 }
 
 /// A simple migration over key-value pairs of a single column.
@@ -131,25 +168,54 @@ impl<T: SimpleMigration> Migration for T {
 	fn alters_existing(&self) -> bool { true }
 
 	fn migrate(&mut self, source: Arc<Database>, config: &Config, dest: &mut Database, col: Option<u32>) -> io::Result<()> {
+		let mut cursor = Cursor::detached(SimpleMigration::version(self), col);
+		self.migrate_from(source, config, dest, col, &mut cursor)
+	}
+
+	fn migrate_from(&mut self, source: Arc<Database>, config: &Config, dest: &mut Database, col: Option<u32>, cursor: &mut Cursor) -> io::Result<()> {
 		let migration_needed = col == SimpleMigration::migrated_column_index(self);
 		let mut batch = Batch::new(config, col);
 
-		let iter = match source.iter(col) {
+		let start = cursor.resume_after().map(|key| key.to_vec());
+		let iter = match start {
+			Some(ref start) => source.iter_from_prefix(col, start),
+			None => source.iter(col),
+		};
+		let iter = match iter {
 			Some(iter) => iter,
 			None => return Ok(()),
 		};
 
+		// source keys whose results are waiting in the batch.
+		let mut pending = 0;
+		let mut last_key = None;
 		for (key, value) in iter {
-			if migration_needed {
-				if let Some((key, value)) = self.simple_migrate(key.into_vec(), value.into_vec()) {
-					batch.insert(key, value, dest)?;
+			if start.as_ref().map_or(false, |start| &key[..] <= &start[..]) {
+				continue;
+			}
+
+			pending += 1;
+			let inserted = if migration_needed {
+				match self.simple_migrate(key.to_vec(), value.into_vec()) {
+					Some((key, value)) => batch.insert(key, value, dest).map(|_| true)?,
+					None => false,
 				}
 			} else {
-				batch.insert(key.into_vec(), value.into_vec(), dest)?;
+				batch.insert(key.to_vec(), value.into_vec(), dest).map(|_| true)?
+			};
+
+			if inserted && batch.is_empty() {
+				cursor.committed(&key, pending)?;
+				pending = 0;
 			}
+			last_key = Some(key);
 		}
 
-		batch.commit(dest)
+		batch.commit(dest)?;
+		match last_key {
+			Some(ref key) if pending > 0 => cursor.committed(key, pending),
+			_ => Ok(()),
+		}
 	}
 }
 
@@ -236,6 +302,10 @@ impl Manager {
 
 	/// Performs migration in order, starting with a source path, migrating between two temporary databases,
 	/// and producing a path where the final migration lives.
+	///
+	/// Migrations altering existing data keep a cursor next to the database, so executing them again after
+	/// an interruption resumes from their last committed batch. Dry runs walk the data through the first
+	/// migration altering it without writing anything, and produce the source path.
 	pub fn execute(&mut self, old_path: &Path, version: u32) -> io::Result<PathBuf> {
 		let config = self.config.clone();
 		let migrations = self.migrations_from(version);
@@ -252,17 +322,51 @@ impl Manager {
 			memory_budget: None,
 			compaction: config.compaction_profile,
 			columns: columns,
+			.. DatabaseConfig::default()
 		};
 
 		let db_root = database_path(old_path);
+		if config.dry_run {
+			return dry_run(migrations, &config, db_config, old_path, &db_root);
+		}
+
+		// resume an interrupted migration if it is still pending.
+		let cursor_path = db_root.join(CURSOR_FILE_NAME);
+		let mut resume = Position::load(&cursor_path)?;
+		let resume_at = match resume {
+			Some(ref position) if position.source.exists() => migrations.iter().position(|m| m.version() == position.version),
+			_ => None,
+		};
+
 		let mut temp_idx = TempIndex::One;
 		let mut temp_path = old_path.to_path_buf();
+		let mut source_path = old_path.to_path_buf();
+		match resume_at {
+			Some(index) => {
+				let position = resume.as_ref().expect("resume_at is only set with a cursor; qed");
+				info!(target: "migration", "Resuming migration to version {}", position.version);
+				for migration in &migrations[..index] {
+					db_config.columns = migration.columns();
+				}
 
-		// start with the old db.
-		let old_path_str = old_path.to_str().ok_or_else(|| other_io_err("Migration impossible."))?;
-		let mut cur_db = Arc::new(Database::open(&db_config, old_path_str)?);
+				source_path = position.source.clone();
+				if position.destination == TempIndex::Two.path(&db_root) {
+					temp_idx = TempIndex::Two;
+				}
+			},
+			None => {
+				resume = None;
+				// leftovers of a migration interrupted before saving its cursor.
+				let _ = fs::remove_dir_all(TempIndex::One.path(&db_root));
+				let _ = fs::remove_dir_all(TempIndex::Two.path(&db_root));
+			},
+		}
+
+		// start with the old db, or the source of the interrupted migration.
+		let source_path_str = source_path.to_str().ok_or_else(|| other_io_err("Migration impossible."))?;
+		let mut cur_db = Arc::new(Database::open(&db_config, source_path_str)?);
 
-		for migration in migrations {
+		for migration in migrations.into_iter().skip(resume_at.unwrap_or(0)) {
 			trace!(target: "migration", "starting migration to version {}", migration.version());
 			// Change number of columns in new db
 			let current_columns = db_config.columns;
@@ -272,26 +376,28 @@ impl Manager {
 			if migration.alters_existing() {
 				temp_path = temp_idx.path(&db_root);
 
+				let position = match resume.take() {
+					Some(position) => position,
+					None => Position::new(migration.version(), source_path.clone(), temp_path.clone()),
+				};
+				let mut cursor = Cursor::new(Some(cursor_path.clone()), position, config.progress.clone());
+
+				// the target may hold the source of the previous migration, which its cursor refers to.
+				cursor.save()?;
+				if cursor.is_fresh() {
+					let _ = fs::remove_dir_all(&temp_path);
+				}
+
 				// open the target temporary database.
 				let temp_path_str = temp_path.to_str().ok_or_else(|| other_io_err("Migration impossible."))?;
 				let mut new_db = Database::open(&db_config, temp_path_str)?;
 
-				match current_columns {
-					// migrate only default column
-					None => migration.migrate(cur_db.clone(), &config, &mut new_db, None)?,
-					Some(v) => {
-						// Migrate all columns in previous DB
-						for col in 0..v {
-							migration.migrate(cur_db.clone(), &config, &mut new_db, Some(col))?
-						}
-					}
-				}
+				migrate_columns(migration, &config, &cur_db, &mut new_db, current_columns, &mut cursor)?;
+
 				// next iteration, we will migrate from this db into the other temp.
 				cur_db = Arc::new(new_db);
+				source_path = temp_path.clone();
 				temp_idx.swap();
-
-				// remove the other temporary migration database.
-				let _ = fs::remove_dir_all(temp_idx.path(&db_root));
 			} else {
 				// migrations which simply add or remove column families.
 				// we can do this in-place.
@@ -303,8 +409,15 @@ impl Manager {
 				while cur_db.num_columns() > goal_columns {
 					cur_db.drop_column().map_err(other_io_err)?;
 				}
+
+				// the cursor of the previous migration can't reopen its changed target.
+				let _ = fs::remove_file(&cursor_path);
 			}
 		}
+
+		// the cursor may refer to the other temporary migration database, so remove it first.
+		let _ = fs::remove_file(&cursor_path);
+		let _ = fs::remove_dir_all(temp_idx.path(&db_root));
 		Ok(temp_path)
 	}
 
@@ -322,6 +435,59 @@ impl Manager {
 	}
 }
 
+// the columns of a database with the given number of columns.
+fn column_indices(columns: Option<u32>) -> Vec<Option<u32>> {
+	match columns {
+		None => vec![None],
+		Some(columns) => (0..columns).map(Some).collect(),
+	}
+}
+
+// migrate the columns of `source` into `dest`, starting with the column of the cursor.
+fn migrate_columns(migration: &mut Box<Migration>, config: &Config, source: &Arc<Database>, dest: &mut Database, columns: Option<u32>, cursor: &mut Cursor) -> io::Result<()> {
+	let start = cursor.position().column as usize;
+	for (index, col) in column_indices(columns).into_iter().enumerate().skip(start) {
+		cursor.start_column(index as u32, col, || source.iter(col).map(|iter| iter.count() as u64))?;
+		migration.migrate_from(source.clone(), config, dest, col, cursor)?;
+	}
+
+	Ok(())
+}
+
+// walk the data through the first migration altering it, without writing anything.
+// later migrations read its output, which a dry run doesn't produce.
+fn dry_run(migrations: Vec<&mut Box<Migration>>, config: &Config, mut db_config: DatabaseConfig, old_path: &Path, db_root: &Path) -> io::Result<PathBuf> {
+	let columns = db_config.columns;
+	let mut altering = migrations.into_iter().filter(|m| m.alters_existing());
+	let migration = match altering.next() {
+		Some(migration) => migration,
+		None => return Ok(old_path.to_path_buf()),
+	};
+
+	let old_path_str = old_path.to_str().ok_or_else(|| other_io_err("Migration impossible."))?;
+	let source = Arc::new(Database::open(&db_config, old_path_str)?);
+
+	// migrations are handed a destination, which stays empty.
+	let scratch_path = db_root.join("temp_migration_dry_run");
+	let scratch_path_str = scratch_path.to_str().ok_or_else(|| other_io_err("Migration impossible."))?;
+	let _ = fs::remove_dir_all(&scratch_path);
+	db_config.columns = migration.columns();
+	let mut scratch = Database::open(&db_config, scratch_path_str)?;
+
+	let position = Position::new(migration.version(), old_path.to_path_buf(), scratch_path.clone());
+	let mut cursor = Cursor::new(None, position, config.progress.clone());
+	let result = migrate_columns(migration, config, &source, &mut scratch, columns, &mut cursor);
+
+	drop(scratch);
+	let _ = fs::remove_dir_all(&scratch_path);
+	result?;
+
+	if let Some(next) = altering.next() {
+		info!(target: "migration", "Dry run stops before the migration to version {}, which needs the migrated data", next.version());
+	}
+	Ok(old_path.to_path_buf())
+}
+
 /// Prints a dot every `max` ticks
 pub struct Progress {
 	current: usize,
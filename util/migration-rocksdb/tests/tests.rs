@@ -25,12 +25,14 @@ extern crate kvdb_rocksdb;
 extern crate migration_rocksdb as migration;
 
 use std::collections::BTreeMap;
-use std::io;
+use std::{fs, io};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tempdir::TempDir;
 use kvdb_rocksdb::Database;
-use migration::{Batch, Config, SimpleMigration, Migration, Manager, ChangeColumns};
+use migration::{Batch, Config, SimpleMigration, Migration, Manager, ChangeColumns, MigrationProgress, ProgressFn};
 
 #[inline]
 fn db_path(path: &Path) -> PathBuf {
@@ -61,6 +63,13 @@ fn verify_migration(path: &Path, pairs: BTreeMap<Vec<u8>, Vec<u8>>) {
 	}
 }
 
+// read all the values of a database.
+fn db_contents(path: &Path) -> BTreeMap<Vec<u8>, Vec<u8>> {
+	let db = Database::open_default(path.to_str().unwrap()).unwrap();
+	let contents = db.iter(None).unwrap().map(|(k, v)| (k.into_vec(), v.into_vec())).collect();
+	contents
+}
+
 struct Migration0;
 
 impl SimpleMigration for Migration0 {
@@ -270,3 +279,117 @@ fn change_columns() {
 	let db = Database::open(&config, new_path.to_str().unwrap()).unwrap();
 	assert_eq!(db.num_columns(), 4);
 }
+
+// appends its version to every key and value, counting them and panicking at the given count.
+struct Interrupting {
+	version: u32,
+	migrated: Arc<AtomicUsize>,
+	panic_at: Option<usize>,
+}
+
+impl SimpleMigration for Interrupting {
+	fn columns(&self) -> Option<u32> {
+		None
+	}
+
+	fn version(&self) -> u32 {
+		self.version
+	}
+
+	fn migrated_column_index(&self) -> Option<u32> {
+		None
+	}
+
+	fn simple_migrate(&mut self, mut key: Vec<u8>, mut value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+		if Some(self.migrated.load(Ordering::SeqCst)) == self.panic_at {
+			panic!("Migration interrupted");
+		}
+
+		self.migrated.fetch_add(1, Ordering::SeqCst);
+		key.push(self.version as u8);
+		value.push(self.version as u8);
+		Some((key, value))
+	}
+}
+
+fn interrupting(version: u32, panic_at: Option<usize>) -> (Interrupting, Arc<AtomicUsize>) {
+	let migrated = Arc::new(AtomicUsize::new(0));
+	(Interrupting { version: version, migrated: migrated.clone(), panic_at: panic_at }, migrated)
+}
+
+fn ten_pairs() -> BTreeMap<Vec<u8>, Vec<u8>> {
+	(0..10u8).map(|i| (vec![i], vec![i * 2])).collect()
+}
+
+#[test]
+fn resumes_interrupted_migration() {
+	let config = Config { batch_size: 4, ..Config::default() };
+
+	let tempdir = TempDir::new("").unwrap();
+	let db_path = db_path(tempdir.path());
+	make_db(&db_path, ten_pairs());
+	let mut manager = Manager::new(config.clone());
+	manager.add_migration(interrupting(1, None).0).unwrap();
+	manager.add_migration(interrupting(2, None).0).unwrap();
+	let uninterrupted = db_contents(&manager.execute(&db_path, 0).unwrap());
+	assert_eq!(uninterrupted.get(&vec![3, 1, 2]), Some(&vec![6, 1, 2]));
+
+	// interrupt the second migration after its first batch of 4 keys was committed.
+	let tempdir = TempDir::new("").unwrap();
+	let db_path = db_path(tempdir.path());
+	make_db(&db_path, ten_pairs());
+	let (first, first_migrated) = interrupting(1, None);
+	let (second, second_migrated) = interrupting(2, Some(6));
+	let mut manager = Manager::new(config.clone());
+	manager.add_migration(first).unwrap();
+	manager.add_migration(second).unwrap();
+	assert!(panic::catch_unwind(AssertUnwindSafe(|| manager.execute(&db_path, 0))).is_err());
+	assert_eq!(first_migrated.load(Ordering::SeqCst), 10);
+	assert_eq!(second_migrated.load(Ordering::SeqCst), 6);
+
+	let (first, first_migrated) = interrupting(1, None);
+	let (second, second_migrated) = interrupting(2, None);
+	let mut manager = Manager::new(config);
+	manager.add_migration(first).unwrap();
+	manager.add_migration(second).unwrap();
+	let end_path = manager.execute(&db_path, 0).unwrap();
+
+	// the first migration is done, the second resumes after its committed batch.
+	assert_eq!(first_migrated.load(Ordering::SeqCst), 0);
+	assert_eq!(second_migrated.load(Ordering::SeqCst), 6);
+	assert_eq!(db_contents(&end_path), uninterrupted);
+	assert!(!tempdir.path().join("migration_cursor").exists());
+}
+
+#[test]
+fn dry_run_walks_without_writing() {
+	let tempdir = TempDir::new("").unwrap();
+	let db_path = db_path(tempdir.path());
+	make_db(&db_path, ten_pairs());
+
+	let reported = Arc::new(Mutex::new(Vec::new()));
+	let progress: ProgressFn = {
+		let reported = reported.clone();
+		Arc::new(move |progress: &MigrationProgress| reported.lock().unwrap().push(progress.clone()))
+	};
+	let config = Config {
+		batch_size: 4,
+		progress: Some(progress),
+		dry_run: true,
+		..Config::default()
+	};
+
+	let (migration, migrated) = interrupting(1, None);
+	let mut manager = Manager::new(config);
+	manager.add_migration(migration).unwrap();
+	assert_eq!(manager.execute(&db_path, 0).unwrap(), db_path);
+
+	assert_eq!(migrated.load(Ordering::SeqCst), 10);
+	assert_eq!(db_contents(&db_path), ten_pairs());
+	assert_eq!(fs::read_dir(tempdir.path()).unwrap().count(), 1, "Only the database is left");
+
+	// reported after both full batches and the rest.
+	let reported = reported.lock().unwrap();
+	assert_eq!(reported.iter().map(|p| p.keys).collect::<Vec<_>>(), vec![4, 8, 10]);
+	assert!(reported.iter().all(|p| p.version == 1 && p.column.is_none() && p.estimated_keys == Some(10)));
+}
@@ -24,7 +24,7 @@ use std::cmp::{min, max};
 use std::path::{Path, PathBuf};
 use std::io::{Read, Write, self};
 use std::fs;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use ethkey::{KeyPair, Secret, Random, Generator};
 use hash::keccak;
 use mio::*;
@@ -163,6 +163,11 @@ impl<'s> NetworkContextTrait for NetworkContext<'s> {
 			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
 	}
 
+	fn ban_peer(&self, peer: PeerId, duration: Duration) {
+		self.io.message(NetworkIoMessage::BanPeer { peer, duration })
+			.unwrap_or_else(|e| warn!("Error sending network IO message: {:?}", e));
+	}
+
 	fn is_expired(&self) -> bool {
 		self.session.as_ref().map_or(false, |s| s.lock().expired())
 	}
@@ -256,6 +261,8 @@ pub struct Host {
 	timers: RwLock<HashMap<TimerToken, ProtocolTimer>>,
 	timer_counter: RwLock<usize>,
 	reserved_nodes: RwLock<HashSet<NodeId>>,
+	/// Banned nodes along with the time their ban expires.
+	banned_nodes: RwLock<HashMap<NodeId, Instant>>,
 	stopping: AtomicBool,
 	filter: Option<Arc<ConnectionFilter>>,
 }
@@ -312,6 +319,7 @@ impl Host {
 			timers: RwLock::new(HashMap::new()),
 			timer_counter: RwLock::new(USER_TIMER),
 			reserved_nodes: RwLock::new(HashSet::new()),
+			banned_nodes: RwLock::new(HashMap::new()),
 			stopping: AtomicBool::new(false),
 			filter,
 		};
@@ -393,6 +401,37 @@ impl Host {
 		Ok(())
 	}
 
+	/// Refuse any connection with the node for given time. Reserved nodes can't be banned.
+	pub fn ban_node(&self, id: NodeId, duration: Duration) {
+		if self.reserved_nodes.read().contains(&id) {
+			return;
+		}
+		self.banned_nodes.write().insert(id, Instant::now() + duration);
+	}
+
+	/// Lift the ban of the node. Accepts either a node id or an enode URL.
+	pub fn unban_node(&self, id: &str) -> Result<(), Error> {
+		let id = match Node::from_str(id) {
+			Ok(ref node) if !node.id.is_zero() => node.id,
+			_ => id.parse().map_err(|_| ErrorKind::InvalidNodeId)?,
+		};
+		self.banned_nodes.write().remove(&id);
+
+		Ok(())
+	}
+
+	/// Returns currently banned nodes.
+	pub fn banned_nodes(&self) -> Vec<NodeId> {
+		let now = Instant::now();
+		let mut banned = self.banned_nodes.write();
+		banned.retain(|_, until| *until > now);
+		banned.keys().cloned().collect()
+	}
+
+	fn is_banned(&self, id: &NodeId) -> bool {
+		self.banned_nodes.read().get(id).map_or(false, |until| *until > Instant::now())
+	}
+
 	pub fn external_url(&self) -> Option<String> {
 		let info = self.info.read();
 		info.public_endpoint.as_ref().map(|e| format!("{}", Node::new(*info.id(), e.clone())))
@@ -574,6 +613,7 @@ impl Host {
 		for id in nodes.filter(|id|
 				!self.have_session(id) &&
 				!self.connecting_to(id) &&
+				!self.is_banned(id) &&
 				*id != self_id &&
 				self.filter.as_ref().map_or(true, |f| f.connection_allowed(&self_id, &id, ConnectionDirection::Outbound))
 			).take(min(max_handshakes_per_round, max_handshakes - handshake_count)) {
@@ -743,6 +783,13 @@ impl Host {
 								break;
 							}
 
+							if self.is_banned(&id) {
+								trace!(target: "network", "Connection refused for banned node {:?}", id);
+								s.disconnect(io, DisconnectReason::UselessPeer);
+								kill = true;
+								break;
+							}
+
 							if !self.filter.as_ref().map_or(true, |f| f.connection_allowed(&self_id, &id, ConnectionDirection::Inbound)) {
 								trace!(target: "network", "Inbound connection not allowed for {:?}", id);
 								s.disconnect(io, DisconnectReason::UnexpectedIdentity);
@@ -1105,6 +1152,22 @@ impl IoHandler<NetworkIoMessage> for Host {
 				trace!(target: "network", "Disabling peer {}", peer);
 				self.kill_connection(*peer, io, false);
 			},
+			NetworkIoMessage::BanPeer { ref peer, ref duration } => {
+				let session = { self.sessions.read().get(*peer).cloned() };
+				if let Some(session) = session {
+					let id = session.lock().id().cloned();
+					if let Some(id) = id {
+						if self.reserved_nodes.read().contains(&id) {
+							trace!(target: "network", "Not banning reserved peer {}", peer);
+							return;
+						}
+						self.ban_node(id, *duration);
+					}
+					session.lock().disconnect(io, DisconnectReason::UselessPeer);
+				}
+				trace!(target: "network", "Banning peer {} for {:?}", peer, duration);
+				self.kill_connection(*peer, io, false);
+			},
 			NetworkIoMessage::InitPublicInterface =>
 				self.init_public_interface(io).unwrap_or_else(|e| warn!("Error initializing public interface: {:?}", e)),
 			_ => {}	// ignore others.
@@ -1243,3 +1306,21 @@ fn host_client_url() {
 	let host: Host = Host::new(config, None).unwrap();
 	assert!(host.local_url().starts_with("enode://101b3ef5a4ea7a1c7928e24c4c75fd053c235d7b80c22ae5c03d145d0ac7396e2a4ffff9adee3133a7b05044a5cee08115fd65145e5165d646bde371010d803c@"));
 }
+
+#[test]
+fn host_bans_nodes_temporarily() {
+	let host: Host = Host::new(NetworkConfiguration::new_local(), None).unwrap();
+	let id = NodeId::from(42);
+
+	host.ban_node(id, Duration::from_secs(3600));
+	assert!(host.is_banned(&id));
+	assert_eq!(host.banned_nodes(), vec![id]);
+
+	host.unban_node(&format!("{:x}", id)).unwrap();
+	assert!(!host.is_banned(&id));
+
+	// the ban is lifted once the cooldown passes
+	host.ban_node(id, Duration::from_secs(0));
+	assert!(!host.is_banned(&id));
+	assert!(host.banned_nodes().is_empty());
+}
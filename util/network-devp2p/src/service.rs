@@ -172,6 +172,23 @@ impl NetworkService {
 		}
 	}
 
+	/// Returns ids of currently banned nodes.
+	pub fn banned_peers(&self) -> Vec<String> {
+		self.host.read().as_ref()
+			.map(|h| h.banned_nodes().into_iter().map(|id| format!("{:x}", id)).collect())
+			.unwrap_or_else(Vec::new)
+	}
+
+	/// Try to lift the ban of a peer.
+	pub fn unban_peer(&self, peer: &str) -> Result<(), Error> {
+		let host = self.host.read();
+		if let Some(ref host) = *host {
+			host.unban_node(peer)
+		} else {
+			Ok(())
+		}
+	}
+
 	/// Set the non-reserved peer mode.
 	pub fn set_non_reserved_mode(&self, mode: NonReservedPeerMode) {
 		let host = self.host.read();
@@ -87,6 +87,13 @@ pub enum NetworkIoMessage {
 	Disconnect(PeerId),
 	/// Disconnect and temporary disable peer.
 	DisablePeer(PeerId),
+	/// Disconnect peer and refuse any connection with its node for given time.
+	BanPeer {
+		/// Peer to ban.
+		peer: PeerId,
+		/// Ban duration.
+		duration: Duration,
+	},
 	/// Network has been started with the host as the given enode.
 	NetworkStarted(String),
 }
@@ -268,6 +275,9 @@ pub trait NetworkContext {
 	/// Disconnect peer. Reconnect can be attempted later.
 	fn disconnect_peer(&self, peer: PeerId);
 
+	/// Disconnect peer and refuse connections with its node for the given time.
+	fn ban_peer(&self, peer: PeerId, duration: Duration);
+
 	/// Check if the session is still active.
 	fn is_expired(&self) -> bool;
 
@@ -311,6 +321,10 @@ impl<'a, T> NetworkContext for &'a T where T: ?Sized + NetworkContext {
 		(**self).disconnect_peer(peer)
 	}
 
+	fn ban_peer(&self, peer: PeerId, duration: Duration) {
+		(**self).ban_peer(peer, duration)
+	}
+
 	fn is_expired(&self) -> bool {
 		(**self).is_expired()
 	}
@@ -24,8 +24,15 @@ extern crate hashdb;
 extern crate keccak_hasher;
 extern crate rlp;
 
+#[cfg(test)]
+extern crate keccak_hash;
+#[cfg(test)]
+extern crate memorydb;
+
+mod proof;
 mod rlp_node_codec;
 
+pub use proof::{verify_proof, ProofError};
 pub use rlp_node_codec::RlpNodeCodec;
 
 use ethereum_types::H256;
@@ -0,0 +1,259 @@
+// Copyright 2015-2018 Parity Technologies (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verification of Merkle proofs of inclusion and exclusion against a trie root.
+
+use std::{error, fmt};
+
+use ethereum_types::H256;
+use hashdb::Hasher;
+use keccak_hasher::KeccakHasher;
+use parity_bytes::Bytes;
+use rlp::DecoderError;
+use trie::{NibbleSlice, NodeCodec, node::Node};
+
+use RlpCodec;
+
+/// Reason a proof fails verification.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofError {
+	/// A proof node doesn't hash to the root, or to the reference of its parent.
+	BadNodeHash {
+		/// Hash the node should have.
+		expected: H256,
+		/// Hash the node has.
+		found: H256,
+	},
+	/// A proof node isn't a valid trie node.
+	Decode(DecoderError),
+	/// The proof takes a branch off the path of the key.
+	PathDivergence,
+	/// The proof lacks the node with the given hash on the path of the key.
+	MissingNode(H256),
+}
+
+impl fmt::Display for ProofError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ProofError::BadNodeHash { ref expected, ref found } =>
+				write!(f, "Bad proof node hash: expected {}, found {}", expected, found),
+			ProofError::Decode(ref err) => write!(f, "Invalid proof node: {}", err),
+			ProofError::PathDivergence => write!(f, "Proof diverges from the path of the key"),
+			ProofError::MissingNode(ref hash) => write!(f, "Proof node {} is missing", hash),
+		}
+	}
+}
+
+impl error::Error for ProofError {
+	fn description(&self) -> &str {
+		"Trie proof verification error"
+	}
+}
+
+impl From<DecoderError> for ProofError {
+	fn from(err: DecoderError) -> Self {
+		ProofError::Decode(err)
+	}
+}
+
+/// Verify that `proof` proves the value of `key` in the trie with the given `root`.
+///
+/// `proof` holds the encoded nodes on the path of the key from the root down, as recorded by a
+/// `trie::Recorder` while looking the key up. Returns the value of the key for an inclusion
+/// proof and `None` for an exclusion proof.
+pub fn verify_proof(root: H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>, ProofError> {
+	if root == RlpCodec::HASHED_NULL_NODE && proof.is_empty() {
+		return Ok(None);
+	}
+
+	let mut nodes = proof.iter();
+	let mut key = NibbleSlice::new(key);
+	let mut expected = root;
+	// hashes of the children of the last branch the key doesn't lead to.
+	let mut siblings = Vec::new();
+	let value = 'path: loop {
+		let node = nodes.next().ok_or(ProofError::MissingNode(expected))?;
+		let found = KeccakHasher::hash(node);
+		if found != expected {
+			return Err(if siblings.contains(&found) {
+				ProofError::PathDivergence
+			} else {
+				ProofError::BadNodeHash { expected, found }
+			});
+		}
+
+		// follow the key through the node and any nodes inlined into it.
+		let mut data = &node[..];
+		loop {
+			let child = match RlpCodec::decode(data)? {
+				Node::Empty => break 'path None,
+				Node::Leaf(partial, value) => break 'path if partial == key { Some(value.to_vec()) } else { None },
+				Node::Extension(partial, child) => {
+					if !key.starts_with(&partial) {
+						break 'path None;
+					}
+					key = key.mid(partial.len());
+					siblings.clear();
+					child
+				},
+				Node::Branch(children, value) => {
+					if key.is_empty() {
+						break 'path value.map(|value| value.to_vec());
+					}
+					let index = key.at(0) as usize;
+					siblings = children.iter().enumerate()
+						.filter(|&(i, _)| i != index)
+						.filter_map(|(_, child)| RlpCodec::try_decode_hash(child))
+						.collect();
+					key = key.mid(1);
+					children[index]
+				},
+			};
+
+			if RlpCodec::is_empty_node(child) {
+				break 'path None;
+			}
+
+			match RlpCodec::try_decode_hash(child) {
+				Some(hash) => {
+					expected = hash;
+					break;
+				},
+				None => data = child,
+			}
+		}
+	};
+
+	// nodes beyond the end of the path prove nothing about the key.
+	match nodes.next() {
+		Some(_) => Err(ProofError::PathDivergence),
+		None => Ok(value),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ethereum_types::H256;
+	use hashdb::HashDB;
+	use keccak_hash::keccak;
+	use memorydb::MemoryDB;
+	use rlp::{self, Rlp};
+	use trie::{Recorder, Trie, TrieMut};
+	use {TrieDB, TrieDBMut};
+	use super::{verify_proof, ProofError};
+
+	fn build_trie(count: u64) -> (MemoryDB<::keccak_hasher::KeccakHasher>, H256) {
+		let mut db = MemoryDB::new();
+		let mut root = H256::new();
+		{
+			let mut trie = TrieDBMut::new(&mut db, &mut root);
+			for i in 0..count {
+				let key = keccak(&rlp::encode(&i)[..]);
+				trie.insert(&key, &rlp::encode(&(i * 1000))).unwrap();
+			}
+		}
+		(db, root)
+	}
+
+	fn prove(db: &MemoryDB<::keccak_hasher::KeccakHasher>, root: &H256, key: &[u8]) -> Vec<Vec<u8>> {
+		let trie = TrieDB::new(db, root).unwrap();
+		let mut recorder = Recorder::new();
+		trie.get_with(key, &mut recorder).unwrap();
+		recorder.drain().into_iter().map(|r| r.data).collect()
+	}
+
+	#[test]
+	fn verifies_inclusion() {
+		let (db, root) = build_trie(100);
+		for i in 0..100u64 {
+			let key = keccak(&rlp::encode(&i)[..]);
+			let proof = prove(&db, &root, &key);
+			assert_eq!(verify_proof(root, &key, &proof), Ok(Some(rlp::encode(&(i * 1000)).into_vec())));
+		}
+	}
+
+	#[test]
+	fn verifies_exclusion() {
+		let (db, root) = build_trie(100);
+		for i in 100..120u64 {
+			let key = keccak(&rlp::encode(&i)[..]);
+			let proof = prove(&db, &root, &key);
+			assert_eq!(verify_proof(root, &key, &proof), Ok(None));
+		}
+
+		let (_, empty_root) = build_trie(0);
+		assert_eq!(verify_proof(empty_root, &keccak("absent"), &[]), Ok(None));
+	}
+
+	#[test]
+	fn rejects_bad_node_hash() {
+		let (db, root) = build_trie(100);
+		let key = keccak(&rlp::encode(&1u64)[..]);
+		let mut proof = prove(&db, &root, &key);
+		let last = proof.len() - 1;
+		let expected = keccak(&proof[last]);
+		proof[last][4] ^= 1;
+
+		assert_eq!(verify_proof(root, &key, &proof), Err(ProofError::BadNodeHash {
+			expected,
+			found: keccak(&proof[last]),
+		}));
+	}
+
+	#[test]
+	fn rejects_incomplete_proof() {
+		let (db, root) = build_trie(100);
+		let key = keccak(&rlp::encode(&1u64)[..]);
+		let proof = prove(&db, &root, &key);
+		let last = proof.len() - 1;
+
+		assert_eq!(verify_proof(root, &key, &proof[..last]), Err(ProofError::MissingNode(keccak(&proof[last]))));
+		assert_eq!(verify_proof(root, &key, &[]), Err(ProofError::MissingNode(root)));
+	}
+
+	#[test]
+	fn rejects_undecodable_node() {
+		let garbage = vec![0xc3, 0x80, 0x80, 0x80];
+		let root = keccak(&garbage);
+		match verify_proof(root, &keccak("key"), &[garbage]) {
+			Err(ProofError::Decode(_)) => {},
+			other => panic!("Expected a decoding error, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn rejects_wrong_branch() {
+		let (db, root) = build_trie(100);
+		let key = keccak(&rlp::encode(&1u64)[..]);
+		let proof = prove(&db, &root, &key);
+
+		// replace the child on the path of the key by a genuine sibling, keeping every hash valid.
+		let root_node = Rlp::new(&proof[0]);
+		assert_eq!(root_node.item_count().unwrap(), 17);
+		let index = (key[0] >> 4) as usize;
+		let sibling_index = (0..16).find(|&i| i != index && !root_node.at(i).unwrap().is_empty()).unwrap();
+		let sibling_hash: H256 = root_node.val_at(sibling_index).unwrap();
+		let sibling = db.get(&sibling_hash).unwrap().into_vec();
+
+		let malicious = vec![proof[0].clone(), sibling];
+		assert_eq!(verify_proof(root, &key, &malicious), Err(ProofError::PathDivergence));
+
+		// nodes off the path are rejected even after the path ends.
+		let mut padded = proof.clone();
+		padded.push(malicious[1].clone());
+		assert_eq!(verify_proof(root, &key, &padded), Err(ProofError::PathDivergence));
+	}
+}
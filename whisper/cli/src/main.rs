@@ -42,6 +42,7 @@ extern crate serde_derive;
 
 use docopt::Docopt;
 use std::{fmt, io, process, env, sync::Arc};
+use std::collections::HashMap;
 use jsonrpc_core::{Metadata, MetaIoHandler};
 use jsonrpc_pubsub::{PubSubMetadata, Session};
 use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation};
@@ -104,6 +105,14 @@ impl whisper::rpc::PoolHandle for WhisperPoolHandle {
 	fn pool_status(&self) -> whisper::net::PoolStatus {
 		self.handle.pool_status()
 	}
+
+	fn set_topic_pow(&self, prefix: Vec<u8>, pow: f64) {
+		self.handle.set_topic_pow(prefix, pow)
+	}
+
+	fn peer_stats(&self) -> HashMap<net::PeerId, whisper::net::PeerStats> {
+		self.handle.peer_stats()
+	}
 }
 
 impl WhisperPoolHandle {
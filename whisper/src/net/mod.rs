@@ -16,19 +16,21 @@
 
 //! Whisper messaging system as a DevP2P subprotocol.
 
-use std::collections::{HashMap, HashSet};
-use std::cmp::Ordering;
-use std::fmt;
-use std::time::{Duration, SystemTime};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::{self, Ordering};
+use std::{fmt, fs, io};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::sync::Arc;
 
+use byteorder::{ByteOrder, BigEndian};
 use ethereum_types::{H256, H512};
 use network::{self, NetworkContext, NodeId, PeerId, ProtocolId, TimerToken};
 use ordered_float::OrderedFloat;
 use parking_lot::{Mutex, RwLock};
 use rlp::{DecoderError, RlpStream, Rlp};
 
-use message::{Message, Error as MessageError};
+use message::{Message, Topic, Error as MessageError};
 
 #[cfg(test)]
 mod tests;
@@ -49,6 +51,15 @@ pub const SUPPORTED_VERSIONS: &'static [(u8, u8)] = &[
 	(PROTOCOL_VERSION as u8, PACKET_COUNT)
 ];
 
+// how often the message pool is saved, if it's persisted.
+const SAVE_POOL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Version of the format of saved message pools.
+pub const POOL_FILE_VERSION: u8 = 1;
+
+// how long peers flooding us are banned for.
+const FLOODING_BAN_DURATION: Duration = Duration::from_secs(30 * 60);
+
 // maximum tolerated delay between messages packets.
 const MAX_TOLERATED_DELAY: Duration = Duration::from_millis(5000);
 
@@ -89,6 +100,8 @@ enum Error {
 	UnknownPeer(PeerId),
 	UnexpectedMessage,
 	InvalidPowReq,
+	InsufficientPow(f64),
+	Flooding,
 }
 
 impl From<DecoderError> for Error {
@@ -118,6 +131,8 @@ impl fmt::Display for Error {
 			Error::UnknownPeer(ref id) => write!(f, "Message received from unknown peer: {}", id),
 			Error::UnexpectedMessage => write!(f, "Unexpected message."),
 			Error::InvalidPowReq => write!(f, "Peer sent invalid PoW requirement."),
+			Error::InsufficientPow(pow) => write!(f, "Peer sent message with PoW {} below advertised requirement.", pow),
+			Error::Flooding => write!(f, "Peer kept flooding us with low PoW messages."),
 		}
 	}
 }
@@ -128,11 +143,12 @@ struct SortedEntry {
 	slab_id: usize,
 	work_proved: OrderedFloat<f64>,
 	expiry: SystemTime,
+	origin: Option<PeerId>,
 }
 
 impl Ord for SortedEntry {
 	fn cmp(&self, other: &SortedEntry) -> Ordering {
-		self.work_proved.cmp(&other.work_proved)
+		other.work_proved.cmp(&self.work_proved)
 	}
 }
 
@@ -172,55 +188,59 @@ impl Messages {
 		self.known.reserve(additional);
 	}
 
-	// whether a message is not known and within the bounds of PoW.
-	fn may_accept(&self, message: &Message) -> bool {
-		!self.known.contains(message.hash()) && {
+	// whether a message is not known and within the bounds of PoW, or may push
+	// out messages from `throttled` peers.
+	fn may_accept(&self, message: &Message, throttled: &HashSet<PeerId>) -> bool {
+		!self.known.contains(message.hash()) && (!self.is_full() || {
 			self.sorted.last().map_or(true, |entry| {
 				let work_proved = OrderedFloat(message.work_proved());
 				OrderedFloat(self.slab[entry.slab_id].work_proved()) < work_proved
 			})
-		}
+		} || {
+			self.sorted.iter().any(|entry| entry.origin.map_or(false, |origin| throttled.contains(&origin)))
+		})
 	}
 
-	// insert a message into the store. for best performance,
-	// call `reserve` before inserting a bunch.
+	// insert a message received from `origin`, if not posted locally, into
+	// the store. for best performance, call `reserve` before inserting a bunch.
 	//
-	fn insert(&mut self, message: Message) -> bool {
+	// messages from `throttled` peers are pushed out first.
+	fn insert(&mut self, message: Message, origin: Option<PeerId>, throttled: &HashSet<PeerId>) -> bool {
 		if !self.known.insert(message.hash().clone()) { return false }
 
 		let work_proved = OrderedFloat(message.work_proved());
 
-		// pop off entries by low PoW until we have enough space for the higher
-		// PoW message being inserted.
+		// pop off entries from throttled peers, then by low PoW, until we have
+		// enough space for the higher PoW message being inserted.
 		let size_upon_insertion = self.cumulative_size + message.encoded_size();
 		if size_upon_insertion >= self.ideal_size {
 			let diff = size_upon_insertion - self.ideal_size;
 			let mut found_diff = 0;
-			for entry in self.sorted.iter().rev() {
+			let mut evicted = HashSet::new();
+			let is_throttled = |entry: &SortedEntry| entry.origin.map_or(false, |origin| throttled.contains(&origin));
+			for entry in self.sorted.iter().rev().filter(|entry| is_throttled(*entry))
+				.chain(self.sorted.iter().rev().filter(|entry| !is_throttled(*entry))) {
 				if found_diff >= diff { break }
 
 				// if we encounter a message with at least the PoW we're looking
 				// at, don't push that message out.
-				if entry.work_proved >= work_proved { return false }
+				if !is_throttled(entry) && entry.work_proved >= work_proved { return false }
 				found_diff += self.slab[entry.slab_id].encoded_size();
+				evicted.insert(entry.slab_id);
 			}
 
 			// message larger than ideal size.
 			if found_diff < diff { return false }
 
-			while found_diff > 0 {
-				let entry = self.sorted.pop()
-					.expect("found_diff built by traversing entries; therefore that many entries exist; qed");
-
-				let message = self.slab.remove(entry.slab_id)
+			for slab_id in &evicted {
+				let message = self.slab.remove(*slab_id)
 					.expect("sorted entry slab IDs always filled; qed");
 
-				found_diff -= message.encoded_size();
-
 				self.cumulative_size -= message.encoded_size();
 				self.known.remove(message.hash());
 				self.removed_hashes.push(message.hash().clone());
 			}
+			self.sorted.retain(|entry| !evicted.contains(&entry.slab_id));
 		}
 
 		let expiry = message.expiry();
@@ -234,6 +254,7 @@ impl Messages {
 			slab_id: id,
 			work_proved: work_proved,
 			expiry: expiry,
+			origin: origin,
 		};
 
 		match self.sorted.binary_search(&sorted_entry) {
@@ -306,8 +327,84 @@ impl Messages {
 			message_count: self.sorted.len(),
 			cumulative_size: self.cumulative_size,
 			target_size: self.ideal_size,
+			min_pow: 0f64,
+			topic_pow: Vec::new(),
+		}
+	}
+}
+
+// minimum PoW for messages to be accepted and relayed: a global one, which is
+// advertised to peers, and higher ones for topics starting with given prefixes.
+struct PowRequirements {
+	global: f64,
+	topics: HashMap<Vec<u8>, f64>,
+}
+
+impl PowRequirements {
+	fn new() -> Self {
+		PowRequirements {
+			global: 0f64,
+			topics: HashMap::new(),
 		}
 	}
+
+	// PoW required for a message on given topics. the longest matching prefix
+	// of each topic applies, and the global minimum for topics without any.
+	fn required(&self, topics: &[Topic]) -> f64 {
+		topics.iter()
+			.filter_map(|topic| (1..topic.0.len() + 1).rev()
+				.filter_map(|len| self.topics.get(&topic.0[..len]))
+				.next())
+			.fold(self.global, |required, pow| required.max(*pow))
+	}
+}
+
+/// Limit on the rate of messages accepted from each peer.
+///
+/// A peer sending more messages per second than `max_message_rate` while proving
+/// less work per second than `min_pow_rate`, both over the last `window`, is
+/// throttled: its messages are dropped until its rates are within the limit again.
+/// It's banned after `max_violations` packets with dropped messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+	/// Period over which rates are measured.
+	pub window: Duration,
+	/// Messages per second accepted regardless of their PoW.
+	pub max_message_rate: f64,
+	/// PoW per second which allows a peer to exceed `max_message_rate`.
+	pub min_pow_rate: f64,
+	/// Packets with dropped messages tolerated before banning a peer.
+	pub max_violations: usize,
+}
+
+impl Default for RateLimit {
+	fn default() -> Self {
+		RateLimit {
+			window: Duration::from_secs(10),
+			max_message_rate: 50.0,
+			min_pow_rate: 50.0,
+			max_violations: 100,
+		}
+	}
+}
+
+/// Whisper statistics of a peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerStats {
+	/// Messages accepted from the peer.
+	pub accepted: u64,
+	/// Messages dropped as the peer was throttled.
+	pub rejected: u64,
+	/// Cumulative PoW of the accepted messages.
+	pub work_proved: f64,
+	/// Accepted messages per second over the rate limit window.
+	pub message_rate: f64,
+	/// PoW per second of the accepted messages over the rate limit window.
+	pub pow_rate: f64,
+	/// Whether messages from the peer are being dropped.
+	pub throttled: bool,
+	/// Number of packets with dropped messages.
+	pub violations: usize,
 }
 
 enum State {
@@ -322,8 +419,16 @@ struct Peer {
 	known_messages: HashSet<H256>,
 	topic_filter: Option<H512>,
 	pow_requirement: f64,
+	advertised_pow: f64,
 	is_parity: bool,
 	_protocol_version: usize,
+	// messages accepted within the rate limit window, with their PoW.
+	window: VecDeque<(Instant, f64)>,
+	throttled: bool,
+	violations: usize,
+	accepted: u64,
+	rejected: u64,
+	work_proved: f64,
 }
 
 impl Peer {
@@ -360,6 +465,47 @@ impl Peer {
 		self.pow_requirement = pow_requirement;
 	}
 
+	// account for a message of given PoW received from this peer. returns
+	// false if the peer is throttled, in which case the message is dropped.
+	fn note_rate(&mut self, limit: &RateLimit, now: Instant, work_proved: f64) -> bool {
+		while self.window.front().map_or(false, |&(time, _)| time + limit.window <= now) {
+			self.window.pop_front();
+		}
+
+		let (message_rate, pow_rate) = self.rates(limit, 1, work_proved);
+		self.throttled = message_rate > limit.max_message_rate && pow_rate < limit.min_pow_rate;
+		if self.throttled {
+			self.rejected += 1;
+			return false;
+		}
+
+		self.window.push_back((now, work_proved));
+		self.accepted += 1;
+		self.work_proved += work_proved;
+		true
+	}
+
+	// messages and PoW per second over the window, with `messages` more of
+	// cumulative PoW `work_proved`.
+	fn rates(&self, limit: &RateLimit, messages: usize, work_proved: f64) -> (f64, f64) {
+		let secs = limit.window.as_secs() as f64 + f64::from(limit.window.subsec_nanos()) / 1_000_000_000.0;
+		let work_proved = self.window.iter().fold(work_proved, |sum, &(_, work)| sum + work);
+		((self.window.len() + messages) as f64 / secs, work_proved / secs)
+	}
+
+	fn stats(&self, limit: &RateLimit) -> PeerStats {
+		let (message_rate, pow_rate) = self.rates(limit, 0, 0f64);
+		PeerStats {
+			accepted: self.accepted,
+			rejected: self.rejected,
+			work_proved: self.work_proved,
+			message_rate: message_rate,
+			pow_rate: pow_rate,
+			throttled: self.throttled,
+			violations: self.violations,
+		}
+	}
+
 	fn can_send_messages(&self) -> bool {
 		match self.state {
 			State::Unconfirmed(_) => false,
@@ -378,6 +524,10 @@ pub struct PoolStatus {
 	pub cumulative_size: usize,
 	/// Target size of the pool.
 	pub target_size: usize,
+	/// Minimum PoW of messages, advertised to peers.
+	pub min_pow: f64,
+	/// Minimum PoW of messages on topics starting with given prefixes.
+	pub topic_pow: Vec<(Vec<u8>, f64)>,
 }
 
 /// Generic network context.
@@ -386,6 +536,8 @@ pub trait Context {
 	fn disconnect_peer(&self, PeerId);
 	/// Disable a peer.
 	fn disable_peer(&self, PeerId);
+	/// Disconnect a peer as useless and refuse connections from it for given time.
+	fn ban_peer(&self, PeerId, Duration);
 	/// Get a peer's node key.
 	fn node_key(&self, PeerId) -> Option<NodeId>;
 	/// Get a peer's protocol version for given protocol.
@@ -401,6 +553,9 @@ impl<T> Context for T where T: ?Sized + NetworkContext {
 	fn disable_peer(&self, peer: PeerId) {
 		NetworkContext::disable_peer(self, peer)
 	}
+	fn ban_peer(&self, peer: PeerId, duration: Duration) {
+		NetworkContext::ban_peer(self, peer, duration)
+	}
 	fn node_key(&self, peer: PeerId) -> Option<NodeId> {
 		self.session_info(peer).and_then(|info| info.id)
 	}
@@ -423,6 +578,10 @@ pub struct Network<T> {
 	messages: Arc<RwLock<Messages>>,
 	handler: T,
 	peers: RwLock<HashMap<PeerId, Mutex<Peer>>>,
+	pow_requirements: RwLock<PowRequirements>,
+	pool_path: Option<PathBuf>,
+	last_saved: Mutex<Instant>,
+	rate_limit: RateLimit,
 }
 
 // public API.
@@ -433,21 +592,159 @@ impl<T> Network<T> {
 			messages: Arc::new(RwLock::new(Messages::new(messages_size_bytes))),
 			handler: handler,
 			peers: RwLock::new(HashMap::new()),
+			pow_requirements: RwLock::new(PowRequirements::new()),
+			pool_path: None,
+			last_saved: Mutex::new(Instant::now()),
+			rate_limit: RateLimit::default(),
 		}
 	}
 
+	/// Limit the rate of messages accepted from each peer.
+	pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+		self.rate_limit = limit;
+		self
+	}
+
+	/// Get whisper statistics of the connected peers.
+	pub fn peer_stats(&self) -> HashMap<PeerId, PeerStats> {
+		self.peers.read().iter()
+			.map(|(id, peer)| (*id, peer.lock().stats(&self.rate_limit)))
+			.collect()
+	}
+
+	// IDs of the peers whose messages are being dropped.
+	fn throttled_peers(&self) -> HashSet<PeerId> {
+		self.peers.read().iter()
+			.filter(|&(_, peer)| peer.lock().throttled)
+			.map(|(id, _)| *id)
+			.collect()
+	}
+
+	/// Persist the message pool at `path`. Messages saved there are loaded now,
+	/// dropping expired ones, and the pool is saved periodically and when the
+	/// handler is dropped.
+	pub fn with_persistence(mut self, path: PathBuf) -> Self {
+		match self.load_pool(&path, SystemTime::now()) {
+			Ok(restored) => debug!(target: "whisper", "Restored {} messages from {}", restored, path.display()),
+			Err(e) => warn!(target: "whisper", "Failed to restore messages from {}: {}", path.display(), e),
+		}
+
+		self.pool_path = Some(path);
+		self
+	}
+
+	/// Save the message pool, if it's persisted.
+	pub fn save_pool(&self) -> io::Result<()> {
+		*self.last_saved.lock() = Instant::now();
+		match self.pool_path {
+			Some(ref path) => self.write_pool(path, SystemTime::now()),
+			None => Ok(()),
+		}
+	}
+
+	// write the messages of the pool to `path`: the format version byte, followed
+	// by the RLP list of the save time in seconds and of the envelopes.
+	fn write_pool(&self, path: &Path, now: SystemTime) -> io::Result<()> {
+		let saved_at = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+		let messages = self.messages.read();
+
+		let mut stream = RlpStream::new_list(2);
+		stream.append(&saved_at);
+		stream.begin_unbounded_list();
+		for message in messages.iter() {
+			stream.append(message.envelope());
+		}
+		stream.complete_unbounded_list();
+
+		let temp_path = path.with_extension("tmp");
+		let mut data = vec![POOL_FILE_VERSION];
+		data.extend(stream.out());
+		fs::write(&temp_path, &data)?;
+		fs::rename(&temp_path, path)
+	}
+
+	// load the messages saved at `path` into the pool, returning how many were restored.
+	fn load_pool(&self, path: &Path, now: SystemTime) -> io::Result<usize> {
+		let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+		let data = match fs::read(path) {
+			Ok(data) => data,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+			Err(e) => return Err(e),
+		};
+
+		let rlp = match data.split_first() {
+			Some((&POOL_FILE_VERSION, rest)) => Rlp::new(rest),
+			Some((version, _)) => return Err(invalid(format!("unsupported version {}", version))),
+			None => return Err(invalid("empty file".into())),
+		};
+
+		// if the clock moved back since the pool was saved, the messages which had
+		// expired by then stay expired.
+		let saved_at: u64 = rlp.val_at(0).map_err(|e| invalid(format!("{}", e)))?;
+		let now = cmp::max(now, UNIX_EPOCH + Duration::from_secs(saved_at));
+
+		let mut messages = self.messages.write();
+		let mut restored = 0;
+		for envelope in rlp.at(1).map_err(|e| invalid(format!("{}", e)))?.iter() {
+			let message = match Message::decode(envelope, now) {
+				Ok(message) => message,
+				Err(e) => {
+					debug!(target: "whisper", "Dropping saved message: {}", e);
+					continue;
+				}
+			};
+
+			if message.expiry() > now && messages.insert(message, None, &HashSet::new()) {
+				restored += 1;
+			}
+		}
+
+		Ok(restored)
+	}
+
 	/// Post a message to the whisper network to be relayed.
+	/// Returns false if PoW is below the requirement for its topics.
 	pub fn post_message<C: ?Sized + Context>(&self, message: Message, context: &C) -> bool
 		where T: MessageHandler
 	{
-		let ok = self.messages.write().insert(message);
+		if message.work_proved() < self.pow_requirements.read().required(message.topics()) {
+			return false;
+		}
+
+		let throttled = self.throttled_peers();
+		let ok = self.messages.write().insert(message, None, &throttled);
 		if ok { self.rally(context) }
 		ok
 	}
 
 	/// Get number of messages and amount of memory used by them.
 	pub fn pool_status(&self) -> PoolStatus {
-		self.messages.read().status()
+		let mut status = self.messages.read().status();
+		let requirements = self.pow_requirements.read();
+		status.min_pow = requirements.global;
+		status.topic_pow = requirements.topics.iter()
+			.map(|(prefix, pow)| (prefix.clone(), *pow))
+			.collect();
+		status.topic_pow.sort_by(|a, b| a.0.cmp(&b.0));
+		status
+	}
+
+	/// Set the minimum PoW of messages to accept and relay. It is advertised
+	/// to peers on the next rally.
+	pub fn set_pow_requirement(&self, pow: f64) {
+		self.pow_requirements.write().global = pow;
+	}
+
+	/// Set the minimum PoW of messages on topics starting with given prefix.
+	/// It applies on top of the global requirement and isn't advertised to peers.
+	/// Zero PoW removes the requirement for the prefix.
+	pub fn set_topic_pow(&self, prefix: Vec<u8>, pow: f64) {
+		let mut requirements = self.pow_requirements.write();
+		if pow > 0f64 {
+			requirements.topics.insert(prefix, pow);
+		} else {
+			requirements.topics.remove(&prefix);
+		}
 	}
 }
 
@@ -462,6 +759,7 @@ impl<T: MessageHandler> Network<T> {
 
 		let messages = self.messages.read();
 		let peers = self.peers.read();
+		let requirements = self.pow_requirements.read();
 
 		// send each peer a packet with new messages it may find relevant.
 		for (peer_id, peer) in peers.iter() {
@@ -484,12 +782,24 @@ impl<T: MessageHandler> Network<T> {
 				State::Confirmed => {}
 			}
 
-			// construct packet, skipping messages the peer won't accept.
+			// advertise changed PoW requirement. peers treat zero as invalid.
+			if peer_data.advertised_pow != requirements.global {
+				peer_data.advertised_pow = requirements.global;
+				if requirements.global.is_normal() {
+					let mut bytes = [0u8; 8];
+					BigEndian::write_f64(&mut bytes, requirements.global);
+					io.send(*peer_id, packet::POW_REQUIREMENT, ::rlp::encode(&bytes.to_vec()).into_vec());
+				}
+			}
+
+			// construct packet, skipping messages the peer won't accept
+			// and the ones we don't relay anymore.
 			let mut stream = RlpStream::new();
 			stream.begin_unbounded_list();
 
 			for message in messages.iter() {
 				if !peer_data.will_accept(message) { continue }
+				if message.work_proved() < requirements.required(message.topics()) { continue }
 
 				if stream.estimate_size(message.encoded_size()) > MAX_MESSAGES_PACKET_SIZE {
 					break;
@@ -526,6 +836,7 @@ impl<T: MessageHandler> Network<T> {
 	fn on_messages(&self, peer: &PeerId, message_packet: Rlp)
 		-> Result<(), Error>
 	{
+		let peer_id = *peer;
 		let mut messages_vec = {
 			let peers = self.peers.read();
 			let peer = match peers.get(peer) {
@@ -550,19 +861,61 @@ impl<T: MessageHandler> Network<T> {
 
 			// disallow duplicates in packet.
 			messages_vec.retain(|message| peer.note_known(&message));
+
+			// only the requirement we advertised is binding for the peer.
+			if let Some(message) = messages_vec.iter().find(|message| message.work_proved() < peer.advertised_pow) {
+				return Err(Error::InsufficientPow(message.work_proved()));
+			}
+
 			messages_vec
 		};
 
+		// only new messages count towards the rate of the peer.
+		{
+			let messages = self.messages.read();
+			messages_vec.retain(|message| !messages.known.contains(message.hash()));
+		}
+		self.limit_rate(peer_id, &mut messages_vec)?;
+
+		// drop messages below the requirements for their topics.
+		{
+			let requirements = self.pow_requirements.read();
+			messages_vec.retain(|message| message.work_proved() >= requirements.required(message.topics()));
+		}
+
 		// import for relaying.
+		let throttled = self.throttled_peers();
 		let mut messages = self.messages.write();
 
-		messages_vec.retain(|message| messages.may_accept(&message));
+		messages_vec.retain(|message| messages.may_accept(&message, &throttled));
 		messages.reserve(messages_vec.len());
 
 		self.handler.handle_messages(&messages_vec);
 
 		for message in messages_vec {
-			messages.insert(message);
+			messages.insert(message, Some(peer_id), &throttled);
+		}
+
+		Ok(())
+	}
+
+	// drop the messages of a throttled peer, failing if it keeps flooding us.
+	fn limit_rate(&self, peer_id: PeerId, messages_vec: &mut Vec<Message>) -> Result<(), Error> {
+		let peers = self.peers.read();
+		let mut peer = match peers.get(&peer_id) {
+			Some(peer) => peer.lock(),
+			None => return Err(Error::UnknownPeer(peer_id)),
+		};
+
+		let now = Instant::now();
+		let received = messages_vec.len();
+		messages_vec.retain(|message| peer.note_rate(&self.rate_limit, now, message.work_proved()));
+		if messages_vec.len() < received {
+			peer.violations += 1;
+			trace!(target: "whisper", "Dropped {} messages from throttled peer {}", received - messages_vec.len(), peer_id);
+			if peer.violations >= self.rate_limit.max_violations {
+				return Err(Error::Flooding);
+			}
 		}
 
 		Ok(())
@@ -571,8 +924,6 @@ impl<T: MessageHandler> Network<T> {
 	fn on_pow_requirement(&self, peer: &PeerId, requirement: Rlp)
 		-> Result<(), Error>
 	{
-		use byteorder::{ByteOrder, BigEndian};
-
 		let peers = self.peers.read();
 		match peers.get(peer) {
 			Some(peer) => {
@@ -653,8 +1004,15 @@ impl<T: MessageHandler> Network<T> {
 			known_messages: HashSet::new(),
 			topic_filter: None,
 			pow_requirement: 0f64,
+			advertised_pow: 0f64,
 			is_parity: io.protocol_version(PARITY_PROTOCOL_ID, *peer).is_some(),
 			_protocol_version: version,
+			window: VecDeque::new(),
+			throttled: false,
+			violations: 0,
+			accepted: 0,
+			rejected: 0,
+			work_proved: 0f64,
 		}));
 
 		io.send(*peer, packet::STATUS, ::rlp::EMPTY_LIST_RLP.to_vec());
@@ -670,9 +1028,16 @@ impl<T: MessageHandler> Network<T> {
 			_ => Ok(()), // ignore unknown packets.
 		};
 
-		if let Err(e) = res {
-			trace!(target: "whisper", "Disabling peer due to misbehavior: {}", e);
-			io.disable_peer(*peer);
+		match res {
+			Ok(()) => {}
+			Err(Error::Flooding) => {
+				debug!(target: "whisper", "Banning peer {} for flooding", peer);
+				io.ban_peer(*peer, FLOODING_BAN_DURATION);
+			}
+			Err(e) => {
+				trace!(target: "whisper", "Disabling peer due to misbehavior: {}", e);
+				io.disable_peer(*peer);
+			}
 		}
 	}
 
@@ -705,12 +1070,27 @@ impl<T: MessageHandler> ::network::NetworkProtocolHandler for Network<T> {
 	fn timeout(&self, io: &NetworkContext, timer: TimerToken) {
 		// rally with each peer and handle timeouts.
 		match timer {
-			RALLY_TOKEN => self.rally(io),
+			RALLY_TOKEN => {
+				self.rally(io);
+				if self.pool_path.is_some() && self.last_saved.lock().elapsed() >= SAVE_POOL_INTERVAL {
+					if let Err(e) = self.save_pool() {
+						warn!(target: "whisper", "Failed to save message pool: {}", e);
+					}
+				}
+			}
 			other => debug!(target: "whisper", "Timeout triggered on unknown token {}", other),
 		}
 	}
 }
 
+impl<T> Drop for Network<T> {
+	fn drop(&mut self) {
+		if let Err(e) = self.save_pool() {
+			warn!(target: "whisper", "Failed to save message pool: {}", e);
+		}
+	}
+}
+
 /// Dummy subprotocol used for parity extensions.
 #[derive(Debug, Copy, Clone)]
 pub struct ParityExtensions;
@@ -18,9 +18,11 @@
 
 use std::collections::HashSet;
 use std::sync::mpsc;
+use std::time::Duration;
 
 use parking_lot::Mutex;
 use network::{NodeId, PeerId};
+use rlp::{Rlp, RlpStream};
 
 use message::{CreateParams, Message};
 use super::*;
@@ -40,16 +42,24 @@ struct TestPeer {
 	network: Network<TestHandler>,
 	recv: mpsc::Receiver<Message>,
 	disconnected: Mutex<HashSet<PeerId>>,
+	banned: Mutex<HashSet<PeerId>>,
 }
 
 impl TestPeer {
 	fn create() -> Self {
+		TestPeer::create_with(|handler| Network::new(10 * 1024 * 1024, handler))
+	}
+
+	fn create_with<F>(make_network: F) -> Self
+		where F: FnOnce(TestHandler) -> Network<TestHandler>
+	{
 		let (tx, rx) = mpsc::channel();
 
 		TestPeer {
-			network: Network::new(10 * 1024 * 1024, TestHandler(Mutex::new(tx))),
+			network: make_network(TestHandler(Mutex::new(tx))),
 			recv: rx,
 			disconnected: Mutex::new(HashSet::new()),
+			banned: Mutex::new(HashSet::new()),
 		}
 	}
 }
@@ -60,7 +70,11 @@ struct TestNetwork {
 
 impl TestNetwork {
 	fn new(n_peers: usize) -> Self {
-		let unconnected_peers: Vec<_> = (0..n_peers).map(|_| TestPeer::create()).collect();
+		TestNetwork::with_peers((0..n_peers).map(|_| TestPeer::create()).collect())
+	}
+
+	fn with_peers(unconnected_peers: Vec<TestPeer>) -> Self {
+		let n_peers = unconnected_peers.len();
 		for i in 0..n_peers {
 			for j in (i + 1)..n_peers {
 				let (peer1, peer2) = (&unconnected_peers[i], &unconnected_peers[j]);
@@ -84,6 +98,7 @@ impl TestNetwork {
 
 enum Event {
 	Disconnect(PeerId, PeerId),
+	Ban(PeerId, PeerId),
 	Send(PeerId, PeerId, u8, Vec<u8>),
 }
 
@@ -112,6 +127,10 @@ impl<'a> Context for TestContext<'a> {
 		self.events.lock().push(Event::Disconnect(self.local_id, id));
 	}
 
+	fn ban_peer(&self, id: PeerId, _duration: Duration) {
+		self.events.lock().push(Event::Ban(self.local_id, id));
+	}
+
 	fn node_key(&self, peer: PeerId) -> Option<NodeId> {
 		let mut id = NodeId::default();
 		id[0] = peer as _;
@@ -138,6 +157,10 @@ impl<'a> Drop for TestContext<'a> {
 			let mut deferred = Vec::new();
 			for event in events.drain(..) {
 				match event {
+					Event::Ban(from, target) => {
+						self.peers[from].banned.lock().insert(target);
+						deferred.push(Event::Disconnect(from, target));
+					}
 					Event::Disconnect(from, target) => {
 						self.peers[from].network.on_disconnect(&target);
 						self.peers[target].network.on_disconnect(&from);
@@ -190,3 +213,182 @@ fn message_gets_relayed() {
 		assert_eq!(network.peers[i].recv.try_recv().unwrap(), message);
 	}
 }
+
+#[test]
+fn topic_pow_requirement_drops_messages() {
+	let network = TestNetwork::new(3);
+	let create = |topic: [u8; 4]| Message::create(CreateParams {
+		ttl: 500,
+		payload: b"this is my payload, pal".to_vec(),
+		topics: vec![topic.into()],
+		work: 25,
+	}).unwrap();
+
+	let guarded = create([0, 1, 2, 3]);
+	let open = create([9, 1, 2, 3]);
+
+	// the longest matching prefix applies.
+	network.peers[1].network.set_topic_pow(vec![0], 0.1);
+	network.peers[1].network.set_topic_pow(vec![0, 1], guarded.work_proved() + 1.0);
+	network.peers[1].network.set_topic_pow(vec![9, 1, 2], open.work_proved() / 2.0);
+
+	network.post_message_from(0, guarded.clone());
+	network.post_message_from(0, open.clone());
+
+	assert_eq!(network.peers[1].recv.try_recv().unwrap(), open);
+	assert!(network.peers[1].recv.try_recv().is_err());
+	assert_eq!(network.peers[1].network.pool_status().message_count, 1);
+	assert_eq!(network.peers[1].network.pool_status().topic_pow.len(), 3);
+
+	assert_eq!(network.peers[2].recv.try_recv().unwrap(), guarded);
+	assert_eq!(network.peers[2].recv.try_recv().unwrap(), open);
+
+	// dropping messages below the topic requirement doesn't punish the sender.
+	assert!(network.peers[0].disconnected.lock().is_empty());
+
+	// nor are they posted locally.
+	let local = create([0, 1, 2, 4]);
+	network.peers[2].network.set_topic_pow(vec![0, 1, 2], local.work_proved() + 1.0);
+	assert!(!network.peers[2].network.post_message(local, &TestContext::new(&network.peers, 2)));
+}
+
+#[test]
+fn global_pow_requirement_is_advertised_and_enforced() {
+	let network = TestNetwork::new(2);
+	let message = Message::create(CreateParams {
+		ttl: 500,
+		payload: b"this is my payload, pal".to_vec(),
+		topics: vec![[0, 1, 2, 3].into()],
+		work: 25,
+	}).unwrap();
+
+	network.peers[1].network.set_pow_requirement(message.work_proved() + 1.0);
+	network.peers[1].network.rally(&TestContext::new(&network.peers, 1));
+	assert_eq!(network.peers[1].network.pool_status().min_pow, message.work_proved() + 1.0);
+
+	// the requirement is respected by the peer.
+	network.post_message_from(0, message.clone());
+	assert!(network.peers[1].recv.try_recv().is_err());
+	assert!(network.peers[0].disconnected.lock().is_empty());
+
+	// and the peer is punished for ignoring it.
+	let mut stream = RlpStream::new_list(1);
+	stream.append(message.envelope());
+	assert!(network.peers[1].network.on_messages(&0, Rlp::new(&stream.out())).is_err());
+}
+
+#[test]
+fn persisted_pool_restores_live_messages() {
+	use std::time::{SystemTime, UNIX_EPOCH};
+	use message::Envelope;
+
+	let dir = ::std::env::temp_dir().join(format!("whisper-pool-{}", ::std::process::id()));
+	::std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("pool");
+
+	let now = SystemTime::now();
+	let now_secs = now.duration_since(UNIX_EPOCH).unwrap().as_secs();
+	let expired = Envelope {
+		expiry: now_secs - 10,
+		ttl: 100,
+		topics: ::smallvec::SmallVec::from_slice(&[[0, 1, 2, 3].into()]),
+		data: b"this is my payload, pal".to_vec(),
+		nonce: 0,
+	};
+	let expired = Message::decode(Rlp::new(&::rlp::encode(&expired)), now).unwrap();
+	let live = Message::create(CreateParams {
+		ttl: 500,
+		payload: b"this is my payload, pal".to_vec(),
+		topics: vec![[0, 1, 2, 3].into()],
+		work: 25,
+	}).unwrap();
+
+	{
+		let (tx, _rx) = mpsc::channel();
+		let network = Network::new(10 * 1024 * 1024, TestHandler(Mutex::new(tx))).with_persistence(path.clone());
+		assert!(network.messages.write().insert(expired, None, &HashSet::new()));
+		assert!(network.messages.write().insert(live.clone(), None, &HashSet::new()));
+	}
+
+	// expired messages aren't restored, even if the clock moved back.
+	let (tx, _rx) = mpsc::channel();
+	let network = Network::new(10 * 1024 * 1024, TestHandler(Mutex::new(tx)));
+	assert_eq!(network.load_pool(&path, now - Duration::from_secs(1000)).unwrap(), 1);
+
+	// restored messages are relayed to connecting peers.
+	let peers = vec![
+		TestPeer::create_with(|handler| Network::new(10 * 1024 * 1024, handler).with_persistence(path.clone())),
+		TestPeer::create(),
+	];
+	assert_eq!(peers[0].network.pool_status().message_count, 1);
+
+	{
+		let (ctx0, ctx1) = (TestContext::new(&peers, 0), TestContext::new(&peers, 1));
+		peers[0].network.on_connect(&ctx0, &1);
+		peers[1].network.on_connect(&ctx1, &0);
+	}
+	peers[0].network.rally(&TestContext::new(&peers, 0));
+	assert_eq!(peers[1].recv.try_recv().unwrap(), live);
+	assert!(peers[1].recv.try_recv().is_err());
+
+	// files of other versions are rejected.
+	let mut data = ::std::fs::read(&path).unwrap();
+	data[0] = POOL_FILE_VERSION + 1;
+	::std::fs::write(&path, &data).unwrap();
+	assert!(network.load_pool(&path, now).is_err());
+
+	drop(peers);
+	::std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn throttled_peer_messages_are_dropped_first() {
+	let create = |payload: u8| Message::create(CreateParams {
+		ttl: 500,
+		payload: vec![payload; 64],
+		topics: vec![[0, 1, 2, 3].into()],
+		work: 0,
+	}).unwrap();
+
+	let spam: Vec<_> = (0..10).map(create).collect();
+	let honest = create(0xff);
+
+	// room for 3 messages, and over 4 messages a minute for any PoW is flooding.
+	let pool_size = spam[0].encoded_size() * 7 / 2;
+	let limit = RateLimit {
+		window: Duration::from_secs(60),
+		max_message_rate: 4.0 / 60.0,
+		min_pow_rate: ::std::f64::MAX,
+		max_violations: 3,
+	};
+	let network = TestNetwork::with_peers(vec![
+		TestPeer::create(),
+		TestPeer::create(),
+		TestPeer::create_with(|handler| Network::new(pool_size, handler).with_rate_limit(limit)),
+	]);
+
+	for message in &spam[..5] {
+		network.post_message_from(0, message.clone());
+	}
+
+	let stats = network.peers[2].network.peer_stats();
+	assert_eq!((stats[&0].accepted, stats[&0].rejected, stats[&0].throttled), (4, 1, true));
+	assert_eq!(stats[&0].violations, 1);
+
+	// the honest message pushes out the ones of the throttled peer, whatever its PoW.
+	network.post_message_from(1, honest.clone());
+	assert!(network.peers[2].network.messages.read().known.contains(honest.hash()));
+
+	let stats = network.peers[2].network.peer_stats();
+	assert!(!stats[&1].throttled);
+	assert_eq!(stats[&1].rejected, 0);
+
+	// the spammer is eventually banned, the honest peer stays.
+	for message in &spam[5..] {
+		network.post_message_from(0, message.clone());
+	}
+
+	assert!(network.peers[2].banned.lock().contains(&0));
+	assert!(!network.peers[2].disconnected.lock().contains(&1));
+	assert!(network.peers[2].network.messages.read().known.contains(honest.hash()));
+}
@@ -16,7 +16,7 @@
 
 //! Abstraction over filters which works with polling and subscription.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{sync::{Arc, atomic, atomic::AtomicBool, mpsc}, thread};
 
 use ethereum_types::{H256, H512};
@@ -44,11 +44,92 @@ enum FilterEntry {
 	Subscription(Arc<Filter>, Sink<FilterItem>),
 }
 
+impl FilterEntry {
+	fn filter(&self) -> &Arc<Filter> {
+		match *self {
+			FilterEntry::Poll(ref filter, _) | FilterEntry::Subscription(ref filter, _) => filter,
+		}
+	}
+}
+
+// IDs of filters by the abridged topics they match: exact topics at depth 4,
+// prefixes above. finding the filters of a message topic takes at most 4 steps
+// however many filters there are.
+#[derive(Default)]
+struct TopicTrie {
+	filters: Vec<H256>,
+	children: HashMap<u8, TopicTrie>,
+}
+
+impl TopicTrie {
+	fn insert(&mut self, key: &[u8], id: H256) {
+		match key.split_first() {
+			Some((byte, rest)) => self.children.entry(*byte).or_insert_with(Default::default).insert(rest, id),
+			None => self.filters.push(id),
+		}
+	}
+
+	fn remove(&mut self, key: &[u8], id: &H256) {
+		match key.split_first() {
+			Some((byte, rest)) => {
+				let is_empty = match self.children.get_mut(byte) {
+					Some(child) => {
+						child.remove(rest, id);
+						child.filters.is_empty() && child.children.is_empty()
+					}
+					None => false,
+				};
+				if is_empty {
+					self.children.remove(byte);
+				}
+			}
+			None => self.filters.retain(|filter| filter != id),
+		}
+	}
+
+	// add IDs of filters matching the topic to `matched`.
+	fn matches(&self, topic: &Topic, matched: &mut HashSet<H256>) {
+		let mut node = self;
+		for byte in topic.0.iter() {
+			node = match node.children.get(byte) {
+				Some(child) => child,
+				None => return,
+			};
+			matched.extend(node.filters.iter().cloned());
+		}
+	}
+}
+
+// filters by ID, indexed by topic.
+#[derive(Default)]
+struct Filters {
+	entries: HashMap<H256, FilterEntry>,
+	topics: TopicTrie,
+}
+
+impl Filters {
+	fn insert(&mut self, id: H256, entry: FilterEntry) {
+		self.remove(&id);
+		for key in entry.filter().topic_keys() {
+			self.topics.insert(&key, id);
+		}
+		self.entries.insert(id, entry);
+	}
+
+	fn remove(&mut self, id: &H256) {
+		if let Some(entry) = self.entries.remove(id) {
+			for key in entry.filter().topic_keys() {
+				self.topics.remove(&key, id);
+			}
+		}
+	}
+}
+
 /// Filter manager. Handles filters as well as a thread for doing decryption
 /// and payload decoding.
 pub struct Manager {
 	key_store: Arc<RwLock<KeyStore>>,
-	filters: RwLock<HashMap<H256, FilterEntry>>,
+	filters: RwLock<Filters>,
 	tx: Mutex<mpsc::Sender<Box<Fn() + Send>>>,
 	join: Option<thread::JoinHandle<()>>,
 	exit: Arc<AtomicBool>,
@@ -78,7 +159,7 @@ impl Manager {
 
 		Ok(Manager {
 			key_store: Arc::new(RwLock::new(KeyStore::new()?)),
-			filters: RwLock::new(HashMap::new()),
+			filters: RwLock::new(Default::default()),
 			tx: Mutex::new(tx),
 			join: Some(join_handle),
 			exit: e,
@@ -92,7 +173,7 @@ impl Manager {
 
 	/// Get filter kind if it's known.
 	pub fn kind(&self, id: &H256) -> Option<Kind> {
-		self.filters.read().get(id).map(|filter| match *filter {
+		self.filters.read().entries.get(id).map(|filter| match *filter {
 			FilterEntry::Poll(_, _) => Kind::Poll,
 			FilterEntry::Subscription(_, _) => Kind::Subscription,
 		})
@@ -134,7 +215,7 @@ impl Manager {
 
 	/// Poll changes on filter identified by ID.
 	pub fn poll_changes(&self, id: &H256) -> Option<Vec<FilterItem>> {
-		self.filters.read().get(id).and_then(|filter| match *filter {
+		self.filters.read().entries.get(id).and_then(|filter| match *filter {
 			FilterEntry::Subscription(_, _) => None,
 			FilterEntry::Poll(_, ref changes)
 				=> Some(::std::mem::replace(&mut *changes.lock(), Vec::new())),
@@ -146,46 +227,48 @@ impl Manager {
 impl ::net::MessageHandler for Arc<Manager> {
 	fn handle_messages(&self, messages: &[Message]) {
 		let filters = self.filters.read();
-		let filters_iter = filters
-			.values()
-			.flat_map(|filter| messages.iter().map(move |msg| (filter, msg))) ;
+		let mut matched = HashSet::new();
+
+		for message in messages {
+			for topic in message.topics() {
+				filters.topics.matches(topic, &mut matched);
+			}
 
-		for	(filter, message) in filters_iter {
-			// if the message matches any of the possible bloom filters,
+			// the message has some of the topics of each matched filter.
 			// send to thread pool to attempt decryption and avoid
 			// blocking the network thread for long.
-			let failed_send = match *filter {
-				FilterEntry::Poll(ref filter, _) | FilterEntry::Subscription(ref filter, _)
-					if !filter.basic_matches(message) => None,
-				FilterEntry::Poll(ref filter, ref buffer) => {
-					let (message, key_store) = (message.clone(), self.key_store.clone());
-					let (filter, buffer) = (filter.clone(), buffer.clone());
-
-					self.tx.lock().send(Box::new(move || {
-						filter.handle_message(
-							&message,
-							&*key_store,
-							|matched| buffer.lock().push(matched),
-						)
-					})).err().map(|x| x.0)
-				}
-				FilterEntry::Subscription(ref filter, ref sink) => {
-					let (message, key_store) = (message.clone(), self.key_store.clone());
-					let (filter, sink) = (filter.clone(), sink.clone());
-
-					self.tx.lock().send(Box::new(move || {
-						filter.handle_message(
-							&message,
-							&*key_store,
-							|matched| { let _ = sink.notify(Ok(matched)); },
-						)
-					})).err().map(|x| x.0)
-				}
-			};
+			for filter in matched.drain().filter_map(|id| filters.entries.get(&id)) {
+				let failed_send = match *filter {
+					FilterEntry::Poll(ref filter, ref buffer) => {
+						let (message, key_store) = (message.clone(), self.key_store.clone());
+						let (filter, buffer) = (filter.clone(), buffer.clone());
+
+						self.tx.lock().send(Box::new(move || {
+							filter.handle_message(
+								&message,
+								&*key_store,
+								|matched| buffer.lock().push(matched),
+							)
+						})).err().map(|x| x.0)
+					}
+					FilterEntry::Subscription(ref filter, ref sink) => {
+						let (message, key_store) = (message.clone(), self.key_store.clone());
+						let (filter, sink) = (filter.clone(), sink.clone());
+
+						self.tx.lock().send(Box::new(move || {
+							filter.handle_message(
+								&message,
+								&*key_store,
+								|matched| { let _ = sink.notify(Ok(matched)); },
+							)
+						})).err().map(|x| x.0)
+					}
+				};
 
-			// if we failed to send work, no option but to do it locally.
-			if let Some(local_work) = failed_send {
-				(local_work)()
+				// if we failed to send work, no option but to do it locally.
+				if let Some(local_work) = failed_send {
+					(local_work)()
+				}
 			}
 		}
 	}
@@ -205,45 +288,81 @@ impl Drop for Manager {
 /// Filter incoming messages by critera.
 pub struct Filter {
 	topics: Vec<(Vec<u8>, H512, Topic)>,
+	prefixes: Vec<Vec<u8>>,
 	from: Option<Public>,
-	decrypt_with: Option<H256>,
+	decrypt_with: Vec<H256>,
 }
 
 impl Filter {
 	/// Create a new filter from filter request.
 	///
-	/// Fails if the topics vector is empty.
+	/// Fails if the topics vector is empty, if a topic prefix isn't 1 to 3 bytes
+	/// long, or if only prefixes are given for broadcast messages, which are
+	/// decrypted with a full topic.
 	pub fn new(params: types::FilterRequest) -> Result<Self, &'static str> {
 		if params.topics.is_empty() {
 			return Err("no topics for filter");
 		}
 
-		let topics: Vec<_> = params.topics.into_iter()
-			.map(|x| x.into_inner())
-			.map(|topic| {
-				let abridged = super::abridge_topic(&topic);
-				(topic, abridged.bloom(), abridged)
-			})
-			.collect();
+		let mut topics = Vec::new();
+		let mut prefixes = Vec::new();
+		for topic in params.topics {
+			match topic {
+				types::FilterTopic::Exact(topic) => {
+					let topic = topic.into_inner();
+					let abridged = super::abridge_topic(&topic);
+					topics.push((topic, abridged.bloom(), abridged));
+				}
+				types::FilterTopic::Prefix { prefix } => {
+					let prefix = prefix.into_inner();
+					if prefix.is_empty() || prefix.len() > 3 {
+						return Err("topic prefixes must be 1 to 3 bytes long");
+					}
+					prefixes.push(prefix);
+				}
+			}
+		}
+
+		let decrypt_with: Vec<_> = params.decrypt_with
+			.map_or_else(Vec::new, |ids| ids.into_vec().into_iter().map(|x| x.into_inner()).collect());
+
+		if decrypt_with.is_empty() && topics.is_empty() {
+			return Err("no full topic to decrypt broadcast messages with");
+		}
 
 		Ok(Filter {
 			topics: topics,
+			prefixes: prefixes,
 			from: params.from.map(|x| x.into_inner()),
-			decrypt_with: params.decrypt_with.map(|x| x.into_inner()),
+			decrypt_with: decrypt_with,
 		})
 	}
 
+	// keys of the filter in the topic trie: abridged topics and prefixes.
+	fn topic_keys(&self) -> Vec<Vec<u8>> {
+		self.topics.iter()
+			.map(|&(_, _, ref abridged)| abridged.0.to_vec())
+			.chain(self.prefixes.iter().cloned())
+			.collect()
+	}
+
+	// whether the topic starts with any of the prefixes of the filter.
+	fn prefix_matches(&self, topic: &Topic) -> bool {
+		self.prefixes.iter().any(|prefix| topic.0.starts_with(prefix))
+	}
+
 	// does basic matching:
 	// whether the given message matches at least one of the topics of the
 	// filter.
 	// TODO: minimum PoW heuristic.
+	#[cfg(test)]
 	fn basic_matches(&self, message: &Message) -> bool {
 		self.topics.iter().any(|&(_, ref bloom, _)| {
 			&(bloom & message.bloom()) == bloom
-		})
+		}) || message.topics().iter().any(|topic| self.prefix_matches(topic))
 	}
 
-	// handle a message that matches the bloom.
+	// handle a message that has some of the topics of the filter.
 	fn handle_message<F: Fn(FilterItem)>(
 		&self,
 		message: &Message,
@@ -263,32 +382,45 @@ impl Filter {
 			})
 			.collect();
 
-		if matched_indices.is_empty() { return }
-
-		let decrypt = match self.decrypt_with {
-			Some(ref id) => match store.read().decryption_instance(id) {
-				Some(d) => d,
-				None => {
-					warn!(target: "whisper", "Filter attempted to decrypt with destroyed identity {}",
-						id);
+		let matched_prefixed: Vec<_> = message.topics().iter()
+			.filter(|topic| self.prefix_matches(topic))
+			.collect();
 
-					return
-				}
-			},
-			None => {
-				let known_idx = matched_indices[0];
-				let known_topic = H256(keccak256(&self.topics[0].0));
+		if matched_indices.is_empty() && matched_prefixed.is_empty() { return }
 
-				DecryptionInstance::broadcast(message.topics().len(), known_idx, known_topic)
-					.expect("known idx is within the range 0..message.topics.len(); qed")
-			}
+		// try the keys in order, skipping the destroyed ones.
+		let decrypted = if self.decrypt_with.is_empty() {
+			let known_idx = match matched_indices.first() {
+				Some(idx) => *idx,
+				None => return,
+			};
+			let known_topic = H256(keccak256(&self.topics[0].0));
+
+			DecryptionInstance::broadcast(message.topics().len(), known_idx, known_topic)
+				.expect("known idx is within the range 0..message.topics.len(); qed")
+				.decrypt(message.data())
+				.map(|decrypted| (None, decrypted))
+		} else {
+			let store = store.read();
+			self.decrypt_with.iter()
+				.filter_map(|id| match store.decryption_instance(id) {
+					Some(d) => Some((id, d)),
+					None => {
+						warn!(target: "whisper", "Filter attempted to decrypt with destroyed identity {}",
+							id);
+
+						None
+					}
+				})
+				.filter_map(|(id, d)| d.decrypt(message.data()).map(|decrypted| (Some(*id), decrypted)))
+				.next()
 		};
 
-		let decrypted = match decrypt.decrypt(message.data()) {
+		let (recipient, decrypted) = match decrypted {
 			Some(d) => d,
 			None => {
 				trace!(target: "whisper", "Failed to decrypt message with {} matching topics",
-					matched_indices.len());
+					matched_indices.len() + matched_prefixed.len());
 
 				return
 			}
@@ -301,12 +433,13 @@ impl Filter {
 				let matched_topics = matched_indices
 					.into_iter()
 					.map(|i| self.topics[i].0.clone())
+					.chain(matched_prefixed.into_iter().map(|topic| topic.0.to_vec()))
 					.map(HexEncode)
 					.collect();
 
 				on_match(FilterItem {
 					from: decoded.from.map(HexEncode),
-					recipient: self.decrypt_with.map(HexEncode),
+					recipient: recipient.map(HexEncode),
 					ttl: message.envelope().ttl,
 					topics: matched_topics,
 					timestamp: message.envelope().expiry - message.envelope().ttl,
@@ -324,7 +457,7 @@ impl Filter {
 #[cfg(test)]
 mod tests {
 	use message::{CreateParams, Message, Topic};
-	use rpc::types::{FilterRequest, HexEncode};
+	use rpc::types::{FilterRequest, FilterTopic, HexEncode, Identities};
 	use rpc::abridge_topic;
 	use super::*;
 
@@ -347,7 +480,7 @@ mod tests {
 		let req = FilterRequest {
 			decrypt_with: Default::default(),
 			from: None,
-			topics: topics.into_iter().map(HexEncode).collect(),
+			topics: topics.into_iter().map(|topic| FilterTopic::Exact(HexEncode(topic))).collect(),
 		};
 
 		let filter = Filter::new(req).unwrap();
@@ -419,9 +552,9 @@ mod tests {
 		}).unwrap();
 
 		let filter = Filter::new(FilterRequest {
-			decrypt_with: Some(HexEncode(decrypt_id)),
+			decrypt_with: Some(Identities::One(HexEncode(decrypt_id))),
 			from: Some(HexEncode(signing_pair.public().unwrap().clone())),
-			topics: topics.into_iter().map(HexEncode).collect(),
+			topics: topics.into_iter().map(|topic| FilterTopic::Exact(HexEncode(topic))).collect(),
 		}).unwrap();
 
 		assert!(filter.basic_matches(&message));
@@ -435,4 +568,120 @@ mod tests {
 
 		assert_eq!(items.get(), 1);
 	}
+
+	#[test]
+	fn rejects_malformed_prefixes() {
+		let prefix_filter = |prefix: Vec<u8>, decrypt_with: Option<Identities>| Filter::new(FilterRequest {
+			decrypt_with: decrypt_with,
+			from: None,
+			topics: vec![FilterTopic::Prefix { prefix: HexEncode(prefix) }],
+		});
+		let key = || Some(Identities::One(HexEncode(H256::from(1))));
+
+		assert!(prefix_filter(vec![], key()).is_err());
+		assert!(prefix_filter(vec![1, 2, 3, 4], key()).is_err());
+		assert!(prefix_filter(vec![1, 2], None).is_err());
+		assert!(prefix_filter(vec![1, 2], key()).is_ok());
+	}
+
+	#[test]
+	fn prefix_match() {
+		let filter = Filter::new(FilterRequest {
+			decrypt_with: Some(Identities::One(HexEncode(H256::from(1)))),
+			from: None,
+			topics: vec![FilterTopic::Prefix { prefix: HexEncode(vec![1, 2]) }],
+		}).unwrap();
+
+		let message = |topic: [u8; 4]| Message::create(CreateParams {
+			ttl: 100,
+			payload: vec![1, 3, 5, 7, 9],
+			topics: vec![Topic(topic)],
+			work: 0,
+		}).unwrap();
+
+		assert!(filter.basic_matches(&message([1, 2, 3, 4])));
+		assert!(filter.basic_matches(&message([1, 2, 0, 0])));
+		assert!(!filter.basic_matches(&message([1, 3, 3, 4])));
+		assert!(!filter.basic_matches(&message([2, 1, 3, 4])));
+	}
+
+	#[test]
+	fn topic_trie_matches() {
+		let (a, b, c) = (H256::from(1), H256::from(2), H256::from(3));
+		let mut trie = TopicTrie::default();
+		trie.insert(&[1, 2], a);
+		trie.insert(&[1, 2, 3, 4], b);
+		trie.insert(&[1, 2, 9], c);
+
+		let matches = |trie: &TopicTrie, topic: [u8; 4]| {
+			let mut matched = HashSet::new();
+			trie.matches(&Topic(topic), &mut matched);
+			matched
+		};
+
+		assert_eq!(matches(&trie, [1, 2, 3, 4]), vec![a, b].into_iter().collect());
+		assert_eq!(matches(&trie, [1, 2, 9, 0]), vec![a, c].into_iter().collect());
+		// shares only the shorter prefix `[1]`, which no filter has.
+		assert!(matches(&trie, [1, 3, 3, 4]).is_empty());
+
+		trie.remove(&[1, 2], &a);
+		trie.remove(&[1, 2, 9], &c);
+		assert_eq!(matches(&trie, [1, 2, 3, 4]), vec![b].into_iter().collect());
+		assert!(matches(&trie, [1, 2, 9, 0]).is_empty());
+		assert!(trie.children[&1].children[&2].children.get(&9).is_none());
+	}
+
+	#[test]
+	fn multi_key_decryption_fallback() {
+		use rpc::payload::{self, EncodeParams};
+		use rpc::key_store::{Key, KeyStore};
+
+		let mut store = KeyStore::new().unwrap();
+		let first_key = Key::new_symmetric(store.rng());
+		let second_key = Key::new_symmetric(store.rng());
+		let first_id = store.insert(first_key);
+		let second_id = store.insert(second_key);
+		let encryption_instance = store.encryption_instance(&second_id).unwrap();
+		let store = ::parking_lot::RwLock::new(store);
+
+		let payload = payload::encode(EncodeParams {
+			message: &[1, 2, 3],
+			padding: None,
+			sign_with: None,
+		}).unwrap();
+
+		let message = Message::create(CreateParams {
+			ttl: 100,
+			payload: encryption_instance.encrypt(&payload).unwrap(),
+			topics: vec![Topic([1, 2, 3, 4])],
+			work: 0,
+		}).unwrap();
+
+		// destroyed and failing keys are skipped, in order.
+		let destroyed_id = H256::from(1);
+		let filter = Filter::new(FilterRequest {
+			decrypt_with: Some(Identities::Many(vec![HexEncode(destroyed_id), HexEncode(first_id), HexEncode(second_id)])),
+			from: None,
+			topics: vec![FilterTopic::Prefix { prefix: HexEncode(vec![1, 2, 3]) }],
+		}).unwrap();
+
+		let items = ::std::cell::RefCell::new(Vec::new());
+		filter.handle_message(&message, &store, |item| items.borrow_mut().push(item));
+
+		let items = items.into_inner();
+		assert_eq!(items.len(), 1);
+		assert_eq!(items[0].recipient, Some(HexEncode(second_id)));
+		assert_eq!(items[0].topics, vec![HexEncode(vec![1, 2, 3, 4])]);
+		assert_eq!(items[0].payload, HexEncode(vec![1, 2, 3]));
+
+		let filter = Filter::new(FilterRequest {
+			decrypt_with: Some(Identities::One(HexEncode(first_id))),
+			from: None,
+			topics: vec![FilterTopic::Prefix { prefix: HexEncode(vec![1, 2, 3]) }],
+		}).unwrap();
+
+		let items = ::std::cell::Cell::new(0);
+		filter.handle_message(&message, &store, |_| items.set(items.get() + 1));
+		assert_eq!(items.get(), 0);
+	}
 }
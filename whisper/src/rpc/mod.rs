@@ -21,6 +21,7 @@
 //!
 //! Provides an interface for using whisper to transmit data securely.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use jsonrpc_core::{Error, ErrorCode, Metadata};
@@ -75,6 +76,10 @@ build_rpc_trait! {
 		#[rpc(name = "shh_info")]
 		fn info(&self) -> Result<types::NodeInfo, Error>;
 
+		/// Whisper statistics of the connected peers.
+		#[rpc(name = "shh_peerStats")]
+		fn peer_stats(&self) -> Result<Vec<types::PeerStats>, Error>;
+
 		/// Generate a new asymmetric key pair and return an identity.
 		#[rpc(name = "shh_newKeyPair")]
 		fn new_key_pair(&self) -> Result<types::Identity, Error>;
@@ -109,6 +114,11 @@ build_rpc_trait! {
 		#[rpc(name = "shh_deleteKey")]
 		fn remove_key(&self, types::Identity) -> Result<bool, Error>;
 
+		/// Set min PoW of messages on topics starting with given prefix of the
+		/// abridged (4-byte) topic. Zero removes the requirement.
+		#[rpc(name = "shh_setTopicPow")]
+		fn set_topic_pow(&self, types::Bytes, f64) -> Result<bool, Error>;
+
 		/// Post a message to the network with given parameters.
 		#[rpc(name = "shh_post")]
 		fn post(&self, types::PostRequest) -> Result<bool, Error>;
@@ -153,6 +163,12 @@ pub trait PoolHandle: Send + Sync {
 
 	/// Number of messages and memory used by resident messages.
 	fn pool_status(&self) -> ::net::PoolStatus;
+
+	/// Set min PoW of messages on topics starting with given prefix.
+	fn set_topic_pow(&self, prefix: Vec<u8>, pow: f64);
+
+	/// Whisper statistics of the connected peers.
+	fn peer_stats(&self) -> HashMap<::network::PeerId, ::net::PeerStats>;
 }
 
 /// Default, simple metadata implementation.
@@ -214,9 +230,31 @@ impl<P: PoolHandle + 'static, M: Send + Sync + 'static> Whisper for WhisperClien
 			messages: status.message_count,
 			memory: status.cumulative_size,
 			target_memory: status.target_size,
+			global_pow: status.min_pow,
+			topic_pow: status.topic_pow.into_iter()
+				.map(|(prefix, pow)| (HexEncode(prefix), pow))
+				.collect(),
 		})
 	}
 
+	fn peer_stats(&self) -> Result<Vec<types::PeerStats>, Error> {
+		let mut stats: Vec<_> = self.pool.peer_stats().into_iter()
+			.map(|(peer, stats)| types::PeerStats {
+				peer: peer,
+				accepted: stats.accepted,
+				rejected: stats.rejected,
+				work_proved: stats.work_proved,
+				message_rate: stats.message_rate,
+				pow_rate: stats.pow_rate,
+				throttled: stats.throttled,
+				violations: stats.violations,
+			})
+			.collect();
+
+		stats.sort_by_key(|stats| stats.peer);
+		Ok(stats)
+	}
+
 	fn new_key_pair(&self) -> Result<types::Identity, Error> {
 		let mut store = self.store.write();
 		let key_pair = Key::new_asymmetric(store.rng());
@@ -271,6 +309,19 @@ impl<P: PoolHandle + 'static, M: Send + Sync + 'static> Whisper for WhisperClien
 		Ok(self.store.write().remove(&id.into_inner()))
 	}
 
+	fn set_topic_pow(&self, prefix: types::Bytes, pow: f64) -> Result<bool, Error> {
+		let prefix = prefix.into_inner();
+		if prefix.is_empty() || prefix.len() > 4 {
+			return Err(whisper_error("Topic prefix must be 1 to 4 bytes long"));
+		}
+		if !pow.is_finite() || pow < 0f64 {
+			return Err(whisper_error("Invalid PoW"));
+		}
+
+		self.pool.set_topic_pow(prefix, pow);
+		Ok(true)
+	}
+
 	fn post(&self, req: types::PostRequest) -> Result<bool, Error> {
 		use self::crypto::EncryptionInstance;
 
@@ -16,6 +16,7 @@
 
 //! Types for Whisper RPC.
 
+use std::collections::HashMap;
 use std::fmt;
 use std::ops::Deref;
 
@@ -188,22 +189,56 @@ pub struct PostRequest {
 	pub ttl: u64,
 }
 
+/// One identity, or several ones tried in order.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Identities {
+	/// Single identity.
+	One(Identity),
+	/// Identities tried in order.
+	Many(Vec<Identity>),
+}
+
+impl Identities {
+	/// Get the identities in order.
+	pub fn into_vec(self) -> Vec<Identity> {
+		match self {
+			Identities::One(id) => vec![id],
+			Identities::Many(ids) => ids,
+		}
+	}
+}
+
+/// Topic matched by a filter.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum FilterTopic {
+	/// Full topic, matching messages on its abridged topic.
+	Exact(Bytes),
+	/// Prefix of 1 to 3 bytes, matching messages on any abridged topic starting with it.
+	Prefix {
+		/// The prefix.
+		prefix: Bytes,
+	},
+}
+
 /// Request for filter or subscription creation.
 #[derive(Deserialize)]
 pub struct FilterRequest {
-	/// ID of key used for decryption.
+	/// ID of key used for decryption, or IDs of keys tried in order.
 	///
-	/// If this identity is removed, then no further messages will be returned.
+	/// If an identity is removed, then no further messages will be returned for it.
 	///
 	/// If optional, this will listen for broadcast messages.
 	#[serde(rename = "decryptWith")]
-	pub decrypt_with: Option<Identity>,
+	pub decrypt_with: Option<Identities>,
 
 	/// Accept only messages signed by given public key.
 	pub from: Option<Public>,
 
-	/// Possible topics. Cannot be empty if the identity is `None`
-	pub topics: Vec<Bytes>,
+	/// Possible topics: full topics or prefixes of abridged topics.
+	/// Cannot be empty, and must have a full topic if the identity is `None`.
+	pub topics: Vec<FilterTopic>,
 }
 
 /// A message captured by a filter or subscription.
@@ -252,6 +287,45 @@ pub struct NodeInfo {
 	/// Target memory of the pool.
 	#[serde(rename = "targetMemory")]
 	pub target_memory: usize,
+
+	/// min PoW of messages to be accepted and relayed, advertised to peers.
+	#[serde(rename = "globalPow")]
+	pub global_pow: f64,
+
+	/// min PoW of messages on topics starting with given abridged topic prefixes.
+	#[serde(rename = "topicPow")]
+	pub topic_pow: HashMap<Bytes, f64>,
+}
+
+/// Whisper statistics of a peer.
+#[derive(Serialize)]
+pub struct PeerStats {
+	/// Local identifier of the peer.
+	pub peer: usize,
+
+	/// Messages accepted from the peer.
+	pub accepted: u64,
+
+	/// Messages dropped as the peer was throttled.
+	pub rejected: u64,
+
+	/// Cumulative PoW of the accepted messages.
+	#[serde(rename = "workProved")]
+	pub work_proved: f64,
+
+	/// Accepted messages per second over the rate limit window.
+	#[serde(rename = "messageRate")]
+	pub message_rate: f64,
+
+	/// PoW per second of the accepted messages over the rate limit window.
+	#[serde(rename = "powRate")]
+	pub pow_rate: f64,
+
+	/// Whether messages from the peer are being dropped.
+	pub throttled: bool,
+
+	/// Number of packets with dropped messages.
+	pub violations: usize,
 }
 
 #[cfg(test)]
@@ -295,4 +369,27 @@ mod tests {
 		assert!(topic2.is_err());
 		assert_eq!(topic3, topic);
 	}
+
+	#[test]
+	fn deserialize_filter_request() {
+		let req: FilterRequest = serde_json::from_str(r#"{
+			"decryptWith": ["0x0000000000000000000000000000000000000000000000000000000000000001", "0x0000000000000000000000000000000000000000000000000000000000000002"],
+			"topics": ["0x01020304", { "prefix": "0xabcd" }]
+		}"#).unwrap();
+
+		assert_eq!(req.decrypt_with.unwrap().into_vec(), vec![HexEncode(H256::from(1)), HexEncode(H256::from(2))]);
+		match req.topics[..] {
+			[FilterTopic::Exact(ref topic), FilterTopic::Prefix { ref prefix }] => {
+				assert_eq!(*topic, Bytes::new(vec![1, 2, 3, 4]));
+				assert_eq!(*prefix, Bytes::new(vec![0xab, 0xcd]));
+			}
+			_ => panic!("unexpected topics"),
+		}
+
+		let req: FilterRequest = serde_json::from_str(r#"{
+			"decryptWith": "0x0000000000000000000000000000000000000000000000000000000000000001",
+			"topics": ["0x01"]
+		}"#).unwrap();
+		assert_eq!(req.decrypt_with.unwrap().into_vec(), vec![HexEncode(H256::from(1))]);
+	}
 }